@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ids::ClusterId;
+
+/// A stored document that doesn't match the current schema of its kind.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SchemaIssue {
+    pub document: String,
+
+    /// The key of the offending document, when it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// The schema version the document was written with, absent for documents
+    /// written before versions were recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+
+    /// JSON pointer to the offending field, empty for the document root.
+    pub path: String,
+    pub message: String,
+}
+
+/// The outcome of checking stored documents against the current schemas.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaReport {
+    /// The number of documents checked.
+    pub sampled: usize,
+    pub issues: Vec<SchemaIssue>,
+}
+
+impl SchemaReport {
+    pub fn is_compatible(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} sampled stored documents don't match the current schemas:",
+            self.issues.len(),
+            self.sampled
+        )?;
+
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue.document)?;
+            if let Some(id) = &issue.id {
+                write!(f, " {}", id)?;
+            }
+            if let Some(version) = issue.version {
+                write!(f, " (schema version {})", version)?;
+            }
+            write!(f, " at '{}': {}", issue.path, issue.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the server is ready, answered by `GET api/v1/admin/ready`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ReadyStatus {
+    Ready,
+
+    /// Ready, though stored documents don't match the current schemas or
+    /// some capabilities are impaired.
+    Degraded,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReadyResponse {
+    pub status: ReadyStatus,
+    pub schema: SchemaReport,
+
+    /// What the server can currently do, given the health of its dependencies.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub capabilities: Capabilities,
+}
+
+/// A feature of the server, impaired when a dependency it needs is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Capability {
+    ClusterCrud,
+    SubscriptionCrud,
+    MetadataRead,
+    Search,
+    Indexing,
+}
+
+/// A service the server depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Dependency {
+    Meilisearch,
+
+    /// The Kafka cluster of each registered cluster, probed one by one.
+    Kafka,
+}
+
+/// How well a capability works, from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CapabilityStatus {
+    Ok,
+    Degraded,
+    Unavailable,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapabilityHealth {
+    pub status: CapabilityStatus,
+
+    /// The dependency holding the capability back, unless it's ok.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limited_by: Option<Dependency>,
+
+    /// The health of the capability for each cluster, when it's scoped to clusters.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clusters: Vec<ClusterCapability>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClusterCapability {
+    pub cluster_id: ClusterId,
+    pub status: CapabilityStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limited_by: Option<Dependency>,
+}
+
+/// The health of every capability, for UIs to grey out the features affected.
+pub type Capabilities = BTreeMap<Capability, CapabilityHealth>;
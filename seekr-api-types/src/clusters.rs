@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::ClusterId;
+use crate::metadata::{AdaptiveState, ClusterMetadata, Priority, ResourceCounts};
+use crate::owner::Owner;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ClusterKind {
+    Unknown,
+    Kafka,
+}
+
+/// The body of `POST api/v2/clusters` and `PUT api/v2/clusters/:id`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClusterRequest {
+    pub kind: ClusterKind,
+    pub name: String,
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+
+    /// Omitted keeps the current owner on update, and an empty owner clears it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListClustersQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IdResponse {
+    pub id: ClusterId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListClustersResponse {
+    pub clusters: Vec<ClusterResource>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReadClusterResponse {
+    pub cluster: ClusterResource,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClusterResource {
+    pub id: ClusterId,
+    pub kind: ClusterKind,
+    pub name: String,
+    pub config: HashMap<String, String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub owner: Option<Owner>,
+    pub ownership_confirmed_at: Option<DateTime<Utc>>,
+    pub ownership_stale: bool,
+}
+
+/// The states of a cluster's cached metadata.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum MetadataResource {
+    Unknown,
+
+    /// The first poll hasn't completed yet.
+    Processing,
+    Ready {
+        metadata: ClusterMetadata,
+    },
+
+    /// Polls failed before any succeeded, and the brokers' error.
+    Failed {
+        message: String,
+    },
+}
+
+/// The body of `GET api/v2/clusters/:id/metadata`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetadataEnvelope {
+    #[serde(flatten)]
+    pub metadata: MetadataResource,
+
+    /// The user resources, and the internal and system ones when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counts: Option<ResourceCounts>,
+    pub polling: Option<PollingResource>,
+
+    /// How fresh ready metadata is, once a poll of the answering instance succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheResource>,
+
+    /// When to ask again while the metadata isn't ready, also sent as `Retry-After`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+
+    /// Set when the cluster exists but wasn't cached, whether this read
+    /// started registering it again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registration_started: Option<bool>,
+}
+
+/// When the cached metadata was fetched, and how the polls after it went.
+///
+/// Failed polls keep the metadata of the last one that succeeded, which goes
+/// stale once it's older than twice the cluster's poll interval.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheResource {
+    pub fetched_at: DateTime<Utc>,
+    pub stale: bool,
+    pub attempts_since_success: u32,
+
+    /// The brokers' error, while polls keep failing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PollingResource {
+    pub priority: Priority,
+    pub interval_ms: u64,
+
+    /// The time between the last two polls, longer than the interval when
+    /// polls wait for the budget behind more important clusters.
+    pub effective_interval_ms: Option<u64>,
+    pub stretch: Option<f64>,
+
+    /// Set for clusters with `metadata.poll.adaptive`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive: Option<AdaptiveResource>,
+    /// How long the next poll is held off while the cluster's circuit is
+    /// open, after polls failed in a row.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+}
+
+/// How an adaptive cluster's poll interval follows its metadata changes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveResource {
+    pub state: AdaptiveState,
+
+    /// The interval the next poll is due after, unless the circuit is open.
+    pub interval_ms: u64,
+    pub max_interval_ms: u64,
+
+    /// The polls in a row that found the metadata unchanged.
+    pub unchanged_polls: u32,
+}
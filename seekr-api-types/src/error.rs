@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// The structured error body returned by v2 routes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    /// A stable, machine readable error code, e.g. `not_found`.
+    pub code: String,
+    pub message: String,
+    /// The id an error on the server's side is logged under.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Declares a strongly typed identifier backed by an `i64`.
+///
+/// The wrapper serializes transparently so the external JSON and path
+/// representation remains a plain number.
+macro_rules! typed_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+        #[serde(transparent)]
+        pub struct $name(pub i64);
+
+        impl $name {
+            /// Returns the raw `i64` value of the identifier.
+            pub fn as_i64(&self) -> i64 {
+                self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse::<i64>().map($name)
+            }
+        }
+
+        impl From<i64> for $name {
+            fn from(v: i64) -> Self {
+                $name(v)
+            }
+        }
+
+        impl From<$name> for i64 {
+            fn from(v: $name) -> Self {
+                v.0
+            }
+        }
+    };
+}
+
+typed_id!(
+    /// The unique identifier of a registered cluster.
+    ClusterId
+);
+
+typed_id!(
+    /// The unique identifier of a topic subscription.
+    ///
+    /// Subscription and cluster ids are distinct types, so swapping them at a
+    /// call site is a compile error:
+    ///
+    /// ```compile_fail
+    /// use seekr_api_types::ids::{ClusterId, SubscriptionId};
+    ///
+    /// fn get(cluster_id: ClusterId, id: SubscriptionId) {}
+    ///
+    /// let cluster_id = ClusterId::from(1);
+    /// let id = SubscriptionId::from(2);
+    /// get(id, cluster_id);
+    /// ```
+    SubscriptionId
+);
+
+typed_id!(
+    /// The unique identifier of a mirrored cluster pair.
+    MirrorPairId
+);
+
+typed_id!(
+    /// The unique identifier of a queued subscription command.
+    ///
+    /// Ids are allocated in increasing order, so they also order the commands
+    /// of a subscription.
+    CommandId
+);
+
+typed_id!(
+    /// The unique identifier of an API key.
+    ApiKeyId
+);
+
+#[test]
+fn it_serializes_as_plain_numbers() {
+    let id = ClusterId(1234);
+    assert_eq!(serde_json::to_string(&id).unwrap(), "1234");
+
+    let id = SubscriptionId(-5);
+    assert_eq!(serde_json::to_string(&id).unwrap(), "-5");
+}
+
+#[test]
+fn it_round_trips_through_serde() {
+    let id: ClusterId = serde_json::from_str("42").unwrap();
+    assert_eq!(id, ClusterId(42));
+    assert_eq!(serde_json::to_string(&id).unwrap(), "42");
+
+    let ids: Vec<SubscriptionId> = serde_json::from_str("[1, 2, 3]").unwrap();
+    assert_eq!(
+        ids,
+        vec![SubscriptionId(1), SubscriptionId(2), SubscriptionId(3)]
+    );
+}
+
+#[test]
+fn it_parses_from_str() {
+    assert_eq!("77".parse::<ClusterId>().unwrap(), ClusterId(77));
+    assert_eq!(SubscriptionId(77).to_string(), "77");
+    assert!("abc".parse::<SubscriptionId>().is_err());
+}
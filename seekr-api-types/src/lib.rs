@@ -0,0 +1,13 @@
+//! The request and response shapes of the seekr REST API.
+//!
+//! The server serializes exactly these types and `seekr-client` deserializes
+//! them, so the two can't drift apart. Every route shape here is pinned by the
+//! server's golden files.
+
+pub mod admin;
+pub mod clusters;
+pub mod error;
+pub mod ids;
+pub mod metadata;
+pub mod owner;
+pub mod subscriptions;
@@ -0,0 +1,236 @@
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ClusterMetadata {
+    pub brokers: Vec<BrokerMetadata>,
+    pub groups: Vec<GroupMetadata>,
+    pub topics: Vec<TopicMetadata>,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct BrokerMetadata {
+    pub id: i32,
+    pub host: String,
+    pub port: i32,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct GroupMember {
+    pub id: String,
+    pub client_id: String,
+    pub client_host: String,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct GroupMetadata {
+    pub name: String,
+    pub state: String,
+    pub members: Vec<GroupMember>,
+
+    /// Whether seekr's own consumers use the group.
+    #[serde(default)]
+    pub managed_by_seekr: bool,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct TopicMetadata {
+    pub name: String,
+    pub partitions: Vec<PartitionMetadata>,
+
+    #[serde(default)]
+    pub category: TopicCategory,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct PartitionMetadata {
+    pub id: i32,
+    pub leader: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    pub error: Option<String>,
+
+    /// Offset of the oldest message still in the partition, `null` when it couldn't be fetched.
+    pub low_watermark: Option<i64>,
+
+    /// Offset the next message produced to the partition gets, `null` when it couldn't be fetched.
+    pub high_watermark: Option<i64>,
+}
+
+/// The watermarks of a topic's partitions.
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct TopicOffsets {
+    pub name: String,
+    pub partitions: Vec<PartitionOffsets>,
+
+    /// Timestamp in UTC Epoch milliseconds of the newest record, when sampled.
+    pub head_timestamp: Option<i64>,
+}
+
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct PartitionOffsets {
+    pub id: i32,
+    pub low: i64,
+    pub high: i64,
+}
+
+/// How far a consumer group is behind in a partition it committed an offset for.
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct GroupLag {
+    pub topic: String,
+    pub partition: i32,
+
+    /// The next offset the group reads.
+    pub committed: i64,
+
+    /// The offset the next message produced to the partition gets.
+    pub high_watermark: i64,
+
+    /// Messages produced the group hasn't read yet, never negative.
+    pub lag: i64,
+}
+
+/// A setting of a topic, as the brokers describe it.
+#[derive(PartialEq, Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct TopicConfigEntry {
+    pub name: String,
+
+    /// Unset for sensitive settings, which the brokers don't reveal.
+    pub value: Option<String>,
+    pub source: TopicConfigSource,
+
+    /// Whether the value is the default rather than an override.
+    pub is_default: bool,
+    pub is_read_only: bool,
+    pub is_sensitive: bool,
+}
+
+/// Where the value of a topic setting comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TopicConfigSource {
+    /// Set on the topic itself.
+    Topic,
+
+    /// Set on the broker while it runs, for that broker or all of them.
+    DynamicBroker,
+    DynamicDefaultBroker,
+
+    /// Set in the broker's config file.
+    StaticBroker,
+    Default,
+    Unknown,
+}
+
+/// Who a topic belongs to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TopicCategory {
+    /// Kafka's own topics, prefixed with `__`, e.g. `__consumer_offsets`.
+    Internal,
+
+    /// Topics of infrastructure running next to Kafka, e.g. a schema registry.
+    System,
+
+    #[default]
+    User,
+}
+
+/// Which resources besides the users' are reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Include {
+    /// Include Kafka's internal topics.
+    #[serde(default, rename = "include_internal")]
+    pub internal: bool,
+
+    /// Include system topics, and the groups seekr manages.
+    #[serde(default, rename = "include_system")]
+    pub system: bool,
+}
+
+impl Include {
+    pub fn topic(&self, category: TopicCategory) -> bool {
+        match category {
+            TopicCategory::Internal => self.internal,
+            TopicCategory::System => self.system,
+            TopicCategory::User => true,
+        }
+    }
+
+    pub fn group(&self, managed_by_seekr: bool) -> bool {
+        !managed_by_seekr || self.system
+    }
+}
+
+/// How many of a cluster's resources are reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceCounts {
+    pub topics: usize,
+    pub partitions: usize,
+    pub groups: usize,
+}
+
+impl ResourceCounts {
+    pub fn of(metadata: &ClusterMetadata, include: Include) -> Self {
+        let topics = metadata
+            .topics
+            .iter()
+            .filter(|t| include.topic(t.category))
+            .collect::<Vec<_>>();
+
+        Self {
+            topics: topics.len(),
+            partitions: topics.iter().map(|t| t.partitions.len()).sum(),
+            groups: metadata
+                .groups
+                .iter()
+                .filter(|g| include.group(g.managed_by_seekr))
+                .count(),
+        }
+    }
+}
+
+/// How urgently a cluster's metadata is refreshed when polls compete for the budget.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Priority {
+    /// Polled on time, using the reserved slice of the budget if need be.
+    High,
+    #[default]
+    Normal,
+    /// Polled after every other due cluster, stretching its interval under contention.
+    Low,
+}
+
+/// Where an adaptive cluster's poll interval stands, see `metadata.poll.adaptive`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AdaptiveState {
+    /// Polled at the configured interval, the metadata changed recently.
+    Base,
+    /// Polled less and less often, the metadata hasn't changed for a while.
+    Stretching,
+    /// Polled at the maximum interval.
+    Stretched,
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(Priority::High),
+            "normal" => Ok(Priority::Normal),
+            "low" => Ok(Priority::Low),
+            _ => Err(format!("unknown metadata priority '{}'", s)),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Who to contact about a cluster or subscription.
+///
+/// Every field is optional, but a team is required as soon as any of them is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Owner {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack_channel: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagerduty_service: Option<String>,
+}
+
+impl Owner {
+    pub fn team(team: &str) -> Self {
+        Owner {
+            team: Some(team.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Whether no field is set, which clears the owner on update.
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(|(_, v)| v.is_none())
+    }
+
+    /// Whether the owner belongs to the team, ignoring case.
+    pub fn is_team(&self, team: &str) -> bool {
+        self.team
+            .as_deref()
+            .is_some_and(|t| t.trim().eq_ignore_ascii_case(team.trim()))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        for (name, value) in self.fields() {
+            if value.is_some_and(|v| v.trim().is_empty()) {
+                return Err(format!("owner.{} must not be empty", name));
+            }
+        }
+
+        if self.team.is_none() {
+            return Err("owner.team is required when an owner is set".to_string());
+        }
+
+        match &self.email {
+            Some(email) if !is_email(email.trim()) => Err(format!(
+                "owner.email '{}' is not a valid email address",
+                email
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Trim every field, returning `None` when the owner is empty.
+    pub fn normalize(self) -> Option<Owner> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let trim = |v: Option<String>| v.map(|v| v.trim().to_string());
+        Some(Owner {
+            team: trim(self.team),
+            email: trim(self.email),
+            slack_channel: trim(self.slack_channel),
+            pagerduty_service: trim(self.pagerduty_service),
+        })
+    }
+
+    fn fields(&self) -> [(&'static str, Option<&str>); 4] {
+        [
+            ("team", self.team.as_deref()),
+            ("email", self.email.as_deref()),
+            ("slack_channel", self.slack_channel.as_deref()),
+            ("pagerduty_service", self.pagerduty_service.as_deref()),
+        ]
+    }
+}
+
+/// A pragmatic address check: a local part, and a dotted domain without empty labels.
+fn is_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && !s.chars().any(char::is_whitespace)
+        && !domain.contains('@')
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+#[test]
+fn it_validates_partial_owners() {
+    let owner = |team: Option<&str>, email: Option<&str>, slack: Option<&str>| Owner {
+        team: team.map(String::from),
+        email: email.map(String::from),
+        slack_channel: slack.map(String::from),
+        pagerduty_service: None,
+    };
+
+    let cases = [
+        (owner(None, None, None), true),
+        (owner(Some("payments"), None, None), true),
+        (owner(Some("payments"), Some("pay@example.com"), None), true),
+        (
+            owner(Some("payments"), None, Some("#payments-oncall")),
+            true,
+        ),
+        (owner(Some(" "), None, None), false),
+        (owner(None, Some("pay@example.com"), None), false),
+        (owner(None, None, Some("#payments-oncall")), false),
+        (owner(Some("payments"), Some(""), None), false),
+        (owner(Some("payments"), None, Some("  ")), false),
+        (owner(Some("payments"), Some("payments"), None), false),
+        (owner(Some("payments"), Some("@example.com"), None), false),
+        (owner(Some("payments"), Some("pay@example"), None), false),
+        (
+            owner(Some("payments"), Some("pay@example..com"), None),
+            false,
+        ),
+        (
+            owner(Some("payments"), Some("pay@@example.com"), None),
+            false,
+        ),
+        (
+            owner(Some("payments"), Some("pay ments@example.com"), None),
+            false,
+        ),
+    ];
+
+    for (owner, valid) in cases {
+        assert_eq!(owner.validate().is_ok(), valid, "{:?}", owner);
+    }
+}
+
+#[test]
+fn it_normalizes_owners() {
+    assert_eq!(Owner::default().normalize(), None);
+
+    let owner = Owner {
+        team: Some(" payments ".to_string()),
+        email: Some("pay@example.com ".to_string()),
+        ..Default::default()
+    };
+    let owner = owner.normalize().unwrap();
+    assert_eq!(owner.team.as_deref(), Some("payments"));
+    assert_eq!(owner.email.as_deref(), Some("pay@example.com"));
+    assert!(owner.is_team("Payments"));
+    assert!(!owner.is_team("pay"));
+}
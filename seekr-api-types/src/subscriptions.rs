@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::owner::Owner;
+
+/// The body of `POST api/v2/subscriptions`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateSubscriptionRequest {
+    pub cluster_id: ClusterId,
+    pub topic_name: String,
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+/// The body of `PUT api/v2/subscriptions/:cluster_id/:id`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpdateSubscriptionRequest {
+    pub topic_name: String,
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+
+    /// Omitted keeps the current owner, and an empty owner clears it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+/// The body of `POST api/v2/clusters/:cluster_id/subscriptions`, the cluster
+/// given by the path.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateClusterSubscriptionRequest {
+    pub topic_name: String,
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+/// The body of `PATCH api/v2/clusters/:cluster_id/subscriptions/:id`, the
+/// omitted fields kept as they are.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PatchSubscriptionRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic_name: Option<String>,
+
+    /// Config entries to set, or to remove when `null`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub config: HashMap<String, Option<String>>,
+
+    /// An empty owner clears it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListSubscriptionsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+
+    /// Also list the subscriptions deleted within their undo window.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_pending_deletion: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IdResponse {
+    pub id: SubscriptionId,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ListSubscriptionsResponse {
+    pub subscriptions: Vec<SubscriptionResource>,
+
+    /// How many subscriptions of the cluster are pending deletion, listed or not.
+    #[serde(default)]
+    pub pending_deletion: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReadSubscriptionResponse {
+    pub subscription: SubscriptionResource,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionResource {
+    pub id: SubscriptionId,
+    pub cluster_id: ClusterId,
+    pub topic_name: String,
+    pub config: HashMap<String, String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub owner: Option<Owner>,
+    pub ownership_confirmed_at: Option<DateTime<Utc>>,
+    pub ownership_stale: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_deletion: Option<PendingDeletionResource>,
+}
+
+/// A delete that can still be undone with `POST api/v2/subscriptions/:cluster_id/:id/undelete`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingDeletionResource {
+    pub requested_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+    pub purge_index: bool,
+}
@@ -0,0 +1,76 @@
+use seekr_api_types::error::ErrorResponse;
+
+/// Why a call to the API failed.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The server answered with its structured error body.
+    #[error("{status} {code}: {message}")]
+    Api {
+        status: u16,
+        code: String,
+        message: String,
+    },
+
+    /// The server answered without a structured error body, e.g. a `401` of
+    /// the authentication layer or a `503` of a standby without a primary.
+    #[error("unexpected {status} response: {body}")]
+    Unexpected { status: u16, body: String },
+
+    /// The server couldn't be reached, or the connection broke.
+    #[error("request failed: {0}")]
+    Http(#[from] isahc::Error),
+
+    #[error("invalid request: {0}")]
+    Request(String),
+
+    /// The response doesn't have the shape of the API types.
+    #[error("invalid response body: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// Map a failed response to the most specific error its body allows.
+    pub(crate) fn from_response(status: u16, body: String) -> Self {
+        match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(e) => Error::Api {
+                status,
+                code: e.error.code,
+                message: e.error.message,
+            },
+            Err(_) => Error::Unexpected { status, body },
+        }
+    }
+
+    /// The HTTP status the server answered with, if it answered.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api { status, .. } | Error::Unexpected { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// The stable error code of a structured error, e.g. `not_found`.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// Whether repeating the same call may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Http(e) => matches!(
+                e.kind(),
+                isahc::error::ErrorKind::ConnectionFailed
+                    | isahc::error::ErrorKind::Io
+                    | isahc::error::ErrorKind::Timeout
+            ),
+            e => matches!(e.status(), Some(429 | 502 | 503 | 504)),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! A typed async client of the seekr REST API.
+//!
+//! Requests and responses are the very types the server serializes, shared
+//! through `seekr-api-types`, so the client can't drift from the API. Calls
+//! that are safe to repeat are retried with backoff on transient failures, and
+//! writes sent to a standby follow its redirect to the primary.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), seekr_client::Error> {
+//! use seekr_client::types::clusters::{ClusterKind, ClusterRequest};
+//! use seekr_client::{Auth, SeekrClient};
+//!
+//! let client = SeekrClient::new("http://localhost:5000", Auth::ApiKey("sk_...".into()))?;
+//! let id = client
+//!     .create_cluster(&ClusterRequest {
+//!         kind: ClusterKind::Kafka,
+//!         name: "local".to_string(),
+//!         config: [("bootstrap.servers".to_string(), "localhost:9092".to_string())].into(),
+//!         owner: None,
+//!     })
+//!     .await?;
+//! let metadata = client.cluster_metadata(id, Default::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use isahc::config::{Configurable, RedirectPolicy};
+use isahc::http::header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+use isahc::http::{Method, Request};
+use isahc::{AsyncReadResponseExt, HttpClient};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use seekr_api_types::admin::ReadyResponse;
+use seekr_api_types::clusters::{
+    self, ClusterRequest, ClusterResource, ListClustersQuery, ListClustersResponse,
+    MetadataEnvelope, ReadClusterResponse,
+};
+use seekr_api_types::ids::{ClusterId, SubscriptionId};
+use seekr_api_types::metadata::Include;
+use seekr_api_types::subscriptions::{
+    self, CreateSubscriptionRequest, ListSubscriptionsQuery, ListSubscriptionsResponse,
+    ReadSubscriptionResponse, SubscriptionResource, UpdateSubscriptionRequest,
+};
+
+pub use seekr_api_types as types;
+
+pub use self::error::Error;
+pub use self::retry::RetryPolicy;
+
+mod error;
+mod retry;
+
+/// How the client authenticates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Auth {
+    /// For servers running without authentication.
+    None,
+
+    /// An API key, or the root key, sent as a bearer token.
+    ApiKey(String),
+}
+
+pub struct SeekrClient {
+    url: String,
+    auth: Auth,
+    http: HttpClient,
+    retry: RetryPolicy,
+}
+
+/// A response, before it's told apart into a result or an error.
+struct Reply {
+    status: u16,
+    hint: Option<Duration>,
+    body: String,
+}
+
+impl SeekrClient {
+    /// A client of the server at `url`, e.g. `http://localhost:5000`.
+    pub fn new(url: impl Into<String>, auth: Auth) -> Result<Self, Error> {
+        let http = HttpClient::builder()
+            .redirect_policy(RedirectPolicy::Limit(3))
+            .build()?;
+
+        Ok(Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            auth,
+            http,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub async fn create_cluster(&self, r: &ClusterRequest) -> Result<ClusterId, Error> {
+        let res: clusters::IdResponse = self.call(Method::POST, "api/v2/clusters", Some(r)).await?;
+        Ok(res.id)
+    }
+
+    /// Every cluster, or the clusters of a team.
+    pub async fn list_clusters(&self, team: Option<&str>) -> Result<Vec<ClusterResource>, Error> {
+        let query = query(&ListClustersQuery {
+            team: team.map(String::from),
+        })?;
+        let path = format!("api/v2/clusters{}", query);
+        let res: ListClustersResponse = self.call(Method::GET, &path, None::<&()>).await?;
+        Ok(res.clusters)
+    }
+
+    pub async fn get_cluster(&self, id: ClusterId) -> Result<ClusterResource, Error> {
+        let path = format!("api/v2/clusters/{}", id);
+        let res: ReadClusterResponse = self.call(Method::GET, &path, None::<&()>).await?;
+        Ok(res.cluster)
+    }
+
+    pub async fn update_cluster(&self, id: ClusterId, r: &ClusterRequest) -> Result<(), Error> {
+        let path = format!("api/v2/clusters/{}", id);
+        let _: clusters::IdResponse = self.call(Method::PUT, &path, Some(r)).await?;
+        Ok(())
+    }
+
+    pub async fn delete_cluster(&self, id: ClusterId) -> Result<(), Error> {
+        let path = format!("api/v2/clusters/{}", id);
+        self.send(Method::DELETE, &path, None::<&()>).await?;
+        Ok(())
+    }
+
+    /// The cluster's cached metadata in whichever state it is, with a
+    /// `retry_after_ms` hint until the first poll completes.
+    pub async fn cluster_metadata(
+        &self,
+        id: ClusterId,
+        include: Include,
+    ) -> Result<MetadataEnvelope, Error> {
+        let path = format!("api/v2/clusters/{}/metadata{}", id, query(&include)?);
+        self.call(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn create_subscription(
+        &self,
+        r: &CreateSubscriptionRequest,
+    ) -> Result<SubscriptionId, Error> {
+        let res: subscriptions::IdResponse = self
+            .call(Method::POST, "api/v2/subscriptions", Some(r))
+            .await?;
+        Ok(res.id)
+    }
+
+    /// The subscriptions of a cluster, or of a team on the cluster.
+    pub async fn list_subscriptions(
+        &self,
+        cluster_id: ClusterId,
+        team: Option<&str>,
+    ) -> Result<Vec<SubscriptionResource>, Error> {
+        let query = query(&ListSubscriptionsQuery {
+            team: team.map(String::from),
+            ..ListSubscriptionsQuery::default()
+        })?;
+        let path = format!("api/v2/subscriptions/{}{}", cluster_id, query);
+        let res: ListSubscriptionsResponse = self.call(Method::GET, &path, None::<&()>).await?;
+        Ok(res.subscriptions)
+    }
+
+    pub async fn get_subscription(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<SubscriptionResource, Error> {
+        let path = format!("api/v2/subscriptions/{}/{}", cluster_id, id);
+        let res: ReadSubscriptionResponse = self.call(Method::GET, &path, None::<&()>).await?;
+        Ok(res.subscription)
+    }
+
+    pub async fn update_subscription(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+        r: &UpdateSubscriptionRequest,
+    ) -> Result<(), Error> {
+        let path = format!("api/v2/subscriptions/{}/{}", cluster_id, id);
+        let _: subscriptions::IdResponse = self.call(Method::PUT, &path, Some(r)).await?;
+        Ok(())
+    }
+
+    pub async fn delete_subscription(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<(), Error> {
+        let path = format!("api/v2/subscriptions/{}/{}", cluster_id, id);
+        self.send(Method::DELETE, &path, None::<&()>).await?;
+        Ok(())
+    }
+
+    /// Restore a subscription deleted within its undo window.
+    pub async fn undelete_subscription(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<SubscriptionResource, Error> {
+        let path = format!("api/v2/subscriptions/{}/{}/undelete", cluster_id, id);
+        let res: ReadSubscriptionResponse = self.call(Method::POST, &path, None::<&()>).await?;
+        Ok(res.subscription)
+    }
+
+    /// Whether the server is ready, and how its stored documents match the schemas.
+    pub async fn ready(&self) -> Result<ReadyResponse, Error> {
+        self.call(Method::GET, "api/v1/admin/ready", None::<&()>)
+            .await
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&impl Serialize>,
+    ) -> Result<T, Error> {
+        let body = self.send(method, path, body).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Send a request, retrying it after transient failures unless it creates a resource.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&impl Serialize>,
+    ) -> Result<String, Error> {
+        let url = format!("{}/{}", self.url, path);
+        let body = body.map(serde_json::to_vec).transpose()?;
+        let idempotent = method != Method::POST;
+
+        let mut retry = 0;
+        loop {
+            let (error, hint) = match self.attempt(&method, &url, body.as_deref()).await {
+                Ok(r) if (200..300).contains(&r.status) => return Ok(r.body),
+                Ok(r) => (Error::from_response(r.status, r.body), r.hint),
+                Err(e) => (e, None),
+            };
+
+            retry += 1;
+            if !idempotent || retry >= self.retry.attempts || !error.is_transient() {
+                return Err(error);
+            }
+            tokio::time::sleep(self.retry.delay(retry, hint)).await;
+        }
+    }
+
+    async fn attempt(
+        &self,
+        method: &Method,
+        url: &str,
+        body: Option<&[u8]>,
+    ) -> Result<Reply, Error> {
+        let mut req = Request::builder().method(method.clone()).uri(url);
+        if let Auth::ApiKey(key) = &self.auth {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", key));
+        }
+        let req = match body {
+            Some(body) => req
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_vec()),
+            None => req.body(vec![]),
+        };
+        let req = req.map_err(|e| Error::Request(e.to_string()))?;
+
+        let mut res = self.http.send_async(req).await?;
+        let hint = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs);
+
+        Ok(Reply {
+            status: res.status().as_u16(),
+            hint,
+            body: res.text().await.map_err(isahc::Error::from)?,
+        })
+    }
+}
+
+/// The query string of a query type, with its leading `?`.
+fn query(q: &impl Serialize) -> Result<String, Error> {
+    let query = serde_urlencoded::to_string(q).map_err(|e| Error::Request(e.to_string()))?;
+    Ok(match query.is_empty() {
+        true => query,
+        false => format!("?{}", query),
+    })
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+/// How idempotent calls are retried after transient failures.
+///
+/// Creating resources is never retried, since a request that timed out may
+/// still have created one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The number of attempts, including the first.
+    pub attempts: u32,
+
+    /// The wait before the first retry, doubling with every retry after.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The wait before the given retry, or the server's `Retry-After` hint
+    /// when it's longer, bounded by `max_backoff` either way.
+    pub fn delay(&self, retry: u32, hint: Option<Duration>) -> Duration {
+        let backoff = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+
+        backoff.max(hint.unwrap_or_default()).min(self.max_backoff)
+    }
+}
+
+#[test]
+fn it_doubles_the_backoff_up_to_the_bound() {
+    let policy = RetryPolicy::default();
+
+    assert_eq!(policy.delay(1, None), Duration::from_millis(200));
+    assert_eq!(policy.delay(3, None), Duration::from_millis(800));
+    assert_eq!(policy.delay(10, None), Duration::from_secs(5));
+    assert_eq!(
+        policy.delay(1, Some(Duration::from_secs(2))),
+        Duration::from_secs(2)
+    );
+    assert_eq!(
+        policy.delay(1, Some(Duration::from_secs(60))),
+        Duration::from_secs(5)
+    );
+}
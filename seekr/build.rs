@@ -1,5 +1,8 @@
+use std::fmt::Write;
+use std::path::Path;
 use std::process::Command;
 use std::string::String;
+use std::{env, fs};
 
 fn set_env(name: &str, cmd: &mut Command) {
     let value = match cmd.output() {
@@ -12,18 +15,85 @@ fn set_env(name: &str, cmd: &mut Command) {
     println!("cargo:rustc-env={}={}", name, value);
 }
 
+/// The route attributes of an endpoints file, as `(method, path)`.
+fn routes(file: &Path) -> Vec<(String, String)> {
+    let source = fs::read_to_string(file).unwrap();
+    source
+        .lines()
+        .filter_map(|line| {
+            let attr = line.trim().strip_prefix("#[")?;
+            let (method, rest) = attr.split_once("(\"")?;
+            let (path, _) = rest.split_once("\")]")?;
+            match method {
+                "get" | "post" | "put" | "patch" | "delete" => {
+                    Some((method.to_uppercase(), path.to_string()))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// List the modules with an `endpoints` directory and the routes of their
+/// versions, so the server can tell when one of them is never mounted.
+fn write_endpoints() {
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("src");
+    let mut modules = fs::read_dir(&root)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().join("endpoints").join("mod.rs").is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    modules.sort();
+
+    let mut out = String::from("pub const ENDPOINT_MODULES: &[&str] = &[\n");
+    for module in &modules {
+        writeln!(out, "    {:?},", module).unwrap();
+    }
+    out.push_str("];\n\npub const ENDPOINT_ROUTES: &[EndpointRoute] = &[\n");
+    for module in &modules {
+        let mut files = fs::read_dir(root.join(module).join("endpoints"))
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter_map(|name| Some(name.strip_suffix(".rs")?.to_string()))
+            .filter(|name| name.starts_with('v') && name[1..].parse::<u32>().is_ok())
+            .collect::<Vec<_>>();
+        files.sort();
+        for version in files {
+            let file = root
+                .join(module)
+                .join("endpoints")
+                .join(format!("{}.rs", version));
+            for (method, path) in routes(&file) {
+                writeln!(
+                    out,
+                    "    EndpointRoute {{ module: {:?}, version: {:?}, method: {:?}, path: {:?} }},",
+                    module, version, method, path
+                )
+                .unwrap();
+            }
+        }
+    }
+    out.push_str("];\n");
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("endpoints.rs");
+    fs::write(dest, out).unwrap();
+}
+
 fn main() {
     set_env(
         "GIT_BRANCH",
-        Command::new("git").args(&["rev-parse", "--abbrev-ref", "HEAD"]),
+        Command::new("git").args(["rev-parse", "--abbrev-ref", "HEAD"]),
     );
     set_env(
         "GIT_SHA",
-        Command::new("git").args(&["rev-parse", "--short", "HEAD"]),
+        Command::new("git").args(["rev-parse", "--short", "HEAD"]),
     );
     set_env(
         "GIT_VERSION",
-        Command::new("git").args(&["describe", "--always", "HEAD"]),
+        Command::new("git").args(["describe", "--always", "HEAD"]),
     );
     set_env("RUST_VERSION", Command::new("rustc").arg("--version"));
+    write_endpoints();
 }
@@ -0,0 +1,348 @@
+//! Integration suite driving an in-process server exclusively through `seekr-client`.
+//!
+//! The server listens on a local port and runs on memory stores, so the suite
+//! covers the whole HTTP round trip, including authentication, redirects and
+//! retries. It doubles as an example of using the client.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::{ServerHandle, Service};
+use actix_web::web::Data;
+use actix_web::{App, HttpServer};
+use seekr_client::types::clusters::{ClusterKind, ClusterRequest, MetadataResource};
+use seekr_client::types::metadata::Include;
+use seekr_client::types::owner::Owner;
+use seekr_client::types::subscriptions::{CreateSubscriptionRequest, UpdateSubscriptionRequest};
+use seekr_client::{Auth, RetryPolicy, SeekrClient};
+
+use crate::auth::store::MemoryApiKeyStore;
+use crate::auth::Authenticator;
+use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+use crate::commands::store::{CommandStore, MemoryCommandStore};
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::kafka::metadata::classify::TopicCategory;
+use crate::kafka::metadata::consumer::MetadataConsumer;
+use crate::kafka::metadata::manager::{MetadataConsumerFactory, MetadataManager};
+use crate::kafka::metadata::{
+    ClusterMetadata, GroupLag, PartitionMetadata, TopicConfigEntry, TopicMetadata, TopicOffsets,
+};
+use crate::shards::store::{DocumentStore, MemoryDocumentStore};
+use crate::standby::{Availability, Standing};
+use crate::subscriptions::store::{
+    MemoryPurgeLog, MemorySubscriptionStore, PurgeLog, SubscriptionStore,
+};
+
+const ROOT: &str = "root-secret";
+
+/// A consumer answering with a single topic of three partitions.
+struct FixedConsumer;
+
+#[async_trait::async_trait]
+impl MetadataConsumer for FixedConsumer {
+    async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        Ok(ClusterMetadata {
+            brokers: vec![],
+            groups: vec![],
+            topics: vec![TopicMetadata {
+                name: "orders".to_string(),
+                partitions: (0..3)
+                    .map(|id| PartitionMetadata {
+                        id,
+                        leader: 0,
+                        replicas: vec![0],
+                        isr: vec![0],
+                        error: None,
+                        low_watermark: None,
+                        high_watermark: None,
+                    })
+                    .collect(),
+                category: TopicCategory::User,
+            }],
+        })
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_topic_config(&self, _topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        Ok(vec![])
+    }
+}
+
+/// A server listening on a local port.
+struct Server {
+    url: String,
+    handle: ServerHandle,
+    manager: Data<MetadataManager>,
+
+    /// The requests the server received.
+    requests: Arc<AtomicUsize>,
+}
+
+impl Server {
+    /// Start a server requiring the root key when `auth` is set, on standby of
+    /// `standby_of` when that is set.
+    async fn start(auth: bool, standby_of: Option<Option<String>>) -> Self {
+        let clusters: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+        let subscriptions: Arc<dyn SubscriptionStore + Send + Sync> =
+            Arc::new(MemorySubscriptionStore::default());
+        let commands: Arc<dyn CommandStore + Send + Sync> = Arc::new(MemoryCommandStore::default());
+        let purges: Arc<dyn PurgeLog + Send + Sync> = Arc::new(MemoryPurgeLog::default());
+        let documents: Arc<dyn DocumentStore + Send + Sync> =
+            Arc::new(MemoryDocumentStore::default());
+        let factory: MetadataConsumerFactory = Arc::new(|_| Ok(Arc::new(FixedConsumer)));
+        let manager = Data::new(MetadataManager::with_factory(clusters.clone(), factory));
+        let authenticator = auth.then(|| {
+            let keys = Arc::new(MemoryApiKeyStore::default());
+            Data::new(Authenticator::new(keys, Some(ROOT)))
+        });
+        let availability = standby_of
+            .map(|primary_url| Data::new(Availability::new(Standing::Standby { primary_url })));
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let (data, counter) = (manager.clone(), requests.clone());
+        let server = HttpServer::new(move || {
+            let counter = counter.clone();
+            let mut app = App::new()
+                .app_data(Data::new(clusters.clone()))
+                .app_data(Data::new(subscriptions.clone()))
+                .app_data(Data::new(commands.clone()))
+                .app_data(Data::new(purges.clone()))
+                .app_data(Data::new(documents.clone()))
+                .app_data(data.clone());
+            if let Some(authenticator) = &authenticator {
+                app = app.app_data(authenticator.clone());
+            }
+            if let Some(availability) = &availability {
+                app = app.app_data(availability.clone());
+            }
+
+            app.wrap_fn(move |req, srv| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                srv.call(req)
+            })
+            .configure(crate::server::routes)
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let url = format!("http://{}", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Self {
+            url,
+            handle,
+            manager,
+            requests,
+        }
+    }
+
+    fn client(&self, auth: Auth) -> SeekrClient {
+        SeekrClient::new(&self.url, auth).unwrap()
+    }
+
+    async fn stop(self) {
+        self.manager.into_inner().stop().await;
+        self.handle.stop(true).await;
+    }
+}
+
+fn cluster(name: &str) -> ClusterRequest {
+    ClusterRequest {
+        kind: ClusterKind::Kafka,
+        name: name.to_string(),
+        config: HashMap::from([(
+            "bootstrap.servers".to_string(),
+            "localhost:9092".to_string(),
+        )]),
+        owner: Some(Owner::team("payments")),
+    }
+}
+
+#[actix_web::test]
+async fn it_manages_clusters_and_subscriptions_through_the_client() {
+    let server = Server::start(false, None).await;
+    let client = server.client(Auth::None);
+
+    let ready = client.ready().await.unwrap();
+    assert!(ready.schema.is_compatible());
+
+    let id = client.create_cluster(&cluster("local")).await.unwrap();
+    let clusters = client.list_clusters(Some("payments")).await.unwrap();
+    assert_eq!(clusters.len(), 1);
+    assert_eq!(clusters[0].id, id);
+    assert!(client
+        .list_clusters(Some("billing"))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The metadata is processing until the first poll, then ready.
+    let mut metadata = client
+        .cluster_metadata(id, Include::default())
+        .await
+        .unwrap();
+    for _ in 0..50 {
+        if let MetadataResource::Ready { .. } = metadata.metadata {
+            break;
+        }
+        assert_eq!(metadata.metadata, MetadataResource::Processing);
+        assert!(metadata.retry_after_ms.is_some());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        metadata = client
+            .cluster_metadata(id, Include::default())
+            .await
+            .unwrap();
+    }
+    let MetadataResource::Ready { metadata: m } = &metadata.metadata else {
+        panic!("metadata isn't ready: {:?}", metadata);
+    };
+    assert_eq!(m.topics[0].name, "orders");
+    assert_eq!(metadata.counts.unwrap().partitions, 3);
+    assert_eq!(metadata.retry_after_ms, None);
+
+    let mut renamed = cluster("renamed");
+    renamed.owner = None;
+    client.update_cluster(id, &renamed).await.unwrap();
+    let c = client.get_cluster(id).await.unwrap();
+    assert_eq!((c.name.as_str(), c.kind), ("renamed", ClusterKind::Kafka));
+    assert_eq!(c.owner, Some(Owner::team("payments")));
+
+    let subscription = CreateSubscriptionRequest {
+        cluster_id: id,
+        topic_name: "orders".to_string(),
+        config: HashMap::new(),
+        owner: None,
+    };
+    let sid = client.create_subscription(&subscription).await.unwrap();
+    let update = UpdateSubscriptionRequest {
+        topic_name: "orders-v2".to_string(),
+        config: HashMap::new(),
+        owner: Some(Owner::team("billing")),
+    };
+    client.update_subscription(id, sid, &update).await.unwrap();
+    let s = client.get_subscription(id, sid).await.unwrap();
+    assert_eq!(s.topic_name, "orders-v2");
+    assert_eq!(
+        client
+            .list_subscriptions(id, Some("billing"))
+            .await
+            .unwrap(),
+        vec![s.clone()]
+    );
+
+    client.delete_subscription(id, sid).await.unwrap();
+    assert!(client
+        .list_subscriptions(id, None)
+        .await
+        .unwrap()
+        .is_empty());
+    let pending = client.get_subscription(id, sid).await.unwrap();
+    assert!(pending.pending_deletion.is_some());
+    assert_eq!(client.undelete_subscription(id, sid).await.unwrap(), s);
+    let e = client.undelete_subscription(id, sid).await.unwrap_err();
+    assert_eq!(e.status(), Some(409));
+    assert_eq!(e.code(), Some("not_pending_deletion"));
+
+    let e = client
+        .get_subscription(id, SubscriptionId(sid.as_i64() + 1))
+        .await
+        .unwrap_err();
+    assert!(e.is_not_found());
+    assert_eq!(e.code(), Some("not_found"));
+
+    client.delete_cluster(id).await.unwrap();
+    assert!(client.list_clusters(None).await.unwrap().is_empty());
+
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn it_maps_errors_and_authenticates_with_api_keys() {
+    let server = Server::start(true, None).await;
+
+    let anonymous = server.client(Auth::None);
+    let e = anonymous.list_clusters(None).await.unwrap_err();
+    assert_eq!(e.status(), Some(401));
+    assert_eq!(e.code(), None);
+
+    let client = server.client(Auth::ApiKey(ROOT.to_string()));
+    let e = client.get_cluster(9.into()).await.unwrap_err();
+    match e {
+        seekr_client::Error::Api {
+            status,
+            code,
+            message,
+        } => assert_eq!(
+            (status, code.as_str(), message.as_str()),
+            (404, "not_found", "Cluster with id '9' not found")
+        ),
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let mut invalid = cluster("local");
+    invalid.owner = Some(Owner {
+        email: Some("payments".to_string()),
+        ..Owner::team("payments")
+    });
+    let e = client.create_cluster(&invalid).await.unwrap_err();
+    assert_eq!((e.status(), e.code()), (Some(400), Some("invalid_request")));
+
+    server.stop().await;
+}
+
+#[actix_web::test]
+async fn it_follows_standby_redirects_and_retries_only_idempotent_calls() {
+    let primary = Server::start(false, None).await;
+    let standby = Server::start(false, Some(Some(primary.url.clone()))).await;
+    let orphan = Server::start(false, Some(None)).await;
+    let retry = RetryPolicy {
+        attempts: 3,
+        backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    };
+
+    // Writes sent to a standby land on the primary.
+    let client = standby.client(Auth::None).with_retry(retry.clone());
+    let id = client.create_cluster(&cluster("local")).await.unwrap();
+    let primary_client = primary.client(Auth::None);
+    assert_eq!(primary_client.get_cluster(id).await.unwrap().name, "local");
+
+    // Without a primary, writes are refused with a 503: updates are retried,
+    // creates aren't.
+    let client = orphan.client(Auth::None).with_retry(retry);
+    let e = client
+        .update_cluster(id, &cluster("local"))
+        .await
+        .unwrap_err();
+    assert_eq!(e.status(), Some(503));
+    assert_eq!(orphan.requests.load(Ordering::SeqCst), 3);
+
+    let e = client.create_cluster(&cluster("local")).await.unwrap_err();
+    assert!(e.is_transient());
+    assert_eq!(orphan.requests.load(Ordering::SeqCst), 4);
+
+    for server in [primary, standby, orphan] {
+        server.stop().await;
+    }
+}
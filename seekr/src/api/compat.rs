@@ -0,0 +1,426 @@
+//! Compatibility suite pinning the exact v1 responses and the v2 contracts.
+//!
+//! v1 bodies are compared byte for byte with the golden files under
+//! `src/api/goldens/v1`. Run with `UPDATE_GOLDENS=1` to rewrite them, which
+//! should only ever happen together with a deliberate v1 change. The v2
+//! goldens under `src/api/goldens/v2` pin the DTOs shared with `seekr-client`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use actix_web::http::header::HeaderMap;
+use actix_web::http::StatusCode;
+use actix_web::test::{self, TestRequest};
+use actix_web::web::Data;
+use actix_web::App;
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+
+use crate::changefeed::store::{ChangefeedStore, MemoryChangefeedStore};
+use crate::clusters::cluster::{Cluster, Kind};
+use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+use crate::commands::store::{CommandStore, MemoryCommandStore};
+use crate::debug::store::{DebugStore, MemoryDebugStore};
+use crate::errors::AnyError;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::metadata::classify::TopicCategory;
+use crate::kafka::metadata::consumer::MetadataConsumer;
+use crate::kafka::metadata::manager::{
+    CachedMetadataEntry, MetadataConsumerFactory, MetadataManager,
+};
+use crate::kafka::metadata::{
+    BrokerMetadata, ClusterMetadata, GroupLag, PartitionMetadata, TopicConfigEntry, TopicMetadata,
+    TopicOffsets,
+};
+use crate::shards::store::{DocumentStore, MemoryDocumentStore};
+use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+use crate::subscriptions::store::{
+    MemoryPurgeLog, MemorySubscriptionStore, PurgeLog, SubscriptionStore,
+};
+use crate::subscriptions::subscription::Subscription;
+
+/// A metadata consumer that never answers, so no broker is needed.
+struct IdleConsumer;
+
+#[async_trait::async_trait]
+impl MetadataConsumer for IdleConsumer {
+    async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        std::future::pending().await
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
+        std::future::pending().await
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        std::future::pending().await
+    }
+
+    async fn fetch_topic_config(&self, _topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        Ok(vec![])
+    }
+}
+
+struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl Response {
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+struct Fixture {
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    changefeed: Arc<dyn ChangefeedStore + Send + Sync>,
+    debug: Arc<dyn DebugStore + Send + Sync>,
+    commands: Arc<dyn CommandStore + Send + Sync>,
+    purges: Arc<dyn PurgeLog + Send + Sync>,
+    documents: Arc<dyn DocumentStore + Send + Sync>,
+    manager: Data<MetadataManager>,
+}
+
+impl Fixture {
+    /// Cluster 1 with subscription 1, both with fixed timestamps.
+    async fn new() -> Self {
+        let at = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let config = |k: &str, v: &str| HashMap::from([(k.to_string(), v.to_string())]);
+
+        let clusters = Arc::new(MemoryClusterStore::default());
+        let cluster = Cluster::init(
+            ClusterId(1),
+            Kind::Kafka,
+            "local".to_string(),
+            config("bootstrap.servers", "localhost:9092"),
+            at,
+            at,
+        );
+        clusters.put(cluster).await.unwrap();
+
+        let subscriptions = Arc::new(MemorySubscriptionStore::default());
+        let subscription = Subscription::init(
+            SubscriptionId(1),
+            ClusterId(1),
+            "orders".to_string(),
+            config("changefeed.enabled", "false"),
+            at,
+            at,
+        );
+        subscriptions.put(subscription).await.unwrap();
+
+        let factory: MetadataConsumerFactory = Arc::new(|_| Ok(Arc::new(IdleConsumer)));
+        let manager = MetadataManager::with_factory(clusters.clone(), factory);
+
+        Self {
+            clusters,
+            subscriptions,
+            changefeed: Arc::new(MemoryChangefeedStore::default()),
+            debug: Arc::new(MemoryDebugStore::default()),
+            commands: Arc::new(MemoryCommandStore::default()),
+            purges: Arc::new(MemoryPurgeLog::default()),
+            documents: Arc::new(MemoryDocumentStore::default()),
+            manager: Data::new(manager),
+        }
+    }
+
+    async fn call(&self, req: TestRequest) -> Response {
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(self.clusters.clone()))
+                .app_data(Data::new(self.subscriptions.clone()))
+                .app_data(Data::new(self.changefeed.clone()))
+                .app_data(Data::new(self.debug.clone()))
+                .app_data(Data::new(self.commands.clone()))
+                .app_data(Data::new(self.purges.clone()))
+                .app_data(Data::new(self.documents.clone()))
+                .app_data(self.manager.clone())
+                .configure(crate::server::routes),
+        )
+        .await;
+
+        let res = test::call_service(&app, req.to_request()).await;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = test::read_body(res).await;
+
+        Response {
+            status,
+            headers,
+            body: String::from_utf8(body.to_vec()).unwrap(),
+        }
+    }
+}
+
+fn assert_golden(name: &str, res: &Response) {
+    let path = format!("{}/src/api/goldens/{}", env!("CARGO_MANIFEST_DIR"), name);
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(&path, &res.body).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(res.body, golden, "response changed, see {}", path);
+}
+
+#[actix_web::test]
+async fn v1_clusters_match_goldens() {
+    let f = Fixture::new().await;
+
+    let res = f.call(TestRequest::get().uri("/api/v1/clusters")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/clusters_list.json", &res);
+
+    let res = f.call(TestRequest::get().uri("/api/v1/clusters/1")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/clusters_get.json", &res);
+
+    let res = f.call(TestRequest::get().uri("/api/v1/clusters/9")).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_golden("v1/clusters_missing.json", &res);
+
+    let body = json!({ "kind": "Kafka", "name": "remote", "config": { "bootstrap.servers": "remote:9092" } });
+    let res = f
+        .call(TestRequest::post().uri("/api/v1/clusters").set_json(body))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/clusters_create.json", &res);
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/clusters/2/metadata"))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/clusters_metadata.json", &res);
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/clusters/9/metadata"))
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_golden("v1/clusters_metadata_missing.json", &res);
+
+    let body = json!({ "kind": "Kafka", "name": "renamed", "config": { "bootstrap.servers": "localhost:9092" } });
+    let res = f
+        .call(TestRequest::put().uri("/api/v1/clusters/1").set_json(body))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/clusters_update.json", &res);
+
+    let res = f
+        .call(TestRequest::delete().uri("/api/v1/clusters/2"))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/clusters_delete.json", &res);
+}
+
+#[actix_web::test]
+async fn v1_subscriptions_match_goldens() {
+    let f = Fixture::new().await;
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/subscriptions/1"))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/subscriptions_list.json", &res);
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/subscriptions/1/1"))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/subscriptions_get.json", &res);
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/subscriptions/1/9"))
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_golden("v1/subscriptions_missing.json", &res);
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/subscriptions/9"))
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_golden("v1/subscriptions_cluster_missing.json", &res);
+
+    let body = json!({ "cluster_id": 1, "topic_name": "payments", "config": {} });
+    let res = f
+        .call(
+            TestRequest::post()
+                .uri("/api/v1/subscriptions?force=true")
+                .set_json(body),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/subscriptions_create.json", &res);
+
+    let body = json!({ "topic_name": "orders-v2", "config": {} });
+    let res = f
+        .call(
+            TestRequest::put()
+                .uri("/api/v1/subscriptions/1/1?force=true")
+                .set_json(body),
+        )
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_golden("v1/subscriptions_update.json", &res);
+
+    let res = f
+        .call(TestRequest::delete().uri("/api/v1/subscriptions/1/2"))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["id"], 2);
+}
+
+#[actix_web::test]
+async fn v2_clusters_use_typed_kinds_and_tagged_metadata() {
+    let f = Fixture::new().await;
+
+    let res = f.call(TestRequest::get().uri("/api/v2/clusters/1")).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["cluster"]["kind"], "kafka");
+    assert_eq!(res.json()["cluster"]["name"], "local");
+
+    let body = json!({ "kind": "kafka", "name": "remote", "config": { "bootstrap.servers": "remote:9092" } });
+    let res = f
+        .call(TestRequest::post().uri("/api/v2/clusters").set_json(body))
+        .await;
+    assert_eq!(res.status, StatusCode::CREATED);
+    assert_eq!(res.json(), json!({ "id": 2 }));
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v2/clusters/2/metadata"))
+        .await;
+    assert_eq!(res.status, StatusCode::ACCEPTED);
+    assert_eq!(res.json()["status"], "processing");
+    assert!(res.json()["retry_after_ms"].is_u64());
+
+    let res = f
+        .call(TestRequest::delete().uri("/api/v2/clusters/2"))
+        .await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn v2_shared_dtos_match_goldens() {
+    let f = Fixture::new().await;
+
+    let metadata = ClusterMetadata {
+        brokers: vec![BrokerMetadata {
+            id: 0,
+            host: "localhost".to_string(),
+            port: 9092,
+        }],
+        groups: vec![],
+        topics: vec![TopicMetadata {
+            name: "orders".to_string(),
+            partitions: vec![PartitionMetadata {
+                id: 0,
+                leader: 0,
+                replicas: vec![0],
+                isr: vec![0],
+                error: None,
+                low_watermark: None,
+                high_watermark: None,
+            }],
+            category: TopicCategory::User,
+        }],
+    };
+    f.manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            entries: vec![SyncedEntry {
+                cluster_id: ClusterId(1),
+                entry: CachedMetadataEntry::Meta(metadata),
+                offsets: None,
+            }],
+            clusters: vec![ClusterId(1)],
+        })
+        .await;
+
+    let cases = [
+        ("/api/v2/clusters", "v2/clusters_list.json"),
+        ("/api/v2/clusters/1", "v2/clusters_get.json"),
+        ("/api/v2/clusters/1/metadata", "v2/clusters_metadata.json"),
+        ("/api/v2/clusters/9", "v2/clusters_missing.json"),
+        ("/api/v2/subscriptions/1", "v2/subscriptions_list.json"),
+        ("/api/v2/subscriptions/1/1", "v2/subscriptions_get.json"),
+        ("/api/v1/admin/ready", "v1/admin_ready.json"),
+    ];
+    for (uri, golden) in cases {
+        let res = f.call(TestRequest::get().uri(uri)).await;
+        assert_golden(golden, &res);
+    }
+
+    let body = json!({ "kind": "kafka", "name": "remote", "config": { "bootstrap.servers": "remote:9092" } });
+    let res = f
+        .call(TestRequest::post().uri("/api/v2/clusters").set_json(body))
+        .await;
+    assert_golden("v2/clusters_create.json", &res);
+}
+
+#[actix_web::test]
+async fn v2_errors_are_structured() {
+    let f = Fixture::new().await;
+
+    let res = f.call(TestRequest::get().uri("/api/v2/clusters/9")).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        res.json(),
+        json!({ "error": { "code": "not_found", "message": "Cluster with id '9' not found" } })
+    );
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v2/subscriptions/1/9"))
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(res.json()["error"]["code"], "not_found");
+
+    let res = f
+        .call(TestRequest::get().uri("/api/v2/subscriptions/9"))
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        res.json()["error"]["message"],
+        "Cluster with id '9' not found"
+    );
+}
+
+#[actix_web::test]
+async fn v1_routes_with_a_successor_are_deprecated() {
+    let f = Fixture::new().await;
+
+    let res = f.call(TestRequest::get().uri("/api/v1/clusters/1")).await;
+    assert_eq!(res.headers.get("deprecation").unwrap(), "true");
+    assert_eq!(
+        res.headers.get("sunset").unwrap(),
+        super::deprecation::V1_SUNSET
+    );
+    assert_eq!(
+        res.headers.get("link").unwrap(),
+        "</api/v2/clusters/1>; rel=\"successor-version\""
+    );
+    assert!(super::deprecation::counts()["/api/v1/clusters/{id}"] >= 1);
+
+    // Routes only served by v1 are not deprecated yet.
+    let res = f
+        .call(TestRequest::get().uri("/api/v1/subscriptions/1/1/debug"))
+        .await;
+    assert!(res.headers.get("deprecation").is_none());
+
+    let res = f.call(TestRequest::get().uri("/api/v2/clusters/1")).await;
+    assert!(res.headers.get("deprecation").is_none());
+}
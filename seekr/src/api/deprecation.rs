@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+
+/// The date superseded v1 routes stop being served, as an HTTP-date.
+pub const V1_SUNSET: &str = "Thu, 01 Jul 2027 00:00:00 GMT";
+
+/// Number of requests between repeated deprecation log lines for a route.
+const LOG_EVERY: u64 = 1_000;
+
+lazy_static! {
+    static ref CALLS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+/// The number of calls made to each superseded route, keyed by route pattern.
+pub fn counts() -> HashMap<String, u64> {
+    CALLS.lock().unwrap().clone()
+}
+
+fn count(pattern: &str) -> u64 {
+    let mut calls = CALLS.lock().unwrap();
+    let count = calls.entry(pattern.to_string()).or_default();
+    *count += 1;
+    *count
+}
+
+/// Add `Deprecation`, `Sunset` and successor `Link` headers to v1 routes that
+/// have a v2 equivalent, and count their use per route.
+pub async fn flag_superseded(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let successor = req.path().replacen("/api/v1/", "/api/v2/", 1);
+    let superseded = req.request().resource_map().has_resource(&successor);
+    let pattern = req
+        .match_pattern()
+        .unwrap_or_else(|| req.path().to_string());
+
+    let mut res = next.call(req).await?;
+    if !superseded {
+        return Ok(res);
+    }
+
+    let calls = count(&pattern);
+    if calls % LOG_EVERY == 1 {
+        warn!(
+            "Deprecated route {} was called {} time(s), clients should migrate to {}",
+            pattern,
+            calls,
+            pattern.replacen("/api/v1/", "/api/v2/", 1)
+        );
+    }
+
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+    headers.insert(
+        HeaderName::from_static("sunset"),
+        HeaderValue::from_static(V1_SUNSET),
+    );
+    if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))
+    {
+        headers.insert(HeaderName::from_static("link"), link);
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,77 @@
+//! The v2 error envelope, `{"error": {"code", "message"}}` as
+//! `seekr_api_types` pins it. Errors v1 answers too are built as the same
+//! `ApiError`s, which v1 answers flat, so both share their codes and keep
+//! internal errors out of the response.
+
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use actix_web::ResponseError;
+use seekr_api_types::error::{ErrorDetail, ErrorResponse};
+use serde_json::json;
+
+use crate::deadline::DeadlineExceeded;
+use crate::errors::{AnyError, ApiError};
+use crate::validate::FieldError;
+
+/// An error only v2 answers, with a code of its own.
+pub fn error(status: StatusCode, code: &'static str, message: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(ErrorResponse {
+        error: ErrorDetail {
+            code: code.to_string(),
+            message: message.into(),
+            correlation_id: None,
+        },
+    })
+}
+
+/// The error in the v2 envelope, as v1 would answer it otherwise.
+pub fn respond(e: &ApiError) -> HttpResponse {
+    let body = e.body();
+    HttpResponse::build(e.status_code()).json(ErrorResponse {
+        error: ErrorDetail {
+            code: body.code.to_string(),
+            message: body.message,
+            correlation_id: body.correlation_id,
+        },
+    })
+}
+
+pub fn not_found(message: impl Into<String>) -> HttpResponse {
+    respond(&ApiError::not_found(message))
+}
+
+pub fn invalid(message: impl Into<String>) -> HttpResponse {
+    respond(&ApiError::invalid(message))
+}
+
+/// A request with invalid fields, each listed under `errors`.
+pub fn invalid_fields(errors: Vec<FieldError>) -> HttpResponse {
+    let body = ApiError::from(errors).body();
+    HttpResponse::BadRequest().json(json!({
+        "error": ErrorDetail {
+            code: body.code.to_string(),
+            message: body.message,
+            correlation_id: None,
+        },
+        "errors": body.details,
+    }))
+}
+
+/// Logged in full, and answered with only the id it's logged under.
+pub fn internal(e: AnyError) -> HttpResponse {
+    respond(&ApiError::Internal(e))
+}
+
+/// The request ran out of its deadline, flagged so callers can tell it from
+/// a dependency failing on its own.
+pub fn deadline_exceeded(e: &DeadlineExceeded) -> HttpResponse {
+    HttpResponse::GatewayTimeout().json(json!({
+        "error": ErrorDetail {
+            code: "timeout".to_string(),
+            message: e.to_string(),
+            correlation_id: None,
+        },
+        "deadline_exceeded": true,
+        "stage": e.stage,
+    }))
+}
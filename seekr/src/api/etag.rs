@@ -0,0 +1,71 @@
+use actix_web::http::header::{ETAG, IF_NONE_MATCH};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The tag of a body, a hash of it so equal bodies are tagged alike however
+/// they were produced.
+pub fn of(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let hex = digest[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("\"{}\"", hex)
+}
+
+/// Whether the client already holds the tagged body, having named its tag,
+/// or `*`, in `If-None-Match`. Weak tags of the body match too.
+pub fn matches(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get_all(IF_NONE_MATCH)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+/// A JSON response tagged with an `ETag`, or `304 Not Modified` without a
+/// body when the client already holds it, so pollers don't download the
+/// same body over and over.
+pub fn json(
+    req: &HttpRequest,
+    builder: &mut HttpResponseBuilder,
+    body: &impl Serialize,
+) -> HttpResponse {
+    let Ok(body) = serde_json::to_vec(body) else {
+        return builder.json(body);
+    };
+
+    let etag = of(&body);
+    if matches(req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header((ETAG, etag))
+            .finish();
+    }
+    builder
+        .insert_header((ETAG, etag))
+        .content_type("application/json")
+        .body(body)
+}
+
+#[test]
+fn it_matches_the_tags_clients_hold() {
+    use actix_web::test::TestRequest;
+
+    let etag = of(b"{\"brokers\":[]}");
+    assert_eq!(etag, of(b"{\"brokers\":[]}"));
+    assert_ne!(etag, of(b"{\"brokers\":[1]}"));
+
+    let held = |value: &str| {
+        TestRequest::default()
+            .insert_header((IF_NONE_MATCH, value))
+            .to_http_request()
+    };
+    assert!(matches(&held(&etag), &etag));
+    assert!(matches(&held(&format!("W/{}", etag)), &etag));
+    assert!(matches(&held(&format!("\"other\", {}", etag)), &etag));
+    assert!(matches(&held("*"), &etag));
+    assert!(!matches(&held("\"other\""), &etag));
+    assert!(!matches(&TestRequest::default().to_http_request(), &etag));
+}
@@ -0,0 +1,842 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::future::{ready, Ready};
+use std::marker::PhantomData;
+
+use actix_web::dev::Payload;
+use actix_web::web::Query;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, ResponseError};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::page;
+
+/// The most items a single page holds.
+pub const MAX_LIMIT: usize = 1000;
+
+/// The type of a listed field, which decides how filter values are parsed
+/// and which operators apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Timestamp,
+    Boolean,
+}
+
+/// A field of the items of a list endpoint, as clients name it.
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub kind: FieldType,
+    pub sortable: bool,
+    pub filterable: bool,
+
+    /// Where the field is stored in Meilisearch documents, e.g. `owner.team`.
+    pub attribute: &'static str,
+}
+
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+/// What a list endpoint can be sorted and filtered by.
+#[derive(Clone, Copy, Debug)]
+pub struct ListSchema {
+    pub fields: &'static [Field],
+
+    /// The unique field items are ordered by by default, and by within ties.
+    pub id: &'static str,
+}
+
+impl ListSchema {
+    fn field(&self, name: &str) -> Option<&'static Field> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    fn allowed(&self, pick: impl Fn(&Field) -> bool) -> String {
+        self.fields
+            .iter()
+            .filter(|f| pick(f))
+            .map(|f| f.name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Items of a list endpoint, whose fields can be read by name.
+pub trait Listable {
+    const SCHEMA: ListSchema;
+
+    /// The value of a field of the schema, `None` when it isn't set.
+    fn value(&self, field: &str) -> Option<Value>;
+}
+
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Timestamp(DateTime<Utc>),
+    Boolean(bool),
+}
+
+impl Value {
+    fn parse(kind: FieldType, s: &str) -> Result<Self, String> {
+        match kind {
+            FieldType::String => Ok(Value::String(s.to_string())),
+            FieldType::Integer => s
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("'{}' is not an integer", s)),
+            FieldType::Timestamp => DateTime::parse_from_rfc3339(s)
+                .map(|t| Value::Timestamp(t.with_timezone(&Utc)))
+                .map_err(|_| format!("'{}' is not an RFC 3339 timestamp", s)),
+            FieldType::Boolean => s
+                .parse()
+                .map(Value::Boolean)
+                .map_err(|_| format!("'{}' is not a boolean", s)),
+        }
+    }
+
+    /// The value as a Meilisearch filter operand.
+    fn to_meili(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        match self {
+            Value::String(s) => quote(s),
+            Value::Integer(i) => i.to_string(),
+            Value::Timestamp(t) => quote(&t.to_rfc3339()),
+            Value::Boolean(b) => b.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Prefix,
+    Gte,
+    Lte,
+}
+
+impl Op {
+    const ALL: [Op; 5] = [Op::Eq, Op::Ne, Op::Prefix, Op::Gte, Op::Lte];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Eq => "eq",
+            Op::Ne => "ne",
+            Op::Prefix => "prefix",
+            Op::Gte => "gte",
+            Op::Lte => "lte",
+        }
+    }
+
+    fn applies_to(&self, kind: FieldType) -> bool {
+        match self {
+            Op::Eq | Op::Ne => true,
+            Op::Prefix => kind == FieldType::String,
+            Op::Gte | Op::Lte => kind != FieldType::Boolean,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sort {
+    pub field: &'static Field,
+    pub descending: bool,
+}
+
+/// The sort as clients give it, e.g. `name:desc`.
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = if self.descending { "desc" } else { "asc" };
+        write!(f, "{}:{}", self.field.name, direction)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub field: &'static Field,
+    pub op: Op,
+    pub value: Value,
+}
+
+impl Filter {
+    fn matches(&self, value: Option<&Value>) -> bool {
+        match (self.op, value) {
+            (Op::Ne, None) => true,
+            (_, None) => false,
+            (Op::Eq, Some(v)) => *v == self.value,
+            (Op::Ne, Some(v)) => *v != self.value,
+            (Op::Gte, Some(v)) => v >= &self.value,
+            (Op::Lte, Some(v)) => v <= &self.value,
+            (Op::Prefix, Some(Value::String(s))) => match &self.value {
+                Value::String(prefix) => s.starts_with(prefix.as_str()),
+                _ => false,
+            },
+            (Op::Prefix, Some(_)) => false,
+        }
+    }
+}
+
+/// An invalid `limit`, `offset`, `cursor`, `sort`, `order` or `filter` parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListError(pub String);
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ResponseError for ListError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::BadRequest().body(self.0.clone())
+    }
+}
+
+fn descending(direction: &str) -> Result<bool, ListError> {
+    match direction {
+        "asc" => Ok(false),
+        "desc" => Ok(true),
+        d => Err(ListError(format!(
+            "unknown sort direction '{}', expected one of: asc, desc",
+            d
+        ))),
+    }
+}
+
+/// The position of the next page, opaque to clients.
+fn encode_cursor(offset: usize) -> String {
+    base64::encode_config(offset.to_string(), base64::URL_SAFE_NO_PAD)
+}
+
+fn decode_cursor(s: &str) -> Result<usize, ListError> {
+    base64::decode_config(s, base64::URL_SAFE_NO_PAD)
+        .ok()
+        .and_then(|b| String::from_utf8(b).ok())
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| ListError(format!("cursor '{}' is not valid", s)))
+}
+
+/// The `limit`, `offset` or `cursor`, `sort` and `order`, and repeated
+/// `filter` parameters of a list endpoint, validated against the schema of
+/// its items.
+///
+/// Parameters other than these are left to the endpoint, so it keeps the ones
+/// it supported before.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListQuery<T> {
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub sort: Option<Sort>,
+    pub filters: Vec<Filter>,
+    _items: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for ListQuery<T> {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            offset: 0,
+            sort: None,
+            filters: vec![],
+            _items: PhantomData,
+        }
+    }
+}
+
+impl<T: Listable> ListQuery<T> {
+    pub fn parse(params: &[(String, String)]) -> Result<Self, ListError> {
+        let schema = T::SCHEMA;
+        let mut query = Self::default();
+        let mut order = None;
+        let mut directed = false;
+
+        for (key, value) in params {
+            match key.as_str() {
+                "limit" => {
+                    let limit = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|l| (1..=MAX_LIMIT).contains(l))
+                        .ok_or_else(|| {
+                            ListError(format!("limit must be between 1 and {}", MAX_LIMIT))
+                        })?;
+                    query.limit = Some(limit);
+                }
+                "offset" => {
+                    query.offset = value.parse().map_err(|_| {
+                        ListError(format!("offset '{}' is not a non-negative integer", value))
+                    })?
+                }
+                "cursor" => query.offset = decode_cursor(value)?,
+                "sort" => {
+                    directed = value.contains(':');
+                    let (name, direction) = value.split_once(':').unwrap_or((value, "asc"));
+                    let field = schema.field(name).filter(|f| f.sortable).ok_or_else(|| {
+                        ListError(format!(
+                            "cannot sort by '{}', expected one of: {}",
+                            name,
+                            schema.allowed(|f| f.sortable)
+                        ))
+                    })?;
+                    let descending = descending(direction)?;
+                    query.sort = Some(Sort { field, descending });
+                }
+                "order" => order = Some(descending(value)?),
+                "filter" => query.filters.push(Self::filter(value)?),
+                _ => {}
+            }
+        }
+
+        // `order` directs a `sort` given as a bare field, e.g. `sort=name&order=desc`.
+        if let Some(descending) = order {
+            match query.sort.as_mut() {
+                None => return Err(ListError("order needs a sort to direct".to_string())),
+                Some(sort) if directed => {
+                    return Err(ListError(format!(
+                        "sort '{}' already has a direction, leave out order",
+                        sort
+                    )))
+                }
+                Some(sort) => sort.descending = descending,
+            }
+        }
+
+        Ok(query)
+    }
+
+    fn filter(s: &str) -> Result<Filter, ListError> {
+        let schema = T::SCHEMA;
+        let mut parts = s.splitn(3, ':');
+        let (Some(name), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(ListError(format!(
+                "filter '{}' must look like field:op:value",
+                s
+            )));
+        };
+
+        let field = schema.field(name).filter(|f| f.filterable).ok_or_else(|| {
+            ListError(format!(
+                "cannot filter by '{}', expected one of: {}",
+                name,
+                schema.allowed(|f| f.filterable)
+            ))
+        })?;
+        let op = Op::ALL
+            .into_iter()
+            .find(|o| o.name() == op && o.applies_to(field.kind))
+            .ok_or_else(|| {
+                let allowed = Op::ALL
+                    .iter()
+                    .filter(|o| o.applies_to(field.kind))
+                    .map(|o| o.name())
+                    .collect::<Vec<_>>();
+                ListError(format!(
+                    "cannot filter '{}' with '{}', expected one of: {}",
+                    name,
+                    op,
+                    allowed.join(", ")
+                ))
+            })?;
+        let value = Value::parse(field.kind, value)
+            .map_err(|e| ListError(format!("cannot filter by '{}': {}", name, e)))?;
+
+        Ok(Filter { field, op, value })
+    }
+
+    /// Whether any of the parameters was given, which endpoints that predate
+    /// them answer with a `Page` rather than their own envelope.
+    pub fn is_given(&self) -> bool {
+        self.limit.is_some() || self.offset > 0 || self.sort.is_some() || !self.filters.is_empty()
+    }
+
+    /// Whether the query only pages, so a store listing in the endpoint's
+    /// default order can page the items itself.
+    pub fn is_paging_only(&self) -> bool {
+        self.sort.is_none() && self.filters.is_empty()
+    }
+
+    /// The page of items a store lists.
+    pub fn window(&self) -> page::Page {
+        page::Page::new(self.offset, self.limit)
+    }
+
+    /// The page of items a store already paged, out of `total` items.
+    pub fn paged(&self, items: Vec<T>, total: usize) -> Page<T> {
+        let end = self.offset + items.len();
+        Page {
+            items,
+            next_cursor: (self.limit.is_some() && end < total).then(|| encode_cursor(end)),
+            total,
+            limit: self.limit,
+            offset: self.offset,
+            total_estimate: total,
+            sort: self.sort.as_ref().map(Sort::to_string),
+        }
+    }
+
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        // Items without the field go last, in either direction.
+        let by = |field: &str, descending: bool| match (a.value(field), b.value(field)) {
+            (Some(a), Some(b)) => {
+                let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+
+        let sorted = self
+            .sort
+            .as_ref()
+            .map_or(Ordering::Equal, |s| by(s.field.name, s.descending));
+        sorted.then_with(|| by(T::SCHEMA.id, false))
+    }
+
+    /// Filters, sorts and pages items that are already in memory.
+    ///
+    /// Without a sort, items keep the order they came in, so the endpoint's
+    /// default order is unchanged.
+    pub fn apply(&self, items: Vec<T>) -> Page<T> {
+        let mut items = items
+            .into_iter()
+            .filter(|i| {
+                self.filters
+                    .iter()
+                    .all(|f| f.matches(i.value(f.field.name).as_ref()))
+            })
+            .collect::<Vec<_>>();
+        if self.sort.is_some() {
+            items.sort_by(|a, b| self.compare(a, b));
+        }
+
+        let total = items.len();
+        let end = self.limit.map_or(total, |l| (self.offset + l).min(total));
+        let items = items
+            .into_iter()
+            .skip(self.offset)
+            .take(end.saturating_sub(self.offset))
+            .collect();
+
+        Page {
+            items,
+            next_cursor: (end < total).then(|| encode_cursor(end)),
+            total,
+            limit: self.limit,
+            offset: self.offset,
+            total_estimate: total,
+            sort: self.sort.as_ref().map(Sort::to_string),
+        }
+    }
+
+    /// The query as Meilisearch search parameters, for endpoints that list
+    /// straight from an index.
+    pub fn to_meili(&self) -> MeiliQuery {
+        let filters = self
+            .filters
+            .iter()
+            .map(|f| {
+                let (attribute, value) = (f.field.attribute, f.value.to_meili());
+                match f.op {
+                    Op::Eq => format!("{} = {}", attribute, value),
+                    Op::Ne => format!("{} != {}", attribute, value),
+                    Op::Prefix => format!("{} STARTS WITH {}", attribute, value),
+                    Op::Gte => format!("{} >= {}", attribute, value),
+                    Op::Lte => format!("{} <= {}", attribute, value),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let id = T::SCHEMA
+            .field(T::SCHEMA.id)
+            .map_or(T::SCHEMA.id, |f| f.attribute);
+        let mut sort = self
+            .sort
+            .iter()
+            .map(|s| {
+                let direction = if s.descending { "desc" } else { "asc" };
+                format!("{}:{}", s.field.attribute, direction)
+            })
+            .collect::<Vec<_>>();
+        sort.push(format!("{}:asc", id));
+
+        MeiliQuery {
+            filter: (!filters.is_empty()).then(|| filters.join(" AND ")),
+            sort,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+/// Parses the parameters of the request, see `ListQuery::parse`.
+///
+/// Endpoints answering errors of their own take `Result<ListQuery<T>, ListError>`.
+impl<T: Listable> FromRequest for ListQuery<T> {
+    type Error = ListError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let params = Query::<Vec<(String, String)>>::from_query(req.query_string())
+            .map(Query::into_inner)
+            .map_err(|e| ListError(e.to_string()));
+        ready(params.and_then(|p| Self::parse(&p)))
+    }
+}
+
+/// A `ListQuery` translated to the parameters of a Meilisearch search.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeiliQuery {
+    pub filter: Option<String>,
+    pub sort: Vec<String>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// A page of a list endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+
+    /// Passed as `cursor` to get the next page, `null` on the last one.
+    pub next_cursor: Option<String>,
+
+    /// How many items match the filters, across all pages.
+    pub total: usize,
+
+    /// The `limit` and `offset` the page was listed with, for pagers.
+    pub limit: Option<usize>,
+    pub offset: usize,
+
+    /// The same as `total`, kept for the clients reading it.
+    pub total_estimate: usize,
+
+    /// The sort the items were listed in, e.g. `name:desc`, unless they're
+    /// in the endpoint's default order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+            total_estimate: self.total_estimate,
+            sort: self.sort,
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+struct Item {
+    id: i64,
+    name: &'static str,
+    team: Option<&'static str>,
+    paused: bool,
+}
+
+#[cfg(test)]
+impl Listable for Item {
+    const SCHEMA: ListSchema = ListSchema {
+        fields: &[
+            Field {
+                name: "id",
+                kind: FieldType::Integer,
+                sortable: true,
+                filterable: true,
+                attribute: "id",
+            },
+            Field {
+                name: "name",
+                kind: FieldType::String,
+                sortable: true,
+                filterable: true,
+                attribute: "name",
+            },
+            Field {
+                name: "team",
+                kind: FieldType::String,
+                sortable: false,
+                filterable: true,
+                attribute: "owner.team",
+            },
+            Field {
+                name: "paused",
+                kind: FieldType::Boolean,
+                sortable: false,
+                filterable: true,
+                attribute: "paused",
+            },
+        ],
+        id: "id",
+    };
+
+    fn value(&self, field: &str) -> Option<Value> {
+        match field {
+            "id" => Some(Value::Integer(self.id)),
+            "name" => Some(Value::String(self.name.to_string())),
+            "team" => self.team.map(|t| Value::String(t.to_string())),
+            "paused" => Some(Value::Boolean(self.paused)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+fn parse(params: &[(&str, &str)]) -> Result<ListQuery<Item>, ListError> {
+    let params = params
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>();
+    ListQuery::parse(&params)
+}
+
+#[test]
+fn it_parses_list_parameters() {
+    let query = parse(&[
+        ("limit", "2"),
+        ("sort", "name:desc"),
+        ("filter", "team:eq:payments"),
+        ("filter", "name:prefix:orders:v2"),
+        ("team", "ignored"),
+    ])
+    .unwrap();
+    assert_eq!(query.limit, Some(2));
+    assert_eq!(
+        query.sort.as_ref().map(|s| (s.field.name, s.descending)),
+        Some(("name", true))
+    );
+    assert_eq!(query.filters.len(), 2);
+    // Values keep their colons.
+    assert_eq!(
+        query.filters[1].value,
+        Value::String("orders:v2".to_string())
+    );
+    assert!(query.is_given());
+    assert!(!parse(&[("team", "payments")]).unwrap().is_given());
+
+    // Sorting defaults to ascending, unless `order` says otherwise.
+    let query = parse(&[("sort", "id")]).unwrap();
+    assert_eq!(query.sort.map(|s| s.descending), Some(false));
+    let query = parse(&[("sort", "name"), ("order", "desc")]).unwrap();
+    assert_eq!(query.sort.unwrap().to_string(), "name:desc");
+
+    let error = |params: &[(&str, &str)]| parse(params).unwrap_err().0;
+    assert_eq!(
+        error(&[("sort", "team")]),
+        "cannot sort by 'team', expected one of: id, name"
+    );
+    assert_eq!(
+        error(&[("filter", "owner:eq:x")]),
+        "cannot filter by 'owner', expected one of: id, name, team, paused"
+    );
+    assert_eq!(
+        error(&[("filter", "paused:gte:true")]),
+        "cannot filter 'paused' with 'gte', expected one of: eq, ne"
+    );
+    assert_eq!(
+        error(&[("filter", "id:eq:one")]),
+        "cannot filter by 'id': 'one' is not an integer"
+    );
+    assert_eq!(
+        error(&[("filter", "id")]),
+        "filter 'id' must look like field:op:value"
+    );
+    assert_eq!(error(&[("limit", "0")]), "limit must be between 1 and 1000");
+    assert_eq!(
+        error(&[("sort", "id:up")]),
+        "unknown sort direction 'up', expected one of: asc, desc"
+    );
+    assert_eq!(error(&[("order", "desc")]), "order needs a sort to direct");
+    assert_eq!(
+        error(&[("sort", "id:asc"), ("order", "desc")]),
+        "sort 'id:asc' already has a direction, leave out order"
+    );
+    assert_eq!(
+        error(&[("sort", "id"), ("order", "down")]),
+        "unknown sort direction 'down', expected one of: asc, desc"
+    );
+    assert_eq!(error(&[("cursor", "!")]), "cursor '!' is not valid");
+    assert_eq!(
+        error(&[("offset", "-1")]),
+        "offset '-1' is not a non-negative integer"
+    );
+}
+
+#[test]
+fn it_filters_sorts_and_pages_in_memory() {
+    let items = vec![
+        Item {
+            id: 3,
+            name: "orders",
+            team: Some("payments"),
+            paused: false,
+        },
+        Item {
+            id: 1,
+            name: "refunds",
+            team: Some("payments"),
+            paused: true,
+        },
+        Item {
+            id: 2,
+            name: "audit",
+            team: None,
+            paused: false,
+        },
+        Item {
+            id: 4,
+            name: "orders-v2",
+            team: Some("billing"),
+            paused: false,
+        },
+    ];
+    let ids = |page: &Page<Item>| page.items.iter().map(|i| i.id).collect::<Vec<_>>();
+
+    // Without parameters, everything is listed in the order it came in.
+    let page = parse(&[]).unwrap().apply(items.clone());
+    assert_eq!(ids(&page), vec![3, 1, 2, 4]);
+    assert_eq!((page.next_cursor, page.total_estimate), (None, 4));
+    assert_eq!(page.sort, None);
+
+    let page = parse(&[("filter", "team:ne:billing"), ("sort", "name:asc")])
+        .unwrap()
+        .apply(items.clone());
+    assert_eq!(ids(&page), vec![2, 3, 1]);
+
+    let page = parse(&[
+        ("filter", "name:prefix:orders"),
+        ("filter", "paused:eq:false"),
+    ])
+    .unwrap()
+    .apply(items.clone());
+    assert_eq!(ids(&page), vec![3, 4]);
+
+    let page = parse(&[("filter", "id:gte:2"), ("filter", "id:lte:3")])
+        .unwrap()
+        .apply(items.clone());
+    assert_eq!(ids(&page), vec![3, 2]);
+
+    // Pages follow each other through the cursor, ties broken by id.
+    let first = parse(&[("limit", "3"), ("sort", "id:desc")])
+        .unwrap()
+        .apply(items.clone());
+    assert_eq!(ids(&first), vec![4, 3, 2]);
+    assert_eq!(first.total_estimate, 4);
+    let cursor = first.next_cursor.unwrap();
+    let second = parse(&[("limit", "3"), ("sort", "id:desc"), ("cursor", &cursor)])
+        .unwrap()
+        .apply(items.clone());
+    assert_eq!(ids(&second), vec![1]);
+    assert_eq!(second.next_cursor, None);
+    assert_eq!(second.sort.as_deref(), Some("id:desc"));
+
+    // Offsets page like cursors, and past the end list nothing.
+    let page = parse(&[("limit", "2"), ("offset", "1")])
+        .unwrap()
+        .apply(items.clone());
+    assert_eq!(ids(&page), vec![1, 2]);
+    assert_eq!((page.total, page.limit, page.offset), (4, Some(2), 1));
+    let past = parse(&[("limit", "2"), ("offset", "9")])
+        .unwrap()
+        .apply(items.clone());
+    assert_eq!(ids(&past), Vec::<i64>::new());
+    assert_eq!((past.next_cursor, past.total), (None, 4));
+
+    // Pages a store already listed continue from where they end.
+    let query = parse(&[("limit", "2"), ("offset", "1")]).unwrap();
+    assert!(query.is_paging_only());
+    assert_eq!(query.window(), crate::page::Page::new(1, Some(2)));
+    let paged = query.paged(items[1..3].to_vec(), 4);
+    assert_eq!(ids(&paged), vec![1, 2]);
+    assert_eq!(paged.next_cursor, Some(encode_cursor(3)));
+    assert!(!parse(&[("sort", "id:asc")]).unwrap().is_paging_only());
+}
+
+#[test]
+fn it_translates_queries_to_meilisearch() {
+    let query = parse(&[
+        ("filter", "team:eq:pay\"ments"),
+        ("filter", "name:prefix:orders"),
+        ("filter", "id:gte:10"),
+        ("filter", "paused:ne:true"),
+        ("sort", "name:desc"),
+        ("limit", "20"),
+        ("cursor", &encode_cursor(40)),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        query.to_meili(),
+        MeiliQuery {
+            filter: Some(
+                "owner.team = \"pay\\\"ments\" AND name STARTS WITH \"orders\" AND id >= 10 AND paused != true"
+                    .to_string()
+            ),
+            sort: vec!["name:desc".to_string(), "id:asc".to_string()],
+            offset: 40,
+            limit: Some(20),
+        }
+    );
+    assert_eq!(
+        parse(&[]).unwrap().to_meili(),
+        MeiliQuery {
+            sort: vec!["id:asc".to_string()],
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn it_serializes_pages() {
+    let page = Page {
+        items: vec![1, 2],
+        next_cursor: Some(encode_cursor(2)),
+        total: 5,
+        limit: Some(2),
+        offset: 0,
+        total_estimate: 5,
+        sort: Some("name:desc".to_string()),
+    };
+    assert_eq!(
+        serde_json::to_value(page.map(|i| i * 10)).unwrap(),
+        serde_json::json!({
+            "items": [10, 20],
+            "next_cursor": "Mg",
+            "total": 5,
+            "limit": 2,
+            "offset": 0,
+            "total_estimate": 5,
+            "sort": "name:desc"
+        })
+    );
+
+    let last = Page::<i32> {
+        items: vec![],
+        next_cursor: None,
+        total: 0,
+        limit: None,
+        offset: 0,
+        total_estimate: 0,
+        sort: None,
+    };
+    assert_eq!(
+        serde_json::to_string(&last).unwrap(),
+        r#"{"items":[],"next_cursor":null,"total":0,"limit":null,"offset":0,"total_estimate":0}"#
+    );
+}
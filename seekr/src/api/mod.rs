@@ -0,0 +1,79 @@
+use std::fmt;
+
+use actix_web::middleware::from_fn;
+use actix_web::web::{self, ServiceConfig};
+
+use crate::{auth, deadline, request_id, standby};
+
+pub mod deprecation;
+pub mod error;
+pub mod etag;
+pub mod list;
+pub mod ndjson;
+pub mod retry;
+pub mod routes;
+pub mod sse;
+
+#[cfg(test)]
+mod client;
+#[cfg(test)]
+mod compat;
+
+/// A major version of the REST API.
+///
+/// Every version is mounted under its own `/api/{version}` scope. Resources
+/// share their business logic through version-agnostic service functions and
+/// only map to and from their own DTOs per version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiVersion::V1 => write!(f, "v1"),
+            ApiVersion::V2 => write!(f, "v2"),
+        }
+    }
+}
+
+/// Mount a resource scope of the given version.
+///
+/// Every scope authenticates its callers, sends writes to the primary while
+/// the instance is a standby, flags routes of deprecated versions once their
+/// successor exists, attaches the deadline requests are answered by, and
+/// handles each request under its id.
+pub fn scope(
+    cfg: &mut ServiceConfig,
+    version: ApiVersion,
+    resource: &str,
+    configure: impl FnOnce(&mut ServiceConfig),
+) {
+    let path = format!("api/{}/{}", version, resource);
+
+    match version {
+        ApiVersion::V1 => cfg.service(
+            web::scope(&path)
+                .wrap(from_fn(deprecation::flag_superseded))
+                .wrap(from_fn(auth::middleware::authenticate))
+                .wrap(from_fn(standby::middleware::redirect_writes))
+                .wrap(from_fn(deadline::propagate))
+                .wrap(from_fn(request_id::propagate))
+                .configure(configure),
+        ),
+        ApiVersion::V2 => cfg.service(
+            web::scope(&path)
+                .wrap(from_fn(auth::middleware::authenticate))
+                .wrap(from_fn(standby::middleware::redirect_writes))
+                .wrap(from_fn(deadline::propagate))
+                .wrap(from_fn(request_id::propagate))
+                .configure(configure),
+        ),
+    };
+}
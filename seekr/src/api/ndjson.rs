@@ -0,0 +1,205 @@
+use std::convert::Infallible;
+
+use actix_web::http::header::ACCEPT;
+use actix_web::{HttpRequest, HttpResponse};
+use bytes::Bytes;
+use serde::Serialize;
+
+pub const CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Whether the caller asked for newline delimited JSON.
+pub fn accepts(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all(ACCEPT)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|t| t.split(';').next())
+        .any(|t| t.trim().eq_ignore_ascii_case(CONTENT_TYPE))
+}
+
+#[derive(Serialize)]
+struct Summary {
+    count: usize,
+
+    /// Set when the stream stopped short of the last item.
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct SummaryLine {
+    summary: Summary,
+}
+
+/// Serializes items one line at a time, closing with the summary line.
+struct Lines<I> {
+    items: I,
+    count: usize,
+    done: bool,
+}
+
+impl<I> Iterator for Lines<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    type Item = Result<Bytes, Infallible>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let truncated = match self.items.next() {
+            Some(item) => match serde_json::to_vec(&item) {
+                Ok(mut line) => {
+                    self.count += 1;
+                    line.push(b'\n');
+                    return Some(Ok(Bytes::from(line)));
+                }
+                Err(e) => {
+                    warn!("Stopped streaming after {} items - {}", self.count, e);
+                    true
+                }
+            },
+            None => false,
+        };
+
+        self.done = true;
+        let summary = SummaryLine {
+            summary: Summary {
+                count: self.count,
+                truncated,
+            },
+        };
+        let mut line = serde_json::to_vec(&summary).unwrap_or_default();
+        line.push(b'\n');
+        Some(Ok(Bytes::from(line)))
+    }
+}
+
+/// Stream the items as newline delimited JSON, one per line, followed by a
+/// `{"summary": {"count", "truncated"}}` line.
+///
+/// Items are serialized as the client reads them, so a response holds a
+/// single line at a time however many items there are, and serializing
+/// stops as soon as the client disconnects. Streams missing the summary
+/// line were cut off.
+pub fn stream<I>(items: I) -> HttpResponse
+where
+    I: IntoIterator,
+    I::IntoIter: 'static,
+    I::Item: Serialize,
+{
+    let lines = Lines {
+        items: items.into_iter(),
+        count: 0,
+        done: false,
+    };
+
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .streaming(futures::stream::iter(lines))
+}
+
+/// The next line of a streamed body, `None` once the stream ended.
+#[cfg(test)]
+pub async fn next_line(body: &mut actix_web::body::BoxBody) -> Option<String> {
+    use actix_web::body::MessageBody;
+
+    let chunk = std::future::poll_fn(|cx| std::pin::Pin::new(&mut *body).poll_next(cx)).await?;
+    Some(String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+}
+
+#[actix_web::test]
+async fn it_frames_one_item_per_line() {
+    use actix_web::test::TestRequest;
+
+    let req = TestRequest::get()
+        .insert_header((ACCEPT, "application/json;q=0.5, application/x-ndjson"))
+        .to_http_request();
+    assert!(accepts(&req));
+    assert!(!accepts(&TestRequest::get().to_http_request()));
+
+    let items = vec![
+        serde_json::json!({ "name": "orders", "note": "line\nbreak" }),
+        serde_json::json!({ "name": "refunds" }),
+    ];
+    let res = stream(items);
+    assert_eq!(res.headers().get("content-type").unwrap(), CONTENT_TYPE);
+
+    let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    let lines = body
+        .lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+        .collect::<Vec<_>>();
+    assert!(body.ends_with('\n'));
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["note"], "line\nbreak");
+    assert_eq!(lines[1]["name"], "refunds");
+    assert_eq!(lines[2]["summary"]["count"], 2);
+    assert_eq!(lines[2]["summary"]["truncated"], false);
+}
+
+#[actix_web::test]
+async fn it_serializes_lazily_and_stops_on_disconnect() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts the items taken and whether it was dropped.
+    struct Counted {
+        taken: Arc<AtomicUsize>,
+        _alive: Arc<()>,
+    }
+
+    impl Iterator for Counted {
+        type Item = Vec<u64>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let n = self.taken.fetch_add(1, Ordering::SeqCst);
+            (n < 100_000).then(|| vec![n as u64; 64])
+        }
+    }
+
+    let taken = Arc::new(AtomicUsize::new(0));
+    let alive = Arc::new(());
+    let items = Counted {
+        taken: taken.clone(),
+        _alive: alive.clone(),
+    };
+    let mut body = stream(items).into_body();
+
+    // Each read holds a single item, never the whole response.
+    for _ in 0..3 {
+        let line = next_line(&mut body).await.unwrap();
+        assert!(line.len() < 1_024, "{}", line.len());
+    }
+    assert_eq!(taken.load(Ordering::SeqCst), 3);
+
+    // Dropping the body, as actix does when the client goes away, drops the items.
+    drop(body);
+    assert_eq!(Arc::strong_count(&alive), 1);
+    assert_eq!(taken.load(Ordering::SeqCst), 3);
+}
+
+#[actix_web::test]
+async fn it_flags_streams_cut_short() {
+    use std::collections::HashMap;
+
+    // Maps with non-string keys don't serialize to JSON.
+    let items: Vec<HashMap<Vec<u8>, u8>> = vec![
+        HashMap::new(),
+        HashMap::from([(vec![1], 1)]),
+        HashMap::new(),
+    ];
+
+    let body = actix_web::body::to_bytes(stream(items).into_body())
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    let lines = body.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec!["{}", r#"{"summary":{"count":1,"truncated":true}}"#]
+    );
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Shortest retry hint handed to clients, so they never retry in a tight loop.
+pub const MIN_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Longest retry hint handed to clients.
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Clamp a retry hint to sane bounds.
+pub fn clamp(hint: Duration) -> Duration {
+    hint.clamp(MIN_RETRY_AFTER, MAX_RETRY_AFTER)
+}
+
+/// The `Retry-After` value of a clamped hint, in whole seconds rounded up.
+fn seconds(hint: Duration) -> u64 {
+    (hint.as_millis() as u64).div_ceil(1_000)
+}
+
+/// Attach a `Retry-After` header to a response whose body can't carry the hint.
+pub fn with_retry_after(
+    builder: &mut HttpResponseBuilder,
+    hint: Duration,
+) -> &mut HttpResponseBuilder {
+    builder.insert_header((RETRY_AFTER, seconds(clamp(hint))))
+}
+
+/// A "try again later" response, hinting when to retry with both a
+/// `Retry-After` header and a `retry_after_ms` field in the JSON body.
+pub fn retry_later(status: StatusCode, body: impl Serialize, hint: Duration) -> HttpResponse {
+    let hint = clamp(hint);
+
+    let mut body = serde_json::to_value(body).unwrap_or(Value::Null);
+    if let Value::Object(fields) = &mut body {
+        fields.insert(
+            "retry_after_ms".to_string(),
+            Value::from(hint.as_millis() as u64),
+        );
+    }
+
+    HttpResponse::build(status)
+        .insert_header((RETRY_AFTER, seconds(hint)))
+        .json(body)
+}
+
+#[test]
+fn it_clamps_hints_and_rounds_up_seconds() {
+    assert_eq!(clamp(Duration::ZERO), MIN_RETRY_AFTER);
+    assert_eq!(clamp(Duration::from_secs(3_600)), MAX_RETRY_AFTER);
+    assert_eq!(seconds(Duration::from_millis(1_001)), 2);
+    assert_eq!(seconds(Duration::from_millis(29_000)), 29);
+}
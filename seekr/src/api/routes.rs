@@ -0,0 +1,234 @@
+use actix_web::middleware::from_fn;
+use actix_web::web::{self, ServiceConfig};
+
+use crate::api::{self, ApiVersion};
+use crate::standby;
+
+/// A route attribute found by the build script in an `endpoints/v{n}.rs` file.
+#[derive(Debug, Clone, Copy)]
+pub struct EndpointRoute {
+    pub module: &'static str,
+    pub version: &'static str,
+    pub method: &'static str,
+    pub path: &'static str,
+}
+
+// `ENDPOINT_MODULES` and `ENDPOINT_ROUTES`, listed by `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/endpoints.rs"));
+
+/// Modules whose endpoints are only compiled in with a feature, and whether it's enabled.
+const OPTIONAL: &[(&str, bool)] = &[("failpoints", cfg!(feature = "chaos"))];
+
+/// Who calls the routes of a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// Users and clients, authenticated by their API key once auth is enabled.
+    Users,
+
+    /// Operators, mounted like the routes of users but refused to anyone
+    /// but admins by the handlers.
+    Admins,
+
+    /// Other seekr instances, authenticated by the internal token.
+    Instances,
+}
+
+/// The routes an `endpoints` module contributes, and where they're mounted.
+#[derive(Clone, Copy)]
+pub struct RouteGroup {
+    /// The `module_path!()` of the endpoints module.
+    pub module: &'static str,
+
+    /// The scope under `api/{version}`, or `internal/{version}` for instances.
+    /// Groups sharing a scope are mounted into it in the order they're registered.
+    pub scope: &'static str,
+
+    pub versions: &'static [ApiVersion],
+    pub audience: Audience,
+    pub configure: fn(&mut ServiceConfig, ApiVersion),
+}
+
+impl RouteGroup {
+    /// The feature of the group, `clusters` for `seekr::clusters::endpoints`.
+    pub fn feature(&self) -> &'static str {
+        let path = self.module.split_once("::").map_or(self.module, |(_, p)| p);
+        path.strip_suffix("::endpoints").unwrap_or(path)
+    }
+
+    pub fn path(&self, version: ApiVersion) -> String {
+        let root = match self.audience {
+            Audience::Instances => "internal",
+            Audience::Users | Audience::Admins => "api",
+        };
+        match self.scope.is_empty() {
+            true => format!("{}/{}", root, version),
+            false => format!("{}/{}/{}", root, version, self.scope),
+        }
+    }
+
+    fn is_internal(&self) -> bool {
+        self.audience == Audience::Instances
+    }
+}
+
+/// Mount the groups, one scope per path in the order the groups are registered.
+///
+/// Internal scopes only exist for the versions of their groups, while every
+/// API scope is mounted on every version, so a route missing from one version
+/// is authenticated like any other before it's not found.
+pub fn mount(cfg: &mut ServiceConfig, groups: &[RouteGroup]) {
+    for version in ApiVersion::ALL {
+        for scope in scopes(groups, true) {
+            let members = members(groups, scope, true, version);
+            if members.is_empty() {
+                continue;
+            }
+            cfg.service(
+                web::scope(&members[0].path(version))
+                    .wrap(from_fn(standby::middleware::authenticate_internal))
+                    .configure(|c| members.iter().for_each(|g| (g.configure)(c, version))),
+            );
+        }
+    }
+
+    for version in ApiVersion::ALL {
+        for scope in scopes(groups, false) {
+            let members = members(groups, scope, false, version);
+            api::scope(cfg, version, scope, |c| {
+                members.iter().for_each(|g| (g.configure)(c, version))
+            });
+        }
+    }
+}
+
+/// The distinct scopes of the internal or the API groups, in registry order.
+fn scopes(groups: &[RouteGroup], internal: bool) -> Vec<&'static str> {
+    let mut scopes = vec![];
+    for group in groups.iter().filter(|g| g.is_internal() == internal) {
+        if !scopes.contains(&group.scope) {
+            scopes.push(group.scope);
+        }
+    }
+    scopes
+}
+
+fn members(
+    groups: &[RouteGroup],
+    scope: &str,
+    internal: bool,
+    version: ApiVersion,
+) -> Vec<RouteGroup> {
+    groups
+        .iter()
+        .filter(|g| g.is_internal() == internal && g.scope == scope)
+        .filter(|g| g.versions.contains(&version))
+        .copied()
+        .collect()
+}
+
+/// The modules with an `endpoints` directory that no group mounts.
+pub fn unmounted(groups: &[RouteGroup]) -> Vec<&'static str> {
+    ENDPOINT_MODULES
+        .iter()
+        .copied()
+        .filter(|m| !groups.iter().any(|g| g.feature() == *m))
+        .filter(|m| !OPTIONAL.contains(&(*m, false)))
+        .collect()
+}
+
+/// Every route the groups mount as `METHOD /path`, in the order they're mounted.
+pub fn dump(groups: &[RouteGroup]) -> Vec<String> {
+    let mut lines = vec![];
+    for internal in [true, false] {
+        for version in ApiVersion::ALL {
+            for scope in scopes(groups, internal) {
+                for group in members(groups, scope, internal, version) {
+                    let routes = ENDPOINT_ROUTES.iter().filter(|r| {
+                        r.module == group.feature() && r.version == version.to_string()
+                    });
+                    for route in routes {
+                        lines.push(format!(
+                            "{} /{}{}",
+                            route.method,
+                            group.path(version),
+                            route.path
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+#[test]
+fn it_mounts_every_endpoints_module() {
+    let groups = crate::server::registry();
+    assert_eq!(unmounted(&groups), Vec::<&str>::new());
+
+    let forgotten = groups
+        .iter()
+        .copied()
+        .filter(|g| g.feature() != "bundle")
+        .collect::<Vec<_>>();
+    assert_eq!(unmounted(&forgotten), ["bundle"]);
+
+    let mut features = groups.iter().map(|g| g.feature()).collect::<Vec<_>>();
+    features.sort_unstable();
+    features.dedup();
+    assert_eq!(features.len(), groups.len(), "a module is registered twice");
+}
+
+#[test]
+fn it_mounts_the_same_routes_as_before_the_registry() {
+    let groups = crate::server::registry()
+        .into_iter()
+        .filter(|g| !OPTIONAL.iter().any(|(m, _)| g.feature() == *m))
+        .collect::<Vec<_>>();
+    let golden = include_str!("goldens/routes.txt");
+    assert_eq!(dump(&groups), golden.lines().collect::<Vec<_>>());
+}
+
+#[actix_web::test]
+async fn it_serves_every_dumped_route() {
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::web::Data;
+    use actix_web::{test, App, HttpResponse};
+
+    use crate::standby::middleware::InternalToken;
+
+    // Unknown routes fall through to a status no handler answers with.
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(InternalToken::new("secret")))
+            .default_service(web::to(HttpResponse::ImATeapot))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let unknown = test::TestRequest::get()
+        .uri("/api/v1/clusters/1/unknown/route")
+        .to_request();
+    let res = test::call_service(&app, unknown).await;
+    assert_eq!(res.status(), StatusCode::IM_A_TEAPOT);
+
+    for line in dump(&crate::server::registry()) {
+        let (method, path) = line.split_once(' ').unwrap();
+        let uri = regex::Regex::new(r"\{[^}]+\}")
+            .unwrap()
+            .replace_all(path, "1")
+            .to_string();
+        let req = test::TestRequest::default()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri(&uri)
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_ne!(
+            res.status(),
+            StatusCode::IM_A_TEAPOT,
+            "{} isn't mounted",
+            line
+        );
+    }
+}
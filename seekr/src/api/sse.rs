@@ -0,0 +1,24 @@
+use std::convert::Infallible;
+
+use bytes::Bytes;
+use serde::Serialize;
+
+/// Format a server-sent event, unnamed ones being plain `message` events.
+pub fn event(name: Option<&str>, data: &impl Serialize) -> Result<Bytes, Infallible> {
+    let data = serde_json::to_string(data).unwrap_or_default();
+    Ok(Bytes::from(match name {
+        Some(name) => format!("event: {}\ndata: {}\n\n", name, data),
+        None => format!("data: {}\n\n", data),
+    }))
+}
+
+#[test]
+fn it_formats_named_and_unnamed_events() {
+    let data = serde_json::json!({ "skipped": 3 });
+
+    assert_eq!(
+        event(Some("lagged"), &data).unwrap(),
+        "event: lagged\ndata: {\"skipped\":3}\n\n"
+    );
+    assert_eq!(event(None, &data).unwrap(), "data: {\"skipped\":3}\n\n");
+}
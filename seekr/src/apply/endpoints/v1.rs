@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Json, ServiceConfig};
+use actix_web::{post, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::apply::{Applier, Operation, ValidationError};
+use crate::auth::Principal;
+use crate::clusters::store::ClusterStore;
+use crate::commands::store::CommandStore;
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::subscriptions::store::SubscriptionStore;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(apply);
+}
+
+#[post("")]
+async fn apply(
+    r: Json<Vec<Operation>>,
+    principal: Principal,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> impl Responder {
+    info!("Applying a batch of {} operations", r.len());
+
+    let applier = Applier::new(
+        cs.get_ref().clone(),
+        ss.get_ref().clone(),
+        qs.get_ref().clone(),
+        manager.into_inner(),
+    );
+
+    match applier.apply(&principal, &r).await {
+        Ok(report) if report.applied => HttpResponse::Ok().json(report),
+        // Nothing of the batch is left in place, it can be retried as is.
+        Ok(report) if report.rolled_back => HttpResponse::Conflict().json(report),
+        Ok(report) => HttpResponse::InternalServerError().json(report),
+        Err(errors) => HttpResponse::BadRequest().json(ValidationErrors { errors }),
+    }
+}
+
+#[derive(Serialize)]
+struct ValidationErrors {
+    errors: Vec<ValidationError>,
+}
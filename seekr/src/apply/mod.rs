@@ -0,0 +1,1006 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::Principal;
+use crate::clusters::cluster::{Cluster, Kind};
+use crate::clusters::service as clusters;
+use crate::clusters::store::ClusterStore;
+use crate::commands::command::CommandKind;
+use crate::commands::enqueue;
+use crate::commands::store::CommandStore;
+use crate::errors::AnyError;
+use crate::governance::owner::Owner;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::auth::AuthProvider;
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::request_id::RequestId;
+use crate::subscriptions::deletion::DEFAULT_GRACE_PERIOD;
+use crate::subscriptions::service::{self as subscriptions, Deletion, SubscriptionError};
+use crate::subscriptions::store::SubscriptionStore;
+use crate::subscriptions::subscription::Subscription;
+
+pub mod endpoints;
+
+/// The most operations a single batch may carry.
+pub const MAX_OPERATIONS: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    Cluster,
+    Subscription,
+}
+
+/// One operation of a batch, its body being what the entity's own endpoint takes.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Operation {
+    pub op: Op,
+    pub entity: Entity,
+
+    /// Names the cluster created, for subscriptions created in the same batch
+    /// to refer to as `"cluster_id": {"ref": name}`.
+    #[serde(default, rename = "ref")]
+    pub reference: Option<String>,
+
+    #[serde(default)]
+    pub body: Value,
+
+    /// The `updated_at` the entity must still have to be updated or deleted.
+    #[serde(default)]
+    pub if_match: Option<DateTime<Utc>>,
+}
+
+/// A cluster's id, or the ref of a cluster created in the same batch.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ClusterTarget {
+    Id(ClusterId),
+    Ref {
+        #[serde(rename = "ref")]
+        name: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct ClusterBody {
+    #[serde(default)]
+    id: Option<ClusterId>,
+    kind: Kind,
+    name: String,
+    #[serde(default)]
+    config: HashMap<String, String>,
+    #[serde(default)]
+    owner: Option<Owner>,
+}
+
+#[derive(Deserialize)]
+struct ClusterKey {
+    id: ClusterId,
+}
+
+#[derive(Deserialize)]
+struct SubscriptionBody {
+    #[serde(default)]
+    id: Option<SubscriptionId>,
+    cluster_id: ClusterTarget,
+    topic_name: String,
+    #[serde(default)]
+    config: HashMap<String, String>,
+    #[serde(default)]
+    owner: Option<Owner>,
+}
+
+#[derive(Deserialize)]
+struct SubscriptionKey {
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+}
+
+/// An operation rejected before anything was written.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ValidationError {
+    pub index: usize,
+    pub message: String,
+}
+
+/// A validated operation, along with what it replaces.
+enum Step {
+    CreateCluster {
+        reference: Option<String>,
+        kind: Kind,
+        name: String,
+        config: HashMap<String, String>,
+        owner: Option<Owner>,
+    },
+    UpdateCluster {
+        prior: Cluster,
+        kind: Kind,
+        name: String,
+        config: HashMap<String, String>,
+        owner: Option<Owner>,
+    },
+    DeleteCluster {
+        prior: Cluster,
+    },
+    CreateSubscription {
+        cluster: ClusterTarget,
+        topic_name: String,
+        config: HashMap<String, String>,
+        owner: Option<Owner>,
+    },
+    UpdateSubscription {
+        prior: Subscription,
+        topic_name: String,
+        config: HashMap<String, String>,
+        owner: Option<Owner>,
+    },
+    DeleteSubscription {
+        prior: Subscription,
+    },
+}
+
+impl Step {
+    /// Clusters are written before their subscriptions, and deletes last.
+    fn rank(&self) -> u8 {
+        match self {
+            Step::CreateCluster { .. } => 0,
+            Step::UpdateCluster { .. } => 1,
+            Step::CreateSubscription { .. } => 2,
+            Step::UpdateSubscription { .. } => 3,
+            Step::DeleteSubscription { .. } => 4,
+            Step::DeleteCluster { .. } => 5,
+        }
+    }
+}
+
+/// How to take back a write of the batch.
+enum Undo {
+    RemoveCluster(ClusterId),
+    RevertCluster(Cluster),
+    ReinstateCluster(Cluster),
+    RemoveSubscription(ClusterId, SubscriptionId),
+    RestoreSubscription {
+        prior: Subscription,
+        /// The delete paused the subscription's worker.
+        resume: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Applied,
+    Failed,
+    /// Not attempted, as an earlier write failed.
+    Skipped,
+    RolledBack,
+    /// Applied, and still in place as taking it back failed too.
+    RollbackFailed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OperationOutcome {
+    pub index: usize,
+    pub op: Op,
+    pub entity: Entity,
+    pub status: Status,
+
+    /// The id of the entity written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What applying a batch did, operations in the order of the request.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ApplyReport {
+    /// Every operation was applied.
+    pub applied: bool,
+
+    /// A write failed and every write before it was taken back.
+    pub rolled_back: bool,
+
+    pub operations: Vec<OperationOutcome>,
+}
+
+/// Applies batches of cluster and subscription operations with best-effort
+/// atomicity.
+///
+/// Every operation is validated before anything is written, and a write
+/// failing midway takes back the writes before it: created entities are
+/// removed, and updated or deleted ones restored as they were before the
+/// batch. Other writers may still see the batch half applied meanwhile.
+pub struct Applier {
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    commands: Arc<dyn CommandStore + Send + Sync>,
+    manager: Arc<MetadataManager>,
+}
+
+impl Applier {
+    pub fn new(
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+        commands: Arc<dyn CommandStore + Send + Sync>,
+        manager: Arc<MetadataManager>,
+    ) -> Self {
+        Self {
+            clusters,
+            subscriptions,
+            commands,
+            manager,
+        }
+    }
+
+    /// Validate every operation, then apply them all or report every error.
+    pub async fn apply(
+        &self,
+        principal: &Principal,
+        operations: &[Operation],
+    ) -> Result<ApplyReport, Vec<ValidationError>> {
+        let steps = self.validate(principal, operations).await?;
+        Ok(self.execute(operations, steps).await)
+    }
+
+    async fn validate(
+        &self,
+        principal: &Principal,
+        operations: &[Operation],
+    ) -> Result<Vec<(usize, Step)>, Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        if operations.is_empty() || operations.len() > MAX_OPERATIONS {
+            errors.push(ValidationError {
+                index: 0,
+                message: format!("A batch carries 1 to {} operations", MAX_OPERATIONS),
+            });
+            return Err(errors);
+        }
+
+        // Refs are resolved whatever the order, as clusters are created first.
+        let mut references = HashSet::new();
+        for (index, o) in operations.iter().enumerate() {
+            let Some(name) = &o.reference else {
+                continue;
+            };
+            let message = match (o.op, o.entity) {
+                (Op::Create, Entity::Cluster) if references.insert(name.as_str()) => continue,
+                (Op::Create, Entity::Cluster) => format!("ref '{}' is taken", name),
+                _ => "Only created clusters can be given a ref".to_string(),
+            };
+            errors.push(ValidationError { index, message });
+        }
+
+        let deleted = operations
+            .iter()
+            .filter(|o| o.op == Op::Delete && o.entity == Entity::Cluster)
+            .filter_map(|o| parse::<ClusterKey>(&o.body).ok())
+            .map(|k| k.id)
+            .collect::<HashSet<_>>();
+
+        let mut targets = HashSet::new();
+        let mut steps = Vec::new();
+        for (index, o) in operations.iter().enumerate() {
+            let step = self
+                .step(principal, o, &references, &deleted)
+                .await
+                .and_then(|step| {
+                    let target = match &step {
+                        Step::UpdateCluster { prior, .. } | Step::DeleteCluster { prior } => {
+                            Some((Entity::Cluster, prior.id.as_i64()))
+                        }
+                        Step::UpdateSubscription { prior, .. }
+                        | Step::DeleteSubscription { prior } => {
+                            Some((Entity::Subscription, prior.id.as_i64()))
+                        }
+                        _ => None,
+                    };
+                    match target.is_some_and(|t| !targets.insert(t)) {
+                        true => Err("The entity is written by an earlier operation".to_string()),
+                        false => Ok(step),
+                    }
+                });
+            match step {
+                Ok(step) => steps.push((index, step)),
+                Err(message) => errors.push(ValidationError { index, message }),
+            }
+        }
+
+        if !errors.is_empty() {
+            errors.sort_by_key(|e| e.index);
+            return Err(errors);
+        }
+
+        // Stable, so operations of the same rank keep the order of the request.
+        steps.sort_by_key(|(_, step)| step.rank());
+        Ok(steps)
+    }
+
+    async fn step(
+        &self,
+        principal: &Principal,
+        o: &Operation,
+        references: &HashSet<&str>,
+        deleted: &HashSet<ClusterId>,
+    ) -> Result<Step, String> {
+        let access = |id: ClusterId| match principal.can_access(id) {
+            true => Ok(()),
+            false => Err(format!("Cluster with id '{}' not found", id)),
+        };
+
+        match (o.op, o.entity) {
+            (Op::Create, Entity::Cluster) => {
+                // Keys scoped to clusters would lose access to the clusters they create.
+                if principal.is_scoped() {
+                    return Err("Keys scoped to clusters can't create clusters".to_string());
+                }
+                let b = parse::<ClusterBody>(&o.body)?;
+                validate_cluster(&b)?;
+                Ok(Step::CreateCluster {
+                    reference: o.reference.clone(),
+                    kind: b.kind,
+                    name: b.name,
+                    config: b.config,
+                    owner: b.owner,
+                })
+            }
+            (Op::Update, Entity::Cluster) => {
+                let b = parse::<ClusterBody>(&o.body)?;
+                let id = b.id.ok_or("Updates need the cluster's id")?;
+                access(id)?;
+                validate_cluster(&b)?;
+                let prior = self.cluster(id, o.if_match).await?;
+                Ok(Step::UpdateCluster {
+                    prior,
+                    kind: b.kind,
+                    name: b.name,
+                    config: b.config,
+                    owner: b.owner,
+                })
+            }
+            (Op::Delete, Entity::Cluster) => {
+                let k = parse::<ClusterKey>(&o.body)?;
+                access(k.id)?;
+                let prior = self.cluster(k.id, o.if_match).await?;
+                Ok(Step::DeleteCluster { prior })
+            }
+            (Op::Create, Entity::Subscription) => {
+                let b = parse::<SubscriptionBody>(&o.body)?;
+                if let Some(Err(e)) = b.owner.as_ref().map(Owner::validate) {
+                    return Err(e);
+                }
+                match &b.cluster_id {
+                    ClusterTarget::Id(id) => {
+                        access(*id)?;
+                        if deleted.contains(id) {
+                            return Err(format!(
+                                "Cluster with id '{}' is deleted by the batch",
+                                id
+                            ));
+                        }
+                        self.cluster(*id, None).await?;
+                    }
+                    ClusterTarget::Ref { name } if !references.contains(name.as_str()) => {
+                        return Err(format!("No cluster of the batch has the ref '{}'", name));
+                    }
+                    ClusterTarget::Ref { .. } => {}
+                }
+                Ok(Step::CreateSubscription {
+                    cluster: b.cluster_id,
+                    topic_name: b.topic_name,
+                    config: b.config,
+                    owner: b.owner,
+                })
+            }
+            (Op::Update, Entity::Subscription) => {
+                let b = parse::<SubscriptionBody>(&o.body)?;
+                let id = b.id.ok_or("Updates need the subscription's id")?;
+                let ClusterTarget::Id(cluster_id) = b.cluster_id else {
+                    return Err("Refs only name the cluster of created subscriptions".to_string());
+                };
+                access(cluster_id)?;
+                if let Some(Err(e)) = b.owner.as_ref().map(Owner::validate) {
+                    return Err(e);
+                }
+                let prior = self.subscription(cluster_id, id, o.if_match).await?;
+                Ok(Step::UpdateSubscription {
+                    prior,
+                    topic_name: b.topic_name,
+                    config: b.config,
+                    owner: b.owner,
+                })
+            }
+            (Op::Delete, Entity::Subscription) => {
+                let k = parse::<SubscriptionKey>(&o.body)?;
+                access(k.cluster_id)?;
+                let prior = self.subscription(k.cluster_id, k.id, o.if_match).await?;
+                Ok(Step::DeleteSubscription { prior })
+            }
+        }
+    }
+
+    async fn cluster(
+        &self,
+        id: ClusterId,
+        if_match: Option<DateTime<Utc>>,
+    ) -> Result<Cluster, String> {
+        match self.clusters.get(id).await {
+            Ok(Some(c)) => precondition(c.updated_at, if_match).map(|_| c),
+            Ok(None) => Err(format!("Cluster with id '{}' not found", id)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn subscription(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+        if_match: Option<DateTime<Utc>>,
+    ) -> Result<Subscription, String> {
+        match self.subscriptions.get(cluster_id, id).await {
+            Ok(Some(s)) => precondition(s.updated_at, if_match).map(|_| s),
+            Ok(None) => Err(format!("Subscription with id '{}' not found", id)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn execute(&self, operations: &[Operation], steps: Vec<(usize, Step)>) -> ApplyReport {
+        let mut outcomes = operations
+            .iter()
+            .enumerate()
+            .map(|(index, o)| OperationOutcome {
+                index,
+                op: o.op,
+                entity: o.entity,
+                status: Status::Skipped,
+                id: None,
+                reference: o.reference.clone(),
+                error: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut refs = HashMap::new();
+        let mut undos = Vec::new();
+        let mut failed = false;
+        for (index, step) in steps {
+            match self.write(step, &mut refs).await {
+                Ok((id, undo)) => {
+                    outcomes[index].status = Status::Applied;
+                    outcomes[index].id = Some(id);
+                    undos.push((index, undo));
+                }
+                Err(e) => {
+                    warn!(
+                        "Operation {} of a batch failed, rolling back - {}",
+                        index, e
+                    );
+                    outcomes[index].status = Status::Failed;
+                    outcomes[index].error = Some(e.to_string());
+                    failed = true;
+                    break;
+                }
+            }
+        }
+
+        let mut rolled_back = failed;
+        if failed {
+            for (index, undo) in undos.into_iter().rev() {
+                match self.undo(undo).await {
+                    Ok(()) => outcomes[index].status = Status::RolledBack,
+                    Err(e) => {
+                        error!("Failed to roll back operation {} of a batch - {}", index, e);
+                        outcomes[index].status = Status::RollbackFailed;
+                        outcomes[index].error = Some(e.to_string());
+                        rolled_back = false;
+                    }
+                }
+            }
+        }
+
+        ApplyReport {
+            applied: !failed,
+            rolled_back,
+            operations: outcomes,
+        }
+    }
+
+    async fn write(
+        &self,
+        step: Step,
+        refs: &mut HashMap<String, ClusterId>,
+    ) -> Result<(i64, Undo), AnyError> {
+        let cs = self.clusters.as_ref();
+        let ss = self.subscriptions.as_ref();
+        let manager = self.manager.clone();
+
+        match step {
+            Step::CreateCluster {
+                reference,
+                kind,
+                name,
+                config,
+                owner,
+            } => {
+                let id = clusters::create(cs, manager, kind, name, config, owner).await?;
+                if let Some(name) = reference {
+                    refs.insert(name, id);
+                }
+                Ok((id.as_i64(), Undo::RemoveCluster(id)))
+            }
+            Step::UpdateCluster {
+                prior,
+                kind,
+                name,
+                config,
+                owner,
+            } => {
+                let id = clusters::update(cs, manager, prior.id, kind, name, config, owner)
+                    .await?
+                    .ok_or_else(|| format!("Cluster with id '{}' not found", prior.id))?;
+                Ok((id.as_i64(), Undo::RevertCluster(prior)))
+            }
+            Step::DeleteCluster { prior } => {
+                clusters::delete(cs, manager, prior.id).await?;
+                Ok((prior.id.as_i64(), Undo::ReinstateCluster(prior)))
+            }
+            Step::CreateSubscription {
+                cluster,
+                topic_name,
+                config,
+                owner,
+            } => {
+                let cluster_id = match cluster {
+                    ClusterTarget::Id(id) => id,
+                    // Validation checked the ref, and its cluster was created first.
+                    ClusterTarget::Ref { name } => refs[&name],
+                };
+                let id = subscriptions::create(cs, ss, cluster_id, topic_name, config, owner)
+                    .await
+                    .map_err(describe)?;
+                Ok((id.as_i64(), Undo::RemoveSubscription(cluster_id, id)))
+            }
+            Step::UpdateSubscription {
+                prior,
+                topic_name,
+                config,
+                owner,
+            } => {
+                let update = subscriptions::update(
+                    cs,
+                    ss,
+                    prior.cluster_id,
+                    prior.id,
+                    topic_name,
+                    config,
+                    owner,
+                );
+                let id = update.await.map_err(describe)?;
+                let undo = Undo::RestoreSubscription {
+                    prior,
+                    resume: false,
+                };
+                Ok((id.as_i64(), undo))
+            }
+            Step::DeleteSubscription { prior } => {
+                let delete = subscriptions::delete(
+                    ss,
+                    &self.commands,
+                    prior.cluster_id,
+                    prior.id,
+                    DEFAULT_GRACE_PERIOD,
+                    false,
+                );
+                match delete.await.map_err(describe)? {
+                    Deletion::Pending(pending) => {
+                        let id = prior.id.as_i64();
+                        let resume = !pending.was_paused && !prior.is_pending_deletion();
+                        Ok((id, Undo::RestoreSubscription { prior, resume }))
+                    }
+                    Deletion::NotFound => {
+                        Err(format!("Subscription with id '{}' not found", prior.id).into())
+                    }
+                }
+            }
+        }
+    }
+
+    async fn undo(&self, undo: Undo) -> Result<(), AnyError> {
+        match undo {
+            Undo::RemoveCluster(id) => {
+                clusters::delete(self.clusters.as_ref(), self.manager.clone(), id).await
+            }
+            Undo::RevertCluster(prior) => {
+                self.clusters.update(prior.clone()).await?;
+                self.manager.clone().reregister(prior).await
+            }
+            Undo::ReinstateCluster(prior) => {
+                self.clusters.put(prior.clone()).await?;
+                self.manager.register(prior, RequestId::current()).await;
+                Ok(())
+            }
+            Undo::RemoveSubscription(cluster_id, id) => {
+                self.subscriptions.remove(cluster_id, id).await.map(drop)
+            }
+            Undo::RestoreSubscription { prior, resume } => {
+                let id = prior.id;
+                self.subscriptions.update(prior).await?;
+                if resume {
+                    enqueue(self.commands.as_ref(), id, CommandKind::Resume, Value::Null).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &Value) -> Result<T, String> {
+    serde_json::from_value(body.clone()).map_err(|e| format!("Invalid body: {}", e))
+}
+
+fn validate_cluster(b: &ClusterBody) -> Result<(), String> {
+    b.kind.validate()?;
+    if let Some(Err(e)) = b.owner.as_ref().map(Owner::validate) {
+        return Err(e);
+    }
+    AuthProvider::of(&b.config).map(drop)
+}
+
+fn precondition(updated_at: DateTime<Utc>, if_match: Option<DateTime<Utc>>) -> Result<(), String> {
+    match if_match {
+        Some(expected) if expected != updated_at => Err(format!(
+            "Precondition failed, the entity was updated at {}",
+            updated_at.to_rfc3339()
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn describe(e: SubscriptionError) -> AnyError {
+    match e {
+        SubscriptionError::ClusterNotFound(id) => {
+            format!("Cluster with id '{}' not found", id).into()
+        }
+        SubscriptionError::Store(e) => e,
+    }
+}
+
+/// Subscriptions failing to insert on a topic, and clusters failing to be
+/// removed, to fail batches midway and their rollback.
+#[cfg(test)]
+struct Flaky<S> {
+    inner: S,
+    failing: &'static str,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl SubscriptionStore for Flaky<crate::subscriptions::store::MemorySubscriptionStore> {
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: crate::page::Page,
+    ) -> Result<Vec<Subscription>, AnyError> {
+        self.inner.list(cluster_id, page).await
+    }
+
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError> {
+        self.inner.count(cluster_id).await
+    }
+
+    async fn search(
+        &self,
+        q: &str,
+        limit: usize,
+    ) -> Result<Vec<crate::search::Scored<Subscription>>, AnyError> {
+        self.inner.search(q, limit).await
+    }
+
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<Option<Subscription>, AnyError> {
+        self.inner.get(cluster_id, id).await
+    }
+
+    async fn insert(&self, s: Subscription) -> Result<SubscriptionId, AnyError> {
+        match s.topic_name == self.failing {
+            true => Err("the store is unavailable".into()),
+            false => self.inner.insert(s).await,
+        }
+    }
+
+    async fn update(&self, s: Subscription) -> Result<SubscriptionId, AnyError> {
+        self.inner.update(s).await
+    }
+
+    async fn put(&self, s: Subscription) -> Result<SubscriptionId, AnyError> {
+        self.inner.put(s).await
+    }
+
+    async fn remove(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<SubscriptionId, AnyError> {
+        self.inner.remove(cluster_id, id).await
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl ClusterStore for Flaky<crate::clusters::store::MemoryClusterStore> {
+    async fn list(
+        &self,
+        ids: Option<Vec<ClusterId>>,
+        page: crate::page::Page,
+    ) -> Result<Vec<Cluster>, AnyError> {
+        self.inner.list(ids, page).await
+    }
+
+    async fn count(&self, ids: Option<Vec<ClusterId>>) -> Result<usize, AnyError> {
+        self.inner.count(ids).await
+    }
+
+    async fn search(
+        &self,
+        q: &str,
+        limit: usize,
+    ) -> Result<Vec<crate::search::Scored<Cluster>>, AnyError> {
+        self.inner.search(q, limit).await
+    }
+
+    async fn get(&self, id: ClusterId) -> Result<Option<Cluster>, AnyError> {
+        self.inner.get(id).await
+    }
+
+    async fn get_by_name(&self, name: &str) -> Result<Option<Cluster>, AnyError> {
+        self.inner.get_by_name(name).await
+    }
+
+    async fn insert(&self, c: Cluster) -> Result<ClusterId, AnyError> {
+        self.inner.insert(c).await
+    }
+
+    async fn update(&self, c: Cluster) -> Result<ClusterId, AnyError> {
+        self.inner.update(c).await
+    }
+
+    async fn put(&self, c: Cluster) -> Result<ClusterId, AnyError> {
+        self.inner.put(c).await
+    }
+
+    async fn remove(&self, id: ClusterId) -> Result<ClusterId, AnyError> {
+        match self
+            .inner
+            .get(id)
+            .await?
+            .is_some_and(|c| c.name == self.failing)
+        {
+            true => Err("the store is unavailable".into()),
+            false => self.inner.remove(id).await,
+        }
+    }
+
+    async fn ping(&self) -> Result<(), AnyError> {
+        self.inner.ping().await
+    }
+}
+
+/// An applier over a cluster "payments" with subscription "orders", failing
+/// to insert subscriptions to `boom` and to remove clusters named `stuck`.
+#[cfg(test)]
+async fn applier() -> (
+    Applier,
+    Arc<dyn ClusterStore + Send + Sync>,
+    Arc<dyn SubscriptionStore + Send + Sync>,
+) {
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::commands::store::MemoryCommandStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(Flaky {
+        inner: MemoryClusterStore::default(),
+        failing: "stuck",
+    });
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(Flaky {
+        inner: MemorySubscriptionStore::default(),
+        failing: "boom",
+    });
+    let cluster = Cluster::new(None, Kind::Kafka, "payments".to_string(), HashMap::new());
+    let cluster_id = cs.insert(cluster).await.unwrap();
+    let subscription = Subscription::new(None, cluster_id, "orders".to_string(), HashMap::new());
+    ss.insert(subscription).await.unwrap();
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Arc::new(MetadataManager::with_factory(cs.clone(), factory));
+    let applier = Applier::new(
+        cs.clone(),
+        ss.clone(),
+        Arc::new(MemoryCommandStore::default()),
+        manager,
+    );
+    (applier, cs, ss)
+}
+
+#[cfg(test)]
+fn operations(value: Value) -> Vec<Operation> {
+    serde_json::from_value(value).unwrap()
+}
+
+#[tokio::test]
+async fn it_validates_every_operation_before_writing() {
+    use serde_json::json;
+
+    let (applier, cs, ss) = applier().await;
+    let stale = Utc::now() - chrono::Duration::days(1);
+    let ops = operations(json!([
+        { "op": "create", "entity": "cluster", "ref": "search", "body": { "kind": "Kafka", "name": "search" } },
+        { "op": "update", "entity": "cluster", "body": { "id": 1, "kind": "Kafka", "name": "p", "owner": { "email": "x" } } },
+        { "op": "create", "entity": "subscription", "body": { "cluster_id": { "ref": "missing" }, "topic_name": "a" } },
+        { "op": "delete", "entity": "subscription", "if_match": stale, "body": { "cluster_id": 1, "id": 1 } },
+        { "op": "delete", "entity": "cluster", "body": { "id": 9 } },
+        { "op": "create", "entity": "subscription", "body": { "topic_name": "a" } },
+    ]));
+
+    let errors = applier.apply(&Principal::root(), &ops).await.unwrap_err();
+    let indices = errors.iter().map(|e| e.index).collect::<Vec<_>>();
+    assert_eq!(indices, vec![1, 2, 3, 4, 5], "{:?}", errors);
+    assert!(errors[1].message.contains("'missing'"));
+    assert!(errors[2].message.starts_with("Precondition failed"));
+    assert!(errors[3].message.contains("not found"));
+
+    // Nothing was written, not even the valid create.
+    assert_eq!(
+        cs.list(None, crate::page::Page::ALL).await.unwrap().len(),
+        1
+    );
+    assert_eq!(
+        cs.get(ClusterId(1)).await.unwrap().unwrap().name,
+        "payments"
+    );
+    let orders = ss
+        .get(ClusterId(1), SubscriptionId(1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!orders.is_pending_deletion());
+
+    let too_many = vec![ops[0].clone(); MAX_OPERATIONS + 1];
+    assert!(applier.apply(&Principal::root(), &too_many).await.is_err());
+}
+
+#[tokio::test]
+async fn it_resolves_refs_to_clusters_created_in_the_batch() {
+    use serde_json::json;
+
+    let (applier, cs, ss) = applier().await;
+    let updated_at = cs.get(ClusterId(1)).await.unwrap().unwrap().updated_at;
+
+    // Subscriptions listed first still land after the cluster they refer to.
+    let ops = operations(json!([
+        { "op": "create", "entity": "subscription", "body": { "cluster_id": { "ref": "search" }, "topic_name": "queries" } },
+        { "op": "delete", "entity": "subscription", "body": { "cluster_id": 1, "id": 1 } },
+        { "op": "update", "entity": "cluster", "if_match": updated_at, "body": { "id": 1, "kind": "Kafka", "name": "billing" } },
+        { "op": "create", "entity": "cluster", "ref": "search", "body": { "kind": "Kafka", "name": "search" } },
+    ]));
+
+    let report = applier.apply(&Principal::root(), &ops).await.unwrap();
+    assert!(report.applied && !report.rolled_back);
+    assert!(report
+        .operations
+        .iter()
+        .all(|o| o.status == Status::Applied));
+    assert_eq!(report.operations[3].id, Some(2));
+    assert_eq!(report.operations[3].reference.as_deref(), Some("search"));
+
+    let queries = ss
+        .list(Some(ClusterId(2)), crate::page::Page::ALL)
+        .await
+        .unwrap();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0].topic_name, "queries");
+    assert_eq!(Some(queries[0].id.as_i64()), report.operations[0].id);
+    assert_eq!(cs.get(ClusterId(1)).await.unwrap().unwrap().name, "billing");
+    let orders = ss
+        .get(ClusterId(1), SubscriptionId(1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(orders.is_pending_deletion());
+}
+
+#[tokio::test]
+async fn it_rolls_back_the_batch_when_a_write_fails() {
+    use serde_json::json;
+
+    let (applier, cs, ss) = applier().await;
+    let before = cs.get(ClusterId(1)).await.unwrap().unwrap();
+    let orders = ss
+        .get(ClusterId(1), SubscriptionId(1))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let ops = operations(json!([
+        { "op": "update", "entity": "cluster", "body": { "id": 1, "kind": "Kafka", "name": "billing" } },
+        { "op": "create", "entity": "cluster", "ref": "search", "body": { "kind": "Kafka", "name": "search" } },
+        { "op": "create", "entity": "subscription", "body": { "cluster_id": { "ref": "search" }, "topic_name": "queries" } },
+        { "op": "create", "entity": "subscription", "body": { "cluster_id": 1, "topic_name": "boom" } },
+        { "op": "delete", "entity": "subscription", "body": { "cluster_id": 1, "id": 1 } },
+    ]));
+
+    let report = applier.apply(&Principal::root(), &ops).await.unwrap();
+    assert!(!report.applied);
+    assert!(report.rolled_back);
+    let statuses = report
+        .operations
+        .iter()
+        .map(|o| o.status)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec![
+            Status::RolledBack,
+            Status::RolledBack,
+            Status::RolledBack,
+            Status::Failed,
+            Status::Skipped
+        ]
+    );
+    assert_eq!(
+        report.operations[3].error.as_deref(),
+        Some("the store is unavailable")
+    );
+
+    // Everything is as it was before the batch.
+    assert_eq!(
+        cs.list(None, crate::page::Page::ALL).await.unwrap(),
+        vec![before]
+    );
+    assert_eq!(
+        ss.list(None, crate::page::Page::ALL).await.unwrap(),
+        vec![orders]
+    );
+}
+
+#[tokio::test]
+async fn it_reports_writes_it_failed_to_roll_back() {
+    use serde_json::json;
+
+    let (applier, cs, _) = applier().await;
+    let ops = operations(json!([
+        { "op": "create", "entity": "cluster", "body": { "kind": "Kafka", "name": "stuck" } },
+        { "op": "create", "entity": "subscription", "body": { "cluster_id": 1, "topic_name": "boom" } },
+    ]));
+
+    let report = applier.apply(&Principal::root(), &ops).await.unwrap();
+    assert!(!report.applied);
+    assert!(!report.rolled_back);
+    assert_eq!(report.operations[0].status, Status::RollbackFailed);
+    assert_eq!(report.operations[0].id, Some(2));
+    assert_eq!(
+        report.operations[0].error.as_deref(),
+        Some("the store is unavailable")
+    );
+    assert_eq!(report.operations[1].status, Status::Failed);
+    assert!(cs.get(ClusterId(2)).await.unwrap().is_some());
+}
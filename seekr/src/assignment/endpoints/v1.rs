@@ -0,0 +1,408 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Json, ServiceConfig};
+use actix_web::{get, post, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::assignment::Assignments;
+use crate::auth::Principal;
+use crate::commands;
+use crate::commands::store::CommandStore;
+use crate::errors::AnyError;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::page::Page;
+use crate::standby::lease::LeaseStore;
+use crate::subscriptions::store::SubscriptionStore;
+use crate::subscriptions::subscription::Subscription;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_assignments).service(simulate_assignments);
+}
+
+#[get("/assignments")]
+async fn get_assignments(
+    principal: Principal,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+    leases: Data<Arc<dyn LeaseStore + Send + Sync>>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let (subs, assignments) = match load(&ss, &leases).await {
+        Ok(loaded) => loaded,
+        Err(e) => return e,
+    };
+    let ids = subs.iter().map(|s| s.id).collect::<Vec<_>>();
+    let assigned = assignments.assign(&ids, &[]);
+
+    let mut subscriptions = Vec::with_capacity(subs.len());
+    for sub in subs {
+        let owner = assignments.owner(sub.id).map(|l| l.holder.clone());
+        let state = match owner {
+            None => AssignmentState::Orphaned,
+            Some(_) => match commands::is_paused(qs.get_ref().clone(), sub.id).await {
+                Ok(true) => AssignmentState::Paused,
+                Ok(false) => AssignmentState::Running,
+                Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+            },
+        };
+
+        subscriptions.push(SubscriptionAssignment {
+            cluster_id: sub.cluster_id,
+            subscription_id: sub.id,
+            topic_name: sub.topic_name,
+            owner,
+            lease_expires_at: assignments.owners.get(&sub.id).map(|l| l.expires_at),
+            state,
+            assigned_to: assigned.get(&sub.id).cloned().flatten(),
+            preferred_instance: assignments.preferred.get(&sub.id).cloned(),
+        });
+    }
+
+    HttpResponse::Ok().json(AssignmentsResponse {
+        instances: assignments.instances.into_iter().collect(),
+        orphaned: subscriptions
+            .iter()
+            .filter(|s| s.state == AssignmentState::Orphaned)
+            .count(),
+        subscriptions,
+    })
+}
+
+#[post("/assignments/simulate")]
+async fn simulate_assignments(
+    principal: Principal,
+    r: Json<SimulateRequest>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    leases: Data<Arc<dyn LeaseStore + Send + Sync>>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let (subs, assignments) = match load(&ss, &leases).await {
+        Ok(loaded) => loaded,
+        Err(e) => return e,
+    };
+    if let Some(unknown) = r
+        .remove_instances
+        .iter()
+        .find(|i| !assignments.instances.contains(*i))
+    {
+        return HttpResponse::BadRequest().body(format!("No live indexer instance '{}'", unknown));
+    }
+
+    // Nothing is written, the prediction is what the schedulers would work out.
+    let ids = subs.iter().map(|s| s.id).collect::<Vec<_>>();
+    let predicted = assignments.assign(&ids, &r.remove_instances);
+    let reassignments = subs
+        .iter()
+        .filter_map(|sub| {
+            let from = assignments.owner(sub.id).map(|l| l.holder.clone());
+            let to = predicted.get(&sub.id).cloned().flatten();
+            (from != to).then_some(Reassignment {
+                cluster_id: sub.cluster_id,
+                subscription_id: sub.id,
+                from,
+                to,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    HttpResponse::Ok().json(SimulateResponse {
+        instances: assignments
+            .instances
+            .iter()
+            .filter(|i| !r.remove_instances.contains(i))
+            .cloned()
+            .collect(),
+        unassigned: predicted.values().filter(|i| i.is_none()).count(),
+        reassignments,
+    })
+}
+
+/// The subscriptions the schedulers run, i.e. those not pending deletion,
+/// and the leases they're shared by.
+async fn load(
+    ss: &Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    leases: &Data<Arc<dyn LeaseStore + Send + Sync>>,
+) -> Result<(Vec<Subscription>, Assignments), HttpResponse> {
+    let internal = |e: AnyError| HttpResponse::InternalServerError().body(e.to_string());
+
+    let mut subs = ss
+        .list(None, Page::ALL)
+        .await
+        .map_err(internal)?
+        .into_iter()
+        .filter(|s| !s.is_pending_deletion())
+        .collect::<Vec<_>>();
+    subs.sort_by_key(|s| s.id);
+    let assignments = Assignments::load(leases.get_ref().as_ref())
+        .await
+        .map_err(internal)?;
+
+    Ok((subs, assignments))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum AssignmentState {
+    Running,
+    Paused,
+    /// No live instance holds the subscription's lease.
+    Orphaned,
+}
+
+#[derive(Serialize)]
+struct SubscriptionAssignment {
+    cluster_id: ClusterId,
+    subscription_id: SubscriptionId,
+    topic_name: String,
+
+    /// The live instance running the subscription's worker.
+    owner: Option<String>,
+
+    /// Point in time in UTC Epoch milliseconds, when the last owner's lease
+    /// lapses, or lapsed, unless renewed.
+    lease_expires_at: Option<i64>,
+    state: AssignmentState,
+
+    /// Where the schedulers move the subscription on their next reconcile.
+    assigned_to: Option<String>,
+    preferred_instance: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AssignmentsResponse {
+    /// The live indexer instances.
+    instances: Vec<String>,
+    orphaned: usize,
+    subscriptions: Vec<SubscriptionAssignment>,
+}
+
+#[derive(Deserialize)]
+struct SimulateRequest {
+    #[serde(default)]
+    remove_instances: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Reassignment {
+    cluster_id: ClusterId,
+    subscription_id: SubscriptionId,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SimulateResponse {
+    /// The instances left.
+    instances: Vec<String>,
+
+    /// Subscriptions whose owner would change, `to` is null when no instance is left.
+    reassignments: Vec<Reassignment>,
+    unassigned: usize,
+}
+
+/// Instances "a" and "b" sharing subscriptions 1 to 4, settled so that "a"
+/// runs 1, 2 and 4 and "b" runs 3.
+#[cfg(test)]
+async fn settled() -> (
+    crate::indexer::Scheduler,
+    crate::indexer::Scheduler,
+    Arc<crate::standby::lease::MemoryLeaseStore>,
+    Arc<dyn SubscriptionStore + Send + Sync>,
+) {
+    use crate::indexer::{scheduler, stores};
+    use crate::standby::lease::MemoryLeaseStore;
+
+    let (cs, ss) = stores(&[1, 2, 3, 4]).await;
+    let memory = Arc::new(MemoryLeaseStore::default());
+    let a = scheduler(cs.clone(), ss.clone()).with_leases("a".to_string(), memory.clone());
+    let b = scheduler(cs, ss.clone()).with_leases("b".to_string(), memory.clone());
+
+    // The first to start takes everything, and hands over once the other shows up.
+    for s in [&a, &b, &a, &b] {
+        s.reconcile().await.unwrap();
+    }
+    let ids = |ids: &[i64]| ids.iter().copied().map(SubscriptionId).collect::<Vec<_>>();
+    assert_eq!(a.subscriptions().await, ids(&[1, 2, 4]));
+    assert_eq!(b.subscriptions().await, ids(&[3]));
+
+    (a, b, memory, ss)
+}
+
+#[actix_web::test]
+async fn it_predicts_what_happens_when_an_instance_lapses() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    use crate::assignment::{instance_lease, owner_lease};
+    use crate::commands::store::MemoryCommandStore;
+
+    let (_a, b, memory, ss) = settled().await;
+    let leases: Arc<dyn LeaseStore + Send + Sync> = memory.clone();
+    let qs: Arc<dyn CommandStore + Send + Sync> = Arc::new(MemoryCommandStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(ss))
+            .app_data(Data::new(qs))
+            .app_data(Data::new(leases))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let get = || {
+        test::TestRequest::get()
+            .uri("/api/v1/indexer/assignments")
+            .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(body["instances"], json!(["a", "b"]));
+    assert_eq!(body["orphaned"], 0);
+    let owners = |body: &Value, key: &str| {
+        body["subscriptions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s[key].clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        owners(&body, "owner"),
+        vec![json!("a"), json!("a"), json!("b"), json!("a")]
+    );
+    assert!(owners(&body, "state").iter().all(|s| s == "running"));
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/indexer/assignments/simulate")
+        .set_json(json!({ "remove_instances": ["a"] }))
+        .to_request();
+    let prediction: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(prediction["instances"], json!(["b"]));
+    assert_eq!(prediction["unassigned"], 0);
+    let moved = prediction["reassignments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            assert_eq!((&r["from"], &r["to"]), (&json!("a"), &json!("b")));
+            SubscriptionId(r["subscription_id"].as_i64().unwrap())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        moved,
+        vec![SubscriptionId(1), SubscriptionId(2), SubscriptionId(4)]
+    );
+
+    // Simulating changed nothing.
+    let body: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(body["orphaned"], 0);
+
+    // "a" dies, its subscriptions have no live owner until "b" reconciles.
+    memory.lapse(&instance_lease("a"));
+    for id in &moved {
+        memory.lapse(&owner_lease(*id));
+    }
+    let body: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(body["instances"], json!(["b"]));
+    assert_eq!(body["orphaned"], 3);
+    assert_eq!(
+        owners(&body, "state"),
+        vec![
+            json!("orphaned"),
+            json!("orphaned"),
+            json!("running"),
+            json!("orphaned")
+        ]
+    );
+    assert_eq!(
+        owners(&body, "owner"),
+        vec![Value::Null, Value::Null, json!("b"), Value::Null]
+    );
+    assert!(owners(&body, "assigned_to").iter().all(|s| s == "b"));
+
+    b.reconcile().await.unwrap();
+    for id in &moved {
+        assert!(b.worker(*id).await.is_some(), "{}", id);
+    }
+    let body: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(body["orphaned"], 0);
+    assert!(owners(&body, "owner").iter().all(|s| s == "b"));
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/indexer/assignments/simulate")
+        .set_json(json!({ "remove_instances": ["a"] }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn it_relocates_subscriptions_on_the_next_reconcile() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    use crate::assignment::owner_lease;
+
+    let (a, b, memory, ss) = settled().await;
+    let leases: Arc<dyn LeaseStore + Send + Sync> = memory.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(ss))
+            .app_data(Data::new(leases))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let relocate = |uri: &str, target: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .set_json(json!({ "target_instance": target }))
+            .to_request()
+    };
+
+    let res = test::call_service(&app, relocate("/api/v1/subscriptions/1/3/relocate", "a")).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(
+        body,
+        json!({ "id": 3, "owner": "b", "target_instance": "a" })
+    );
+
+    // "a" waits for "b" to hand the subscription over, it never runs twice.
+    a.reconcile().await.unwrap();
+    assert!(a.worker(SubscriptionId(3)).await.is_none());
+    b.reconcile().await.unwrap();
+    assert!(b.worker(SubscriptionId(3)).await.is_none());
+    a.reconcile().await.unwrap();
+    assert!(a.worker(SubscriptionId(3)).await.is_some());
+    assert_eq!(
+        memory.get(&owner_lease(SubscriptionId(3))).unwrap().holder,
+        "a"
+    );
+
+    // The preference sticks, and the others stay where they were.
+    b.reconcile().await.unwrap();
+    assert!(b.subscriptions().await.is_empty());
+    assert_eq!(a.subscriptions().await.len(), 4);
+
+    for (uri, target, status) in [
+        (
+            "/api/v1/subscriptions/1/3/relocate",
+            "c",
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "/api/v1/subscriptions/1/9/relocate",
+            "a",
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let res = test::call_service(&app, relocate(uri, target)).await;
+        assert_eq!(res.status(), status);
+    }
+}
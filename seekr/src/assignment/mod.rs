@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::sampling::sketch::murmur2;
+use crate::standby::lease::{Lease, LeaseStore};
+
+pub mod endpoints;
+
+/// Names of the heartbeat leases of indexer instances.
+pub const INSTANCE_PREFIX: &str = "indexer:";
+
+/// Names of the leases held by the instance running a subscription's worker.
+pub const OWNER_PREFIX: &str = "subscription:";
+
+/// Names of the records of the instance a subscription was relocated to.
+pub const PREFERENCE_PREFIX: &str = "preferred:";
+
+/// How long an indexer counts as live after its last heartbeat.
+pub const INSTANCE_TTL: Duration = Duration::from_secs(30);
+
+/// How long an indexer keeps a subscription after its last renewal.
+pub const OWNER_TTL: Duration = Duration::from_secs(30);
+
+pub fn instance_lease(instance: &str) -> String {
+    format!("{}{}", INSTANCE_PREFIX, instance)
+}
+
+pub fn owner_lease(id: SubscriptionId) -> String {
+    format!("{}{}", OWNER_PREFIX, id)
+}
+
+pub fn preference_lease(id: SubscriptionId) -> String {
+    format!("{}{}", PREFERENCE_PREFIX, id)
+}
+
+/// A preference never lapses, it's honored whenever its instance is live.
+pub fn preference(id: SubscriptionId, instance: &str) -> Lease {
+    Lease {
+        name: preference_lease(id),
+        holder: instance.to_string(),
+        url: String::new(),
+        epoch: 0,
+        expires_at: i64::MAX,
+    }
+}
+
+/// The instance each subscription should run on, `None` without live instances.
+///
+/// A subscription goes to the live instance it was relocated to, otherwise to
+/// the instance ranking it highest by rendezvous hashing. Every instance works
+/// it out the same from the same leases, and an instance joining or leaving
+/// only moves the subscriptions it gains or loses.
+pub fn assign(
+    subscriptions: &[SubscriptionId],
+    instances: &BTreeSet<String>,
+    preferred: &HashMap<SubscriptionId, String>,
+) -> BTreeMap<SubscriptionId, Option<String>> {
+    subscriptions
+        .iter()
+        .map(|id| {
+            let instance = match preferred.get(id) {
+                Some(p) if instances.contains(p) => Some(p.clone()),
+                _ => instances
+                    .iter()
+                    .max_by_key(|i| murmur2(format!("{}/{}", i, id).as_bytes()) as u32)
+                    .cloned(),
+            };
+            (*id, instance)
+        })
+        .collect()
+}
+
+/// The indexers and subscription owners the lease store knows of.
+#[derive(Clone, Debug, Default)]
+pub struct Assignments {
+    /// The store's time the leases were read at.
+    pub now: i64,
+
+    /// Instances that sent a heartbeat within `INSTANCE_TTL`.
+    pub instances: BTreeSet<String>,
+
+    /// The owner leases of subscriptions, lapsed ones included.
+    pub owners: HashMap<SubscriptionId, Lease>,
+
+    /// The instances subscriptions were relocated to.
+    pub preferred: HashMap<SubscriptionId, String>,
+}
+
+impl Assignments {
+    pub async fn load(store: &(dyn LeaseStore + Send + Sync)) -> Result<Self, AnyError> {
+        let now = store.now();
+        let instances = store
+            .list(INSTANCE_PREFIX)
+            .await?
+            .into_iter()
+            .filter(|l| l.expires_at > now)
+            .map(|l| l.holder)
+            .collect();
+
+        let by_id = |leases: Vec<Lease>, prefix: &str| {
+            leases
+                .into_iter()
+                .filter_map(|l| {
+                    let id = l.name.strip_prefix(prefix)?.parse().ok()?;
+                    Some((id, l))
+                })
+                .collect::<HashMap<SubscriptionId, _>>()
+        };
+        let owners = by_id(store.list(OWNER_PREFIX).await?, OWNER_PREFIX);
+        let preferred = by_id(store.list(PREFERENCE_PREFIX).await?, PREFERENCE_PREFIX)
+            .into_iter()
+            .map(|(id, l)| (id, l.holder))
+            .collect();
+
+        Ok(Self {
+            now,
+            instances,
+            owners,
+            preferred,
+        })
+    }
+
+    /// The instance running the subscription's worker, if its lease is current
+    /// and the instance itself still live.
+    pub fn owner(&self, id: SubscriptionId) -> Option<&Lease> {
+        self.owners
+            .get(&id)
+            .filter(|l| l.expires_at > self.now && self.instances.contains(&l.holder))
+    }
+
+    /// Where the subscriptions would run were the `removed` instances gone.
+    pub fn assign(
+        &self,
+        subscriptions: &[SubscriptionId],
+        removed: &[String],
+    ) -> BTreeMap<SubscriptionId, Option<String>> {
+        let instances = self
+            .instances
+            .iter()
+            .filter(|i| !removed.contains(i))
+            .cloned()
+            .collect();
+        assign(subscriptions, &instances, &self.preferred)
+    }
+}
+
+#[test]
+fn it_assigns_subscriptions_by_rendezvous_hashing() {
+    let ids = (1..=8).map(SubscriptionId).collect::<Vec<_>>();
+    let two = BTreeSet::from(["a".to_string(), "b".to_string()]);
+    let three = BTreeSet::from(["a".to_string(), "b".to_string(), "c".to_string()]);
+
+    let before = assign(&ids, &two, &HashMap::new());
+    let after = assign(&ids, &three, &HashMap::new());
+    for id in &ids {
+        // An instance joining only takes subscriptions, it never shuffles others.
+        match after[id].as_deref() {
+            Some("c") => {}
+            owner => assert_eq!(owner, before[id].as_deref(), "{}", id),
+        }
+    }
+    assert!(after.values().any(|i| i.as_deref() == Some("c")));
+
+    // Relocations are honored while their instance is live.
+    let preferred = HashMap::from([(SubscriptionId(1), "c".to_string())]);
+    assert_eq!(
+        assign(&ids, &three, &preferred)[&SubscriptionId(1)].as_deref(),
+        Some("c")
+    );
+    assert_eq!(
+        assign(&ids, &two, &preferred)[&SubscriptionId(1)],
+        before[&SubscriptionId(1)]
+    );
+
+    assert!(assign(&ids, &BTreeSet::new(), &preferred)
+        .values()
+        .all(Option::is_none));
+}
@@ -0,0 +1,119 @@
+use actix_web::web::{Data, Json, Path, ServiceConfig};
+use actix_web::{delete, get, post, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::key::ApiKey;
+use crate::auth::{Authenticator, Principal, Role};
+use crate::ids::{ApiKeyId, ClusterId};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(create_api_key)
+        .service(get_api_keys)
+        .service(delete_api_key);
+}
+
+#[post("")]
+async fn create_api_key(
+    r: Json<CreateApiKeyRequest>,
+    principal: Principal,
+    auth: Data<Authenticator>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    info!("Creating a new {:?} API key", r.role);
+
+    let r = r.into_inner();
+    if r.role == Role::Admin && r.clusters.is_some() {
+        return HttpResponse::BadRequest().body("Admin keys can't be scoped to clusters");
+    }
+
+    match auth.create(r.name, r.role, r.clusters).await {
+        Ok((key, secret)) => HttpResponse::Ok().json(CreateApiKeyResponse {
+            id: key.id,
+            secret,
+            api_key: key.to_summary(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("")]
+async fn get_api_keys(principal: Principal, auth: Data<Authenticator>) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    info!("Fetching all API keys");
+
+    match auth.list().await {
+        Ok(keys) => HttpResponse::Ok().json(ListApiKeysResponse {
+            api_keys: keys.iter().map(|k| k.to_summary()).collect(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_api_key(
+    id: Path<ApiKeyId>,
+    principal: Principal,
+    auth: Data<Authenticator>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let id = id.into_inner();
+    info!("Revoking API key with id {}", id);
+
+    match auth.revoke(id).await {
+        Ok(true) => HttpResponse::Ok().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateApiKeyRequest {
+    name: String,
+    role: Role,
+    clusters: Option<Vec<ClusterId>>,
+}
+
+#[derive(Serialize)]
+struct CreateApiKeyResponse {
+    id: ApiKeyId,
+    secret: String,
+    api_key: ApiKeySummary,
+}
+
+#[derive(Serialize)]
+struct ListApiKeysResponse {
+    api_keys: Vec<ApiKeySummary>,
+}
+
+#[derive(Serialize)]
+struct ApiKeySummary {
+    id: ApiKeyId,
+    name: String,
+    role: Role,
+    clusters: Option<Vec<ClusterId>>,
+    created_at: DateTime<Utc>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    fn to_summary(&self) -> ApiKeySummary {
+        ApiKeySummary {
+            id: self.id,
+            name: self.name.clone(),
+            role: self.role,
+            clusters: self.clusters.clone(),
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+        }
+    }
+}
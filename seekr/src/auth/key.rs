@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::{ApiKeyId, ClusterId};
+
+use super::Role;
+
+/// An API key, of which only the hash of the secret is ever stored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+
+    /// A human readable name identifying the key holder.
+    pub name: String,
+
+    pub role: Role,
+
+    /// The clusters, and by extension their subscriptions, the key is limited to.
+    /// Keys without grants can access every cluster.
+    pub clusters: Option<Vec<ClusterId>>,
+
+    /// Hex encoded SHA-256 hash of the secret.
+    pub secret_hash: String,
+
+    /// Represents the point in time in UTC Epoch time, when the key was created.
+    pub created_at: DateTime<Utc>,
+
+    /// Represents the point in time in UTC Epoch time, when the key was last used.
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn new(
+        name: String,
+        role: Role,
+        clusters: Option<Vec<ClusterId>>,
+        secret_hash: String,
+    ) -> Self {
+        ApiKey {
+            id: ApiKeyId::default(),
+            name,
+            role,
+            clusters,
+            secret_hash,
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+}
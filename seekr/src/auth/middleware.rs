@@ -0,0 +1,482 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpMessage, HttpResponse, ResponseError};
+
+use crate::errors::{ApiError, ErrorBody};
+use crate::ids::ClusterId;
+
+use super::{forbidden, Authenticator};
+
+/// Resolve the caller's API key or token and attach its principal to the
+/// request, answering `401` to callers without a known one and `403`, naming
+/// the role required, to writes by callers whose role only reads.
+///
+/// Requests pass through untouched when no `Authenticator` is registered,
+/// i.e. when neither `--auth` nor `--api-tokens` is set. Routes addressing a cluster in their
+/// path answer 404 for clusters outside the key's grants, so keys can't probe
+/// for the existence of other clusters.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let Some(auth) = req.app_data::<Data<Authenticator>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let secret = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let principal = match secret {
+        Some(secret) => auth.authenticate(secret.trim()).await,
+        None => Ok(None),
+    };
+
+    let principal = match principal {
+        Ok(Some(p)) => p,
+        Ok(None) => {
+            let message =
+                "A known API key or token is required as 'Authorization: Bearer <secret>'";
+            let res = HttpResponse::Unauthorized().json(ErrorBody::new("unauthorized", message));
+            return reject(req, res);
+        }
+        Err(e) => return reject(req, ApiError::Internal(e).error_response()),
+    };
+
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if !safe && !principal.has_role(principal.write_role()) {
+        return reject(req, forbidden(principal.write_role()).error_response());
+    }
+
+    if cluster_in_path(req.path()).is_some_and(|id| !principal.can_access(id)) {
+        return reject(req, HttpResponse::NotFound().finish());
+    }
+
+    debug!(
+        "Authenticated {} {} as {}",
+        req.method(),
+        req.path(),
+        principal.actor()
+    );
+    req.extensions_mut().insert(principal);
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+fn reject<B>(
+    req: ServiceRequest,
+    res: HttpResponse,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// The cluster addressed by cluster and subscription routes, which both lead
+/// with the cluster id.
+fn cluster_in_path(path: &str) -> Option<ClusterId> {
+    let mut segments = path.trim_start_matches('/').split('/');
+
+    match (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) {
+        (Some("api"), Some(_), Some("clusters" | "subscriptions"), Some(id)) => id.parse().ok(),
+        _ => None,
+    }
+}
+
+#[test]
+fn it_finds_the_cluster_in_the_path() {
+    assert_eq!(cluster_in_path("/api/v1/clusters/7"), Some(ClusterId(7)));
+    assert_eq!(
+        cluster_in_path("/api/v2/subscriptions/7/12/debug"),
+        Some(ClusterId(7))
+    );
+    assert_eq!(cluster_in_path("/api/v1/clusters"), None);
+    assert_eq!(cluster_in_path("/api/v1/mirror-pairs/7"), None);
+}
+
+#[cfg(test)]
+mod harness {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::web::Data;
+    use actix_web::App;
+    use serde_json::{json, Value};
+
+    use crate::auth::store::MemoryApiKeyStore;
+    use crate::auth::Authenticator;
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::kafka::metadata::manager::{MetadataConsumerFactory, MetadataManager};
+    use crate::shards::store::{DocumentStore, MemoryDocumentStore};
+    use crate::subscriptions::store::{
+        MemoryPurgeLog, MemorySubscriptionStore, PurgeLog, SubscriptionStore,
+    };
+
+    pub const ROOT: &str = "root-secret";
+
+    pub struct Harness {
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+        documents: Arc<dyn DocumentStore + Send + Sync>,
+        purges: Arc<dyn PurgeLog + Send + Sync>,
+        manager: Data<MetadataManager>,
+        auth: Data<Authenticator>,
+        pub keys: Arc<MemoryApiKeyStore>,
+    }
+
+    impl Harness {
+        /// Clusters 1 and 2, with authentication enabled.
+        pub async fn new() -> Self {
+            let clusters = Arc::new(MemoryClusterStore::default());
+            for name in ["payments", "orders"] {
+                let cluster = Cluster::new(None, Kind::Kafka, name.to_string(), HashMap::new());
+                clusters.insert(cluster).await.unwrap();
+            }
+
+            let keys = Arc::new(MemoryApiKeyStore::default());
+            let offline: MetadataConsumerFactory = Arc::new(|_| Err("offline".into()));
+            Self {
+                manager: Data::new(MetadataManager::with_factory(clusters.clone(), offline)),
+                clusters,
+                subscriptions: Arc::new(MemorySubscriptionStore::default()),
+                documents: Arc::new(MemoryDocumentStore::default()),
+                purges: Arc::new(MemoryPurgeLog::default()),
+                auth: Data::new(Authenticator::new(keys.clone(), Some(ROOT))),
+                keys,
+            }
+        }
+
+        pub async fn call(&self, req: TestRequest, secret: Option<&str>) -> (StatusCode, Value) {
+            let app = test::init_service(
+                App::new()
+                    .app_data(Data::new(self.clusters.clone()))
+                    .app_data(Data::new(self.subscriptions.clone()))
+                    .app_data(Data::new(self.documents.clone()))
+                    .app_data(Data::new(self.purges.clone()))
+                    .app_data(self.manager.clone())
+                    .app_data(self.auth.clone())
+                    .configure(crate::server::routes),
+            )
+            .await;
+
+            let req = match secret {
+                Some(s) => req.insert_header(("authorization", format!("Bearer {}", s))),
+                None => req,
+            };
+            let res = test::call_service(&app, req.to_request()).await;
+            let status = res.status();
+            let body = test::read_body(res).await;
+
+            (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+        }
+
+        /// Create a key with the root secret, returning its id and secret.
+        pub async fn key(&self, role: &str, clusters: Option<Vec<i64>>) -> (i64, String) {
+            let body = json!({ "name": "team", "role": role, "clusters": clusters });
+            let req = TestRequest::post().uri("/api/v1/api-keys").set_json(body);
+            let (status, body) = self.call(req, Some(ROOT)).await;
+            assert_eq!(status, StatusCode::OK);
+
+            let id = body["id"].as_i64().unwrap();
+            (id, body["secret"].as_str().unwrap().to_string())
+        }
+    }
+}
+
+#[actix_web::test]
+async fn it_hides_clusters_outside_the_grants() {
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    let h = harness::Harness::new().await;
+    let (_, secret) = h.key("editor", Some(vec![1])).await;
+    let get = |uri: &str| TestRequest::get().uri(uri);
+
+    let (status, _) = h.call(get("/api/v1/clusters/1"), Some(&secret)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = h.call(get("/api/v1/clusters/2"), Some(&secret)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = h.call(get("/api/v2/subscriptions/2"), Some(&secret)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // A non-granted cluster looks exactly like a missing one.
+    let (missing, _) = h.call(get("/api/v1/clusters/9"), Some(harness::ROOT)).await;
+    assert_eq!(missing, StatusCode::NOT_FOUND);
+
+    let (_, body) = h.call(get("/api/v1/clusters"), Some(&secret)).await;
+    let names = body["clusters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["payments"]);
+
+    let (_, body) = h.call(get("/api/v2/clusters"), Some(harness::ROOT)).await;
+    assert_eq!(body["clusters"].as_array().unwrap().len(), 2);
+
+    let sub = serde_json::json!({ "cluster_id": 2, "topic_name": "orders", "config": {} });
+    let req = TestRequest::post()
+        .uri("/api/v1/subscriptions")
+        .set_json(sub);
+    let (status, _) = h.call(req, Some(&secret)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Scoped keys manage neither clusters nor keys.
+    let cluster = serde_json::json!({ "kind": "Kafka", "name": "new", "config": {} });
+    let req = TestRequest::post()
+        .uri("/api/v1/clusters")
+        .set_json(cluster);
+    let (status, _) = h.call(req, Some(&secret)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = h.call(get("/api/v1/api-keys"), Some(&secret)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn it_rejects_missing_and_readonly_writes() {
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    let h = harness::Harness::new().await;
+    let (_, secret) = h.key("readonly", None).await;
+
+    let (status, _) = h
+        .call(TestRequest::get().uri("/api/v1/clusters"), None)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = h
+        .call(TestRequest::get().uri("/api/v1/clusters"), Some("nope"))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = h
+        .call(TestRequest::get().uri("/api/v1/clusters/2"), Some(&secret))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = h
+        .call(
+            TestRequest::delete().uri("/api/v1/clusters/2"),
+            Some(&secret),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn it_revokes_keys_immediately() {
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    let h = harness::Harness::new().await;
+    let (id, secret) = h.key("editor", None).await;
+    let get = || TestRequest::get().uri("/api/v1/clusters/1");
+
+    let (status, _) = h.call(get(), Some(&secret)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Uses are written back in the background.
+    tokio::task::yield_now().await;
+    let (_, body) = h
+        .call(
+            TestRequest::get().uri("/api/v1/api-keys"),
+            Some(harness::ROOT),
+        )
+        .await;
+    let key = &body["api_keys"][0];
+    assert!(key["last_used_at"].is_string());
+    assert!(key.get("secret").is_none() && key.get("secret_hash").is_none());
+
+    let uri = format!("/api/v1/api-keys/{}", id);
+    let (status, _) = h
+        .call(TestRequest::delete().uri(&uri), Some(harness::ROOT))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = h.call(get(), Some(&secret)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn it_rejects_unauthenticated_principals_when_auth_is_enabled() {
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{get, App, HttpResponse, Responder};
+
+    use crate::auth::store::MemoryApiKeyStore;
+    use crate::auth::Principal;
+
+    // A route reached without the middleware must not fall back to root.
+    #[get("/whoami")]
+    async fn whoami(principal: Principal) -> impl Responder {
+        HttpResponse::Ok().body(principal.actor())
+    }
+
+    let app = test::init_service(App::new().service(whoami)).await;
+    let res = test::call_service(&app, TestRequest::get().uri("/whoami").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let auth = Authenticator::new(std::sync::Arc::new(MemoryApiKeyStore::default()), None);
+    let app = test::init_service(App::new().app_data(Data::new(auth)).service(whoami)).await;
+    let res = test::call_service(&app, TestRequest::get().uri("/whoami").to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn it_requires_a_configured_token_on_api_routes_only() {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+    use serde_json::Value;
+
+    use crate::auth::store::MemoryApiKeyStore;
+    use crate::auth::ApiToken;
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+
+    let tokens = ApiToken::parse_all(&["deploys:s3cret".to_string()]).unwrap();
+    let auth = Authenticator::new(Arc::new(MemoryApiKeyStore::default()), None).with_tokens(tokens);
+    let clusters: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(clusters))
+            .app_data(Data::new(auth))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let res = test::call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+    assert_ne!(res.status(), StatusCode::UNAUTHORIZED);
+
+    for secret in [None, Some("guess")] {
+        let mut req = TestRequest::delete().uri("/api/v1/clusters/1");
+        if let Some(secret) = secret {
+            req = req.insert_header((AUTHORIZATION, format!("Bearer {}", secret)));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["code"], "unauthorized");
+    }
+
+    let req = TestRequest::get()
+        .uri("/api/v1/clusters")
+        .insert_header((AUTHORIZATION, "Bearer s3cret"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn it_requires_the_admin_role_of_tokens_writing() {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+    use serde_json::Value;
+
+    use crate::auth::store::MemoryApiKeyStore;
+    use crate::auth::ApiToken;
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::kafka::metadata::manager::{MetadataConsumerFactory, MetadataManager};
+    use crate::settings::{RuntimeSettings, SettingsBuilder};
+    use crate::shards::store::{DocumentStore, MemoryDocumentStore};
+    use crate::subscriptions::store::{
+        MemoryPurgeLog, MemorySubscriptionStore, PurgeLog, SubscriptionStore,
+    };
+
+    let entries = [
+        "deploys:s3cret:admin".to_string(),
+        "dashboards:gr4phs:read".to_string(),
+    ];
+    let tokens = ApiToken::parse_all(&entries).unwrap();
+    let auth = Authenticator::new(Arc::new(MemoryApiKeyStore::default()), None).with_tokens(tokens);
+    let clusters: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let subscriptions: Arc<dyn SubscriptionStore + Send + Sync> =
+        Arc::new(MemorySubscriptionStore::default());
+    let documents: Arc<dyn DocumentStore + Send + Sync> = Arc::new(MemoryDocumentStore::default());
+    let purges: Arc<dyn PurgeLog + Send + Sync> = Arc::new(MemoryPurgeLog::default());
+    let offline: MetadataConsumerFactory = Arc::new(|_| Err("offline".into()));
+    let manager = MetadataManager::with_factory(clusters.clone(), offline);
+    let settings = RuntimeSettings::new(SettingsBuilder::new("server").build());
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(clusters))
+            .app_data(Data::new(subscriptions))
+            .app_data(Data::new(documents))
+            .app_data(Data::new(purges))
+            .app_data(Data::new(manager))
+            .app_data(Data::new(settings))
+            .app_data(Data::new(auth))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let bearer = |secret: &str| (AUTHORIZATION, format!("Bearer {}", secret));
+
+    let req = TestRequest::get()
+        .uri("/api/v1/clusters")
+        .insert_header(bearer("gr4phs"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = TestRequest::delete()
+        .uri("/api/v1/clusters/1")
+        .insert_header(bearer("gr4phs"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["code"], "forbidden");
+    assert_eq!(error["details"]["required_role"], "admin");
+
+    let req = TestRequest::delete()
+        .uri("/api/v1/clusters/1")
+        .insert_header(bearer("s3cret"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Reads only admins may make check the role in the handler.
+    let req = TestRequest::get()
+        .uri("/api/v1/debug/config")
+        .insert_header(bearer("gr4phs"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["details"]["required_role"], "admin");
+    let req = TestRequest::get()
+        .uri("/api/v1/debug/config")
+        .insert_header(bearer("s3cret"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn it_hides_store_failures_behind_a_correlation_id() {
+    use std::sync::atomic::Ordering;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    let h = harness::Harness::new().await;
+    h.keys.unreachable.store(true, Ordering::SeqCst);
+
+    let get = TestRequest::get().uri("/api/v1/clusters");
+    let (status, body) = h.call(get, Some("unknown-secret")).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "internal");
+    assert!(body["correlation_id"].is_string());
+    assert!(!body.to_string().contains("api-keys.internal"));
+}
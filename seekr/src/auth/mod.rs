@@ -0,0 +1,588 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::{ready, Ready};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::Payload;
+use actix_web::error::ErrorUnauthorized;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+
+use crate::errors::{AnyError, ApiError};
+use crate::ids::{ApiKeyId, ClusterId};
+
+use self::key::ApiKey;
+use self::store::ApiKeyStore;
+
+pub mod endpoints;
+pub mod key;
+pub mod middleware;
+pub mod reveal;
+pub mod store;
+
+/// Prefix of generated secrets, so leaked keys are easy to recognize.
+const SECRET_PREFIX: &str = "seekr_";
+
+/// How often the last use of a key is written back to the store.
+const LAST_USED_RESOLUTION: Duration = Duration::from_secs(60);
+
+/// How long an unknown secret is rejected without asking the store again.
+const MISS_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Full access, including key management when not scoped to clusters.
+    Admin,
+    /// Read and write access.
+    Editor,
+    /// Read only access.
+    Readonly,
+}
+
+impl Role {
+    /// The role as keys are created with it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Editor => "editor",
+            Role::Readonly => "readonly",
+        }
+    }
+}
+
+/// The authenticated caller of a request, with the grants of its key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Principal {
+    pub key_id: Option<ApiKeyId>,
+    pub role: Role,
+    pub clusters: Option<HashSet<ClusterId>>,
+
+    /// The name of the configured API token the caller sent, if any.
+    pub token: Option<String>,
+}
+
+impl Principal {
+    /// The unrestricted principal of the bootstrap key, and of every request
+    /// when authentication is disabled.
+    pub fn root() -> Self {
+        Self {
+            key_id: None,
+            role: Role::Admin,
+            clusters: None,
+            token: None,
+        }
+    }
+
+    /// The principal of a configured API token, unscoped with its role.
+    pub fn token(name: &str, role: Role) -> Self {
+        Self {
+            token: Some(name.to_string()),
+            role,
+            ..Self::root()
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin && self.clusters.is_none()
+    }
+
+    pub fn is_scoped(&self) -> bool {
+        self.clusters.is_some()
+    }
+
+    pub fn can_write(&self) -> bool {
+        self.role != Role::Readonly
+    }
+
+    pub fn can_access(&self, id: ClusterId) -> bool {
+        self.clusters.as_ref().is_none_or(|c| c.contains(&id))
+    }
+
+    /// Whether the principal holds the role, or one granting more.
+    pub fn has_role(&self, role: Role) -> bool {
+        match role {
+            Role::Admin => self.is_admin(),
+            Role::Editor => self.can_write(),
+            Role::Readonly => true,
+        }
+    }
+
+    /// The role writes require of the principal: tokens are either read or
+    /// admin, while keys can write from editor up.
+    pub fn write_role(&self) -> Role {
+        match self.token {
+            Some(_) => Role::Admin,
+            None => Role::Editor,
+        }
+    }
+
+    /// Identifies the principal in audit records.
+    pub fn actor(&self) -> String {
+        match (self.key_id, &self.token) {
+            (Some(id), _) => format!("key:{}", id),
+            (None, Some(name)) => format!("token:{}", name),
+            (None, None) => "root".to_string(),
+        }
+    }
+}
+
+impl From<&ApiKey> for Principal {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            key_id: Some(key.id),
+            role: key.role,
+            clusters: key.clusters.as_ref().map(|c| c.iter().copied().collect()),
+            token: None,
+        }
+    }
+}
+
+impl FromRequest for Principal {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    /// The principal attached by the authentication middleware. Requests
+    /// without one are only let through when authentication is disabled.
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let principal = req.extensions().get::<Principal>().cloned();
+        ready(match principal {
+            Some(p) => Ok(p),
+            None if req.app_data::<Data<Authenticator>>().is_none() => Ok(Principal::root()),
+            None => Err(ErrorUnauthorized("missing credentials")),
+        })
+    }
+}
+
+/// The role and token of the authenticated caller, for handlers checking
+/// more than the middleware does, such as reads only admins may make.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestAuth {
+    principal: Principal,
+}
+
+impl RequestAuth {
+    pub fn role(&self) -> Role {
+        self.principal.role
+    }
+
+    /// The name of the configured API token the caller sent, if any.
+    pub fn token(&self) -> Option<&str> {
+        self.principal.token.as_deref()
+    }
+
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    /// Answer `403` naming the role unless the caller holds it.
+    pub fn require(&self, role: Role) -> Result<(), ApiError> {
+        match self.principal.has_role(role) {
+            true => Ok(()),
+            false => Err(forbidden(role)),
+        }
+    }
+}
+
+impl FromRequest for RequestAuth {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        ready(
+            Principal::from_request(req, payload)
+                .into_inner()
+                .map(|principal| Self { principal }),
+        )
+    }
+}
+
+/// The error of a caller without the role a route requires, naming it.
+pub fn forbidden(required: Role) -> ApiError {
+    let name = required.name();
+    ApiError::Forbidden(
+        format!("Requires the {} role", name),
+        Some(json!({ "required_role": name })),
+    )
+}
+
+pub fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// Whether the secrets are the same, taking as long wherever they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// A named secret from the `--api-tokens` setting, with the `read` or
+/// `admin` role.
+#[derive(Clone)]
+pub struct ApiToken {
+    name: String,
+    hash: String,
+    role: Role,
+}
+
+impl fmt::Debug for ApiToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApiToken")
+            .field("name", &self.name)
+            .field("role", &self.role)
+            .finish()
+    }
+}
+
+impl ApiToken {
+    /// Parse `name:token:role` entries, the role `read` or `admin` and admin
+    /// when left out, rejecting empty or repeated names and empty tokens.
+    pub fn parse_all(entries: &[String]) -> Result<Vec<Self>, String> {
+        let mut tokens = Vec::<Self>::new();
+        for entry in entries.iter().map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let mut parts = entry.splitn(3, ':').map(str::trim);
+            let (Some(name), Some(secret)) = (parts.next(), parts.next()) else {
+                return Err("API tokens must be given as name:token:role".to_string());
+            };
+            let role = match parts.next() {
+                None | Some("admin") => Role::Admin,
+                Some("read") => Role::Readonly,
+                Some(role) => {
+                    return Err(format!(
+                        "API token '{}' has role '{}', not read or admin",
+                        name, role
+                    ))
+                }
+            };
+            if name.is_empty() || secret.is_empty() {
+                return Err("API tokens need both a name and a token".to_string());
+            }
+            if tokens.iter().any(|t| t.name == name) {
+                return Err(format!("API token '{}' is given more than once", name));
+            }
+            tokens.push(Self {
+                name: name.to_string(),
+                hash: hash_secret(secret),
+                role,
+            });
+        }
+        Ok(tokens)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn generate_secret() -> String {
+    format!("{}{}", SECRET_PREFIX, uuid::Uuid::new_v4().simple())
+}
+
+struct CachedKey {
+    key: ApiKey,
+
+    /// Milliseconds since the epoch of the last use written back, or 0.
+    touched_at: AtomicI64,
+}
+
+/// Resolves request secrets to principals.
+///
+/// Resolved keys are cached by secret hash, and dropped from the cache as
+/// soon as they are revoked. Unknown secrets are remembered briefly, so
+/// repeated guesses don't all reach the store.
+pub struct Authenticator {
+    store: Arc<dyn ApiKeyStore + Send + Sync>,
+    root: Option<String>,
+    tokens: Vec<ApiToken>,
+    cache: RwLock<HashMap<String, CachedKey>>,
+    misses: RwLock<HashMap<String, Instant>>,
+
+    /// Bumped by every revocation, so a lookup racing one isn't cached.
+    revocations: AtomicU64,
+}
+
+impl Authenticator {
+    pub fn new(store: Arc<dyn ApiKeyStore + Send + Sync>, root: Option<&str>) -> Self {
+        Self {
+            store,
+            root: root.map(hash_secret),
+            tokens: Vec::new(),
+            cache: RwLock::new(HashMap::new()),
+            misses: RwLock::new(HashMap::new()),
+            revocations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_tokens(mut self, tokens: Vec<ApiToken>) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// The configured token with the given secret hash, compared against
+    /// every token in constant time so timings don't tell how close a guess is.
+    fn token(&self, hash: &str) -> Option<&ApiToken> {
+        self.tokens.iter().fold(None, |found, token| {
+            match constant_time_eq(token.hash.as_bytes(), hash.as_bytes()) {
+                true => Some(token),
+                false => found,
+            }
+        })
+    }
+
+    /// The principal of the configured token or key with the given secret, if
+    /// it exists.
+    ///
+    /// Locks are only held to read and update the caches, never across the
+    /// store, and uses are written back in the background.
+    pub async fn authenticate(&self, secret: &str) -> Result<Option<Principal>, AnyError> {
+        let hash = hash_secret(secret);
+        if self.root.as_ref() == Some(&hash) {
+            return Ok(Some(Principal::root()));
+        }
+        if let Some(token) = self.token(&hash) {
+            return Ok(Some(Principal::token(token.name(), token.role)));
+        }
+
+        if let Some(cached) = self.cache.read().await.get(&hash) {
+            self.touch(cached);
+            return Ok(Some(Principal::from(&cached.key)));
+        }
+
+        if self
+            .misses
+            .read()
+            .await
+            .get(&hash)
+            .is_some_and(|&until| Instant::now() < until)
+        {
+            return Ok(None);
+        }
+
+        let revocations = self.revocations.load(Ordering::SeqCst);
+        let Some(key) = self.store.find(&hash).await? else {
+            let mut misses = self.misses.write().await;
+            let now = Instant::now();
+            misses.retain(|_, &mut until| now < until);
+            misses.insert(hash, now + MISS_TTL);
+            return Ok(None);
+        };
+
+        let principal = Principal::from(&key);
+        let cached = CachedKey {
+            key,
+            touched_at: AtomicI64::new(0),
+        };
+        self.touch(&cached);
+
+        let mut cache = self.cache.write().await;
+        if self.revocations.load(Ordering::SeqCst) == revocations {
+            cache.insert(hash, cached);
+        }
+
+        Ok(Some(principal))
+    }
+
+    /// Record the use of a key, at most once per `LAST_USED_RESOLUTION`.
+    fn touch(&self, cached: &CachedKey) {
+        let now = Utc::now();
+        let last = cached.touched_at.load(Ordering::SeqCst);
+        let due =
+            last == 0 || last + LAST_USED_RESOLUTION.as_millis() as i64 <= now.timestamp_millis();
+        if !due {
+            return;
+        }
+
+        // Only the request that claims the touch writes it back.
+        if cached
+            .touched_at
+            .compare_exchange(
+                last,
+                now.timestamp_millis(),
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_err()
+        {
+            return;
+        }
+
+        let store = self.store.clone();
+        let id = cached.key.id;
+        tokio::spawn(async move {
+            if let Err(e) = store.touch(id, now).await {
+                warn!("Unable to record use of API key {}: {}", id, e);
+            }
+        });
+    }
+
+    /// Create a key, returning it along with its secret, which is never
+    /// available again.
+    pub async fn create(
+        &self,
+        name: String,
+        role: Role,
+        clusters: Option<Vec<ClusterId>>,
+    ) -> Result<(ApiKey, String), AnyError> {
+        let secret = generate_secret();
+        let key = ApiKey::new(name, role, clusters, hash_secret(&secret));
+        let id = self.store.insert(key.clone()).await?;
+
+        Ok((ApiKey { id, ..key }, secret))
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKey>, AnyError> {
+        self.store.list().await
+    }
+
+    /// Delete a key, rejecting its secret from now on.
+    pub async fn revoke(&self, id: ApiKeyId) -> Result<bool, AnyError> {
+        {
+            let mut cache = self.cache.write().await;
+            self.revocations.fetch_add(1, Ordering::SeqCst);
+            cache.retain(|_, c| c.key.id != id);
+        }
+
+        let Some(_) = self.store.get(id).await? else {
+            return Ok(false);
+        };
+
+        self.store.remove(id).await?;
+        Ok(true)
+    }
+}
+
+#[test]
+fn it_limits_scoped_principals_to_their_grants() {
+    let scoped = Principal {
+        key_id: Some(ApiKeyId(1)),
+        role: Role::Admin,
+        clusters: Some(HashSet::from([ClusterId(1)])),
+        token: None,
+    };
+
+    assert!(scoped.can_access(ClusterId(1)));
+    assert!(!scoped.can_access(ClusterId(2)));
+    assert!(!scoped.is_admin());
+    assert!(Principal::root().is_admin());
+    assert!(Principal::root().can_access(ClusterId(2)));
+    assert_eq!(scoped.actor(), "key:1");
+    assert_eq!(Principal::root().actor(), "root");
+    assert_eq!(
+        Principal::token("deploys", Role::Admin).actor(),
+        "token:deploys"
+    );
+
+    let reader = Principal::token("dashboards", Role::Readonly);
+    assert!(reader.has_role(Role::Readonly));
+    assert!(!reader.has_role(Role::Editor));
+    assert_eq!(reader.write_role(), Role::Admin);
+    assert!(!scoped.has_role(Role::Admin) && scoped.has_role(Role::Editor));
+    assert_eq!(scoped.write_role(), Role::Editor);
+}
+
+#[tokio::test]
+async fn it_authenticates_configured_tokens() {
+    let pairs = |pairs: &[&str]| pairs.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+    let tokens = ApiToken::parse_all(&pairs(&[
+        "deploys:s3cret",
+        " oncall : p4ger : admin",
+        "dashboards:gr4phs:read",
+        "",
+    ]))
+    .unwrap();
+    assert_eq!(
+        tokens.iter().map(ApiToken::name).collect::<Vec<_>>(),
+        vec!["deploys", "oncall", "dashboards"]
+    );
+    assert!(!format!("{:?}", tokens).contains("s3cret"));
+    for invalid in [
+        &["deploys"][..],
+        &["deploys:"],
+        &[":s3cret"],
+        &["a:1", "a:2"],
+        &["a:1:readonly"],
+        &["a:1:"],
+    ] {
+        assert!(
+            ApiToken::parse_all(&pairs(invalid)).is_err(),
+            "{:?}",
+            invalid
+        );
+    }
+
+    let store = Arc::new(store::MemoryApiKeyStore::default());
+    let auth = Authenticator::new(store.clone(), None).with_tokens(tokens);
+    let principal = auth.authenticate("p4ger").await.unwrap().unwrap();
+    assert_eq!(principal.actor(), "token:oncall");
+    assert!(principal.is_admin());
+    let principal = auth.authenticate("gr4phs").await.unwrap().unwrap();
+    assert_eq!(principal.role, Role::Readonly);
+    assert_eq!(principal.token.as_deref(), Some("dashboards"));
+    assert!(auth
+        .authenticate("s3cret")
+        .await
+        .unwrap()
+        .unwrap()
+        .is_admin());
+    assert!(auth.authenticate("s3cre").await.unwrap().is_none());
+    assert!(auth.authenticate("deploys:s3cret").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn it_stores_only_hashed_secrets() {
+    let store = Arc::new(store::MemoryApiKeyStore::default());
+    let auth = Authenticator::new(store.clone(), None);
+
+    let (key, secret) = auth
+        .create("payments".to_string(), Role::Editor, None)
+        .await
+        .unwrap();
+    assert!(secret.starts_with(SECRET_PREFIX));
+
+    let stored = serde_json::to_string(&store.list().await.unwrap()).unwrap();
+    assert!(!stored.contains(&secret));
+    assert!(!stored.contains(secret.trim_start_matches(SECRET_PREFIX)));
+    assert!(stored.contains(&hash_secret(&secret)));
+
+    let principal = auth.authenticate(&secret).await.unwrap().unwrap();
+    assert_eq!(principal.key_id, Some(key.id));
+    assert!(auth.authenticate("seekr_guess").await.unwrap().is_none());
+
+    // The first use is recorded right away, in the background.
+    tokio::task::yield_now().await;
+    let stored = store.get(key.id).await.unwrap().unwrap();
+    assert!(stored.last_used_at.is_some());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_only_asks_the_store_about_uncached_secrets() {
+    let store = Arc::new(store::MemoryApiKeyStore::default());
+    let auth = Authenticator::new(store.clone(), None);
+    let finds = || store.finds.load(Ordering::SeqCst);
+
+    let (_, secret) = auth
+        .create("payments".to_string(), Role::Editor, None)
+        .await
+        .unwrap();
+    for _ in 0..3 {
+        assert!(auth.authenticate(&secret).await.unwrap().is_some());
+    }
+    assert_eq!(finds(), 1);
+
+    // Unknown secrets are rejected from the cache until the miss expires.
+    for _ in 0..3 {
+        assert!(auth.authenticate("seekr_guess").await.unwrap().is_none());
+    }
+    assert_eq!(finds(), 2);
+
+    tokio::time::advance(MISS_TTL).await;
+    assert!(auth.authenticate("seekr_guess").await.unwrap().is_none());
+    assert_eq!(finds(), 3);
+}
@@ -0,0 +1,170 @@
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::error::ErrorBadRequest;
+use actix_web::web::{Data, Query};
+use actix_web::{FromRequest, HttpRequest};
+use serde::Deserialize;
+
+use super::hash_secret;
+
+/// The header reads send the reveal token in.
+pub const REVEAL_TOKEN_HEADER: &str = "X-Reveal-Token";
+
+/// The admin token that lets reads return config secrets as they are.
+pub struct RevealToken {
+    hash: String,
+}
+
+impl RevealToken {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            hash: hash_secret(secret),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RevealQuery {
+    #[serde(default)]
+    reveal: bool,
+}
+
+/// Whether a read returns config secrets, asked for with `?reveal=true`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reveal {
+    /// Secrets are redacted, the read didn't ask for them.
+    Redacted,
+    /// The read asked with the reveal token.
+    Granted,
+    /// The read asked without the reveal token, or none is configured.
+    Denied,
+}
+
+impl FromRequest for Reveal {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let query = match Query::<RevealQuery>::from_query(req.query_string()) {
+            Ok(query) => query,
+            Err(_) => return ready(Err(ErrorBadRequest("reveal must be true or false"))),
+        };
+        if !query.reveal {
+            return ready(Ok(Reveal::Redacted));
+        }
+
+        let secret = req
+            .headers()
+            .get(REVEAL_TOKEN_HEADER)
+            .and_then(|v| v.to_str().ok());
+        ready(Ok(match (req.app_data::<Data<RevealToken>>(), secret) {
+            (Some(token), Some(secret)) if hash_secret(secret.trim()) == token.hash => {
+                Reveal::Granted
+            }
+            _ => Reveal::Denied,
+        }))
+    }
+}
+
+#[actix_web::test]
+async fn it_reveals_config_secrets_only_with_the_token() {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::ids::{ClusterId, SubscriptionId};
+    use crate::kafka::sensitive::REDACTED;
+    use crate::subscriptions::store::{MemorySubscriptionStore, SubscriptionStore};
+    use crate::subscriptions::subscription::Subscription;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let config = HashMap::from([
+        ("sasl.username".to_string(), "seekr".to_string()),
+        ("sasl.password".to_string(), "hunter2".to_string()),
+    ]);
+    let cluster = Cluster::new(Some(ClusterId(1)), Kind::Kafka, "c".to_string(), config);
+    cs.put(cluster).await.unwrap();
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let config = HashMap::from([("schema.registry.secret".to_string(), "s3cr3t".to_string())]);
+    let subscription = Subscription::new(
+        Some(SubscriptionId(1)),
+        ClusterId(1),
+        "orders".to_string(),
+        config,
+    );
+    ss.put(subscription).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs.clone()))
+            .app_data(Data::new(ss.clone()))
+            .app_data(Data::new(RevealToken::new("reveal-secret")))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let get = |uri: &str, token: Option<&str>| {
+        let req = test::TestRequest::get().uri(uri);
+        match token {
+            Some(token) => req.insert_header((REVEAL_TOKEN_HEADER, token)),
+            None => req,
+        }
+        .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, get("/api/v1/clusters/1", None)).await;
+    assert_eq!(body["cluster"]["config"]["sasl.username"], "seekr");
+    assert_eq!(body["cluster"]["config"]["sasl.password"], REDACTED);
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        get("/api/v1/clusters/1?reveal=true", Some("reveal-secret")),
+    )
+    .await;
+    assert_eq!(body["cluster"]["config"]["sasl.password"], "hunter2");
+
+    let uri = "/api/v1/subscriptions/1/1";
+    let body: Value = test::call_and_read_body_json(&app, get(uri, Some("reveal-secret"))).await;
+    assert_eq!(
+        body["subscription"]["config"]["schema.registry.secret"],
+        REDACTED
+    );
+    let uri = "/api/v1/subscriptions/1/1?reveal=true";
+    let body: Value = test::call_and_read_body_json(&app, get(uri, Some("reveal-secret"))).await;
+    assert_eq!(
+        body["subscription"]["config"]["schema.registry.secret"],
+        "s3cr3t"
+    );
+
+    for (uri, token) in [
+        ("/api/v1/clusters/1?reveal=true", None),
+        ("/api/v1/clusters/1?reveal=true", Some("guess")),
+        ("/api/v1/subscriptions/1/1?reveal=true", None),
+    ] {
+        let res = test::call_service(&app, get(uri, token)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", uri);
+        let error: Value = test::read_body_json(res).await;
+        assert_eq!(error["code"], "reveal_forbidden");
+    }
+    let res = test::call_service(&app, get("/api/v1/clusters/1?reveal=maybe", None)).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Without a configured token, secrets stay redacted for everyone.
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(ss))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let res = test::call_service(
+        &app,
+        get("/api/v1/clusters/1?reveal=true", Some("reveal-secret")),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+
+use crate::errors::AnyError;
+use crate::id;
+use crate::ids::ApiKeyId;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+use super::key::ApiKey;
+
+#[async_trait]
+pub trait ApiKeyStore {
+    async fn list(&self) -> Result<Vec<ApiKey>, AnyError>;
+    async fn get(&self, id: ApiKeyId) -> Result<Option<ApiKey>, AnyError>;
+
+    /// The key whose secret has the given hash.
+    async fn find(&self, secret_hash: &str) -> Result<Option<ApiKey>, AnyError>;
+
+    async fn insert(&self, key: ApiKey) -> Result<ApiKeyId, AnyError>;
+    async fn remove(&self, id: ApiKeyId) -> Result<ApiKeyId, AnyError>;
+
+    /// Record that the key was used at the given time.
+    async fn touch(&self, id: ApiKeyId, at: DateTime<Utc>) -> Result<(), AnyError>;
+}
+
+pub const INDEX_NAME: &str = "api_keys";
+
+pub struct MSApiKeyStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+    /// A Distributed Unique ID generator.
+    generator: Arc<id::Generator>,
+}
+
+impl MSApiKeyStore {
+    pub async fn new(client: Arc<Client>, generator: Arc<id::Generator>) -> Self {
+        if let Ok(task) = client.clone().create_index(INDEX_NAME, Some("id")).await {
+            task.wait_for_completion(&client, None, None).await.unwrap();
+        }
+
+        let index = client.index(INDEX_NAME);
+        if let Err(e) = index.set_filterable_attributes(["secret_hash"]).await {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                INDEX_NAME, e
+            );
+        }
+
+        Self { client, generator }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+
+    async fn put(&self, key: &ApiKey) -> Result<(), AnyError> {
+        self.index()
+            .add_or_replace(&[key], Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for MSApiKeyStore {
+    async fn list(&self) -> Result<Vec<ApiKey>, AnyError> {
+        let keys = self.index().get_documents::<ApiKey>().await?;
+        Ok(keys.results)
+    }
+
+    async fn get(&self, id: ApiKeyId) -> Result<Option<ApiKey>, AnyError> {
+        let key = self.index().get_document::<ApiKey>(&id.to_string()).await;
+
+        meilisearch::found(key)
+    }
+
+    async fn find(&self, secret_hash: &str) -> Result<Option<ApiKey>, AnyError> {
+        let filter = format!("secret_hash = \"{}\"", secret_hash);
+        let results = self
+            .index()
+            .search()
+            .with_filter(&filter)
+            .with_limit(1)
+            .execute::<ApiKey>()
+            .await?;
+
+        Ok(results.hits.into_iter().next().map(|h| h.result))
+    }
+
+    async fn insert(&self, key: ApiKey) -> Result<ApiKeyId, AnyError> {
+        let key = ApiKey {
+            id: ApiKeyId(self.generator.next_id()?),
+            ..key
+        };
+
+        self.put(&key).await?;
+        Ok(key.id)
+    }
+
+    async fn remove(&self, id: ApiKeyId) -> Result<ApiKeyId, AnyError> {
+        self.index()
+            .delete_document(id.to_string())
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+        Ok(id)
+    }
+
+    async fn touch(&self, id: ApiKeyId, at: DateTime<Utc>) -> Result<(), AnyError> {
+        let Some(key) = self.get(id).await? else {
+            return Ok(());
+        };
+
+        let key = ApiKey {
+            last_used_at: Some(at),
+            ..key
+        };
+        self.put(&key).await
+    }
+}
+
+/// An in-memory store used to exercise API keys in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryApiKeyStore {
+    keys: tokio::sync::RwLock<std::collections::BTreeMap<ApiKeyId, ApiKey>>,
+
+    /// How many lookups by secret reached the store.
+    pub finds: std::sync::atomic::AtomicUsize,
+
+    /// Fails lookups by secret, as a store that can't be reached does.
+    pub unreachable: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl ApiKeyStore for MemoryApiKeyStore {
+    async fn list(&self) -> Result<Vec<ApiKey>, AnyError> {
+        Ok(self.keys.read().await.values().cloned().collect())
+    }
+
+    async fn get(&self, id: ApiKeyId) -> Result<Option<ApiKey>, AnyError> {
+        Ok(self.keys.read().await.get(&id).cloned())
+    }
+
+    async fn find(&self, secret_hash: &str) -> Result<Option<ApiKey>, AnyError> {
+        self.finds.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        if self.unreachable.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err("api-keys.internal unreachable".into());
+        }
+        let keys = self.keys.read().await;
+        Ok(keys
+            .values()
+            .find(|k| k.secret_hash == secret_hash)
+            .cloned())
+    }
+
+    async fn insert(&self, key: ApiKey) -> Result<ApiKeyId, AnyError> {
+        let mut keys = self.keys.write().await;
+        let id = ApiKeyId(keys.keys().next_back().map_or(1, |id| id.0 + 1));
+        keys.insert(id, ApiKey { id, ..key });
+        Ok(id)
+    }
+
+    async fn remove(&self, id: ApiKeyId) -> Result<ApiKeyId, AnyError> {
+        self.keys.write().await.remove(&id);
+        Ok(id)
+    }
+
+    async fn touch(&self, id: ApiKeyId, at: DateTime<Utc>) -> Result<(), AnyError> {
+        if let Some(key) = self.keys.write().await.get_mut(&id) {
+            key.last_used_at = Some(at);
+        }
+        Ok(())
+    }
+}
+
+pub async fn init_api_key_store(config: &MeilisearchConfig) -> Arc<dyn ApiKeyStore + Send + Sync> {
+    Arc::new(MSApiKeyStore::new(config.client(), id::generator()).await)
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AnyError;
+use crate::id::IdConfig;
+use crate::meilisearch::MeilisearchConfig;
+use crate::session::{self, CdrsSession};
+use crate::SESSION;
+
+pub const DEFAULT_CASSANDRA_CONTACT_POINT: &str = "localhost:9042";
+
+/// What a store that has more than one implementation is kept in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    #[default]
+    Meilisearch,
+    Cassandra,
+    /// Kept in the process only, so lost when it exits.
+    Memory,
+}
+
+impl fmt::Display for StoreBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreBackend::Meilisearch => write!(f, "meilisearch"),
+            StoreBackend::Cassandra => write!(f, "cassandra"),
+            StoreBackend::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+/// Where the Cassandra backed stores are kept.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CassandraConfig {
+    pub contact_point: String,
+}
+
+impl Default for CassandraConfig {
+    fn default() -> Self {
+        Self {
+            contact_point: DEFAULT_CASSANDRA_CONTACT_POINT.to_string(),
+        }
+    }
+}
+
+impl CassandraConfig {
+    /// The session shared by the Cassandra backed stores, connected on first use.
+    pub async fn session(&self) -> Result<Arc<CdrsSession>, AnyError> {
+        let session = SESSION
+            .get_or_try_init(|| async {
+                let session = session::create_session(&self.contact_point)
+                    .await
+                    .map_err(|e| {
+                        format!("Cassandra at {} is unreachable: {}", self.contact_point, e)
+                    })?;
+                Ok::<_, AnyError>(Arc::new(session))
+            })
+            .await?;
+        Ok(session.clone())
+    }
+}
+
+/// Which backend the clusters and subscriptions are each kept in, how to
+/// reach the backends, and which worker the ids of what's stored are
+/// generated as.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StoreConfig {
+    pub clusters: StoreBackend,
+    pub subscriptions: StoreBackend,
+    pub meilisearch: MeilisearchConfig,
+    pub cassandra: CassandraConfig,
+    pub ids: IdConfig,
+}
+
+impl StoreConfig {
+    /// Check that the backend answers, for the store named `store`, so a
+    /// misconfigured one fails at startup rather than on its first request.
+    pub async fn verify(&self, store: &str, backend: StoreBackend) -> Result<(), AnyError> {
+        let checked = match backend {
+            StoreBackend::Meilisearch => self.meilisearch.verify().await,
+            StoreBackend::Cassandra => self.cassandra.session().await.map(|_| ()),
+            StoreBackend::Memory => Ok(()),
+        };
+        checked.map_err(|e| {
+            format!("Unable to start the {} store on {}: {}", store, backend, e).into()
+        })
+    }
+}
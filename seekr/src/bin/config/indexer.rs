@@ -1,6 +1,11 @@
-use clap::Args;
+use std::time::Duration;
 
-use seekr::logger::Level;
+use clap::{ArgMatches, Args};
+
+use seekr::logger::{Format, Level};
+use seekr::settings::SettingsBuilder;
+
+use super::{source, StoreArgs};
 
 #[derive(Args, Debug)]
 pub struct IndexerConfig {
@@ -15,16 +20,96 @@ pub struct IndexerConfig {
     )]
     /// The logging level
     pub log: Level,
+
+    #[clap(
+        long = "log-format",
+        env = "SEEKER_LOG_FORMAT",
+        default_value = "text",
+        forbid_empty_values = true,
+        help = "How logs are written, text or one JSON object per line",
+        value_enum
+    )]
+    /// How logs are written, text or one JSON object per line
+    pub log_format: Format,
+
+    #[clap(flatten)]
+    pub stores: StoreArgs,
+
+    #[clap(
+        long,
+        env = "SEEKER_INSTANCE",
+        help = "The name this indexer shares subscriptions with other indexers under"
+    )]
+    /// The name this indexer shares subscriptions with other indexers under
+    pub instance: Option<String>,
+
+    #[clap(
+        long = "stop-timeout",
+        env = "SEEKER_STOP_TIMEOUT",
+        default_value = "30",
+        help = "Seconds workers get to finish the messages in flight on shutdown before they're aborted"
+    )]
+    /// Seconds workers get to finish the messages in flight on shutdown before they're aborted
+    pub stop_timeout: u64,
+
+    #[clap(
+        long = "scheduler-reconcile-interval-ms",
+        env = "SEEKER_SCHEDULER_RECONCILE_INTERVAL_MS",
+        default_value = "60000",
+        help = "Milliseconds between listings of the subscriptions for ones created, updated or deleted"
+    )]
+    /// Milliseconds between listings of the subscriptions for ones created, updated or deleted
+    pub scheduler_reconcile_interval_ms: u64,
 }
 
 impl From<seekr::indexer::IndexerConfig> for IndexerConfig {
     fn from(c: seekr::indexer::IndexerConfig) -> Self {
-        Self { log: c.log }
+        Self {
+            log: c.log,
+            log_format: c.log_format,
+            stores: c.stores.into(),
+            instance: c.instance,
+            stop_timeout: c.stop_timeout.as_secs(),
+            scheduler_reconcile_interval_ms: c.reconcile_interval.as_millis() as u64,
+        }
     }
 }
 
-impl From<IndexerConfig> for seekr::indexer::IndexerConfig {
-    fn from(c: IndexerConfig) -> Self {
-        Self { log: c.log }
+impl IndexerConfig {
+    /// The indexer configuration, recording where each setting came from in
+    /// the arguments the configuration was parsed from.
+    pub fn build(self, matches: &ArgMatches) -> seekr::indexer::IndexerConfig {
+        let settings = SettingsBuilder::new("indexer")
+            .setting("log", &self.log, source(matches, "log"))
+            .setting("log-format", self.log_format, source(matches, "log-format"));
+        let settings = self
+            .stores
+            .record(settings, matches)
+            .setting(
+                "instance",
+                self.instance.as_deref().unwrap_or_default(),
+                source(matches, "instance"),
+            )
+            .setting(
+                "stop-timeout",
+                self.stop_timeout,
+                source(matches, "stop-timeout"),
+            )
+            .setting(
+                "scheduler-reconcile-interval-ms",
+                self.scheduler_reconcile_interval_ms,
+                source(matches, "scheduler-reconcile-interval-ms"),
+            )
+            .build();
+
+        seekr::indexer::IndexerConfig {
+            log: self.log,
+            log_format: self.log_format,
+            stores: self.stores.build(),
+            instance: self.instance,
+            stop_timeout: Duration::from_secs(self.stop_timeout),
+            reconcile_interval: Duration::from_millis(self.scheduler_reconcile_interval_ms),
+            settings,
+        }
     }
 }
@@ -0,0 +1,63 @@
+use clap::{ArgMatches, Args};
+
+use seekr::meilisearch::{MeilisearchConfig, DEFAULT_API_KEY, DEFAULT_URL};
+use seekr::settings::SettingsBuilder;
+
+use super::source;
+
+#[derive(Args, Debug)]
+pub struct MeilisearchArgs {
+    #[clap(
+        long = "meilisearch-url",
+        env = "SEEKR_MEILISEARCH_URL",
+        default_value = DEFAULT_URL,
+        forbid_empty_values = true,
+        help = "Url of the Meilisearch instance the stores are kept in"
+    )]
+    /// Url of the Meilisearch instance the stores are kept in
+    pub meilisearch_url: String,
+
+    #[clap(
+        long = "meilisearch-api-key",
+        env = "SEEKR_MEILISEARCH_API_KEY",
+        default_value = DEFAULT_API_KEY,
+        hide_default_value = true,
+        hide_env_values = true,
+        help = "Key the stores read and write Meilisearch with"
+    )]
+    /// Key the stores read and write Meilisearch with
+    pub meilisearch_api_key: String,
+}
+
+impl From<MeilisearchConfig> for MeilisearchArgs {
+    fn from(c: MeilisearchConfig) -> Self {
+        Self {
+            meilisearch_url: c.url,
+            meilisearch_api_key: c.api_key,
+        }
+    }
+}
+
+impl MeilisearchArgs {
+    /// Record the settings, and where they came from, with the others of a command.
+    pub fn record(&self, settings: SettingsBuilder, matches: &ArgMatches) -> SettingsBuilder {
+        settings
+            .setting(
+                "meilisearch-url",
+                &self.meilisearch_url,
+                source(matches, "meilisearch-url"),
+            )
+            .secret(
+                "meilisearch-api-key",
+                Some(&self.meilisearch_api_key),
+                source(matches, "meilisearch-api-key"),
+            )
+    }
+
+    pub fn build(self) -> MeilisearchConfig {
+        MeilisearchConfig {
+            url: self.meilisearch_url,
+            api_key: self.meilisearch_api_key,
+        }
+    }
+}
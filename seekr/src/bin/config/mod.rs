@@ -1,5 +1,23 @@
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+
+use seekr::settings::Source;
+
 mod indexer;
+mod meilisearch;
 mod server;
+mod store;
 
 pub use indexer::IndexerConfig;
+pub use meilisearch::MeilisearchArgs;
 pub use server::ServerConfig;
+pub use store::StoreArgs;
+
+/// Where the value clap parsed for the argument came from.
+fn source(matches: &ArgMatches, id: &str) -> Source {
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => Source::Flag,
+        Some(ValueSource::EnvVariable) => Source::Env,
+        _ => Source::Default,
+    }
+}
@@ -1,6 +1,12 @@
-use clap::Args;
+use std::time::Duration;
 
-use seekr::logger::Level;
+use clap::{ArgMatches, Args};
+
+use seekr::logger::{Format, Level};
+use seekr::settings::SettingsBuilder;
+use seekr::standby::ServerRole;
+
+use super::{source, StoreArgs};
 
 #[derive(Args, Debug)]
 pub struct ServerConfig {
@@ -16,6 +22,20 @@ pub struct ServerConfig {
     /// The logging level
     pub log: Level,
 
+    #[clap(
+        long = "log-format",
+        env = "SEEKER_LOG_FORMAT",
+        default_value = "text",
+        forbid_empty_values = true,
+        help = "How logs are written, text or one JSON object per line",
+        value_enum
+    )]
+    /// How logs are written, text or one JSON object per line
+    pub log_format: Format,
+
+    #[clap(flatten)]
+    pub stores: StoreArgs,
+
     #[clap(
         long = "host",
         env = "SEEKER_HOST",
@@ -35,24 +55,504 @@ pub struct ServerConfig {
     )]
     /// Port where server will bind to
     pub port: u16,
+
+    #[clap(
+        long = "auth",
+        env = "SEEKER_AUTH",
+        help = "Require an API key on every request"
+    )]
+    /// Require an API key on every request
+    pub auth: bool,
+
+    #[clap(
+        long = "admin-key",
+        env = "SEEKER_ADMIN_KEY",
+        help = "Bootstrap secret with unrestricted access"
+    )]
+    /// Bootstrap secret with unrestricted access
+    pub admin_key: Option<String>,
+
+    #[clap(
+        long = "api-tokens",
+        env = "SEEKR_API_TOKENS",
+        value_delimiter = ',',
+        help = "Named secrets required on every API request, as name:token:role with role read or admin (the default)"
+    )]
+    /// Named secrets required on every API request, as name:token:role with role read or admin (the default)
+    pub api_tokens: Vec<String>,
+
+    #[clap(
+        long = "reveal-token",
+        env = "SEEKER_REVEAL_TOKEN",
+        help = "Admin token reads send to reveal config secrets with ?reveal=true"
+    )]
+    /// Admin token reads send to reveal config secrets with ?reveal=true
+    pub reveal_token: Option<String>,
+
+    #[clap(
+        long = "cors-allowed-origins",
+        env = "SEEKR_CORS_ALLOWED_ORIGINS",
+        value_delimiter = ',',
+        help = "Origins browsers may call the API from, as scheme://host[:port] or * for any"
+    )]
+    /// Origins browsers may call the API from, as scheme://host[:port] or * for any
+    pub cors_allowed_origins: Vec<String>,
+
+    #[clap(
+        long = "cors-allow-credentials",
+        env = "SEEKR_CORS_ALLOW_CREDENTIALS",
+        help = "Let browsers send credentials from the allowed origins, which can't include *"
+    )]
+    /// Let browsers send credentials from the allowed origins, which can't include *
+    pub cors_allow_credentials: bool,
+
+    #[clap(
+        long = "ownership-stale-days",
+        env = "SEEKER_OWNERSHIP_STALE_DAYS",
+        default_value = "90",
+        help = "Days after which an unconfirmed owner is flagged as stale"
+    )]
+    /// Days after which an unconfirmed owner is flagged as stale
+    pub ownership_stale_days: u32,
+
+    #[clap(
+        long = "drain-grace-period",
+        env = "SEEKER_DRAIN_GRACE_PERIOD",
+        default_value = "10",
+        help = "Seconds long-lived connections get to finish on shutdown before they're closed"
+    )]
+    /// Seconds long-lived connections get to finish on shutdown before they're closed
+    pub drain_grace_period: u64,
+
+    #[clap(
+        long = "shutdown-timeout",
+        env = "SEEKR_SHUTDOWN_TIMEOUT",
+        default_value = "30",
+        help = "Seconds shutdown gets to complete before the remaining tasks are aborted"
+    )]
+    /// Seconds shutdown gets to complete before the remaining tasks are aborted
+    pub shutdown_timeout: u64,
+
+    #[clap(
+        long = "live-streams-per-cluster",
+        env = "SEEKER_LIVE_STREAMS_PER_CLUSTER",
+        default_value = "10",
+        help = "How many live message streams each cluster serves at once"
+    )]
+    /// How many live message streams each cluster serves at once
+    pub live_streams_per_cluster: usize,
+
+    #[clap(
+        long = "safe-restart-exit-code",
+        env = "SEEKER_SAFE_RESTART_EXIT_CODE",
+        default_value = "0",
+        help = "The code the process exits with once a safe restart completed"
+    )]
+    /// The code the process exits with once a safe restart completed
+    pub safe_restart_exit_code: i32,
+
+    #[clap(
+        long = "strict-schema",
+        env = "SEEKER_STRICT_SCHEMA",
+        help = "Refuse to start when stored documents don't match the current schemas"
+    )]
+    /// Refuse to start when stored documents don't match the current schemas
+    pub strict_schema: bool,
+
+    #[clap(
+        long = "metadata-poll-budget",
+        env = "SEEKER_METADATA_POLL_BUDGET",
+        default_value = "8",
+        help = "How many cluster metadata polls run at once, a quarter of which is reserved for high priority clusters"
+    )]
+    /// How many cluster metadata polls run at once, a quarter of which is reserved for high priority clusters
+    pub metadata_poll_budget: usize,
+
+    #[clap(
+        long = "log-buffer-size",
+        env = "SEEKER_LOG_BUFFER_SIZE",
+        default_value = "5000",
+        help = "How many of the latest log records are kept searchable at /debug/logs"
+    )]
+    /// How many of the latest log records are kept searchable at /debug/logs
+    pub log_buffer_size: usize,
+
+    #[clap(
+        long = "no-search-cache",
+        env = "SEEKER_NO_SEARCH_CACHE",
+        help = "Search Meilisearch on every request instead of caching repeated searches"
+    )]
+    /// Search Meilisearch on every request instead of caching repeated searches
+    pub no_search_cache: bool,
+
+    #[clap(
+        long = "search-cache-entries",
+        env = "SEEKER_SEARCH_CACHE_ENTRIES",
+        default_value = "1000",
+        help = "How many searches the search cache keeps"
+    )]
+    /// How many searches the search cache keeps
+    pub search_cache_entries: usize,
+
+    #[clap(
+        long = "search-cache-bytes",
+        env = "SEEKER_SEARCH_CACHE_BYTES",
+        default_value = "67108864",
+        help = "How many bytes of results the search cache keeps"
+    )]
+    /// How many bytes of results the search cache keeps
+    pub search_cache_bytes: usize,
+
+    #[clap(
+        long = "role",
+        env = "SEEKER_ROLE",
+        default_value = "primary",
+        help = "Poll Kafka and accept writes (primary), follow --primary-url (standby), or be elected through the primary lease (auto)",
+        value_enum
+    )]
+    /// Poll Kafka and accept writes (primary), follow --primary-url (standby), or be elected through the primary lease (auto)
+    pub role: ServerRole,
+
+    #[clap(
+        long = "primary-url",
+        env = "SEEKER_PRIMARY_URL",
+        help = "The primary a standby syncs its metadata cache from and redirects writes to"
+    )]
+    /// The primary a standby syncs its metadata cache from and redirects writes to
+    pub primary_url: Option<String>,
+
+    #[clap(
+        long = "advertise-url",
+        env = "SEEKER_ADVERTISE_URL",
+        help = "Where other instances reach this one once elected, http://host:port by default"
+    )]
+    /// Where other instances reach this one once elected, http://host:port by default
+    pub advertise_url: Option<String>,
+
+    #[clap(
+        long = "internal-token",
+        env = "SEEKER_INTERNAL_TOKEN",
+        help = "Secret instances authenticate to each other's internal API with"
+    )]
+    /// Secret instances authenticate to each other's internal API with
+    pub internal_token: Option<String>,
+
+    #[clap(
+        long = "deny-lints",
+        env = "SEEKER_DENY_LINTS",
+        value_delimiter = ',',
+        help = "Lint rules whose warnings reject a cluster or subscription, see /lint-rules"
+    )]
+    /// Lint rules whose warnings reject a cluster or subscription, see /lint-rules
+    pub deny_lints: Vec<String>,
+
+    #[clap(
+        long = "redaction-profile",
+        env = "SEEKER_REDACTION_PROFILES",
+        value_delimiter = ';',
+        help = "Named redaction of cluster metadata, as name=category,category"
+    )]
+    /// Named redaction of cluster metadata, as name=category,category
+    pub redaction_profiles: Vec<String>,
+
+    #[clap(
+        long = "sensitive-topic-pattern",
+        env = "SEEKER_SENSITIVE_TOPIC_PATTERNS",
+        value_delimiter = ';',
+        help = "Pattern of topic name segments the topics redaction masks"
+    )]
+    /// Pattern of topic name segments the topics redaction masks
+    pub sensitive_topic_patterns: Vec<String>,
 }
 
 impl From<seekr::server::ServerConfig> for ServerConfig {
     fn from(c: seekr::server::ServerConfig) -> Self {
         Self {
             log: c.log,
+            log_format: c.log_format,
+            stores: c.stores.into(),
             host: c.host,
             port: c.port,
+            auth: c.auth,
+            admin_key: c.admin_key,
+            api_tokens: c.api_tokens,
+            reveal_token: c.reveal_token,
+            cors_allowed_origins: c.cors_allowed_origins,
+            cors_allow_credentials: c.cors_allow_credentials,
+            ownership_stale_days: c.ownership_stale_days,
+            drain_grace_period: c.drain_grace_period.as_secs(),
+            shutdown_timeout: c.shutdown_timeout.as_secs(),
+            live_streams_per_cluster: c.live_streams_per_cluster,
+            safe_restart_exit_code: c.safe_restart_exit_code,
+            strict_schema: c.strict_schema,
+            metadata_poll_budget: c.metadata_poll_budget,
+            log_buffer_size: c.log_buffer_size,
+            no_search_cache: !c.search_cache,
+            search_cache_entries: c.search_cache_entries,
+            search_cache_bytes: c.search_cache_bytes,
+            role: c.role,
+            primary_url: c.primary_url,
+            advertise_url: c.advertise_url,
+            internal_token: c.internal_token,
+            deny_lints: c.deny_lints,
+            redaction_profiles: c.redaction_profiles,
+            sensitive_topic_patterns: c.sensitive_topic_patterns,
         }
     }
 }
 
-impl From<ServerConfig> for seekr::server::ServerConfig {
-    fn from(c: ServerConfig) -> Self {
-        Self {
-            log: c.log,
-            host: c.host,
-            port: c.port,
+impl ServerConfig {
+    /// The server configuration, recording where each setting came from in the
+    /// arguments the configuration was parsed from.
+    pub fn build(self, matches: &ArgMatches) -> seekr::server::ServerConfig {
+        let at = |id| source(matches, id);
+        let settings = SettingsBuilder::new("server")
+            .setting("log", &self.log, at("log"))
+            .setting("log-format", self.log_format, at("log-format"));
+        let settings = self
+            .stores
+            .record(settings, matches)
+            .setting("host", &self.host, at("host"))
+            .setting("port", self.port, at("port"))
+            .setting("auth", self.auth, at("auth"))
+            .secret("admin-key", self.admin_key.as_ref(), at("admin-key"))
+            .secret(
+                "api-tokens",
+                (!self.api_tokens.is_empty()).then_some(()),
+                at("api-tokens"),
+            )
+            .secret(
+                "reveal-token",
+                self.reveal_token.as_ref(),
+                at("reveal-token"),
+            )
+            .setting(
+                "cors-allowed-origins",
+                self.cors_allowed_origins.join(","),
+                at("cors-allowed-origins"),
+            )
+            .setting(
+                "cors-allow-credentials",
+                self.cors_allow_credentials,
+                at("cors-allow-credentials"),
+            )
+            .setting(
+                "ownership-stale-days",
+                self.ownership_stale_days,
+                at("ownership-stale-days"),
+            )
+            .setting(
+                "drain-grace-period",
+                self.drain_grace_period,
+                at("drain-grace-period"),
+            )
+            .setting(
+                "shutdown-timeout",
+                self.shutdown_timeout,
+                at("shutdown-timeout"),
+            )
+            .setting(
+                "live-streams-per-cluster",
+                self.live_streams_per_cluster,
+                at("live-streams-per-cluster"),
+            )
+            .setting(
+                "safe-restart-exit-code",
+                self.safe_restart_exit_code,
+                at("safe-restart-exit-code"),
+            )
+            .setting("strict-schema", self.strict_schema, at("strict-schema"))
+            .setting(
+                "metadata-poll-budget",
+                self.metadata_poll_budget,
+                at("metadata-poll-budget"),
+            )
+            .setting(
+                "log-buffer-size",
+                self.log_buffer_size,
+                at("log-buffer-size"),
+            )
+            .setting(
+                "no-search-cache",
+                self.no_search_cache,
+                at("no-search-cache"),
+            )
+            .setting(
+                "search-cache-entries",
+                self.search_cache_entries,
+                at("search-cache-entries"),
+            )
+            .setting(
+                "search-cache-bytes",
+                self.search_cache_bytes,
+                at("search-cache-bytes"),
+            )
+            .setting("role", self.role, at("role"))
+            .setting(
+                "primary-url",
+                self.primary_url.as_deref().unwrap_or_default(),
+                at("primary-url"),
+            )
+            .setting(
+                "advertise-url",
+                self.advertise_url.as_deref().unwrap_or_default(),
+                at("advertise-url"),
+            )
+            .secret(
+                "internal-token",
+                self.internal_token.as_ref(),
+                at("internal-token"),
+            )
+            .setting("deny-lints", self.deny_lints.join(","), at("deny-lints"))
+            .setting(
+                "redaction-profile",
+                self.redaction_profiles.join(";"),
+                at("redaction-profile"),
+            )
+            .setting(
+                "sensitive-topic-pattern",
+                self.sensitive_topic_patterns.join(";"),
+                at("sensitive-topic-pattern"),
+            )
+            .build();
+
+        seekr::server::ServerConfig {
+            log: self.log,
+            log_format: self.log_format,
+            stores: self.stores.build(),
+            host: self.host,
+            port: self.port,
+            auth: self.auth,
+            admin_key: self.admin_key,
+            api_tokens: self.api_tokens,
+            reveal_token: self.reveal_token,
+            cors_allowed_origins: self.cors_allowed_origins,
+            cors_allow_credentials: self.cors_allow_credentials,
+            ownership_stale_days: self.ownership_stale_days,
+            drain_grace_period: Duration::from_secs(self.drain_grace_period),
+            shutdown_timeout: Duration::from_secs(self.shutdown_timeout),
+            live_streams_per_cluster: self.live_streams_per_cluster,
+            safe_restart_exit_code: self.safe_restart_exit_code,
+            strict_schema: self.strict_schema,
+            metadata_poll_budget: self.metadata_poll_budget,
+            log_buffer_size: self.log_buffer_size,
+            search_cache: !self.no_search_cache,
+            search_cache_entries: self.search_cache_entries,
+            search_cache_bytes: self.search_cache_bytes,
+            role: self.role,
+            primary_url: self.primary_url,
+            advertise_url: self.advertise_url,
+            internal_token: self.internal_token,
+            deny_lints: self.deny_lints,
+            redaction_profiles: self.redaction_profiles,
+            sensitive_topic_patterns: self.sensitive_topic_patterns,
+            settings,
         }
     }
 }
+
+#[test]
+fn it_records_where_each_setting_came_from() {
+    use clap::{CommandFactory, FromArgMatches, Parser};
+
+    use seekr::settings::Source;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        server: ServerConfig,
+    }
+
+    let build = |args: &[&str]| {
+        let matches = Cli::command()
+            .try_get_matches_from(["seekrd"].iter().chain(args))
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.server.build(&matches).settings
+    };
+    let setting = |settings: &seekr::settings::Snapshot, name: &str| {
+        let s = settings.get(name).unwrap();
+        (s.value.clone(), s.source)
+    };
+
+    // The environment is shared by the whole process, so every case runs here.
+    let defaults = build(&[]);
+    assert_eq!(
+        setting(&defaults, "port"),
+        (Some("5000".into()), Source::Default)
+    );
+    assert_eq!(setting(&defaults, "admin-key"), (None, Source::Default));
+    assert_eq!(
+        setting(&defaults, "log-format"),
+        (Some("text".into()), Source::Default)
+    );
+    assert_eq!(
+        setting(&defaults, "meilisearch-url"),
+        (Some("http://localhost:7700".into()), Source::Default)
+    );
+    assert!(defaults
+        .settings
+        .iter()
+        .all(|s| s.source == Source::Default));
+
+    let flags = build(&[
+        "--port",
+        "6000",
+        "--auth",
+        "--admin-key",
+        "seekr_root",
+        "--meilisearch-api-key",
+        "search_secret",
+        "--subscription-store-backend",
+        "cassandra",
+    ]);
+    assert_eq!(setting(&flags, "port"), (Some("6000".into()), Source::Flag));
+    assert_eq!(setting(&flags, "auth"), (Some("true".into()), Source::Flag));
+    assert_eq!(
+        setting(&flags, "admin-key"),
+        (Some("[redacted]".into()), Source::Flag)
+    );
+    assert_eq!(
+        setting(&flags, "meilisearch-api-key"),
+        (Some("[redacted]".into()), Source::Flag)
+    );
+    assert_eq!(
+        setting(&flags, "cluster-store-backend"),
+        (Some("meilisearch".into()), Source::Default)
+    );
+    assert_eq!(
+        setting(&flags, "subscription-store-backend"),
+        (Some("cassandra".into()), Source::Flag)
+    );
+    assert_eq!(
+        setting(&flags, "host"),
+        (Some("localhost".into()), Source::Default)
+    );
+
+    std::env::set_var("SEEKER_PORT", "7000");
+    std::env::set_var("SEEKER_LOG", "debug");
+    let env = build(&[]);
+    assert_eq!(setting(&env, "port"), (Some("7000".into()), Source::Env));
+    assert_eq!(setting(&env, "log"), (Some("debug".into()), Source::Env));
+
+    // Flags take precedence over the environment, which takes precedence over defaults.
+    let layered = build(&["--port", "8000"]);
+    assert_eq!(
+        setting(&layered, "port"),
+        (Some("8000".into()), Source::Flag)
+    );
+    assert_eq!(
+        setting(&layered, "log"),
+        (Some("debug".into()), Source::Env)
+    );
+    assert_eq!(
+        setting(&layered, "drain-grace-period"),
+        (Some("10".into()), Source::Default)
+    );
+    assert_eq!(
+        layered.summary(),
+        "Running server with log=debug (env), port=8000 (flag)"
+    );
+    std::env::remove_var("SEEKER_PORT");
+    std::env::remove_var("SEEKER_LOG");
+}
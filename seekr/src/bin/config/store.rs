@@ -0,0 +1,142 @@
+use clap::{ArgMatches, Args};
+
+use seekr::backend::{CassandraConfig, StoreBackend, StoreConfig, DEFAULT_CASSANDRA_CONTACT_POINT};
+use seekr::id::{IdConfig, MAX_DATACENTER_ID, MAX_WORKER_ID};
+use seekr::settings::SettingsBuilder;
+
+use super::{source, MeilisearchArgs};
+
+#[derive(Args, Debug)]
+pub struct StoreArgs {
+    #[clap(
+        long = "store-backend",
+        env = "SEEKER_STORE_BACKEND",
+        default_value = "meilisearch",
+        forbid_empty_values = true,
+        help = "Backend the clusters and subscriptions are kept in",
+        value_enum
+    )]
+    /// Backend the clusters and subscriptions are kept in
+    pub store_backend: StoreBackend,
+
+    #[clap(
+        long = "cluster-store-backend",
+        env = "SEEKER_CLUSTER_STORE_BACKEND",
+        help = "Backend the clusters are kept in, instead of --store-backend",
+        value_enum
+    )]
+    /// Backend the clusters are kept in, instead of --store-backend
+    pub cluster_store_backend: Option<StoreBackend>,
+
+    #[clap(
+        long = "subscription-store-backend",
+        env = "SEEKER_SUBSCRIPTION_STORE_BACKEND",
+        help = "Backend the subscriptions are kept in, instead of --store-backend",
+        value_enum
+    )]
+    /// Backend the subscriptions are kept in, instead of --store-backend
+    pub subscription_store_backend: Option<StoreBackend>,
+
+    #[clap(
+        long = "cassandra-contact-point",
+        env = "SEEKER_CASSANDRA_CONTACT_POINT",
+        default_value = DEFAULT_CASSANDRA_CONTACT_POINT,
+        forbid_empty_values = true,
+        help = "Host and port of the Cassandra node the cassandra backend connects to"
+    )]
+    /// Host and port of the Cassandra node the cassandra backend connects to
+    pub cassandra_contact_point: String,
+
+    #[clap(
+        long = "worker-id",
+        env = "SEEKR_WORKER_ID",
+        default_value = "0",
+        value_parser = clap::value_parser!(i64).range(0..=MAX_WORKER_ID),
+        help = "Worker the ids are generated as, unique among the instances of a datacenter"
+    )]
+    /// Worker the ids are generated as, unique among the instances of a datacenter
+    pub worker_id: i64,
+
+    #[clap(
+        long = "datacenter-id",
+        env = "SEEKR_DATACENTER_ID",
+        default_value = "0",
+        value_parser = clap::value_parser!(i64).range(0..=MAX_DATACENTER_ID),
+        help = "Datacenter the ids are generated in"
+    )]
+    /// Datacenter the ids are generated in
+    pub datacenter_id: i64,
+
+    #[clap(flatten)]
+    pub meilisearch: MeilisearchArgs,
+}
+
+impl From<StoreConfig> for StoreArgs {
+    fn from(c: StoreConfig) -> Self {
+        Self {
+            store_backend: c.clusters,
+            cluster_store_backend: None,
+            subscription_store_backend: (c.subscriptions != c.clusters).then_some(c.subscriptions),
+            cassandra_contact_point: c.cassandra.contact_point,
+            worker_id: c.ids.worker_id,
+            datacenter_id: c.ids.datacenter_id,
+            meilisearch: c.meilisearch.into(),
+        }
+    }
+}
+
+impl StoreArgs {
+    /// Record the settings, and where they came from, with the others of a command.
+    ///
+    /// The backend of each store is recorded as resolved, coming from its own
+    /// flag when given and from `--store-backend` otherwise.
+    pub fn record(&self, settings: SettingsBuilder, matches: &ArgMatches) -> SettingsBuilder {
+        let resolved = |own: Option<StoreBackend>, id| match own {
+            Some(backend) => (backend, source(matches, id)),
+            None => (self.store_backend, source(matches, "store-backend")),
+        };
+        let (clusters, clusters_source) =
+            resolved(self.cluster_store_backend, "cluster-store-backend");
+        let (subscriptions, subscriptions_source) = resolved(
+            self.subscription_store_backend,
+            "subscription-store-backend",
+        );
+
+        let settings = settings
+            .setting("cluster-store-backend", clusters, clusters_source)
+            .setting(
+                "subscription-store-backend",
+                subscriptions,
+                subscriptions_source,
+            )
+            .setting(
+                "cassandra-contact-point",
+                &self.cassandra_contact_point,
+                source(matches, "cassandra-contact-point"),
+            )
+            .setting("worker-id", self.worker_id, source(matches, "worker-id"))
+            .setting(
+                "datacenter-id",
+                self.datacenter_id,
+                source(matches, "datacenter-id"),
+            );
+        self.meilisearch.record(settings, matches)
+    }
+
+    pub fn build(self) -> StoreConfig {
+        StoreConfig {
+            clusters: self.cluster_store_backend.unwrap_or(self.store_backend),
+            subscriptions: self
+                .subscription_store_backend
+                .unwrap_or(self.store_backend),
+            meilisearch: self.meilisearch.build(),
+            cassandra: CassandraConfig {
+                contact_point: self.cassandra_contact_point,
+            },
+            ids: IdConfig {
+                worker_id: self.worker_id,
+                datacenter_id: self.datacenter_id,
+            },
+        }
+    }
+}
@@ -1,13 +1,12 @@
 mod config;
 
-use clap::Parser;
-use clap::Subcommand;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use log::error;
 
 use seekr::version;
 use seekr::BANNER;
 
-use config::{IndexerConfig, ServerConfig};
+use config::{IndexerConfig, ServerConfig, StoreArgs};
 
 pub const LOG: &str = "seekrd";
 
@@ -27,16 +26,26 @@ struct AppOptions {
 enum Commands {
     Server(ServerConfig),
     Indexer(IndexerConfig),
+    /// Check the stores for entities whose ids collided
+    Doctor(StoreArgs),
     Version,
 }
 
 #[actix_web::main]
 async fn main() {
-    let app = AppOptions::parse();
+    // The matches tell where each setting came from, which the parsed options don't.
+    let matches = AppOptions::command().get_matches();
+    let app = AppOptions::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let args = |name| {
+        matches
+            .subcommand_matches(name)
+            .expect("subcommand matched")
+    };
 
     let output = match app.command {
-        Commands::Server(c) => seekr::server::run(c.into()).await,
-        Commands::Indexer(c) => seekr::indexer::run(c.into()).await,
+        Commands::Server(c) => seekr::server::run(c.build(args("server"))).await,
+        Commands::Indexer(c) => seekr::indexer::run(c.build(args("indexer"))).await,
+        Commands::Doctor(c) => seekr::doctor::run(&c.build()).await,
         Commands::Version => version::init(),
     };
 
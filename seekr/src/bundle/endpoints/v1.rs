@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::{Data, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder, ResponseError};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{RequestAuth, Role};
+use crate::bundle::{Bundler, Section};
+use crate::drain::{ConnectionKind, Drain, ShutdownPhase};
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataManager};
+use crate::logs::{LogBuffer, LogFilter, MAX_LIMIT};
+use crate::settings::RuntimeSettings;
+use crate::sweeper::Sweeper;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_bundle);
+}
+
+#[derive(Deserialize)]
+struct BundleQuery {
+    /// Adds the cached metadata of every cluster, which can be large.
+    #[serde(default)]
+    include_metadata: bool,
+}
+
+#[get("/bundle")]
+#[allow(clippy::too_many_arguments)]
+async fn get_bundle(
+    auth: RequestAuth,
+    query: Query<BundleQuery>,
+    bundler: Data<Bundler>,
+    settings: Option<Data<RuntimeSettings>>,
+    logs: Option<Data<LogBuffer>>,
+    drain: Option<Data<Drain>>,
+    sweeper: Option<Data<Sweeper>>,
+    manager: Option<Data<MetadataManager>>,
+) -> impl Responder {
+    if let Err(e) = auth.require(Role::Admin) {
+        return e.error_response();
+    }
+
+    let mut sections = vec![
+        Section::new("version", async {
+            Ok::<_, AnyError>(VersionSection::current())
+        }),
+        Section::new("config", async move {
+            Ok::<_, AnyError>(available(settings, "settings")?.current())
+        }),
+        Section::new("logs", async move {
+            let filter = LogFilter {
+                limit: MAX_LIMIT,
+                ..Default::default()
+            };
+            Ok::<_, AnyError>(available(logs, "log buffer")?.query(&filter))
+        }),
+        Section::new("connections", async move {
+            let drain = available(drain, "drain")?;
+            Ok::<_, AnyError>(ConnectionsSection {
+                phase: drain.phase(),
+                connections: drain.connections(),
+            })
+        }),
+        Section::new("sweeper", async move {
+            Ok::<_, AnyError>(available(sweeper, "sweeper")?.jobs())
+        }),
+    ];
+    let manager_ = manager.clone();
+    sections.push(Section::new("clusters", async move {
+        Ok::<_, AnyError>(available(manager_, "metadata cache")?.warmup().await)
+    }));
+    if query.include_metadata {
+        sections.push(Section::new("metadata", async move {
+            let manager = available(manager, "metadata cache")?;
+            let mut clusters = vec![];
+            for cluster in manager.warmup().await.clusters {
+                if let Some(entry) = manager.snapshot(cluster.cluster_id).await {
+                    clusters.push(MetadataSection {
+                        cluster_id: cluster.cluster_id,
+                        entry: entry.as_ref().clone(),
+                    });
+                }
+            }
+            Ok::<_, AnyError>(clusters)
+        }));
+    }
+
+    let Some(archive) = bundler.assemble(sections) else {
+        return HttpResponse::TooManyRequests().body("A bundle is already being assembled");
+    };
+
+    info!("Assembling a diagnostic bundle");
+    let filename = format!(
+        "seekr-bundle-{}.tar.gz",
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    HttpResponse::Ok()
+        .content_type("application/gzip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(archive)
+}
+
+/// The data a section reads, which processes without it don't register.
+fn available<T: ?Sized>(data: Option<Data<T>>, what: &str) -> Result<Data<T>, AnyError> {
+    data.ok_or_else(|| format!("no {} on this instance", what).into())
+}
+
+#[derive(Serialize)]
+struct VersionSection {
+    name: &'static str,
+    version: &'static str,
+    rust_version: &'static str,
+    git_version: &'static str,
+    git_branch: &'static str,
+    git_sha: &'static str,
+}
+
+impl VersionSection {
+    fn current() -> Self {
+        Self {
+            name: crate::PKG_NAME,
+            version: crate::PKG_VERS,
+            rust_version: crate::RUST_VERS,
+            git_version: crate::GIT_VERS,
+            git_branch: crate::GIT_BRANCH,
+            git_sha: crate::GIT_SHA,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ConnectionsSection {
+    phase: ShutdownPhase,
+    connections: HashMap<ConnectionKind, usize>,
+}
+
+#[derive(Serialize)]
+struct MetadataSection {
+    cluster_id: ClusterId,
+    entry: CachedMetadataEntry,
+}
+
+#[actix_web::test]
+async fn it_downloads_a_redacted_bundle() {
+    use actix_web::{test, App};
+
+    use crate::settings::{SettingsBuilder, Source};
+
+    let settings = Data::new(RuntimeSettings::new(
+        SettingsBuilder::new("server")
+            .setting("port", 5000, Source::Default)
+            .secret("admin-key", Some("seekr_root_secret"), Source::Flag)
+            .build(),
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Bundler::default()))
+            .app_data(settings)
+            .app_data(Data::new(Sweeper::default()))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/debug/bundle")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+    assert_eq!(res.headers()["content-type"], "application/gzip");
+    let archive = test::read_body(res).await;
+
+    let files = crate::bundle::unpack(&archive);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        [
+            "clusters.error.json",
+            "config.json",
+            "connections.error.json",
+            "logs.error.json",
+            "manifest.json",
+            "sweeper.json",
+            "version.json"
+        ]
+    );
+    let manifest: serde_json::Value = serde_json::from_slice(&files["manifest.json"]).unwrap();
+    assert_eq!(manifest["sections"][0]["section"], "version");
+    assert_eq!(
+        manifest["sections"][2]["error"],
+        "no log buffer on this instance"
+    );
+
+    // Nothing the redaction hides ends up in any file.
+    for (name, data) in &files {
+        let text = String::from_utf8_lossy(data);
+        assert!(
+            !text.contains("seekr_root_secret"),
+            "{} leaks a secret",
+            name
+        );
+    }
+    assert!(String::from_utf8_lossy(&files["config.json"]).contains("[redacted]"));
+}
+
+#[actix_web::test]
+async fn it_refuses_a_second_bundle_while_one_is_streamed() {
+    use actix_web::{test, App};
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(Bundler::default()))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    // The first bundle isn't read yet, so it's still being assembled.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/debug/bundle")
+        .to_request();
+    let first = test::call_service(&app, req).await;
+    assert!(first.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/debug/bundle")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 429);
+
+    test::read_body(first).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/debug/bundle?include_metadata=true")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+}
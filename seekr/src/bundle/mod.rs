@@ -0,0 +1,342 @@
+use std::future::Future;
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::Stream;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::errors::AnyError;
+
+pub mod endpoints;
+
+/// How long a section is collected for, before an error file takes its place.
+pub const DEFAULT_SECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most bytes a section's file holds, the rest is cut off.
+pub const DEFAULT_MAX_SECTION_BYTES: usize = 4 * 1024 * 1024;
+
+type Collect = Pin<Box<dyn Future<Output = Result<Vec<u8>, AnyError>> + Send>>;
+
+/// One file of a bundle, collected while the archive is streamed.
+pub struct Section {
+    pub name: String,
+    collect: Collect,
+}
+
+impl Section {
+    /// A section written as `<name>.json`, `name` may contain `/` to group sections.
+    pub fn new<F, T>(name: impl Into<String>, collect: F) -> Self
+    where
+        F: Future<Output = Result<T, AnyError>> + Send + 'static,
+        T: Serialize + 'static,
+    {
+        Self {
+            name: name.into(),
+            collect: Box::pin(async move {
+                Ok::<_, AnyError>(serde_json::to_vec_pretty(&collect.await?)?)
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionStatus {
+    Ok,
+    Failed,
+    TimedOut,
+}
+
+/// What the manifest records of a section.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    pub section: String,
+    pub file: String,
+    pub status: SectionStatus,
+    pub bytes: usize,
+    /// Whether the file was cut off at the size cap, it's no longer valid JSON then.
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The last file of a bundle, listing the sections before it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Manifest {
+    pub created_at: DateTime<Utc>,
+    pub version: &'static str,
+    pub sections: Vec<ManifestEntry>,
+}
+
+/// Assembles diagnostic bundles, one at a time.
+#[derive(Debug)]
+pub struct Bundler {
+    timeout: Duration,
+    max_section_bytes: usize,
+    busy: Arc<AtomicBool>,
+}
+
+impl Default for Bundler {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_SECTION_TIMEOUT,
+            max_section_bytes: DEFAULT_MAX_SECTION_BYTES,
+            busy: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Bundler {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_max_section_bytes(mut self, max_section_bytes: usize) -> Self {
+        self.max_section_bytes = max_section_bytes;
+        self
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::Acquire)
+    }
+
+    /// Streams the sections as a gzipped tar, followed by the manifest, or
+    /// returns `None` while another bundle is being streamed.
+    ///
+    /// Sections are collected one after the other as the stream is polled,
+    /// so at most one of them is held in memory.
+    pub fn assemble(
+        &self,
+        sections: Vec<Section>,
+    ) -> Option<impl Stream<Item = Result<Bytes, io::Error>>> {
+        let permit = Permit::acquire(&self.busy)?;
+        let assembly = Assembly {
+            sections: sections.into_iter(),
+            archive: tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default())),
+            entries: vec![],
+            created_at: Utc::now(),
+            timeout: self.timeout,
+            max_section_bytes: self.max_section_bytes,
+            _permit: permit,
+        };
+
+        Some(futures::stream::unfold(
+            Some(assembly),
+            |assembly| async move {
+                let mut assembly = assembly?;
+                match assembly.sections.next() {
+                    Some(section) => match assembly.append(section).await {
+                        Ok(bytes) => Some((Ok(bytes), Some(assembly))),
+                        Err(e) => Some((Err(e), None)),
+                    },
+                    None => Some((assembly.finish(), None)),
+                }
+            },
+        ))
+    }
+}
+
+/// Held while a bundle is streamed, the next one can start once it's dropped.
+#[derive(Debug)]
+struct Permit(Arc<AtomicBool>);
+
+impl Permit {
+    fn acquire(busy: &Arc<AtomicBool>) -> Option<Self> {
+        busy.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Self(busy.clone()))
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+struct Assembly {
+    sections: std::vec::IntoIter<Section>,
+    archive: tar::Builder<GzEncoder<Vec<u8>>>,
+    entries: Vec<ManifestEntry>,
+    created_at: DateTime<Utc>,
+    timeout: Duration,
+    max_section_bytes: usize,
+    _permit: Permit,
+}
+
+impl Assembly {
+    /// Collects the section within its budget and returns the compressed
+    /// bytes of its file.
+    async fn append(&mut self, section: Section) -> Result<Bytes, io::Error> {
+        let started = Instant::now();
+        let collected = tokio::time::timeout(self.timeout, section.collect).await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let (status, mut data, error) = match collected {
+            Ok(Ok(data)) => (SectionStatus::Ok, data, None),
+            Ok(Err(e)) => (SectionStatus::Failed, vec![], Some(e.to_string())),
+            Err(_) => (
+                SectionStatus::TimedOut,
+                vec![],
+                Some(format!("timed out after {}ms", self.timeout.as_millis())),
+            ),
+        };
+        let file = match &error {
+            None => format!("{}.json", section.name),
+            Some(error) => {
+                data = serde_json::to_vec_pretty(&serde_json::json!({ "error": error }))?;
+                format!("{}.error.json", section.name)
+            }
+        };
+        let truncated = data.len() > self.max_section_bytes;
+        data.truncate(self.max_section_bytes);
+
+        self.write(&file, &data)?;
+        self.entries.push(ManifestEntry {
+            section: section.name,
+            file,
+            status,
+            bytes: data.len(),
+            truncated,
+            elapsed_ms,
+            error,
+        });
+
+        let encoder = self.archive.get_mut();
+        encoder.flush()?;
+        Ok(Bytes::from(std::mem::take(encoder.get_mut())))
+    }
+
+    /// Writes the manifest and the end of the archive.
+    fn finish(mut self) -> Result<Bytes, io::Error> {
+        let manifest = Manifest {
+            created_at: self.created_at,
+            version: crate::PKG_VERS,
+            sections: std::mem::take(&mut self.entries),
+        };
+        self.write("manifest.json", &serde_json::to_vec_pretty(&manifest)?)?;
+        Ok(Bytes::from(self.archive.into_inner()?.finish()?))
+    }
+
+    fn write(&mut self, file: &str, data: &[u8]) -> Result<(), io::Error> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(self.created_at.timestamp().max(0) as u64);
+        self.archive.append_data(&mut header, file, data)
+    }
+}
+
+/// The files of a bundle by name, for tests to look into.
+#[cfg(test)]
+pub fn unpack(archive: &[u8]) -> std::collections::BTreeMap<String, Vec<u8>> {
+    use std::io::Read;
+
+    let mut files = std::collections::BTreeMap::new();
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().to_string();
+        let mut data = vec![];
+        entry.read_to_end(&mut data).unwrap();
+        files.insert(name, data);
+    }
+    files
+}
+
+#[cfg(test)]
+async fn collect(stream: impl Stream<Item = Result<Bytes, io::Error>>) -> Vec<u8> {
+    use futures::TryStreamExt;
+
+    stream
+        .try_fold(vec![], |mut all, bytes| async move {
+            all.extend_from_slice(&bytes);
+            Ok(all)
+        })
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn it_archives_sections_with_a_manifest() {
+    let bundler = Bundler::default().with_max_section_bytes(64);
+    let sections = vec![
+        Section::new("version", async {
+            Ok::<_, AnyError>(serde_json::json!({ "version": "1" }))
+        }),
+        Section::new("metadata/1", async {
+            Ok::<_, AnyError>(vec!["topic"; 100])
+        }),
+        Section::new("sweeper", async {
+            Err::<(), AnyError>("sweeper unavailable".into())
+        }),
+    ];
+    let archive = collect(bundler.assemble(sections).unwrap()).await;
+    assert!(!bundler.is_busy());
+
+    let files = unpack(&archive);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        [
+            "manifest.json",
+            "metadata/1.json",
+            "sweeper.error.json",
+            "version.json"
+        ]
+    );
+    let version: serde_json::Value = serde_json::from_slice(&files["version.json"]).unwrap();
+    assert_eq!(version["version"], "1");
+    assert_eq!(files["metadata/1.json"].len(), 64);
+
+    let manifest: serde_json::Value = serde_json::from_slice(&files["manifest.json"]).unwrap();
+    let sections = manifest["sections"].as_array().unwrap();
+    assert_eq!(sections.len(), 3);
+    assert_eq!(sections[0]["status"], "ok");
+    assert_eq!(sections[0]["truncated"], false);
+    assert_eq!(sections[1]["truncated"], true);
+    assert_eq!(sections[2]["status"], "failed");
+    assert_eq!(sections[2]["error"], "sweeper unavailable");
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_replaces_hung_sections_with_an_error_file() {
+    let bundler = Bundler::default().with_timeout(Duration::from_secs(1));
+    let sections = vec![
+        Section::new("clusters", async {
+            futures::future::pending::<()>().await;
+            Ok::<_, AnyError>(())
+        }),
+        Section::new("version", async { Ok::<_, AnyError>("1") }),
+    ];
+    let files = unpack(&collect(bundler.assemble(sections).unwrap()).await);
+
+    let error: serde_json::Value = serde_json::from_slice(&files["clusters.error.json"]).unwrap();
+    assert_eq!(error["error"], "timed out after 1000ms");
+    assert!(files.contains_key("version.json"));
+
+    let manifest: serde_json::Value = serde_json::from_slice(&files["manifest.json"]).unwrap();
+    assert_eq!(manifest["sections"][0]["status"], "timed_out");
+    assert_eq!(manifest["sections"][0]["file"], "clusters.error.json");
+}
+
+#[tokio::test]
+async fn it_assembles_one_bundle_at_a_time() {
+    let bundler = Bundler::default();
+    let first = bundler.assemble(vec![]).unwrap();
+    assert!(bundler.is_busy());
+    assert!(bundler.assemble(vec![]).is_none());
+
+    drop(first);
+    assert!(!bundler.is_busy());
+    assert!(bundler.assemble(vec![]).is_some());
+}
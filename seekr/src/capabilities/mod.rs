@@ -0,0 +1,440 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use meilisearch_sdk::Client;
+
+use crate::clusters::health::{self, HealthStatus};
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::classify::Include;
+use crate::kafka::metadata::manager::MetadataManager;
+
+pub use seekr_api_types::admin::{
+    Capabilities, Capability, CapabilityHealth, CapabilityStatus, ClusterCapability, Dependency,
+};
+
+/// How long the probes get, together, before the dependencies they didn't
+/// hear from count as down.
+pub const PROBE_BUDGET: Duration = Duration::from_secs(2);
+
+/// How a capability suffers from one of its dependencies being down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Impact {
+    /// The capability is unavailable without the dependency.
+    Requires,
+
+    /// The capability still works without the dependency, though not as well,
+    /// e.g. reads served from a cache that's no longer refreshed.
+    Degrades,
+}
+
+/// What each capability depends on.
+///
+/// Capabilities depending on Kafka are scoped to clusters, the Kafka of each
+/// cluster only feeding the capability's status for that cluster.
+pub const GRAPH: &[(Capability, &[(Dependency, Impact)])] = &[
+    (
+        Capability::ClusterCrud,
+        &[(Dependency::Meilisearch, Impact::Requires)],
+    ),
+    (
+        Capability::SubscriptionCrud,
+        &[(Dependency::Meilisearch, Impact::Requires)],
+    ),
+    (
+        Capability::MetadataRead,
+        &[(Dependency::Kafka, Impact::Degrades)],
+    ),
+    (
+        Capability::Search,
+        &[(Dependency::Meilisearch, Impact::Requires)],
+    ),
+    (
+        Capability::Indexing,
+        &[
+            (Dependency::Meilisearch, Impact::Requires),
+            (Dependency::Kafka, Impact::Requires),
+        ],
+    ),
+];
+
+/// The health of a dependency, from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DependencyStatus {
+    Up,
+    Degraded,
+    Down,
+}
+
+/// What the probes found about each dependency.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Probes {
+    pub meilisearch: DependencyStatus,
+    pub kafka: BTreeMap<ClusterId, DependencyStatus>,
+}
+
+impl Impact {
+    fn status(self, dependency: DependencyStatus) -> CapabilityStatus {
+        match (self, dependency) {
+            (_, DependencyStatus::Up) => CapabilityStatus::Ok,
+            (Impact::Requires, DependencyStatus::Down) => CapabilityStatus::Unavailable,
+            _ => CapabilityStatus::Degraded,
+        }
+    }
+}
+
+/// The worst of the statuses, along with the first dependency limiting to it.
+fn worst(
+    statuses: impl IntoIterator<Item = (CapabilityStatus, Dependency)>,
+) -> (CapabilityStatus, Option<Dependency>) {
+    statuses.into_iter().fold(
+        (CapabilityStatus::Ok, None),
+        |(status, by), (s, d)| match s > status {
+            true => (s, Some(d)),
+            false => (status, by),
+        },
+    )
+}
+
+/// The health of every capability of the graph, given the probes.
+pub fn evaluate(probes: &Probes) -> Capabilities {
+    GRAPH
+        .iter()
+        .map(|(capability, dependencies)| (*capability, evaluate_one(dependencies, probes)))
+        .collect()
+}
+
+fn evaluate_one(dependencies: &[(Dependency, Impact)], probes: &Probes) -> CapabilityHealth {
+    // The dependencies shared by every cluster, Kafka is only known per cluster.
+    let global = worst(dependencies.iter().filter_map(|(d, impact)| match d {
+        Dependency::Meilisearch => Some((impact.status(probes.meilisearch), *d)),
+        _ => None,
+    }));
+    let Some((_, kafka)) = dependencies.iter().find(|(d, _)| *d == Dependency::Kafka) else {
+        return CapabilityHealth {
+            status: global.0,
+            limited_by: global.1,
+            clusters: vec![],
+        };
+    };
+
+    let clusters = probes
+        .kafka
+        .iter()
+        .map(|(id, status)| {
+            let own = (kafka.status(*status), Dependency::Kafka);
+            let (status, limited_by) =
+                worst(global.1.map(|d| (global.0, d)).into_iter().chain([own]));
+            ClusterCapability {
+                cluster_id: *id,
+                status,
+                limited_by,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Clusters impaired by their own Kafka only make the capability
+    // unavailable as a whole once none of them is left.
+    let by_clusters = match clusters.iter().map(|c| c.status).max() {
+        None | Some(CapabilityStatus::Ok) => CapabilityStatus::Ok,
+        _ if clusters
+            .iter()
+            .all(|c| c.status == CapabilityStatus::Unavailable) =>
+        {
+            CapabilityStatus::Unavailable
+        }
+        _ => CapabilityStatus::Degraded,
+    };
+    let (status, limited_by) = match by_clusters > global.0 {
+        true => (by_clusters, Some(Dependency::Kafka)),
+        false => global,
+    };
+
+    CapabilityHealth {
+        status,
+        limited_by,
+        clusters,
+    }
+}
+
+/// Checks a dependency, failing when it's unhealthy.
+pub type HealthCheck = Arc<dyn Fn() -> BoxFuture<'static, Result<(), AnyError>> + Send + Sync>;
+
+/// Collects the probes of every dependency.
+pub struct Prober {
+    meilisearch: HealthCheck,
+    metadata: Arc<MetadataManager>,
+    budget: Duration,
+}
+
+impl Prober {
+    pub fn new(client: Arc<Client>, metadata: Arc<MetadataManager>) -> Self {
+        Self {
+            meilisearch: Arc::new(move || {
+                let client = client.clone();
+                async move {
+                    client.health().await?;
+                    Ok::<_, AnyError>(())
+                }
+                .boxed()
+            }),
+            metadata,
+            budget: PROBE_BUDGET,
+        }
+    }
+
+    /// Check Meilisearch with `check` instead of its health endpoint.
+    pub fn with_meilisearch(mut self, check: HealthCheck) -> Self {
+        self.meilisearch = check;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Run the probes concurrently, within the budget.
+    pub async fn probe(&self) -> Probes {
+        let meilisearch = async {
+            match tokio::time::timeout(self.budget, (self.meilisearch)()).await {
+                Ok(Ok(())) => DependencyStatus::Up,
+                Ok(Err(e)) => {
+                    debug!("Meilisearch health check failed: {}", e);
+                    DependencyStatus::Down
+                }
+                Err(_) => {
+                    debug!("Meilisearch health check timed out after {:?}", self.budget);
+                    DependencyStatus::Down
+                }
+            }
+        };
+        let kafka = tokio::time::timeout(self.budget, self.kafka());
+
+        let (meilisearch, kafka) = tokio::join!(meilisearch, kafka);
+        Probes {
+            meilisearch,
+            // Reading the cache only outlasts the budget while it's contended, leaving no cluster known.
+            kafka: kafka.unwrap_or_default(),
+        }
+    }
+
+    /// The Kafka of each cluster, by the health of its cached metadata.
+    async fn kafka(&self) -> BTreeMap<ClusterId, DependencyStatus> {
+        let caches = self.metadata.cache_infos().await;
+        self.metadata
+            .snapshots()
+            .await
+            .into_iter()
+            .map(|(id, entry)| {
+                let health =
+                    health::summarize(&entry, None, Include::default()).with_cache(caches.get(&id));
+                let status = match health.status {
+                    HealthStatus::Healthy | HealthStatus::Degraded => DependencyStatus::Up,
+                    HealthStatus::Unhealthy if !health.internal.under_replicated.is_empty() => {
+                        DependencyStatus::Degraded
+                    }
+                    HealthStatus::Unhealthy => DependencyStatus::Down,
+                    // Not polled yet, nothing is known of the cluster.
+                    HealthStatus::Unknown => DependencyStatus::Degraded,
+                };
+                (id, status)
+            })
+            .collect()
+    }
+}
+
+/// Whether any capability isn't fully working, which readiness reports as degraded.
+pub fn impaired(capabilities: &Capabilities) -> bool {
+    capabilities
+        .values()
+        .any(|c| c.status != CapabilityStatus::Ok)
+}
+
+#[cfg(test)]
+fn statuses(
+    capabilities: &Capabilities,
+) -> Vec<(Capability, CapabilityStatus, Option<Dependency>)> {
+    capabilities
+        .iter()
+        .map(|(c, h)| (*c, h.status, h.limited_by))
+        .collect()
+}
+
+#[test]
+fn it_cascades_dependency_failures_to_capabilities() {
+    use CapabilityStatus::{Degraded, Ok, Unavailable};
+    use Dependency::{Kafka, Meilisearch};
+    use DependencyStatus::{Down, Up};
+
+    let kafka = |clusters: &[(i64, DependencyStatus)]| {
+        clusters
+            .iter()
+            .map(|(id, s)| (ClusterId(*id), *s))
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    #[allow(clippy::type_complexity)]
+    let cases: &[(
+        &str,
+        Probes,
+        &[(Capability, CapabilityStatus, Option<Dependency>)],
+    )] = &[
+        (
+            "everything up",
+            Probes {
+                meilisearch: Up,
+                kafka: kafka(&[(1, Up), (2, Up)]),
+            },
+            &[
+                (Capability::ClusterCrud, Ok, None),
+                (Capability::SubscriptionCrud, Ok, None),
+                (Capability::MetadataRead, Ok, None),
+                (Capability::Search, Ok, None),
+                (Capability::Indexing, Ok, None),
+            ],
+        ),
+        (
+            "meilisearch down, kafka still polled",
+            Probes {
+                meilisearch: Down,
+                kafka: kafka(&[(1, Up), (2, Up)]),
+            },
+            &[
+                (Capability::ClusterCrud, Unavailable, Some(Meilisearch)),
+                (Capability::SubscriptionCrud, Unavailable, Some(Meilisearch)),
+                (Capability::MetadataRead, Ok, None),
+                (Capability::Search, Unavailable, Some(Meilisearch)),
+                (Capability::Indexing, Unavailable, Some(Meilisearch)),
+            ],
+        ),
+        (
+            "one cluster's kafka down",
+            Probes {
+                meilisearch: Up,
+                kafka: kafka(&[(1, Down), (2, Up)]),
+            },
+            &[
+                (Capability::ClusterCrud, Ok, None),
+                (Capability::SubscriptionCrud, Ok, None),
+                (Capability::MetadataRead, Degraded, Some(Kafka)),
+                (Capability::Search, Ok, None),
+                (Capability::Indexing, Degraded, Some(Kafka)),
+            ],
+        ),
+        (
+            "everything down",
+            Probes {
+                meilisearch: Down,
+                kafka: kafka(&[(1, Down), (2, Down)]),
+            },
+            &[
+                (Capability::ClusterCrud, Unavailable, Some(Meilisearch)),
+                (Capability::SubscriptionCrud, Unavailable, Some(Meilisearch)),
+                (Capability::MetadataRead, Degraded, Some(Kafka)),
+                (Capability::Search, Unavailable, Some(Meilisearch)),
+                (Capability::Indexing, Unavailable, Some(Meilisearch)),
+            ],
+        ),
+        (
+            "every cluster's kafka down",
+            Probes {
+                meilisearch: Up,
+                kafka: kafka(&[(1, Down), (2, Down)]),
+            },
+            &[
+                (Capability::ClusterCrud, Ok, None),
+                (Capability::SubscriptionCrud, Ok, None),
+                (Capability::MetadataRead, Degraded, Some(Kafka)),
+                (Capability::Search, Ok, None),
+                (Capability::Indexing, Unavailable, Some(Kafka)),
+            ],
+        ),
+    ];
+
+    for (name, probes, expected) in cases {
+        assert_eq!(statuses(&evaluate(probes)), *expected, "{}", name);
+    }
+}
+
+#[test]
+fn it_scopes_kafka_health_to_its_cluster() {
+    use CapabilityStatus::{Degraded, Ok, Unavailable};
+    use DependencyStatus::{Down, Up};
+
+    let probes = Probes {
+        meilisearch: Up,
+        kafka: BTreeMap::from([
+            (ClusterId(1), Down),
+            (ClusterId(2), Up),
+            (ClusterId(3), DependencyStatus::Degraded),
+        ]),
+    };
+    let capabilities = evaluate(&probes);
+
+    let clusters = |c: Capability| {
+        capabilities[&c]
+            .clusters
+            .iter()
+            .map(|c| (c.cluster_id.as_i64(), c.status, c.limited_by))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        clusters(Capability::Indexing),
+        [
+            (1, Unavailable, Some(Dependency::Kafka)),
+            (2, Ok, None),
+            (3, Degraded, Some(Dependency::Kafka)),
+        ]
+    );
+    assert_eq!(
+        clusters(Capability::MetadataRead),
+        [
+            (1, Degraded, Some(Dependency::Kafka)),
+            (2, Ok, None),
+            (3, Degraded, Some(Dependency::Kafka)),
+        ]
+    );
+    assert!(capabilities[&Capability::Search].clusters.is_empty());
+
+    // Meilisearch being down limits every cluster, ahead of their own Kafka.
+    let capabilities = evaluate(&Probes {
+        meilisearch: Down,
+        ..probes
+    });
+    assert!(capabilities[&Capability::Indexing]
+        .clusters
+        .iter()
+        .all(|c| c.status == Unavailable && c.limited_by == Some(Dependency::Meilisearch)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_bounds_the_time_probes_take() {
+    use tokio::time::Instant;
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers".into()));
+    let manager = Arc::new(MetadataManager::with_factory(
+        Arc::new(MemoryClusterStore::default()),
+        factory,
+    ));
+    let hanging: HealthCheck = Arc::new(|| futures::future::pending().boxed());
+    let client = crate::meilisearch::MeilisearchConfig::default().client();
+    let prober = Prober::new(client, manager)
+        .with_meilisearch(hanging)
+        .with_budget(Duration::from_millis(500));
+
+    let started = Instant::now();
+    let probes = prober.probe().await;
+    assert_eq!(started.elapsed(), Duration::from_millis(500));
+    assert_eq!(probes.meilisearch, DependencyStatus::Down);
+
+    let failing: HealthCheck = Arc::new(|| async { Err("unreachable".into()) }.boxed());
+    let probes = prober.with_meilisearch(failing).probe().await;
+    assert_eq!(probes.meilisearch, DependencyStatus::Down);
+}
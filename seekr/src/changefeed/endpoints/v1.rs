@@ -0,0 +1,332 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::changefeed::cursor::Cursor;
+use crate::changefeed::store::ChangefeedStore;
+use crate::changefeed::{self, stream, FeedError};
+use crate::drain::{ConnectionKind, Drain};
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::config;
+use crate::subscriptions::store::SubscriptionStore;
+
+/// Default number of records returned per page.
+const DEFAULT_LIMIT: usize = 100;
+
+/// Maximum number of records returned per page.
+const MAX_LIMIT: usize = 1_000;
+
+/// Maximum time a read waits for records to arrive.
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_changefeed).service(stream_changefeed);
+}
+
+#[get("/{cluster_id}/{id}/changefeed")]
+async fn get_changefeed(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<ChangefeedQuery>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    store: Data<Arc<dyn ChangefeedStore + Send + Sync>>,
+    drain: Data<Drain>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Reading changefeed of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    let cursor = match open(ss.as_ref().as_ref(), cluster_id, id, &query).await {
+        Ok(cursor) => cursor,
+        Err(res) => return res,
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let store = store.get_ref().clone();
+
+    let result = match query.wait_ms {
+        Some(ms) if ms > 0 => {
+            let wait = Duration::from_millis(ms).min(MAX_WAIT);
+            let mut connection = drain.connect(ConnectionKind::LongPoll);
+            changefeed::wait(store, id, cursor, limit, wait, &mut connection).await
+        }
+        _ => changefeed::read(store, id, cursor, limit).await,
+    };
+
+    match result {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(FeedError::CursorExpired) => HttpResponse::Gone().json(CursorExpiredResponse {
+            code: "cursor_expired",
+            message: "records after the cursor have been truncated, restart from an empty cursor",
+        }),
+        Err(FeedError::Store(e)) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{cluster_id}/{id}/changefeed/stream")]
+async fn stream_changefeed(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<ChangefeedQuery>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    store: Data<Arc<dyn ChangefeedStore + Send + Sync>>,
+    drain: Data<Drain>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Streaming changefeed of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    let cursor = match open(ss.as_ref().as_ref(), cluster_id, id, &query).await {
+        Ok(cursor) => cursor,
+        Err(res) => return res,
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let connection = drain.connect(ConnectionKind::Sse);
+    let events = stream::events(store.get_ref().clone(), id, cursor, limit, connection);
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events)
+}
+
+/// The cursor to read the subscription's changefeed from, or the response
+/// when it can't be read.
+async fn open(
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    query: &ChangefeedQuery,
+) -> Result<Cursor, HttpResponse> {
+    let subscription = match ss.get(cluster_id, id).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return Err(HttpResponse::NotFound().finish()),
+        Err(e) => return Err(HttpResponse::InternalServerError().body(e.to_string())),
+    };
+
+    let enabled = subscription
+        .config
+        .get(config::CHANGEFEED_ENABLED)
+        .is_some_and(|v| v == "true");
+    if !enabled {
+        return Err(HttpResponse::NotFound().body(format!(
+            "Changefeed is not enabled for subscription '{}'",
+            id
+        )));
+    }
+
+    match query.cursor.as_deref() {
+        None | Some("") => Ok(Cursor::default()),
+        Some(c) => Cursor::decode(c).map_err(|e| HttpResponse::BadRequest().body(e)),
+    }
+}
+
+#[derive(Deserialize)]
+struct ChangefeedQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
+
+    /// How long to wait for records when there are none yet.
+    wait_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct CursorExpiredResponse {
+    code: &'static str,
+    message: &'static str,
+}
+
+#[cfg(test)]
+struct Fixture {
+    ss: Arc<dyn SubscriptionStore + Send + Sync>,
+    store: Arc<dyn ChangefeedStore + Send + Sync>,
+    drain: Data<Drain>,
+}
+
+#[cfg(test)]
+impl Fixture {
+    /// Subscription 1 of cluster 1, with one record in its changefeed.
+    async fn new() -> Self {
+        use std::collections::HashMap;
+
+        use crate::subscriptions::store::MemorySubscriptionStore;
+        use crate::subscriptions::subscription::Subscription;
+
+        let ss = Arc::new(MemorySubscriptionStore::default());
+        let config = HashMap::from([(config::CHANGEFEED_ENABLED.to_string(), "true".to_string())]);
+        let subscription = Subscription::new(
+            Some(SubscriptionId(1)),
+            ClusterId(1),
+            "orders".to_string(),
+            config,
+        );
+        ss.put(subscription).await.unwrap();
+
+        Self {
+            ss,
+            store: changefeed::feed_with(&[(0, 1, 0)]).await,
+            drain: Data::new(Drain::new(GRACE)),
+        }
+    }
+
+    async fn call(&self, uri: &str) -> actix_web::dev::ServiceResponse<actix_web::body::BoxBody> {
+        use actix_web::{test, App};
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(self.ss.clone()))
+                .app_data(Data::new(self.store.clone()))
+                .app_data(self.drain.clone())
+                .configure(crate::server::routes),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri(uri).to_request();
+        test::call_service(&app, req).await.map_into_boxed_body()
+    }
+
+    /// Start draining in the background.
+    fn shutdown(&self) -> tokio::task::JoinHandle<bool> {
+        let drain = self.drain.clone();
+        tokio::spawn(async move { drain.shutdown().await })
+    }
+}
+
+#[cfg(test)]
+const GRACE: Duration = Duration::from_secs(10);
+
+#[cfg(test)]
+const STREAM: &str = "/api/v1/subscriptions/1/1/changefeed/stream";
+
+/// The next chunk of a streamed body, `None` once the stream ended.
+#[cfg(test)]
+pub(crate) async fn next_chunk(body: &mut actix_web::body::BoxBody) -> Option<String> {
+    use actix_web::body::MessageBody;
+
+    let chunk = std::future::poll_fn(|cx| std::pin::Pin::new(&mut *body).poll_next(cx)).await?;
+    Some(String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+}
+
+#[actix_web::test]
+async fn it_ends_streams_with_a_shutdown_event() {
+    tokio::time::pause();
+    let f = Fixture::new().await;
+
+    let res = f.call(STREAM).await;
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = res.into_body();
+
+    let records = next_chunk(&mut body).await.unwrap();
+    assert!(records.starts_with("event: records\ndata: "));
+    assert_eq!(f.drain.connections()[&ConnectionKind::Sse], 1);
+
+    let started = tokio::time::Instant::now();
+    let shutdown = f.shutdown();
+
+    let event = next_chunk(&mut body).await.unwrap();
+    let data = event
+        .strip_prefix("event: server_shutting_down\ndata: ")
+        .unwrap();
+    let data: serde_json::Value = serde_json::from_str(data.trim()).unwrap();
+    let cursor = Cursor::decode(data["next_cursor"].as_str().unwrap()).unwrap();
+    assert_eq!(
+        cursor.positions()[&0],
+        1,
+        "the cursor resumes after the last record"
+    );
+
+    // The client reconnects elsewhere, which lets the server exit right away.
+    drop(body);
+    assert!(!shutdown.await.unwrap());
+    assert!(started.elapsed() < GRACE);
+    assert_eq!(f.drain.connections()[&ConnectionKind::Sse], 0);
+}
+
+#[actix_web::test]
+async fn it_completes_short_requests_while_draining() {
+    tokio::time::pause();
+    let f = Fixture::new().await;
+
+    let mut body = f.call(STREAM).await.into_body();
+    next_chunk(&mut body).await.unwrap();
+    let shutdown = f.shutdown();
+    next_chunk(&mut body).await.unwrap();
+
+    let res = f.call("/api/v1/subscriptions/1/1/changefeed").await;
+    assert_eq!(res.status(), actix_web::http::StatusCode::OK);
+    let page: serde_json::Value = actix_web::test::read_body_json(res).await;
+    assert_eq!(page["records"].as_array().unwrap().len(), 1);
+
+    // Long-polls return their current watermark instead of waiting.
+    let started = tokio::time::Instant::now();
+    let cursor = page["next_cursor"].as_str().unwrap();
+    let uri = format!(
+        "/api/v1/subscriptions/1/1/changefeed?cursor={}&wait_ms=30000",
+        cursor
+    );
+    let page: serde_json::Value = actix_web::test::read_body_json(f.call(&uri).await).await;
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(page["records"].as_array().unwrap().is_empty());
+    assert_eq!(page["next_cursor"], cursor);
+
+    let res = f.call("/api/v1/debug/connections").await;
+    let connections: serde_json::Value = actix_web::test::read_body_json(res).await;
+    assert_eq!(connections["phase"], "draining");
+    assert_eq!(connections["connections"]["sse"], 1);
+    assert_eq!(connections["connections"]["long_poll"], 0);
+
+    drop(body);
+    assert!(!shutdown.await.unwrap());
+}
+
+#[actix_web::test]
+async fn it_waits_for_records_until_the_long_poll_expires() {
+    tokio::time::pause();
+    let f = Fixture::new().await;
+
+    let cursor = {
+        let mut cursor = Cursor::default();
+        cursor.advance(&f.store.read(SubscriptionId(1), &cursor, 1).await.unwrap());
+        cursor.encode()
+    };
+    let uri = format!(
+        "/api/v1/subscriptions/1/1/changefeed?cursor={}&wait_ms=2000",
+        cursor
+    );
+
+    let started = tokio::time::Instant::now();
+    let page: serde_json::Value = actix_web::test::read_body_json(f.call(&uri).await).await;
+    assert!(page["records"].as_array().unwrap().is_empty());
+    assert!(started.elapsed() >= Duration::from_secs(2));
+}
+
+#[actix_web::test]
+async fn it_closes_streams_that_ignore_the_shutdown_event() {
+    tokio::time::pause();
+    let f = Fixture::new().await;
+
+    let mut body = f.call(STREAM).await.into_body();
+    next_chunk(&mut body).await.unwrap();
+
+    let started = tokio::time::Instant::now();
+    let shutdown = f.shutdown();
+    let event = next_chunk(&mut body).await.unwrap();
+    assert!(event.starts_with("event: server_shutting_down"));
+
+    // The client keeps the stream open, so it's closed after the grace period.
+    assert_eq!(next_chunk(&mut body).await, None);
+    assert!(started.elapsed() >= GRACE);
+    assert!(started.elapsed() < GRACE + Duration::from_secs(1));
+    assert!(shutdown.await.unwrap(), "the stream had to be closed");
+    assert_eq!(f.drain.phase(), crate::drain::ShutdownPhase::Closed);
+}
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::drain::Connection;
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+
+use self::cursor::Cursor;
+use self::record::ChangeRecord;
+use self::store::ChangefeedStore;
+
+pub mod cursor;
+pub mod endpoints;
+pub mod record;
+pub mod store;
+pub mod stream;
+
+/// How often waiting readers check for new records.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize)]
+pub struct FeedPage {
+    pub records: Vec<ChangeRecord>,
+    pub next_cursor: String,
+    pub has_more: bool,
+}
+
+#[derive(Debug)]
+pub enum FeedError {
+    /// Records after the cursor were truncated before they could be read.
+    CursorExpired,
+    Store(AnyError),
+}
+
+impl From<AnyError> for FeedError {
+    fn from(e: AnyError) -> Self {
+        FeedError::Store(e)
+    }
+}
+
+/// Read the next page of a subscription's change feed.
+pub async fn read(
+    store: Arc<dyn ChangefeedStore + Send + Sync>,
+    id: SubscriptionId,
+    cursor: Cursor,
+    limit: usize,
+) -> Result<FeedPage, FeedError> {
+    let truncated = store.truncated(id).await?;
+    if cursor.is_expired(&truncated) {
+        return Err(FeedError::CursorExpired);
+    }
+
+    // Fetch one extra record to learn whether another page follows.
+    let mut records = store.read(id, &cursor, limit + 1).await?;
+    let has_more = records.len() > limit;
+    records.truncate(limit);
+
+    let mut next = cursor;
+    next.advance(&records);
+
+    Ok(FeedPage {
+        records,
+        next_cursor: next.encode(),
+        has_more,
+    })
+}
+
+/// Read the next page, waiting up to `wait` for records to arrive.
+///
+/// An empty page, whose cursor is the current watermark, is returned when the
+/// wait expires or as soon as the server starts draining.
+pub async fn wait(
+    store: Arc<dyn ChangefeedStore + Send + Sync>,
+    id: SubscriptionId,
+    cursor: Cursor,
+    limit: usize,
+    wait: Duration,
+    connection: &mut Connection,
+) -> Result<FeedPage, FeedError> {
+    let deadline = Instant::now() + wait;
+
+    loop {
+        let page = read(store.clone(), id, cursor.clone(), limit).await?;
+        let now = Instant::now();
+        if !page.records.is_empty() || now >= deadline {
+            return Ok(page);
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL.min(deadline - now)) => {}
+            _ = connection.draining() => return Ok(page),
+        }
+    }
+}
+
+#[cfg(test)]
+async fn feed_with(records: &[(i32, i64, i64)]) -> Arc<dyn ChangefeedStore + Send + Sync> {
+    use crate::kafka::streams::StreamsMessage;
+
+    let records = records
+        .iter()
+        .map(|&(partition, offset, ts)| {
+            let message = StreamsMessage {
+                payload: Some(format!("{}-{}", partition, offset)),
+                payload_json: None,
+                topic: "orders".to_string(),
+                key: None,
+                headers: Default::default(),
+                partition,
+                offset,
+                timestamp: None,
+            };
+            ChangeRecord {
+                seekr_ts: ts,
+                ..ChangeRecord::from_message(SubscriptionId(1), &message, false)
+            }
+        })
+        .collect();
+
+    let store = store::MemoryChangefeedStore::default();
+    store.append(records).await.unwrap();
+    Arc::new(store)
+}
+
+#[cfg(test)]
+fn coordinates(page: &FeedPage) -> Vec<(i32, i64)> {
+    page.records
+        .iter()
+        .map(|r| (r.partition, r.offset))
+        .collect()
+}
+
+#[tokio::test]
+async fn it_orders_records_by_partition_and_offset() {
+    let store = feed_with(&[(1, 5, 0), (0, 9, 0), (1, 2, 0), (0, 3, 0)]).await;
+
+    let page = read(store, SubscriptionId(1), Cursor::default(), 10)
+        .await
+        .unwrap();
+
+    assert_eq!(coordinates(&page), vec![(0, 3), (0, 9), (1, 2), (1, 5)]);
+    assert!(!page.has_more);
+}
+
+#[tokio::test]
+async fn it_resumes_from_the_cursor() {
+    let store = feed_with(&[(0, 1, 0), (0, 2, 0), (1, 1, 0), (1, 2, 0)]).await;
+
+    let first = read(store.clone(), SubscriptionId(1), Cursor::default(), 3)
+        .await
+        .unwrap();
+    assert_eq!(coordinates(&first), vec![(0, 1), (0, 2), (1, 1)]);
+    assert!(first.has_more);
+
+    // New records arrive on a partition the reader already moved past.
+    let message = crate::kafka::streams::StreamsMessage {
+        payload: None,
+        payload_json: None,
+        topic: "orders".to_string(),
+        key: None,
+        headers: Default::default(),
+        partition: 0,
+        offset: 3,
+        timestamp: None,
+    };
+    let late = ChangeRecord::from_message(SubscriptionId(1), &message, false);
+    store.append(vec![late]).await.unwrap();
+
+    let cursor = Cursor::decode(&first.next_cursor).unwrap();
+    let second = read(store, SubscriptionId(1), cursor, 3).await.unwrap();
+    assert_eq!(coordinates(&second), vec![(0, 3), (1, 2)]);
+    assert!(!second.has_more);
+}
+
+#[tokio::test]
+async fn it_signals_expired_cursors_after_truncation() {
+    let store = feed_with(&[(0, 1, 100), (0, 2, 200), (0, 3, 300)]).await;
+
+    let first = read(store.clone(), SubscriptionId(1), Cursor::default(), 1)
+        .await
+        .unwrap();
+    assert_eq!(coordinates(&first), vec![(0, 1)]);
+
+    assert_eq!(store.truncate(SubscriptionId(1), 300).await.unwrap(), 2);
+
+    let cursor = Cursor::decode(&first.next_cursor).unwrap();
+    let result = read(store.clone(), SubscriptionId(1), cursor, 10).await;
+    assert!(matches!(result, Err(FeedError::CursorExpired)));
+
+    // A reader that already passed the truncated records is unaffected.
+    let mut past = Cursor::default();
+    past.advance(&[ChangeRecord {
+        offset: 2,
+        ..first.records[0].clone()
+    }]);
+    let page = read(store.clone(), SubscriptionId(1), past, 10)
+        .await
+        .unwrap();
+    assert_eq!(coordinates(&page), vec![(0, 3)]);
+
+    // So is a fresh reader, which starts at the lowest retained offset.
+    let page = read(store, SubscriptionId(1), Cursor::default(), 10)
+        .await
+        .unwrap();
+    assert_eq!(coordinates(&page), vec![(0, 3)]);
+}
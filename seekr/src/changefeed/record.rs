@@ -0,0 +1,132 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ids::SubscriptionId;
+use crate::kafka::streams::StreamsMessage;
+
+wire_enum! {
+    /// What a change did to its document, stored as its lowercase name.
+    pub enum Operation {
+        Upsert = "upsert",
+        Delete = "delete",
+    }
+}
+
+/// A compact record describing a single change made to a subscription's index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChangeRecord {
+    /// The unique id of the record, derived from its Kafka coordinates.
+    pub id: String,
+
+    /// The subscription the change belongs to.
+    pub subscription_id: SubscriptionId,
+
+    /// The id of the indexed document that changed.
+    pub document_id: String,
+
+    /// The operation applied to the document.
+    pub operation: Operation,
+
+    /// Point in time in UTC Epoch milliseconds, when the change was indexed.
+    #[serde(rename = "_seekr_ts")]
+    pub seekr_ts: i64,
+
+    /// The partition of the source message.
+    pub partition: i32,
+
+    /// The offset of the source message.
+    pub offset: i64,
+
+    /// Hex encoded SHA-256 hash of the payload.
+    pub payload_hash: String,
+
+    /// The full payload, only present when `changefeed.include.payload` is enabled.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub payload: Option<String>,
+}
+
+impl ChangeRecord {
+    pub fn from_message(
+        subscription_id: SubscriptionId,
+        message: &StreamsMessage,
+        include_payload: bool,
+    ) -> Self {
+        let document_id = format!("{}-{}", message.partition, message.offset);
+        let operation = match message.payload {
+            Some(_) => Operation::Upsert,
+            None => Operation::Delete,
+        };
+
+        let payload = message.payload.as_deref().unwrap_or_default();
+        let payload_hash = format!("{:x}", Sha256::digest(payload.as_bytes()));
+
+        ChangeRecord {
+            id: format!("{}-{}", subscription_id, document_id),
+            subscription_id,
+            document_id,
+            operation,
+            seekr_ts: Utc::now().timestamp_millis(),
+            partition: message.partition,
+            offset: message.offset,
+            payload_hash,
+            payload: message.payload.clone().filter(|_| include_payload),
+        }
+    }
+
+    /// The record of a document identified by other than its message's
+    /// coordinates, the record's own id is left as is.
+    pub fn with_document_id(mut self, document_id: String) -> Self {
+        self.document_id = document_id;
+        self
+    }
+}
+
+#[cfg(test)]
+fn message(payload: Option<&str>) -> StreamsMessage {
+    StreamsMessage {
+        payload: payload.map(|p| p.to_string()),
+        payload_json: None,
+        topic: "orders".to_string(),
+        key: None,
+        headers: Default::default(),
+        partition: 2,
+        offset: 41,
+        timestamp: None,
+    }
+}
+
+#[test]
+fn it_only_includes_the_payload_when_requested() {
+    let m = message(Some("{\"a\":1}"));
+
+    let without = ChangeRecord::from_message(SubscriptionId(7), &m, false);
+    assert_eq!(without.payload, None);
+    assert!(!serde_json::to_string(&without)
+        .unwrap()
+        .contains("payload\""));
+
+    let with = ChangeRecord::from_message(SubscriptionId(7), &m, true);
+    assert_eq!(with.payload.as_deref(), Some("{\"a\":1}"));
+    assert_eq!(with.payload_hash, without.payload_hash);
+}
+
+#[test]
+fn it_derives_ids_and_operations_from_the_message() {
+    let record = ChangeRecord::from_message(SubscriptionId(7), &message(None), true);
+
+    assert_eq!(record.id, "7-2-41");
+    assert_eq!(record.document_id, "2-41");
+    assert_eq!(record.operation, Operation::Delete);
+    assert_eq!(record.payload, None);
+}
+
+#[test]
+fn it_records_the_document_of_keyed_messages() {
+    let record = ChangeRecord::from_message(SubscriptionId(7), &message(None), false)
+        .with_document_id("k-1".to_string());
+
+    assert_eq!(record.id, "7-2-41");
+    assert_eq!(record.document_id, "k-1");
+    assert_eq!(record.operation, Operation::Delete);
+}
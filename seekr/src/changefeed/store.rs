@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+use super::cursor::Cursor;
+use super::record::ChangeRecord;
+
+#[async_trait]
+pub trait ChangefeedStore {
+    /// Append records to their subscription's feed.
+    async fn append(&self, records: Vec<ChangeRecord>) -> Result<(), AnyError>;
+
+    /// Read up to `limit` records after the cursor, ordered by (partition, offset).
+    async fn read(
+        &self,
+        id: SubscriptionId,
+        cursor: &Cursor,
+        limit: usize,
+    ) -> Result<Vec<ChangeRecord>, AnyError>;
+
+    /// The record of the message at the given partition and offset, unless truncated.
+    async fn get(
+        &self,
+        id: SubscriptionId,
+        partition: i32,
+        offset: i64,
+    ) -> Result<Option<ChangeRecord>, AnyError>;
+
+    /// Remove records indexed before `before_ms`, returning the number removed.
+    async fn truncate(&self, id: SubscriptionId, before_ms: i64) -> Result<usize, AnyError>;
+
+    /// The highest truncated offset per partition.
+    async fn truncated(&self, id: SubscriptionId) -> Result<HashMap<i32, i64>, AnyError>;
+}
+
+pub const INDEX_NAME: &str = "changefeed";
+pub const MARKS_INDEX_NAME: &str = "changefeed_marks";
+
+/// Maximum number of records removed per truncation round trip.
+const TRUNCATE_BATCH: usize = 1_000;
+
+/// Records how far a subscription's feed has been truncated for one partition.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TruncationMark {
+    id: String,
+    subscription_id: SubscriptionId,
+    partition: i32,
+    offset: i64,
+}
+
+pub struct MSChangefeedStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+}
+
+impl MSChangefeedStore {
+    pub async fn new(client: Arc<Client>) -> Self {
+        for name in [INDEX_NAME, MARKS_INDEX_NAME] {
+            if let Ok(task) = client.clone().create_index(name, Some("id")).await {
+                task.wait_for_completion(&client, None, None).await.unwrap();
+            }
+        }
+
+        let index = client.index(INDEX_NAME);
+        let filterable = ["subscription_id", "partition", "offset", "_seekr_ts"];
+        if let Err(e) = index.set_filterable_attributes(filterable).await {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                INDEX_NAME, e
+            );
+        }
+        if let Err(e) = index.set_sortable_attributes(["partition", "offset"]).await {
+            warn!("Unable to set sortable attributes on {}: {}", INDEX_NAME, e);
+        }
+
+        let marks = client.index(MARKS_INDEX_NAME);
+        if let Err(e) = marks.set_filterable_attributes(["subscription_id"]).await {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                MARKS_INDEX_NAME, e
+            );
+        }
+
+        Self { client }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+
+    fn marks(&self) -> Index {
+        self.client.index(MARKS_INDEX_NAME)
+    }
+}
+
+#[async_trait]
+impl ChangefeedStore for MSChangefeedStore {
+    async fn append(&self, records: Vec<ChangeRecord>) -> Result<(), AnyError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        self.index()
+            .add_or_replace(&records, Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn read(
+        &self,
+        id: SubscriptionId,
+        cursor: &Cursor,
+        limit: usize,
+    ) -> Result<Vec<ChangeRecord>, AnyError> {
+        let positions = cursor.positions();
+        let mut filter = format!("subscription_id = {}", id);
+
+        if !positions.is_empty() {
+            let mut admitted = positions
+                .iter()
+                .map(|(p, o)| format!("(partition = {} AND offset > {})", p, o))
+                .collect::<Vec<_>>();
+
+            let unseen = positions
+                .keys()
+                .map(|p| format!("partition != {}", p))
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            admitted.push(format!("({})", unseen));
+
+            filter = format!("{} AND ({})", filter, admitted.join(" OR "));
+        }
+
+        let results = self
+            .index()
+            .search()
+            .with_filter(&filter)
+            .with_sort(&["partition:asc", "offset:asc"])
+            .with_limit(limit)
+            .execute::<ChangeRecord>()
+            .await?;
+
+        Ok(results.hits.into_iter().map(|h| h.result).collect())
+    }
+
+    async fn get(
+        &self,
+        id: SubscriptionId,
+        partition: i32,
+        offset: i64,
+    ) -> Result<Option<ChangeRecord>, AnyError> {
+        let record_id = format!("{}-{}-{}", id, partition, offset);
+
+        let record = self.index().get_document::<ChangeRecord>(&record_id).await;
+
+        meilisearch::found(record)
+    }
+
+    async fn truncate(&self, id: SubscriptionId, before_ms: i64) -> Result<usize, AnyError> {
+        let filter = format!("subscription_id = {} AND _seekr_ts < {}", id, before_ms);
+        let mut marks = self.truncated(id).await?;
+        let mut removed = 0;
+
+        loop {
+            let results = self
+                .index()
+                .search()
+                .with_filter(&filter)
+                .with_limit(TRUNCATE_BATCH)
+                .execute::<ChangeRecord>()
+                .await?;
+
+            if results.hits.is_empty() {
+                break;
+            }
+
+            let mut ids = Vec::with_capacity(results.hits.len());
+            for r in results.hits.into_iter().map(|h| h.result) {
+                let offset = marks.entry(r.partition).or_insert(r.offset);
+                *offset = (*offset).max(r.offset);
+                ids.push(r.id);
+            }
+
+            // Persist the marks before deleting, so readers never miss a truncation.
+            let docs = marks
+                .iter()
+                .map(|(&partition, &offset)| TruncationMark {
+                    id: format!("{}-{}", id, partition),
+                    subscription_id: id,
+                    partition,
+                    offset,
+                })
+                .collect::<Vec<_>>();
+
+            self.marks()
+                .add_or_replace(&docs, Some("id"))
+                .await?
+                .wait_for_completion(&self.client, None, None)
+                .await?;
+
+            removed += ids.len();
+            self.index()
+                .delete_documents(&ids)
+                .await?
+                .wait_for_completion(&self.client, None, None)
+                .await?;
+        }
+
+        Ok(removed)
+    }
+
+    async fn truncated(&self, id: SubscriptionId) -> Result<HashMap<i32, i64>, AnyError> {
+        let results = self
+            .marks()
+            .search()
+            .with_filter(&format!("subscription_id = {}", id))
+            .execute::<TruncationMark>()
+            .await?;
+
+        Ok(results
+            .hits
+            .into_iter()
+            .map(|h| (h.result.partition, h.result.offset))
+            .collect())
+    }
+}
+
+/// An in-memory feed used to exercise the feed semantics in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryChangefeedStore {
+    records: tokio::sync::RwLock<Vec<ChangeRecord>>,
+    marks: tokio::sync::RwLock<HashMap<SubscriptionId, HashMap<i32, i64>>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl ChangefeedStore for MemoryChangefeedStore {
+    async fn append(&self, records: Vec<ChangeRecord>) -> Result<(), AnyError> {
+        self.records.write().await.extend(records);
+        Ok(())
+    }
+
+    async fn read(
+        &self,
+        id: SubscriptionId,
+        cursor: &Cursor,
+        limit: usize,
+    ) -> Result<Vec<ChangeRecord>, AnyError> {
+        let records = self.records.read().await;
+        let records = records.iter().filter(|r| r.subscription_id == id).cloned();
+        Ok(super::cursor::paginate(records, cursor, limit))
+    }
+
+    async fn get(
+        &self,
+        id: SubscriptionId,
+        partition: i32,
+        offset: i64,
+    ) -> Result<Option<ChangeRecord>, AnyError> {
+        let records = self.records.read().await;
+        Ok(records
+            .iter()
+            .find(|r| r.subscription_id == id && r.partition == partition && r.offset == offset)
+            .cloned())
+    }
+
+    async fn truncate(&self, id: SubscriptionId, before_ms: i64) -> Result<usize, AnyError> {
+        let mut records = self.records.write().await;
+        let mut marks = self.marks.write().await;
+        let marks = marks.entry(id).or_default();
+
+        let before = records.len();
+        records.retain(|r| {
+            if r.subscription_id != id || r.seekr_ts >= before_ms {
+                return true;
+            }
+            let offset = marks.entry(r.partition).or_insert(r.offset);
+            *offset = (*offset).max(r.offset);
+            false
+        });
+
+        Ok(before - records.len())
+    }
+
+    async fn truncated(&self, id: SubscriptionId) -> Result<HashMap<i32, i64>, AnyError> {
+        Ok(self
+            .marks
+            .read()
+            .await
+            .get(&id)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+pub async fn init_changefeed_store(
+    config: &MeilisearchConfig,
+) -> Arc<dyn ChangefeedStore + Send + Sync> {
+    Arc::new(MSChangefeedStore::new(config.client()).await)
+}
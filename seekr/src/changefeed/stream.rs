@@ -0,0 +1,106 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::Stream;
+use serde::Serialize;
+
+use crate::api::sse::event;
+use crate::drain::Connection;
+use crate::ids::SubscriptionId;
+
+use super::cursor::Cursor;
+use super::store::ChangefeedStore;
+use super::{FeedError, POLL_INTERVAL};
+
+/// The final event sent to stream readers when the server starts draining.
+pub const SHUTTING_DOWN: &str = "server_shutting_down";
+
+#[derive(Serialize)]
+struct Resume {
+    next_cursor: String,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    code: &'static str,
+    message: String,
+}
+
+enum State {
+    Open {
+        connection: Connection,
+        cursor: Cursor,
+    },
+
+    /// The shutdown event was sent, the client is expected to disconnect.
+    Draining {
+        connection: Connection,
+    },
+    Done,
+}
+
+/// Stream the change feed as server-sent events, one `records` event per page.
+///
+/// Once the server starts draining, a final `server_shutting_down` event
+/// carries the cursor to resume from. The stream then stays open until the
+/// client disconnects, or the drain grace period closes it.
+pub fn events(
+    store: Arc<dyn ChangefeedStore + Send + Sync>,
+    id: SubscriptionId,
+    cursor: Cursor,
+    limit: usize,
+    connection: Connection,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let state = State::Open { connection, cursor };
+
+    futures::stream::unfold(state, move |state| {
+        let store = store.clone();
+        async move {
+            match state {
+                State::Open {
+                    mut connection,
+                    mut cursor,
+                } => loop {
+                    match super::read(store.clone(), id, cursor.clone(), limit).await {
+                        Ok(page) if !page.records.is_empty() => {
+                            cursor.advance(&page.records);
+                            let state = State::Open { connection, cursor };
+                            return Some((event(Some("records"), &page), state));
+                        }
+                        Ok(_) => {}
+                        Err(FeedError::CursorExpired) => {
+                            let failure = Failure {
+                                code: "cursor_expired",
+                                message: "records after the cursor have been truncated".to_string(),
+                            };
+                            return Some((event(Some("error"), &failure), State::Done));
+                        }
+                        Err(FeedError::Store(e)) => {
+                            warn!("Failed to read changefeed of subscription {} - {}", id, e);
+                            let failure = Failure {
+                                code: "internal",
+                                message: e.to_string(),
+                            };
+                            return Some((event(Some("error"), &failure), State::Done));
+                        }
+                    }
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                        _ = connection.draining() => {
+                            let resume = Resume { next_cursor: cursor.encode() };
+                            let state = State::Draining { connection };
+                            return Some((event(Some(SHUTTING_DOWN), &resume), state));
+                        }
+                    }
+                },
+                State::Draining { mut connection } => {
+                    connection.closed().await;
+                    None
+                }
+                State::Done => None,
+            }
+        }
+    })
+}
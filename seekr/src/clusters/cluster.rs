@@ -1,13 +1,29 @@
 use std::collections::HashMap;
 
 use chrono::prelude::*;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[repr(i32)]
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum Kind {
-    Unknown,
-    Kafka,
+use crate::api::list::{Field, FieldType, ListSchema, Listable, Value};
+use crate::governance::owner::Owner;
+use crate::ids::ClusterId;
+
+wire_enum! {
+    /// The kind of a cluster, stored as its name, and as its code in the
+    /// `kind` column of `adm.clusters`.
+    ///
+    /// | Variant   | Name        | Code |
+    /// |-----------|-------------|------|
+    /// | `Unknown` | `"Unknown"` | 0    |
+    /// | `Kafka`   | `"Kafka"`   | 1    |
+    ///
+    /// A code read from the column that isn't in the table is kept as its digits.
+    pub enum Kind {
+        Unknown = "Unknown",
+        Kafka = "Kafka",
+    }
 }
 
 impl Kind {
@@ -17,24 +33,57 @@ impl Kind {
             _ => "UNKNOWN",
         }
     }
+
+    /// The code of the kind in the `kind` column.
+    pub fn code(&self) -> i32 {
+        match self {
+            Kind::Kafka => 1,
+            Kind::Unrecognized(raw) => raw.parse().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Kinds can only be set to those this version knows.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Kind::Unrecognized(raw) => Err(format!(
+                "unknown cluster kind '{}', expected Unknown or Kafka",
+                raw
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
-impl TryFrom<i32> for Kind {
-    type Error = ();
+impl From<i32> for Kind {
+    fn from(code: i32) -> Self {
+        match code {
+            0 => Kind::Unknown,
+            1 => Kind::Kafka,
+            code => Kind::Unrecognized(code.to_string()),
+        }
+    }
+}
+
+impl JsonSchema for Kind {
+    fn schema_name() -> String {
+        "Kind".to_string()
+    }
 
-    fn try_from(v: i32) -> Result<Self, Self::Error> {
-        match v {
-            x if x == Kind::Unknown as i32 => Ok(Kind::Unknown),
-            x if x == Kind::Kafka as i32 => Ok(Kind::Kafka),
-            _ => Err(()),
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(Kind::KNOWN.iter().map(|k| k.as_str().into()).collect()),
+            ..Default::default()
         }
+        .into()
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct Cluster {
     /// The id of cluster entry.
-    pub id: i64,
+    pub id: ClusterId,
 
     /// The kind of cluster entry.
     pub kind: Kind,
@@ -50,22 +99,37 @@ pub struct Cluster {
 
     /// Represents the point in time in UTC Epoch time, when the cluster was modified.
     pub updated_at: DateTime<Utc>,
+
+    /// Who to contact about the cluster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+
+    /// Represents the point in time in UTC Epoch time, when the owner was last confirmed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership_confirmed_at: Option<DateTime<Utc>>,
 }
 
 impl Cluster {
-    pub fn new(id: Option<i64>, kind: Kind, name: String, config: HashMap<String, String>) -> Self {
+    pub fn new(
+        id: Option<ClusterId>,
+        kind: Kind,
+        name: String,
+        config: HashMap<String, String>,
+    ) -> Self {
         Cluster {
-            id: id.unwrap_or(0),
+            id: id.unwrap_or_default(),
             kind,
             name,
             config,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            owner: None,
+            ownership_confirmed_at: None,
         }
     }
 
     pub fn init(
-        id: i64,
+        id: ClusterId,
         kind: Kind,
         name: String,
         config: HashMap<String, String>,
@@ -79,6 +143,80 @@ impl Cluster {
             config,
             created_at,
             updated_at,
+            owner: None,
+            ownership_confirmed_at: None,
+        }
+    }
+
+    /// Whether the cluster goes by `name`, ignoring case and surrounding whitespace.
+    pub fn is_named(&self, name: &str) -> bool {
+        name_key(&self.name) == name_key(name)
+    }
+}
+
+/// A cluster name as it's compared with the others, which must be unique.
+pub fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl Listable for Cluster {
+    const SCHEMA: ListSchema = ListSchema {
+        fields: &[
+            Field {
+                name: "id",
+                kind: FieldType::Integer,
+                sortable: true,
+                filterable: true,
+                attribute: "id",
+            },
+            Field {
+                name: "name",
+                kind: FieldType::String,
+                sortable: true,
+                filterable: true,
+                attribute: "name",
+            },
+            Field {
+                name: "kind",
+                kind: FieldType::String,
+                sortable: false,
+                filterable: true,
+                attribute: "kind",
+            },
+            Field {
+                name: "created_at",
+                kind: FieldType::Timestamp,
+                sortable: true,
+                filterable: true,
+                attribute: "created_at",
+            },
+            Field {
+                name: "updated_at",
+                kind: FieldType::Timestamp,
+                sortable: true,
+                filterable: true,
+                attribute: "updated_at",
+            },
+            Field {
+                name: "team",
+                kind: FieldType::String,
+                sortable: false,
+                filterable: true,
+                attribute: "owner.team",
+            },
+        ],
+        id: "id",
+    };
+
+    fn value(&self, field: &str) -> Option<Value> {
+        match field {
+            "id" => Some(Value::Integer(self.id.as_i64())),
+            "name" => Some(Value::String(self.name.clone())),
+            "kind" => Some(Value::String(self.kind.to_string())),
+            "created_at" => Some(Value::Timestamp(self.created_at)),
+            "updated_at" => Some(Value::Timestamp(self.updated_at)),
+            "team" => self.owner.as_ref()?.team.clone().map(Value::String),
+            _ => None,
         }
     }
 }
@@ -1 +1,22 @@
+use actix_web::web::ServiceConfig;
+
+use crate::api::routes::{Audience, RouteGroup};
+use crate::api::ApiVersion;
+
 pub mod v1;
+pub mod v2;
+
+pub const ROUTES: RouteGroup = RouteGroup {
+    module: module_path!(),
+    scope: "clusters",
+    versions: &[ApiVersion::V1, ApiVersion::V2],
+    audience: Audience::Users,
+    configure,
+};
+
+pub fn configure(cfg: &mut ServiceConfig, version: ApiVersion) {
+    match version {
+        ApiVersion::V1 => v1::configure(cfg),
+        ApiVersion::V2 => v2::configure(cfg),
+    }
+}
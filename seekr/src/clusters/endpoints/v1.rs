@@ -1,136 +1,583 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use actix_web::web::{Data, Json, Path, ServiceConfig};
-use actix_web::{delete, get, post, put, HttpResponse, Responder};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::web::{Data, Json, Path, Query, ServiceConfig};
+use actix_web::{delete, get, patch, post, put, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::api::list::ListQuery;
+use crate::api::{etag, ndjson, retry};
+use crate::auth::reveal::{Reveal, REVEAL_TOKEN_HEADER};
+use crate::auth::Principal;
 use crate::clusters::cluster::Cluster;
 use crate::clusters::cluster::Kind;
-use crate::clusters::store::ClusterStore;
-use crate::kafka::metadata::manager::MetadataManager;
+use crate::clusters::health::ClusterHealth;
+use crate::clusters::service::{self, ClusterFilter, MetadataRead, TopicRead};
+use crate::clusters::store::{ClusterNotFound, ClusterStore};
+use crate::errors::{ApiError, ErrorBody};
+use crate::governance::owner::{Confirmation, Owner};
+use crate::governance::policy::OwnershipPolicy;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::admin::{NewTopicRequest, TopicAdmin, TopicAdminError};
+use crate::kafka::auth::AuthProvider;
+use crate::kafka::metadata::classify::Include;
+use crate::kafka::metadata::manager::{
+    CacheInfo, CachedMetadataEntry, GroupLagRead, MetadataManager,
+};
+use crate::kafka::metadata::redact::{RedactQuery, Redaction, RedactionPolicy};
+use crate::kafka::metadata::throughput::TopicThroughput;
+use crate::kafka::metadata::{
+    BrokerMetadata, GroupLag, GroupMetadata, TopicConfigEntry, TopicMetadata,
+};
+use crate::kafka::sensitive;
+use crate::lint::{self, LintPolicy, LintWarning, Subject};
+use crate::shards::store::DocumentStore;
+use crate::standby::Availability;
+use crate::storage::collector::StorageCollector;
+use crate::subscriptions::store::{PurgeLog, SubscriptionStore};
+use crate::validate;
 
 pub fn configure(cfg: &mut ServiceConfig) {
     cfg.service(create_cluster)
         .service(get_clusters)
         .service(get_cluster)
         .service(update_cluster)
+        .service(patch_cluster)
         .service(delete_cluster)
-        .service(get_cluster_metadata);
+        .service(confirm_ownership)
+        .service(get_cluster_metadata)
+        .service(refresh_cluster_metadata)
+        .service(get_cluster_health)
+        .service(get_cluster_lint)
+        .service(get_topic)
+        .service(create_topic)
+        .service(delete_topic)
+        .service(get_group_lag);
 }
 
 #[post("")]
 async fn create_cluster(
     r: Json<CreateClusterRequest>,
+    principal: Principal,
+    lints: LintPolicy,
     store: Data<Arc<dyn ClusterStore + Send + Sync>>,
     manager: Data<MetadataManager>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     info!("Creating a new cluster");
 
-    let cluster = Cluster::new(None, r.kind.clone(), r.name.clone(), r.config.clone());
-    let manager = manager.into_inner().clone();
+    // Keys scoped to clusters would lose access to the clusters they create.
+    if principal.is_scoped() {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
 
-    match store.insert(cluster.to_owned()).await {
-        Ok(id) => {
-            manager.register(Cluster { id, ..cluster }).await;
-            HttpResponse::Ok().json(CreateClusterResponse { id })
-        }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    let r = r.into_inner();
+    validate::cluster(&r.kind, &r.name, &r.config)?;
+    if let Some(owner) = &r.owner {
+        owner.validate().map_err(ApiError::invalid)?;
     }
+    AuthProvider::of(&r.config).map_err(ApiError::invalid)?;
+    let cluster = Cluster::new(None, r.kind.clone(), r.name.clone(), r.config.clone());
+    let warnings = lint::lint(Subject::Cluster(&cluster));
+    lints.check(&warnings).map_err(ApiError::invalid)?;
+
+    let manager = manager.into_inner();
+    let result = service::create(
+        store.as_ref().as_ref(),
+        manager,
+        r.kind,
+        r.name,
+        r.config,
+        r.owner,
+    );
+
+    let id = result.await?;
+    Ok(HttpResponse::Ok().json(CreateClusterResponse { id, warnings }))
 }
 
 #[get("")]
-async fn get_clusters(store: Data<Arc<dyn ClusterStore + Send + Sync>>) -> impl Responder {
+async fn get_clusters(
+    req: HttpRequest,
+    query: Query<ListClustersQuery>,
+    list: ListQuery<Cluster>,
+    principal: Principal,
+    policy: OwnershipPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
     info!("Fetching all clusters");
 
-    match store.list(None).await {
-        Ok(clusters) => {
-            let clusters = clusters
-                .iter()
-                .map(|c| c.to_summary())
-                .collect::<Vec<ClusterSummery>>();
-            HttpResponse::Ok().json(ListClustersResponse { clusters })
-        }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    // The store pages the clusters itself, unless some are left out or reordered first.
+    let store = store.as_ref().as_ref();
+    let filter = query.into_inner().filter();
+    let page = if list.is_given()
+        && list.is_paging_only()
+        && filter.is_empty()
+        && !principal.is_scoped()
+    {
+        let (clusters, total) = service::page(store, list.window()).await?;
+        list.paged(clusters, total)
+    } else {
+        let clusters = service::list(store, &filter).await?;
+        list.apply(
+            clusters
+                .into_iter()
+                .filter(|c| principal.can_access(c.id))
+                .collect(),
+        )
+    };
+
+    if ndjson::accepts(&req) {
+        let lines = page.items.into_iter().map(move |c| c.to_summary(&policy));
+        return Ok(ndjson::stream(lines));
     }
+    Ok(match list.is_given() {
+        true => HttpResponse::Ok().json(page.map(|c| c.to_summary(&policy))),
+        false => HttpResponse::Ok().json(ListClustersResponse {
+            clusters: page.items.iter().map(|c| c.to_summary(&policy)).collect(),
+        }),
+    })
 }
 
 #[get("/{id}")]
 async fn get_cluster(
-    id: Path<i64>,
+    id: Path<ClusterId>,
+    policy: OwnershipPolicy,
+    reveal: Reveal,
     store: Data<Arc<dyn ClusterStore + Send + Sync>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
     info!("Fetching cluster with id {}", id);
 
-    match store.get(id).await {
-        Ok(cluster) => {
-            let Some(c) = cluster else {
-				return HttpResponse::NotFound().finish();
-			};
-
-            HttpResponse::Ok().json(ReadClusterResponse {
-                cluster: c.to_summary(),
-            })
-        }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    if reveal == Reveal::Denied {
+        return Ok(HttpResponse::Forbidden().json(reveal_forbidden()));
     }
+    let c = service::get(store.as_ref().as_ref(), id)
+        .await?
+        .ok_or(ClusterNotFound(id))?;
+    let mut cluster = c.to_summary(&policy);
+    if reveal == Reveal::Granted {
+        info!("Revealing the config secrets of cluster {}", id);
+        cluster.config = c.config;
+    }
+    Ok(HttpResponse::Ok().json(ReadClusterResponse { cluster }))
+}
+
+/// Answered to reads asking for config secrets without the reveal token.
+pub(crate) fn reveal_forbidden() -> ErrorBody {
+    ErrorBody::new(
+        "reveal_forbidden",
+        format!(
+            "Revealing config secrets needs the reveal token in the {} header",
+            REVEAL_TOKEN_HEADER
+        ),
+    )
 }
 
 #[put("/{id}")]
 async fn update_cluster(
-    id: Path<i64>,
+    id: Path<ClusterId>,
     r: Json<UpdateClusterRequest>,
+    lints: LintPolicy,
     store: Data<Arc<dyn ClusterStore + Send + Sync>>,
-) -> impl Responder {
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
     info!("Updating cluster with id {}", id);
 
+    let r = r.into_inner();
+    validate::cluster(&r.kind, &r.name, &r.config)?;
+    if let Some(owner) = &r.owner {
+        owner.validate().map_err(ApiError::invalid)?;
+    }
+    AuthProvider::of(&r.config).map_err(ApiError::invalid)?;
     let cluster = Cluster::new(Some(id), r.kind.clone(), r.name.clone(), r.config.clone());
+    let warnings = lint::lint(Subject::Cluster(&cluster));
+    lints.check(&warnings).map_err(ApiError::invalid)?;
+
+    let result = service::update(
+        store.as_ref().as_ref(),
+        manager.into_inner(),
+        id,
+        r.kind,
+        r.name,
+        r.config,
+        r.owner,
+    );
+
+    let id = result.await?.ok_or(ClusterNotFound(id))?;
+    Ok(HttpResponse::Ok().json(UpdateClusterResponse { id, warnings }))
+}
+
+#[patch("/{id}")]
+async fn patch_cluster(
+    id: Path<ClusterId>,
+    r: Json<UpdateClusterPatch>,
+    lints: LintPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    info!("Patching cluster with id {}", id);
+
+    let r = r.into_inner();
+    let store = store.as_ref().as_ref();
+    let cluster = service::patched(store, id, r.kind, r.name, r.config)
+        .await?
+        .ok_or(ClusterNotFound(id))?;
+    validate::cluster(&cluster.kind, &cluster.name, &cluster.config)?;
+    AuthProvider::of(&cluster.config).map_err(ApiError::invalid)?;
+    let warnings = lint::lint(Subject::Cluster(&cluster));
+    lints.check(&warnings).map_err(ApiError::invalid)?;
+
+    let result = service::update(
+        store,
+        manager.into_inner(),
+        id,
+        cluster.kind,
+        cluster.name,
+        cluster.config,
+        None,
+    );
+
+    let id = result.await?.ok_or(ClusterNotFound(id))?;
+    Ok(HttpResponse::Ok().json(UpdateClusterResponse { id, warnings }))
+}
+
+#[post("/{id}/confirm-ownership")]
+async fn confirm_ownership(
+    id: Path<ClusterId>,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    info!("Confirming ownership of cluster with id {}", id);
 
-    match store.update(cluster).await {
-        Ok(id) => HttpResponse::Ok().json(UpdateClusterResponse { id }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    match service::confirm_ownership(store.as_ref().as_ref(), id).await? {
+        Confirmation::Confirmed(at) => Ok(HttpResponse::Ok().json(ConfirmOwnershipResponse {
+            id,
+            ownership_confirmed_at: at,
+        })),
+        Confirmation::Unowned => Err(ApiError::conflict(format!(
+            "Cluster with id '{}' has no owner to confirm",
+            id
+        ))),
+        Confirmation::NotFound => Err(ClusterNotFound(id).into()),
     }
 }
 
 #[delete("/{id}")]
 async fn delete_cluster(
-    id: Path<i64>,
+    id: Path<ClusterId>,
     store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    subscriptions: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    documents: Data<Arc<dyn DocumentStore + Send + Sync>>,
+    purges: Data<Arc<dyn PurgeLog + Send + Sync>>,
     manager: Data<MetadataManager>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    info!("Deleting cluster with id {}", id);
-    let manager = manager.into_inner().clone();
+    info!("Deleting cluster with id {} and its subscriptions", id);
 
-    match store.remove(id).await {
-        Ok(_) => {
-            manager.remove(id).await;
-            HttpResponse::Ok().finish()
-        }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+    let result = service::delete_with_subscriptions(
+        store.as_ref().as_ref(),
+        subscriptions.as_ref().as_ref(),
+        documents.as_ref().as_ref(),
+        purges.as_ref().as_ref(),
+        manager.into_inner(),
+        id,
+    );
+
+    let deletion = result.await?;
+    let response = DeleteClusterResponse {
+        cluster_id: id,
+        subscriptions_deleted: deletion.subscriptions_deleted,
+        subscriptions_failed: deletion
+            .subscriptions_failed
+            .into_iter()
+            .map(|(id, error)| FailedSubscription { id, error })
+            .collect(),
+    };
+    Ok(match response.subscriptions_failed.is_empty() {
+        true => HttpResponse::Ok().json(response),
+        false => HttpResponse::InternalServerError().json(response),
+    })
 }
 
+/// Whether a read of a cluster that exists but isn't cached registered it again.
+pub const REGISTRATION_STARTED: &str = "X-Registration-Started";
+
+/// When the metadata answered was fetched, the last poll that succeeded.
+pub const METADATA_FETCHED_AT: &str = "X-Metadata-Fetched-At";
+
+/// Whether the metadata answered is older than twice its cluster's poll interval.
+pub const METADATA_STALE: &str = "X-Metadata-Stale";
+
 #[get("/{id}/metadata")]
-async fn get_cluster_metadata(path: Path<i64>, manager: Data<MetadataManager>) -> impl Responder {
+async fn get_cluster_metadata(
+    req: HttpRequest,
+    path: Path<ClusterId>,
+    redact: Query<RedactQuery>,
+    policy: RedactionPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+    availability: Option<Data<Availability>>,
+) -> Result<HttpResponse, ApiError> {
     let id = path.into_inner();
     info!("Fetching metadata for cluster with id {}", id);
 
-    let result = manager.into_inner().get(id).await;
-    if result.is_err() {
-        let msg = format!("{}", result.unwrap_err());
-        return HttpResponse::InternalServerError().body(msg);
+    let redaction = match redact.redact.as_deref() {
+        Some(r) => policy.resolve(r).map_err(ApiError::invalid)?,
+        None => Redaction::default(),
+    };
+
+    let manager = manager.into_inner();
+    if ndjson::accepts(&req) {
+        let snapshot = manager.snapshot(id).await;
+        if let Some(entry) = snapshot.filter(|e| matches!(**e, CachedMetadataEntry::Meta(_))) {
+            manager.activity(id).await;
+            let cache = manager.cache_info(id).await;
+            let entry = match redaction.is_empty() {
+                true => entry,
+                false => Arc::new(policy.apply_entry(&redaction, &entry)),
+            };
+            let mut res = ndjson::stream(MetadataLines { entry, next: 0 });
+            cache_headers(res.headers_mut(), cache);
+            return Ok(res);
+        }
+    }
+
+    // Standbys sync their cache rather than polling, so only a primary heals it.
+    let heal = availability.map_or(true, |a| a.is_primary());
+    let read = service::metadata(store.as_ref().as_ref(), manager.clone(), id, heal).await?;
+    let (mut res, entry) = match read {
+        MetadataRead::Cached(entry) => (HttpResponse::Ok(), entry),
+        MetadataRead::Uncached { registering } => {
+            let mut res = HttpResponse::Accepted();
+            res.insert_header((REGISTRATION_STARTED, registering.to_string()));
+            (res, Arc::new(CachedMetadataEntry::Processing))
+        }
+        MetadataRead::Missing => return Err(metadata_not_found(id)),
+    };
+
+    // The v1 body is a bare entry, so the hint only travels in the header.
+    if let Some(hint) = service::retry_after(&manager, id, &entry).await {
+        retry::with_retry_after(&mut res, hint);
+    }
+    let cache = service::cache_info(&manager, id, &entry).await;
+    // The shared entry is serialized as it is, tagged so pollers holding it
+    // are answered without a body.
+    let mut res = match redaction.is_empty() {
+        true => etag::json(&req, &mut res, entry.as_ref()),
+        false => etag::json(&req, &mut res, &policy.apply_entry(&redaction, &entry)),
+    };
+    cache_headers(res.headers_mut(), cache);
+    Ok(res)
+}
+
+/// How fresh the metadata answered is, which the bare v1 entry can't tell.
+fn cache_headers(headers: &mut HeaderMap, cache: Option<CacheInfo>) {
+    let Some(cache) = cache else {
+        return;
+    };
+    let fetched_at = cache
+        .fetched_at
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    if let Ok(value) = HeaderValue::from_str(&fetched_at) {
+        headers.insert(HeaderName::from_static("x-metadata-fetched-at"), value);
+    }
+    headers.insert(
+        HeaderName::from_static("x-metadata-stale"),
+        HeaderValue::from_static(if cache.stale { "true" } else { "false" }),
+    );
+}
+
+#[post("/{id}/metadata/refresh")]
+async fn refresh_cluster_metadata(
+    path: Path<ClusterId>,
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    info!("Refreshing metadata of cluster with id {}", id);
+
+    // Failed polls are answered like reads, with the entry holding their error.
+    let Some(entry) = manager.refresh(id).await else {
+        return Err(metadata_not_found(id));
+    };
+    let cache = service::cache_info(&manager, id, &entry).await;
+    let mut res = HttpResponse::Ok().json(entry.as_ref());
+    cache_headers(res.headers_mut(), cache);
+    Ok(res)
+}
+
+#[get("/{id}/health")]
+async fn get_cluster_health(
+    path: Path<ClusterId>,
+    include: Query<Include>,
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    info!("Fetching health for cluster with id {}", id);
+
+    let health = service::health(manager.into_inner(), id, include.into_inner())
+        .await?
+        .ok_or_else(|| metadata_not_found(id))?;
+    Ok(HttpResponse::Ok().json(ClusterHealthResponse { health }))
+}
+
+#[get("/{id}/lint")]
+async fn get_cluster_lint(
+    id: Path<ClusterId>,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    info!("Linting cluster with id {}", id);
+
+    let c = service::get(store.as_ref().as_ref(), id)
+        .await?
+        .ok_or(ClusterNotFound(id))?;
+    Ok(HttpResponse::Ok().json(LintResponse {
+        id,
+        warnings: lint::lint(Subject::Cluster(&c)),
+    }))
+}
+
+#[get("/{id}/topics/{topic}")]
+async fn get_topic(
+    path: Path<(ClusterId, String)>,
+    manager: Data<MetadataManager>,
+    storage: Option<Data<StorageCollector>>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, topic) = path.into_inner();
+    info!("Fetching topic {} for cluster with id {}", topic, id);
+
+    let size_bytes = storage.and_then(|s| s.size_bytes(id, &topic));
+    let manager = manager.into_inner();
+    let (metadata, throughput) = match service::topic(manager.clone(), id, &topic).await? {
+        TopicRead::Topic(metadata, throughput) => (metadata, throughput),
+        TopicRead::Processing => return Err(metadata_processing(id)),
+        TopicRead::MissingCluster => return Err(metadata_not_found(id)),
+        TopicRead::MissingTopic => {
+            return Err(ApiError::not_found(format!("Topic '{}' not found", topic)))
+        }
+    };
+
+    // The cached partitions are answered even when the brokers can't describe the topic.
+    let (config, config_error) = match service::topic_config(&manager, id, &topic).await {
+        Ok(config) => (Some(config), None),
+        Err(e) => {
+            warn!(
+                cluster_id = id.as_i64();
+                "Failed to describe topic {} of cluster {} - {}", topic, id, e
+            );
+            (None, Some(e.to_string()))
+        }
+    };
+    Ok(HttpResponse::Ok().json(ReadTopicResponse {
+        topic: metadata,
+        throughput,
+        size_bytes,
+        config,
+        config_error,
+    }))
+}
+
+#[post("/{id}/topics")]
+async fn create_topic(
+    path: Path<ClusterId>,
+    query: Query<WriteTopicQuery>,
+    body: Json<NewTopicRequest>,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    admin: Data<Arc<dyn TopicAdmin + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let dry_run = query.dry_run;
+    let topic = body.into_inner();
+    info!(
+        "Creating topic {} for cluster with id {} (dry run: {})",
+        topic.name, id, dry_run
+    );
+
+    validate::topic(&topic.name, topic.partitions, topic.replication_factor)?;
+    let c = service::get(store.as_ref().as_ref(), id)
+        .await?
+        .ok_or(ClusterNotFound(id))?;
+    if let Err(e) = admin.create_topic(&c, &topic, dry_run).await {
+        return topic_admin_error(e);
     }
 
-    let Some(entry) = result.unwrap() else {
-        return HttpResponse::NotFound()
-            .body(format!("Cluster metadata with id '{}' not found", id));
+    let response = WriteTopicResponse {
+        name: topic.name,
+        dry_run,
     };
+    if dry_run {
+        return Ok(HttpResponse::Ok().json(response));
+    }
+    // Answered once the cache holds the topic, so reads that follow find it.
+    manager.refresh(id).await;
+    Ok(HttpResponse::Created().json(response))
+}
+
+#[delete("/{id}/topics/{topic}")]
+async fn delete_topic(
+    path: Path<(ClusterId, String)>,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    admin: Data<Arc<dyn TopicAdmin + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, topic) = path.into_inner();
+    info!("Deleting topic {} for cluster with id {}", topic, id);
+
+    let c = service::get(store.as_ref().as_ref(), id)
+        .await?
+        .ok_or(ClusterNotFound(id))?;
+    if let Err(e) = admin.delete_topic(&c, &topic).await {
+        return topic_admin_error(e);
+    }
+
+    manager.refresh(id).await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Answer the brokers refusing a topic change with their reason.
+fn topic_admin_error(e: TopicAdminError) -> Result<HttpResponse, ApiError> {
+    match e {
+        TopicAdminError::Exists(message) => Err(ApiError::conflict(message)),
+        TopicAdminError::Invalid(message) => {
+            Ok(HttpResponse::UnprocessableEntity().json(ErrorBody::new("invalid_topic", message)))
+        }
+        TopicAdminError::NotFound(message) => Err(ApiError::not_found(message)),
+        TopicAdminError::Failed(e) => Err(e.into()),
+    }
+}
+
+#[get("/{id}/groups/{group}/lag")]
+async fn get_group_lag(
+    path: Path<(ClusterId, String)>,
+    manager: Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, group) = path.into_inner();
+    info!("Fetching lag of group {} for cluster with id {}", group, id);
+
+    match service::group_lag(&manager, id, &group).await? {
+        GroupLagRead::Lag(lag) => Ok(HttpResponse::Ok().json(GroupLagResponse { group, lag })),
+        GroupLagRead::Processing => Err(metadata_processing(id)),
+        GroupLagRead::MissingCluster => Err(metadata_not_found(id)),
+        GroupLagRead::MissingGroup => Err(ApiError::not_found(format!(
+            "Consumer group '{}' not found",
+            group
+        ))),
+    }
+}
+
+fn metadata_not_found(id: ClusterId) -> ApiError {
+    ApiError::not_found(format!("Cluster metadata with id '{}' not found", id))
+}
 
-    HttpResponse::Ok().json(entry)
+fn metadata_processing(id: ClusterId) -> ApiError {
+    ApiError::unavailable(format!(
+        "Cluster metadata with id '{}' is still processing",
+        id
+    ))
 }
 
 #[derive(Deserialize)]
@@ -138,15 +585,33 @@ struct CreateClusterRequest {
     kind: Kind,
     name: String,
     config: HashMap<String, String>,
+    #[serde(default)]
+    owner: Option<Owner>,
 }
 
 #[derive(Serialize)]
 struct CreateClusterResponse {
-    id: i64,
+    id: ClusterId,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<LintWarning>,
 }
 
 #[derive(Deserialize)]
-struct ListClustersRequest {}
+struct ListClustersQuery {
+    team: Option<String>,
+    kind: Option<String>,
+    name_contains: Option<String>,
+}
+
+impl ListClustersQuery {
+    fn filter(self) -> ClusterFilter {
+        ClusterFilter {
+            team: self.team,
+            kind: self.kind,
+            name_contains: self.name_contains.filter(|n| !n.is_empty()),
+        }
+    }
+}
 
 #[derive(Serialize)]
 struct ListClustersResponse {
@@ -163,32 +628,1248 @@ struct UpdateClusterRequest {
     kind: Kind,
     name: String,
     config: HashMap<String, String>,
+    #[serde(default)]
+    owner: Option<Owner>,
+}
+
+/// The fields of a cluster to change, the omitted ones kept as they are.
+#[derive(Deserialize)]
+struct UpdateClusterPatch {
+    kind: Option<Kind>,
+    name: Option<String>,
+
+    /// Config entries to set, or to remove when `null`.
+    #[serde(default)]
+    config: HashMap<String, Option<String>>,
 }
 
 #[derive(Serialize)]
 struct UpdateClusterResponse {
-    id: i64,
+    id: ClusterId,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<LintWarning>,
+}
+
+/// The subscriptions a cluster's deletion removed, and those it failed to,
+/// which keep the cluster in place until it's deleted again.
+#[derive(Serialize)]
+struct DeleteClusterResponse {
+    cluster_id: ClusterId,
+    subscriptions_deleted: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subscriptions_failed: Vec<FailedSubscription>,
+}
+
+#[derive(Serialize)]
+struct FailedSubscription {
+    id: SubscriptionId,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct LintResponse {
+    id: ClusterId,
+    warnings: Vec<LintWarning>,
+}
+
+#[derive(Serialize)]
+struct ConfirmOwnershipResponse {
+    id: ClusterId,
+    ownership_confirmed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct ClusterHealthResponse {
+    health: ClusterHealth,
+}
+
+#[derive(Serialize)]
+struct ReadTopicResponse {
+    topic: TopicMetadata,
+    throughput: Option<TopicThroughput>,
+    /// The disk used across replicas, as of the last storage collection.
+    size_bytes: Option<u64>,
+
+    /// The topic's settings as the brokers describe them, unless they couldn't.
+    config: Option<Vec<TopicConfigEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WriteTopicQuery {
+    /// Only have the brokers validate the topic, without creating it.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+struct WriteTopicResponse {
+    name: String,
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+struct GroupLagResponse {
+    group: String,
+    lag: Vec<GroupLag>,
+}
+
+/// An element of streamed metadata, tagged with its kind.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MetadataLine {
+    Broker(BrokerMetadata),
+    Group(GroupMetadata),
+    Topic(TopicMetadata),
+}
+
+/// The brokers, groups and topics of a cached entry, in that order, copied
+/// one at a time as they are streamed.
+struct MetadataLines {
+    entry: Arc<CachedMetadataEntry>,
+    next: usize,
+}
+
+impl Iterator for MetadataLines {
+    type Item = MetadataLine;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let CachedMetadataEntry::Meta(m) = self.entry.as_ref() else {
+            return None;
+        };
+        let i = self.next;
+        self.next += 1;
+
+        let groups = i.checked_sub(m.brokers.len());
+        let topics = groups.and_then(|i| i.checked_sub(m.groups.len()));
+        match (groups, topics) {
+            (None, _) => Some(MetadataLine::Broker(m.brokers[i].clone())),
+            (Some(i), None) => Some(MetadataLine::Group(m.groups[i].clone())),
+            (_, Some(i)) => m.topics.get(i).cloned().map(MetadataLine::Topic),
+        }
+    }
 }
 
 #[derive(Serialize)]
-struct ClusterSummery {
-    id: i64,
+pub(crate) struct ClusterSummery {
+    id: ClusterId,
     kind: Kind,
     name: String,
     config: HashMap<String, String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<Owner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ownership_confirmed_at: Option<DateTime<Utc>>,
+    ownership_stale: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<LintWarning>,
 }
 
 impl Cluster {
-    fn to_summary(&self) -> ClusterSummery {
+    pub(crate) fn to_summary(&self, policy: &OwnershipPolicy) -> ClusterSummery {
+        let stale = policy.stale(self.owner.as_ref(), self.ownership_confirmed_at, Utc::now());
+
         ClusterSummery {
             id: self.id,
             kind: self.kind.clone(),
             name: self.name.clone(),
-            config: self.config.clone(),
+            config: sensitive::redact(&self.config),
             created_at: self.created_at,
             updated_at: self.updated_at,
+            owner: self.owner.clone(),
+            ownership_confirmed_at: self.ownership_confirmed_at,
+            ownership_stale: stale.is_some(),
+            warnings: lint::lint(Subject::Cluster(self)),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn it_filters_and_confirms_owned_clusters() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::json;
+
+    use crate::clusters::store::MemoryClusterStore;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let factory: crate::kafka::metadata::manager::MetadataConsumerFactory =
+        Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store.clone()))
+            .app_data(manager)
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let create = |name: &str, owner: serde_json::Value| {
+        let body = json!({ "kind": "Kafka", "name": name, "config": { "bootstrap.servers": "localhost:9092" }, "owner": owner });
+        test::TestRequest::post()
+            .uri("/api/v1/clusters")
+            .set_json(body)
+            .to_request()
+    };
+
+    let owner = json!({ "team": "payments", "email": "pay@example.com" });
+    let res = test::call_service(&app, create("payments", owner)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, create("billing", json!({ "team": "Billing" }))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, create("search", json!({}))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Owners without a team, or with a malformed email, are rejected.
+    let res = test::call_service(&app, create("x", json!({ "email": "pay@example.com" }))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test::call_service(&app, create("x", json!({ "team": "x", "email": "x" }))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // So are unknown auth providers.
+    let body = json!({
+        "kind": "Kafka",
+        "name": "c",
+        "config": { "bootstrap.servers": "localhost:9092", "auth.provider": "kerberos" },
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/v1/clusters")
+        .set_json(body)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Invalid fields are each reported, and nothing is created.
+    let body =
+        json!({ "kind": "Kafka", "name": " ", "config": { "metadata.poll.interval.ms": "0" } });
+    let req = test::TestRequest::post()
+        .uri("/api/v1/clusters")
+        .set_json(body)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "invalid_request");
+    let fields = body["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        vec![
+            "name",
+            "config.bootstrap.servers",
+            "config.metadata.poll.interval.ms"
+        ]
+    );
+    assert_eq!(store.count(None).await.unwrap(), 3);
+
+    let list = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/clusters{}", query))
+            .to_request()
+    };
+    let names = |body: serde_json::Value| {
+        body["clusters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| {
+                (
+                    c["id"].as_i64().unwrap(),
+                    c["ownership_stale"].as_bool().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let body = test::call_and_read_body_json(&app, list("")).await;
+    assert_eq!(names(body), vec![(1, false), (2, false), (3, true)]);
+    let body = test::call_and_read_body_json(&app, list("?team=payments")).await;
+    assert_eq!(names(body), vec![(1, false)]);
+    let body = test::call_and_read_body_json(&app, list("?team=billing")).await;
+    assert_eq!(names(body), vec![(2, false)]);
+    let body = test::call_and_read_body_json(&app, list("?team=search")).await;
+    assert_eq!(names(body), vec![]);
+
+    // Updates without an owner keep it, and an empty owner clears it.
+    let update = |owner: Option<serde_json::Value>| {
+        let mut body = json!({ "kind": "Kafka", "name": "c", "config": { "bootstrap.servers": "localhost:9092" } });
+        if let Some(owner) = owner {
+            body["owner"] = owner;
+        }
+        test::TestRequest::put()
+            .uri("/api/v1/clusters/1")
+            .set_json(body)
+            .to_request()
+    };
+    test::call_service(&app, update(None)).await;
+    assert!(store
+        .get(ClusterId(1))
+        .await
+        .unwrap()
+        .unwrap()
+        .owner
+        .is_some());
+    test::call_service(&app, update(Some(json!({})))).await;
+    assert!(store
+        .get(ClusterId(1))
+        .await
+        .unwrap()
+        .unwrap()
+        .owner
+        .is_none());
+
+    let confirm = |id: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/clusters/{}/confirm-ownership", id))
+            .to_request()
+    };
+    let res = test::call_service(&app, confirm(1)).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = test::call_service(&app, confirm(9)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let before = store.get(ClusterId(2)).await.unwrap().unwrap();
+    let res = test::call_service(&app, confirm(2)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let after = store.get(ClusterId(2)).await.unwrap().unwrap();
+    assert!(after.ownership_confirmed_at > before.ownership_confirmed_at);
+}
+
+#[actix_web::test]
+async fn it_streams_metadata_without_holding_the_cache() {
+    use std::time::Duration;
+
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let sync = |version, entry| CacheSync {
+        cursor: SyncCursor {
+            instance: "primary".to_string(),
+            version,
+        },
+        full: true,
+        entries: vec![SyncedEntry {
+            cluster_id: ClusterId(1),
+            entry,
+            offsets: None,
+        }],
+        clusters: vec![ClusterId(1)],
+    };
+
+    let names = (0..5_000)
+        .map(|i| format!("topic-{}", i))
+        .collect::<Vec<_>>();
+    let topics = names.iter().map(|n| (n.as_str(), 10)).collect::<Vec<_>>();
+    let entry = CachedMetadataEntry::Meta(metadata(&[1, 2, 3], &topics));
+    let size = serde_json::to_vec(&entry).unwrap().len();
+    manager.apply(sync(1, entry)).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/clusters/1/metadata")
+        .insert_header(("Accept", ndjson::CONTENT_TYPE))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        ndjson::CONTENT_TYPE
+    );
+    let mut body = res.map_into_boxed_body().into_body();
+
+    let first = ndjson::next_line(&mut body).await.unwrap();
+    let first: serde_json::Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(first["broker"]["id"], 1);
+
+    // The entry was snapshotted, so the cache is written to mid-stream.
+    let failed = CachedMetadataEntry::Failed("unreachable".to_string());
+    tokio::time::timeout(Duration::from_secs(1), manager.apply(sync(2, failed)))
+        .await
+        .unwrap();
+
+    let mut lines = vec![];
+    while let Some(line) = ndjson::next_line(&mut body).await {
+        lines.push(line);
+    }
+    assert_eq!(lines.len(), 2 + 5_000 + 1);
+    let largest = lines.iter().map(|l| l.len()).max().unwrap();
+    assert!(largest * 100 < size, "{} {}", largest, size);
+
+    let topic: serde_json::Value = serde_json::from_str(&lines[2]).unwrap();
+    assert_eq!(topic["topic"]["name"], "topic-0");
+    assert_eq!(topic["topic"]["partitions"].as_array().unwrap().len(), 10);
+    let summary: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+    assert_eq!(summary["summary"]["count"], 5_003);
+    assert_eq!(summary["summary"]["truncated"], false);
+
+    // Without asking for NDJSON, the response is the usual JSON entry.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/clusters/1/metadata")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["Failed"], "unreachable");
+}
+
+#[actix_web::test]
+async fn it_pages_clusters_without_changing_the_default_listing() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    for (name, team) in [
+        ("orders", "payments"),
+        ("search", "billing"),
+        ("refunds", "payments"),
+    ] {
+        let cluster = Cluster {
+            owner: Some(Owner::team(team)),
+            ..Cluster::new(None, Kind::Kafka, name.to_string(), HashMap::new())
+        };
+        store.insert(cluster).await.unwrap();
+    }
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(manager)
+            .configure(crate::server::routes),
+    )
+    .await;
+    let list = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/clusters{}", query))
+            .to_request()
+    };
+    let names = |items: &Value| {
+        items
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // The envelope and parameters from before are unchanged.
+    let body: Value = test::call_and_read_body_json(&app, list("")).await;
+    assert_eq!(
+        names(&body["clusters"]),
+        vec!["orders", "search", "refunds"]
+    );
+    assert!(body.get("items").is_none());
+    let before: Value = test::call_and_read_body_json(&app, list("?team=payments")).await;
+    assert_eq!(names(&before["clusters"]), vec!["orders", "refunds"]);
+
+    // The same listing through a filter, as a page.
+    let after: Value = test::call_and_read_body_json(&app, list("?filter=team:eq:payments")).await;
+    assert_eq!(names(&after["items"]), names(&before["clusters"]));
+    assert_eq!(after["total_estimate"], 2);
+    assert_eq!(after["next_cursor"], Value::Null);
+
+    let first: Value = test::call_and_read_body_json(&app, list("?sort=name:desc&limit=2")).await;
+    assert_eq!(names(&first["items"]), vec!["search", "refunds"]);
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let uri = format!("?sort=name:desc&limit=2&cursor={}", cursor);
+    let second: Value = test::call_and_read_body_json(&app, list(&uri)).await;
+    assert_eq!(names(&second["items"]), vec!["orders"]);
+    assert_eq!(second["total_estimate"], 3);
+
+    // Without filters or a sort, the store pages the clusters itself.
+    let page: Value = test::call_and_read_body_json(&app, list("?limit=2&offset=1")).await;
+    assert_eq!(names(&page["items"]), vec!["search", "refunds"]);
+    assert_eq!(page["total"], 3);
+    assert_eq!(
+        (&page["limit"], &page["offset"]),
+        (&Value::from(2), &Value::from(1))
+    );
+    assert_eq!(page["next_cursor"], Value::Null);
+
+    let past: Value = test::call_and_read_body_json(&app, list("?offset=5")).await;
+    assert_eq!(names(&past["items"]), Vec::<String>::new());
+    assert_eq!(past["total"], 3);
+
+    // Narrowed by kind and name, in the order asked for, which the page names.
+    let body: Value = test::call_and_read_body_json(&app, list("?name_contains=R")).await;
+    assert_eq!(
+        names(&body["clusters"]),
+        vec!["orders", "search", "refunds"]
+    );
+    let body: Value =
+        test::call_and_read_body_json(&app, list("?kind=kafka&name_contains=ER&sort=name")).await;
+    assert_eq!(names(&body["items"]), vec!["orders", "refunds"]);
+    assert_eq!(body["sort"], "name:asc");
+    let body: Value = test::call_and_read_body_json(&app, list("?sort=name&order=desc")).await;
+    assert_eq!(names(&body["items"]), vec!["search", "refunds", "orders"]);
+    assert_eq!(body["sort"], "name:desc");
+    let body: Value = test::call_and_read_body_json(&app, list("?kind=unknown")).await;
+    assert_eq!(names(&body["clusters"]), Vec::<String>::new());
+
+    let res = test::call_service(&app, list("?order=desc")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test::call_service(&app, list("?sort=config")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = test::read_body(res).await;
+    assert_eq!(
+        body,
+        "cannot sort by 'config', expected one of: id, name, created_at, updated_at"
+    );
+}
+
+#[actix_web::test]
+async fn it_tells_uncached_clusters_from_missing_ones() {
+    use std::time::Duration;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::{MetadataConsumerFactory, HEAL_INTERVAL};
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    tokio::time::pause();
+
+    // Registrations fail, so a healed cluster stays uncached.
+    let registrations = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counted = registrations.clone();
+    let factory: MetadataConsumerFactory = Arc::new(move |_| {
+        counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Err("no brokers in tests".into())
+    });
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    for name in ["cached", "uncached"] {
+        let cluster = Cluster::new(None, Kind::Kafka, name.to_string(), HashMap::new());
+        store.insert(cluster).await.unwrap();
+    }
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            entries: vec![SyncedEntry {
+                cluster_id: ClusterId(1),
+                entry: CachedMetadataEntry::Meta(metadata(&[1], &[])),
+                offsets: None,
+            }],
+            clusters: vec![ClusterId(1)],
+        })
+        .await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(manager)
+            .configure(crate::server::routes),
+    )
+    .await;
+    let get = |id: i64| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/clusters/{}/metadata", id))
+            .to_request()
+    };
+
+    let res = test::call_service(&app, get(1)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get(REGISTRATION_STARTED).is_none());
+
+    let res = test::call_service(&app, get(2)).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(res.headers()[REGISTRATION_STARTED], "true");
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body, "Processing");
+    assert_eq!(registrations.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // Repeated misses don't register the cluster again until the interval passed.
+    for _ in 0..10 {
+        let res = test::call_service(&app, get(2)).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(res.headers()[REGISTRATION_STARTED], "false");
+    }
+    assert_eq!(registrations.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    tokio::time::advance(HEAL_INTERVAL + Duration::from_secs(1)).await;
+    let res = test::call_service(&app, get(2)).await;
+    assert_eq!(res.headers()[REGISTRATION_STARTED], "true");
+    assert_eq!(registrations.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    // Clusters that don't exist are never registered.
+    let res = test::call_service(&app, get(9)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(registrations.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+/// A consumer serving metadata with a `billing` group behind in `orders`, or
+/// failing every fetch when it can't reach the brokers.
+#[cfg(test)]
+struct LaggingConsumer {
+    reachable: bool,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl crate::kafka::metadata::consumer::MetadataConsumer for LaggingConsumer {
+    async fn fetch_meta(
+        &self,
+    ) -> Result<crate::kafka::metadata::ClusterMetadata, crate::errors::AnyError> {
+        if !self.reachable {
+            return Err("brokers are unreachable".into());
+        }
+        let mut metadata = crate::history::diff::metadata(&[1], &[("orders", 2)]);
+        metadata.groups.push(GroupMetadata {
+            name: "billing".to_string(),
+            state: "Empty".to_string(),
+            members: vec![],
+            managed_by_seekr: false,
+        });
+        Ok(metadata)
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &crate::kafka::metadata::ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<crate::kafka::metadata::TopicOffsets>, crate::errors::AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        group: &str,
+        _metadata: &crate::kafka::metadata::ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, crate::errors::AnyError> {
+        assert_eq!(group, "billing");
+        Ok(vec![GroupLag {
+            topic: "orders".to_string(),
+            partition: 1,
+            committed: 40,
+            high_watermark: 42,
+            lag: 2,
+        }])
+    }
+
+    async fn fetch_topic_config(
+        &self,
+        topic: &str,
+    ) -> Result<Vec<crate::kafka::metadata::TopicConfigEntry>, crate::errors::AnyError> {
+        use crate::kafka::metadata::TopicConfigSource;
+
+        assert_eq!(topic, "orders");
+        Ok(vec![TopicConfigEntry {
+            name: "retention.ms".to_string(),
+            value: Some("86400000".to_string()),
+            source: TopicConfigSource::Topic,
+            is_default: false,
+            is_read_only: false,
+            is_sensitive: false,
+        }])
+    }
+}
+
+#[actix_web::test]
+async fn it_tags_metadata_and_answers_pollers_holding_it() {
+    use actix_web::http::header::{ETAG, IF_NONE_MATCH};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let sync = |version, topics: &[(&str, usize)]| CacheSync {
+        cursor: SyncCursor {
+            instance: "primary".to_string(),
+            version,
+        },
+        full: true,
+        entries: vec![SyncedEntry {
+            cluster_id: ClusterId(1),
+            entry: CachedMetadataEntry::Meta(metadata(&[1], topics)),
+            offsets: None,
+        }],
+        clusters: vec![ClusterId(1)],
+    };
+    manager.apply(sync(1, &[("orders", 2)])).await;
+
+    // Reads share the cached entry rather than copying it.
+    let read = || manager.clone().into_inner().get(ClusterId(1), None);
+    let (a, b) = (read().await.unwrap(), read().await.unwrap());
+    assert!(Arc::ptr_eq(&a.unwrap(), &b.unwrap()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let get = |etag: Option<&str>| {
+        let mut req = test::TestRequest::get().uri("/api/v1/clusters/1/metadata");
+        if let Some(etag) = etag {
+            req = req.insert_header((IF_NONE_MATCH, etag));
         }
+        req.to_request()
+    };
+
+    let res = test::call_service(&app, get(None)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let etag = res.headers()[ETAG].to_str().unwrap().to_string();
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["Meta"]["topics"][0]["name"], "orders");
+
+    // Pollers holding the metadata get no body until it changes.
+    let res = test::call_service(&app, get(Some(&etag))).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers()[ETAG], etag.as_str());
+    assert!(test::read_body(res).await.is_empty());
+
+    manager
+        .apply(sync(2, &[("orders", 2), ("refunds", 1)]))
+        .await;
+    let res = test::call_service(&app, get(Some(&etag))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_ne!(res.headers()[ETAG], etag.as_str());
+}
+
+#[actix_web::test]
+async fn it_reports_group_lag_once_metadata_is_cached() {
+    use std::time::Duration;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    tokio::time::pause();
+
+    let store = Arc::new(MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|c| {
+        Ok(Arc::new(LaggingConsumer {
+            reachable: c.id == ClusterId(1),
+        }))
+    });
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    for id in [1, 2] {
+        let mut cluster = Cluster::new(None, Kind::Kafka, id.to_string(), HashMap::new());
+        cluster.id = ClusterId(id);
+        manager.clone().into_inner().register(cluster, None).await;
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = store;
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let get = |id: i64, group: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/clusters/{}/groups/{}/lag", id, group))
+            .to_request()
+    };
+
+    let body: serde_json::Value = test::call_and_read_body_json(&app, get(1, "billing")).await;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "group": "billing",
+            "lag": [{
+                "topic": "orders",
+                "partition": 1,
+                "committed": 40,
+                "high_watermark": 42,
+                "lag": 2,
+            }],
+        })
+    );
+
+    let res = test::call_service(&app, get(1, "shipping")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "code": "not_found",
+            "message": "Consumer group 'shipping' not found",
+        })
+    );
+
+    let res = test::call_service(&app, get(2, "billing")).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let request_id = res
+        .headers()
+        .get(crate::request_id::HEADER)
+        .cloned()
+        .unwrap();
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "unavailable");
+    assert_eq!(body["correlation_id"], request_id.to_str().unwrap());
+    assert_eq!(
+        body["message"],
+        "Cluster metadata with id '2' is still processing"
+    );
+
+    let res = test::call_service(&app, get(9, "billing")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Topics are read from the cache, their settings from the brokers.
+    let topic = |id: i64, topic: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/clusters/{}/topics/{}", id, topic))
+            .to_request()
+    };
+    let body: serde_json::Value = test::call_and_read_body_json(&app, topic(1, "orders")).await;
+    assert_eq!(body["topic"]["partitions"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        body["config"],
+        serde_json::json!([{
+            "name": "retention.ms",
+            "value": "86400000",
+            "source": "topic",
+            "is_default": false,
+            "is_read_only": false,
+            "is_sensitive": false,
+        }])
+    );
+    for (id, name, status) in [
+        (1, "refunds", StatusCode::NOT_FOUND),
+        (2, "orders", StatusCode::SERVICE_UNAVAILABLE),
+        (9, "orders", StatusCode::NOT_FOUND),
+    ] {
+        let res = test::call_service(&app, topic(id, name)).await;
+        assert_eq!(res.status(), status, "{} in {}", name, id);
+    }
+
+    // A refresh answers with what its poll found, the broker error included.
+    let refresh = |id: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/clusters/{}/metadata/refresh", id))
+            .to_request()
+    };
+    let res = test::call_service(&app, refresh(1)).await;
+    assert_eq!(res.headers()[METADATA_STALE], "false");
+    assert!(res.headers().contains_key(METADATA_FETCHED_AT));
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["Meta"]["topics"][0]["name"], "orders");
+    let body: serde_json::Value = test::call_and_read_body_json(&app, refresh(2)).await;
+    assert!(body["Failed"]
+        .as_str()
+        .unwrap()
+        .contains("brokers are unreachable"));
+    let res = test::call_service(&app, refresh(9)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    manager.into_inner().stop().await;
+}
+
+#[actix_web::test]
+async fn it_patches_clusters_keeping_what_is_left_out() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use chrono::TimeZone;
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let created_at = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+    let config = HashMap::from([
+        ("bootstrap.servers".to_string(), "a:9092".to_string()),
+        ("metadata.priority".to_string(), "low".to_string()),
+    ]);
+    let cluster = Cluster::init(
+        ClusterId(1),
+        Kind::Kafka,
+        "orders".to_string(),
+        config,
+        created_at,
+        created_at,
+    );
+    store.insert(cluster).await.unwrap();
+
+    let factory: MetadataConsumerFactory =
+        Arc::new(|_| Ok(Arc::new(LaggingConsumer { reachable: false })));
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store.clone()))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let patch = |id: i64, body: serde_json::Value| {
+        test::TestRequest::patch()
+            .uri(&format!("/api/v1/clusters/{}", id))
+            .set_json(body)
+            .to_request()
+    };
+
+    let res = test::call_service(
+        &app,
+        patch(
+            1,
+            serde_json::json!({
+                "config": {"bootstrap.servers": "b:9092", "metadata.priority": null},
+            }),
+        ),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let patched = store.get(ClusterId(1)).await.unwrap().unwrap();
+    assert_eq!(patched.name, "orders");
+    assert_eq!(
+        patched.config,
+        HashMap::from([("bootstrap.servers".to_string(), "b:9092".to_string())])
+    );
+    assert_eq!(patched.created_at, created_at);
+    assert!(patched.updated_at > created_at);
+
+    let res = test::call_service(&app, patch(1, serde_json::json!({"name": "payments"}))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let renamed = store.get(ClusterId(1)).await.unwrap().unwrap();
+    assert_eq!(renamed.name, "payments");
+    assert_eq!(renamed.config, patched.config);
+
+    // Replacing keeps when the cluster was created too.
+    let req = test::TestRequest::put()
+        .uri("/api/v1/clusters/1")
+        .set_json(serde_json::json!({
+            "kind": "Kafka",
+            "name": "orders",
+            "config": {"bootstrap.servers": "c:9092"},
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let replaced = store.get(ClusterId(1)).await.unwrap().unwrap();
+    assert_eq!(replaced.created_at, created_at);
+    assert_eq!(
+        replaced.config,
+        HashMap::from([("bootstrap.servers".to_string(), "c:9092".to_string())])
+    );
+
+    let res = test::call_service(&app, patch(9, serde_json::json!({"name": "x"}))).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    manager.into_inner().stop().await;
+}
+
+#[actix_web::test]
+async fn it_creates_reads_updates_and_deletes_clusters() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::shards::store::MemoryDocumentStore;
+    use crate::subscriptions::store::{MemoryPurgeLog, MemorySubscriptionStore};
+    use crate::subscriptions::subscription::Subscription;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let subscriptions: Arc<dyn SubscriptionStore + Send + Sync> =
+        Arc::new(MemorySubscriptionStore::default());
+    let factory: crate::kafka::metadata::manager::MetadataConsumerFactory =
+        Arc::new(|_| Err("no brokers in tests".into()));
+    let documents: Arc<dyn DocumentStore + Send + Sync> = Arc::new(MemoryDocumentStore::default());
+    let purges: Arc<dyn PurgeLog + Send + Sync> = Arc::new(MemoryPurgeLog::default());
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store.clone()))
+            .app_data(Data::new(subscriptions.clone()))
+            .app_data(Data::new(documents))
+            .app_data(Data::new(purges))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let get = |id: i64| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/clusters/{}", id))
+            .to_request()
+    };
+    let delete = |id: i64| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/v1/clusters/{}", id))
+            .to_request()
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/clusters")
+        .set_json(json!({ "kind": "Kafka", "name": "orders", "config": { "bootstrap.servers": "localhost:9092" } }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created["id"], 1);
+
+    let read: Value = test::call_and_read_body_json(&app, get(1)).await;
+    assert_eq!(read["cluster"]["name"], "orders");
+
+    let req = test::TestRequest::put()
+        .uri("/api/v1/clusters/1")
+        .set_json(json!({ "kind": "Kafka", "name": "payments", "config": { "bootstrap.servers": "localhost:9092" } }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/clusters")
+        .to_request();
+    let listed: Value = test::call_and_read_body_json(&app, req).await;
+    let names: Vec<_> = listed["clusters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].clone())
+        .collect();
+    assert_eq!(names, vec![json!("payments")]);
+
+    // Its subscriptions go with it, those of other clusters stay.
+    for (cluster_id, topic) in [(1, "orders"), (1, "refunds"), (2, "orders")] {
+        let s = Subscription::new(None, ClusterId(cluster_id), topic.into(), HashMap::new());
+        subscriptions.insert(s).await.unwrap();
+    }
+    let deleted: Value = test::call_and_read_body_json(&app, delete(1)).await;
+    assert_eq!(
+        deleted,
+        json!({ "cluster_id": 1, "subscriptions_deleted": 2 })
+    );
+    assert_eq!(subscriptions.count(Some(ClusterId(1))).await.unwrap(), 0);
+    assert_eq!(subscriptions.count(None).await.unwrap(), 1);
+    let res = test::call_service(&app, get(1)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Deleting what is already gone says so, rather than succeeding again.
+    let res = test::call_service(&app, delete(1)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    manager.into_inner().stop().await;
+}
+
+#[actix_web::test]
+async fn it_refuses_names_taken_by_other_clusters() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    use crate::clusters::store::MemoryClusterStore;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let factory: crate::kafka::metadata::manager::MetadataConsumerFactory =
+        Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store.clone()))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let create = |name: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/clusters")
+            .set_json(json!({ "kind": "Kafka", "name": name, "config": { "bootstrap.servers": "localhost:9092" } }))
+            .to_request()
+    };
+    let update = |id: i64, name: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/api/v1/clusters/{}", id))
+            .set_json(json!({ "kind": "Kafka", "name": name, "config": { "bootstrap.servers": "localhost:9092" } }))
+            .to_request()
+    };
+
+    for name in ["prod", "staging"] {
+        let res = test::call_service(&app, create(name)).await;
+        assert_eq!(res.status(), StatusCode::OK);
     }
+
+    // Names differing only in case or surrounding whitespace are the same.
+    let res = test::call_service(&app, create(" PROD ")).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "conflict");
+    assert_eq!(body["details"]["conflicting_id"], 1);
+    assert_eq!(store.count(None).await.unwrap(), 2);
+
+    // A cluster keeps its own name, but can't take another's.
+    let res = test::call_service(&app, update(1, "Prod")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, update(2, "prod")).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let req = test::TestRequest::patch()
+        .uri("/api/v1/clusters/2")
+        .set_json(json!({ "name": "prod" }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::CONFLICT
+    );
+    let staging = store.get(ClusterId(2)).await.unwrap().unwrap();
+    assert_eq!(staging.name, "staging");
+
+    manager.into_inner().stop().await;
+}
+
+#[actix_web::test]
+async fn it_creates_and_deletes_topics_through_the_brokers() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use rdkafka::types::RDKafkaErrorCode;
+    use serde_json::{json, Value};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::admin::RecordingTopicAdmin;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(None, Kind::Kafka, "orders".to_string(), HashMap::new());
+    let id = store.insert(cluster).await.unwrap();
+    let factory: MetadataConsumerFactory =
+        Arc::new(|_| Ok(Arc::new(LaggingConsumer { reachable: true })));
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let cluster = store.get(id).await.unwrap().unwrap();
+    manager.clone().into_inner().register(cluster, None).await;
+
+    let admin = Arc::new(RecordingTopicAdmin {
+        failures: HashMap::from([
+            ("orders".to_string(), RDKafkaErrorCode::TopicAlreadyExists),
+            (
+                "wide".to_string(),
+                RDKafkaErrorCode::InvalidReplicationFactor,
+            ),
+            (
+                "refunds".to_string(),
+                RDKafkaErrorCode::UnknownTopicOrPartition,
+            ),
+        ]),
+        ..Default::default()
+    });
+    let topic_admin: Arc<dyn TopicAdmin + Send + Sync> = admin.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(Data::new(topic_admin))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let create = |cluster: i64, name: &str, query: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/clusters/{}/topics{}", cluster, query))
+            .set_json(json!({
+                "name": name,
+                "partitions": 3,
+                "replication_factor": 3,
+                "config": { "retention.ms": "86400000" },
+            }))
+            .to_request()
+    };
+    let delete = |name: &str| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/v1/clusters/1/topics/{}", name))
+            .to_request()
+    };
+
+    let res = test::call_service(&app, create(1, "payments", "?dry_run=true")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body, json!({ "name": "payments", "dry_run": true }));
+    let res = test::call_service(&app, create(1, "payments", "")).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    // Refusals of the brokers are answered with their reason.
+    let res = test::call_service(&app, create(1, "orders", "")).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = test::call_service(&app, create(1, "wide", "")).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "invalid_topic");
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Topic 'wide': "));
+
+    // Topics Kafka would refuse and missing clusters never reach the brokers.
+    let res = test::call_service(&app, create(1, "orders v2", "")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test::call_service(&app, create(9, "payments", "")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = test::call_service(&app, delete("payments")).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = test::call_service(&app, delete("refunds")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    assert_eq!(
+        *admin.calls.lock().unwrap(),
+        [
+            "create payments dry_run=true",
+            "create payments dry_run=false",
+            "create orders dry_run=false",
+            "create wide dry_run=false",
+            "delete payments",
+            "delete refunds",
+        ]
+    );
+
+    manager.into_inner().stop().await;
+}
+
+#[actix_web::test]
+async fn it_answers_not_found_for_clusters_missing_from_meilisearch() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::store::MSClusterStore;
+
+    let (config, meilisearch) = crate::meilisearch::missing();
+    let store: Arc<dyn ClusterStore + Send + Sync> =
+        Arc::new(MSClusterStore::new(config.client(), crate::id::generator()).await);
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/clusters/42")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["code"], "not_found");
+
+    meilisearch.stop(true).await;
 }
@@ -0,0 +1,745 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use actix_web::http::header::LOCATION;
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Json, Path, Query, ServiceConfig};
+use actix_web::{delete, get, patch, post, put, HttpResponse, Responder};
+use chrono::Utc;
+use seekr_api_types::clusters::{
+    AdaptiveResource, CacheResource, ClusterKind, ClusterRequest, ClusterResource, IdResponse,
+    ListClustersQuery, ListClustersResponse, PollingResource, ReadClusterResponse,
+};
+use seekr_api_types::subscriptions::{
+    CreateClusterSubscriptionRequest, IdResponse as SubscriptionIdResponse, ListSubscriptionsQuery,
+    PatchSubscriptionRequest, UpdateSubscriptionRequest,
+};
+use serde::Serialize;
+
+use crate::api::list::{ListError, ListQuery};
+use crate::api::{error, retry};
+use crate::auth::Principal;
+use crate::clusters::cluster::{Cluster, Kind};
+use crate::clusters::service::{self, ClusterFilter, MetadataRead, NameTaken};
+use crate::clusters::store::{ClusterNotFound, ClusterStore};
+use crate::commands::store::CommandStore;
+use crate::errors::AnyError;
+use crate::governance::owner::Owner;
+use crate::governance::policy::OwnershipPolicy;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::auth::AuthProvider;
+use crate::kafka::metadata::classify::{Include, ResourceCounts};
+use crate::kafka::metadata::manager::{
+    CacheInfo, CachedMetadataEntry, InvalidConsumerConfig, MetadataManager,
+};
+use crate::kafka::metadata::redact::{RedactQuery, Redaction, RedactionPolicy};
+use crate::kafka::metadata::schedule::PollStats;
+use crate::kafka::metadata::ClusterMetadata;
+use crate::kafka::sensitive;
+use crate::lint::{self, LintPolicy, Subject};
+use crate::shards::store::DocumentStore;
+use crate::standby::Availability;
+use crate::subscriptions::endpoints::v2::{self as subscriptions, DeleteSubscriptionQuery};
+use crate::subscriptions::store::{PurgeLog, SubscriptionStore};
+use crate::subscriptions::subscription::Subscription;
+use crate::validate;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(create_cluster)
+        .service(get_clusters)
+        .service(get_cluster)
+        .service(update_cluster)
+        .service(delete_cluster)
+        .service(get_cluster_metadata)
+        .service(create_cluster_subscription)
+        .service(get_cluster_subscriptions)
+        .service(get_cluster_subscription)
+        .service(update_cluster_subscription)
+        .service(patch_cluster_subscription)
+        .service(delete_cluster_subscription);
+}
+
+#[post("")]
+async fn create_cluster(
+    r: Json<ClusterRequest>,
+    principal: Principal,
+    lints: LintPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> impl Responder {
+    if principal.is_scoped() {
+        let message = "Keys scoped to clusters can't create clusters";
+        return error::error(StatusCode::FORBIDDEN, "forbidden", message);
+    }
+
+    let r = r.into_inner();
+    if let Err(errors) = validate::cluster(&r.kind.into(), &r.name, &r.config) {
+        return error::invalid_fields(errors);
+    }
+    if let Some(Err(e)) = r.owner.as_ref().map(Owner::validate) {
+        return error::invalid(e);
+    }
+    if let Err(e) = AuthProvider::of(&r.config) {
+        return error::invalid(e);
+    }
+    let cluster = Cluster::new(None, r.kind.into(), r.name.clone(), r.config.clone());
+    if let Err(e) = lints.check(&lint::lint(Subject::Cluster(&cluster))) {
+        return error::invalid(e);
+    }
+
+    let manager = manager.into_inner();
+    match service::create(
+        store.as_ref().as_ref(),
+        manager,
+        r.kind.into(),
+        r.name,
+        r.config,
+        r.owner,
+    )
+    .await
+    {
+        Ok(id) => HttpResponse::Created().json(IdResponse { id }),
+        Err(e) => write_failed(e),
+    }
+}
+
+/// Answers a create or update that failed: `409` naming the other cluster
+/// when the name is taken, `400` when no metadata consumer can be built from
+/// the config.
+fn write_failed(e: AnyError) -> HttpResponse {
+    if let Some(e) = e.downcast_ref::<NameTaken>() {
+        return error::error(StatusCode::CONFLICT, "name_taken", e.to_string());
+    }
+    match e.downcast_ref::<InvalidConsumerConfig>() {
+        Some(e) => error::invalid(e.to_string()),
+        None => error::internal(e),
+    }
+}
+
+#[get("")]
+async fn get_clusters(
+    query: Query<ListClustersQuery>,
+    list: Result<ListQuery<Cluster>, ListError>,
+    principal: Principal,
+    policy: OwnershipPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+) -> impl Responder {
+    let list = match list {
+        Ok(list) => list,
+        Err(e) => return error::invalid(e.0),
+    };
+    let filter = ClusterFilter {
+        team: query.team.clone(),
+        ..Default::default()
+    };
+    let clusters = match service::list(store.as_ref().as_ref(), &filter).await {
+        Ok(clusters) => clusters,
+        Err(e) => return error::internal(e),
+    };
+    let page = list.apply(
+        clusters
+            .into_iter()
+            .filter(|c| principal.can_access(c.id))
+            .collect(),
+    );
+
+    match list.is_given() {
+        true => HttpResponse::Ok().json(page.map(|c| cluster_resource(&c, &policy))),
+        false => HttpResponse::Ok().json(ListClustersResponse {
+            clusters: page
+                .items
+                .iter()
+                .map(|c| cluster_resource(c, &policy))
+                .collect(),
+        }),
+    }
+}
+
+#[get("/{id}")]
+async fn get_cluster(
+    id: Path<ClusterId>,
+    policy: OwnershipPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+) -> impl Responder {
+    let id = id.into_inner();
+
+    match service::get(store.as_ref().as_ref(), id).await {
+        Ok(Some(c)) => HttpResponse::Ok().json(ReadClusterResponse {
+            cluster: cluster_resource(&c, &policy),
+        }),
+        Ok(None) => error::not_found(format!("Cluster with id '{}' not found", id)),
+        Err(e) => error::internal(e),
+    }
+}
+
+#[put("/{id}")]
+async fn update_cluster(
+    id: Path<ClusterId>,
+    r: Json<ClusterRequest>,
+    lints: LintPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let r = r.into_inner();
+    if let Err(errors) = validate::cluster(&r.kind.into(), &r.name, &r.config) {
+        return error::invalid_fields(errors);
+    }
+    if let Some(Err(e)) = r.owner.as_ref().map(Owner::validate) {
+        return error::invalid(e);
+    }
+    if let Err(e) = AuthProvider::of(&r.config) {
+        return error::invalid(e);
+    }
+    let cluster = Cluster::new(Some(id), r.kind.into(), r.name.clone(), r.config.clone());
+    if let Err(e) = lints.check(&lint::lint(Subject::Cluster(&cluster))) {
+        return error::invalid(e);
+    }
+
+    let result = service::update(
+        store.as_ref().as_ref(),
+        manager.into_inner(),
+        id,
+        r.kind.into(),
+        r.name,
+        r.config,
+        r.owner,
+    );
+
+    match result.await {
+        Ok(Some(id)) => HttpResponse::Ok().json(IdResponse { id }),
+        Ok(None) => error::not_found(format!("Cluster with id '{}' not found", id)),
+        Err(e) => write_failed(e),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_cluster(
+    id: Path<ClusterId>,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    subscriptions: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    documents: Data<Arc<dyn DocumentStore + Send + Sync>>,
+    purges: Data<Arc<dyn PurgeLog + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> impl Responder {
+    let id = id.into_inner();
+
+    let result = service::delete_with_subscriptions(
+        store.as_ref().as_ref(),
+        subscriptions.as_ref().as_ref(),
+        documents.as_ref().as_ref(),
+        purges.as_ref().as_ref(),
+        manager.into_inner(),
+        id,
+    );
+
+    match result.await {
+        Ok(deletion) if deletion.is_complete() => HttpResponse::NoContent().finish(),
+        Ok(deletion) => {
+            let failed = deletion
+                .subscriptions_failed
+                .iter()
+                .map(|(id, e)| format!("{} ({})", id, e))
+                .collect::<Vec<_>>();
+            // The failures are the client's to act on, so they're answered.
+            error::error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
+                format!(
+                    "Cluster with id '{}' was kept, its subscriptions {} couldn't be deleted",
+                    id,
+                    failed.join(", ")
+                ),
+            )
+        }
+        Err(e) => match e.downcast_ref::<ClusterNotFound>() {
+            Some(e) => error::not_found(e.to_string()),
+            None => error::internal(e),
+        },
+    }
+}
+
+#[get("/{id}/metadata")]
+async fn get_cluster_metadata(
+    path: Path<ClusterId>,
+    include: Query<Include>,
+    redact: Query<RedactQuery>,
+    policy: RedactionPolicy,
+    store: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+    availability: Option<Data<Availability>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let redaction = match redact.redact.as_deref().map(|r| policy.resolve(r)) {
+        Some(Ok(redaction)) => redaction,
+        Some(Err(e)) => return error::invalid(e),
+        None => Redaction::default(),
+    };
+
+    // Standbys sync their cache rather than polling, so only a primary heals it.
+    let heal = availability.map_or(true, |a| a.is_primary());
+    let manager = manager.into_inner();
+    let read = service::metadata(store.as_ref().as_ref(), manager.clone(), id, heal).await;
+    let (entry, registration_started) = match read {
+        Ok(MetadataRead::Cached(entry)) => (entry, None),
+        Ok(MetadataRead::Uncached { registering }) => {
+            (Arc::new(CachedMetadataEntry::Processing), Some(registering))
+        }
+        Ok(MetadataRead::Missing) => {
+            return error::not_found(format!("Cluster metadata with id '{}' not found", id))
+        }
+        Err(e) => return error::internal(e),
+    };
+
+    let status = match *entry {
+        CachedMetadataEntry::Unknown | CachedMetadataEntry::Processing => StatusCode::ACCEPTED,
+        _ => StatusCode::OK,
+    };
+    let hint = service::retry_after(&manager, id, &entry).await;
+    let polling = service::polling(&manager, id).await;
+    let cache = service::cache_info(&manager, id, &entry).await;
+    let counts = match entry.as_ref() {
+        CachedMetadataEntry::Meta(m) => Some(ResourceCounts::of(m, include.into_inner())),
+        _ => None,
+    };
+    // Counts are the same either way, a redaction renames rather than removes.
+    let entry = match redaction.is_empty() {
+        true => Cow::Borrowed(entry.as_ref()),
+        false => Cow::Owned(policy.apply_entry(&redaction, &entry)),
+    };
+    let resource = MetadataView {
+        metadata: metadata_resource(&entry),
+        counts,
+        polling: polling.as_ref().map(polling_resource),
+        cache: cache.map(cache_resource),
+        registration_started,
+    };
+
+    match hint {
+        Some(hint) => retry::retry_later(status, resource, hint),
+        None => HttpResponse::build(status).json(resource),
+    }
+}
+
+// The subscriptions of a cluster, addressed under it rather than by a
+// cluster id in the body, and otherwise served as `api/v2/subscriptions` is.
+
+#[post("/{cluster_id}/subscriptions")]
+async fn create_cluster_subscription(
+    path: Path<ClusterId>,
+    r: Json<CreateClusterSubscriptionRequest>,
+    principal: Principal,
+    lints: LintPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let cluster_id = path.into_inner();
+    let r = r.into_inner();
+    let result = subscriptions::create(
+        &principal,
+        &lints,
+        cs.as_ref().as_ref(),
+        ss.as_ref().as_ref(),
+        cluster_id,
+        r.topic_name,
+        r.config,
+        r.owner,
+    );
+
+    match result.await {
+        Ok(id) => HttpResponse::Created()
+            .insert_header((
+                LOCATION,
+                format!("/api/v2/clusters/{}/subscriptions/{}", cluster_id, id),
+            ))
+            .json(SubscriptionIdResponse { id }),
+        Err(res) => res,
+    }
+}
+
+#[get("/{cluster_id}/subscriptions")]
+async fn get_cluster_subscriptions(
+    path: Path<ClusterId>,
+    query: Query<ListSubscriptionsQuery>,
+    list: Result<ListQuery<Subscription>, ListError>,
+    policy: OwnershipPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+    subscriptions::get_all(path.into_inner(), &query, list, &policy, cs, ss).await
+}
+
+#[get("/{cluster_id}/subscriptions/{id}")]
+async fn get_cluster_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    policy: OwnershipPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+    subscriptions::get_one(cluster_id, id, &policy, cs, ss).await
+}
+
+#[put("/{cluster_id}/subscriptions/{id}")]
+async fn update_cluster_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    r: Json<UpdateSubscriptionRequest>,
+    lints: LintPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+    subscriptions::update(cluster_id, id, r.into_inner(), &lints, cs, ss).await
+}
+
+#[patch("/{cluster_id}/subscriptions/{id}")]
+async fn patch_cluster_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    r: Json<PatchSubscriptionRequest>,
+    lints: LintPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+    subscriptions::patch(cluster_id, id, r.into_inner(), &lints, cs, ss).await
+}
+
+#[delete("/{cluster_id}/subscriptions/{id}")]
+async fn delete_cluster_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<DeleteSubscriptionQuery>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    subscriptions::delete(cluster_id, id, &query, ss.as_ref().as_ref(), qs.as_ref()).await
+}
+
+impl From<ClusterKind> for Kind {
+    fn from(k: ClusterKind) -> Self {
+        match k {
+            ClusterKind::Kafka => Kind::Kafka,
+            _ => Kind::Unknown,
+        }
+    }
+}
+
+fn cluster_kind(k: &Kind) -> ClusterKind {
+    match k {
+        Kind::Kafka => ClusterKind::Kafka,
+        _ => ClusterKind::Unknown,
+    }
+}
+
+fn cluster_resource(c: &Cluster, policy: &OwnershipPolicy) -> ClusterResource {
+    let stale = policy.stale(c.owner.as_ref(), c.ownership_confirmed_at, Utc::now());
+
+    ClusterResource {
+        id: c.id,
+        kind: cluster_kind(&c.kind),
+        name: c.name.clone(),
+        config: sensitive::redact(&c.config),
+        created_at: c.created_at,
+        updated_at: c.updated_at,
+        owner: c.owner.clone(),
+        ownership_confirmed_at: c.ownership_confirmed_at,
+        ownership_stale: stale.is_some(),
+    }
+}
+
+/// A [`seekr_api_types::clusters::MetadataEnvelope`] borrowing the cached metadata, so reads serialize it
+/// without copying the snapshot first. `retry_after_ms` is added by
+/// `retry::retry_later` when there is one.
+#[derive(Serialize)]
+struct MetadataView<'a> {
+    #[serde(flatten)]
+    metadata: MetadataRef<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counts: Option<ResourceCounts>,
+    polling: Option<PollingResource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache: Option<CacheResource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registration_started: Option<bool>,
+}
+
+/// [`seekr_api_types::clusters::MetadataResource`], borrowed.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum MetadataRef<'a> {
+    Unknown,
+    Processing,
+    Ready { metadata: &'a ClusterMetadata },
+    Failed { message: &'a str },
+}
+
+fn metadata_resource(entry: &CachedMetadataEntry) -> MetadataRef<'_> {
+    match entry {
+        CachedMetadataEntry::Unknown => MetadataRef::Unknown,
+        CachedMetadataEntry::Processing => MetadataRef::Processing,
+        CachedMetadataEntry::Meta(metadata) => MetadataRef::Ready { metadata },
+        CachedMetadataEntry::Failed(message) => MetadataRef::Failed { message },
+    }
+}
+
+fn cache_resource(c: CacheInfo) -> CacheResource {
+    CacheResource {
+        fetched_at: c.fetched_at,
+        stale: c.stale,
+        attempts_since_success: c.attempts_since_success,
+        last_error: c.last_error,
+    }
+}
+
+fn polling_resource(s: &PollStats) -> PollingResource {
+    PollingResource {
+        priority: s.priority,
+        interval_ms: s.interval.as_millis() as u64,
+        effective_interval_ms: s.effective.map(|e| e.as_millis() as u64),
+        stretch: s.stretch(),
+        adaptive: s.adaptive.as_ref().map(|a| AdaptiveResource {
+            state: a.state(),
+            interval_ms: a.interval.as_millis() as u64,
+            max_interval_ms: a.max.as_millis() as u64,
+            unchanged_polls: a.unchanged,
+        }),
+        backoff_ms: s.backoff.map(|b| b.as_millis() as u64),
+    }
+}
+
+/// A metadata consumer whose brokers are unreachable.
+#[cfg(test)]
+struct UnreachableConsumer;
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl crate::kafka::metadata::consumer::MetadataConsumer for UnreachableConsumer {
+    async fn fetch_meta(
+        &self,
+    ) -> Result<crate::kafka::metadata::ClusterMetadata, crate::errors::AnyError> {
+        Err("brokers are unreachable".into())
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &crate::kafka::metadata::ClusterMetadata,
+        _topics: &std::collections::HashMap<String, bool>,
+    ) -> Result<Vec<crate::kafka::metadata::TopicOffsets>, crate::errors::AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &crate::kafka::metadata::ClusterMetadata,
+    ) -> Result<Vec<crate::kafka::metadata::GroupLag>, crate::errors::AnyError> {
+        Err("brokers are unreachable".into())
+    }
+
+    async fn fetch_topic_config(
+        &self,
+        _topic: &str,
+    ) -> Result<Vec<crate::kafka::metadata::TopicConfigEntry>, crate::errors::AnyError> {
+        Err("brokers are unreachable".into())
+    }
+}
+
+#[actix_web::test]
+async fn it_hints_retries_until_the_next_poll() {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    tokio::time::pause();
+
+    let store = Arc::new(MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Ok(Arc::new(UnreachableConsumer)));
+    let manager = Data::new(MetadataManager::with_factory(store.clone(), factory));
+    let mut cluster = Cluster::new(None, Kind::Kafka, "local".to_string(), HashMap::new());
+    cluster.id = ClusterId(1);
+    manager.clone().into_inner().register(cluster, None).await;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = store;
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(store))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let mut hints = vec![];
+    for wait in [1, 20, 8] {
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/v2/clusters/1/metadata")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let header = res.headers().get("retry-after").unwrap();
+        let header = header.to_str().unwrap().to_string();
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(body["status"], "failed");
+        assert_eq!(body["polling"]["priority"], "normal");
+        assert_eq!(body["polling"]["interval_ms"], 30_000);
+
+        // The header is the JSON hint, rounded up to whole seconds.
+        let ms = body["retry_after_ms"].as_u64().unwrap();
+        assert_eq!(header, ms.div_ceil(1_000).to_string());
+        hints.push(header);
+    }
+
+    // The default 30s poll interval counts down, and the hint never drops below 1s.
+    assert_eq!(hints, vec!["29", "9", "1"]);
+
+    manager.into_inner().stop().await;
+}
+
+#[actix_web::test]
+async fn it_nests_subscriptions_under_their_cluster() {
+    use std::collections::HashMap;
+
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+    use serde_json::{json, Value};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::commands::store::MemoryCommandStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let qs: Arc<dyn CommandStore + Send + Sync> = Arc::new(MemoryCommandStore::default());
+    let cluster = Cluster::new(None, Kind::Kafka, "c".to_string(), HashMap::new());
+    let cluster_id = cs.insert(cluster).await.unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(ss.clone()))
+            .app_data(Data::new(qs))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let root = format!("/api/v2/clusters/{}/subscriptions", cluster_id);
+
+    let body = json!({
+        "topic_name": "orders",
+        "config": { "schema.registry.secret": "s3cr3t", "note": "first" },
+    });
+    let req = TestRequest::post().uri(&root).set_json(body);
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let location = res.headers().get(LOCATION).unwrap().to_str().unwrap();
+    let location = location.to_string();
+    let created: Value = test::read_body_json(res).await;
+    assert_eq!(location, format!("{}/{}", root, created["id"]));
+
+    let body: Value =
+        test::call_and_read_body_json(&app, TestRequest::get().uri(&root).to_request()).await;
+    assert_eq!(body["subscriptions"][0]["topic_name"], "orders");
+    let body: Value =
+        test::call_and_read_body_json(&app, TestRequest::get().uri(&location).to_request()).await;
+    assert_eq!(body["subscription"]["cluster_id"], json!(cluster_id));
+    assert_eq!(
+        body["subscription"]["config"]["schema.registry.secret"],
+        sensitive::REDACTED
+    );
+
+    // Patches keep what they leave out, redacted secrets included.
+    let patch = json!({ "config": { "note": null, "schema.registry.url": "http://sr" } });
+    let req = TestRequest::patch().uri(&location).set_json(patch);
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let id = SubscriptionId(created["id"].as_i64().unwrap());
+    let patched = ss.get(cluster_id, id).await.unwrap().unwrap();
+    assert_eq!(patched.topic_name, "orders");
+    assert_eq!(patched.config["schema.registry.secret"], "s3cr3t");
+    assert_eq!(patched.config["schema.registry.url"], "http://sr");
+    assert!(!patched.config.contains_key("note"));
+
+    let body = json!({ "topic_name": "orders.v2", "config": {} });
+    let req = TestRequest::put().uri(&location).set_json(body);
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = TestRequest::delete().uri(&location);
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // Errors use the envelope of v2, whichever part of the path is missing.
+    for uri in [
+        format!("{}/999", root),
+        "/api/v2/clusters/999/subscriptions".to_string(),
+    ] {
+        let res = test::call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", uri);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["code"], "not_found");
+    }
+    let req = TestRequest::patch()
+        .uri(&location)
+        .set_json(json!({ "topic_name": "" }));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "invalid_request");
+}
+
+#[test]
+fn it_serializes_borrowed_metadata_as_the_envelope() {
+    use seekr_api_types::clusters::{MetadataEnvelope, MetadataResource};
+
+    use crate::history::diff::metadata;
+
+    let entry = CachedMetadataEntry::Meta(metadata(&[1], &[("orders", 2)]));
+    let view = MetadataView {
+        metadata: metadata_resource(&entry),
+        counts: Some(ResourceCounts {
+            topics: 1,
+            partitions: 2,
+            groups: 0,
+        }),
+        polling: None,
+        cache: None,
+        registration_started: Some(false),
+    };
+    let envelope: MetadataEnvelope =
+        serde_json::from_value(serde_json::to_value(&view).unwrap()).unwrap();
+    assert_eq!(
+        envelope.metadata,
+        MetadataResource::Ready {
+            metadata: metadata(&[1], &[("orders", 2)])
+        }
+    );
+    assert_eq!(envelope.counts.unwrap().partitions, 2);
+    assert_eq!(envelope.registration_started, Some(false));
+
+    let failed = CachedMetadataEntry::Failed("down".to_string());
+    let view = MetadataView {
+        metadata: metadata_resource(&failed),
+        counts: None,
+        polling: None,
+        cache: None,
+        registration_started: None,
+    };
+    let envelope: MetadataEnvelope =
+        serde_json::from_value(serde_json::to_value(&view).unwrap()).unwrap();
+    assert_eq!(
+        envelope.metadata,
+        MetadataResource::Failed {
+            message: "down".to_string()
+        }
+    );
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::kafka::metadata::classify::{Include, ResourceCounts, TopicCategory};
+use crate::kafka::metadata::manager::{CacheInfo, CachedMetadataEntry};
+use crate::kafka::metadata::throughput::TopicThroughput;
+use crate::kafka::metadata::TopicMetadata;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Metadata has not been fetched yet.
+    Unknown,
+    Healthy,
+    /// The cluster is reachable, but some of its topics need attention.
+    Degraded,
+    /// The last metadata poll failed, or Kafka's internal topics are under-replicated.
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HotPartition {
+    pub topic: String,
+    pub partition: Option<i32>,
+    pub share: f64,
+}
+
+/// A rollup of a cluster's metadata and topic level health flags.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClusterHealth {
+    pub status: HealthStatus,
+
+    /// The reported resources, once metadata was fetched.
+    pub counts: Option<ResourceCounts>,
+    pub hot_partitions: Vec<HotPartition>,
+
+    /// Reported topics with partitions missing in-sync replicas.
+    pub under_replicated: Vec<String>,
+    pub internal: InternalHealth,
+}
+
+/// Kafka's internal topics, which every client of the cluster depends on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct InternalHealth {
+    pub under_replicated: Vec<String>,
+}
+
+impl ClusterHealth {
+    /// Flag the cluster unhealthy while its polls fail, though the metadata
+    /// of the last one that succeeded is still cached.
+    pub fn with_cache(mut self, cache: Option<&CacheInfo>) -> Self {
+        if cache.is_some_and(|c| c.attempts_since_success > 0) {
+            self.status = HealthStatus::Unhealthy;
+        }
+        self
+    }
+}
+
+fn is_under_replicated(t: &TopicMetadata) -> bool {
+    t.partitions.iter().any(|p| p.isr.len() < p.replicas.len())
+}
+
+/// Summarize the health of the resources `include` reports, always
+/// evaluating the internal topics on their own.
+pub fn summarize(
+    entry: &CachedMetadataEntry,
+    throughput: Option<&HashMap<String, TopicThroughput>>,
+    include: Include,
+) -> ClusterHealth {
+    let mut hot_partitions = throughput
+        .into_iter()
+        .flat_map(|topics| topics.values())
+        .filter(|t| t.hot_partition)
+        .map(|t| HotPartition {
+            topic: t.name.clone(),
+            partition: t.hottest_partition,
+            share: t.hottest_share,
+        })
+        .collect::<Vec<_>>();
+    hot_partitions.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+    let mut counts = None;
+    let mut under_replicated = vec![];
+    let mut internal = InternalHealth::default();
+    if let CachedMetadataEntry::Meta(metadata) = entry {
+        counts = Some(ResourceCounts::of(metadata, include));
+        for t in metadata.topics.iter().filter(|t| is_under_replicated(t)) {
+            match t.category {
+                TopicCategory::Internal => internal.under_replicated.push(t.name.clone()),
+                c if include.topic(c) => under_replicated.push(t.name.clone()),
+                _ => {}
+            }
+        }
+    }
+
+    let status = match entry {
+        CachedMetadataEntry::Unknown | CachedMetadataEntry::Processing => HealthStatus::Unknown,
+        CachedMetadataEntry::Failed(_) => HealthStatus::Unhealthy,
+        CachedMetadataEntry::Meta(_) if !internal.under_replicated.is_empty() => {
+            HealthStatus::Unhealthy
+        }
+        CachedMetadataEntry::Meta(_)
+            if !hot_partitions.is_empty() || !under_replicated.is_empty() =>
+        {
+            HealthStatus::Degraded
+        }
+        CachedMetadataEntry::Meta(_) => HealthStatus::Healthy,
+    };
+
+    ClusterHealth {
+        status,
+        counts,
+        hot_partitions,
+        under_replicated,
+        internal,
+    }
+}
+
+#[test]
+fn it_rolls_up_hot_partitions() {
+    use crate::kafka::metadata::ClusterMetadata;
+
+    let topic = |name: &str, hot: bool| TopicThroughput {
+        name: name.to_string(),
+        rate: 100.0,
+        partitions: vec![],
+        coefficient_of_variation: 0.0,
+        hottest_partition: Some(0),
+        hottest_share: 0.9,
+        hot_partition: hot,
+    };
+    let metadata = CachedMetadataEntry::Meta(ClusterMetadata {
+        brokers: vec![],
+        groups: vec![],
+        topics: vec![],
+    });
+
+    let calm = HashMap::from([("orders".to_string(), topic("orders", false))]);
+    assert_eq!(
+        summarize(&metadata, Some(&calm), Include::default()).status,
+        HealthStatus::Healthy
+    );
+    assert_eq!(
+        summarize(&metadata, None, Include::default()).status,
+        HealthStatus::Healthy
+    );
+
+    let hot = HashMap::from([
+        ("payments".to_string(), topic("payments", true)),
+        ("orders".to_string(), topic("orders", false)),
+    ]);
+    let health = summarize(&metadata, Some(&hot), Include::default());
+    assert_eq!(health.status, HealthStatus::Degraded);
+    assert_eq!(health.hot_partitions.len(), 1);
+    assert_eq!(health.hot_partitions[0].topic, "payments");
+
+    let failed = CachedMetadataEntry::Failed("unreachable".to_string());
+    assert_eq!(
+        summarize(&failed, Some(&hot), Include::default()).status,
+        HealthStatus::Unhealthy
+    );
+
+    // Metadata kept from before the polls started failing doesn't hide them.
+    let failing = CacheInfo {
+        fetched_at: chrono::Utc::now(),
+        stale: true,
+        attempts_since_success: 2,
+        last_error: Some("unreachable".to_string()),
+    };
+    let health = summarize(&metadata, None, Include::default());
+    assert_eq!(
+        health.clone().with_cache(None).status,
+        HealthStatus::Healthy
+    );
+    assert_eq!(
+        health.with_cache(Some(&failing)).status,
+        HealthStatus::Unhealthy
+    );
+}
+
+#[test]
+fn it_evaluates_internal_topics_separately() {
+    let mut metadata = crate::history::diff::metadata(
+        &[1, 2],
+        &[("orders", 1), ("_schemas", 1), ("__consumer_offsets", 1)],
+    );
+    let lagging = |metadata: &mut crate::kafka::metadata::ClusterMetadata, name: &str| {
+        let topic = metadata.topics.iter_mut().find(|t| t.name == name).unwrap();
+        topic.partitions[0].replicas = vec![1, 2];
+    };
+
+    lagging(&mut metadata, "_schemas");
+    let entry = CachedMetadataEntry::Meta(metadata.clone());
+    let health = summarize(&entry, None, Include::default());
+    assert_eq!(health.status, HealthStatus::Healthy);
+    assert_eq!(health.counts.unwrap().topics, 1);
+
+    let system = Include {
+        system: true,
+        ..Include::default()
+    };
+    let health = summarize(&entry, None, system);
+    assert_eq!(health.status, HealthStatus::Degraded);
+    assert_eq!(health.under_replicated, ["_schemas"]);
+
+    // Internal topics are flagged on their own, whatever is included.
+    lagging(&mut metadata, "__consumer_offsets");
+    let entry = CachedMetadataEntry::Meta(metadata);
+    let health = summarize(&entry, None, Include::default());
+    assert_eq!(health.status, HealthStatus::Unhealthy);
+    assert!(health.under_replicated.is_empty());
+    assert_eq!(health.internal.under_replicated, ["__consumer_offsets"]);
+}
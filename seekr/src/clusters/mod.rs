@@ -1,3 +1,5 @@
 pub mod cluster;
 pub mod endpoints;
+pub mod health;
+pub mod service;
 pub mod store;
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::errors::AnyError;
+use crate::governance::owner::{self, Confirmation, Owner};
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::metadata::classify::Include;
+use crate::kafka::metadata::manager::{
+    CacheInfo, CachedMetadataEntry, GroupLagRead, MetadataManager,
+};
+use crate::kafka::metadata::schedule::PollStats;
+use crate::kafka::metadata::throughput::TopicThroughput;
+use crate::kafka::metadata::{TopicConfigEntry, TopicMetadata};
+use crate::kafka::sensitive::{self, SensitiveKeys, REDACTED};
+use crate::page::Page;
+use crate::request_id::RequestId;
+use crate::shards::store::DocumentStore;
+use crate::subscriptions::deletion;
+use crate::subscriptions::store::{PurgeLog, SubscriptionStore};
+
+use super::cluster::{Cluster, Kind};
+use super::health::{self, ClusterHealth};
+use super::store::{ClusterNotFound, ClusterStore};
+
+// Version-agnostic cluster operations shared by every API version.
+
+/// A name already taken by another cluster, ignoring case and surrounding whitespace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameTaken {
+    pub name: String,
+    pub id: ClusterId,
+}
+
+impl fmt::Display for NameTaken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Cluster name '{}' is taken by the cluster with id '{}'",
+            self.name, self.id
+        )
+    }
+}
+
+impl std::error::Error for NameTaken {}
+
+/// Fails with `NameTaken` when a cluster other than `id` goes by `name`.
+async fn check_name(
+    store: &(dyn ClusterStore + Send + Sync),
+    name: &str,
+    id: Option<ClusterId>,
+) -> Result<(), AnyError> {
+    match store.get_by_name(name).await? {
+        Some(taken) if Some(taken.id) != id => Err(NameTaken {
+            name: taken.name,
+            id: taken.id,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+pub async fn create(
+    store: &(dyn ClusterStore + Send + Sync),
+    manager: Arc<MetadataManager>,
+    kind: Kind,
+    name: String,
+    config: HashMap<String, String>,
+    owner: Option<Owner>,
+) -> Result<ClusterId, AnyError> {
+    check_name(store, &name, None).await?;
+    let owner = owner.and_then(Owner::normalize);
+    let cluster = Cluster {
+        ownership_confirmed_at: owner.as_ref().map(|_| Utc::now()),
+        owner,
+        ..Cluster::new(None, kind, name, config)
+    };
+    let id = store.insert(cluster.clone()).await?;
+
+    manager
+        .register(Cluster { id, ..cluster }, RequestId::current())
+        .await;
+    Ok(id)
+}
+
+/// Which clusters a listing keeps, every one unless narrowed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClusterFilter {
+    /// Owned by the team.
+    pub team: Option<String>,
+    /// Of the kind, e.g. `kafka`, in any case.
+    pub kind: Option<String>,
+    /// With a name containing this, in any case.
+    pub name_contains: Option<String>,
+}
+
+impl ClusterFilter {
+    pub fn is_empty(&self) -> bool {
+        self.team.is_none() && self.kind.is_none() && self.name_contains.is_none()
+    }
+
+    pub fn matches(&self, cluster: &Cluster) -> bool {
+        let team = self.team.as_deref().map_or(true, |team| {
+            cluster.owner.as_ref().is_some_and(|o| o.is_team(team))
+        });
+        let kind = self.kind.as_deref().map_or(true, |kind| {
+            cluster.kind.to_string().eq_ignore_ascii_case(kind)
+        });
+        let name = self.name_contains.as_deref().map_or(true, |part| {
+            cluster.name.to_lowercase().contains(&part.to_lowercase())
+        });
+        team && kind && name
+    }
+}
+
+/// Every cluster the filter keeps.
+pub async fn list(
+    store: &(dyn ClusterStore + Send + Sync),
+    filter: &ClusterFilter,
+) -> Result<Vec<Cluster>, AnyError> {
+    let clusters = store.list(None, Page::ALL).await?;
+    Ok(clusters.into_iter().filter(|c| filter.matches(c)).collect())
+}
+
+/// A page of every cluster, paged by the store, and how many clusters there are.
+pub async fn page(
+    store: &(dyn ClusterStore + Send + Sync),
+    page: Page,
+) -> Result<(Vec<Cluster>, usize), AnyError> {
+    let clusters = store.list(None, page).await?;
+    Ok((clusters, store.count(None).await?))
+}
+
+pub async fn get(
+    store: &(dyn ClusterStore + Send + Sync),
+    id: ClusterId,
+) -> Result<Option<Cluster>, AnyError> {
+    store.get(id).await
+}
+
+/// Replace the cluster, which also re-confirms its owner, keeping when it was
+/// created. `None` when it doesn't exist.
+///
+/// An omitted owner keeps the current one, and an empty one clears it, and
+/// secrets still redacted keep their values. The cluster is polled with its new config from then on, see
+/// `MetadataManager::reregister`.
+pub async fn update(
+    store: &(dyn ClusterStore + Send + Sync),
+    manager: Arc<MetadataManager>,
+    id: ClusterId,
+    kind: Kind,
+    name: String,
+    config: HashMap<String, String>,
+    owner: Option<Owner>,
+) -> Result<Option<ClusterId>, AnyError> {
+    let Some(current) = store.get(id).await? else {
+        return Ok(None);
+    };
+    check_name(store, &name, Some(id)).await?;
+    let owner = owner::resolve(current.owner.clone(), owner);
+    let cluster = Cluster {
+        created_at: current.created_at,
+        ownership_confirmed_at: owner.as_ref().map(|_| Utc::now()),
+        owner: owner.clone(),
+        ..Cluster::new(
+            Some(id),
+            kind,
+            name,
+            sensitive::restore(config, &current.config),
+        )
+    };
+
+    let id = store.update(cluster.clone()).await?;
+    manager.set_owner(id, owner).await;
+
+    // A config the cluster can't be polled with is undone, so the stored
+    // config stays the one its metadata is polled with.
+    if let Err(e) = manager.reregister(cluster).await {
+        store.update(current).await?;
+        return Err(e);
+    }
+    Ok(Some(id))
+}
+
+/// The cluster with only the given fields changed, `None` when it doesn't
+/// exist. Config entries are set one by one, and removed when `None`, but
+/// secrets set to their redacted value are left as they are.
+pub async fn patched(
+    store: &(dyn ClusterStore + Send + Sync),
+    id: ClusterId,
+    kind: Option<Kind>,
+    name: Option<String>,
+    config: HashMap<String, Option<String>>,
+) -> Result<Option<Cluster>, AnyError> {
+    let Some(mut cluster) = store.get(id).await? else {
+        return Ok(None);
+    };
+    if let Some(kind) = kind {
+        cluster.kind = kind;
+    }
+    if let Some(name) = name {
+        cluster.name = name;
+    }
+    merge_config(&mut cluster.config, config);
+    Ok(Some(cluster))
+}
+
+fn merge_config(config: &mut HashMap<String, String>, patch: HashMap<String, Option<String>>) {
+    let sensitive = SensitiveKeys::default();
+    for (key, value) in patch {
+        match value {
+            Some(value) if value == REDACTED && sensitive.is_sensitive(&key) => None,
+            Some(value) => config.insert(key, value),
+            None => config.remove(&key),
+        };
+    }
+}
+
+/// Confirm the cluster is still owned by its current owner.
+pub async fn confirm_ownership(
+    store: &(dyn ClusterStore + Send + Sync),
+    id: ClusterId,
+) -> Result<Confirmation, AnyError> {
+    let Some(cluster) = store.get(id).await? else {
+        return Ok(Confirmation::NotFound);
+    };
+    if cluster.owner.is_none() {
+        return Ok(Confirmation::Unowned);
+    }
+
+    let now = Utc::now();
+    store
+        .update(Cluster {
+            ownership_confirmed_at: Some(now),
+            ..cluster
+        })
+        .await?;
+    Ok(Confirmation::Confirmed(now))
+}
+
+pub async fn delete(
+    store: &(dyn ClusterStore + Send + Sync),
+    manager: Arc<MetadataManager>,
+    id: ClusterId,
+) -> Result<(), AnyError> {
+    store.remove(id).await?;
+    manager.remove(id, RequestId::current()).await;
+    Ok(())
+}
+
+/// What deleting a cluster along with its subscriptions came to.
+#[derive(Debug, Default, PartialEq)]
+pub struct ClusterDeletion {
+    pub subscriptions_deleted: usize,
+    /// The subscriptions that couldn't be removed, and why. The cluster is
+    /// kept while there are any, so deleting it again picks up where this left off.
+    pub subscriptions_failed: Vec<(SubscriptionId, String)>,
+}
+
+impl ClusterDeletion {
+    pub fn is_complete(&self) -> bool {
+        self.subscriptions_failed.is_empty()
+    }
+}
+
+/// Delete the cluster and its subscriptions, which the indexer then stops the
+/// workers of. Subscriptions are purged as their undo window running out
+/// would, so those pending deletion lose the documents they asked to, and
+/// undeletes answer they're gone. The cluster, and its metadata polling, are
+/// only removed once every subscription is.
+pub async fn delete_with_subscriptions(
+    store: &(dyn ClusterStore + Send + Sync),
+    subscriptions: &(dyn SubscriptionStore + Send + Sync),
+    documents: &(dyn DocumentStore + Send + Sync),
+    purges: &(dyn PurgeLog + Send + Sync),
+    manager: Arc<MetadataManager>,
+    id: ClusterId,
+) -> Result<ClusterDeletion, AnyError> {
+    if store.get(id).await?.is_none() {
+        return Err(ClusterNotFound(id).into());
+    }
+
+    let mut deletion = ClusterDeletion::default();
+    let now = Utc::now();
+    for s in subscriptions.list(Some(id), Page::ALL).await? {
+        match deletion::purge(subscriptions, documents, purges, &s, now).await {
+            Ok(_) => deletion.subscriptions_deleted += 1,
+            Err(e) => deletion.subscriptions_failed.push((s.id, e.to_string())),
+        }
+    }
+
+    if deletion.is_complete() {
+        delete(store, manager, id).await?;
+    }
+    Ok(deletion)
+}
+
+/// What a read of a cluster's metadata found.
+#[derive(Debug, PartialEq)]
+pub enum MetadataRead {
+    /// The cached entry, shared with the cache rather than copied.
+    Cached(Arc<CachedMetadataEntry>),
+
+    /// The cluster exists but isn't cached, e.g. its registration failed or a
+    /// standby hasn't synced it yet. `registering` when the read just started
+    /// registering it again.
+    Uncached {
+        registering: bool,
+    },
+
+    Missing,
+}
+
+/// The cached metadata of the cluster, falling back to the store on a miss to
+/// tell a cluster that isn't cached from one that doesn't exist. With `heal`,
+/// a cluster that exists is registered again, see `MetadataManager::heal`.
+pub async fn metadata(
+    store: &(dyn ClusterStore + Send + Sync),
+    manager: Arc<MetadataManager>,
+    id: ClusterId,
+    heal: bool,
+) -> Result<MetadataRead, AnyError> {
+    if let Some(entry) = manager.clone().get(id, RequestId::current()).await? {
+        manager.activity(id).await;
+        return Ok(MetadataRead::Cached(entry));
+    }
+
+    let Some(cluster) = store.get(id).await? else {
+        return Ok(MetadataRead::Missing);
+    };
+    let registering = heal && manager.heal(cluster).await;
+    Ok(MetadataRead::Uncached { registering })
+}
+
+/// How long clients should wait before an entry that isn't ready may change.
+pub async fn retry_after(
+    manager: &MetadataManager,
+    id: ClusterId,
+    entry: &CachedMetadataEntry,
+) -> Option<Duration> {
+    match entry {
+        CachedMetadataEntry::Meta(_) => None,
+        _ => manager.retry_after(id).await,
+    }
+}
+
+/// How fresh the metadata of entries holding it is.
+pub async fn cache_info(
+    manager: &MetadataManager,
+    id: ClusterId,
+    entry: &CachedMetadataEntry,
+) -> Option<CacheInfo> {
+    match entry {
+        CachedMetadataEntry::Meta(_) => manager.cache_info(id).await,
+        _ => None,
+    }
+}
+
+/// The cluster's metadata poll priority and how its polls keep up with their interval.
+pub async fn polling(manager: &MetadataManager, id: ClusterId) -> Option<PollStats> {
+    manager.polling(id).await
+}
+
+pub async fn health(
+    manager: Arc<MetadataManager>,
+    id: ClusterId,
+    include: Include,
+) -> Result<Option<ClusterHealth>, AnyError> {
+    let throughput = manager.throughput(id).await;
+    let cache = manager.cache_info(id).await;
+    let entry = manager.get(id, RequestId::current()).await?;
+    Ok(entry
+        .map(|e| health::summarize(&e, throughput.as_ref(), include).with_cache(cache.as_ref())))
+}
+
+/// What a read of a topic found.
+#[derive(Debug)]
+pub enum TopicRead {
+    /// The topic's metadata, along with its throughput when it is tracked.
+    Topic(TopicMetadata, Option<TopicThroughput>),
+
+    /// The cluster's metadata isn't cached yet, or no poll of it succeeded.
+    Processing,
+
+    MissingCluster,
+    MissingTopic,
+}
+
+/// The topic in the cluster's cached metadata.
+pub async fn topic(
+    manager: Arc<MetadataManager>,
+    id: ClusterId,
+    name: &str,
+) -> Result<TopicRead, AnyError> {
+    let throughput = manager.throughput(id).await;
+    let entry = manager.get(id, RequestId::current()).await?;
+    let metadata = match entry.as_deref() {
+        Some(CachedMetadataEntry::Meta(metadata)) => metadata,
+        Some(_) => return Ok(TopicRead::Processing),
+        None => return Ok(TopicRead::MissingCluster),
+    };
+
+    match metadata.topics.iter().find(|t| t.name == name) {
+        Some(t) => {
+            let t = t.clone();
+            let throughput = throughput.and_then(|mut topics| topics.remove(name));
+            Ok(TopicRead::Topic(t, throughput))
+        }
+        None => Ok(TopicRead::MissingTopic),
+    }
+}
+
+/// The topic's settings, read from the brokers on each call.
+pub async fn topic_config(
+    manager: &MetadataManager,
+    id: ClusterId,
+    name: &str,
+) -> Result<Vec<TopicConfigEntry>, AnyError> {
+    manager.topic_config(id, name).await
+}
+
+/// How far the consumer group is behind in each partition it committed an offset for.
+pub async fn group_lag(
+    manager: &MetadataManager,
+    id: ClusterId,
+    group: &str,
+) -> Result<GroupLagRead, AnyError> {
+    manager.group_lag(id, group).await
+}
+
+#[test]
+fn it_merges_config_patches_key_by_key() {
+    let mut config = HashMap::from([
+        ("bootstrap.servers".to_string(), "a:9092".to_string()),
+        ("metadata.poll.interval.ms".to_string(), "1000".to_string()),
+        ("security.protocol".to_string(), "SSL".to_string()),
+        ("sasl.password".to_string(), "hunter2".to_string()),
+    ]);
+    merge_config(
+        &mut config,
+        HashMap::from([
+            ("bootstrap.servers".to_string(), Some("b:9092".to_string())),
+            ("security.protocol".to_string(), None),
+            ("metadata.priority".to_string(), Some("high".to_string())),
+            ("throughput.enabled".to_string(), None),
+            ("sasl.password".to_string(), Some(REDACTED.to_string())),
+        ]),
+    );
+
+    assert_eq!(
+        config,
+        HashMap::from([
+            ("bootstrap.servers".to_string(), "b:9092".to_string()),
+            ("metadata.poll.interval.ms".to_string(), "1000".to_string()),
+            ("metadata.priority".to_string(), "high".to_string()),
+            ("sasl.password".to_string(), "hunter2".to_string()),
+        ])
+    );
+}
+
+/// Subscriptions on a topic failing to be removed.
+#[cfg(test)]
+struct Undeletable {
+    inner: crate::subscriptions::store::MemorySubscriptionStore,
+    topic: &'static str,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl SubscriptionStore for Undeletable {
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: Page,
+    ) -> Result<Vec<crate::subscriptions::subscription::Subscription>, AnyError> {
+        self.inner.list(cluster_id, page).await
+    }
+
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError> {
+        self.inner.count(cluster_id).await
+    }
+
+    async fn search(
+        &self,
+        q: &str,
+        limit: usize,
+    ) -> Result<
+        Vec<crate::search::Scored<crate::subscriptions::subscription::Subscription>>,
+        AnyError,
+    > {
+        self.inner.search(q, limit).await
+    }
+
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<Option<crate::subscriptions::subscription::Subscription>, AnyError> {
+        self.inner.get(cluster_id, id).await
+    }
+
+    async fn insert(
+        &self,
+        s: crate::subscriptions::subscription::Subscription,
+    ) -> Result<SubscriptionId, AnyError> {
+        self.inner.insert(s).await
+    }
+
+    async fn update(
+        &self,
+        s: crate::subscriptions::subscription::Subscription,
+    ) -> Result<SubscriptionId, AnyError> {
+        self.inner.update(s).await
+    }
+
+    async fn put(
+        &self,
+        s: crate::subscriptions::subscription::Subscription,
+    ) -> Result<SubscriptionId, AnyError> {
+        self.inner.put(s).await
+    }
+
+    async fn remove(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> Result<SubscriptionId, AnyError> {
+        match self.inner.get(cluster_id, id).await? {
+            Some(s) if s.topic_name == self.topic => Err("the store is unavailable".into()),
+            _ => self.inner.remove(cluster_id, id).await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn it_keeps_clusters_until_their_subscriptions_are_deleted() {
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::shards::store::MemoryDocumentStore;
+    use crate::subscriptions::store::MemoryPurgeLog;
+    use crate::subscriptions::subscription::Subscription;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("offline".into()));
+    let manager = Arc::new(MetadataManager::with_factory(store.clone(), factory));
+    let subscriptions = Undeletable {
+        inner: Default::default(),
+        topic: "refunds",
+    };
+    let documents = MemoryDocumentStore::default();
+    let purges = MemoryPurgeLog::default();
+
+    let (a, b) = (ClusterId(1), ClusterId(2));
+    for name in ["a", "b"] {
+        let c = Cluster::new(None, Kind::Kafka, name.to_string(), HashMap::new());
+        store.insert(c).await.unwrap();
+    }
+    for (cluster_id, topic) in [(a, "orders"), (a, "refunds"), (b, "orders")] {
+        let s = Subscription::new(None, cluster_id, topic.to_string(), HashMap::new());
+        subscriptions.insert(s).await.unwrap();
+    }
+
+    // The failed removal is reported, and the cluster kept to delete again.
+    let deletion = delete_with_subscriptions(
+        store.as_ref(),
+        &subscriptions,
+        &documents,
+        &purges,
+        manager.clone(),
+        a,
+    )
+    .await
+    .unwrap();
+    assert_eq!(deletion.subscriptions_deleted, 1);
+    assert_eq!(
+        deletion.subscriptions_failed,
+        vec![(SubscriptionId(2), "the store is unavailable".to_string())]
+    );
+    assert!(store.get(a).await.unwrap().is_some());
+
+    let deletion = delete_with_subscriptions(
+        store.as_ref(),
+        &subscriptions,
+        &documents,
+        &purges,
+        manager.clone(),
+        b,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        deletion,
+        ClusterDeletion {
+            subscriptions_deleted: 1,
+            subscriptions_failed: vec![],
+        }
+    );
+    assert!(store.get(b).await.unwrap().is_none());
+    assert_eq!(subscriptions.count(Some(b)).await.unwrap(), 0);
+
+    let e = delete_with_subscriptions(
+        store.as_ref(),
+        &subscriptions,
+        &documents,
+        &purges,
+        manager.clone(),
+        b,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&ClusterNotFound(b)));
+
+    manager.stop().await;
+}
+
+#[tokio::test]
+async fn it_purges_the_subscriptions_of_deleted_clusters() {
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::shards::shard::Shard;
+    use crate::shards::store::MemoryDocumentStore;
+    use crate::subscriptions::deletion::{schedule, DEFAULT_GRACE_PERIOD};
+    use crate::subscriptions::store::{MemoryPurgeLog, MemorySubscriptionStore};
+    use crate::subscriptions::subscription::Subscription;
+
+    let store: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("offline".into()));
+    let manager = Arc::new(MetadataManager::with_factory(store.clone(), factory));
+    let subscriptions = MemorySubscriptionStore::default();
+    let documents = MemoryDocumentStore::default();
+    let purges = MemoryPurgeLog::default();
+
+    let c = Cluster::new(None, Kind::Kafka, "a".to_string(), HashMap::new());
+    let id = store.insert(c).await.unwrap();
+
+    // One deleted with its documents but still in its undo window, one active.
+    let pending = Some(schedule(None, Utc::now(), DEFAULT_GRACE_PERIOD, true, false).unwrap());
+    let deleted = Subscription {
+        pending_deletion: pending,
+        ..Subscription::new(None, id, "orders".to_string(), HashMap::new())
+    };
+    let deleted = subscriptions.insert(deleted).await.unwrap();
+    let active = Subscription::new(None, id, "refunds".to_string(), HashMap::new());
+    let active = subscriptions.insert(active).await.unwrap();
+    for s in [deleted, active] {
+        documents
+            .put_shard(&Shard::route(s, None, 0))
+            .await
+            .unwrap();
+    }
+
+    let deletion = delete_with_subscriptions(
+        store.as_ref(),
+        &subscriptions,
+        &documents,
+        &purges,
+        manager.clone(),
+        id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(deletion.subscriptions_deleted, 2);
+    assert!(store.get(id).await.unwrap().is_none());
+
+    // Only the delete that asked for it takes the documents, and undeletes answer gone.
+    assert!(documents.shards(deleted).await.unwrap().is_empty());
+    assert_eq!(documents.shards(active).await.unwrap().len(), 1);
+    for s in [deleted, active] {
+        assert!(purges.purged(s).await.unwrap().is_some());
+    }
+
+    manager.stop().await;
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::option::Option;
 use std::result;
 use std::sync::Arc;
@@ -13,22 +14,65 @@ use cdrs_tokio::types::{AsRustType, ByName};
 use chrono::{DateTime, Utc};
 use meilisearch_sdk::indexes::Index;
 use meilisearch_sdk::Client;
+use serde::Serialize;
 
+use crate::backend::{StoreBackend, StoreConfig};
 use crate::errors::AnyError;
+use crate::governance::owner;
+use crate::id;
+use crate::ids::ClusterId;
+use crate::meilisearch;
+use crate::page::{self, Page};
+use crate::schemas;
+use crate::search::{self, Scored, SearchUnsupported};
 use crate::session::CdrsSession;
-use crate::{id, ID_GENERATOR, MS_CLIENT};
 
-use super::cluster::{Cluster, Kind};
+use super::cluster::{self, Cluster, Kind};
 
 #[async_trait]
 pub trait ClusterStore {
-    async fn list(&self, ids: Option<Vec<i64>>) -> Result<Vec<Cluster>, AnyError>;
-    async fn get(&self, id: i64) -> result::Result<Option<Cluster>, AnyError>;
-    async fn insert(&self, cluster: Cluster) -> result::Result<i64, AnyError>;
-    async fn update(&self, cluster: Cluster) -> result::Result<i64, AnyError>;
-    async fn remove(&self, id: i64) -> result::Result<i64, AnyError>;
+    /// The clusters with the given ids, or every cluster, in the page.
+    async fn list(&self, ids: Option<Vec<ClusterId>>, page: Page)
+        -> Result<Vec<Cluster>, AnyError>;
+    /// How many clusters `list` has across all pages.
+    async fn count(&self, ids: Option<Vec<ClusterId>>) -> Result<usize, AnyError>;
+    /// The best `limit` clusters matching `q`, best first, or
+    /// `SearchUnsupported` for backends that can't search.
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Cluster>>, AnyError>;
+    async fn get(&self, id: ClusterId) -> result::Result<Option<Cluster>, AnyError>;
+    /// The cluster going by `name`, compared as `cluster::name_key` does.
+    async fn get_by_name(&self, name: &str) -> result::Result<Option<Cluster>, AnyError>;
+    async fn insert(&self, cluster: Cluster) -> result::Result<ClusterId, AnyError>;
+    async fn update(&self, cluster: Cluster) -> result::Result<ClusterId, AnyError>;
+    async fn remove(&self, id: ClusterId) -> result::Result<ClusterId, AnyError>;
+    /// Write the cluster under its own id whether or not it's stored, as
+    /// seeding and reinstating a removed cluster do.
+    async fn put(&self, cluster: Cluster) -> result::Result<ClusterId, AnyError> {
+        self.update(cluster).await
+    }
+
+    /// Check that the store answers, as cheaply as it can.
+    async fn ping(&self) -> Result<(), AnyError>;
+
+    /// Write the `name_key` of the clusters stored without one, which
+    /// `get_by_name` can't find otherwise. How many were.
+    async fn backfill_name_keys(&self) -> Result<usize, AnyError> {
+        Ok(0)
+    }
 }
 
+/// A cluster that was asked to be removed but isn't in the store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusterNotFound(pub ClusterId);
+
+impl fmt::Display for ClusterNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cluster with id '{}' not found", self.0)
+    }
+}
+
+impl std::error::Error for ClusterNotFound {}
+
 pub const INDEX_NAME: &str = "clusters";
 
 pub struct MSClusterStore {
@@ -36,6 +80,8 @@ pub struct MSClusterStore {
     client: Arc<Client>,
     /// A Distributed Unique ID generator.
     generator: Arc<id::Generator>,
+    /// Ids of inserts in flight, so racing inserts don't share one.
+    claims: id::Claims,
 }
 
 impl MSClusterStore {
@@ -49,66 +95,136 @@ impl MSClusterStore {
             }
         };
 
-        Self { client, generator }
+        let index = client.index(INDEX_NAME);
+        if let Err(e) = index
+            .set_filterable_attributes(["id", "name", "name_key", "kind"])
+            .await
+        {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                INDEX_NAME, e
+            );
+        }
+        if let Err(e) = index
+            .set_sortable_attributes(["name", "created_at", "updated_at"])
+            .await
+        {
+            warn!("Unable to set sortable attributes on {}: {}", INDEX_NAME, e);
+        }
+
+        Self {
+            client,
+            generator,
+            claims: id::Claims::default(),
+        }
     }
 
     fn index(&self) -> Index {
         self.client.index(INDEX_NAME)
     }
+
+    async fn exists(&self, id: i64) -> Result<bool, AnyError> {
+        let document = self
+            .index()
+            .get_document::<serde_json::Value>(&id.to_string())
+            .await;
+        Ok(meilisearch::found(document)?.is_some())
+    }
+}
+
+/// The Meilisearch filter of the clusters with the given ids.
+fn filter(ids: &[ClusterId]) -> String {
+    let ids = ids.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    format!("id IN [{}]", ids.join(", "))
+}
+
+/// The Meilisearch filter of the clusters named `name`, by the `name_key`
+/// stored along, since names are stored as given.
+fn name_filter(name: &str) -> String {
+    let key = cluster::name_key(name)
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("name_key = \"{}\"", key)
+}
+
+/// A cluster as Meilisearch stores it, with its `name_key` to be found by name.
+#[derive(Serialize)]
+struct ClusterDocument<'a> {
+    #[serde(flatten)]
+    cluster: &'a Cluster,
+    name_key: String,
+}
+
+impl<'a> From<&'a Cluster> for ClusterDocument<'a> {
+    fn from(cluster: &'a Cluster) -> Self {
+        Self {
+            cluster,
+            name_key: cluster::name_key(&cluster.name),
+        }
+    }
 }
 
 #[async_trait]
 impl ClusterStore for MSClusterStore {
-    async fn list(&self, ids: Option<Vec<i64>>) -> Result<Vec<Cluster>, AnyError> {
-        if ids.is_none() {
-            let docs = self.index().get_documents::<Cluster>().await?;
-            return Ok(docs.results);
+    async fn list(
+        &self,
+        ids: Option<Vec<ClusterId>>,
+        page: Page,
+    ) -> Result<Vec<Cluster>, AnyError> {
+        match ids.as_deref() {
+            // No ids aren't filtered on, `IN []` isn't a valid filter.
+            Some([]) => Ok(vec![]),
+            Some(ids) => page::hits(&self.index(), &filter(ids), page).await,
+            None => page::documents(&self.index(), page).await,
         }
+    }
 
-        let filter = ids
-            .unwrap()
-            .iter()
-            .map(|&x| format!("id = {}", x))
-            .collect::<Vec<String>>()
-            .join(" or ");
+    async fn count(&self, ids: Option<Vec<ClusterId>>) -> Result<usize, AnyError> {
+        match ids.as_deref() {
+            Some([]) => Ok(0),
+            Some(ids) => page::count(&self.index(), Some(&filter(ids))).await,
+            None => page::count(&self.index(), None).await,
+        }
+    }
 
-        let results = self
-            .index()
-            .search()
-            .with_filter(&filter)
-            .execute::<Cluster>()
-            .await?;
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Cluster>>, AnyError> {
+        search::meilisearch(&self.index(), q, limit).await
+    }
 
-        let clusters = results
-            .hits
-            .iter()
-            .map(|h| h.result.clone())
-            .collect::<Vec<_>>();
+    async fn get(&self, id: ClusterId) -> result::Result<Option<Cluster>, AnyError> {
+        let cluster = self.index().get_document::<Cluster>(&id.to_string()).await;
 
-        Ok(clusters)
+        meilisearch::found(cluster)
     }
 
-    async fn get(&self, id: i64) -> result::Result<Option<Cluster>, AnyError> {
-        let cluster = self
-            .index()
-            .get_document::<Cluster>(&id.to_string())
-            .await?;
-
-        Ok(Some(cluster))
+    async fn get_by_name(&self, name: &str) -> result::Result<Option<Cluster>, AnyError> {
+        let clusters: Vec<Cluster> =
+            page::hits(&self.index(), &name_filter(name), Page::ALL).await?;
+        Ok(clusters.into_iter().find(|c| c.is_named(name)))
     }
 
-    async fn insert(&self, c: Cluster) -> result::Result<i64, AnyError> {
+    async fn insert(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
+        // Regenerate ids already taken, e.g. by an instance sharing the worker id
+        let claim = self
+            .claims
+            .claim(|| Ok(self.generator.next_id()?), |id| self.exists(id))
+            .await?;
         let cluster = Cluster {
-            id: self.generator.next_id().unwrap(),
+            id: ClusterId(claim.id),
             kind: c.kind,
             name: c.name,
             config: c.config,
             created_at: c.created_at,
             updated_at: c.updated_at,
+            owner: c.owner,
+            ownership_confirmed_at: c.ownership_confirmed_at,
         };
 
         self.index()
-            .add_or_replace(&vec![&cluster], Some("id"))
+            .add_or_replace(
+                &[schemas::CLUSTERS.versioned(&ClusterDocument::from(&cluster))],
+                Some("id"),
+            )
             .await?
             .wait_for_completion(&self.client, None, None)
             .await?;
@@ -116,9 +232,12 @@ impl ClusterStore for MSClusterStore {
         Ok(cluster.id)
     }
 
-    async fn update(&self, c: Cluster) -> result::Result<i64, AnyError> {
+    async fn update(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
         self.index()
-            .add_or_replace(&vec![&c], Some("id"))
+            .add_or_replace(
+                &[schemas::CLUSTERS.versioned(&ClusterDocument::from(&c))],
+                Some("id"),
+            )
             .await?
             .wait_for_completion(&self.client, None, None)
             .await?;
@@ -126,11 +245,39 @@ impl ClusterStore for MSClusterStore {
         Ok(c.id)
     }
 
-    async fn remove(&self, id: i64) -> result::Result<i64, AnyError> {
+    async fn remove(&self, id: ClusterId) -> result::Result<ClusterId, AnyError> {
         self.index().delete_document(id.to_string()).await?;
 
         Ok(id)
     }
+
+    async fn ping(&self) -> Result<(), AnyError> {
+        self.client.health().await?;
+        Ok(())
+    }
+
+    async fn backfill_name_keys(&self) -> Result<usize, AnyError> {
+        let documents: Vec<serde_json::Value> = page::documents(&self.index(), Page::ALL).await?;
+        let keys = documents
+            .iter()
+            .filter(|d| d.get("name_key").is_none())
+            .filter_map(|d| {
+                let name = d.get("name")?.as_str()?;
+                Some(serde_json::json!({ "id": d.get("id")?, "name_key": cluster::name_key(name) }))
+            })
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        // Only the key is written, the rest of each document is kept as is.
+        self.index()
+            .add_or_update(&keys, Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+        Ok(keys.len())
+    }
 }
 
 pub struct CdrsClusterStore {
@@ -151,66 +298,125 @@ impl CdrsClusterStore {
         let rows = result?
             .response_body()?
             .into_rows()
-            .ok_or(Error::General(format!("Failed to parse database response")))?;
+            .ok_or_else(|| Error::General("Failed to parse database response".to_string()))?;
         Ok(rows)
     }
 
+    /// The clusters with the given ids, skipping those that don't exist.
+    async fn list_ids(&self, ids: Vec<ClusterId>) -> Result<Vec<Cluster>, AnyError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Ids are the partition key, so IN only reads the partitions asked for.
+        let stmt = "SELECT * FROM adm.clusters WHERE id IN ?;";
+        let ids = ids.iter().map(|id| id.as_i64()).collect::<Vec<_>>();
+        let rows = self
+            .session
+            .query_with_values(stmt, query_values!(ids))
+            .await;
+        let rows = self.parse(rows)?;
+        Ok(rows.iter().map(|r| self.map(r)).collect())
+    }
+
     fn map(&self, row: &Row) -> Cluster {
-        let id = row.r_by_name::<i64>(&"id").unwrap();
-        let name = row.r_by_name::<String>(&"name").unwrap();
+        let id = ClusterId(row.r_by_name::<i64>("id").unwrap());
+        let name = row.r_by_name::<String>("name").unwrap();
 
-        let kind = match row.r_by_name::<i32>(&"kind").unwrap() {
-            1 => Kind::Kafka,
-            _ => Kind::Unknown,
-        };
+        let kind = Kind::from(row.r_by_name::<i32>("kind").unwrap());
 
-        let config: HashMap<String, String> = match row.r_by_name::<Map>(&"config") {
+        let config: HashMap<String, String> = match row.r_by_name::<Map>("config") {
             Ok(m) => m.as_r_type().unwrap(),
             Err(_) => HashMap::new(),
         };
 
-        let created_at = row.r_by_name::<DateTime<Utc>>(&"created_at").unwrap();
-        let updated_at = row.r_by_name::<DateTime<Utc>>(&"updated_at").unwrap();
+        let created_at = row.r_by_name::<DateTime<Utc>>("created_at").unwrap();
+        let updated_at = row.r_by_name::<DateTime<Utc>>("updated_at").unwrap();
 
-        Cluster::init(id, kind, name, config, created_at, updated_at)
+        Cluster {
+            owner: owner::read(row),
+            ownership_confirmed_at: owner::read_confirmed_at(row),
+            ..Cluster::init(id, kind, name, config, created_at, updated_at)
+        }
     }
 }
 
 #[async_trait]
 impl ClusterStore for CdrsClusterStore {
-    async fn list(&self, ids: Option<Vec<i64>>) -> Result<Vec<Cluster>, AnyError> {
-        let stmt = "SELECT * FROM adm.clusters LIMIT 100;";
+    async fn list(
+        &self,
+        ids: Option<Vec<ClusterId>>,
+        page: Page,
+    ) -> Result<Vec<Cluster>, AnyError> {
+        if let Some(ids) = ids {
+            let mut clusters = self.list_ids(ids).await?;
+            clusters.sort_by_key(|c| c.id);
+            return Ok(page.slice(clusters));
+        }
+
+        // CQL has no offset, so the rows up to the end of the page are read and the offset skipped.
+        let stmt = match page.end() {
+            Some(end) => format!("SELECT * FROM adm.clusters LIMIT {};", end.max(1)),
+            None => "SELECT * FROM adm.clusters;".to_string(),
+        };
+        let rows = self.session.query(stmt).await;
+        let rows = self.parse(rows)?;
+        Ok(page.slice(rows.iter().map(|r| self.map(r))))
+    }
+
+    async fn count(&self, ids: Option<Vec<ClusterId>>) -> Result<usize, AnyError> {
+        if let Some(ids) = ids {
+            return Ok(self.list_ids(ids).await?.len());
+        }
+
+        let stmt = "SELECT COUNT(*) FROM adm.clusters;";
         let rows = self.session.query(stmt).await;
         let rows = self.parse(rows)?;
-        let clusters = rows.iter().map(|r| self.map(r)).collect::<Vec<_>>();
-        Ok(clusters)
+        let count = rows
+            .first()
+            .map_or(0, |r| r.r_by_name::<i64>("count").unwrap());
+        Ok(count as usize)
+    }
+
+    async fn search(&self, _q: &str, _limit: usize) -> Result<Vec<Scored<Cluster>>, AnyError> {
+        Err(SearchUnsupported("Cassandra").into())
     }
 
-    async fn get(&self, id: i64) -> result::Result<Option<Cluster>, AnyError> {
+    async fn get(&self, id: ClusterId) -> result::Result<Option<Cluster>, AnyError> {
         let stmt = "SELECT * FROM adm.clusters WHERE id = ?;";
-        let values = query_values!(id);
+        let values = query_values!(id.as_i64());
+        let rows = self.session.query_with_values(stmt, values).await;
+        let rows = self.parse(rows)?;
+
+        Ok(rows.first().map(|r| self.map(r)))
+    }
+
+    async fn get_by_name(&self, name: &str) -> result::Result<Option<Cluster>, AnyError> {
+        // `name_key` is indexed, CQL can't compare text case-insensitively.
+        let stmt = "SELECT * FROM adm.clusters WHERE name_key = ?;";
+        let values = query_values!(cluster::name_key(name));
         let rows = self.session.query_with_values(stmt, values).await;
         let rows = self.parse(rows)?;
-        if rows.len() == 0 {
-            return Ok(None);
-        }
 
-        Ok(Some(self.map(rows.get(0).unwrap())))
+        Ok(rows.first().map(|r| self.map(r)))
     }
 
-    async fn insert(&self, c: Cluster) -> result::Result<i64, AnyError> {
+    async fn insert(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
         let stmt = "
-            INSERT INTO adm.clusters (id, kind, name, config, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?);";
+            INSERT INTO adm.clusters (id, kind, name, name_key, config, created_at, updated_at, owner, ownership_confirmed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);";
 
-        let id = self.generator.next_id().unwrap();
+        let id = ClusterId(self.generator.next_id()?);
         let values = query_values!(
-            id,
-            c.kind.clone() as i32,
+            id.as_i64(),
+            c.kind.code(),
             c.name.clone(),
+            cluster::name_key(&c.name),
             c.config.clone(),
             c.created_at,
-            c.updated_at
+            c.updated_at,
+            owner::write(c.owner.as_ref()),
+            c.ownership_confirmed_at
         );
 
         self.session.query_with_values(stmt, values).await?;
@@ -218,28 +424,274 @@ impl ClusterStore for CdrsClusterStore {
         Ok(id)
     }
 
-    async fn update(&self, c: Cluster) -> result::Result<i64, AnyError> {
+    async fn update(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
         let stmt = "
 			UPDATE adm.clusters
-			SET name = ?, config = ?, updated_at = ?
+			SET name = ?, name_key = ?, config = ?, updated_at = ?, owner = ?, ownership_confirmed_at = ?
             WHERE id = ?;";
 
-        let values = query_values!(c.name.to_owned(), c.config.to_owned(), c.updated_at, c.id);
+        let values = query_values!(
+            c.name.to_owned(),
+            cluster::name_key(&c.name),
+            c.config.to_owned(),
+            c.updated_at,
+            owner::write(c.owner.as_ref()),
+            c.ownership_confirmed_at,
+            c.id.as_i64()
+        );
         self.session.query_with_values(stmt, values).await?;
 
         Ok(c.id)
     }
 
-    async fn remove(&self, id: i64) -> result::Result<i64, AnyError> {
+    async fn remove(&self, id: ClusterId) -> result::Result<ClusterId, AnyError> {
         let stmt = "DELETE FROM adm.clusters WHERE id = ?;";
-        let values = query_values!(id);
+        let values = query_values!(id.as_i64());
         self.session.query_with_values(stmt, values).await?;
 
         Ok(id)
     }
+
+    async fn ping(&self) -> Result<(), AnyError> {
+        let rows = self.session.query("SELECT now() FROM system.local;").await;
+        self.parse(rows)?;
+        Ok(())
+    }
+
+    async fn backfill_name_keys(&self) -> Result<usize, AnyError> {
+        let rows = self
+            .session
+            .query("SELECT id, name, name_key FROM adm.clusters;")
+            .await;
+        let rows = self.parse(rows)?;
+
+        let mut backfilled = 0;
+        for row in rows
+            .iter()
+            .filter(|r| r.by_name::<String>("name_key").ok().flatten().is_none())
+        {
+            let stmt = "UPDATE adm.clusters SET name_key = ? WHERE id = ?;";
+            let name = row.r_by_name::<String>("name")?;
+            let values = query_values!(cluster::name_key(&name), row.r_by_name::<i64>("id")?);
+            self.session.query_with_values(stmt, values).await?;
+            backfilled += 1;
+        }
+        Ok(backfilled)
+    }
+}
+
+/// An in-memory store, for tests and for trying seekr out without Cassandra.
+/// Nothing in it outlives the process.
+#[derive(Default)]
+pub struct MemoryClusterStore {
+    clusters: tokio::sync::RwLock<std::collections::BTreeMap<ClusterId, Cluster>>,
+    /// The highest id ever stored, so removing the newest cluster doesn't
+    /// hand its id to the next one.
+    last_id: std::sync::atomic::AtomicI64,
+
+    /// Fails pings, as a store that can't be reached does.
+    pub unreachable: std::sync::atomic::AtomicBool,
+}
+
+#[async_trait]
+impl ClusterStore for MemoryClusterStore {
+    async fn list(
+        &self,
+        ids: Option<Vec<ClusterId>>,
+        page: Page,
+    ) -> Result<Vec<Cluster>, AnyError> {
+        let clusters = self.clusters.read().await;
+        Ok(page.slice(
+            clusters
+                .values()
+                .filter(|c| ids.as_ref().is_none_or(|ids| ids.contains(&c.id)))
+                .cloned(),
+        ))
+    }
+
+    async fn count(&self, ids: Option<Vec<ClusterId>>) -> Result<usize, AnyError> {
+        Ok(self.list(ids, Page::ALL).await?.len())
+    }
+
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Cluster>>, AnyError> {
+        let clusters = self.clusters.read().await;
+        Ok(search::in_memory(
+            clusters.values().cloned(),
+            q,
+            limit,
+            |c| &c.name,
+        ))
+    }
+
+    async fn get(&self, id: ClusterId) -> result::Result<Option<Cluster>, AnyError> {
+        Ok(self.clusters.read().await.get(&id).cloned())
+    }
+
+    async fn get_by_name(&self, name: &str) -> result::Result<Option<Cluster>, AnyError> {
+        let clusters = self.clusters.read().await;
+        Ok(clusters.values().find(|c| c.is_named(name)).cloned())
+    }
+
+    async fn insert(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
+        let mut clusters = self.clusters.write().await;
+        let id = ClusterId(self.last_id.load(std::sync::atomic::Ordering::SeqCst) + 1);
+        self.last_id
+            .store(id.as_i64(), std::sync::atomic::Ordering::SeqCst);
+        clusters.insert(id, Cluster { id, ..c });
+        Ok(id)
+    }
+
+    async fn update(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
+        match self.clusters.write().await.get_mut(&c.id) {
+            Some(stored) => {
+                *stored = c.clone();
+                Ok(c.id)
+            }
+            None => Err(ClusterNotFound(c.id).into()),
+        }
+    }
+
+    async fn put(&self, c: Cluster) -> result::Result<ClusterId, AnyError> {
+        let mut clusters = self.clusters.write().await;
+        self.last_id
+            .fetch_max(c.id.as_i64(), std::sync::atomic::Ordering::SeqCst);
+        clusters.insert(c.id, c.clone());
+        Ok(c.id)
+    }
+
+    async fn remove(&self, id: ClusterId) -> result::Result<ClusterId, AnyError> {
+        match self.clusters.write().await.remove(&id) {
+            Some(_) => Ok(id),
+            None => Err(ClusterNotFound(id).into()),
+        }
+    }
+
+    async fn ping(&self) -> Result<(), AnyError> {
+        match self.unreachable.load(std::sync::atomic::Ordering::SeqCst) {
+            true => Err("the store is unreachable".into()),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Fails when the selected backend can't be reached with the config.
+pub async fn init_cluster_store(
+    config: &StoreConfig,
+) -> Result<Arc<dyn ClusterStore + Send + Sync>, AnyError> {
+    config.verify("cluster", config.clusters).await?;
+    match config.clusters {
+        StoreBackend::Meilisearch => {
+            let client = config.meilisearch.client();
+            Ok(Arc::new(MSClusterStore::new(client, id::generator()).await))
+        }
+        StoreBackend::Cassandra => {
+            let session = config.cassandra.session().await?;
+            Ok(Arc::new(CdrsClusterStore::new(session, id::generator())))
+        }
+        StoreBackend::Memory => Ok(Arc::new(MemoryClusterStore::default())),
+    }
+}
+
+#[test]
+fn it_filters_clusters_by_id() {
+    assert_eq!(filter(&[ClusterId(3), ClusterId(1)]), "id IN [3, 1]");
+}
+
+#[test]
+fn it_filters_clusters_by_name_key() {
+    assert_eq!(name_filter(" Prod "), "name_key = \"prod\"");
+    assert_eq!(name_filter("a\"b"), "name_key = \"a\\\"b\"");
+
+    let cluster = Cluster::new(None, Kind::Kafka, " Prod".to_string(), Default::default());
+    let document = serde_json::to_value(ClusterDocument::from(&cluster)).unwrap();
+    assert_eq!(document["name"], " Prod");
+    assert_eq!(document["name_key"], "prod");
+}
+
+#[tokio::test]
+async fn it_lists_only_the_clusters_asked_for() {
+    use super::cluster::Kind;
+
+    let store = MemoryClusterStore::default();
+    for name in ["a", "b", "c"] {
+        let c = Cluster::new(None, Kind::Kafka, name.to_string(), Default::default());
+        store.insert(c).await.unwrap();
+    }
+    let names = |clusters: Vec<Cluster>| clusters.into_iter().map(|c| c.name).collect::<Vec<_>>();
+
+    // Unknown ids are skipped, and the clusters come back in order of id.
+    let ids = vec![ClusterId(3), ClusterId(42), ClusterId(1)];
+    let listed = store.list(Some(ids.clone()), Page::ALL).await.unwrap();
+    assert_eq!(names(listed), ["a", "c"]);
+    assert_eq!(store.count(Some(ids)).await.unwrap(), 2);
+
+    // No ids list nothing, no filter lists everything.
+    assert!(store
+        .list(Some(vec![]), Page::ALL)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(store.count(Some(vec![])).await.unwrap(), 0);
+    assert_eq!(
+        names(store.list(None, Page::ALL).await.unwrap()),
+        ["a", "b", "c"]
+    );
+}
+
+#[tokio::test]
+async fn it_gives_concurrent_inserts_their_own_ids() {
+    let store = Arc::new(MemoryClusterStore::default());
+    let inserts = (0..16).map(|i| {
+        let store = store.clone();
+        tokio::spawn(async move {
+            let c = Cluster::new(None, Kind::Kafka, i.to_string(), Default::default());
+            store.insert(c).await.unwrap()
+        })
+    });
+    let mut ids = Vec::new();
+    for insert in inserts.collect::<Vec<_>>() {
+        ids.push(insert.await.unwrap());
+    }
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 16);
+    assert_eq!(store.count(None).await.unwrap(), 16);
+
+    store.remove(ids[0]).await.unwrap();
+    let e = store.remove(ids[0]).await.unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&ClusterNotFound(ids[0])));
 }
 
-pub async fn init_cluster_store() -> Arc<dyn ClusterStore + Send + Sync> {
-    // Arc::new(CdrsClusterStore::new(session, generator))
-    Arc::new(MSClusterStore::new(MS_CLIENT.clone(), ID_GENERATOR.clone()).await)
+#[tokio::test]
+async fn it_never_reuses_ids_nor_updates_missing_clusters() {
+    let store = MemoryClusterStore::default();
+    let cluster =
+        |name: &str| Cluster::new(None, Kind::Kafka, name.to_string(), Default::default());
+    let a = store.insert(cluster("a")).await.unwrap();
+    let b = store.insert(cluster("b")).await.unwrap();
+
+    // The newest cluster's id stays retired once it's removed.
+    store.remove(b).await.unwrap();
+    let c = store.insert(cluster("c")).await.unwrap();
+    assert_eq!((a, c), (ClusterId(1), ClusterId(3)));
+
+    let e = store
+        .update(Cluster {
+            id: b,
+            ..cluster("b")
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(e.downcast_ref(), Some(&ClusterNotFound(b)));
+    assert!(store.get(b).await.unwrap().is_none());
+
+    // Putting writes under the cluster's own id, and inserts go past it.
+    store
+        .put(Cluster {
+            id: ClusterId(10),
+            ..cluster("d")
+        })
+        .await
+        .unwrap();
+    assert_eq!(store.insert(cluster("e")).await.unwrap(), ClusterId(11));
 }
@@ -0,0 +1,88 @@
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, post, HttpResponse, Responder};
+
+use crate::auth::Principal;
+use crate::collisions::CollisionScanner;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(scan_id_collisions).service(get_id_collisions);
+}
+
+/// Scans run in the background, their report is read with a GET.
+#[post("/scan-id-collisions")]
+async fn scan_id_collisions(
+    principal: Principal,
+    scanner: Data<CollisionScanner>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    info!("Scanning for id collisions");
+    match scanner.clone().into_inner().start() {
+        true => HttpResponse::Accepted().json(scanner.status()),
+        false => HttpResponse::Conflict().body("A scan is running already"),
+    }
+}
+
+#[get("/scan-id-collisions")]
+async fn get_id_collisions(
+    principal: Principal,
+    scanner: Data<CollisionScanner>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    HttpResponse::Ok().json(scanner.status())
+}
+
+#[actix_web::test]
+async fn it_scans_in_the_background() {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::mirrors::store::MemoryMirrorPairStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs = Arc::new(MemoryClusterStore::default());
+    let mut cluster = Cluster::new(None, Kind::Kafka, "local".to_string(), HashMap::new());
+    cluster.created_at = cluster.updated_at + chrono::Duration::days(1);
+    cs.insert(cluster).await.unwrap();
+    let scanner = Data::new(CollisionScanner::new(
+        cs,
+        Arc::new(MemorySubscriptionStore::default()),
+        Arc::new(MemoryMirrorPairStore::default()),
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(scanner.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/scan-id-collisions")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    while scanner.status().running {
+        tokio::task::yield_now().await;
+    }
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/scan-id-collisions")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["report"]["scanned"], 1);
+    assert_eq!(body["report"]["suspects"][0]["entity"], "cluster");
+    assert_eq!(
+        body["report"]["suspects"][0]["sign"],
+        "created_after_updated"
+    );
+}
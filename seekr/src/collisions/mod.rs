@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::clusters::cluster::Cluster;
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::mirrors::mirror_pair::MirrorPair;
+use crate::mirrors::store::MirrorPairStore;
+use crate::page::Page;
+use crate::subscriptions::store::SubscriptionStore;
+use crate::subscriptions::subscription::Subscription;
+
+pub mod endpoints;
+
+/// Clock skew between instances tolerated before an entity is taken to be
+/// older than the cluster it belongs to.
+const SKEW_TOLERANCE: Duration = Duration::seconds(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    Cluster,
+    Subscription,
+    MirrorPair,
+}
+
+/// A tell-tale sign of an entity overwritten by another one with the same id.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "sign", rename_all = "snake_case")]
+pub enum Sign {
+    /// Replaced by a document created later, which kept the other's `updated_at`.
+    CreatedAfterUpdated {
+        created_at: DateTime<Utc>,
+        updated_at: DateTime<Utc>,
+    },
+
+    /// Created before the cluster it belongs to, which must have replaced the
+    /// cluster it was created in.
+    PredatesCluster {
+        cluster_id: i64,
+        cluster_name: String,
+        cluster_created_at: DateTime<Utc>,
+    },
+}
+
+/// An entity to review by hand, its data possibly mixed up with another's.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Suspect {
+    pub entity: Entity,
+    pub id: i64,
+    /// The name of a cluster, the topic of a subscription.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub sign: Sign,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CollisionReport {
+    pub scanned_at: DateTime<Utc>,
+    /// The number of entities looked at.
+    pub scanned: usize,
+    pub suspects: Vec<Suspect>,
+}
+
+/// An entity of any kind, as far as the scan is concerned.
+struct Candidate {
+    entity: Entity,
+    id: i64,
+    name: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    /// The clusters the entity belongs to.
+    cluster_ids: Vec<ClusterId>,
+}
+
+/// Look for entities whose id collided with another's, overwriting it.
+///
+/// Nothing is repaired, the suspects are left for manual review.
+pub fn scan(
+    clusters: &[Cluster],
+    subscriptions: &[Subscription],
+    mirror_pairs: &[MirrorPair],
+) -> CollisionReport {
+    let mut suspects = vec![];
+    let by_id = clusters
+        .iter()
+        .map(|c| (c.id, c))
+        .collect::<HashMap<_, _>>();
+
+    let candidates = clusters
+        .iter()
+        .map(|c| Candidate {
+            entity: Entity::Cluster,
+            id: c.id.as_i64(),
+            name: c.name.clone(),
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+            cluster_ids: vec![],
+        })
+        .chain(subscriptions.iter().map(|s| Candidate {
+            entity: Entity::Subscription,
+            id: s.id.as_i64(),
+            name: s.topic_name.clone(),
+            created_at: s.created_at,
+            updated_at: s.updated_at,
+            cluster_ids: vec![s.cluster_id],
+        }))
+        .chain(mirror_pairs.iter().map(|p| Candidate {
+            entity: Entity::MirrorPair,
+            id: p.id.as_i64(),
+            name: format!("{} -> {}", p.source_cluster_id, p.target_cluster_id),
+            created_at: p.created_at,
+            updated_at: p.updated_at,
+            cluster_ids: vec![p.source_cluster_id, p.target_cluster_id],
+        }));
+
+    for Candidate {
+        entity,
+        id,
+        name,
+        created_at,
+        updated_at,
+        cluster_ids,
+    } in candidates
+    {
+        if created_at > updated_at {
+            suspects.push(Suspect {
+                entity,
+                id,
+                name: name.clone(),
+                created_at,
+                sign: Sign::CreatedAfterUpdated {
+                    created_at,
+                    updated_at,
+                },
+            });
+        }
+
+        // Entities of deleted clusters are orphaned, not collided.
+        for cluster in cluster_ids.iter().filter_map(|id| by_id.get(id)) {
+            if created_at + SKEW_TOLERANCE < cluster.created_at {
+                suspects.push(Suspect {
+                    entity,
+                    id,
+                    name: name.clone(),
+                    created_at,
+                    sign: Sign::PredatesCluster {
+                        cluster_id: cluster.id.as_i64(),
+                        cluster_name: cluster.name.clone(),
+                        cluster_created_at: cluster.created_at,
+                    },
+                });
+            }
+        }
+    }
+
+    CollisionReport {
+        scanned_at: Utc::now(),
+        scanned: clusters.len() + subscriptions.len() + mirror_pairs.len(),
+        suspects,
+    }
+}
+
+/// The state of the last scan started.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ScanStatus {
+    pub running: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub report: Option<CollisionReport>,
+}
+
+/// Scans the stores for past id collisions in the background.
+pub struct CollisionScanner {
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    mirror_pairs: Arc<dyn MirrorPairStore + Send + Sync>,
+    status: RwLock<ScanStatus>,
+}
+
+impl CollisionScanner {
+    pub fn new(
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+        mirror_pairs: Arc<dyn MirrorPairStore + Send + Sync>,
+    ) -> Self {
+        Self {
+            clusters,
+            subscriptions,
+            mirror_pairs,
+            status: RwLock::new(ScanStatus::default()),
+        }
+    }
+
+    pub async fn scan(&self) -> Result<CollisionReport, AnyError> {
+        let clusters = self.clusters.list(None, Page::ALL).await?;
+        let subscriptions = self.subscriptions.list(None, Page::ALL).await?;
+        let mirror_pairs = self.mirror_pairs.list().await?;
+
+        let report = scan(&clusters, &subscriptions, &mirror_pairs);
+        if !report.suspects.is_empty() {
+            warn!(
+                "Found {} entities whose ids may have collided, review them at /admin/scan-id-collisions",
+                report.suspects.len()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Start a scan in the background, `false` when one is running already.
+    pub fn start(self: Arc<Self>) -> bool {
+        {
+            let mut status = self.status.write().unwrap();
+            if status.running {
+                return false;
+            }
+            *status = ScanStatus {
+                running: true,
+                started_at: Some(Utc::now()),
+                ..status.clone()
+            };
+        }
+
+        tokio::spawn(async move {
+            let result = self.scan().await;
+
+            let mut status = self.status.write().unwrap();
+            status.running = false;
+            status.finished_at = Some(Utc::now());
+            match result {
+                Ok(report) => {
+                    status.error = None;
+                    status.report = Some(report);
+                }
+                Err(e) => {
+                    warn!("Failed to scan for id collisions - {}", e);
+                    status.error = Some(e.to_string());
+                }
+            }
+        });
+        true
+    }
+
+    pub fn status(&self) -> ScanStatus {
+        self.status.read().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+fn at(seconds: i64) -> DateTime<Utc> {
+    chrono::TimeZone::timestamp_opt(&Utc, 1_700_000_000 + seconds, 0).unwrap()
+}
+
+#[cfg(test)]
+fn cluster(id: i64, name: &str, created: i64, updated: i64) -> Cluster {
+    use crate::clusters::cluster::Kind;
+
+    Cluster {
+        created_at: at(created),
+        updated_at: at(updated),
+        ..Cluster::new(
+            Some(ClusterId(id)),
+            Kind::Kafka,
+            name.to_string(),
+            HashMap::new(),
+        )
+    }
+}
+
+#[cfg(test)]
+fn subscription(id: i64, cluster_id: i64, topic: &str, created: i64) -> Subscription {
+    use crate::ids::SubscriptionId;
+
+    Subscription {
+        created_at: at(created),
+        updated_at: at(created),
+        ..Subscription::new(
+            Some(SubscriptionId(id)),
+            ClusterId(cluster_id),
+            topic.to_string(),
+            HashMap::new(),
+        )
+    }
+}
+
+#[test]
+fn it_flags_the_signs_of_past_collisions() {
+    use crate::ids::MirrorPairId;
+    use crate::mirrors::mirror_pair::TopicMapping;
+
+    // "payments" overwrote "local" at id 1 a day after it was created, and
+    // kept the updated_at of the document it replaced.
+    let clusters = [
+        cluster(1, "payments", 86_400, 3_600),
+        cluster(2, "search", 0, 0),
+    ];
+    let subscriptions = [
+        subscription(10, 1, "orders", 60),
+        subscription(11, 2, "orders", 60),
+    ];
+    let mirror_pairs = [MirrorPair {
+        created_at: at(120),
+        updated_at: at(120),
+        ..MirrorPair::new(
+            Some(MirrorPairId(20)),
+            ClusterId(2),
+            ClusterId(1),
+            TopicMapping::Explicit(HashMap::new()),
+        )
+    }];
+
+    let report = scan(&clusters, &subscriptions, &mirror_pairs);
+    assert_eq!(report.scanned, 5);
+    let suspects = report
+        .suspects
+        .iter()
+        .map(|s| (s.entity, s.id))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        suspects,
+        vec![
+            (Entity::Cluster, 1),
+            (Entity::Subscription, 10),
+            (Entity::MirrorPair, 20),
+        ]
+    );
+    assert_eq!(
+        report.suspects[1].sign,
+        Sign::PredatesCluster {
+            cluster_id: 1,
+            cluster_name: "payments".to_string(),
+            cluster_created_at: at(86_400),
+        }
+    );
+}
+
+#[test]
+fn it_leaves_healthy_entities_alone() {
+    let clusters = [
+        // Updated since created, and never updated.
+        cluster(1, "local", 0, 3_600),
+        cluster(2, "search", 600, 600),
+    ];
+    let subscriptions = [
+        subscription(10, 1, "orders", 60),
+        // Within the clock skew of the instance that created the cluster.
+        Subscription {
+            created_at: at(600) - Duration::milliseconds(500),
+            ..subscription(11, 2, "orders", 600)
+        },
+        // Of a deleted cluster, orphaned rather than collided.
+        subscription(12, 3, "orders", 0),
+    ];
+
+    let report = scan(&clusters, &subscriptions, &[]);
+    assert_eq!(report.scanned, 5);
+    assert_eq!(report.suspects, vec![]);
+}
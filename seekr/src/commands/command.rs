@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ids::{CommandId, SubscriptionId};
+
+wire_enum! {
+    /// A control action, stored as its lowercase name.
+    pub enum CommandKind {
+        Pause = "pause",
+        Resume = "resume",
+    }
+}
+
+wire_enum! {
+    /// Where a command is in its delivery, stored as its lowercase name.
+    pub enum CommandStatus {
+        /// Waiting for the indexer to apply the command.
+        Pending = "pending",
+        /// Applied by the indexer.
+        Acknowledged = "acknowledged",
+        /// Rejected by the indexer, or not acknowledged in time.
+        Failed = "failed",
+    }
+}
+
+/// A control action the server asks the indexer to apply to a subscription.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Command {
+    /// The unique id of the command, which also orders it within its subscription.
+    pub id: CommandId,
+
+    /// The subscription the command applies to.
+    pub subscription_id: SubscriptionId,
+
+    /// The action to apply.
+    pub kind: CommandKind,
+
+    /// Arguments of the action, if any.
+    #[serde(default)]
+    pub payload: Value,
+
+    /// The delivery status of the command.
+    pub status: CommandStatus,
+
+    /// The outcome reported when the command was acknowledged or failed.
+    pub result: Option<String>,
+
+    /// Represents the point in time in UTC Epoch time, when the command was queued.
+    pub created_at: DateTime<Utc>,
+
+    /// Represents the point in time in UTC Epoch time, when the command was acknowledged or failed.
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl Command {
+    pub fn new(subscription_id: SubscriptionId, kind: CommandKind, payload: Value) -> Self {
+        Command {
+            id: CommandId::default(),
+            subscription_id,
+            kind,
+            payload,
+            status: CommandStatus::Pending,
+            result: None,
+            created_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    /// Returns `true` if the command is still pending after the timeout.
+    pub fn is_expired(&self, now: DateTime<Utc>, timeout: chrono::Duration) -> bool {
+        self.status == CommandStatus::Pending && self.created_at + timeout <= now
+    }
+
+    /// Complete the command with the given status and result.
+    pub fn complete(
+        self,
+        status: CommandStatus,
+        result: Option<String>,
+        at: DateTime<Utc>,
+    ) -> Self {
+        Command {
+            status,
+            result,
+            completed_at: Some(at),
+            ..self
+        }
+    }
+}
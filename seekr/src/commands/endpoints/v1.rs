@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, post, HttpResponse, Responder, ResponseError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::commands;
+use crate::commands::command::Command;
+use crate::commands::store::CommandStore;
+use crate::errors::ApiError;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::subscriptions::service::{self, StateChange};
+use crate::subscriptions::store::SubscriptionStore;
+use crate::subscriptions::subscription::SubscriptionState;
+
+/// Default number of commands returned.
+const DEFAULT_COMMANDS_LIMIT: usize = 50;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(pause_subscription)
+        .service(resume_subscription)
+        .service(get_commands);
+}
+
+#[post("/{cluster_id}/{id}/pause")]
+async fn pause_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Pausing subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    send(cluster_id, id, SubscriptionState::Paused, &ss, &qs).await
+}
+
+#[post("/{cluster_id}/{id}/resume")]
+async fn resume_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Resuming subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    send(cluster_id, id, SubscriptionState::Active, &ss, &qs).await
+}
+
+#[get("/{cluster_id}/{id}/commands")]
+async fn get_commands(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<CommandsQuery>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+
+    let subscription = match ss.get(cluster_id, id).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    // Fail the commands a stopped indexer never picked up.
+    let timeout = commands::timeout(&subscription);
+    if let Err(e) = commands::expire(qs.as_ref().as_ref(), id, timeout, Utc::now()).await {
+        return HttpResponse::InternalServerError().body(e.to_string());
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_COMMANDS_LIMIT);
+
+    match qs.list(id, limit).await {
+        Ok(commands) => HttpResponse::Ok().json(CommandsResponse { commands }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Move the subscription to `state`, answering with the command telling its
+/// worker to follow, or with the state alone when it's already there.
+async fn send(
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    state: SubscriptionState,
+    ss: &Arc<dyn SubscriptionStore + Send + Sync>,
+    qs: &Arc<dyn CommandStore + Send + Sync>,
+) -> HttpResponse {
+    match service::set_state(ss.as_ref(), qs, cluster_id, id, state).await {
+        Ok(StateChange::Changed(command)) => {
+            HttpResponse::Accepted().json(CommandResponse { command })
+        }
+        Ok(StateChange::Unchanged(state)) => HttpResponse::Ok().json(StateResponse { id, state }),
+        Ok(StateChange::NotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => ApiError::from(e).error_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct CommandsQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct CommandResponse {
+    command: Command,
+}
+
+#[derive(Serialize)]
+struct StateResponse {
+    id: SubscriptionId,
+    state: SubscriptionState,
+}
+
+#[derive(Serialize)]
+struct CommandsResponse {
+    commands: Vec<Command>,
+}
+
+#[actix_web::test]
+async fn it_pauses_and_resumes_subscriptions_once() {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::commands::command::CommandKind;
+    use crate::commands::store::MemoryCommandStore;
+    use crate::debug::store::{DebugStore, MemoryDebugStore};
+    use crate::kafka::streams::service::WorkerState;
+    use crate::kafka::streams::stages::StageReport;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::Subscription;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let subscription = Subscription::new(
+        Some(SubscriptionId(1)),
+        ClusterId(1),
+        "orders".to_string(),
+        HashMap::new(),
+    );
+    ss.put(subscription).await.unwrap();
+    let qs: Arc<dyn CommandStore + Send + Sync> = Arc::new(MemoryCommandStore::default());
+    let ds: Arc<dyn DebugStore + Send + Sync> = Arc::new(MemoryDebugStore::default());
+    let running = StageReport {
+        state: Some(WorkerState::Running),
+        ..StageReport::default()
+    };
+    ds.put_stages(SubscriptionId(1), running).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(ss.clone()))
+            .app_data(Data::new(qs.clone()))
+            .app_data(Data::new(ds))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let post = |action: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/subscriptions/1/1/{}", action))
+            .to_request()
+    };
+    let get = || {
+        test::TestRequest::get()
+            .uri("/api/v1/subscriptions/1/1")
+            .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, get()).await;
+    assert!(body["subscription"].get("state").is_none());
+    assert_eq!(body["worker_status"], "Running");
+
+    let res = test::call_service(&app, post("pause")).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["command"]["kind"], "pause");
+    let body: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(body["subscription"]["state"], "Paused");
+    assert_eq!(body["worker_status"], "Paused");
+
+    // Pausing again changes nothing, and tells the worker nothing.
+    let res = test::call_service(&app, post("pause")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["state"], "Paused");
+    assert_eq!(qs.list(SubscriptionId(1), 10).await.unwrap().len(), 1);
+
+    let res = test::call_service(&app, post("resume")).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let subscription = ss.get(ClusterId(1), SubscriptionId(1)).await.unwrap();
+    assert!(subscription.unwrap().state.is_active());
+    let res = test::call_service(&app, post("resume")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let commands = qs.list(SubscriptionId(1), 10).await.unwrap();
+    assert_eq!(commands.len(), 2);
+    assert!(commands.iter().any(|c| c.kind == CommandKind::Resume));
+
+    let res = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/v1/subscriptions/1/9/pause")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::kafka::config;
+use crate::subscriptions::subscription::Subscription;
+
+use self::command::{Command, CommandKind, CommandStatus};
+use self::store::CommandStore;
+
+pub mod command;
+pub mod endpoints;
+pub mod processor;
+pub mod store;
+
+/// How long a command may stay pending before it fails.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The command timeout configured for a subscription.
+pub fn timeout(subscription: &Subscription) -> Duration {
+    subscription
+        .config
+        .get(config::COMMANDS_TIMEOUT)
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_TIMEOUT)
+}
+
+/// Queue a command for the indexer, returning it with its allocated id.
+pub async fn enqueue(
+    store: &(dyn CommandStore + Send + Sync),
+    id: SubscriptionId,
+    kind: CommandKind,
+    payload: Value,
+) -> Result<Command, AnyError> {
+    let command = Command::new(id, kind, payload);
+    let id = store.append(command.clone()).await?;
+    Ok(Command { id, ..command })
+}
+
+/// Fail the pending commands of a subscription that were not acknowledged in time.
+pub async fn expire(
+    store: &(dyn CommandStore + Send + Sync),
+    id: SubscriptionId,
+    timeout: Duration,
+    now: DateTime<Utc>,
+) -> Result<usize, AnyError> {
+    let mut expired = 0;
+
+    for command in store.pending(id).await? {
+        if command.is_expired(now, chrono::Duration::from_std(timeout)?) {
+            store.complete(timed_out(command, timeout, now)).await?;
+            expired += 1;
+        }
+    }
+
+    Ok(expired)
+}
+
+fn timed_out(command: Command, timeout: Duration, now: DateTime<Utc>) -> Command {
+    let result = format!("not acknowledged within {}ms", timeout.as_millis());
+    command.complete(CommandStatus::Failed, Some(result), now)
+}
+
+/// Whether the most recently applied pause or resume command paused the subscription.
+pub async fn is_paused(
+    store: Arc<dyn CommandStore + Send + Sync>,
+    id: SubscriptionId,
+) -> Result<bool, AnyError> {
+    let last = store
+        .last_acknowledged(id, &[CommandKind::Pause, CommandKind::Resume])
+        .await?;
+    Ok(last.is_some_and(|c| c.kind == CommandKind::Pause))
+}
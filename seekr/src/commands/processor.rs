@@ -0,0 +1,262 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::errors::AnyError;
+use crate::ids::{CommandId, SubscriptionId};
+
+use super::command::{Command, CommandStatus};
+use super::store::CommandStore;
+
+/// Applies commands to a running worker.
+///
+/// Commands are delivered at least once: a command applied right before the
+/// indexer stopped is delivered again after a restart, so handlers must be
+/// idempotent.
+#[async_trait]
+pub trait CommandHandler {
+    /// Apply the command, returning a short description of the outcome.
+    async fn handle(&self, command: &Command) -> Result<Option<String>, AnyError>;
+}
+
+/// Consumes the command queue of a single subscription in order.
+pub struct CommandProcessor {
+    store: Arc<dyn CommandStore + Send + Sync>,
+    id: SubscriptionId,
+    timeout: Duration,
+    /// The last command acknowledged by this processor.
+    last: Option<CommandId>,
+}
+
+impl CommandProcessor {
+    pub fn new(
+        store: Arc<dyn CommandStore + Send + Sync>,
+        id: SubscriptionId,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            store,
+            id,
+            timeout,
+            last: None,
+        }
+    }
+
+    /// Apply and acknowledge the pending commands, returning the number completed.
+    ///
+    /// Processing stops at the first store error, so a command is never
+    /// acknowledged before the ones queued ahead of it.
+    pub async fn poll(&mut self, handler: &(dyn CommandHandler + Sync)) -> Result<usize, AnyError> {
+        let timeout = chrono::Duration::from_std(self.timeout)?;
+        let mut completed = 0;
+
+        for command in self.store.pending(self.id).await? {
+            // A stale read can still list commands this processor acknowledged.
+            if self.last.is_some_and(|last| command.id <= last) {
+                continue;
+            }
+
+            let now = Utc::now();
+            let command = if command.is_expired(now, timeout) {
+                super::timed_out(command, self.timeout, now)
+            } else {
+                match handler.handle(&command).await {
+                    Ok(result) => command.complete(CommandStatus::Acknowledged, result, Utc::now()),
+                    Err(e) => {
+                        command.complete(CommandStatus::Failed, Some(e.to_string()), Utc::now())
+                    }
+                }
+            };
+
+            let id = command.id;
+            self.store.complete(command).await?;
+            self.last = Some(id);
+            completed += 1;
+        }
+
+        Ok(completed)
+    }
+}
+
+/// A fake indexer that records the commands it applied.
+#[cfg(test)]
+#[derive(Default)]
+struct FakeIndexer {
+    applied: std::sync::Mutex<Vec<CommandId>>,
+    paused: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl CommandHandler for FakeIndexer {
+    async fn handle(&self, command: &Command) -> Result<Option<String>, AnyError> {
+        use super::command::CommandKind;
+        use std::sync::atomic::Ordering;
+
+        self.applied.lock().unwrap().push(command.id);
+        let paused = command.kind == CommandKind::Pause;
+        self.paused.store(paused, Ordering::SeqCst);
+        Ok(Some(command.kind.as_str().to_string()))
+    }
+}
+
+/// A store whose next `complete` fails, as if the indexer stopped before acknowledging.
+#[cfg(test)]
+#[derive(Default)]
+struct CrashingStore {
+    inner: super::store::MemoryCommandStore,
+    crash: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl CommandStore for CrashingStore {
+    async fn append(&self, command: Command) -> Result<CommandId, AnyError> {
+        self.inner.append(command).await
+    }
+
+    async fn pending(&self, id: SubscriptionId) -> Result<Vec<Command>, AnyError> {
+        self.inner.pending(id).await
+    }
+
+    async fn list(&self, id: SubscriptionId, limit: usize) -> Result<Vec<Command>, AnyError> {
+        self.inner.list(id, limit).await
+    }
+
+    async fn last_acknowledged(
+        &self,
+        id: SubscriptionId,
+        kinds: &[super::command::CommandKind],
+    ) -> Result<Option<Command>, AnyError> {
+        self.inner.last_acknowledged(id, kinds).await
+    }
+
+    async fn complete(&self, command: Command) -> Result<(), AnyError> {
+        if self.crash.swap(false, std::sync::atomic::Ordering::SeqCst) {
+            return Err("indexer stopped".into());
+        }
+        self.inner.complete(command).await
+    }
+}
+
+#[cfg(test)]
+async fn queue(store: &(dyn CommandStore + Send + Sync), kinds: &[super::command::CommandKind]) {
+    for kind in kinds {
+        super::enqueue(store, SubscriptionId(1), kind.clone(), Default::default())
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn it_applies_commands_in_order_per_subscription() {
+    use super::command::CommandKind::{Pause, Resume};
+
+    let store: Arc<dyn CommandStore + Send + Sync> =
+        Arc::new(super::store::MemoryCommandStore::default());
+    queue(store.as_ref(), &[Pause, Resume, Pause]).await;
+    super::enqueue(
+        store.as_ref(),
+        SubscriptionId(2),
+        Resume,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    let indexer = FakeIndexer::default();
+    let mut processor =
+        CommandProcessor::new(store.clone(), SubscriptionId(1), Duration::from_secs(60));
+
+    assert_eq!(processor.poll(&indexer).await.unwrap(), 3);
+    assert_eq!(
+        *indexer.applied.lock().unwrap(),
+        vec![CommandId(1), CommandId(2), CommandId(3)]
+    );
+    assert!(indexer.paused.load(std::sync::atomic::Ordering::SeqCst));
+
+    // Acknowledged commands are never applied again, and other subscriptions are untouched.
+    assert_eq!(processor.poll(&indexer).await.unwrap(), 0);
+    assert_eq!(store.pending(SubscriptionId(2)).await.unwrap().len(), 1);
+
+    let history = store.list(SubscriptionId(1), 10).await.unwrap();
+    assert!(history
+        .iter()
+        .all(|c| c.status == CommandStatus::Acknowledged));
+    assert!(history.iter().all(|c| c.completed_at.is_some()));
+}
+
+#[tokio::test]
+async fn it_redelivers_commands_interrupted_by_a_restart() {
+    use super::command::CommandKind::{Pause, Resume};
+    use std::sync::atomic::Ordering;
+
+    let store = Arc::new(CrashingStore::default());
+    queue(store.as_ref(), &[Pause, Resume]).await;
+
+    // The indexer applies the pause but stops before acknowledging it.
+    let indexer = FakeIndexer::default();
+    let mut processor =
+        CommandProcessor::new(store.clone(), SubscriptionId(1), Duration::from_secs(60));
+    store.crash.store(true, Ordering::SeqCst);
+    assert!(processor.poll(&indexer).await.is_err());
+    assert_eq!(*indexer.applied.lock().unwrap(), vec![CommandId(1)]);
+    assert_eq!(store.pending(SubscriptionId(1)).await.unwrap().len(), 2);
+
+    // After a restart the pause is delivered again before the resume.
+    let mut restarted =
+        CommandProcessor::new(store.clone(), SubscriptionId(1), Duration::from_secs(60));
+    assert_eq!(restarted.poll(&indexer).await.unwrap(), 2);
+    assert_eq!(
+        *indexer.applied.lock().unwrap(),
+        vec![CommandId(1), CommandId(1), CommandId(2)]
+    );
+    assert!(!indexer.paused.load(Ordering::SeqCst));
+
+    // A second restart finds nothing left to replay.
+    let mut again =
+        CommandProcessor::new(store.clone(), SubscriptionId(1), Duration::from_secs(60));
+    assert_eq!(again.poll(&indexer).await.unwrap(), 0);
+    assert_eq!(indexer.applied.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn it_fails_commands_that_were_not_acknowledged_in_time() {
+    use super::command::CommandKind::Pause;
+
+    let store: Arc<dyn CommandStore + Send + Sync> =
+        Arc::new(super::store::MemoryCommandStore::default());
+    queue(store.as_ref(), &[Pause]).await;
+
+    // The indexer is down, so the server expires the command.
+    let later = Utc::now() + chrono::Duration::seconds(61);
+    let expired = super::expire(
+        store.as_ref(),
+        SubscriptionId(1),
+        Duration::from_secs(60),
+        later,
+    )
+    .await
+    .unwrap();
+    assert_eq!(expired, 1);
+
+    let command = &store.list(SubscriptionId(1), 1).await.unwrap()[0];
+    assert_eq!(command.status, CommandStatus::Failed);
+    assert_eq!(
+        command.result.as_deref(),
+        Some("not acknowledged within 60000ms")
+    );
+
+    // An indexer that comes back does not apply it, and fails stale commands itself.
+    queue(store.as_ref(), &[Pause]).await;
+    let indexer = FakeIndexer::default();
+    let mut processor = CommandProcessor::new(store.clone(), SubscriptionId(1), Duration::ZERO);
+    assert_eq!(processor.poll(&indexer).await.unwrap(), 1);
+    assert!(indexer.applied.lock().unwrap().is_empty());
+    assert_eq!(
+        store.list(SubscriptionId(1), 1).await.unwrap()[0].status,
+        CommandStatus::Failed
+    );
+}
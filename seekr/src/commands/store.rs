@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+
+use crate::errors::AnyError;
+use crate::id;
+use crate::ids::{CommandId, SubscriptionId};
+use crate::meilisearch::MeilisearchConfig;
+
+use super::command::{Command, CommandKind};
+
+#[async_trait]
+pub trait CommandStore {
+    /// Queue a command, returning its newly allocated id.
+    async fn append(&self, command: Command) -> Result<CommandId, AnyError>;
+
+    /// The pending commands of a subscription, oldest first.
+    async fn pending(&self, id: SubscriptionId) -> Result<Vec<Command>, AnyError>;
+
+    /// The most recent `limit` commands of a subscription, newest first.
+    async fn list(&self, id: SubscriptionId, limit: usize) -> Result<Vec<Command>, AnyError>;
+
+    /// The most recently acknowledged command of a subscription with one of the given kinds.
+    async fn last_acknowledged(
+        &self,
+        id: SubscriptionId,
+        kinds: &[CommandKind],
+    ) -> Result<Option<Command>, AnyError>;
+
+    /// Replace a command with its completed version.
+    async fn complete(&self, command: Command) -> Result<(), AnyError>;
+}
+
+pub const INDEX_NAME: &str = "commands";
+
+pub struct MSCommandStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+    /// A Distributed Unique ID generator.
+    generator: Arc<id::Generator>,
+}
+
+impl MSCommandStore {
+    pub async fn new(client: Arc<Client>, generator: Arc<id::Generator>) -> Self {
+        if let Ok(task) = client.clone().create_index(INDEX_NAME, Some("id")).await {
+            task.wait_for_completion(&client, None, None).await.unwrap();
+        }
+
+        let index = client.index(INDEX_NAME);
+        let filterable = ["subscription_id", "status", "kind"];
+        if let Err(e) = index.set_filterable_attributes(filterable).await {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                INDEX_NAME, e
+            );
+        }
+        if let Err(e) = index.set_sortable_attributes(["id"]).await {
+            warn!("Unable to set sortable attributes on {}: {}", INDEX_NAME, e);
+        }
+
+        Self { client, generator }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+
+    async fn search(
+        &self,
+        filter: &str,
+        sort: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<Command>, AnyError> {
+        let index = self.index();
+        let sort = [sort];
+        let mut query = index.search();
+        query.with_filter(filter).with_sort(&sort);
+        if let Some(limit) = limit {
+            query.with_limit(limit);
+        }
+
+        let results = query.execute::<Command>().await?;
+        Ok(results.hits.into_iter().map(|h| h.result).collect())
+    }
+
+    async fn put(&self, command: &Command) -> Result<(), AnyError> {
+        self.index()
+            .add_or_replace(&[command], Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CommandStore for MSCommandStore {
+    async fn append(&self, command: Command) -> Result<CommandId, AnyError> {
+        let command = Command {
+            id: CommandId(self.generator.next_id()?),
+            ..command
+        };
+
+        self.put(&command).await?;
+        Ok(command.id)
+    }
+
+    async fn pending(&self, id: SubscriptionId) -> Result<Vec<Command>, AnyError> {
+        let filter = format!("subscription_id = {} AND status = pending", id);
+        self.search(&filter, "id:asc", None).await
+    }
+
+    async fn list(&self, id: SubscriptionId, limit: usize) -> Result<Vec<Command>, AnyError> {
+        let filter = format!("subscription_id = {}", id);
+        self.search(&filter, "id:desc", Some(limit)).await
+    }
+
+    async fn last_acknowledged(
+        &self,
+        id: SubscriptionId,
+        kinds: &[CommandKind],
+    ) -> Result<Option<Command>, AnyError> {
+        if kinds.is_empty() {
+            return Ok(None);
+        }
+
+        let kinds = kinds
+            .iter()
+            .map(|k| format!("kind = {}", k.as_str()))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let filter = format!(
+            "subscription_id = {} AND status = acknowledged AND ({})",
+            id, kinds
+        );
+
+        let commands = self.search(&filter, "id:desc", Some(1)).await?;
+        Ok(commands.into_iter().next())
+    }
+
+    async fn complete(&self, command: Command) -> Result<(), AnyError> {
+        self.put(&command).await
+    }
+}
+
+/// An in-memory queue used to exercise the queue semantics in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryCommandStore {
+    commands: tokio::sync::RwLock<std::collections::BTreeMap<CommandId, Command>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl CommandStore for MemoryCommandStore {
+    async fn append(&self, command: Command) -> Result<CommandId, AnyError> {
+        let mut commands = self.commands.write().await;
+        let id = CommandId(commands.keys().next_back().map_or(1, |id| id.0 + 1));
+        commands.insert(id, Command { id, ..command });
+        Ok(id)
+    }
+
+    async fn pending(&self, id: SubscriptionId) -> Result<Vec<Command>, AnyError> {
+        use super::command::CommandStatus;
+
+        Ok(self
+            .commands
+            .read()
+            .await
+            .values()
+            .filter(|c| c.subscription_id == id && c.status == CommandStatus::Pending)
+            .cloned()
+            .collect())
+    }
+
+    async fn list(&self, id: SubscriptionId, limit: usize) -> Result<Vec<Command>, AnyError> {
+        Ok(self
+            .commands
+            .read()
+            .await
+            .values()
+            .rev()
+            .filter(|c| c.subscription_id == id)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn last_acknowledged(
+        &self,
+        id: SubscriptionId,
+        kinds: &[CommandKind],
+    ) -> Result<Option<Command>, AnyError> {
+        use super::command::CommandStatus;
+
+        Ok(self
+            .commands
+            .read()
+            .await
+            .values()
+            .rev()
+            .find(|c| {
+                c.subscription_id == id
+                    && c.status == CommandStatus::Acknowledged
+                    && kinds.contains(&c.kind)
+            })
+            .cloned())
+    }
+
+    async fn complete(&self, command: Command) -> Result<(), AnyError> {
+        self.commands.write().await.insert(command.id, command);
+        Ok(())
+    }
+}
+
+pub async fn init_command_store(config: &MeilisearchConfig) -> Arc<dyn CommandStore + Send + Sync> {
+    Arc::new(MSCommandStore::new(config.client(), id::generator()).await)
+}
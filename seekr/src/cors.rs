@@ -0,0 +1,236 @@
+use actix_cors::Cors;
+
+use crate::auth::reveal::REVEAL_TOKEN_HEADER;
+use crate::clusters::endpoints::v1::{METADATA_FETCHED_AT, METADATA_STALE, REGISTRATION_STARTED};
+use crate::{deadline, request_id};
+
+/// The methods of the JSON API. Event streams are opened with `GET` too.
+const ALLOWED_METHODS: [&str; 7] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+/// The request headers browsers may send besides the safelisted ones,
+/// `Last-Event-ID` being what an `EventSource` resumes a stream with.
+const ALLOWED_HEADERS: [&str; 8] = [
+    "Authorization",
+    "Content-Type",
+    "Accept",
+    "Cache-Control",
+    "Last-Event-ID",
+    request_id::HEADER,
+    deadline::HEADER,
+    REVEAL_TOKEN_HEADER,
+];
+
+/// The response headers scripts may read besides the safelisted ones.
+const EXPOSED_HEADERS: [&str; 9] = [
+    request_id::HEADER,
+    "Deprecation",
+    "Sunset",
+    "Link",
+    "Retry-After",
+    "Content-Disposition",
+    REGISTRATION_STARTED,
+    METADATA_FETCHED_AT,
+    METADATA_STALE,
+];
+
+/// How long browsers may cache a preflight, in seconds.
+const MAX_AGE: usize = 3600;
+
+/// Which origins browsers may call the API from, e.g. a UI served elsewhere.
+///
+/// Without origins, no CORS headers are sent and cross-origin calls from
+/// browsers fail as they always did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CorsConfig {
+    allowed_origins: Vec<String>,
+    allow_credentials: bool,
+}
+
+impl CorsConfig {
+    /// Origins are given as `scheme://host[:port]`, or `*` for any origin,
+    /// which can't be combined with credentials.
+    pub fn new(allowed_origins: &[String], allow_credentials: bool) -> Result<Self, String> {
+        let allowed_origins = allowed_origins
+            .iter()
+            .map(|o| o.trim().to_string())
+            .filter(|o| !o.is_empty())
+            .collect::<Vec<_>>();
+        for origin in allowed_origins.iter().filter(|o| *o != "*") {
+            let valid = origin.split_once("://").is_some_and(|(scheme, host)| {
+                matches!(scheme, "http" | "https") && !host.is_empty() && !host.contains('/')
+            });
+            if !valid {
+                return Err(format!(
+                    "CORS origin '{}' isn't a scheme://host[:port] origin",
+                    origin
+                ));
+            }
+        }
+        if allow_credentials && allowed_origins.iter().any(|o| o == "*") {
+            return Err("CORS credentials can't be allowed from any origin, list them".to_string());
+        }
+
+        Ok(Self {
+            allowed_origins,
+            allow_credentials,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty()
+    }
+
+    /// The middleware answering preflights and adding CORS headers to the
+    /// responses of allowed origins.
+    pub fn middleware(&self) -> Cors {
+        let mut cors = Cors::default()
+            .allowed_methods(ALLOWED_METHODS)
+            .allowed_headers(ALLOWED_HEADERS)
+            .expose_headers(EXPOSED_HEADERS)
+            .max_age(MAX_AGE);
+
+        if self.allowed_origins.iter().any(|o| o == "*") {
+            cors = cors.allow_any_origin().send_wildcard();
+        } else {
+            for origin in &self.allowed_origins {
+                cors = cors.allowed_origin(origin);
+            }
+        }
+        if self.allow_credentials {
+            cors = cors.supports_credentials();
+        }
+        cors
+    }
+}
+
+#[test]
+fn it_accepts_only_origins() {
+    let origins = |origins: &[&str]| origins.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+
+    let config = CorsConfig::new(&origins(&["https://ui.seekr.io", " ", ""]), true).unwrap();
+    assert!(config.is_enabled());
+    assert!(!CorsConfig::new(&[], false).unwrap().is_enabled());
+    assert!(CorsConfig::new(&origins(&["*"]), false).is_ok());
+
+    for invalid in [
+        "ui.seekr.io",
+        "ftp://ui.seekr.io",
+        "https://",
+        "https://ui.seekr.io/app",
+    ] {
+        assert!(
+            CorsConfig::new(&origins(&[invalid]), false).is_err(),
+            "{}",
+            invalid
+        );
+    }
+    assert!(CorsConfig::new(&origins(&["*"]), true).is_err());
+}
+
+#[cfg(test)]
+async fn preflight(
+    config: &CorsConfig,
+    origin: &str,
+    method: &str,
+) -> actix_web::dev::ServiceResponse {
+    use actix_web::http::header::{
+        ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+    };
+    use actix_web::{test, App};
+
+    let app = test::init_service(
+        App::new()
+            .wrap(config.middleware())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/v1/clusters")
+        .insert_header((ORIGIN, origin))
+        .insert_header((ACCESS_CONTROL_REQUEST_METHOD, method))
+        .insert_header((ACCESS_CONTROL_REQUEST_HEADERS, "authorization,content-type"));
+    test::call_service(&app, req.to_request()).await
+}
+
+#[actix_web::test]
+async fn it_answers_preflights_from_allowed_origins() {
+    use actix_web::http::header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+        ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+    };
+    use actix_web::http::StatusCode;
+
+    let config = CorsConfig::new(&["https://ui.seekr.io".to_string()], false).unwrap();
+    let res = preflight(&config, "https://ui.seekr.io", "DELETE").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let header = |name| res.headers().get(name).unwrap().to_str().unwrap();
+    assert_eq!(header(ACCESS_CONTROL_ALLOW_ORIGIN), "https://ui.seekr.io");
+    assert!(header(ACCESS_CONTROL_ALLOW_METHODS).contains("DELETE"));
+    let allowed = header(ACCESS_CONTROL_ALLOW_HEADERS).to_lowercase();
+    assert!(allowed.contains("authorization") && allowed.contains("content-type"));
+    assert!(res
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+        .is_none());
+
+    let config = CorsConfig::new(&["https://ui.seekr.io".to_string()], true).unwrap();
+    let res = preflight(&config, "https://ui.seekr.io", "GET").await;
+    let header = |name| res.headers().get(name).unwrap().to_str().unwrap();
+    assert_eq!(header(ACCESS_CONTROL_ALLOW_ORIGIN), "https://ui.seekr.io");
+    assert_eq!(header(ACCESS_CONTROL_ALLOW_CREDENTIALS), "true");
+}
+
+#[actix_web::test]
+async fn it_leaves_out_disallowed_origins() {
+    use actix_web::http::header::ACCESS_CONTROL_ALLOW_ORIGIN;
+
+    let config = CorsConfig::new(&["https://ui.seekr.io".to_string()], false).unwrap();
+    for origin in ["https://evil.example", "http://ui.seekr.io"] {
+        let res = preflight(&config, origin, "GET").await;
+        assert!(
+            res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none(),
+            "{}",
+            origin
+        );
+    }
+}
+
+#[actix_web::test]
+async fn it_allows_any_origin_with_a_wildcard() {
+    use actix_web::http::header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_EXPOSE_HEADERS, ORIGIN,
+    };
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    let config = CorsConfig::new(&["*".to_string()], false).unwrap();
+    let res = preflight(&config, "https://anywhere.example", "POST").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+    assert!(res
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+        .is_none());
+
+    // Responses to the calls themselves carry the headers too.
+    let app = test::init_service(
+        App::new()
+            .wrap(config.middleware())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/healthz")
+        .insert_header((ORIGIN, "https://anywhere.example"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+    let exposed = res.headers().get(ACCESS_CONTROL_EXPOSE_HEADERS).unwrap();
+    assert!(exposed
+        .to_str()
+        .unwrap()
+        .to_lowercase()
+        .contains("x-request-id"));
+}
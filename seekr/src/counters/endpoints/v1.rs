@@ -0,0 +1,98 @@
+use actix_web::web::{Data, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Principal;
+use crate::capabilities::{self, Capabilities, Prober};
+use crate::counters::{Counters, Counts};
+use crate::ids::ClusterId;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_summary);
+}
+
+/// The tallies are read as they are, never recounted, so the totals cost the
+/// same however many clusters and topics there are.
+#[get("/summary")]
+async fn get_summary(
+    query: Query<SummaryQuery>,
+    principal: Principal,
+    counters: Data<Counters>,
+    prober: Option<Data<Prober>>,
+) -> impl Responder {
+    let capabilities = match prober {
+        Some(prober) => {
+            let mut capabilities = capabilities::evaluate(&prober.probe().await);
+            for capability in capabilities.values_mut() {
+                capability
+                    .clusters
+                    .retain(|c| principal.can_access(c.cluster_id));
+            }
+            Some(capabilities)
+        }
+        None => None,
+    };
+
+    if !principal.is_scoped() && !query.by_cluster {
+        return HttpResponse::Ok().json(SummaryResponse {
+            clusters: counters.cluster_count(),
+            totals: counters.totals(),
+            by_cluster: None,
+            capabilities,
+        });
+    }
+
+    let mut clusters = counters
+        .clusters()
+        .into_iter()
+        .filter(|(id, _)| principal.can_access(*id))
+        .map(|(cluster_id, counts)| ClusterCounts { cluster_id, counts })
+        .collect::<Vec<_>>();
+    clusters.sort_by_key(|c| c.cluster_id);
+
+    // Keys limited to some clusters only see the totals of those.
+    let (cluster_count, totals) = match principal.is_scoped() {
+        true => (clusters.len(), sum(&clusters)),
+        false => (counters.cluster_count(), counters.totals()),
+    };
+
+    HttpResponse::Ok().json(SummaryResponse {
+        clusters: cluster_count,
+        totals,
+        by_cluster: query.by_cluster.then_some(clusters),
+        capabilities,
+    })
+}
+
+fn sum(clusters: &[ClusterCounts]) -> Counts {
+    clusters.iter().fold(Counts::default(), |total, c| Counts {
+        topics: total.topics + c.counts.topics,
+        partitions: total.partitions + c.counts.partitions,
+        subscriptions: total.subscriptions + c.counts.subscriptions,
+        documents: total.documents + c.counts.documents,
+    })
+}
+
+#[derive(Deserialize)]
+struct SummaryQuery {
+    #[serde(default)]
+    by_cluster: bool,
+}
+
+#[derive(Serialize)]
+struct SummaryResponse {
+    clusters: usize,
+    #[serde(flatten)]
+    totals: Counts,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    by_cluster: Option<Vec<ClusterCounts>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capabilities: Option<Capabilities>,
+}
+
+#[derive(Serialize)]
+struct ClusterCounts {
+    cluster_id: ClusterId,
+    #[serde(flatten)]
+    counts: Counts,
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+
+use crate::ids::ClusterId;
+use crate::kafka::metadata::ClusterMetadata;
+
+pub mod endpoints;
+pub mod reconcile;
+pub mod store;
+
+/// Total corrections of a reconciliation above which an update path is likely missed.
+pub const DEFAULT_DRIFT_THRESHOLD: i64 = 10;
+
+/// The facts tallied per cluster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Counter {
+    Topics,
+    Partitions,
+    Subscriptions,
+    Documents,
+}
+
+impl Counter {
+    pub const ALL: [Counter; 4] = [
+        Counter::Topics,
+        Counter::Partitions,
+        Counter::Subscriptions,
+        Counter::Documents,
+    ];
+}
+
+/// A point in time copy of the tallies of a cluster, or of every cluster.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub topics: i64,
+    pub partitions: i64,
+    pub subscriptions: i64,
+    pub documents: i64,
+}
+
+impl Counts {
+    pub fn get(&self, counter: Counter) -> i64 {
+        match counter {
+            Counter::Topics => self.topics,
+            Counter::Partitions => self.partitions,
+            Counter::Subscriptions => self.subscriptions,
+            Counter::Documents => self.documents,
+        }
+    }
+
+    /// The topic and partition counts of polled metadata.
+    pub fn metadata(metadata: &ClusterMetadata) -> Self {
+        Self {
+            topics: metadata.topics.len() as i64,
+            partitions: metadata
+                .topics
+                .iter()
+                .map(|t| t.partitions.len() as i64)
+                .sum(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A counter found off by `delta` by a reconciliation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Correction {
+    pub cluster_id: ClusterId,
+    pub counter: Counter,
+    pub delta: i64,
+}
+
+impl Correction {
+    fn new(cluster_id: ClusterId, counter: Counter, delta: i64) -> Option<Self> {
+        (delta != 0).then_some(Self {
+            cluster_id,
+            counter,
+            delta,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    topics: AtomicI64,
+    partitions: AtomicI64,
+    subscriptions: AtomicI64,
+    documents: AtomicI64,
+}
+
+impl Tally {
+    fn counter(&self, counter: Counter) -> &AtomicI64 {
+        match counter {
+            Counter::Topics => &self.topics,
+            Counter::Partitions => &self.partitions,
+            Counter::Subscriptions => &self.subscriptions,
+            Counter::Documents => &self.documents,
+        }
+    }
+
+    fn counts(&self) -> Counts {
+        Counts {
+            topics: self.topics.load(Ordering::Relaxed),
+            partitions: self.partitions.load(Ordering::Relaxed),
+            subscriptions: self.subscriptions.load(Ordering::Relaxed),
+            documents: self.documents.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Cluster-wide tallies, kept up to date where the facts they count change.
+///
+/// Metadata polls set the topics and partitions of a cluster, the stores add
+/// and remove clusters and subscriptions, and the reconciliation pass sets the
+/// documents and corrects whatever drifted. Every change to a cluster's tally
+/// is added to the totals as well, so reading them is constant time.
+#[derive(Default)]
+pub struct Counters {
+    clusters: RwLock<HashMap<ClusterId, Arc<Tally>>>,
+    totals: Tally,
+}
+
+impl Counters {
+    /// Start tallying a cluster.
+    pub fn add_cluster(&self, id: ClusterId) {
+        self.clusters.write().unwrap().entry(id).or_default();
+    }
+
+    /// Stop tallying a cluster, taking its counts off the totals.
+    pub fn remove_cluster(&self, id: ClusterId) {
+        let Some(tally) = self.clusters.write().unwrap().remove(&id) else {
+            return;
+        };
+
+        for counter in Counter::ALL {
+            let value = tally.counter(counter).swap(0, Ordering::Relaxed);
+            self.totals
+                .counter(counter)
+                .fetch_sub(value, Ordering::Relaxed);
+        }
+    }
+
+    /// Add to a counter of a tallied cluster.
+    pub fn add(&self, id: ClusterId, counter: Counter, delta: i64) {
+        let Some(tally) = self.tally(id) else {
+            return;
+        };
+
+        tally.counter(counter).fetch_add(delta, Ordering::Relaxed);
+        self.totals
+            .counter(counter)
+            .fetch_add(delta, Ordering::Relaxed);
+    }
+
+    /// Set a counter of a tallied cluster, returning by how much it changed.
+    pub fn set(&self, id: ClusterId, counter: Counter, value: i64) -> i64 {
+        let Some(tally) = self.tally(id) else {
+            return 0;
+        };
+
+        let delta = value - tally.counter(counter).swap(value, Ordering::Relaxed);
+        self.totals
+            .counter(counter)
+            .fetch_add(delta, Ordering::Relaxed);
+        delta
+    }
+
+    /// Set the topics and partitions of a cluster from its polled metadata.
+    pub fn set_metadata(&self, id: ClusterId, metadata: &ClusterMetadata) {
+        let counts = Counts::metadata(metadata);
+        self.set(id, Counter::Topics, counts.topics);
+        self.set(id, Counter::Partitions, counts.partitions);
+    }
+
+    /// Forget the topics and partitions of a cluster no longer polled.
+    pub fn clear_metadata(&self, id: ClusterId) {
+        self.set(id, Counter::Topics, 0);
+        self.set(id, Counter::Partitions, 0);
+    }
+
+    /// Make the tallies match the counts of `actual`, returning every correction.
+    ///
+    /// Updates racing the reconciliation may leave a little drift behind,
+    /// which the next one corrects.
+    pub fn reconcile(&self, actual: &HashMap<ClusterId, Counts>) -> Vec<Correction> {
+        let tallied = self
+            .clusters
+            .read()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut corrections = vec![];
+
+        for id in tallied.iter().filter(|id| !actual.contains_key(id)) {
+            let counts = self.cluster(*id).unwrap_or_default();
+            self.remove_cluster(*id);
+            corrections.extend(
+                Counter::ALL
+                    .into_iter()
+                    .filter_map(|counter| Correction::new(*id, counter, -counts.get(counter))),
+            );
+        }
+
+        let mut ids = actual.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            let counts = actual[&id];
+            self.add_cluster(id);
+            corrections.extend(Counter::ALL.into_iter().filter_map(|counter| {
+                Correction::new(id, counter, self.set(id, counter, counts.get(counter)))
+            }));
+        }
+
+        corrections
+    }
+
+    /// The number of tallied clusters.
+    pub fn cluster_count(&self) -> usize {
+        self.clusters.read().unwrap().len()
+    }
+
+    /// The counts of a cluster, `None` when it isn't tallied.
+    pub fn cluster(&self, id: ClusterId) -> Option<Counts> {
+        self.tally(id).map(|t| t.counts())
+    }
+
+    /// The counts of every tallied cluster.
+    pub fn clusters(&self) -> HashMap<ClusterId, Counts> {
+        let clusters = self.clusters.read().unwrap();
+        clusters.iter().map(|(id, t)| (*id, t.counts())).collect()
+    }
+
+    /// The counts summed over every cluster.
+    pub fn totals(&self) -> Counts {
+        self.totals.counts()
+    }
+
+    fn tally(&self, id: ClusterId) -> Option<Arc<Tally>> {
+        self.clusters.read().unwrap().get(&id).cloned()
+    }
+}
+
+#[test]
+fn it_applies_deltas_across_polls() {
+    use crate::history::diff::metadata;
+
+    let counters = Counters::default();
+    counters.add_cluster(ClusterId(1));
+    counters.add_cluster(ClusterId(2));
+
+    let orders = [("orders", 1), ("refunds", 2), ("payments", 3)];
+    counters.set_metadata(ClusterId(1), &metadata(&[1], &orders));
+    counters.set_metadata(ClusterId(2), &metadata(&[1], &[("events", 4)]));
+    assert_eq!(counters.totals().topics, 4);
+    assert_eq!(counters.totals().partitions, 1 + 2 + 3 + 4);
+
+    // A topic deleted, another created and one repartitioned between polls.
+    let polled = metadata(&[1], &[("refunds", 2), ("payments", 3), ("invoices", 5)]);
+    counters.set_metadata(ClusterId(1), &polled);
+    let cluster = counters.cluster(ClusterId(1)).unwrap();
+    assert_eq!(cluster, Counts::metadata(&polled));
+    assert_eq!(counters.totals().topics, 4);
+    assert_eq!(counters.totals().partitions, 2 + 3 + 5 + 4);
+
+    counters.add(ClusterId(1), Counter::Subscriptions, 2);
+    counters.add(ClusterId(2), Counter::Subscriptions, 1);
+    counters.add(ClusterId(1), Counter::Subscriptions, -1);
+    assert_eq!(counters.totals().subscriptions, 2);
+
+    // Clusters that aren't tallied are ignored.
+    counters.add(ClusterId(9), Counter::Subscriptions, 5);
+    assert_eq!(counters.set(ClusterId(9), Counter::Topics, 5), 0);
+    assert_eq!(counters.totals().subscriptions, 2);
+
+    counters.clear_metadata(ClusterId(2));
+    assert_eq!(counters.totals().topics, 3);
+    counters.remove_cluster(ClusterId(1));
+    assert_eq!(counters.cluster_count(), 1);
+    assert_eq!(
+        counters.totals(),
+        Counts {
+            subscriptions: 1,
+            ..Counts::default()
+        }
+    );
+}
+
+#[test]
+fn it_reads_totals_regardless_of_the_cluster_count() {
+    use std::time::{Duration, Instant};
+
+    use crate::history::diff::metadata;
+
+    // The fastest of a few rounds, to keep the comparison clear of scheduling noise.
+    let time = |counters: &Counters| {
+        (0..5)
+            .map(|_| {
+                let started = Instant::now();
+                for _ in 0..10_000 {
+                    std::hint::black_box(counters.totals());
+                    std::hint::black_box(counters.cluster_count());
+                }
+                started.elapsed()
+            })
+            .min()
+            .unwrap()
+    };
+    let counters = |clusters: i64| {
+        let counters = Counters::default();
+        let polled = metadata(&[1, 2, 3], &[("orders", 8), ("refunds", 8)]);
+        for id in 1..=clusters {
+            counters.add_cluster(ClusterId(id));
+            counters.set_metadata(ClusterId(id), &polled);
+        }
+        counters
+    };
+
+    let small = time(&counters(10));
+    let large = time(&counters(10_000));
+    assert!(
+        large < small * 4 + Duration::from_millis(5),
+        "{:?} vs {:?}",
+        large,
+        small
+    );
+}
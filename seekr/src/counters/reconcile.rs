@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataManager};
+use crate::page::Page;
+use crate::shards::store::DocumentStore;
+use crate::subscriptions::store::SubscriptionStore;
+use crate::sweeper::Job;
+
+use super::{Correction, Counter, Counters, Counts};
+
+/// How often the tallies are recounted the slow way.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Periodically recounts every tally from the stores and the metadata cache,
+/// correcting the drift left by missed updates.
+///
+/// The indexer runs in its own process, so the documents of each cluster are
+/// only counted here.
+pub struct Reconciler {
+    counters: Arc<Counters>,
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    documents: Arc<dyn DocumentStore + Send + Sync>,
+    manager: Arc<MetadataManager>,
+    threshold: i64,
+}
+
+impl Reconciler {
+    pub fn new(
+        counters: Arc<Counters>,
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+        documents: Arc<dyn DocumentStore + Send + Sync>,
+        manager: Arc<MetadataManager>,
+    ) -> Self {
+        Self {
+            counters,
+            clusters,
+            subscriptions,
+            documents,
+            manager,
+            threshold: super::DEFAULT_DRIFT_THRESHOLD,
+        }
+    }
+
+    /// Warn once a reconciliation corrects more than `threshold` in total.
+    pub fn with_threshold(mut self, threshold: i64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The sweeper job reconciling the tallies, counting the corrections.
+    pub fn job(self: Arc<Self>) -> Job {
+        Job::new("counter_reconciliation", RECONCILE_INTERVAL, move || {
+            let reconciler = self.clone();
+            async move { Ok::<_, AnyError>(reconciler.reconcile().await?.len()) }
+        })
+    }
+
+    /// Fill the tallies at startup, when there's no drift to speak of yet.
+    pub async fn load(&self) -> Result<(), AnyError> {
+        let actual = self.recount().await?;
+        self.counters.reconcile(&actual);
+        Ok(())
+    }
+
+    /// Correct the tallies, warning when the drift points at a missed update.
+    pub async fn reconcile(&self) -> Result<Vec<Correction>, AnyError> {
+        let actual = self.recount().await?;
+        let corrections = self.counters.reconcile(&actual);
+
+        let drift = drift(&corrections);
+        if drift > self.threshold {
+            warn!(
+                "Reconciliation corrected counters by {} in total, an update path may be missed: {:?}",
+                drift, corrections
+            );
+        }
+
+        Ok(corrections)
+    }
+
+    /// Every count of every cluster, the slow way.
+    async fn recount(&self) -> Result<HashMap<ClusterId, Counts>, AnyError> {
+        let mut counts = HashMap::new();
+
+        for c in self.clusters.list(None, Page::ALL).await? {
+            let metadata = match self.manager.clone().get(c.id, None).await?.as_deref() {
+                Some(CachedMetadataEntry::Meta(metadata)) => Counts::metadata(metadata),
+                _ => Counts::default(),
+            };
+            counts.insert(c.id, metadata);
+        }
+
+        for s in self.subscriptions.list(None, Page::ALL).await? {
+            let Some(counts) = counts.get_mut(&s.cluster_id) else {
+                continue;
+            };
+
+            counts.subscriptions += 1;
+            for shard in self.documents.shards(s.id).await? {
+                counts.documents += self.documents.count(&shard).await? as i64;
+            }
+        }
+
+        Ok(counts)
+    }
+}
+
+/// The total of the corrections to counters kept up to date incrementally.
+///
+/// Documents are only ever counted by reconciliation, so they don't drift.
+pub fn drift(corrections: &[Correction]) -> i64 {
+    corrections
+        .iter()
+        .filter(|c| c.counter != Counter::Documents)
+        .map(|c| c.delta.abs())
+        .sum()
+}
+
+#[tokio::test]
+async fn it_corrects_drift() {
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::ids::SubscriptionId;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::shards::shard::{IndexSettings, Shard};
+    use crate::shards::store::MemoryDocumentStore;
+    use crate::shards::PRIMARY_KEY;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::Subscription;
+
+    let counters = Arc::new(Counters::default());
+    let cs = Arc::new(MemoryClusterStore::default());
+    let ss = Arc::new(MemorySubscriptionStore::default());
+    let ds = Arc::new(MemoryDocumentStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Arc::new(MetadataManager::with_factory(cs.clone(), factory));
+
+    for id in [1, 2] {
+        let cluster = Cluster::new(
+            Some(ClusterId(id)),
+            Kind::Kafka,
+            id.to_string(),
+            HashMap::new(),
+        );
+        cs.put(cluster).await.unwrap();
+    }
+    for (id, cluster) in [(1, 1), (2, 1), (3, 2)] {
+        let subscription = Subscription::new(
+            Some(SubscriptionId(id)),
+            ClusterId(cluster),
+            "orders".to_string(),
+            HashMap::new(),
+        );
+        ss.put(subscription).await.unwrap();
+    }
+    let shard = Shard::route(SubscriptionId(1), None, 0);
+    ds.put_shard(&shard).await.unwrap();
+    ds.create_index(&shard, &IndexSettings::default())
+        .await
+        .unwrap();
+    let documents = (0..3)
+        .map(|i| serde_json::json!({ PRIMARY_KEY: i }))
+        .collect::<Vec<_>>();
+    ds.add_documents(&shard, &documents).await.unwrap();
+
+    let reconciler = Reconciler::new(counters.clone(), cs, ss, ds, manager).with_threshold(2);
+    reconciler.load().await.unwrap();
+    let expected = Counts {
+        subscriptions: 3,
+        documents: 3,
+        ..Counts::default()
+    };
+    assert_eq!(counters.totals(), expected);
+    assert!(reconciler.reconcile().await.unwrap().is_empty());
+
+    // Missed updates: a removed subscription never taken off, a cluster never added.
+    counters.add(ClusterId(1), Counter::Subscriptions, 4);
+    counters.remove_cluster(ClusterId(2));
+    assert_eq!(counters.totals().subscriptions, 6);
+
+    let corrections = reconciler.reconcile().await.unwrap();
+    assert_eq!(drift(&corrections), 5, "over the threshold, so it warns");
+    assert_eq!(
+        corrections,
+        vec![
+            Correction {
+                cluster_id: ClusterId(1),
+                counter: Counter::Subscriptions,
+                delta: -4
+            },
+            Correction {
+                cluster_id: ClusterId(2),
+                counter: Counter::Subscriptions,
+                delta: 1
+            },
+        ]
+    );
+    assert_eq!(counters.totals(), expected);
+    assert_eq!(counters.cluster_count(), 2);
+}
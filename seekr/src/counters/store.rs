@@ -0,0 +1,186 @@
+use std::result;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::clusters::cluster::Cluster;
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::page::Page;
+use crate::search::Scored;
+use crate::subscriptions::store::SubscriptionStore;
+use crate::subscriptions::subscription::Subscription;
+
+use super::{Counter, Counters};
+
+/// A cluster store tallying the clusters it inserts and removes.
+pub struct CountedClusterStore {
+    inner: Arc<dyn ClusterStore + Send + Sync>,
+    counters: Arc<Counters>,
+}
+
+impl CountedClusterStore {
+    pub fn new(inner: Arc<dyn ClusterStore + Send + Sync>, counters: Arc<Counters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+#[async_trait]
+impl ClusterStore for CountedClusterStore {
+    async fn list(
+        &self,
+        ids: Option<Vec<ClusterId>>,
+        page: Page,
+    ) -> Result<Vec<Cluster>, AnyError> {
+        self.inner.list(ids, page).await
+    }
+
+    async fn count(&self, ids: Option<Vec<ClusterId>>) -> Result<usize, AnyError> {
+        self.inner.count(ids).await
+    }
+
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Cluster>>, AnyError> {
+        self.inner.search(q, limit).await
+    }
+
+    async fn get(&self, id: ClusterId) -> result::Result<Option<Cluster>, AnyError> {
+        self.inner.get(id).await
+    }
+
+    async fn get_by_name(&self, name: &str) -> result::Result<Option<Cluster>, AnyError> {
+        self.inner.get_by_name(name).await
+    }
+
+    async fn insert(&self, cluster: Cluster) -> result::Result<ClusterId, AnyError> {
+        let id = self.inner.insert(cluster).await?;
+        self.counters.add_cluster(id);
+        Ok(id)
+    }
+
+    async fn update(&self, cluster: Cluster) -> result::Result<ClusterId, AnyError> {
+        self.inner.update(cluster).await
+    }
+
+    async fn remove(&self, id: ClusterId) -> result::Result<ClusterId, AnyError> {
+        let id = self.inner.remove(id).await?;
+        self.counters.remove_cluster(id);
+        Ok(id)
+    }
+
+    async fn put(&self, cluster: Cluster) -> result::Result<ClusterId, AnyError> {
+        let stored = self.inner.get(cluster.id).await?.is_some();
+        let id = self.inner.put(cluster).await?;
+        if !stored {
+            self.counters.add_cluster(id);
+        }
+        Ok(id)
+    }
+
+    async fn ping(&self) -> Result<(), AnyError> {
+        self.inner.ping().await
+    }
+}
+
+/// A subscription store tallying the subscriptions of each cluster it inserts and removes.
+pub struct CountedSubscriptionStore {
+    inner: Arc<dyn SubscriptionStore + Send + Sync>,
+    counters: Arc<Counters>,
+}
+
+impl CountedSubscriptionStore {
+    pub fn new(inner: Arc<dyn SubscriptionStore + Send + Sync>, counters: Arc<Counters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+#[async_trait]
+impl SubscriptionStore for CountedSubscriptionStore {
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: Page,
+    ) -> Result<Vec<Subscription>, AnyError> {
+        self.inner.list(cluster_id, page).await
+    }
+
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError> {
+        self.inner.count(cluster_id).await
+    }
+
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Subscription>>, AnyError> {
+        self.inner.search(q, limit).await
+    }
+
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<Option<Subscription>, AnyError> {
+        self.inner.get(cluster_id, id).await
+    }
+
+    async fn insert(&self, subscription: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        let cluster_id = subscription.cluster_id;
+        let id = self.inner.insert(subscription).await?;
+        self.counters.add(cluster_id, Counter::Subscriptions, 1);
+        Ok(id)
+    }
+
+    async fn update(&self, subscription: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        self.inner.update(subscription).await
+    }
+
+    async fn remove(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<SubscriptionId, AnyError> {
+        let id = self.inner.remove(cluster_id, id).await?;
+        self.counters.add(cluster_id, Counter::Subscriptions, -1);
+        Ok(id)
+    }
+
+    async fn put(&self, subscription: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        let cluster_id = subscription.cluster_id;
+        let stored = self.inner.get(cluster_id, subscription.id).await?.is_some();
+        let id = self.inner.put(subscription).await?;
+        if !stored {
+            self.counters.add(cluster_id, Counter::Subscriptions, 1);
+        }
+        Ok(id)
+    }
+}
+
+#[tokio::test]
+async fn it_tallies_inserts_and_removes() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let counters = Arc::new(Counters::default());
+    let cs = CountedClusterStore::new(Arc::new(MemoryClusterStore::default()), counters.clone());
+    let ss = CountedSubscriptionStore::new(
+        Arc::new(MemorySubscriptionStore::default()),
+        counters.clone(),
+    );
+
+    let cluster = Cluster::new(None, Kind::Kafka, "local".to_string(), HashMap::new());
+    let a = cs.insert(cluster.clone()).await.unwrap();
+    let b = cs.insert(cluster).await.unwrap();
+    for (cluster_id, topic) in [(a, "orders"), (a, "refunds"), (b, "orders")] {
+        let subscription = Subscription::new(None, cluster_id, topic.to_string(), HashMap::new());
+        ss.insert(subscription).await.unwrap();
+    }
+    assert_eq!(counters.cluster_count(), 2);
+    assert_eq!(counters.cluster(a).unwrap().subscriptions, 2);
+    assert_eq!(counters.totals().subscriptions, 3);
+
+    ss.remove(a, SubscriptionId(1)).await.unwrap();
+    assert_eq!(counters.cluster(a).unwrap().subscriptions, 1);
+    cs.remove(b).await.unwrap();
+    assert_eq!(counters.cluster_count(), 1);
+    assert_eq!(counters.totals().subscriptions, 1);
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::future::{ready, Future, Ready};
+use std::time::Duration;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use tokio::time::Instant;
+
+use crate::api::error;
+
+/// The header callers send the milliseconds they are willing to wait in.
+pub const HEADER: &str = "X-Request-Deadline-Ms";
+
+/// The budget of routes without one of their own.
+pub const DEFAULT_BUDGET: Duration = Duration::from_secs(30);
+
+/// Callers can't ask for more than this, however patient.
+const MAX_BUDGET: Duration = Duration::from_secs(5 * 60);
+
+/// The budget of routes reaching Kafka on demand, by the last segment of their path.
+const ROUTE_BUDGETS: [(&str, Duration); 2] = [
+    ("lookup", Duration::from_secs(15)),
+    ("messages", Duration::from_secs(10)),
+];
+
+/// The instant by which a request must be answered.
+///
+/// Every store and Kafka call a request makes runs within what remains of
+/// it, so a slow dependency fails the request with the stage that used the
+/// budget up rather than holding it past the caller's patience. Background
+/// work, i.e. polls and the indexer, runs without one.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    at: Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    pub fn after(budget: Duration) -> Self {
+        Self {
+            at: Instant::now() + budget,
+            budget,
+        }
+    }
+
+    /// The deadline of a request without a header, by its path.
+    pub fn of_route(path: &str) -> Self {
+        let last = path.trim_end_matches('/').rsplit('/').next();
+        let budget = ROUTE_BUDGETS
+            .iter()
+            .find(|(route, _)| Some(*route) == last)
+            .map_or(DEFAULT_BUDGET, |(_, budget)| *budget);
+        Self::after(budget)
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// The timeout of a call, cut short to what remains of the budget.
+    pub fn clamp(&self, timeout: Duration) -> Duration {
+        timeout.min(self.remaining())
+    }
+
+    /// Fail with the stage when nothing remains of the budget.
+    pub fn check(&self, stage: &'static str) -> Result<(), DeadlineExceeded> {
+        match self.is_expired() {
+            true => Err(self.exceeded(stage)),
+            false => Ok(()),
+        }
+    }
+
+    /// Run a stage of the request, abandoning it once the deadline passes.
+    pub async fn run<T>(
+        &self,
+        stage: &'static str,
+        f: impl Future<Output = T>,
+    ) -> Result<T, DeadlineExceeded> {
+        tokio::time::timeout_at(self.at, f)
+            .await
+            .map_err(|_| self.exceeded(stage))
+    }
+
+    fn exceeded(&self, stage: &'static str) -> DeadlineExceeded {
+        DeadlineExceeded {
+            stage,
+            budget: self.budget,
+        }
+    }
+}
+
+/// The deadline attached by the middleware, or the route's default.
+impl FromRequest for Deadline {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let deadline = req.extensions().get::<Deadline>().copied();
+        let deadline = deadline.unwrap_or_else(|| Deadline::of_route(req.path()));
+        ready(Ok(deadline))
+    }
+}
+
+/// A request ran out of its budget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeadlineExceeded {
+    /// The stage running when the deadline passed, e.g. `kafka_position`.
+    pub stage: &'static str,
+    pub budget: Duration,
+}
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The request's deadline of {}ms passed during {}",
+            self.budget.as_millis(),
+            self.stage
+        )
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+/// Attach the request's deadline, read from its header or its route's budget.
+pub async fn propagate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let header = req.headers().get(HEADER).map(|v| {
+        v.to_str()
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|ms| *ms > 0)
+    });
+
+    let deadline = match header {
+        Some(Some(ms)) => Deadline::after(Duration::from_millis(ms).min(MAX_BUDGET)),
+        Some(None) => {
+            let message = format!("{} must be a positive number of milliseconds", HEADER);
+            let res = error::invalid(message);
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+        None => Deadline::of_route(req.path()),
+    };
+
+    req.extensions_mut().insert(deadline);
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_clamps_timeouts_to_the_remaining_budget() {
+    let deadline = Deadline::after(Duration::from_millis(2_000));
+    assert_eq!(
+        deadline.clamp(Duration::from_millis(500)),
+        Duration::from_millis(500)
+    );
+    assert_eq!(
+        deadline.clamp(Duration::from_secs(5)),
+        Duration::from_millis(2_000)
+    );
+
+    tokio::time::advance(Duration::from_millis(1_500)).await;
+    assert_eq!(
+        deadline.clamp(Duration::from_secs(5)),
+        Duration::from_millis(500)
+    );
+    assert!(deadline.check("clusters").is_ok());
+
+    tokio::time::advance(Duration::from_millis(600)).await;
+    assert_eq!(deadline.clamp(Duration::from_secs(5)), Duration::ZERO);
+    assert_eq!(
+        deadline.check("kafka_fetch").unwrap_err().stage,
+        "kafka_fetch"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_derives_budgets_from_routes() {
+    let budget = |path| Deadline::of_route(path).remaining();
+    assert_eq!(
+        budget("/api/v1/subscriptions/1/2/lookup"),
+        Duration::from_secs(15)
+    );
+    assert_eq!(
+        budget("/api/v1/clusters/1/topics/orders/messages"),
+        Duration::from_secs(10)
+    );
+    assert_eq!(budget("/api/v1/clusters/1"), DEFAULT_BUDGET);
+}
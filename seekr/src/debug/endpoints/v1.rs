@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::{Data, Json, Path, Query, ServiceConfig};
+use actix_web::{get, post, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::debug::store::DebugStore;
+use crate::debug::trace::TraceEvent;
+use crate::debug::DebugSession;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::logger::Level;
+use crate::subscriptions::store::SubscriptionStore;
+
+/// Maximum duration of a debug session.
+const MAX_DURATION_MS: u64 = 60 * 60 * 1_000;
+
+/// Default number of trace events returned.
+const DEFAULT_TRACE_LIMIT: usize = 200;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(start_debug)
+        .service(get_debug)
+        .service(get_trace)
+        .service(get_stages);
+}
+
+#[post("/{cluster_id}/{id}/debug")]
+async fn start_debug(
+    path: Path<(ClusterId, SubscriptionId)>,
+    r: Json<StartDebugRequest>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    ds: Data<Arc<dyn DebugStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Starting {:?} debug session for subscription from cluster id {} with id {}",
+        r.level, cluster_id, id
+    );
+
+    if r.duration_ms == 0 || r.duration_ms > MAX_DURATION_MS {
+        return HttpResponse::BadRequest().body(format!(
+            "duration_ms must be between 1 and {}",
+            MAX_DURATION_MS
+        ));
+    }
+
+    match ss.get(cluster_id, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let session = DebugSession::new(id, r.level.clone(), Duration::from_millis(r.duration_ms));
+
+    match ds.set_session(session.clone()).await {
+        Ok(_) => HttpResponse::Ok().json(DebugResponse { session }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{cluster_id}/{id}/debug")]
+async fn get_debug(
+    path: Path<(ClusterId, SubscriptionId)>,
+    ds: Data<Arc<dyn DebugStore + Send + Sync>>,
+) -> impl Responder {
+    let (_cluster_id, id) = path.into_inner();
+
+    match ds.session(id).await {
+        Ok(Some(session)) if session.is_active(Utc::now()) => {
+            HttpResponse::Ok().json(DebugResponse { session })
+        }
+        Ok(_) => HttpResponse::NotFound()
+            .body(format!("No active debug session for subscription '{}'", id)),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{cluster_id}/{id}/debug/trace")]
+async fn get_trace(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<TraceQuery>,
+    ds: Data<Arc<dyn DebugStore + Send + Sync>>,
+) -> impl Responder {
+    let (_cluster_id, id) = path.into_inner();
+
+    match ds.session(id).await {
+        Ok(Some(session)) if session.is_active(Utc::now()) => {}
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .body(format!("No active debug session for subscription '{}'", id))
+        }
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_TRACE_LIMIT);
+
+    match ds.trace(id, limit).await {
+        Ok(events) => HttpResponse::Ok().json(TraceResponse { events }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{cluster_id}/{id}/stages")]
+async fn get_stages(
+    path: Path<(ClusterId, SubscriptionId)>,
+    ds: Data<Arc<dyn DebugStore + Send + Sync>>,
+) -> impl Responder {
+    let (_cluster_id, id) = path.into_inner();
+
+    match ds.stages(id).await {
+        Ok(Some(report)) => HttpResponse::Ok().json(report),
+        Ok(None) => HttpResponse::NotFound().body(format!(
+            "No stage timings published for subscription '{}'",
+            id
+        )),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct StartDebugRequest {
+    level: Level,
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct DebugResponse {
+    session: DebugSession,
+}
+
+#[derive(Deserialize)]
+struct TraceQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct TraceResponse {
+    events: Vec<TraceEvent>,
+}
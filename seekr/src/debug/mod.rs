@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::SubscriptionId;
+use crate::logger::{self, Level};
+
+use self::trace::Tracer;
+
+pub mod endpoints;
+pub mod store;
+pub mod trace;
+
+/// The log target of the streams worker for a subscription.
+pub fn log_target(id: SubscriptionId) -> String {
+    format!("seekr::worker::{}", id)
+}
+
+/// A time-limited request to trace a single subscription's pipeline verbosely.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DebugSession {
+    /// The subscription being debugged.
+    pub subscription_id: SubscriptionId,
+
+    /// The log level of the subscription's worker while the session is active.
+    pub level: Level,
+
+    /// Point in time in UTC, when the session was requested.
+    pub started_at: DateTime<Utc>,
+
+    /// Point in time in UTC, when the session expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DebugSession {
+    pub fn new(subscription_id: SubscriptionId, level: Level, duration: Duration) -> Self {
+        let started_at = Utc::now();
+        let expires_at = started_at + chrono::Duration::milliseconds(duration.as_millis() as i64);
+
+        Self {
+            subscription_id,
+            level,
+            started_at,
+            expires_at,
+        }
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// Applies debug sessions to a single streams worker.
+///
+/// Sessions requested before the worker started are ignored, so an elevated
+/// state never survives an indexer restart.
+pub struct DebugControl {
+    target: String,
+    since: DateTime<Utc>,
+    tracer: Arc<Tracer>,
+    active: Option<DebugSession>,
+}
+
+impl DebugControl {
+    pub fn new(id: SubscriptionId, tracer: Arc<Tracer>) -> Self {
+        Self {
+            target: log_target(id),
+            since: Utc::now(),
+            tracer,
+            active: None,
+        }
+    }
+
+    pub fn active(&self) -> Option<&DebugSession> {
+        self.active.as_ref()
+    }
+
+    /// Apply the latest session, returning `true` if the elevated state changed.
+    pub fn apply(&mut self, session: Option<DebugSession>, now: DateTime<Utc>) -> bool {
+        let session = session.filter(|s| s.started_at >= self.since && s.is_active(now));
+        if session == self.active {
+            return false;
+        }
+
+        match &session {
+            Some(s) => {
+                logger::elevate(&self.target, &s.level);
+                self.tracer.enable();
+            }
+            None => {
+                logger::restore(&self.target);
+                self.tracer.disable();
+            }
+        }
+
+        self.active = session;
+        true
+    }
+}
+
+impl Drop for DebugControl {
+    fn drop(&mut self) {
+        if self.active.is_some() {
+            logger::restore(&self.target);
+        }
+    }
+}
+
+#[test]
+fn it_expires_sessions_automatically() {
+    let id = SubscriptionId(9_431);
+    let tracer = Arc::new(Tracer::new(10));
+    let mut control = DebugControl::new(id, tracer.clone());
+
+    let session = DebugSession::new(id, Level::Trace, Duration::from_secs(600));
+    let now = session.started_at;
+
+    assert!(control.apply(Some(session.clone()), now));
+    assert!(tracer.is_enabled());
+    assert_eq!(
+        logger::override_for(&log_target(id)),
+        Some(log::LevelFilter::Trace)
+    );
+
+    // Re-reading the same session is a noop.
+    assert!(!control.apply(Some(session.clone()), now));
+
+    let later = now + chrono::Duration::minutes(11);
+    assert!(control.apply(Some(session), later));
+    assert!(control.active().is_none());
+    assert!(!tracer.is_enabled());
+    assert_eq!(logger::override_for(&log_target(id)), None);
+}
+
+#[test]
+fn it_ignores_sessions_from_before_a_restart() {
+    let id = SubscriptionId(9_432);
+    let mut session = DebugSession::new(id, Level::Debug, Duration::from_secs(600));
+    session.started_at -= chrono::Duration::seconds(1);
+
+    let mut control = DebugControl::new(id, Arc::new(Tracer::new(10)));
+    assert!(!control.apply(Some(session), Utc::now()));
+    assert!(control.active().is_none());
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::kafka::streams::stages::StageReport;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+use super::trace::TraceEvent;
+use super::DebugSession;
+
+#[async_trait]
+pub trait DebugStore {
+    /// Request a debug session, replacing any previous session of the subscription.
+    async fn set_session(&self, session: DebugSession) -> Result<(), AnyError>;
+
+    /// The most recently requested session of a subscription.
+    async fn session(&self, id: SubscriptionId) -> Result<Option<DebugSession>, AnyError>;
+
+    /// Replace the published trace of a subscription.
+    async fn put_trace(&self, id: SubscriptionId, events: Vec<TraceEvent>) -> Result<(), AnyError>;
+
+    /// The most recent `limit` events of a subscription's published trace, oldest first.
+    async fn trace(&self, id: SubscriptionId, limit: usize) -> Result<Vec<TraceEvent>, AnyError>;
+
+    /// Replace the published stage timings of a subscription.
+    async fn put_stages(&self, id: SubscriptionId, report: StageReport) -> Result<(), AnyError>;
+
+    /// The stage timings a subscription's worker published last.
+    async fn stages(&self, id: SubscriptionId) -> Result<Option<StageReport>, AnyError>;
+}
+
+pub const SESSIONS_INDEX_NAME: &str = "debug_sessions";
+pub const TRACES_INDEX_NAME: &str = "debug_traces";
+pub const STAGES_INDEX_NAME: &str = "debug_stages";
+
+/// The trace a worker published for its subscription.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TraceSnapshot {
+    subscription_id: SubscriptionId,
+    events: Vec<TraceEvent>,
+}
+
+/// The stage timings a worker published for its subscription.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StagesSnapshot {
+    subscription_id: SubscriptionId,
+    #[serde(flatten)]
+    report: StageReport,
+}
+
+pub struct MSDebugStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+}
+
+impl MSDebugStore {
+    pub async fn new(client: Arc<Client>) -> Self {
+        for name in [SESSIONS_INDEX_NAME, TRACES_INDEX_NAME, STAGES_INDEX_NAME] {
+            if let Ok(task) = client
+                .clone()
+                .create_index(name, Some("subscription_id"))
+                .await
+            {
+                task.wait_for_completion(&client, None, None).await.unwrap();
+            }
+        }
+
+        Self { client }
+    }
+
+    async fn get<T: DeserializeOwned + 'static>(
+        &self,
+        index: Index,
+        id: SubscriptionId,
+    ) -> Result<Option<T>, AnyError> {
+        let doc = index.get_document::<T>(&id.to_string()).await;
+
+        meilisearch::found(doc)
+    }
+
+    async fn put<T: Serialize + Send + Sync>(&self, index: Index, doc: T) -> Result<(), AnyError> {
+        index
+            .add_or_replace(&[doc], Some("subscription_id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DebugStore for MSDebugStore {
+    async fn set_session(&self, session: DebugSession) -> Result<(), AnyError> {
+        self.put(self.client.index(SESSIONS_INDEX_NAME), session)
+            .await
+    }
+
+    async fn session(&self, id: SubscriptionId) -> Result<Option<DebugSession>, AnyError> {
+        self.get(self.client.index(SESSIONS_INDEX_NAME), id).await
+    }
+
+    async fn put_trace(&self, id: SubscriptionId, events: Vec<TraceEvent>) -> Result<(), AnyError> {
+        let snapshot = TraceSnapshot {
+            subscription_id: id,
+            events,
+        };
+        self.put(self.client.index(TRACES_INDEX_NAME), snapshot)
+            .await
+    }
+
+    async fn trace(&self, id: SubscriptionId, limit: usize) -> Result<Vec<TraceEvent>, AnyError> {
+        let snapshot = self
+            .get::<TraceSnapshot>(self.client.index(TRACES_INDEX_NAME), id)
+            .await?;
+
+        let mut events = snapshot.map(|s| s.events).unwrap_or_default();
+        events.drain(..events.len().saturating_sub(limit));
+        Ok(events)
+    }
+
+    async fn put_stages(&self, id: SubscriptionId, report: StageReport) -> Result<(), AnyError> {
+        let snapshot = StagesSnapshot {
+            subscription_id: id,
+            report,
+        };
+        self.put(self.client.index(STAGES_INDEX_NAME), snapshot)
+            .await
+    }
+
+    async fn stages(&self, id: SubscriptionId) -> Result<Option<StageReport>, AnyError> {
+        let snapshot = self
+            .get::<StagesSnapshot>(self.client.index(STAGES_INDEX_NAME), id)
+            .await?;
+        Ok(snapshot.map(|s| s.report))
+    }
+}
+
+/// An in-memory store used to exercise debug sessions in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryDebugStore {
+    sessions: tokio::sync::RwLock<std::collections::HashMap<SubscriptionId, DebugSession>>,
+    traces: tokio::sync::RwLock<std::collections::HashMap<SubscriptionId, Vec<TraceEvent>>>,
+    stages: tokio::sync::RwLock<std::collections::HashMap<SubscriptionId, StageReport>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl DebugStore for MemoryDebugStore {
+    async fn set_session(&self, session: DebugSession) -> Result<(), AnyError> {
+        self.sessions
+            .write()
+            .await
+            .insert(session.subscription_id, session);
+        Ok(())
+    }
+
+    async fn session(&self, id: SubscriptionId) -> Result<Option<DebugSession>, AnyError> {
+        Ok(self.sessions.read().await.get(&id).cloned())
+    }
+
+    async fn put_trace(&self, id: SubscriptionId, events: Vec<TraceEvent>) -> Result<(), AnyError> {
+        self.traces.write().await.insert(id, events);
+        Ok(())
+    }
+
+    async fn trace(&self, id: SubscriptionId, limit: usize) -> Result<Vec<TraceEvent>, AnyError> {
+        let mut events = self
+            .traces
+            .read()
+            .await
+            .get(&id)
+            .cloned()
+            .unwrap_or_default();
+        events.drain(..events.len().saturating_sub(limit));
+        Ok(events)
+    }
+
+    async fn put_stages(&self, id: SubscriptionId, report: StageReport) -> Result<(), AnyError> {
+        self.stages.write().await.insert(id, report);
+        Ok(())
+    }
+
+    async fn stages(&self, id: SubscriptionId) -> Result<Option<StageReport>, AnyError> {
+        Ok(self.stages.read().await.get(&id).cloned())
+    }
+}
+
+pub async fn init_debug_store(config: &MeilisearchConfig) -> Arc<dyn DebugStore + Send + Sync> {
+    Arc::new(MSDebugStore::new(config.client()).await)
+}
@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Default number of events kept by a `Tracer`.
+pub const DEFAULT_CAPACITY: usize = 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Consume,
+    Decode,
+    Filter,
+    Transform,
+    Sink,
+    Commit,
+}
+
+impl Stage {
+    /// Every stage, in pipeline order.
+    pub const ALL: [Stage; 6] = [
+        Stage::Consume,
+        Stage::Decode,
+        Stage::Filter,
+        Stage::Transform,
+        Stage::Sink,
+        Stage::Commit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Consume => "consume",
+            Stage::Decode => "decode",
+            Stage::Filter => "filter",
+            Stage::Transform => "transform",
+            Stage::Sink => "sink",
+            Stage::Commit => "commit",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    Empty,
+    Failed,
+}
+
+/// A single step taken by a streams worker while processing a message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    /// Point in time in UTC, when the stage completed.
+    pub at: DateTime<Utc>,
+
+    /// The pipeline stage the event describes.
+    pub stage: Stage,
+
+    /// Time spent in the stage, in microseconds.
+    pub elapsed_us: u64,
+
+    /// The result of the stage.
+    pub outcome: Outcome,
+
+    /// Additional context, such as the message coordinates or the error.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub detail: Option<String>,
+}
+
+impl TraceEvent {
+    pub fn new(stage: Stage, elapsed: Duration, outcome: Outcome, detail: Option<String>) -> Self {
+        Self {
+            at: Utc::now(),
+            stage,
+            elapsed_us: elapsed.as_micros() as u64,
+            outcome,
+            detail,
+        }
+    }
+}
+
+/// A bounded recorder of pipeline events.
+///
+/// Recording is a single atomic load while the tracer is disabled, so it can
+/// stay wired into the hot path of every worker.
+#[derive(Debug)]
+pub struct Tracer {
+    enabled: AtomicBool,
+    capacity: usize,
+    events: Mutex<VecDeque<TraceEvent>>,
+}
+
+impl Tracer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop recording and discard the recorded events.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+        self.events.lock().unwrap().clear();
+    }
+
+    /// Record the event built by `f`, evicting the oldest event when full.
+    pub fn record(&self, f: impl FnOnce() -> TraceEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(f());
+    }
+
+    /// The recorded events, oldest first.
+    pub fn snapshot(&self) -> Vec<TraceEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+fn event(offset: i64) -> TraceEvent {
+    TraceEvent::new(
+        Stage::Consume,
+        Duration::from_micros(5),
+        Outcome::Ok,
+        Some(offset.to_string()),
+    )
+}
+
+#[test]
+fn it_bounds_the_recorded_events() {
+    let tracer = Tracer::new(3);
+    tracer.enable();
+
+    for offset in 0..5 {
+        tracer.record(|| event(offset));
+    }
+
+    let details = tracer
+        .snapshot()
+        .into_iter()
+        .map(|e| e.detail.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(details, vec!["2", "3", "4"]);
+}
+
+#[test]
+fn it_records_nothing_while_disabled() {
+    let tracer = Tracer::new(3);
+    tracer.record(|| unreachable!("events are not built while disabled"));
+    assert!(tracer.snapshot().is_empty());
+
+    tracer.enable();
+    tracer.record(|| event(1));
+    tracer.disable();
+    assert!(tracer.snapshot().is_empty());
+}
@@ -0,0 +1,36 @@
+use crate::backend::StoreConfig;
+use crate::clusters::store::init_cluster_store;
+use crate::collisions::CollisionScanner;
+use crate::logger;
+use crate::mirrors::store::init_mirror_pair_store;
+use crate::subscriptions::store::init_subscription_store;
+
+/// Check the stores for signs of trouble, printing what was found.
+///
+/// Fails when anything needs a closer look, so it can gate deployments.
+pub async fn run(stores: &StoreConfig) -> std::io::Result<()> {
+    logger::init(&logger::Level::Warn, logger::Format::Text, None);
+
+    let scanner = CollisionScanner::new(
+        init_cluster_store(stores)
+            .await
+            .map_err(std::io::Error::other)?,
+        init_subscription_store(stores)
+            .await
+            .map_err(std::io::Error::other)?,
+        init_mirror_pair_store(&stores.meilisearch).await,
+    );
+    let report = scanner.scan().await.map_err(std::io::Error::other)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?
+    );
+
+    match report.suspects.len() {
+        0 => Ok(()),
+        n => Err(std::io::Error::other(format!(
+            "{} entities may have had their ids collide, review them before writing to the stores",
+            n
+        ))),
+    }
+}
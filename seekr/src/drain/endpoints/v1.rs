@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::auth::Principal;
+use crate::drain::{ConnectionKind, Drain, ShutdownPhase};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_connections);
+}
+
+#[get("/connections")]
+async fn get_connections(principal: Principal, drain: Data<Drain>) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    HttpResponse::Ok().json(ConnectionsResponse {
+        phase: drain.phase(),
+        connections: drain.connections(),
+    })
+}
+
+#[derive(Serialize)]
+struct ConnectionsResponse {
+    phase: ShutdownPhase,
+    connections: HashMap<ConnectionKind, usize>,
+}
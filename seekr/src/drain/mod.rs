@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::{watch, Notify};
+
+pub mod endpoints;
+
+/// Default time long-lived connections get to close after draining starts.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownPhase {
+    Running,
+
+    /// Long-lived connections are asked to finish, new short requests are still served.
+    Draining,
+
+    /// The grace period elapsed, remaining long-lived connections are closed.
+    Closed,
+}
+
+/// The kinds of connections that outlive a single request/response exchange.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionKind {
+    /// A server-sent events stream.
+    Sse,
+
+    /// A request held open until data arrives or its wait expires.
+    LongPoll,
+}
+
+type Live = Arc<Mutex<HashMap<ConnectionKind, usize>>>;
+
+/// Tracks long-lived connections so shutdown doesn't wait for their full timeout.
+///
+/// `HttpServer` stops gracefully by waiting for in-flight requests, which a
+/// stream or long-poll holds open until its own timeout. On shutdown every
+/// connection is told to finish, and the ones still open after the grace
+/// period are closed, while short requests complete as usual.
+pub struct Drain {
+    phase: watch::Sender<ShutdownPhase>,
+    live: Live,
+    idle: Arc<Notify>,
+    grace: Duration,
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        Self::new(DEFAULT_GRACE_PERIOD)
+    }
+}
+
+impl Drain {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            phase: watch::Sender::new(ShutdownPhase::Running),
+            live: Default::default(),
+            idle: Arc::new(Notify::new()),
+            grace,
+        }
+    }
+
+    pub fn phase(&self) -> ShutdownPhase {
+        *self.phase.borrow()
+    }
+
+    /// The number of live connections of each kind.
+    pub fn connections(&self) -> HashMap<ConnectionKind, usize> {
+        self.live.lock().unwrap().clone()
+    }
+
+    fn total(&self) -> usize {
+        self.live.lock().unwrap().values().sum()
+    }
+
+    /// Track a long-lived connection until the returned guard is dropped.
+    pub fn connect(&self, kind: ConnectionKind) -> Connection {
+        *self.live.lock().unwrap().entry(kind).or_default() += 1;
+
+        Connection {
+            kind,
+            phase: self.phase.subscribe(),
+            live: self.live.clone(),
+            idle: self.idle.clone(),
+        }
+    }
+
+    /// Ask every long-lived connection to finish, and close those still open
+    /// after the grace period. Returns whether any had to be closed.
+    pub async fn shutdown(&self) -> bool {
+        info!("Draining {} long-lived connections...", self.total());
+        self.phase.send_replace(ShutdownPhase::Draining);
+
+        let idle = async {
+            loop {
+                let notified = self.idle.notified();
+                if self.total() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+
+        if tokio::time::timeout(self.grace, idle).await.is_ok() {
+            return false;
+        }
+
+        warn!(
+            "Closing {} long-lived connections still open after the {:?} grace period",
+            self.total(),
+            self.grace
+        );
+        self.phase.send_replace(ShutdownPhase::Closed);
+        true
+    }
+}
+
+/// A live long-lived connection, counted until dropped.
+pub struct Connection {
+    kind: ConnectionKind,
+    phase: watch::Receiver<ShutdownPhase>,
+    live: Live,
+    idle: Arc<Notify>,
+}
+
+impl Connection {
+    /// Resolves once draining started, immediately when it already has.
+    pub async fn draining(&mut self) {
+        let _ = self.phase.wait_for(|p| *p != ShutdownPhase::Running).await;
+    }
+
+    /// Resolves once the connection must be closed.
+    pub async fn closed(&mut self) {
+        let _ = self.phase.wait_for(|p| *p == ShutdownPhase::Closed).await;
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut live = self.live.lock().unwrap();
+        if let Some(count) = live.get_mut(&self.kind) {
+            *count = count.saturating_sub(1);
+        }
+
+        if live.values().sum::<usize>() == 0 {
+            self.idle.notify_waiters();
+        }
+    }
+}
+
+#[tokio::test]
+async fn it_finishes_once_connections_close() {
+    tokio::time::pause();
+
+    let drain = Arc::new(Drain::new(Duration::from_secs(5)));
+    let mut sse = drain.connect(ConnectionKind::Sse);
+    let poll = drain.connect(ConnectionKind::LongPoll);
+    assert_eq!(drain.connections()[&ConnectionKind::Sse], 1);
+    assert_eq!(drain.connections()[&ConnectionKind::LongPoll], 1);
+
+    let shutdown = tokio::spawn({
+        let drain = drain.clone();
+        async move { drain.shutdown().await }
+    });
+
+    sse.draining().await;
+    assert_eq!(drain.phase(), ShutdownPhase::Draining);
+    drop(poll);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(!shutdown.is_finished());
+
+    let started = tokio::time::Instant::now();
+    drop(sse);
+    assert!(!shutdown.await.unwrap(), "nothing had to be closed");
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(drain.phase(), ShutdownPhase::Draining);
+    assert_eq!(drain.total(), 0);
+}
+
+#[tokio::test]
+async fn it_closes_connections_after_the_grace_period() {
+    tokio::time::pause();
+
+    let drain = Drain::new(Duration::from_secs(5));
+    let mut stubborn = drain.connect(ConnectionKind::Sse);
+
+    let started = tokio::time::Instant::now();
+    assert!(drain.shutdown().await, "the connection had to be closed");
+    assert!(started.elapsed() >= Duration::from_secs(5));
+    assert!(started.elapsed() < Duration::from_secs(6));
+    assert_eq!(drain.phase(), ShutdownPhase::Closed);
+
+    // Already closed connections resolve right away.
+    stubborn.closed().await;
+}
+
+#[tokio::test]
+async fn it_finishes_right_away_without_connections() {
+    let drain = Drain::new(Duration::from_secs(60));
+    assert!(!drain.shutdown().await);
+}
@@ -1,5 +1,258 @@
+// error_chain! expands to cfg checks that newer compilers no longer know about.
+#![allow(unexpected_cfgs)]
+
+use std::fmt;
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::clusters::service::NameTaken;
+use crate::clusters::store::ClusterNotFound;
+use crate::kafka::metadata::manager::InvalidConsumerConfig;
+use crate::request_id::RequestId;
+use crate::subscriptions::service::SubscriptionError;
+use crate::subscriptions::store::SubscriptionNotFound;
+use crate::validate::FieldError;
+
 error_chain! {
     errors {}
 }
 
 pub type AnyError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An error an endpoint answers with, as an [`ErrorBody`] and its status.
+///
+/// v1 answers the body as is, flat. v2 nests its code and message under
+/// `error`, the envelope `seekr_api_types` pins, see `api::error::respond`.
+/// Internal errors are logged in full under the request's id, and only that
+/// id reaches the client.
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    Forbidden(String, Option<Value>),
+    Validation(String, Option<Value>),
+    Conflict(String, Option<Value>),
+    Unavailable(String),
+    Internal(AnyError),
+}
+
+impl ApiError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::NotFound(message.into())
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        ApiError::Validation(message.into(), None)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        ApiError::Conflict(message.into(), None)
+    }
+
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        ApiError::Unavailable(message.into())
+    }
+
+    /// The code the error is answered with, the same in v1 and v2.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Forbidden(..) => "forbidden",
+            ApiError::Validation(..) => "invalid_request",
+            ApiError::Conflict(..) => "conflict",
+            ApiError::Unavailable(_) => "unavailable",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    /// The body the error is answered with, with the id of the request when
+    /// it failed on our side, which internal errors are logged under.
+    pub fn body(&self) -> ErrorBody {
+        let request_id = RequestId::current().map(|id| id.0);
+        match self {
+            ApiError::Internal(e) => {
+                let correlation_id =
+                    request_id.unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+                error!("Internal error {}: {}", correlation_id, chain(e.as_ref()));
+                ErrorBody {
+                    correlation_id: Some(correlation_id),
+                    ..ErrorBody::new(self.code(), "Internal error, reported in the server logs")
+                }
+            }
+            ApiError::Unavailable(_) => ErrorBody {
+                correlation_id: request_id,
+                ..ErrorBody::new(self.code(), self.to_string())
+            },
+            ApiError::Forbidden(_, details)
+            | ApiError::Validation(_, details)
+            | ApiError::Conflict(_, details) => ErrorBody {
+                details: details.clone(),
+                ..ErrorBody::new(self.code(), self.to_string())
+            },
+            ApiError::NotFound(_) => ErrorBody::new(self.code(), self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::NotFound(message)
+            | ApiError::Forbidden(message, _)
+            | ApiError::Validation(message, _)
+            | ApiError::Conflict(message, _)
+            | ApiError::Unavailable(message) => f.write_str(message),
+            ApiError::Internal(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Forbidden(..) => StatusCode::FORBIDDEN,
+            ApiError::Validation(..) => StatusCode::BAD_REQUEST,
+            ApiError::Conflict(..) => StatusCode::CONFLICT,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
+
+/// An error and every error that caused it, outermost first.
+fn chain(e: &(dyn std::error::Error + 'static)) -> String {
+    let mut chain = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        chain.push_str(": ");
+        chain.push_str(&e.to_string());
+        source = e.source();
+    }
+    chain
+}
+
+/// Errors of the stores and the metadata manager, answered as the ones the
+/// client can act on when they're known and as internal otherwise.
+impl From<AnyError> for ApiError {
+    fn from(e: AnyError) -> Self {
+        if e.is::<ClusterNotFound>() || e.is::<SubscriptionNotFound>() {
+            return ApiError::NotFound(e.to_string());
+        }
+        if let Some(taken) = e.downcast_ref::<NameTaken>() {
+            let details = json!({ "conflicting_id": taken.id });
+            return ApiError::Conflict(taken.to_string(), Some(details));
+        }
+        if e.is::<InvalidConsumerConfig>() {
+            return ApiError::Validation(e.to_string(), None);
+        }
+        ApiError::Internal(e)
+    }
+}
+
+impl From<SubscriptionError> for ApiError {
+    fn from(e: SubscriptionError) -> Self {
+        match e {
+            SubscriptionError::ClusterNotFound(id) => ClusterNotFound(id).into(),
+            SubscriptionError::Store(e) => e.into(),
+        }
+    }
+}
+
+impl From<ClusterNotFound> for ApiError {
+    fn from(e: ClusterNotFound) -> Self {
+        ApiError::NotFound(e.to_string())
+    }
+}
+
+impl From<SubscriptionNotFound> for ApiError {
+    fn from(e: SubscriptionNotFound) -> Self {
+        ApiError::NotFound(e.to_string())
+    }
+}
+
+impl From<Vec<FieldError>> for ApiError {
+    fn from(errors: Vec<FieldError>) -> Self {
+        let fields = errors.iter().map(|e| e.field.as_str()).collect::<Vec<_>>();
+        let message = format!("Invalid fields: {}", fields.join(", "));
+        ApiError::Validation(message, Some(json!(errors)))
+    }
+}
+
+/// The body of every error: `{ "code", "message", "details" }`, plus the
+/// `correlation_id` of errors on our side, the id of their request.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl ErrorBody {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            correlation_id: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+#[actix_web::test]
+async fn it_maps_known_errors_and_hides_internal_ones() {
+    use actix_web::body::to_bytes;
+
+    use crate::ids::ClusterId;
+
+    let body = |e: ApiError| async move {
+        let res = e.error_response();
+        let bytes = to_bytes(res.into_body()).await.unwrap();
+        serde_json::from_slice::<Value>(&bytes).unwrap()
+    };
+
+    let missing: AnyError = Box::new(ClusterNotFound(ClusterId(9)));
+    let e = ApiError::from(missing);
+    assert_eq!(e.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        body(e).await,
+        json!({ "code": "not_found", "message": "Cluster with id '9' not found" })
+    );
+
+    let taken: AnyError = Box::new(NameTaken {
+        name: "orders".to_string(),
+        id: ClusterId(1),
+    });
+    let e = ApiError::from(taken);
+    assert_eq!(e.status_code(), StatusCode::CONFLICT);
+    assert_eq!(body(e).await["details"]["conflicting_id"], 1);
+
+    let cause = std::io::Error::new(std::io::ErrorKind::Other, "broker-7.internal unreachable");
+    let e = ApiError::from(Box::new(cause) as AnyError);
+    assert_eq!(e.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = body(e).await;
+    assert_eq!(body["code"], "internal");
+    assert!(!body.to_string().contains("broker-7"));
+    assert!(body["correlation_id"].is_string());
+
+    let id = RequestId("checkout-42".to_string());
+    let e = ApiError::unavailable("Cluster metadata with id '1' is still processing");
+    let res = crate::request_id::sync_scope(id, || e.error_response());
+    let bytes = to_bytes(res.into_body()).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["correlation_id"], "checkout-42");
+}
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use actix_web::http::header::{CacheControl, CacheDirective, ACCEPT};
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::clusters::store::{ClusterNotFound, ClusterStore};
+use crate::drain::{ConnectionKind, Drain};
+use crate::errors::ApiError;
+use crate::events::{self, MetadataEvent};
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::MetadataManager;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_events);
+}
+
+/// The metadata changes of the cluster after `since`, as server-sent events
+/// followed by the ones still to come when asked for `text/event-stream`.
+#[get("/{id}/events")]
+async fn get_events(
+    req: HttpRequest,
+    path: Path<ClusterId>,
+    query: Query<EventsQuery>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+    drain: Data<Drain>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    info!("Fetching metadata events for cluster with id {}", id);
+
+    cs.get(id).await?.ok_or(ClusterNotFound(id))?;
+
+    if !streams(&req) {
+        let events = manager.events().since(id, query.since);
+        return Ok(HttpResponse::Ok().json(EventsResponse { events }));
+    }
+
+    let (backlog, receiver) = manager.events().follow(id, query.since);
+    let connection = drain.connect(ConnectionKind::Sse);
+    let events = events::stream(id, backlog, receiver, connection);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events))
+}
+
+/// Whether the request accepts server-sent events.
+fn streams(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all(ACCEPT)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|t| t.split(';').next())
+        .any(|t| t.trim().eq_ignore_ascii_case("text/event-stream"))
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// Only the events after this time.
+    since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct EventsResponse {
+    events: Vec<MetadataEvent>,
+}
+
+#[actix_web::test]
+async fn it_lists_and_streams_the_changes_of_a_cluster() {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::changefeed::endpoints::v1::next_chunk;
+    use crate::changefeed::stream::SHUTTING_DOWN;
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::Change;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+    let manager = Arc::new(MetadataManager::new(cs.clone()));
+    let drain = Data::new(Drain::default());
+
+    let at = |s: i64| DateTime::<Utc>::from_timestamp(s, 0).unwrap();
+    let added = |topic: &str| Change::TopicCreated {
+        topic: topic.to_string(),
+        partitions: 1,
+    };
+    manager
+        .events()
+        .publish(ClusterId(1), at(1_700_000_000), vec![added("orders")]);
+    manager
+        .events()
+        .publish(ClusterId(1), at(1_700_000_060), vec![added("payments")]);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::from(manager.clone()))
+            .app_data(drain.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let since = "/api/v1/clusters/1/events?since=2023-11-14T22:13:20Z";
+    let res = test::call_service(&app, test::TestRequest::get().uri(since).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    let events = body["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["cluster_id"], 1);
+    assert_eq!(events[0]["change"], "topic_created");
+    assert_eq!(events[0]["topic"], "payments");
+
+    let missing = test::TestRequest::get().uri("/api/v1/clusters/2/events");
+    let res = test::call_service(&app, missing.to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // A stream sends the kept events, then the ones published since.
+    let req = test::TestRequest::get()
+        .uri(since)
+        .insert_header((ACCEPT, "text/event-stream"));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = res.map_into_boxed_body().into_body();
+    let topic = |event: String| {
+        let data = event.strip_prefix("event: metadata\ndata: ").unwrap();
+        let event: serde_json::Value = serde_json::from_str(data.trim()).unwrap();
+        event["topic"].as_str().unwrap().to_string()
+    };
+    assert_eq!(topic(next_chunk(&mut body).await.unwrap()), "payments");
+
+    manager
+        .events()
+        .publish(ClusterId(2), at(1_700_000_120), vec![added("other")]);
+    manager
+        .events()
+        .publish(ClusterId(1), at(1_700_000_120), vec![added("refunds")]);
+    assert_eq!(topic(next_chunk(&mut body).await.unwrap()), "refunds");
+
+    // Streams end once the server drains.
+    let shutdown = tokio::spawn(async move { drain.shutdown().await });
+    let last = next_chunk(&mut body).await.unwrap();
+    assert!(last.starts_with(&format!("event: {}\n", SHUTTING_DOWN)));
+    assert!(next_chunk(&mut body).await.is_none());
+    assert!(!shutdown.await.unwrap());
+}
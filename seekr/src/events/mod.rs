@@ -0,0 +1,239 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::api::sse::event;
+use crate::changefeed::stream::SHUTTING_DOWN;
+use crate::drain::Connection;
+use crate::history::diff::Change;
+use crate::ids::ClusterId;
+
+pub mod endpoints;
+
+/// Events kept for each cluster, for readers asking what happened since.
+pub const RECENT_EVENTS: usize = 500;
+
+/// Events held for subscribers that fall behind before they miss some.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// A change of a cluster's metadata, as published to subscribers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetadataEvent {
+    pub cluster_id: ClusterId,
+
+    /// When the poll that found the change fetched the metadata.
+    pub at: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// Publishes the metadata changes of every cluster, and keeps the last
+/// `RECENT_EVENTS` of each for readers catching up.
+pub struct EventLog {
+    sender: broadcast::Sender<MetadataEvent>,
+    recent: Mutex<HashMap<ClusterId, VecDeque<MetadataEvent>>>,
+    capacity: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(RECENT_EVENTS)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            recent: Default::default(),
+            capacity,
+        }
+    }
+
+    /// Publish the changes a poll of the cluster found, dropping the oldest
+    /// events kept of it past the capacity.
+    pub fn publish(&self, cluster_id: ClusterId, at: DateTime<Utc>, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        // Kept and sent under the lock, so subscribers catching up see each once.
+        let mut recent = self.recent.lock().unwrap();
+        let kept = recent.entry(cluster_id).or_default();
+        for change in changes {
+            let event = MetadataEvent {
+                cluster_id,
+                at,
+                change,
+            };
+            kept.push_back(event.clone());
+            if kept.len() > self.capacity {
+                kept.pop_front();
+            }
+            // Without subscribers the event is only kept.
+            let _ = self.sender.send(event);
+        }
+    }
+
+    /// Receive the events of every cluster published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<MetadataEvent> {
+        self.sender.subscribe()
+    }
+
+    /// The kept events of the cluster after `since`, all of them without.
+    pub fn since(&self, cluster_id: ClusterId, since: Option<DateTime<Utc>>) -> Vec<MetadataEvent> {
+        let recent = self.recent.lock().unwrap();
+        kept(&recent, cluster_id, since)
+    }
+
+    /// The kept events of the cluster after `since`, along with a receiver
+    /// of those published after them, none missed nor repeated.
+    pub fn follow(
+        &self,
+        cluster_id: ClusterId,
+        since: Option<DateTime<Utc>>,
+    ) -> (Vec<MetadataEvent>, broadcast::Receiver<MetadataEvent>) {
+        let recent = self.recent.lock().unwrap();
+        (kept(&recent, cluster_id, since), self.sender.subscribe())
+    }
+
+    /// Drop the events kept of a removed cluster.
+    pub fn forget(&self, cluster_id: ClusterId) {
+        self.recent.lock().unwrap().remove(&cluster_id);
+    }
+}
+
+fn kept(
+    recent: &HashMap<ClusterId, VecDeque<MetadataEvent>>,
+    cluster_id: ClusterId,
+    since: Option<DateTime<Utc>>,
+) -> Vec<MetadataEvent> {
+    recent
+        .get(&cluster_id)
+        .into_iter()
+        .flatten()
+        .filter(|e| since.is_none_or(|since| e.at > since))
+        .cloned()
+        .collect()
+}
+
+#[derive(Serialize)]
+struct Lagged {
+    skipped: u64,
+}
+
+#[derive(Serialize)]
+struct ShuttingDown {}
+
+enum State {
+    Open {
+        backlog: VecDeque<MetadataEvent>,
+        receiver: broadcast::Receiver<MetadataEvent>,
+        connection: Connection,
+    },
+    Done,
+}
+
+/// Stream the cluster's metadata changes as server-sent `metadata` events,
+/// the kept ones first.
+///
+/// A reader too slow to keep up gets a `lagged` event with the number of
+/// events it missed, of every cluster. Once the server starts draining, a
+/// final `server_shutting_down` event ends the stream.
+pub fn stream(
+    cluster_id: ClusterId,
+    backlog: Vec<MetadataEvent>,
+    receiver: broadcast::Receiver<MetadataEvent>,
+    connection: Connection,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let state = State::Open {
+        backlog: backlog.into(),
+        receiver,
+        connection,
+    };
+
+    futures::stream::unfold(state, move |state| async move {
+        let State::Open {
+            mut backlog,
+            mut receiver,
+            mut connection,
+        } = state
+        else {
+            return None;
+        };
+
+        if let Some(e) = backlog.pop_front() {
+            let next = event(Some("metadata"), &e);
+            let state = State::Open {
+                backlog,
+                receiver,
+                connection,
+            };
+            return Some((next, state));
+        }
+
+        loop {
+            tokio::select! {
+                received = receiver.recv() => {
+                    let next = match received {
+                        Ok(e) if e.cluster_id == cluster_id => event(Some("metadata"), &e),
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(skipped)) => event(Some("lagged"), &Lagged { skipped }),
+                        Err(RecvError::Closed) => return None,
+                    };
+                    let state = State::Open { backlog, receiver, connection };
+                    return Some((next, state));
+                }
+                _ = connection.draining() => {
+                    return Some((event(Some(SHUTTING_DOWN), &ShuttingDown {}), State::Done));
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn it_keeps_the_last_events_of_each_cluster() {
+    let log = EventLog::new(3);
+    let at = |s: i64| DateTime::<Utc>::from_timestamp(s, 0).unwrap();
+    let added = |topic: &str| Change::TopicCreated {
+        topic: topic.to_string(),
+        partitions: 1,
+    };
+
+    let mut receiver = log.subscribe();
+    log.publish(ClusterId(1), at(1), vec![added("a"), added("b")]);
+    log.publish(ClusterId(2), at(2), vec![added("c")]);
+    log.publish(ClusterId(1), at(3), vec![added("d"), added("e")]);
+    log.publish(ClusterId(1), at(4), vec![]);
+
+    let topics = |events: Vec<MetadataEvent>| {
+        events
+            .into_iter()
+            .map(|e| match e.change {
+                Change::TopicCreated { topic, .. } => topic,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(topics(log.since(ClusterId(1), None)), ["b", "d", "e"]);
+    assert_eq!(topics(log.since(ClusterId(1), Some(at(1)))), ["d", "e"]);
+    assert_eq!(topics(log.since(ClusterId(2), None)), ["c"]);
+    assert!(log.since(ClusterId(3), None).is_empty());
+
+    // Subscribers get every event, of every cluster.
+    let received = (0..5)
+        .map(|_| receiver.try_recv().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(topics(received), ["a", "b", "c", "d", "e"]);
+
+    log.forget(ClusterId(1));
+    assert!(log.since(ClusterId(1), None).is_empty());
+}
@@ -0,0 +1,55 @@
+use actix_web::web::{Json, ServiceConfig};
+use actix_web::{delete, get, post, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::auth::Principal;
+use crate::failpoints::{self, Failpoint};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(arm_failpoint)
+        .service(list_failpoints)
+        .service(clear_failpoints);
+}
+
+#[post("/failpoints")]
+async fn arm_failpoint(principal: Principal, r: Json<Failpoint>) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+    if r.count == Some(0) {
+        return HttpResponse::BadRequest().body("count must be greater than 0");
+    }
+
+    failpoints::arm(r.into_inner());
+    list()
+}
+
+#[get("/failpoints")]
+async fn list_failpoints(principal: Principal) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+    list()
+}
+
+#[delete("/failpoints")]
+async fn clear_failpoints(principal: Principal) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    warn!("Disarming every failpoint");
+    failpoints::clear();
+    list()
+}
+
+fn list() -> HttpResponse {
+    HttpResponse::Ok().json(FailpointsResponse {
+        failpoints: failpoints::list(),
+    })
+}
+
+#[derive(Serialize)]
+struct FailpointsResponse {
+    failpoints: Vec<Failpoint>,
+}
@@ -0,0 +1,189 @@
+//! Named failure injection points, compiled in with the `chaos` feature.
+//!
+//! Production code consults the registry through `fail_point!`, which
+//! expands to `Ok(())` in builds without the feature.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AnyError;
+
+pub mod endpoints;
+
+/// Metadata fetched by the metadata manager, scoped by cluster id.
+pub const METADATA_FETCH: &str = "metadata.fetch";
+/// Creation of a subscription's streams consumer, scoped by subscription id.
+pub const CONSUMER_CREATE: &str = "consumer.create";
+/// Documents submitted to Meilisearch by the indexer, scoped by subscription id.
+pub const MEILISEARCH_SUBMIT: &str = "meilisearch.submit";
+/// Offsets committed by the streams consumer, scoped by subscription id.
+pub const OFFSET_COMMIT: &str = "offset.commit";
+/// Renewals of the primary lease by the elector, scoped by instance.
+pub const LEASE_RENEW: &str = "lease.renew";
+
+/// What happens when a failpoint triggers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Mode {
+    Error,
+    Delay(u64),
+    Panic,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let delay = s
+            .strip_prefix("delay(")
+            .and_then(|s| s.strip_suffix(')'))
+            .map(|ms| ms.parse().map_err(|_| format!("invalid delay '{}'", ms)));
+
+        match (s, delay) {
+            ("error", _) => Ok(Mode::Error),
+            ("panic", _) => Ok(Mode::Panic),
+            (_, Some(ms)) => Ok(Mode::Delay(ms?)),
+            _ => Err(format!(
+                "unknown mode '{}', expected error, delay(ms) or panic",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Mode {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mode::Error => write!(f, "error"),
+            Mode::Delay(ms) => write!(f, "delay({})", ms),
+            Mode::Panic => write!(f, "panic"),
+        }
+    }
+}
+
+impl From<Mode> for String {
+    fn from(m: Mode) -> Self {
+        m.to_string()
+    }
+}
+
+/// An armed failpoint.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Failpoint {
+    /// The failpoint name, optionally narrowed to one scope as `name:scope`, e.g. `metadata.fetch:42`.
+    pub name: String,
+
+    pub mode: Mode,
+
+    /// Number of times the failpoint triggers before disarming, forever when absent.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub count: Option<u32>,
+}
+
+lazy_static! {
+    static ref REGISTRY: Mutex<HashMap<String, Failpoint>> = Mutex::new(HashMap::new());
+}
+
+/// Arm a failpoint, replacing any failpoint with the same name.
+pub fn arm(failpoint: Failpoint) {
+    warn!(
+        "Arming failpoint {} to {} {:?} times",
+        failpoint.name, failpoint.mode, failpoint.count
+    );
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.insert(failpoint.name.clone(), failpoint);
+}
+
+/// Disarm every failpoint.
+pub fn clear() {
+    REGISTRY.lock().unwrap().clear();
+}
+
+/// The armed failpoints, ordered by name.
+pub fn list() -> Vec<Failpoint> {
+    let mut failpoints = REGISTRY
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    failpoints.sort_by(|a, b| a.name.cmp(&b.name));
+    failpoints
+}
+
+/// Consume one trigger of the failpoint, preferring the one narrowed to the scope.
+fn take(name: &str, scope: &str) -> Option<Mode> {
+    let mut registry = REGISTRY.lock().unwrap();
+    let key = [format!("{}:{}", name, scope), name.to_string()]
+        .into_iter()
+        .find(|k| registry.contains_key(k))?;
+
+    let failpoint = registry.get_mut(&key).unwrap();
+    let mode = failpoint.mode;
+    match failpoint.count {
+        Some(1) => {
+            registry.remove(&key);
+        }
+        Some(n) => failpoint.count = Some(n - 1),
+        None => {}
+    }
+    Some(mode)
+}
+
+/// Evaluate a failpoint, as expanded by `fail_point!`.
+pub async fn eval(name: &str, scope: &str) -> Result<(), AnyError> {
+    match take(name, scope) {
+        None => Ok(()),
+        Some(Mode::Error) => Err(format!("failpoint {} triggered", name).into()),
+        Some(Mode::Delay(ms)) => {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            Ok(())
+        }
+        Some(Mode::Panic) => panic!("failpoint {} triggered", name),
+    }
+}
+
+#[test]
+fn it_parses_modes() {
+    assert_eq!("error".parse(), Ok(Mode::Error));
+    assert_eq!("delay(250)".parse(), Ok(Mode::Delay(250)));
+    assert_eq!("panic".parse(), Ok(Mode::Panic));
+    assert!("delay(soon)".parse::<Mode>().is_err());
+    assert!("explode".parse::<Mode>().is_err());
+
+    let failpoint: Failpoint =
+        serde_json::from_str(r#"{"name":"metadata.fetch","mode":"delay(5)","count":2}"#).unwrap();
+    assert_eq!(failpoint.mode, Mode::Delay(5));
+    assert_eq!(
+        serde_json::to_string(&failpoint).unwrap(),
+        r#"{"name":"metadata.fetch","mode":"delay(5)","count":2}"#
+    );
+}
+
+#[tokio::test]
+async fn it_triggers_scoped_failpoints_count_times() {
+    arm(Failpoint {
+        name: "test.scoped:7".to_string(),
+        mode: Mode::Error,
+        count: Some(2),
+    });
+
+    assert!(eval("test.scoped", "8").await.is_ok());
+    assert!(eval("test.scoped", "7").await.is_err());
+    assert!(eval("test.scoped", "7").await.is_err());
+    assert!(eval("test.scoped", "7").await.is_ok());
+    assert!(!list().iter().any(|f| f.name.starts_with("test.scoped")));
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use chrono::Utc;
+
+use crate::auth::Principal;
+use crate::clusters::store::ClusterStore;
+use crate::governance::policy::OwnershipPolicy;
+use crate::governance::report;
+use crate::subscriptions::store::SubscriptionStore;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_stale_ownership);
+}
+
+#[get("/stale-ownership")]
+async fn get_stale_ownership(
+    principal: Principal,
+    policy: OwnershipPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    info!("Fetching entities with stale ownership");
+
+    let report = report::stale_ownership(
+        cs.as_ref().as_ref(),
+        ss.as_ref().as_ref(),
+        &policy,
+        Utc::now(),
+    );
+
+    match report.await {
+        Ok(mut report) => {
+            report.retain(|id| principal.can_access(id));
+            HttpResponse::Ok().json(report)
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[actix_web::test]
+async fn it_reports_stale_ownership_within_the_principals_grants() {
+    use std::collections::HashMap;
+
+    use actix_web::{test, App, HttpMessage};
+    use chrono::Duration;
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::governance::owner::Owner;
+    use crate::ids::ClusterId;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+
+    let confirmed = |days: i64| Some(Utc::now() - Duration::days(days));
+    for (id, confirmed_at) in [(1, confirmed(1)), (2, confirmed(8)), (3, None)] {
+        let cluster = Cluster {
+            owner: confirmed_at.map(|_| Owner::team("payments")),
+            ownership_confirmed_at: confirmed_at,
+            ..Cluster::new(
+                Some(ClusterId(id)),
+                Kind::Kafka,
+                id.to_string(),
+                HashMap::new(),
+            )
+        };
+        cs.put(cluster).await.unwrap();
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(ss))
+            .app_data(Data::new(OwnershipPolicy::days(7)))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let stale = |body: Value| {
+        body["clusters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| (s["cluster_id"].as_i64().unwrap(), s["reason"].clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/governance/stale-ownership")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        stale(body),
+        vec![(2, "unconfirmed".into()), (3, "unowned".into())]
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/governance/stale-ownership")
+        .to_request();
+    req.extensions_mut().insert(Principal {
+        key_id: None,
+        role: crate::auth::Role::Readonly,
+        clusters: Some([ClusterId(3)].into()),
+        token: None,
+    });
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stale(body), vec![(3, "unowned".into())]);
+}
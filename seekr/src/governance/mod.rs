@@ -0,0 +1,4 @@
+pub mod endpoints;
+pub mod owner;
+pub mod policy;
+pub mod report;
@@ -0,0 +1,55 @@
+use cdrs_tokio::types::prelude::Row;
+use cdrs_tokio::types::ByName;
+use chrono::{DateTime, Utc};
+
+pub use seekr_api_types::owner::Owner;
+
+/// The outcome of confirming the owner of an entity.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Confirmation {
+    Confirmed(DateTime<Utc>),
+
+    /// There's no owner to confirm.
+    Unowned,
+    NotFound,
+}
+
+/// The owner after an update: an omitted owner keeps the current one, and an
+/// empty one clears it.
+pub fn resolve(current: Option<Owner>, requested: Option<Owner>) -> Option<Owner> {
+    match requested {
+        Some(owner) => owner.normalize(),
+        None => current,
+    }
+}
+
+/// The owner as stored in a Cassandra text column, as JSON.
+pub fn write(owner: Option<&Owner>) -> Option<String> {
+    owner.and_then(|o| serde_json::to_string(o).ok())
+}
+
+/// Read the owner column of a Cassandra row, if it is set.
+pub fn read(row: &Row) -> Option<Owner> {
+    let json = row.by_name::<String>("owner").ok().flatten()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Read the ownership confirmation column of a Cassandra row, if it is set.
+pub fn read_confirmed_at(row: &Row) -> Option<DateTime<Utc>> {
+    row.by_name::<DateTime<Utc>>("ownership_confirmed_at")
+        .ok()
+        .flatten()
+}
+
+#[test]
+fn it_resolves_updated_owners() {
+    let current = Some(Owner::team("payments"));
+
+    assert_eq!(resolve(current.clone(), None), current);
+    assert_eq!(resolve(current.clone(), Some(Owner::default())), None);
+    assert_eq!(
+        resolve(current, Some(Owner::team("billing"))),
+        Some(Owner::team("billing"))
+    );
+    assert_eq!(resolve(None, None), None);
+}
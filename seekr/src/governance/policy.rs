@@ -0,0 +1,103 @@
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use super::owner::Owner;
+
+/// Default number of days an owner stays confirmed.
+pub const DEFAULT_STALE_DAYS: i64 = 90;
+
+/// Why the ownership of an entity is stale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleReason {
+    /// No owner was ever set, or it was cleared.
+    Unowned,
+
+    /// The owner was not re-confirmed within the window.
+    Unconfirmed,
+}
+
+/// How long an owner stays confirmed before it has to be re-confirmed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnershipPolicy {
+    pub window: Duration,
+}
+
+impl Default for OwnershipPolicy {
+    fn default() -> Self {
+        Self::days(DEFAULT_STALE_DAYS)
+    }
+}
+
+impl OwnershipPolicy {
+    pub fn days(days: i64) -> Self {
+        Self {
+            window: Duration::days(days),
+        }
+    }
+
+    /// Why the ownership is stale at `now`, if it is.
+    ///
+    /// Entities without an owner are always stale, since nobody can be
+    /// contacted about them. An owner confirmed exactly one window ago is stale.
+    pub fn stale(
+        &self,
+        owner: Option<&Owner>,
+        confirmed_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<StaleReason> {
+        match (owner, confirmed_at) {
+            (None, _) => Some(StaleReason::Unowned),
+            (Some(_), Some(at)) if now - at < self.window => None,
+            (Some(_), _) => Some(StaleReason::Unconfirmed),
+        }
+    }
+}
+
+/// The policy registered with the server, or the default one.
+impl FromRequest for OwnershipPolicy {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let policy = req.app_data::<Data<OwnershipPolicy>>();
+        ready(Ok(policy.map(|p| p.get_ref().clone()).unwrap_or_default()))
+    }
+}
+
+#[test]
+fn it_flags_stale_ownership_at_the_window_boundary() {
+    let policy = OwnershipPolicy::days(30);
+    let now = Utc::now();
+    let owner = Owner::team("payments");
+
+    let confirmed = |ago: Duration| policy.stale(Some(&owner), Some(now - ago), now);
+    assert_eq!(confirmed(Duration::zero()), None);
+    assert_eq!(confirmed(Duration::days(30) - Duration::seconds(1)), None);
+    assert_eq!(
+        confirmed(Duration::days(30)),
+        Some(StaleReason::Unconfirmed)
+    );
+    assert_eq!(
+        confirmed(Duration::days(31)),
+        Some(StaleReason::Unconfirmed)
+    );
+
+    // An owner without a confirmation, e.g. written before confirmations existed.
+    assert_eq!(
+        policy.stale(Some(&owner), None, now),
+        Some(StaleReason::Unconfirmed)
+    );
+
+    // Entities that never had an owner are stale regardless of their age.
+    assert_eq!(policy.stale(None, None, now), Some(StaleReason::Unowned));
+    assert_eq!(
+        policy.stale(None, Some(now), now),
+        Some(StaleReason::Unowned)
+    );
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::history::notify::NOTIFY_TARGET;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::page::Page;
+use crate::subscriptions::store::SubscriptionStore;
+use crate::sweeper::Job;
+
+use super::owner::Owner;
+use super::policy::{OwnershipPolicy, StaleReason};
+
+/// How often the ownership of every entity is checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A cluster or subscription whose ownership is stale.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StaleOwnership {
+    pub cluster_id: ClusterId,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_id: Option<SubscriptionId>,
+
+    /// The cluster name, or the topic name of a subscription.
+    pub name: String,
+
+    pub owner: Option<Owner>,
+    pub ownership_confirmed_at: Option<DateTime<Utc>>,
+    pub reason: StaleReason,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct StaleOwnershipReport {
+    pub clusters: Vec<StaleOwnership>,
+    pub subscriptions: Vec<StaleOwnership>,
+}
+
+impl StaleOwnershipReport {
+    pub fn len(&self) -> usize {
+        self.clusters.len() + self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keep only the entries of clusters matching the predicate.
+    pub fn retain(&mut self, f: impl Fn(ClusterId) -> bool) {
+        self.clusters.retain(|s| f(s.cluster_id));
+        self.subscriptions.retain(|s| f(s.cluster_id));
+    }
+}
+
+/// Every cluster and subscription whose ownership is stale at `now`.
+pub async fn stale_ownership(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    policy: &OwnershipPolicy,
+    now: DateTime<Utc>,
+) -> Result<StaleOwnershipReport, AnyError> {
+    let clusters = cs
+        .list(None, Page::ALL)
+        .await?
+        .into_iter()
+        .filter_map(|c| {
+            let reason = policy.stale(c.owner.as_ref(), c.ownership_confirmed_at, now)?;
+            Some(StaleOwnership {
+                cluster_id: c.id,
+                subscription_id: None,
+                name: c.name,
+                owner: c.owner,
+                ownership_confirmed_at: c.ownership_confirmed_at,
+                reason,
+            })
+        })
+        .collect();
+
+    let subscriptions = ss
+        .list(None, Page::ALL)
+        .await?
+        .into_iter()
+        .filter_map(|s| {
+            let reason = policy.stale(s.owner.as_ref(), s.ownership_confirmed_at, now)?;
+            Some(StaleOwnership {
+                cluster_id: s.cluster_id,
+                subscription_id: Some(s.id),
+                name: s.topic_name,
+                owner: s.owner,
+                ownership_confirmed_at: s.ownership_confirmed_at,
+                reason,
+            })
+        })
+        .collect();
+
+    Ok(StaleOwnershipReport {
+        clusters,
+        subscriptions,
+    })
+}
+
+/// Periodically notifies about entities whose ownership went stale.
+pub struct OwnershipCheck {
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    policy: OwnershipPolicy,
+}
+
+impl OwnershipCheck {
+    pub fn new(
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+        policy: OwnershipPolicy,
+    ) -> Self {
+        Self {
+            clusters,
+            subscriptions,
+            policy,
+        }
+    }
+
+    /// The sweeper job notifying about stale ownership. It removes nothing.
+    pub fn job(self: Arc<Self>) -> Job {
+        Job::new("ownership_check", CHECK_INTERVAL, move || {
+            let check = self.clone();
+            async move {
+                check.check().await?;
+                Ok::<_, AnyError>(0)
+            }
+        })
+    }
+
+    async fn check(&self) -> Result<(), AnyError> {
+        let report = stale_ownership(
+            self.clusters.as_ref(),
+            self.subscriptions.as_ref(),
+            &self.policy,
+            Utc::now(),
+        )
+        .await?;
+
+        if report.is_empty() {
+            return Ok(());
+        }
+
+        warn!("Found {} entities with stale ownership", report.len());
+        for stale in report.clusters.iter().chain(&report.subscriptions) {
+            match serde_json::to_string(stale) {
+                Ok(line) => warn!(target: NOTIFY_TARGET, "{}", line),
+                Err(e) => error!(target: NOTIFY_TARGET, "Unable to serialize notification: {}", e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn it_reports_unowned_and_unconfirmed_entities() {
+    use std::collections::HashMap;
+
+    use chrono::Duration;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::Subscription;
+
+    let now = Utc::now();
+    let policy = OwnershipPolicy::days(30);
+    let cs = MemoryClusterStore::default();
+    let ss = MemorySubscriptionStore::default();
+
+    let cluster = |id: i64, confirmed: Option<i64>| Cluster {
+        owner: confirmed.map(|_| Owner::team("payments")),
+        ownership_confirmed_at: confirmed.map(|days| now - Duration::days(days)),
+        ..Cluster::new(
+            Some(ClusterId(id)),
+            Kind::Kafka,
+            id.to_string(),
+            HashMap::new(),
+        )
+    };
+    cs.put(cluster(1, Some(1))).await.unwrap();
+    cs.put(cluster(2, Some(30))).await.unwrap();
+    cs.put(cluster(3, None)).await.unwrap();
+
+    let subscription = Subscription {
+        owner: Some(Owner::team("payments")),
+        ownership_confirmed_at: Some(now),
+        ..Subscription::new(
+            Some(SubscriptionId(1)),
+            ClusterId(1),
+            "orders".to_string(),
+            HashMap::new(),
+        )
+    };
+    ss.put(subscription).await.unwrap();
+    let unowned = Subscription::new(
+        Some(SubscriptionId(2)),
+        ClusterId(1),
+        "refunds".to_string(),
+        HashMap::new(),
+    );
+    ss.put(unowned).await.unwrap();
+
+    let mut report = stale_ownership(&cs, &ss, &policy, now).await.unwrap();
+    let reasons = report
+        .clusters
+        .iter()
+        .map(|s| (s.cluster_id, s.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        vec![
+            (ClusterId(2), StaleReason::Unconfirmed),
+            (ClusterId(3), StaleReason::Unowned)
+        ]
+    );
+    assert_eq!(report.subscriptions.len(), 1);
+    assert_eq!(
+        report.subscriptions[0].subscription_id,
+        Some(SubscriptionId(2))
+    );
+    assert_eq!(report.subscriptions[0].reason, StaleReason::Unowned);
+
+    report.retain(|id| id == ClusterId(3));
+    assert_eq!(report.len(), 1);
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::clusters::store::ClusterStore;
+use crate::kafka::metadata::manager::MetadataManager;
+
+// Probes for load balancers and orchestrators, mounted at the root rather than
+// under an API version, and answered without authentication.
+
+/// How long the cluster store gets to answer a readiness probe.
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(healthz).service(readyz);
+}
+
+/// Live as soon as the server answers.
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(ProbeResponse {
+        status: "ok",
+        failing: vec![],
+    })
+}
+
+/// Ready once the cluster store answers and the metadata manager started.
+#[get("/readyz")]
+async fn readyz(
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+) -> impl Responder {
+    let mut failing = vec![];
+    match tokio::time::timeout(PING_TIMEOUT, cs.ping()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => failing.push(Failure {
+            component: "cluster_store",
+            reason: e.to_string(),
+        }),
+        Err(_) => failing.push(Failure {
+            component: "cluster_store",
+            reason: format!("no answer within {:?}", PING_TIMEOUT),
+        }),
+    }
+    if !manager.is_ready() {
+        failing.push(Failure {
+            component: "metadata_manager",
+            reason: "not started yet".to_string(),
+        });
+    }
+
+    if failing.is_empty() {
+        HttpResponse::Ok().json(ProbeResponse {
+            status: "ready",
+            failing,
+        })
+    } else {
+        HttpResponse::ServiceUnavailable().json(ProbeResponse {
+            status: "unavailable",
+            failing,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ProbeResponse {
+    status: &'static str,
+    failing: Vec<Failure>,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    component: &'static str,
+    reason: String,
+}
+
+#[actix_web::test]
+async fn it_is_ready_once_every_component_is() {
+    use std::sync::atomic::Ordering;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    let store = Arc::new(MemoryClusterStore::default());
+    store.unreachable.store(true, Ordering::SeqCst);
+    let cs: Arc<dyn ClusterStore + Send + Sync> = store.clone();
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Data::new(MetadataManager::with_factory(cs.clone(), factory));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(manager.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let probe =
+        |uri: &str| test::call_service(&app, test::TestRequest::get().uri(uri).to_request());
+
+    let res = probe("/healthz").await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = probe("/readyz").await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = test::read_body_json(res).await;
+    let components = body["failing"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["component"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        components,
+        [json!("cluster_store"), json!("metadata_manager")]
+    );
+
+    store.unreachable.store(false, Ordering::SeqCst);
+    manager.clone().into_inner().start().await.unwrap();
+    let res = probe("/readyz").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body, json!({ "status": "ready", "failing": [] }));
+}
@@ -0,0 +1,331 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::kafka::metadata::{ClusterMetadata, PartitionMetadata};
+
+/// A single difference between two metadata snapshots of a cluster.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    TopicCreated {
+        topic: String,
+        partitions: usize,
+    },
+    TopicDeleted {
+        topic: String,
+    },
+    PartitionsChanged {
+        topic: String,
+        from: usize,
+        to: usize,
+    },
+
+    /// Replicas left the partition's in-sync replicas.
+    IsrShrunk {
+        topic: String,
+        partition: i32,
+        from: Vec<i32>,
+        to: Vec<i32>,
+    },
+
+    /// The partition was moved to other brokers.
+    ReplicasReassigned {
+        topic: String,
+        partition: i32,
+        from: Vec<i32>,
+        to: Vec<i32>,
+    },
+    BrokerAdded {
+        broker: i32,
+    },
+    BrokerRemoved {
+        broker: i32,
+    },
+    GroupStateChanged {
+        group: String,
+        from: String,
+        to: String,
+    },
+}
+
+/// The changes that turn `before` into `after`, ordered by kind and name:
+/// created and kept topics, each with its partitions by id, then deleted
+/// topics, brokers and groups.
+///
+/// Watermarks, leaders and the order of replicas move all the time and aren't
+/// changes. A partition whose replicas are other brokers was reassigned,
+/// whether or not its ISR shrank too.
+pub fn diff(before: &ClusterMetadata, after: &ClusterMetadata) -> Vec<Change> {
+    let topics = |m: &ClusterMetadata| {
+        m.topics
+            .iter()
+            .map(|t| (t.name.clone(), &t.partitions))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let brokers = |m: &ClusterMetadata| m.brokers.iter().map(|b| b.id).collect::<BTreeSet<_>>();
+
+    let (old, new) = (topics(before), topics(after));
+    let mut changes = Vec::new();
+
+    for (topic, partitions) in &new {
+        match old.get(topic) {
+            None => changes.push(Change::TopicCreated {
+                topic: topic.clone(),
+                partitions: partitions.len(),
+            }),
+            Some(from) => diff_partitions(topic, from, partitions, &mut changes),
+        }
+    }
+    for topic in old.keys().filter(|t| !new.contains_key(*t)) {
+        changes.push(Change::TopicDeleted {
+            topic: topic.clone(),
+        });
+    }
+
+    let (old, new) = (brokers(before), brokers(after));
+    for &broker in new.difference(&old) {
+        changes.push(Change::BrokerAdded { broker });
+    }
+    for &broker in old.difference(&new) {
+        changes.push(Change::BrokerRemoved { broker });
+    }
+
+    let groups = |m: &ClusterMetadata| {
+        m.groups
+            .iter()
+            .map(|g| (g.name.clone(), g.state.clone()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let old = groups(before);
+    for (group, to) in groups(after) {
+        match old.get(&group) {
+            Some(from) if *from != to => changes.push(Change::GroupStateChanged {
+                group,
+                from: from.clone(),
+                to,
+            }),
+            _ => {}
+        }
+    }
+
+    changes
+}
+
+fn diff_partitions(
+    topic: &str,
+    before: &[PartitionMetadata],
+    after: &[PartitionMetadata],
+    changes: &mut Vec<Change>,
+) {
+    if before.len() != after.len() {
+        changes.push(Change::PartitionsChanged {
+            topic: topic.to_string(),
+            from: before.len(),
+            to: after.len(),
+        });
+    }
+
+    let sorted = |ids: &[i32]| {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids
+    };
+    let old = before.iter().map(|p| (p.id, p)).collect::<BTreeMap<_, _>>();
+    let mut partitions = after.iter().collect::<Vec<_>>();
+    partitions.sort_by_key(|p| p.id);
+    for p in partitions {
+        let Some(o) = old.get(&p.id) else {
+            continue;
+        };
+
+        let (from, to) = (sorted(&o.replicas), sorted(&p.replicas));
+        if from != to {
+            changes.push(Change::ReplicasReassigned {
+                topic: topic.to_string(),
+                partition: p.id,
+                from,
+                to,
+            });
+        }
+
+        // Replicas removed by a reassignment left the ISR on purpose.
+        let left = o
+            .isr
+            .iter()
+            .any(|r| !p.isr.contains(r) && p.replicas.contains(r));
+        if left {
+            changes.push(Change::IsrShrunk {
+                topic: topic.to_string(),
+                partition: p.id,
+                from: sorted(&o.isr),
+                to: sorted(&p.isr),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+pub fn metadata(brokers: &[i32], topics: &[(&str, usize)]) -> ClusterMetadata {
+    use crate::kafka::metadata::classify::Classifier;
+    use crate::kafka::metadata::{BrokerMetadata, TopicMetadata};
+
+    ClusterMetadata {
+        brokers: brokers
+            .iter()
+            .map(|&id| BrokerMetadata {
+                id,
+                host: "localhost".to_string(),
+                port: 9092,
+            })
+            .collect(),
+        groups: vec![],
+        topics: topics
+            .iter()
+            .map(|&(name, partitions)| TopicMetadata {
+                name: name.to_string(),
+                category: Classifier::default().topic(name),
+                partitions: (0..partitions as i32)
+                    .map(|id| PartitionMetadata {
+                        id,
+                        leader: 1,
+                        replicas: vec![1],
+                        isr: vec![1],
+                        error: None,
+                        low_watermark: None,
+                        high_watermark: None,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+fn partition(id: i32, replicas: &[i32], isr: &[i32]) -> PartitionMetadata {
+    PartitionMetadata {
+        id,
+        leader: replicas[0],
+        replicas: replicas.to_vec(),
+        isr: isr.to_vec(),
+        error: None,
+        low_watermark: None,
+        high_watermark: None,
+    }
+}
+
+#[test]
+fn it_diffs_topics_brokers_and_groups() {
+    use crate::kafka::metadata::GroupMetadata;
+
+    let group = |name: &str, state: &str| GroupMetadata {
+        name: name.to_string(),
+        state: state.to_string(),
+        members: vec![],
+        managed_by_seekr: false,
+    };
+    let mut before = metadata(&[1, 2], &[("orders", 3), ("payments", 1), ("legacy", 1)]);
+    before.groups = vec![group("billing", "Stable"), group("audit", "Empty")];
+    let mut after = metadata(&[1, 3], &[("orders", 6), ("payments", 1), ("refunds", 2)]);
+    after.groups = vec![
+        group("billing", "PreparingRebalance"),
+        group("new", "Stable"),
+    ];
+
+    assert_eq!(
+        diff(&before, &after),
+        vec![
+            Change::PartitionsChanged {
+                topic: "orders".to_string(),
+                from: 3,
+                to: 6
+            },
+            Change::TopicCreated {
+                topic: "refunds".to_string(),
+                partitions: 2
+            },
+            Change::TopicDeleted {
+                topic: "legacy".to_string()
+            },
+            Change::BrokerAdded { broker: 3 },
+            Change::BrokerRemoved { broker: 2 },
+            Change::GroupStateChanged {
+                group: "billing".to_string(),
+                from: "Stable".to_string(),
+                to: "PreparingRebalance".to_string()
+            },
+        ]
+    );
+    assert!(diff(&after, &after).is_empty());
+}
+
+#[test]
+fn it_ignores_watermarks_leaders_and_replica_order() {
+    let mut before = metadata(&[1, 2], &[("orders", 1)]);
+    before.topics[0].partitions[0] = partition(0, &[1, 2], &[1, 2]);
+    let mut after = before.clone();
+    let p = &mut after.topics[0].partitions[0];
+    *p = partition(0, &[2, 1], &[2, 1]);
+    p.low_watermark = Some(3);
+    p.high_watermark = Some(17);
+
+    assert!(diff(&before, &after).is_empty());
+}
+
+#[test]
+fn it_diffs_reassigned_replicas_and_shrunk_isrs() {
+    let mut before = metadata(&[1, 2, 3, 4], &[("orders", 3)]);
+    before.topics[0].partitions = vec![
+        partition(0, &[1, 2, 3], &[1, 2, 3]),
+        partition(1, &[2, 3, 1], &[2, 3, 1]),
+        partition(2, &[3, 1, 2], &[3, 1, 2]),
+    ];
+    let mut after = before.clone();
+    after.topics[0].partitions = vec![
+        // Partition ids stay, partition 0 moves from broker 3 to 4.
+        partition(0, &[1, 2, 4], &[1, 2, 4]),
+        // Broker 1 falls behind.
+        partition(1, &[2, 3, 1], &[2, 3]),
+        // Being moved to broker 4, which isn't caught up yet.
+        partition(2, &[3, 1, 2, 4], &[3, 1, 2]),
+    ];
+
+    assert_eq!(
+        diff(&before, &after),
+        vec![
+            Change::ReplicasReassigned {
+                topic: "orders".to_string(),
+                partition: 0,
+                from: vec![1, 2, 3],
+                to: vec![1, 2, 4]
+            },
+            Change::IsrShrunk {
+                topic: "orders".to_string(),
+                partition: 1,
+                from: vec![1, 2, 3],
+                to: vec![2, 3]
+            },
+            Change::ReplicasReassigned {
+                topic: "orders".to_string(),
+                partition: 2,
+                from: vec![1, 2, 3],
+                to: vec![1, 2, 3, 4]
+            },
+        ]
+    );
+
+    // A reassigned partition whose kept replicas fell behind shrank too.
+    after.topics[0].partitions[0] = partition(0, &[1, 2, 4], &[1, 4]);
+    let changes = diff(&before, &after);
+    assert_eq!(changes.len(), 4);
+    assert_eq!(
+        changes[1],
+        Change::IsrShrunk {
+            topic: "orders".to_string(),
+            partition: 0,
+            from: vec![1, 2, 3],
+            to: vec![1, 4]
+        }
+    );
+}
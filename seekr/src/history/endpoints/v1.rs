@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::clusters::store::ClusterStore;
+use crate::history::event::HistoryEntry;
+use crate::history::store::HistoryStore;
+use crate::ids::ClusterId;
+
+/// Default number of history entries returned.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_history);
+}
+
+#[get("/{id}/history")]
+async fn get_history(
+    path: Path<ClusterId>,
+    query: Query<HistoryQuery>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    hs: Data<Arc<dyn HistoryStore + Send + Sync>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    info!("Fetching metadata history for cluster with id {}", id);
+
+    match cs.get(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+    match hs.list(id, limit).await {
+        Ok(events) => HttpResponse::Ok().json(HistoryResponse { events }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    events: Vec<HistoryEntry>,
+}
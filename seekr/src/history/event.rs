@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ids::ClusterId;
+
+use super::diff::Change;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HistoryEvent {
+    /// A change observed between two consecutive polls.
+    Change { change: Change },
+
+    /// A window in which the cluster was not observed, e.g. while the server
+    /// was down. The changes are reconstructed from the snapshots on either
+    /// side of the window, so their order and timing within it are unknown.
+    Gap {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        reconstructed: bool,
+        changes: Vec<Change>,
+    },
+}
+
+/// An event in the metadata history of a cluster.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryEntry {
+    /// The unique id of the entry, which also orders the history of a cluster.
+    pub id: i64,
+
+    pub cluster_id: ClusterId,
+
+    /// Represents the point in time in UTC Epoch time, when the event was recorded.
+    pub recorded_at: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub event: HistoryEvent,
+}
+
+impl HistoryEntry {
+    pub fn new(cluster_id: ClusterId, recorded_at: DateTime<Utc>, event: HistoryEvent) -> Self {
+        HistoryEntry {
+            id: 0,
+            cluster_id,
+            recorded_at,
+            event,
+        }
+    }
+}
+
+/// The last metadata observed for a cluster.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Snapshot {
+    pub cluster_id: ClusterId,
+    pub observed_at: DateTime<Utc>,
+    pub metadata: crate::kafka::metadata::ClusterMetadata,
+}
+
+#[test]
+fn it_renders_gap_events_distinctly() {
+    let at = chrono::TimeZone::with_ymd_and_hms(&Utc, 2022, 10, 1, 12, 0, 0).unwrap();
+    let entry = HistoryEntry::new(
+        ClusterId(1),
+        at,
+        HistoryEvent::Gap {
+            from: at,
+            to: at,
+            reconstructed: true,
+            changes: vec![Change::TopicDeleted {
+                topic: "orders".to_string(),
+            }],
+        },
+    );
+
+    let json = serde_json::to_value(&entry).unwrap();
+    assert_eq!(json["type"], "gap");
+    assert_eq!(json["reconstructed"], true);
+    assert_eq!(json["changes"][0]["change"], "topic_deleted");
+    assert_eq!(serde_json::from_value::<HistoryEntry>(json).unwrap(), entry);
+}
@@ -0,0 +1,6 @@
+pub mod diff;
+pub mod endpoints;
+pub mod event;
+pub mod notify;
+pub mod recorder;
+pub mod store;
@@ -0,0 +1,55 @@
+use serde::Serialize;
+
+use crate::governance::owner::Owner;
+
+use super::event::HistoryEntry;
+
+/// The log target history notifications are written to.
+pub const NOTIFY_TARGET: &str = "seekr::notifications";
+
+/// A history entry, along with who to contact about its cluster.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Notification {
+    #[serde(flatten)]
+    pub entry: HistoryEntry,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+/// Dispatches history entries to interested parties.
+///
+/// Each entry is dispatched exactly once, so a reconstructed gap results in a
+/// single notification rather than one per change it contains.
+pub trait Notifier {
+    fn notify(&self, notification: &Notification);
+}
+
+/// Writes notifications as JSON lines to the `seekr::notifications` log target.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, notification: &Notification) {
+        match serde_json::to_string(notification) {
+            Ok(line) => info!(target: NOTIFY_TARGET, "{}", line),
+            Err(e) => error!(target: NOTIFY_TARGET, "Unable to serialize notification: {}", e),
+        }
+    }
+}
+
+/// Collects notifications in memory for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryNotifier {
+    pub notifications: std::sync::Mutex<Vec<Notification>>,
+}
+
+#[cfg(test)]
+impl Notifier for MemoryNotifier {
+    fn notify(&self, notification: &Notification) {
+        self.notifications
+            .lock()
+            .unwrap()
+            .push(notification.clone());
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+
+use crate::errors::AnyError;
+use crate::governance::owner::Owner;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::ClusterMetadata;
+
+use super::diff::diff;
+use super::event::{HistoryEntry, HistoryEvent, Snapshot};
+use super::notify::{Notification, Notifier};
+use super::store::HistoryStore;
+
+/// Records the metadata history of clusters from successive polls.
+pub struct HistoryRecorder {
+    store: Arc<dyn HistoryStore + Send + Sync>,
+    notifier: Arc<dyn Notifier + Send + Sync>,
+    /// The metadata observed on the previous poll of each cluster.
+    last: Mutex<HashMap<ClusterId, ClusterMetadata>>,
+    /// The owner of each cluster, attached to its notifications.
+    owners: Mutex<HashMap<ClusterId, Owner>>,
+}
+
+impl HistoryRecorder {
+    pub fn new(
+        store: Arc<dyn HistoryStore + Send + Sync>,
+        notifier: Arc<dyn Notifier + Send + Sync>,
+    ) -> Self {
+        Self {
+            store,
+            notifier,
+            last: Mutex::new(HashMap::new()),
+            owners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a poll of the cluster, returning the entries appended to its history.
+    ///
+    /// The first poll after startup is compared against the persisted
+    /// snapshot. When the cluster went unobserved for at least one poll
+    /// interval, the difference is recorded as a single reconstructed gap
+    /// event instead of individual changes.
+    pub async fn record(
+        &self,
+        id: ClusterId,
+        metadata: &ClusterMetadata,
+        now: DateTime<Utc>,
+        interval: Duration,
+    ) -> Result<Vec<HistoryEntry>, AnyError> {
+        let mut last = self.last.lock().await;
+
+        let events = match last.get(&id) {
+            Some(previous) => changes(previous, metadata),
+            None => match self.store.snapshot(id).await? {
+                Some(snapshot) => {
+                    let unobserved =
+                        now - snapshot.observed_at - chrono::Duration::from_std(interval)?;
+                    if unobserved >= chrono::Duration::from_std(interval)? {
+                        vec![HistoryEvent::Gap {
+                            from: snapshot.observed_at,
+                            to: now,
+                            reconstructed: true,
+                            changes: diff(&snapshot.metadata, metadata),
+                        }]
+                    } else {
+                        changes(&snapshot.metadata, metadata)
+                    }
+                }
+                None => vec![],
+            },
+        };
+
+        let entries = events
+            .into_iter()
+            .map(|e| HistoryEntry::new(id, now, e))
+            .collect();
+        let entries = self.store.append(entries).await?;
+
+        let snapshot = Snapshot {
+            cluster_id: id,
+            observed_at: now,
+            metadata: metadata.clone(),
+        };
+        self.store.put_snapshot(snapshot).await?;
+        last.insert(id, metadata.clone());
+
+        let owner = self.owners.lock().await.get(&id).cloned();
+        for entry in &entries {
+            self.notifier.notify(&Notification {
+                entry: entry.clone(),
+                owner: owner.clone(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Set who notifications about the cluster are routed to.
+    pub async fn set_owner(&self, id: ClusterId, owner: Option<Owner>) {
+        let mut owners = self.owners.lock().await;
+        match owner {
+            Some(owner) => owners.insert(id, owner),
+            None => owners.remove(&id),
+        };
+    }
+
+    /// Forget the cluster, e.g. once it was removed.
+    pub async fn forget(&self, id: ClusterId) {
+        self.last.lock().await.remove(&id);
+        self.owners.lock().await.remove(&id);
+    }
+}
+
+fn changes(before: &ClusterMetadata, after: &ClusterMetadata) -> Vec<HistoryEvent> {
+    diff(before, after)
+        .into_iter()
+        .map(|change| HistoryEvent::Change { change })
+        .collect()
+}
+
+#[cfg(test)]
+fn recorder(
+    store: Arc<super::store::MemoryHistoryStore>,
+) -> (HistoryRecorder, Arc<super::notify::MemoryNotifier>) {
+    let notifier = Arc::new(super::notify::MemoryNotifier::default());
+    (HistoryRecorder::new(store, notifier.clone()), notifier)
+}
+
+#[cfg(test)]
+const INTERVAL: Duration = Duration::from_secs(30);
+
+#[tokio::test]
+async fn it_reconstructs_gaps_after_downtime() {
+    use super::diff::{metadata, Change};
+
+    let store = Arc::new(super::store::MemoryHistoryStore::default());
+    let before = metadata(&[1], &[("orders", 3), ("legacy", 1)]);
+    let after = metadata(&[1], &[("orders", 6), ("refunds", 2)]);
+    let t0 = Utc::now();
+
+    let (first, _) = recorder(store.clone());
+    assert!(first
+        .record(ClusterId(1), &before, t0, INTERVAL)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The server was down for an hour, and restarts with an empty memory.
+    let t1 = t0 + chrono::Duration::hours(1);
+    let (restarted, notifier) = recorder(store.clone());
+    let entries = restarted
+        .record(ClusterId(1), &after, t1, INTERVAL)
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].event,
+        HistoryEvent::Gap {
+            from: t0,
+            to: t1,
+            reconstructed: true,
+            changes: vec![
+                Change::PartitionsChanged {
+                    topic: "orders".to_string(),
+                    from: 3,
+                    to: 6
+                },
+                Change::TopicCreated {
+                    topic: "refunds".to_string(),
+                    partitions: 2
+                },
+                Change::TopicDeleted {
+                    topic: "legacy".to_string()
+                },
+            ],
+        }
+    );
+
+    // A single notification covers the whole gap.
+    let notifications = notifier.notifications.lock().unwrap().clone();
+    let notified = notifications.into_iter().map(|n| n.entry);
+    assert_eq!(notified.collect::<Vec<_>>(), entries);
+    assert_eq!(
+        store.list(ClusterId(1), 10).await.unwrap(),
+        entries,
+        "the gap is persisted in the history"
+    );
+}
+
+#[tokio::test]
+async fn it_records_short_restarts_as_changes() {
+    use super::diff::metadata;
+
+    let store = Arc::new(super::store::MemoryHistoryStore::default());
+    let t0 = Utc::now();
+
+    let (first, _) = recorder(store.clone());
+    first
+        .record(
+            ClusterId(1),
+            &metadata(&[1], &[("orders", 3)]),
+            t0,
+            INTERVAL,
+        )
+        .await
+        .unwrap();
+
+    // Restarting within a poll interval leaves no unobserved window.
+    let t1 = t0 + chrono::Duration::seconds(50);
+    let after = metadata(&[1, 2], &[("orders", 3), ("refunds", 1)]);
+    let (restarted, notifier) = recorder(store.clone());
+    let entries = restarted
+        .record(ClusterId(1), &after, t1, INTERVAL)
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 2);
+    assert!(entries
+        .iter()
+        .all(|e| matches!(e.event, HistoryEvent::Change { .. })));
+    assert_eq!(notifier.notifications.lock().unwrap().len(), 2);
+
+    // Unchanged polls record nothing.
+    let t2 = t1 + chrono::Duration::seconds(30);
+    let entries = restarted
+        .record(ClusterId(1), &after, t2, INTERVAL)
+        .await
+        .unwrap();
+    assert!(entries.is_empty());
+}
+
+#[tokio::test]
+async fn it_attaches_the_owner_to_notifications() {
+    use super::diff::metadata;
+
+    let store = Arc::new(super::store::MemoryHistoryStore::default());
+    let (recorder, notifier) = recorder(store);
+    let owner = Owner {
+        email: Some("payments@example.com".to_string()),
+        slack_channel: Some("#payments-oncall".to_string()),
+        ..Owner::team("payments")
+    };
+    recorder.set_owner(ClusterId(1), Some(owner.clone())).await;
+
+    let t0 = Utc::now();
+    let polls = [
+        metadata(&[1], &[("orders", 3)]),
+        metadata(&[1], &[("orders", 6)]),
+    ];
+    for (i, m) in polls.iter().enumerate() {
+        let at = t0 + chrono::Duration::seconds(30 * i as i64);
+        recorder
+            .record(ClusterId(1), m, at, INTERVAL)
+            .await
+            .unwrap();
+    }
+
+    let notifications = notifier.notifications.lock().unwrap().clone();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].owner, Some(owner));
+
+    // The owner is part of the dispatched payload, next to the entry.
+    let payload = serde_json::to_value(&notifications[0]).unwrap();
+    assert_eq!(payload["cluster_id"], 1);
+    assert_eq!(payload["type"], "change");
+    assert_eq!(payload["owner"]["team"], "payments");
+    assert_eq!(payload["owner"]["slack_channel"], "#payments-oncall");
+
+    // Clusters without an owner are notified without one.
+    recorder.set_owner(ClusterId(1), None).await;
+    let at = t0 + chrono::Duration::seconds(60);
+    let m = metadata(&[1], &[("orders", 9)]);
+    recorder
+        .record(ClusterId(1), &m, at, INTERVAL)
+        .await
+        .unwrap();
+
+    let notifications = notifier.notifications.lock().unwrap();
+    let payload = serde_json::to_value(&notifications[1]).unwrap();
+    assert!(payload.get("owner").is_none());
+}
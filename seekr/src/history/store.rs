@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+
+use crate::errors::AnyError;
+use crate::id;
+use crate::ids::ClusterId;
+use crate::meilisearch::{self, MeilisearchConfig};
+use crate::schemas;
+
+use super::event::{HistoryEntry, Snapshot};
+
+#[async_trait]
+pub trait HistoryStore {
+    /// Append entries to their cluster's history, returning them with their allocated ids.
+    async fn append(&self, entries: Vec<HistoryEntry>) -> Result<Vec<HistoryEntry>, AnyError>;
+
+    /// The most recent `limit` entries of a cluster's history, newest first.
+    async fn list(&self, id: ClusterId, limit: usize) -> Result<Vec<HistoryEntry>, AnyError>;
+
+    /// The last persisted snapshot of a cluster.
+    async fn snapshot(&self, id: ClusterId) -> Result<Option<Snapshot>, AnyError>;
+
+    async fn put_snapshot(&self, snapshot: Snapshot) -> Result<(), AnyError>;
+}
+
+pub const INDEX_NAME: &str = "metadata_history";
+pub const SNAPSHOTS_INDEX_NAME: &str = "metadata_snapshots";
+
+pub struct MSHistoryStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+    /// A Distributed Unique ID generator.
+    generator: Arc<id::Generator>,
+}
+
+impl MSHistoryStore {
+    pub async fn new(client: Arc<Client>, generator: Arc<id::Generator>) -> Self {
+        for (name, key) in [(INDEX_NAME, "id"), (SNAPSHOTS_INDEX_NAME, "cluster_id")] {
+            if let Ok(task) = client.clone().create_index(name, Some(key)).await {
+                task.wait_for_completion(&client, None, None).await.unwrap();
+            }
+        }
+
+        let index = client.index(INDEX_NAME);
+        if let Err(e) = index.set_filterable_attributes(["cluster_id"]).await {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                INDEX_NAME, e
+            );
+        }
+        if let Err(e) = index.set_sortable_attributes(["id"]).await {
+            warn!("Unable to set sortable attributes on {}: {}", INDEX_NAME, e);
+        }
+
+        Self { client, generator }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+
+    fn snapshots(&self) -> Index {
+        self.client.index(SNAPSHOTS_INDEX_NAME)
+    }
+}
+
+#[async_trait]
+impl HistoryStore for MSHistoryStore {
+    async fn append(&self, entries: Vec<HistoryEntry>) -> Result<Vec<HistoryEntry>, AnyError> {
+        if entries.is_empty() {
+            return Ok(entries);
+        }
+
+        let entries = entries
+            .into_iter()
+            .map(|e| {
+                let id = self.generator.next_id()?;
+                Ok(HistoryEntry { id, ..e })
+            })
+            .collect::<Result<Vec<_>, AnyError>>()?;
+
+        let versioned = entries
+            .iter()
+            .map(|e| schemas::HISTORY.versioned(e))
+            .collect::<Vec<_>>();
+        self.index()
+            .add_or_replace(&versioned, Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(entries)
+    }
+
+    async fn list(&self, id: ClusterId, limit: usize) -> Result<Vec<HistoryEntry>, AnyError> {
+        let results = self
+            .index()
+            .search()
+            .with_filter(&format!("cluster_id = {}", id))
+            .with_sort(&["id:desc"])
+            .with_limit(limit)
+            .execute::<HistoryEntry>()
+            .await?;
+
+        Ok(results.hits.into_iter().map(|h| h.result).collect())
+    }
+
+    async fn snapshot(&self, id: ClusterId) -> Result<Option<Snapshot>, AnyError> {
+        let snapshot = self
+            .snapshots()
+            .get_document::<Snapshot>(&id.to_string())
+            .await;
+
+        meilisearch::found(snapshot)
+    }
+
+    async fn put_snapshot(&self, snapshot: Snapshot) -> Result<(), AnyError> {
+        self.snapshots()
+            .add_or_replace(
+                &[schemas::SNAPSHOTS.versioned(&snapshot)],
+                Some("cluster_id"),
+            )
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// An in-memory history used to exercise the recorder in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryHistoryStore {
+    entries: tokio::sync::RwLock<Vec<HistoryEntry>>,
+    snapshots: tokio::sync::RwLock<std::collections::HashMap<ClusterId, Snapshot>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl HistoryStore for MemoryHistoryStore {
+    async fn append(&self, entries: Vec<HistoryEntry>) -> Result<Vec<HistoryEntry>, AnyError> {
+        let mut stored = self.entries.write().await;
+        let mut appended = Vec::with_capacity(entries.len());
+        for e in entries {
+            let entry = HistoryEntry {
+                id: stored.len() as i64 + 1,
+                ..e
+            };
+            stored.push(entry.clone());
+            appended.push(entry);
+        }
+        Ok(appended)
+    }
+
+    async fn list(&self, id: ClusterId, limit: usize) -> Result<Vec<HistoryEntry>, AnyError> {
+        let entries = self.entries.read().await;
+        Ok(entries
+            .iter()
+            .rev()
+            .filter(|e| e.cluster_id == id)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn snapshot(&self, id: ClusterId) -> Result<Option<Snapshot>, AnyError> {
+        Ok(self.snapshots.read().await.get(&id).cloned())
+    }
+
+    async fn put_snapshot(&self, snapshot: Snapshot) -> Result<(), AnyError> {
+        self.snapshots
+            .write()
+            .await
+            .insert(snapshot.cluster_id, snapshot);
+        Ok(())
+    }
+}
+
+pub async fn init_history_store(config: &MeilisearchConfig) -> Arc<dyn HistoryStore + Send + Sync> {
+    Arc::new(MSHistoryStore::new(config.client(), id::generator()).await)
+}
@@ -0,0 +1,63 @@
+use actix_web::web::{Path, ServiceConfig};
+use actix_web::{get, HttpResponse};
+use serde::Serialize;
+
+use crate::errors::{ApiError, ErrorBody};
+use crate::id::{self, IdParts};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(decompose_id);
+}
+
+#[get("/{id}")]
+async fn decompose_id(path: Path<String>) -> Result<HttpResponse, ApiError> {
+    let raw = path.into_inner();
+    let id = raw
+        .parse::<i64>()
+        .map_err(|_| ApiError::invalid(format!("Id '{}' is not a number", raw)))?;
+
+    Ok(match id::decompose(id) {
+        Ok(parts) => HttpResponse::Ok().json(DecomposeIdResponse { id, parts }),
+        Err(e) => {
+            HttpResponse::UnprocessableEntity().json(ErrorBody::new("invalid_id", e.to_string()))
+        }
+    })
+}
+
+#[derive(Serialize)]
+struct DecomposeIdResponse {
+    id: i64,
+    #[serde(flatten)]
+    parts: IdParts,
+}
+
+#[actix_web::test]
+async fn it_decomposes_ids() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    let app = test::init_service(App::new().configure(crate::server::routes)).await;
+    let get = |id: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/ids/{}", id))
+            .to_request()
+    };
+
+    let generated = id::Generator::new(4, 2).next_id().unwrap();
+    let body: Value = test::call_and_read_body_json(&app, get(&generated.to_string())).await;
+    assert_eq!(body["id"], generated);
+    assert_eq!(body["worker_id"], 4);
+    assert_eq!(body["datacenter_id"], 2);
+    assert_eq!(body["sequence"], 0);
+    assert!(body["timestamp"].is_string());
+
+    for (raw, status) in [
+        ("-5", StatusCode::UNPROCESSABLE_ENTITY),
+        ("9223372036854775807", StatusCode::UNPROCESSABLE_ENTITY),
+        ("orders", StatusCode::BAD_REQUEST),
+    ] {
+        let res = test::call_service(&app, get(raw)).await;
+        assert_eq!(res.status(), status, "{}", raw);
+    }
+}
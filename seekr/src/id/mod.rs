@@ -0,0 +1,533 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::errors::AnyError;
+
+pub mod endpoints;
+
+// The ID as a whole is a 63 bit integer stored in an int64
+// 41 bits are used to store a timestamp with millisecond precision, using a custom epoch.
+// 10 bits are used to store a node/datacenter id - a range from 0 through 1023.
+// 12 bits are used to store a sequence number - a range from 0 through 4095.
+// +----------------------------------------------------------------------------------------------+
+// | 1 Bit Unused | 41 Bit Timestamp |  5 Bit NodeID  | 5 Bit DatacenterID |   12 Bit Sequence ID |
+// +----------------------------------------------------------------------------------------------+;
+const SEQUENCE_BITS: i64 = 12;
+const MAX_SEQUENCE: i64 = -1i64 ^ (-1i64 << SEQUENCE_BITS);
+const TIME_SHIFT: i64 = 22; // TIME_SHIFT = NODE_BITS + SEQUENCE_BITS
+const NODE_SHIFT: i64 = 17; // NODE_SHIFT = DATACENTER_BITS + SEQUENCE_BITS
+const DATA_SHIFT: i64 = 12; // DATA_SHIFT = SEQUENCE_BITS
+
+/// The largest worker and datacenter ids, 5 bits each.
+pub const MAX_WORKER_ID: i64 = 31;
+pub const MAX_DATACENTER_ID: i64 = 31;
+
+/// Clock regressions shorter than this are waited out, longer ones fail.
+pub const MAX_BACKWARDS_WAIT: Duration = Duration::from_millis(10);
+
+// Service sentinel date: 2020-05-20 08:00:00 +0800 CST
+const EPOCH: i64 = 1589923200000;
+
+/// Which worker of which datacenter an instance generates ids as. Instances
+/// sharing both generate the same ids.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IdConfig {
+    pub worker_id: i64,
+    pub datacenter_id: i64,
+}
+
+impl IdConfig {
+    /// Both ids have to fit their bits of the ids generated.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0..=MAX_WORKER_ID).contains(&self.worker_id) {
+            return Err(format!(
+                "worker id must be between 0 and {}, not {}",
+                MAX_WORKER_ID, self.worker_id
+            ));
+        }
+        if !(0..=MAX_DATACENTER_ID).contains(&self.datacenter_id) {
+            return Err(format!(
+                "datacenter id must be between 0 and {}, not {}",
+                MAX_DATACENTER_ID, self.datacenter_id
+            ));
+        }
+        Ok(())
+    }
+}
+
+static GENERATOR: OnceLock<Arc<Generator>> = OnceLock::new();
+
+/// Generate the ids of this process as the configured worker from then on.
+///
+/// Called once at startup, before any id is generated; fails when the config
+/// is invalid or ids were generated already.
+pub fn install(config: IdConfig) -> Result<(), AnyError> {
+    config.validate()?;
+    let generator = Generator::new(config.worker_id, config.datacenter_id);
+    GENERATOR
+        .set(Arc::new(generator))
+        .map_err(|_| "ids were generated before the worker id was configured".into())
+}
+
+/// The generator of this process, worker 0 of datacenter 0 unless installed.
+pub fn generator() -> Arc<Generator> {
+    GENERATOR
+        .get_or_init(|| Arc::new(Generator::new(0, 0)))
+        .clone()
+}
+
+/// What an id generated by a `Generator` is made of.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct IdParts {
+    /// When the id was generated, to the millisecond.
+    pub timestamp: DateTime<Utc>,
+    pub datacenter_id: i64,
+    pub worker_id: i64,
+
+    /// The id's place among those generated by the worker in its millisecond.
+    pub sequence: i64,
+}
+
+/// An id that no generator could have generated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidId(pub String);
+
+impl fmt::Display for InvalidId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidId {}
+
+/// How far ahead of this instance's clock the ids of other instances are
+/// still taken as generated.
+const MAX_CLOCK_SKEW_MS: i64 = 60_000;
+
+/// Split an id back into what it was generated from, see `Generator::next_id`.
+///
+/// Ids from before the epoch, negative ones, or from the future don't come
+/// from a generator.
+pub fn decompose(id: i64) -> Result<IdParts, InvalidId> {
+    if id < 0 {
+        return Err(InvalidId(format!("Id {} is from before the epoch", id)));
+    }
+
+    let millis = (id >> TIME_SHIFT) + EPOCH;
+    if millis > Utc::now().timestamp_millis() + MAX_CLOCK_SKEW_MS {
+        return Err(InvalidId(format!("Id {} is from the future", id)));
+    }
+    let timestamp = DateTime::from_timestamp_millis(millis)
+        .ok_or_else(|| InvalidId(format!("Id {} has no valid timestamp", id)))?;
+
+    Ok(IdParts {
+        timestamp,
+        datacenter_id: (id >> DATA_SHIFT) & MAX_DATACENTER_ID,
+        worker_id: (id >> NODE_SHIFT) & MAX_WORKER_ID,
+        sequence: id & MAX_SEQUENCE,
+    })
+}
+
+/// The clock went back further than the generator waits out, see `MAX_BACKWARDS_WAIT`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClockMovedBackwards {
+    pub by_ms: i64,
+}
+
+impl fmt::Display for ClockMovedBackwards {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Clock moved backwards by {}ms, no ids are generated until it catches up",
+            self.by_ms
+        )
+    }
+}
+
+impl std::error::Error for ClockMovedBackwards {}
+
+#[derive(Debug)]
+struct State {
+    last_timestamp: i64,
+    sequence: i64,
+}
+
+/// A Distributed Unique ID generator.
+pub struct Generator {
+    node_id: i64,
+    datacenter_id: i64,
+    mu: Mutex<State>,
+    clock: Box<dyn Fn() -> i64 + Send + Sync>,
+    wait: Box<dyn Fn(Duration) + Send + Sync>,
+}
+
+impl Generator {
+    pub fn new(node_id: i64, datacenter_id: i64) -> Self {
+        Generator {
+            node_id,
+            datacenter_id,
+            mu: Mutex::new(State {
+                last_timestamp: 0,
+                sequence: 0,
+            }),
+            clock: Box::new(|| Utc::now().timestamp_millis()),
+            wait: Box::new(wait),
+        }
+    }
+
+    /// Read the time in milliseconds from `clock` rather than the system's.
+    pub fn with_clock(mut self, clock: impl Fn() -> i64 + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Wait out clock regressions with `wait` rather than by sleeping.
+    pub fn with_wait(mut self, wait: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.wait = Box::new(wait);
+        self
+    }
+
+    /// Each time you generate an ID:
+    /// - A timestamp with millisecond precision is stored using 41 bits of the ID.
+    /// - The NodeID and DatacenterIDs are added in subsequent bits.
+    /// - the Sequence Number is added, starting at 0 and incrementing for each ID generated in the same millisecond.
+    /// - If enough IDs are generated in the same millisecond, causing the sequence to overfill, then the function will pause until the next millisecond.
+    /// - If the clock went back less than `MAX_BACKWARDS_WAIT`, the function pauses until it's caught up, and fails otherwise.
+    pub fn next_id(&self) -> Result<i64, ClockMovedBackwards> {
+        let mut waited = false;
+        let (mut state, mut now) = loop {
+            let state = self.mu.lock().unwrap();
+            let now = (self.clock)();
+            if now >= state.last_timestamp {
+                break (state, now);
+            }
+
+            let behind = state.last_timestamp - now;
+            if waited || behind >= MAX_BACKWARDS_WAIT.as_millis() as i64 {
+                return Err(ClockMovedBackwards { by_ms: behind });
+            }
+
+            // Waited out without the lock, other callers behind too wait alike.
+            drop(state);
+            (self.wait)(Duration::from_millis(behind as u64));
+            waited = true;
+        };
+
+        if now == state.last_timestamp {
+            state.sequence = (state.sequence + 1) & MAX_SEQUENCE;
+            if state.sequence == 0 {
+                while now <= state.last_timestamp {
+                    now = (self.clock)();
+                }
+            }
+        } else {
+            state.sequence = 0;
+        }
+
+        state.last_timestamp = now;
+
+        let id = (now - EPOCH) << TIME_SHIFT
+            | self.node_id << NODE_SHIFT
+            | self.datacenter_id << DATA_SHIFT
+            | state.sequence;
+
+        Ok(id)
+    }
+}
+
+/// Block the thread for a clock regression, handing a multi-threaded
+/// runtime's other tasks to its other workers meanwhile.
+fn wait(duration: Duration) {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    match Handle::try_current() {
+        Ok(h) if h.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| std::thread::sleep(duration))
+        }
+        _ => std::thread::sleep(duration),
+    }
+}
+
+/// Ids generated before an insert gives up on finding an unused one.
+pub const MAX_ATTEMPTS: usize = 8;
+
+/// Generated ids checked unused and not yet written.
+///
+/// Generators of instances sharing a worker id hand out the same ids, which
+/// stores writing with `add_or_replace` would silently overwrite. Inserts claim
+/// an id before checking the store for it, so two racing inserts of this
+/// process never both find the same id unused.
+#[derive(Default)]
+pub struct Claims {
+    ids: Mutex<HashSet<i64>>,
+}
+
+/// An id claimed for an insert, released once the insert is written and dropped.
+pub struct Claim<'a> {
+    claims: &'a Claims,
+    pub id: i64,
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        self.claims.ids.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl Claims {
+    /// Claim an id from `next` that `exists` reports unused, regenerating on collisions.
+    pub async fn claim<N, E, F>(&self, mut next: N, exists: E) -> Result<Claim<'_>, AnyError>
+    where
+        N: FnMut() -> Result<i64, AnyError>,
+        E: Fn(i64) -> F,
+        F: Future<Output = Result<bool, AnyError>>,
+    {
+        for _ in 0..MAX_ATTEMPTS {
+            let id = next()?;
+            if !self.ids.lock().unwrap().insert(id) {
+                warn!(
+                    "Generated id {} is being inserted already, regenerating",
+                    id
+                );
+                continue;
+            }
+
+            // Dropped on collisions and errors, releasing the id
+            let claim = Claim { claims: self, id };
+            if exists(id).await? {
+                warn!("Generated id {} is already taken, regenerating", id);
+                continue;
+            }
+            return Ok(claim);
+        }
+
+        Err(format!("no unused id after {} attempts", MAX_ATTEMPTS).into())
+    }
+}
+
+#[test]
+fn it_works() {
+    let generator = Generator::new(0, 0);
+    let id1 = generator.next_id().unwrap();
+    let id2 = generator.next_id().unwrap();
+    println!("id1: {}, id2: {}", id1, id2);
+    assert_ne!(id1, id2);
+}
+
+#[test]
+fn it_really_works() {
+    let generator = Generator::new(1, 1);
+    let mut set = std::collections::HashSet::<i64>::new();
+
+    for _ in 0..1_000_000 {
+        let id = generator.next_id().unwrap();
+        println!("id = {}", id);
+        set.insert(id);
+    }
+
+    assert_eq!(set.len(), 1_000_000)
+}
+
+#[test]
+fn it_waits_out_small_clock_regressions_only() {
+    use std::collections::VecDeque;
+
+    let at = |times: &[i64]| {
+        let times = Mutex::new(times.iter().copied().collect::<VecDeque<_>>());
+        Generator::new(3, 1).with_clock(move || {
+            let mut times = times.lock().unwrap();
+            match times.len() {
+                1 => times[0],
+                _ => times.pop_front().unwrap(),
+            }
+        })
+    };
+
+    // Back 5ms, read again once waited out.
+    let generator = at(&[EPOCH + 1_000, EPOCH + 995, EPOCH + 1_000]);
+    let first = generator.next_id().unwrap();
+    let second = generator.next_id().unwrap();
+    assert!(second > first);
+    assert_eq!(second - first, 1);
+
+    // Still behind once waited out, or back too far to wait.
+    let generator = at(&[EPOCH + 1_000, EPOCH + 995, EPOCH + 998]);
+    generator.next_id().unwrap();
+    assert_eq!(generator.next_id(), Err(ClockMovedBackwards { by_ms: 2 }));
+    let generator = at(&[EPOCH + 1_000, EPOCH + 900]);
+    generator.next_id().unwrap();
+    assert_eq!(generator.next_id(), Err(ClockMovedBackwards { by_ms: 100 }));
+    assert!(generator.next_id().is_err());
+}
+
+#[test]
+fn it_waits_out_clock_regressions_without_holding_others_up() {
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::mpsc;
+
+    // The clock goes back 9ms once, and is caught up for the next reads.
+    let now = Arc::new(AtomicI64::new(EPOCH + 1_000));
+    let clock = now.clone();
+    let (behind, read) = mpsc::channel();
+    let (generated, waited_out) = mpsc::channel::<()>();
+    let (waits, waited_for) = mpsc::channel();
+    let waited_out = Mutex::new(waited_out);
+    let generator = Arc::new(
+        Generator::new(3, 1)
+            .with_clock(move || {
+                let now = clock.load(Ordering::SeqCst);
+                if now < EPOCH + 1_000 {
+                    clock.store(EPOCH + 1_000, Ordering::SeqCst);
+                    behind.send(()).unwrap();
+                }
+                now
+            })
+            // Waited out only once the other caller has generated its id.
+            .with_wait(move |duration| {
+                waits.send(duration).unwrap();
+                waited_out.lock().unwrap().recv().unwrap();
+            }),
+    );
+    let first = generator.next_id().unwrap();
+
+    now.store(EPOCH + 991, Ordering::SeqCst);
+    let waiting = generator.clone();
+    let waiter = std::thread::spawn(move || waiting.next_id().unwrap());
+    read.recv().unwrap();
+    assert_eq!(waited_for.recv().unwrap(), Duration::from_millis(9));
+
+    // Generated while the other caller waits, so first in the millisecond.
+    let meanwhile = generator.next_id().unwrap();
+    generated.send(()).unwrap();
+    let waited = waiter.join().unwrap();
+    assert_eq!((meanwhile - first, waited - first), (1, 2));
+}
+
+#[test]
+fn it_generates_unique_increasing_ids_across_threads() {
+    let generator = Arc::new(Generator::new(MAX_WORKER_ID, 2));
+    let threads = (0..16)
+        .map(|_| {
+            let generator = generator.clone();
+            std::thread::spawn(move || {
+                (0..20_000)
+                    .map(|_| generator.next_id().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut all = HashSet::new();
+    for thread in threads {
+        let ids = thread.join().unwrap();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        for id in ids {
+            assert_eq!((id >> NODE_SHIFT) & MAX_WORKER_ID, MAX_WORKER_ID);
+            assert_eq!((id >> DATA_SHIFT) & MAX_DATACENTER_ID, 2);
+            assert!(all.insert(id), "id {} was generated twice", id);
+        }
+    }
+    assert_eq!(all.len(), 16 * 20_000);
+}
+
+#[test]
+fn it_decomposes_generated_ids() {
+    let generator = Generator::new(5, 3);
+    let first = decompose(generator.next_id().unwrap()).unwrap();
+    let second = decompose(generator.next_id().unwrap()).unwrap();
+
+    assert_eq!((first.worker_id, first.datacenter_id), (5, 3));
+    let drift = Utc::now() - first.timestamp;
+    assert!(drift >= chrono::Duration::zero() && drift < chrono::Duration::seconds(1));
+    assert!(second.timestamp > first.timestamp || second.sequence == first.sequence + 1);
+
+    let parts = decompose((1_000 << TIME_SHIFT) | (2 << NODE_SHIFT) | (1 << DATA_SHIFT) | 7);
+    assert_eq!(
+        parts,
+        Ok(IdParts {
+            timestamp: DateTime::from_timestamp_millis(EPOCH + 1_000).unwrap(),
+            datacenter_id: 1,
+            worker_id: 2,
+            sequence: 7,
+        })
+    );
+
+    assert!(decompose(-1).is_err());
+    assert!(decompose(i64::MAX).is_err());
+}
+
+#[test]
+fn it_validates_worker_and_datacenter_ids() {
+    let config = |worker_id, datacenter_id| IdConfig {
+        worker_id,
+        datacenter_id,
+    };
+    assert_eq!(config(31, 0).validate(), Ok(()));
+    assert_eq!(
+        config(32, 0).validate(),
+        Err("worker id must be between 0 and 31, not 32".to_string())
+    );
+    assert!(config(0, -1).validate().is_err());
+}
+
+#[tokio::test]
+async fn it_keeps_racing_inserts_of_the_same_id_apart() {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    // Two generators sharing a worker id, handing out the same ids.
+    let claims = Arc::new(Claims::default());
+    let written = Arc::new(tokio::sync::Mutex::new(HashMap::<i64, &str>::new()));
+    let insert = |name: &'static str| {
+        let claims = claims.clone();
+        let written = written.clone();
+        async move {
+            let mut ids = [7, 8, 9].into_iter();
+            let exists = |id| {
+                let written = written.clone();
+                async move {
+                    tokio::task::yield_now().await;
+                    Ok::<_, AnyError>(written.lock().await.contains_key(&id))
+                }
+            };
+            let claim = claims
+                .claim(|| Ok(ids.next().unwrap()), exists)
+                .await
+                .unwrap();
+
+            tokio::task::yield_now().await;
+            let previous = written.lock().await.insert(claim.id, name);
+            assert_eq!(previous, None, "{} overwrote id {}", name, claim.id);
+            claim.id
+        }
+    };
+
+    let (a, b) = tokio::join!(
+        tokio::spawn(insert("orders")),
+        tokio::spawn(insert("refunds"))
+    );
+    let (a, b) = (a.unwrap(), b.unwrap());
+    assert_ne!(a, b);
+    assert_eq!(written.lock().await.len(), 2);
+
+    // Released once written, later inserts see the stored ids instead.
+    let c = insert("payments").await;
+    assert_eq!(c, 9);
+    assert!(claims.ids.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn it_gives_up_when_every_id_is_taken() {
+    let claims = Claims::default();
+    let result = claims
+        .claim(|| Ok(1), |_| async { Ok::<_, AnyError>(true) })
+        .await;
+
+    assert!(result.is_err());
+    assert!(claims.ids.lock().unwrap().is_empty());
+}
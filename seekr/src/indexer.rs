@@ -1,30 +1,117 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use std::{collections::HashMap, sync::Arc};
 
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
 
+use crate::assignment::{self, Assignments, INSTANCE_TTL, OWNER_TTL};
+use crate::backend::StoreConfig;
+use crate::changefeed::store::{init_changefeed_store, ChangefeedStore};
+use crate::clusters::cluster::Cluster;
 use crate::clusters::store::{init_cluster_store, ClusterStore};
+use crate::commands::store::{init_command_store, CommandStore};
+use crate::debug::store::{init_debug_store, DebugStore};
 use crate::errors::AnyError;
-use crate::kafka::streams::service::StreamsService;
+use crate::id;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::streams::service::{
+    kafka_consumers, ConsumerFactory, StreamsService, WorkerState,
+};
 use crate::logger;
+use crate::page::Page;
+use crate::settings::Snapshot;
+use crate::shards::store::{init_document_store, DocumentStore};
+use crate::shutdown::Shutdown;
+use crate::standby::lease::{init_lease_store, LeaseStore};
 use crate::subscriptions::store::{init_subscription_store, SubscriptionStore};
+use crate::subscriptions::subscription::Subscription;
 use crate::BANNER;
 
+/// How long an errored worker waits before its first restart.
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest an errored worker waits before it's restarted.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// How long a worker has to run before its restart backoff starts over.
+const RESTART_RESET: Duration = Duration::from_secs(10 * 60);
+
+/// How often an instance sharing subscriptions renews its leases and rebalances.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long the workers get to stop on shutdown, unless configured otherwise.
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often an instance running every subscription lists them for ones
+/// created, updated or deleted, unless configured otherwise.
+pub const SCHEDULER_RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub struct IndexerConfig {
     pub log: logger::Level,
+    pub log_format: logger::Format,
+
+    /// Which backends the stores are kept in, and how to reach them.
+    pub stores: StoreConfig,
+
+    /// The name this indexer shares subscriptions with other indexers under,
+    /// without one it runs every subscription itself.
+    pub instance: Option<String>,
+
+    /// How long the workers get to finish the messages in flight on shutdown.
+    pub stop_timeout: Duration,
+
+    /// How often an indexer without an instance lists the subscriptions to
+    /// start, restart or stop their workers.
+    pub reconcile_interval: Duration,
+
+    /// The settings above, along with where each of them came from.
+    pub settings: Snapshot,
 }
 
 pub async fn run(config: IndexerConfig) -> std::io::Result<()> {
     // Set the default log level
-    logger::init(&config.log);
+    logger::init(&config.log, config.log_format, None);
 
     // Output seekr banner
     info!("{}", BANNER);
     info!("Starting indexer...");
+    info!("{}", config.settings.summary());
+
+    // Generate ids as the configured worker, before any store or request does
+    id::install(config.stores.ids).map_err(std::io::Error::other)?;
 
     // Initialize shared state
-    let clusters = init_cluster_store().await;
-    let subscriptions = init_subscription_store().await;
-    let scheduler = Arc::new(Scheduler::new(clusters.clone(), subscriptions.clone()));
+    let ms = &config.stores.meilisearch;
+    let clusters = init_cluster_store(&config.stores)
+        .await
+        .map_err(std::io::Error::other)?;
+    let subscriptions = init_subscription_store(&config.stores)
+        .await
+        .map_err(std::io::Error::other)?;
+    let changefeed = init_changefeed_store(ms).await;
+    let debug = init_debug_store(ms).await;
+    let commands = init_command_store(ms).await;
+    let documents = init_document_store(ms).await;
+    let mut scheduler = Scheduler::new(
+        clusters.clone(),
+        subscriptions.clone(),
+        changefeed.clone(),
+        debug.clone(),
+        commands.clone(),
+        documents.clone(),
+    );
+    if let Some(instance) = config.instance {
+        scheduler = scheduler.with_leases(instance, init_lease_store(ms).await);
+    }
+    scheduler = scheduler
+        .with_stop_timeout(config.stop_timeout)
+        .with_reconcile_interval(config.reconcile_interval);
+    let scheduler = Arc::new(scheduler);
 
     // Start index scheduler
     let scheduler_clone = scheduler.clone();
@@ -50,12 +137,48 @@ pub async fn run(config: IndexerConfig) -> std::io::Result<()> {
 }
 
 struct State {
-    workers: HashMap<i64, Arc<StreamsService>>,
+    workers: HashMap<SubscriptionId, Worker>,
+}
+
+/// A streams worker, restarted by its supervisor whenever it errors.
+struct Worker {
+    service: Arc<StreamsService>,
+    restarts: Arc<AtomicU64>,
+    supervisor: JoinHandle<()>,
+
+    /// The `updated_at` of the subscription the worker was started with.
+    updated_at: DateTime<Utc>,
+
+    /// Stops the service, or its supervisor while it waits to restart it.
+    sd: Arc<Shutdown>,
+}
+
+impl Worker {
+    /// Stop the worker, aborting it when it takes longer than `timeout`.
+    /// Returns whether it stopped in time.
+    async fn stop(&self, timeout: Duration) -> bool {
+        self.sd.begin();
+        let stopped = tokio::time::timeout(timeout, self.sd.wait_complete())
+            .await
+            .is_ok();
+        self.supervisor.abort();
+        stopped
+    }
 }
 
 pub struct Scheduler {
     cs: Arc<dyn ClusterStore + Send + Sync>,
     ss: Arc<dyn SubscriptionStore + Send + Sync>,
+    fs: Arc<dyn ChangefeedStore + Send + Sync>,
+    ds: Arc<dyn DebugStore + Send + Sync>,
+    qs: Arc<dyn CommandStore + Send + Sync>,
+    xs: Arc<dyn DocumentStore + Send + Sync>,
+    factory: ConsumerFactory,
+    leases: Option<Arc<dyn LeaseStore + Send + Sync>>,
+    instance: String,
+    stop_timeout: Duration,
+    reconcile_interval: Duration,
+    sd: Arc<Shutdown>,
     state: Arc<RwLock<State>>,
 }
 
@@ -63,6 +186,10 @@ impl Scheduler {
     pub fn new(
         cs: Arc<dyn ClusterStore + Send + Sync>,
         ss: Arc<dyn SubscriptionStore + Send + Sync>,
+        fs: Arc<dyn ChangefeedStore + Send + Sync>,
+        ds: Arc<dyn DebugStore + Send + Sync>,
+        qs: Arc<dyn CommandStore + Send + Sync>,
+        xs: Arc<dyn DocumentStore + Send + Sync>,
     ) -> Self {
         let state = State {
             workers: HashMap::new(),
@@ -70,54 +197,704 @@ impl Scheduler {
         Self {
             cs,
             ss,
+            fs,
+            ds,
+            qs,
+            xs,
+            factory: kafka_consumers(),
+            leases: None,
+            instance: String::new(),
+            stop_timeout: STOP_TIMEOUT,
+            reconcile_interval: SCHEDULER_RECONCILE_INTERVAL,
+            sd: Arc::new(Shutdown::new()),
             state: Arc::new(RwLock::new(state)),
         }
     }
 
+    /// Build the workers' consumers with the given factory.
+    pub fn with_factory(mut self, factory: ConsumerFactory) -> Self {
+        self.factory = factory;
+        self
+    }
+
+    /// Give the workers `timeout` to stop on shutdown, after which they're
+    /// aborted with whatever message they're indexing.
+    pub fn with_stop_timeout(mut self, timeout: Duration) -> Self {
+        self.stop_timeout = timeout;
+        self
+    }
+
+    /// List the subscriptions every `interval` to pick up those created,
+    /// updated or deleted since, when not sharing them through leases.
+    pub fn with_reconcile_interval(mut self, interval: Duration) -> Self {
+        self.reconcile_interval = interval;
+        self
+    }
+
+    /// Share the subscriptions with the other indexers holding leases in the
+    /// store, running only those assigned to `instance`.
+    pub fn with_leases(
+        mut self,
+        instance: String,
+        leases: Arc<dyn LeaseStore + Send + Sync>,
+    ) -> Self {
+        self.instance = instance;
+        self.leases = Some(leases);
+        self
+    }
+
     pub async fn start(self: Arc<Self>) -> Result<(), AnyError> {
         debug!("Starting stream scheduler...");
 
-        // Acquire lock to prevent multiple starts
+        self.reconcile().await?;
+        let every = match self.leases {
+            Some(_) => RECONCILE_INTERVAL,
+            None => self.reconcile_interval,
+        };
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = self.sd.wait_begin() => break,
+                }
+                if let Err(e) = self.reconcile().await {
+                    warn!("Unable to reconcile subscriptions - {}", e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Bring the workers in line with the subscriptions, sharing them with the
+    /// other instances when holding leases, or running all of them otherwise.
+    ///
+    /// Holds the state for the whole reconcile, so one overlapping a slow
+    /// start never starts a subscription's worker twice.
+    pub async fn reconcile(&self) -> Result<(), AnyError> {
+        match &self.leases {
+            Some(leases) => self.rebalance(leases.as_ref()).await,
+            None => self.refresh().await,
+        }
+    }
+
+    /// Run a worker for every active subscription, stopping those of
+    /// subscriptions deleted or paused and restarting those of subscriptions
+    /// updated since they started.
+    async fn refresh(&self) -> Result<(), AnyError> {
         let mut state = self.state.write().await;
+        if self.sd.is_shutdown() {
+            return Ok(());
+        }
 
-        // Fetch all subscriptions
-        let subs = self.ss.list(None).await?;
-        let ids = subs.iter().map(|x| x.cluster_id).collect::<Vec<i64>>();
+        // Fetch all subscriptions, except those pending deletion or paused
+        let (subs, paused): (Vec<_>, Vec<_>) = self
+            .ss
+            .list(None, Page::ALL)
+            .await?
+            .into_iter()
+            .filter(|s| !s.is_pending_deletion())
+            .partition(|s| s.state.is_active());
+        let paused = paused.into_iter().map(|s| s.id).collect::<HashSet<_>>();
+        let subs = subs
+            .into_iter()
+            .map(|s| (s.id, s))
+            .collect::<HashMap<_, _>>();
+
+        let stale = state
+            .workers
+            .iter()
+            .filter(|(id, w)| subs.get(*id).map(|s| s.updated_at) != Some(w.updated_at))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in stale {
+            if let Some(worker) = state.workers.remove(&id) {
+                if subs.contains_key(&id) {
+                    info!("Restarting subscription {} with its new config", id);
+                } else if paused.contains(&id) {
+                    info!("Stopping paused subscription {}", id);
+                } else {
+                    info!("Stopping deleted subscription {}", id);
+                }
+                self.retire(id, worker).await;
+            }
+        }
+
+        let missing = subs
+            .into_values()
+            .filter(|s| !state.workers.contains_key(&s.id))
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let ids = missing
+            .iter()
+            .map(|x| x.cluster_id)
+            .collect::<Vec<ClusterId>>();
         let clusters = self
             .cs
-            .list(Some(ids))
+            .list(Some(ids), Page::ALL)
             .await?
-            .iter()
-            .map(|x| (x.id, x.clone()))
+            .into_iter()
+            .map(|c| (c.id, c))
             .collect::<HashMap<_, _>>();
 
-        for sub in subs {
-            // Create StreamService for each subscription
-            let cluster = clusters
-                .get(&sub.cluster_id)
-                .expect("unable to find for sub")
-                .clone();
-            let service = Arc::new(StreamsService::new(cluster, sub.clone()));
+        for sub in missing {
+            match clusters.get(&sub.cluster_id) {
+                Some(cluster) => {
+                    debug!("Starting subscription {}", sub.id);
+                    state
+                        .workers
+                        .insert(sub.id, self.spawn(cluster.clone(), sub));
+                }
+                None => warn!(
+                    "Unable to find cluster {} of subscription {}",
+                    sub.cluster_id, sub.id
+                ),
+            }
+        }
 
-            // Track service
-            state.workers.insert(sub.id, service.clone());
+        Ok(())
+    }
 
-            // Spawn thread in the background
-            tokio::spawn(async move { service.start().await });
+    /// Run the subscriptions assigned to this instance and hand over the
+    /// others, renewing the leases of those it keeps.
+    ///
+    /// A subscription changing hands is only started once its previous owner
+    /// released its lease, or the lease lapsed, so it never runs twice.
+    async fn rebalance(&self, leases: &(dyn LeaseStore + Send + Sync)) -> Result<(), AnyError> {
+        let me = self.instance.as_str();
+        let mut state = self.state.write().await;
+        if self.sd.is_shutdown() {
+            return Ok(());
+        }
+
+        leases
+            .acquire(&assignment::instance_lease(me), me, "", INSTANCE_TTL)
+            .await?;
+        let assignments = Assignments::load(leases).await?;
+        let (subs, paused): (Vec<_>, Vec<_>) = self
+            .ss
+            .list(None, Page::ALL)
+            .await?
+            .into_iter()
+            .filter(|s| !s.is_pending_deletion())
+            .partition(|s| s.state.is_active());
+        let paused = paused.into_iter().map(|s| s.id).collect::<HashSet<_>>();
+        let ids = subs.iter().map(|s| s.id).collect::<Vec<_>>();
+        let assigned = assignments.assign(&ids, &[]);
+        let is_mine =
+            |id: &SubscriptionId| assigned.get(id).cloned().flatten().as_deref() == Some(me);
+
+        // Hand over first, the new owners take over once the leases are released
+        let running = state.workers.keys().copied().collect::<Vec<_>>();
+        for id in running.into_iter().filter(|id| !is_mine(id)) {
+            if let Some(worker) = state.workers.remove(&id) {
+                match paused.contains(&id) {
+                    true => info!("Stopping paused subscription {}", id),
+                    false => info!("Handing over subscription {}", id),
+                }
+                self.retire(id, worker).await;
+                leases.release(&assignment::owner_lease(id), me).await?;
+            }
+        }
+
+        let mine = subs
+            .into_iter()
+            .filter(|s| is_mine(&s.id))
+            .collect::<Vec<_>>();
+        let cluster_ids = mine.iter().map(|s| s.cluster_id).collect::<Vec<_>>();
+        let clusters = self
+            .cs
+            .list(Some(cluster_ids), Page::ALL)
+            .await?
+            .into_iter()
+            .map(|c| (c.id, c))
+            .collect::<HashMap<_, _>>();
+
+        for sub in mine {
+            let lease = leases
+                .acquire(&assignment::owner_lease(sub.id), me, "", OWNER_TTL)
+                .await?;
+            if lease.holder != me {
+                continue;
+            }
+            let started = state.workers.get(&sub.id).map(|w| w.updated_at);
+            if started == Some(sub.updated_at) {
+                continue;
+            }
+            if let Some(worker) = state.workers.remove(&sub.id) {
+                info!("Restarting subscription {} with its new config", sub.id);
+                self.retire(sub.id, worker).await;
+            }
+            match clusters.get(&sub.cluster_id) {
+                Some(cluster) => {
+                    info!("Taking over subscription {}", sub.id);
+                    state
+                        .workers
+                        .insert(sub.id, self.spawn(cluster.clone(), sub));
+                }
+                None => warn!(
+                    "Unable to find cluster {} of subscription {}",
+                    sub.cluster_id, sub.id
+                ),
+            }
         }
 
         Ok(())
     }
 
+    /// Stop a worker taken out of the state, warning when it didn't stop in time.
+    async fn retire(&self, id: SubscriptionId, worker: Worker) {
+        if !worker.stop(self.stop_timeout).await {
+            warn!("Stream service for subscription {} didn't stop in time", id);
+        }
+    }
+
+    /// Create the subscription's worker and supervise it in the background.
+    fn spawn(&self, cluster: Cluster, sub: Subscription) -> Worker {
+        let id = sub.id;
+        let updated_at = sub.updated_at;
+        let sd = Arc::new(Shutdown::new());
+        let service = StreamsService::with_factory(
+            cluster,
+            sub,
+            self.fs.clone(),
+            self.ds.clone(),
+            self.qs.clone(),
+            self.xs.clone(),
+            self.factory.clone(),
+        )
+        .with_shutdown(sd.clone());
+        let service = Arc::new(service);
+        let restarts = Arc::new(AtomicU64::new(0));
+        let supervisor = tokio::spawn(supervise(id, service.clone(), restarts.clone(), sd.clone()));
+
+        Worker {
+            service,
+            restarts,
+            supervisor,
+            updated_at,
+            sd,
+        }
+    }
+
+    /// The subscriptions this instance runs workers for.
+    pub async fn subscriptions(&self) -> Vec<SubscriptionId> {
+        let state = self.state.read().await;
+        let mut ids = state.workers.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    /// How many times the subscription's worker was restarted.
+    pub async fn restarts(&self, id: SubscriptionId) -> Option<u64> {
+        let state = self.state.read().await;
+        let worker = state.workers.get(&id)?;
+        Some(worker.restarts.load(Ordering::SeqCst))
+    }
+
+    /// The worker of the subscription, if it has one.
+    pub async fn worker(&self, id: SubscriptionId) -> Option<Arc<StreamsService>> {
+        let state = self.state.read().await;
+        state.workers.get(&id).map(|w| w.service.clone())
+    }
+
+    /// The state of the worker of each subscription this instance runs.
+    pub async fn status(&self) -> BTreeMap<SubscriptionId, WorkerState> {
+        let state = self.state.read().await;
+        let statuses = state
+            .workers
+            .iter()
+            .map(|(id, w)| async move { (*id, w.service.status().await.state) });
+        join_all(statuses).await.into_iter().collect()
+    }
+
+    /// Stop every worker, once the messages in flight are committed. Workers
+    /// still running after the stop timeout are aborted, logging which.
     pub async fn stop(self: Arc<Self>) {
         debug!("Stopping streams scheduler...");
-        debug!("Streams scheduler shutdown has been initiated...");
+        self.sd.begin();
 
-        let state = self.state.read().await;
-        let workers = state.workers.values();
+        // No worker is started once the shutdown began.
+        let mut state = self.state.write().await;
+        let workers = state.workers.drain().collect::<Vec<_>>();
+        drop(state);
+        debug!("Streams scheduler shutdown has been initiated...");
 
-        todo!("shutdown workers");
+        // Stopped side by side, so the timeout bounds the shutdown as a whole.
+        let timeout = self.stop_timeout;
+        let stopped = workers
+            .iter()
+            .map(|(id, w)| async move { (*id, w.stop(timeout).await) });
+        let mut pending = join_all(stopped)
+            .await
+            .into_iter()
+            .filter(|(_, stopped)| !stopped)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        if !pending.is_empty() {
+            pending.sort();
+            warn!(
+                "Stream services of subscriptions {:?} didn't stop within {:?}, aborted them",
+                pending, timeout
+            );
+        }
 
         debug!("Streams scheduler shutdown has been completed...");
     }
 }
+
+/// Run the worker, restarting it with exponential backoff whenever it errors.
+///
+/// The backoff starts over once the worker ran for `RESTART_RESET`, so a
+/// worker that errors rarely isn't held back by failures long past.
+///
+/// Once `sd` begins, the worker isn't restarted anymore and the shutdown
+/// completes as soon as the service stopped.
+async fn supervise(
+    id: SubscriptionId,
+    service: Arc<StreamsService>,
+    restarts: Arc<AtomicU64>,
+    sd: Arc<Shutdown>,
+) {
+    let mut backoff = RESTART_BACKOFF;
+
+    loop {
+        let started = Instant::now();
+        service.clone().start().await;
+
+        // The service stopped, or failed while it was stopping.
+        if sd.is_shutdown() {
+            sd.complete();
+            return;
+        }
+
+        if started.elapsed() >= RESTART_RESET {
+            backoff = RESTART_BACKOFF;
+        }
+
+        warn!(
+            "Restarting stream service for subscription {} in {:?}",
+            id, backoff
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = sd.wait_begin() => {
+                sd.complete();
+                return;
+            }
+        }
+        backoff = std::cmp::min(backoff * 2, MAX_RESTART_BACKOFF);
+        restarts.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// A consumer that never delivers, on a topic without partitions.
+#[cfg(test)]
+struct IdleConsumer;
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl crate::kafka::streams::consumer::StreamsConsumer for IdleConsumer {
+    async fn consume(&self) -> Result<Option<crate::kafka::streams::StreamsMessage>, AnyError> {
+        std::future::pending().await
+    }
+
+    async fn commit(
+        &self,
+        _message: &crate::kafka::streams::StreamsMessage,
+    ) -> Result<(), AnyError> {
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<HashMap<i32, i64>, AnyError> {
+        Ok(HashMap::new())
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        Ok(0)
+    }
+}
+
+/// In-memory stores, with a subscription of cluster 1 for each id.
+#[cfg(test)]
+pub(crate) async fn stores(
+    ids: &[i64],
+) -> (
+    Arc<dyn ClusterStore + Send + Sync>,
+    Arc<dyn SubscriptionStore + Send + Sync>,
+) {
+    use crate::clusters::cluster::Kind;
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::config;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "test".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    for &id in ids {
+        let config = HashMap::from([(config::LIVENESS_ENABLED.to_string(), "false".to_string())]);
+        let sub = Subscription::new(
+            Some(SubscriptionId(id)),
+            ClusterId(1),
+            "orders".to_string(),
+            config,
+        );
+        ss.put(sub).await.unwrap();
+    }
+
+    (cs, ss)
+}
+
+/// A scheduler over the stores, whose workers never receive records.
+#[cfg(test)]
+pub(crate) fn scheduler(
+    cs: Arc<dyn ClusterStore + Send + Sync>,
+    ss: Arc<dyn SubscriptionStore + Send + Sync>,
+) -> Scheduler {
+    let factory: ConsumerFactory = Arc::new(|_, _| Ok(Arc::new(IdleConsumer)));
+    Scheduler::new(
+        cs,
+        ss,
+        Arc::new(crate::changefeed::store::MemoryChangefeedStore::default()),
+        Arc::new(crate::debug::store::MemoryDebugStore::default()),
+        Arc::new(crate::commands::store::MemoryCommandStore::default()),
+        Arc::new(crate::shards::store::MemoryDocumentStore::default()),
+    )
+    .with_factory(factory)
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(start_paused = true)]
+async fn it_restarts_errored_workers_with_backoff() {
+    use crate::failpoints::{self, Failpoint, Mode};
+
+    let id = SubscriptionId(9522);
+    let (cs, ss) = stores(&[id.0]).await;
+    let scheduler = Arc::new(scheduler(cs, ss));
+
+    // The worker fails to connect three times in a row, then recovers.
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::CONSUMER_CREATE, id),
+        mode: Mode::Error,
+        count: Some(3),
+    });
+    scheduler.clone().start().await.unwrap();
+
+    let expected = [(500, 0), (1_500, 1), (3_500, 2), (6_900, 2), (7_100, 3)];
+    let mut elapsed = 0;
+    for (at, restarts) in expected {
+        tokio::time::sleep(Duration::from_millis(at - elapsed)).await;
+        elapsed = at;
+        assert_eq!(scheduler.restarts(id).await, Some(restarts), "at {}ms", at);
+    }
+
+    let worker = scheduler.worker(id).await.unwrap();
+    assert_eq!(worker.status().await.state, WorkerState::Running);
+}
+
+/// A consumer delivering a single message, whose commit takes `commit`, or
+/// never returns without one.
+#[cfg(test)]
+struct CommittingConsumer {
+    delivered: std::sync::atomic::AtomicBool,
+    commit: Option<Duration>,
+    commits: Arc<std::sync::Mutex<Vec<i64>>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl crate::kafka::streams::consumer::StreamsConsumer for CommittingConsumer {
+    async fn consume(&self) -> Result<Option<crate::kafka::streams::StreamsMessage>, AnyError> {
+        if self.delivered.swap(true, Ordering::SeqCst) {
+            return std::future::pending().await;
+        }
+        Ok(Some(crate::kafka::streams::StreamsMessage {
+            payload: Some(r#"{"id": 1}"#.to_string()),
+            payload_json: None,
+            topic: "orders".to_string(),
+            key: None,
+            headers: HashMap::new(),
+            partition: 0,
+            offset: 0,
+            timestamp: None,
+        }))
+    }
+
+    async fn commit(
+        &self,
+        message: &crate::kafka::streams::StreamsMessage,
+    ) -> Result<(), AnyError> {
+        match self.commit {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => std::future::pending().await,
+        }
+        self.commits.lock().unwrap().push(message.offset);
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<HashMap<i32, i64>, AnyError> {
+        Ok(HashMap::new())
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        Ok(1)
+    }
+}
+
+/// A scheduler of subscription 1, whose worker commits the message it
+/// receives as `CommittingConsumer` does, and the offsets it committed.
+#[cfg(test)]
+async fn committing(
+    commit: Option<Duration>,
+    stop_timeout: Duration,
+) -> (Arc<Scheduler>, Arc<std::sync::Mutex<Vec<i64>>>) {
+    let (cs, ss) = stores(&[1]).await;
+    let commits = Arc::new(std::sync::Mutex::new(vec![]));
+    let consumer = Arc::new(CommittingConsumer {
+        delivered: std::sync::atomic::AtomicBool::new(false),
+        commit,
+        commits: commits.clone(),
+    });
+    let factory: ConsumerFactory = Arc::new(move |_, _| Ok(consumer.clone()));
+    let scheduler = scheduler(cs, ss)
+        .with_factory(factory)
+        .with_stop_timeout(stop_timeout);
+    (Arc::new(scheduler), commits)
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_finishes_messages_in_flight_before_stopping() {
+    let (scheduler, commits) = committing(Some(Duration::from_secs(5)), STOP_TIMEOUT).await;
+    scheduler.clone().start().await.unwrap();
+
+    // The worker is committing its message when the shutdown begins.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(commits.lock().unwrap().is_empty());
+
+    let stopping = Instant::now();
+    scheduler.clone().stop().await;
+    assert_eq!(*commits.lock().unwrap(), vec![0]);
+    assert!(stopping.elapsed() < STOP_TIMEOUT);
+    assert!(scheduler.subscriptions().await.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_gives_up_on_workers_that_do_not_stop_in_time() {
+    let (scheduler, commits) = committing(None, Duration::from_secs(2)).await;
+    scheduler.clone().start().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let stopping = Instant::now();
+    tokio::time::timeout(Duration::from_secs(60), scheduler.clone().stop())
+        .await
+        .expect("the stop timeout bounds the shutdown");
+    assert_eq!(stopping.elapsed(), Duration::from_secs(2));
+    assert!(commits.lock().unwrap().is_empty());
+    assert!(scheduler.subscriptions().await.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_picks_up_subscriptions_created_updated_and_deleted_at_runtime() {
+    use crate::kafka::config;
+
+    let (cs, ss) = stores(&[1, 2]).await;
+    let scheduler = scheduler(cs, ss.clone()).with_reconcile_interval(Duration::from_secs(5));
+    let scheduler = Arc::new(scheduler);
+    scheduler.clone().start().await.unwrap();
+    assert_eq!(
+        scheduler.subscriptions().await,
+        vec![SubscriptionId(1), SubscriptionId(2)]
+    );
+    let before = scheduler.worker(SubscriptionId(2)).await.unwrap();
+
+    // Subscription 1 is deleted, 2 updated and 3 created.
+    ss.remove(ClusterId(1), SubscriptionId(1)).await.unwrap();
+    let mut updated = ss
+        .get(ClusterId(1), SubscriptionId(2))
+        .await
+        .unwrap()
+        .unwrap();
+    updated
+        .config
+        .insert(config::RETENTION.to_string(), "1000".to_string());
+    updated.updated_at += chrono::Duration::seconds(1);
+    ss.put(updated).await.unwrap();
+    let created = Subscription::new(
+        Some(SubscriptionId(3)),
+        ClusterId(1),
+        "payments".to_string(),
+        HashMap::new(),
+    );
+    ss.put(created).await.unwrap();
+
+    tokio::time::sleep(Duration::from_secs(6)).await;
+    assert_eq!(
+        scheduler.subscriptions().await,
+        vec![SubscriptionId(2), SubscriptionId(3)]
+    );
+    let after = scheduler.worker(SubscriptionId(2)).await.unwrap();
+    assert!(!Arc::ptr_eq(&before, &after));
+
+    // Nothing changed, nothing restarts.
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let unchanged = scheduler.worker(SubscriptionId(2)).await.unwrap();
+    assert!(Arc::ptr_eq(&after, &unchanged));
+    let status = scheduler.status().await;
+    assert_eq!(
+        status.keys().copied().collect::<Vec<_>>(),
+        vec![SubscriptionId(2), SubscriptionId(3)]
+    );
+
+    scheduler.clone().stop().await;
+    assert!(scheduler.status().await.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_stops_paused_subscriptions_until_they_are_resumed() {
+    use crate::subscriptions::subscription::SubscriptionState;
+
+    let (cs, ss) = stores(&[1, 2]).await;
+    let scheduler = scheduler(cs, ss.clone()).with_reconcile_interval(Duration::from_secs(5));
+    let scheduler = Arc::new(scheduler);
+    scheduler.clone().start().await.unwrap();
+    let set_state = |state: SubscriptionState| {
+        let ss = ss.clone();
+        async move {
+            let sub = ss.get(ClusterId(1), SubscriptionId(1)).await.unwrap();
+            let sub = Subscription {
+                state,
+                ..sub.unwrap()
+            };
+            ss.put(sub).await.unwrap();
+        }
+    };
+
+    set_state(SubscriptionState::Paused).await;
+    tokio::time::sleep(Duration::from_secs(6)).await;
+    assert_eq!(scheduler.subscriptions().await, vec![SubscriptionId(2)]);
+
+    // Still paused, still stopped.
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert_eq!(scheduler.subscriptions().await, vec![SubscriptionId(2)]);
+
+    set_state(SubscriptionState::Active).await;
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert_eq!(
+        scheduler.subscriptions().await,
+        vec![SubscriptionId(1), SubscriptionId(2)]
+    );
+
+    scheduler.clone().stop().await;
+}
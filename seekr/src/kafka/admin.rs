@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication, TopicResult};
+use rdkafka::types::RDKafkaErrorCode;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::auth::AuthContext;
+use crate::kafka::client::broker_config;
+
+/// Timeout for the brokers to answer a topic change.
+pub const ADMIN_TIMEOUT: Duration = Duration::from_millis(15_000);
+
+/// A topic to create.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct NewTopicRequest {
+    pub name: String,
+    pub partitions: i32,
+    pub replication_factor: i32,
+
+    /// Topic settings overriding the brokers' defaults, e.g. `retention.ms`.
+    #[serde(default)]
+    pub config: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum TopicAdminError {
+    Exists(String),
+
+    /// The brokers refused the topic, e.g. for more replicas than brokers.
+    Invalid(String),
+    NotFound(String),
+    Failed(AnyError),
+}
+
+impl fmt::Display for TopicAdminError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TopicAdminError::Exists(message)
+            | TopicAdminError::Invalid(message)
+            | TopicAdminError::NotFound(message) => f.write_str(message),
+            TopicAdminError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<AnyError> for TopicAdminError {
+    fn from(e: AnyError) -> Self {
+        TopicAdminError::Failed(e)
+    }
+}
+
+#[async_trait]
+pub trait TopicAdmin {
+    /// Create the topic, or only have the brokers validate it with `dry_run`.
+    async fn create_topic(
+        &self,
+        cluster: &Cluster,
+        topic: &NewTopicRequest,
+        dry_run: bool,
+    ) -> Result<(), TopicAdminError>;
+
+    async fn delete_topic(&self, cluster: &Cluster, name: &str) -> Result<(), TopicAdminError>;
+}
+
+/// Changes topics through Kafka's admin API, keeping one client per cluster
+/// until the cluster is updated.
+#[derive(Default)]
+pub struct KafkaAdminService {
+    clients: RwLock<HashMap<ClusterId, AdminContext>>,
+}
+
+#[derive(Clone)]
+struct AdminContext {
+    client: Arc<AdminClient<AuthContext>>,
+    auth: AuthContext,
+
+    /// The version of the cluster the client was created with.
+    updated_at: DateTime<Utc>,
+}
+
+impl KafkaAdminService {
+    async fn client(&self, cluster: &Cluster) -> Result<AdminContext, AnyError> {
+        if let Some(c) = self.clients.read().await.get(&cluster.id) {
+            if c.updated_at == cluster.updated_at {
+                return Ok(c.clone());
+            }
+        }
+
+        let auth = AuthContext::of(cluster)?;
+        let mut client = broker_config(cluster);
+        auth.configure(&mut client);
+        let context = AdminContext {
+            client: Arc::new(client.create_with_context(auth.clone())?),
+            auth,
+            updated_at: cluster.updated_at,
+        };
+
+        self.clients
+            .write()
+            .await
+            .insert(cluster.id, context.clone());
+        Ok(context)
+    }
+}
+
+#[async_trait]
+impl TopicAdmin for KafkaAdminService {
+    async fn create_topic(
+        &self,
+        cluster: &Cluster,
+        topic: &NewTopicRequest,
+        dry_run: bool,
+    ) -> Result<(), TopicAdminError> {
+        let admin = self.client(cluster).await?;
+        admin.auth.check().await?;
+
+        let mut new_topic = NewTopic::new(
+            &topic.name,
+            topic.partitions,
+            TopicReplication::Fixed(topic.replication_factor),
+        );
+        for (key, value) in &topic.config {
+            new_topic = new_topic.set(key, value);
+        }
+        let options = options().validate_only(dry_run);
+        let results = admin
+            .client
+            .create_topics([&new_topic], &options)
+            .await
+            .map_err(|e| TopicAdminError::Failed(e.into()))?;
+        outcome(&topic.name, results)
+    }
+
+    async fn delete_topic(&self, cluster: &Cluster, name: &str) -> Result<(), TopicAdminError> {
+        let admin = self.client(cluster).await?;
+        admin.auth.check().await?;
+
+        let results = admin
+            .client
+            .delete_topics(&[name], &options())
+            .await
+            .map_err(|e| TopicAdminError::Failed(e.into()))?;
+        outcome(name, results)
+    }
+}
+
+fn options() -> AdminOptions {
+    AdminOptions::new()
+        .request_timeout(Some(ADMIN_TIMEOUT))
+        .operation_timeout(Some(ADMIN_TIMEOUT))
+}
+
+/// The outcome of the change of a single topic.
+fn outcome(name: &str, results: Vec<TopicResult>) -> Result<(), TopicAdminError> {
+    match results.into_iter().next() {
+        Some(Ok(_)) => Ok(()),
+        Some(Err((_, code))) => Err(classify(name, code)),
+        None => Err(TopicAdminError::Failed(
+            format!("The brokers didn't answer for topic {}", name).into(),
+        )),
+    }
+}
+
+/// Tell the errors the client can act on from the brokers failing.
+fn classify(name: &str, code: RDKafkaErrorCode) -> TopicAdminError {
+    let message = format!("Topic '{}': {}", name, code);
+    match code {
+        RDKafkaErrorCode::TopicAlreadyExists => TopicAdminError::Exists(message),
+        RDKafkaErrorCode::InvalidTopic
+        | RDKafkaErrorCode::InvalidPartitions
+        | RDKafkaErrorCode::InvalidReplicationFactor
+        | RDKafkaErrorCode::InvalidReplicaAssignment
+        | RDKafkaErrorCode::InvalidConfig
+        | RDKafkaErrorCode::InvalidRequest
+        | RDKafkaErrorCode::PolicyViolation => TopicAdminError::Invalid(message),
+        RDKafkaErrorCode::UnknownTopicOrPartition | RDKafkaErrorCode::UnknownTopic => {
+            TopicAdminError::NotFound(message)
+        }
+        _ => TopicAdminError::Failed(message.into()),
+    }
+}
+
+/// Records the topic changes asked for, failing those of the topics it's told to.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingTopicAdmin {
+    pub calls: std::sync::Mutex<Vec<String>>,
+    pub failures: HashMap<String, RDKafkaErrorCode>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl TopicAdmin for RecordingTopicAdmin {
+    async fn create_topic(
+        &self,
+        _cluster: &Cluster,
+        topic: &NewTopicRequest,
+        dry_run: bool,
+    ) -> Result<(), TopicAdminError> {
+        let call = format!("create {} dry_run={}", topic.name, dry_run);
+        self.calls.lock().unwrap().push(call);
+        match self.failures.get(&topic.name) {
+            Some(code) => Err(classify(&topic.name, *code)),
+            None => Ok(()),
+        }
+    }
+
+    async fn delete_topic(&self, _cluster: &Cluster, name: &str) -> Result<(), TopicAdminError> {
+        self.calls.lock().unwrap().push(format!("delete {}", name));
+        match self.failures.get(name) {
+            Some(code) => Err(classify(name, *code)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn it_classifies_broker_errors() {
+    let exists = classify("orders", RDKafkaErrorCode::TopicAlreadyExists);
+    assert!(matches!(exists, TopicAdminError::Exists(_)));
+    assert!(exists.to_string().starts_with("Topic 'orders': "));
+
+    for code in [
+        RDKafkaErrorCode::InvalidReplicationFactor,
+        RDKafkaErrorCode::InvalidPartitions,
+        RDKafkaErrorCode::PolicyViolation,
+    ] {
+        assert!(matches!(
+            classify("orders", code),
+            TopicAdminError::Invalid(_)
+        ));
+    }
+    assert!(matches!(
+        classify("orders", RDKafkaErrorCode::UnknownTopicOrPartition),
+        TopicAdminError::NotFound(_)
+    ));
+    assert!(matches!(
+        classify("orders", RDKafkaErrorCode::BrokerNotAvailable),
+        TopicAdminError::Failed(_)
+    ));
+}
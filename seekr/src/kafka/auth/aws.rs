@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use aws_config::meta::region::RegionProviderChain;
+use aws_msk_iam_sasl_signer::{generate_auth_token, generate_auth_token_from_role_arn};
+use aws_types::region::Region;
+
+use crate::errors::AnyError;
+
+use super::{Token, TokenSource};
+
+/// The session name of assumed roles, showing in CloudTrail.
+const SESSION_NAME: &str = "seekr";
+
+/// Signs MSK IAM tokens with the default AWS credentials chain, i.e. the
+/// environment, profiles, or the instance and task roles.
+pub struct MskIamTokenSource {
+    region: Option<String>,
+    role_arn: Option<String>,
+}
+
+impl MskIamTokenSource {
+    pub fn new(region: Option<String>, role_arn: Option<String>) -> Self {
+        Self { region, role_arn }
+    }
+
+    /// The region set on the cluster, or the one of the environment.
+    async fn region(&self) -> Result<Region, AnyError> {
+        let region = RegionProviderChain::first_try(self.region.clone().map(Region::new))
+            .or_default_provider()
+            .region()
+            .await;
+        region.ok_or_else(|| "no aws.region set and none found in the environment".into())
+    }
+}
+
+#[async_trait]
+impl TokenSource for MskIamTokenSource {
+    fn name(&self) -> &'static str {
+        "AWS"
+    }
+
+    async fn generate(&self) -> Result<Token, AnyError> {
+        let region = self.region().await?;
+        let (value, expires_at_ms) = match &self.role_arn {
+            Some(arn) => {
+                generate_auth_token_from_role_arn(region, arn.clone(), Some(SESSION_NAME.into()))
+                    .await?
+            }
+            None => generate_auth_token(region).await?,
+        };
+
+        Ok(Token {
+            value,
+            principal: self.role_arn.clone().unwrap_or_else(|| SESSION_NAME.into()),
+            expires_at_ms,
+        })
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use rdkafka::client::OAuthToken;
+use rdkafka::consumer::ConsumerContext;
+use rdkafka::{ClientConfig, ClientContext};
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::kafka::config;
+
+#[cfg(feature = "aws-auth")]
+pub mod aws;
+
+/// The `auth.provider` of MSK clusters authenticating with IAM.
+pub const AWS_MSK_IAM: &str = "aws-msk-iam";
+
+/// Tokens are refreshed once this share of their lifetime passed.
+const REFRESH_AT: f64 = 0.8;
+
+/// How long a failed refresh waits before the next, doubling up to `MAX_RETRY`.
+const RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+/// How a cluster's clients authenticate, as set by `auth.provider`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthProvider {
+    /// Nothing beyond the static config.
+    None,
+
+    /// SASL OAUTHBEARER with tokens signed by the AWS credentials chain,
+    /// optionally of an assumed role.
+    AwsMskIam {
+        region: Option<String>,
+        role_arn: Option<String>,
+    },
+}
+
+impl AuthProvider {
+    pub fn of(config: &HashMap<String, String>) -> Result<Self, String> {
+        match config.get(config::AUTH_PROVIDER).map(|p| p.as_str()) {
+            None | Some("") => Ok(AuthProvider::None),
+            Some(AWS_MSK_IAM) if !cfg!(feature = "aws-auth") => Err(format!(
+                "{} {} needs seekr built with the aws-auth feature",
+                config::AUTH_PROVIDER,
+                AWS_MSK_IAM
+            )),
+            Some(AWS_MSK_IAM) => Ok(AuthProvider::AwsMskIam {
+                region: config.get(config::AWS_REGION).cloned(),
+                role_arn: config.get(config::AWS_ROLE_ARN).cloned(),
+            }),
+            Some(other) => Err(format!(
+                "Unknown {} '{}', expected {}",
+                config::AUTH_PROVIDER,
+                other,
+                AWS_MSK_IAM
+            )),
+        }
+    }
+}
+
+/// A bearer token and when it expires, in milliseconds since the epoch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub value: String,
+    pub principal: String,
+    pub expires_at_ms: i64,
+}
+
+#[async_trait]
+pub trait TokenSource {
+    /// Whose credentials the tokens are signed with, e.g. `AWS`.
+    fn name(&self) -> &'static str;
+
+    async fn generate(&self) -> Result<Token, AnyError>;
+}
+
+/// Keeps a fresh token at hand for the client's refresh callback, which
+/// librdkafka calls synchronously on its own threads.
+///
+/// Tokens are generated in the background, ahead of their expiry, and
+/// failures are retried with backoff. Until a token is generated, the error
+/// of the last attempt is reported instead.
+pub struct TokenCache {
+    source: Arc<dyn TokenSource + Send + Sync>,
+    current: RwLock<Option<Result<Token, String>>>,
+}
+
+impl TokenCache {
+    /// Generate tokens from the source for as long as the cache is in use.
+    pub fn start(source: Arc<dyn TokenSource + Send + Sync>) -> Arc<Self> {
+        let cache = Arc::new(Self {
+            source,
+            current: RwLock::new(None),
+        });
+        tokio::spawn(Self::refresh_loop(Arc::downgrade(&cache)));
+        cache
+    }
+
+    async fn refresh_loop(cache: Weak<Self>) {
+        let mut retry = RETRY;
+        loop {
+            let Some(c) = cache.upgrade() else {
+                return;
+            };
+            let delay = match c.refresh().await {
+                Ok(token) => {
+                    retry = RETRY;
+                    let lifetime = token.expires_at_ms - Utc::now().timestamp_millis();
+                    let lifetime = Duration::from_millis(lifetime.max(0) as u64);
+                    lifetime.mul_f64(REFRESH_AT)
+                }
+                Err(e) => {
+                    warn!("{}, retrying in {:?}", e, retry);
+                    let delay = retry;
+                    retry = std::cmp::min(retry * 2, MAX_RETRY);
+                    delay
+                }
+            };
+            // The consumers may go away while waiting.
+            drop(c);
+            tokio::time::sleep(delay.max(RETRY)).await;
+        }
+    }
+
+    /// Generate a new token, keeping it for the callback.
+    pub async fn refresh(&self) -> Result<Token, String> {
+        let result = self
+            .source
+            .generate()
+            .await
+            .map_err(|e| format!("could not obtain {} credentials: {}", self.source.name(), e));
+        *self.current.write().unwrap() = Some(result.clone());
+        result
+    }
+
+    /// The last token generated, or why there is none.
+    pub fn token(&self) -> Result<Token, String> {
+        match self.current.read().unwrap().clone() {
+            Some(result) => result,
+            None => Err(format!("no {} token was generated yet", self.source.name())),
+        }
+    }
+
+    /// The last token generated, waiting for the first one.
+    pub async fn ready(&self) -> Result<Token, String> {
+        let current = self.current.read().unwrap().clone();
+        match current {
+            Some(result) => result,
+            None => self.refresh().await,
+        }
+    }
+}
+
+/// The rdkafka context of every client of a cluster, serving the tokens of
+/// its auth provider to librdkafka's OAUTHBEARER refresh callback.
+#[derive(Clone, Default)]
+pub struct AuthContext {
+    tokens: Option<Arc<TokenCache>>,
+}
+
+impl AuthContext {
+    /// Start generating tokens when the cluster's provider needs them.
+    pub fn of(cluster: &Cluster) -> Result<Self, AnyError> {
+        match AuthProvider::of(&cluster.config)? {
+            AuthProvider::None => Ok(Self::default()),
+            #[cfg(feature = "aws-auth")]
+            AuthProvider::AwsMskIam { region, role_arn } => {
+                let source = aws::MskIamTokenSource::new(region, role_arn);
+                Ok(Self::with_tokens(TokenCache::start(Arc::new(source))))
+            }
+            #[cfg(not(feature = "aws-auth"))]
+            AuthProvider::AwsMskIam { .. } => unreachable!("rejected by AuthProvider::of"),
+        }
+    }
+
+    pub fn with_tokens(tokens: Arc<TokenCache>) -> Self {
+        Self {
+            tokens: Some(tokens),
+        }
+    }
+
+    /// Set the client up for OAUTHBEARER when tokens are served.
+    pub fn configure(&self, config: &mut ClientConfig) {
+        if self.tokens.is_some() {
+            config
+                .set("security.protocol", "SASL_SSL")
+                .set("sasl.mechanism", "OAUTHBEARER");
+        }
+    }
+
+    /// Fail with why no token can be served, rather than letting the client
+    /// time out on brokers refusing it.
+    pub async fn check(&self) -> Result<(), AnyError> {
+        match &self.tokens {
+            Some(tokens) => tokens.ready().await.map(drop).map_err(Into::into),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ClientContext for AuthContext {
+    const ENABLE_REFRESH_OAUTH_TOKEN: bool = true;
+
+    fn generate_oauth_token(
+        &self,
+        _oauthbearer_config: Option<&str>,
+    ) -> Result<OAuthToken, Box<dyn Error>> {
+        let Some(tokens) = &self.tokens else {
+            return Err("the cluster has no auth.provider serving tokens".into());
+        };
+
+        let token = tokens.token()?;
+        Ok(OAuthToken {
+            token: token.value,
+            principal_name: token.principal,
+            lifetime_ms: token.expires_at_ms,
+        })
+    }
+}
+
+impl ConsumerContext for AuthContext {}
+
+/// Tokens of a scripted lifetime, or failures, counting how many were generated.
+#[cfg(test)]
+pub struct MockTokenSource {
+    pub lifetime: Duration,
+    pub failures: std::sync::atomic::AtomicUsize,
+    pub generated: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl MockTokenSource {
+    pub fn new(lifetime: Duration, failures: usize) -> Self {
+        Self {
+            lifetime,
+            failures: failures.into(),
+            generated: 0.into(),
+        }
+    }
+
+    pub fn generated(&self) -> usize {
+        self.generated.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl TokenSource for MockTokenSource {
+    fn name(&self) -> &'static str {
+        "AWS"
+    }
+
+    async fn generate(&self) -> Result<Token, AnyError> {
+        use std::sync::atomic::Ordering;
+
+        let failing = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            return Err("no credentials in the chain".into());
+        }
+
+        let n = self.generated.fetch_add(1, Ordering::SeqCst);
+        Ok(Token {
+            value: format!("token-{}", n),
+            principal: "seekr".to_string(),
+            expires_at_ms: Utc::now().timestamp_millis() + self.lifetime.as_millis() as i64,
+        })
+    }
+}
+
+#[test]
+fn it_validates_the_auth_provider() {
+    let config = |provider: &str| {
+        HashMap::from([
+            (config::AUTH_PROVIDER.to_string(), provider.to_string()),
+            (config::AWS_REGION.to_string(), "eu-west-1".to_string()),
+        ])
+    };
+
+    assert_eq!(AuthProvider::of(&HashMap::new()), Ok(AuthProvider::None));
+    assert!(AuthProvider::of(&config("kerberos"))
+        .unwrap_err()
+        .contains("Unknown auth.provider 'kerberos'"));
+
+    let msk = AuthProvider::of(&config(AWS_MSK_IAM));
+    match cfg!(feature = "aws-auth") {
+        true => assert_eq!(
+            msk,
+            Ok(AuthProvider::AwsMskIam {
+                region: Some("eu-west-1".to_string()),
+                role_arn: None,
+            })
+        ),
+        false => assert!(msk.unwrap_err().contains("aws-auth feature")),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_refreshes_tokens_before_they_expire() {
+    let source = Arc::new(MockTokenSource::new(Duration::from_secs(15 * 60), 0));
+    let tokens = TokenCache::start(source.clone());
+    let context = AuthContext::with_tokens(tokens.clone());
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(source.generated(), 1);
+    let token = context.generate_oauth_token(None).unwrap();
+    assert_eq!(token.token, "token-0");
+    assert_eq!(token.principal_name, "seekr");
+
+    // Refreshed at 80% of the token's 15 minute lifetime.
+    tokio::time::sleep(Duration::from_secs(11 * 60)).await;
+    assert_eq!(source.generated(), 1);
+    tokio::time::sleep(Duration::from_secs(60) + Duration::from_millis(10)).await;
+    assert_eq!(source.generated(), 2);
+    assert_eq!(tokens.token().unwrap().value, "token-1");
+
+    // The refreshes stop with the clients using the tokens.
+    drop(context);
+    drop(tokens);
+    tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+    assert_eq!(source.generated(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_retries_failed_refreshes_with_backoff() {
+    let source = Arc::new(MockTokenSource::new(Duration::from_secs(15 * 60), 3));
+    let tokens = TokenCache::start(source.clone());
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let e = tokens.token().unwrap_err();
+    assert_eq!(
+        e,
+        "could not obtain AWS credentials: no credentials in the chain"
+    );
+    let context = AuthContext::with_tokens(tokens.clone());
+    assert!(context.generate_oauth_token(None).is_err());
+    assert_eq!(context.check().await.unwrap_err().to_string(), e);
+
+    // Retried after 1, 2 and 4 seconds.
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert!(tokens.token().is_err());
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert!(tokens.token().is_ok());
+    assert_eq!(source.generated(), 1);
+}
@@ -0,0 +1,129 @@
+use rdkafka::ClientConfig;
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::kafka::config;
+use crate::kafka::metadata::classify::DEFAULT_GROUP_ID;
+use crate::kafka::streams::consumer::offset_reset;
+use crate::subscriptions::subscription::Subscription;
+
+/// The settings of every client of the cluster's brokers, with its
+/// `security.*`, `sasl.*` and `ssl.*` settings as they are.
+pub fn broker_config(cluster: &Cluster) -> ClientConfig {
+    let bootstraps = cluster
+        .config
+        .get(config::BOOTSTRAP_SERVERS)
+        .map_or("localhost:9092", String::as_str);
+
+    let mut client = ClientConfig::new();
+    client
+        .set("bootstrap.servers", bootstraps)
+        .set("api.version.request", "true");
+    for key in config::CONNECTION {
+        if let Some(value) = cluster.config.get(key) {
+            client.set(key, value);
+        }
+    }
+    client
+}
+
+/// The settings of a consumer of the cluster, which never commits on its own.
+///
+/// Consumers of a subscription join its group, with its `auto.offset.reset`,
+/// the others the cluster's group.
+pub fn build_client_config(
+    cluster: &Cluster,
+    subscription: Option<&Subscription>,
+) -> Result<ClientConfig, AnyError> {
+    let mut client = broker_config(cluster);
+    client.set("enable.auto.commit", "false");
+    match subscription {
+        Some(subscription) => {
+            client.set("group.id", group_id(cluster, subscription));
+            if let Some(reset) = offset_reset(&subscription.config)? {
+                client.set(config::AUTO_OFFSET_RESET, reset);
+            }
+        }
+        None => {
+            client.set("group.id", cluster_group_id(cluster));
+        }
+    }
+    Ok(client)
+}
+
+/// The group of the cluster's consumers, from its `seekr.group.id`.
+pub fn cluster_group_id(cluster: &Cluster) -> String {
+    cluster
+        .config
+        .get(config::SEEKR_GROUP_ID)
+        .map_or(DEFAULT_GROUP_ID, String::as_str)
+        .to_string()
+}
+
+/// The group of a subscription's workers, its own `seekr.group.id` or the
+/// cluster's suffixed with the subscription id.
+pub fn group_id(cluster: &Cluster, subscription: &Subscription) -> String {
+    match subscription.config.get(config::SEEKR_GROUP_ID) {
+        Some(group_id) => group_id.clone(),
+        None => format!("{}.{}", cluster_group_id(cluster), subscription.id),
+    }
+}
+
+#[test]
+fn it_passes_connection_settings_to_every_client() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+    use crate::ids::{ClusterId, SubscriptionId};
+
+    let config = HashMap::from([
+        (
+            config::BOOTSTRAP_SERVERS.to_string(),
+            "kafka:9096".to_string(),
+        ),
+        (
+            config::SECURITY_PROTOCOL.to_string(),
+            "SASL_SSL".to_string(),
+        ),
+        (
+            config::SASL_MECHANISM.to_string(),
+            "SCRAM-SHA-512".to_string(),
+        ),
+        (config::SASL_USERNAME.to_string(), "seekr".to_string()),
+        (config::SASL_PASSWORD.to_string(), "hunter2".to_string()),
+        (
+            config::SSL_CA_LOCATION.to_string(),
+            "/etc/ca.pem".to_string(),
+        ),
+        (config::METADATA_PRIORITY.to_string(), "high".to_string()),
+    ]);
+    let cluster = Cluster::new(Some(ClusterId(1)), Kind::Kafka, "c".to_string(), config);
+    let subscription = Subscription::new(
+        Some(SubscriptionId(7)),
+        ClusterId(1),
+        "orders".to_string(),
+        HashMap::new(),
+    );
+
+    for client in [
+        broker_config(&cluster),
+        build_client_config(&cluster, None).unwrap(),
+        build_client_config(&cluster, Some(&subscription)).unwrap(),
+    ] {
+        assert_eq!(client.get("bootstrap.servers"), Some("kafka:9096"));
+        assert_eq!(client.get("security.protocol"), Some("SASL_SSL"));
+        assert_eq!(client.get("sasl.mechanism"), Some("SCRAM-SHA-512"));
+        assert_eq!(client.get("sasl.username"), Some("seekr"));
+        assert_eq!(client.get("sasl.password"), Some("hunter2"));
+        assert_eq!(client.get("ssl.ca.location"), Some("/etc/ca.pem"));
+        assert_eq!(client.get("ssl.key.location"), None);
+        // Settings of seekr's own aren't the client's.
+        assert_eq!(client.get(config::METADATA_PRIORITY), None);
+    }
+
+    assert_eq!(broker_config(&cluster).get("group.id"), None);
+    let client = build_client_config(&cluster, None).unwrap();
+    assert_eq!(client.get("group.id"), Some("seekr.io"));
+    let client = build_client_config(&cluster, Some(&subscription)).unwrap();
+    assert_eq!(client.get("group.id"), Some("seekr.io.7"));
+}
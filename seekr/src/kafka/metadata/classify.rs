@@ -0,0 +1,138 @@
+use crate::clusters::cluster::Cluster;
+use crate::kafka::config;
+
+pub use seekr_api_types::metadata::{Include, ResourceCounts, TopicCategory};
+
+/// Infrastructure topics of the Kafka ecosystem, unless a cluster lists its own.
+pub const DEFAULT_SYSTEM_TOPICS: [&str; 4] = [
+    "_schemas",
+    "connect-configs",
+    "connect-offsets",
+    "connect-status",
+];
+
+/// The group seekr consumes with, unless a cluster configures its own.
+pub const DEFAULT_GROUP_ID: &str = "seekr.io";
+
+/// Classifies a cluster's topics and groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Classifier {
+    system_topics: Vec<String>,
+    group_id: String,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Self {
+            system_topics: DEFAULT_SYSTEM_TOPICS.map(String::from).to_vec(),
+            group_id: DEFAULT_GROUP_ID.to_string(),
+        }
+    }
+}
+
+impl Classifier {
+    /// The classifier of a cluster, whose `metadata.system.topics` replaces the
+    /// default system topics, and whose `seekr.group.id` is the group seekr consumes with.
+    pub fn from(cluster: &Cluster) -> Self {
+        let mut classifier = Self::default();
+        if let Some(topics) = cluster.config.get(config::METADATA_SYSTEM_TOPICS) {
+            classifier.system_topics = topics
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(group_id) = cluster.config.get(config::SEEKR_GROUP_ID) {
+            classifier.group_id = group_id.clone();
+        }
+
+        classifier
+    }
+
+    pub fn topic(&self, name: &str) -> TopicCategory {
+        if name.starts_with("__") {
+            TopicCategory::Internal
+        } else if self.system_topics.iter().any(|t| t == name) {
+            TopicCategory::System
+        } else {
+            TopicCategory::User
+        }
+    }
+
+    /// Whether the group is the one seekr's own consumers join, or one named
+    /// after it, like the groups of subscriptions and live streams.
+    pub fn managed_by_seekr(&self, group: &str) -> bool {
+        group
+            .strip_prefix(self.group_id.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    }
+}
+
+#[test]
+fn it_classifies_topics_and_groups() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+
+    let classifier = Classifier::default();
+    assert_eq!(
+        classifier.topic("__consumer_offsets"),
+        TopicCategory::Internal
+    );
+    assert_eq!(
+        classifier.topic("__transaction_state"),
+        TopicCategory::Internal
+    );
+    assert_eq!(classifier.topic("_schemas"), TopicCategory::System);
+    assert_eq!(classifier.topic("orders"), TopicCategory::User);
+    assert_eq!(classifier.topic("_orders"), TopicCategory::User);
+    assert!(classifier.managed_by_seekr("seekr.io"));
+    assert!(classifier.managed_by_seekr("seekr.io.42"));
+    assert!(!classifier.managed_by_seekr("seekr.iox"));
+    assert!(!classifier.managed_by_seekr("billing"));
+
+    let config = HashMap::from([
+        (
+            config::METADATA_SYSTEM_TOPICS.to_string(),
+            "_registry, audit-sink".to_string(),
+        ),
+        (config::SEEKR_GROUP_ID.to_string(), "seekr.prod".to_string()),
+    ]);
+    let cluster = Cluster::new(None, Kind::Kafka, "local".to_string(), config);
+    let classifier = Classifier::from(&cluster);
+    assert_eq!(classifier.topic("audit-sink"), TopicCategory::System);
+    assert_eq!(classifier.topic("_schemas"), TopicCategory::User);
+    assert_eq!(
+        classifier.topic("__consumer_offsets"),
+        TopicCategory::Internal
+    );
+    assert!(classifier.managed_by_seekr("seekr.prod"));
+    assert!(!classifier.managed_by_seekr("seekr.io"));
+}
+
+#[test]
+fn it_counts_user_resources_by_default() {
+    use super::GroupMetadata;
+
+    let mut metadata = crate::history::diff::metadata(
+        &[1],
+        &[("orders", 3), ("_schemas", 1), ("__consumer_offsets", 50)],
+    );
+    metadata.groups = [("billing", false), ("seekr.io", true)]
+        .map(|(name, managed_by_seekr)| GroupMetadata {
+            name: name.to_string(),
+            state: "Stable".to_string(),
+            members: vec![],
+            managed_by_seekr,
+        })
+        .to_vec();
+
+    let counts = |internal, system| ResourceCounts::of(&metadata, Include { internal, system });
+    let user = counts(false, false);
+    assert_eq!((user.topics, user.partitions, user.groups), (1, 3, 1));
+    let system = counts(false, true);
+    assert_eq!((system.topics, system.partitions, system.groups), (2, 4, 2));
+    let all = counts(true, true);
+    assert_eq!((all.topics, all.partitions, all.groups), (3, 54, 2));
+}
@@ -1,58 +1,86 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use std::{result::Result, sync::Arc};
 
 use async_trait::async_trait;
+use rdkafka::admin::{AdminClient, AdminOptions, ConfigEntry, ConfigSource, ResourceSpecifier};
 use rdkafka::consumer::{BaseConsumer, Consumer};
 use rdkafka::error::KafkaError;
 use rdkafka::groups::GroupInfo;
 use rdkafka::metadata::{MetadataBroker, MetadataTopic};
-use rdkafka::ClientConfig;
-use tokio::sync::Mutex;
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use tokio::sync::{Mutex, OnceCell};
 
 use crate::clusters::cluster::Cluster;
 use crate::errors::AnyError;
-use crate::kafka::config;
+use crate::kafka::auth::AuthContext;
+use crate::kafka::client::build_client_config;
+use crate::kafka::sensitive::redact;
 
+use super::classify::Classifier;
 use super::{
-    BrokerMetadata, ClusterMetadata, GroupMember, GroupMetadata, PartitionMetadata, TopicMetadata,
+    BrokerMetadata, ClusterMetadata, GroupLag, GroupMember, GroupMetadata, PartitionMetadata,
+    PartitionOffsets, TopicConfigEntry, TopicConfigSource, TopicMetadata, TopicOffsets,
 };
 
 /// Timeout for fetching metadata.
 pub const FETCH_METADATA_TIMEOUT_MS: Duration = Duration::from_millis(15_000);
 
+/// Timeout for sampling the newest records of a topic.
+pub const SAMPLE_HEAD_TIMEOUT_MS: Duration = Duration::from_millis(2_000);
+
 #[async_trait]
 pub trait MetadataConsumer {
     async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError>;
+
+    /// Fetch the watermarks of the given topics, keyed by topic name and
+    /// sampling the newest record timestamp of topics flagged `true`.
+    async fn fetch_offsets(
+        &self,
+        metadata: &ClusterMetadata,
+        topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError>;
+
+    /// Fetch the offsets the group committed in the topics of the metadata,
+    /// along with how far behind their high watermarks they are.
+    async fn fetch_group_offsets(
+        &self,
+        group: &str,
+        metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError>;
+
+    /// Describe the settings of the topic, defaults included.
+    async fn fetch_topic_config(&self, topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError>;
 }
 
 pub struct KafkaMetadataConsumer {
-    pub inner: Arc<Mutex<BaseConsumer>>,
+    pub inner: Arc<Mutex<BaseConsumer<AuthContext>>>,
+    classifier: Classifier,
+    auth: AuthContext,
+
+    /// The settings of `inner`, for consumers reading other groups' offsets.
+    client: ClientConfig,
+
+    /// Created on the first topic described, then kept connected.
+    admin: OnceCell<AdminClient<AuthContext>>,
 }
 
 impl KafkaMetadataConsumer {
     pub fn create(cluster: &Cluster) -> Result<Self, AnyError> {
-        debug!("cluster config: {:?}", cluster.config);
-
-        let bootstraps = cluster
-            .config
-            .get(config::BOOTSTRAP_SERVERS)
-            .unwrap_or(&String::from("localhost:9092"))
-            .to_owned();
-
-        let group_id = cluster
-            .config
-            .get(config::SEEKR_GROUP_ID)
-            .unwrap_or(&String::from("seekr.io"))
-            .to_owned();
-
-        let consumer = ClientConfig::new()
-            .set("bootstrap.servers", &bootstraps)
-            .set("group.id", &group_id)
-            .set("api.version.request", "true")
-            .create::<BaseConsumer>()?;
+        debug!("cluster config: {:?}", redact(&cluster.config));
+
+        let auth = AuthContext::of(cluster)?;
+        // Head sampling assigns partitions, it must never move the group offsets.
+        let mut client = build_client_config(cluster, None)?;
+        auth.configure(&mut client);
+        let consumer = client.create_with_context::<_, BaseConsumer<_>>(auth.clone())?;
 
         Ok(Self {
             inner: Arc::new(Mutex::new(consumer)),
+            classifier: Classifier::from(cluster),
+            auth,
+            client,
+            admin: OnceCell::new(),
         })
     }
 }
@@ -60,37 +88,281 @@ impl KafkaMetadataConsumer {
 #[async_trait]
 impl MetadataConsumer for KafkaMetadataConsumer {
     async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        // Brokers refuse clients without a token, fail with why there is none.
+        self.auth.check().await?;
+
+        // The broker round trips block for up to their timeout, so they keep
+        // a blocking thread busy rather than one of the executor's.
+        let inner = self.inner.clone().lock_owned().await;
+        let classifier = self.classifier.clone();
+        tokio::task::spawn_blocking(move || {
+            let metadata = inner.fetch_metadata(None, FETCH_METADATA_TIMEOUT_MS)?;
+
+            let mut brokers = metadata
+                .brokers()
+                .iter()
+                .map(parse_broker)
+                .collect::<Vec<_>>();
+            brokers.sort_by(|a, b| a.host.cmp(&b.host));
+
+            let mut topics = metadata
+                .topics()
+                .iter()
+                .map(|t| parse_topic(t, &classifier))
+                .collect::<Vec<_>>();
+            topics.sort_by(|a, b| a.name.cmp(&b.name));
+
+            let deadline = Instant::now() + FETCH_METADATA_TIMEOUT_MS;
+            fill_watermarks(&mut topics, deadline, |topic, partition, timeout| {
+                inner.fetch_watermarks(topic, partition, timeout)
+            });
+
+            let mut groups = inner
+                .fetch_group_list(None, FETCH_METADATA_TIMEOUT_MS)?
+                .groups()
+                .iter()
+                .map(|g| parse_group(g, &classifier))
+                .collect::<Vec<_>>();
+            groups.sort_by(|a, b| a.name.cmp(&b.name));
+
+            Ok(ClusterMetadata {
+                brokers,
+                groups,
+                topics,
+            })
+        })
+        .await?
+    }
+
+    async fn fetch_offsets(
+        &self,
+        metadata: &ClusterMetadata,
+        topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
         let inner = self.inner.lock().await;
-        let metadata = inner.fetch_metadata(None, FETCH_METADATA_TIMEOUT_MS)?;
 
-        let mut brokers = metadata
-            .brokers()
-            .iter()
-            .map(parse_broker)
-            .collect::<Vec<_>>();
-        brokers.sort_by(|a, b| a.host.cmp(&b.host));
+        let mut offsets = Vec::new();
+        for t in &metadata.topics {
+            let Some(&sample) = topics.get(&t.name) else {
+                continue;
+            };
 
-        let mut topics = metadata
-            .topics()
-            .iter()
-            .map(parse_topic)
-            .collect::<Vec<_>>();
-        topics.sort_by(|a, b| a.name.cmp(&b.name));
+            let mut partitions = Vec::with_capacity(t.partitions.len());
+            for p in &t.partitions {
+                let (low, high) =
+                    inner.fetch_watermarks(&t.name, p.id, FETCH_METADATA_TIMEOUT_MS)?;
+                partitions.push(PartitionOffsets {
+                    id: p.id,
+                    low,
+                    high,
+                });
+            }
+
+            let head_timestamp = match sample {
+                true => sample_head(&inner, &t.name, &partitions),
+                false => None,
+            };
+
+            offsets.push(TopicOffsets {
+                name: t.name.clone(),
+                partitions,
+                head_timestamp,
+            });
+        }
+
+        Ok(offsets)
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        group: &str,
+        metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        self.auth.check().await?;
+
+        // A consumer of the group only to read its offsets, which never joins
+        // it nor commits, so the group's members are left undisturbed.
+        let mut client = self.client.clone();
+        client.set("group.id", group);
+        let consumer = client.create_with_context::<_, BaseConsumer<_>>(self.auth.clone())?;
+
+        let mut tpl = TopicPartitionList::new();
+        for t in &metadata.topics {
+            for p in &t.partitions {
+                tpl.add_partition(&t.name, p.id);
+            }
+        }
 
-        let mut groups = inner
-            .fetch_group_list(None, FETCH_METADATA_TIMEOUT_MS)?
-            .groups()
+        // Offset and watermark queries are blocking broker round trips.
+        tokio::task::spawn_blocking(move || {
+            let committed = consumer
+                .committed_offsets(tpl, FETCH_METADATA_TIMEOUT_MS)?
+                .elements()
+                .iter()
+                .filter_map(|e| match e.offset() {
+                    Offset::Offset(o) => Some((e.topic().to_string(), e.partition(), o)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            let lag = group_lag(committed, |topic, partition| {
+                consumer
+                    .fetch_watermarks(topic, partition, FETCH_METADATA_TIMEOUT_MS)
+                    .map(|(_, high)| high)
+            })?;
+            Ok(lag)
+        })
+        .await?
+    }
+
+    async fn fetch_topic_config(&self, topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        self.auth.check().await?;
+
+        let admin = self
+            .admin
+            .get_or_try_init(|| async {
+                self.client
+                    .create_with_context::<_, AdminClient<_>>(self.auth.clone())
+            })
+            .await?;
+        let options = AdminOptions::new().request_timeout(Some(FETCH_METADATA_TIMEOUT_MS));
+        let resources = admin
+            .describe_configs(&[ResourceSpecifier::Topic(topic)], &options)
+            .await?;
+
+        let resource = match resources.into_iter().next() {
+            Some(Ok(resource)) => resource,
+            Some(Err(code)) => {
+                return Err(format!("Unable to describe topic {}: {}", topic, code).into())
+            }
+            None => return Err(format!("Topic {} wasn't described", topic).into()),
+        };
+        let mut entries = resource
+            .entries
             .iter()
-            .map(parse_group)
+            .map(topic_config_entry)
             .collect::<Vec<_>>();
-        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
 
-        Ok(ClusterMetadata {
-            brokers,
-            groups,
-            topics,
+fn topic_config_entry(e: &ConfigEntry) -> TopicConfigEntry {
+    TopicConfigEntry {
+        name: e.name.clone(),
+        value: e.value.clone(),
+        source: match e.source {
+            ConfigSource::DynamicTopic => TopicConfigSource::Topic,
+            ConfigSource::DynamicBroker => TopicConfigSource::DynamicBroker,
+            ConfigSource::DynamicDefaultBroker => TopicConfigSource::DynamicDefaultBroker,
+            ConfigSource::StaticBroker => TopicConfigSource::StaticBroker,
+            ConfigSource::Default => TopicConfigSource::Default,
+            ConfigSource::Unknown => TopicConfigSource::Unknown,
+        },
+        is_default: e.is_default,
+        is_read_only: e.is_read_only,
+        is_sensitive: e.is_sensitive,
+    }
+}
+
+/// The lag of the group in every partition it committed an offset for, out
+/// of the committed offsets and the high watermarks `fetch` gets.
+fn group_lag<F>(
+    committed: Vec<(String, i32, i64)>,
+    mut fetch: F,
+) -> Result<Vec<GroupLag>, KafkaError>
+where
+    F: FnMut(&str, i32) -> Result<i64, KafkaError>,
+{
+    committed
+        .into_iter()
+        .map(|(topic, partition, committed)| {
+            let high_watermark = fetch(&topic, partition)?;
+            Ok(GroupLag {
+                lag: (high_watermark - committed).max(0),
+                topic,
+                partition,
+                committed,
+                high_watermark,
+            })
         })
+        .collect()
+}
+
+/// Set the watermarks of every partition, fetched one at a time until the
+/// deadline. Partitions in error, failing or left when the deadline passes
+/// keep `None`, so a slow partition doesn't fail the whole metadata.
+fn fill_watermarks<F>(topics: &mut [TopicMetadata], deadline: Instant, mut fetch: F)
+where
+    F: FnMut(&str, i32, Duration) -> Result<(i64, i64), KafkaError>,
+{
+    for t in topics.iter_mut() {
+        for p in t.partitions.iter_mut().filter(|p| p.error.is_none()) {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                debug!(
+                    "Ran out of time fetching the watermarks of topic {}",
+                    t.name
+                );
+                return;
+            }
+
+            match fetch(&t.name, p.id, timeout) {
+                Ok((low, high)) => {
+                    p.low_watermark = Some(low);
+                    p.high_watermark = Some(high);
+                }
+                Err(e) => debug!(
+                    "Unable to fetch the watermarks of partition {}-{}: {}",
+                    t.name, p.id, e
+                ),
+            }
+        }
+    }
+}
+
+/// Read the last record of every non-empty partition and return the newest timestamp.
+fn sample_head(
+    inner: &BaseConsumer<AuthContext>,
+    topic: &str,
+    partitions: &[PartitionOffsets],
+) -> Option<i64> {
+    let mut tpl = TopicPartitionList::new();
+    for p in partitions.iter().filter(|p| p.high > p.low) {
+        tpl.add_partition_offset(topic, p.id, Offset::Offset(p.high - 1))
+            .ok()?;
+    }
+
+    if tpl.count() == 0 {
+        return None;
+    }
+
+    inner.assign(&tpl).ok()?;
+
+    let deadline = Instant::now() + SAMPLE_HEAD_TIMEOUT_MS;
+    let mut remaining = tpl.count();
+    let mut newest = None;
+
+    while remaining > 0 {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match inner.poll(timeout) {
+            Some(Ok(m)) => {
+                newest = newest.max(m.timestamp().to_millis());
+                remaining -= 1;
+            }
+            Some(Err(e)) => {
+                warn!("Unable to sample head of topic {}: {}", topic, e);
+                break;
+            }
+            None => break,
+        }
+    }
+
+    if let Err(e) = inner.assign(&TopicPartitionList::new()) {
+        warn!("Unable to unassign sampled topic {}: {}", topic, e);
     }
+
+    newest
 }
 
 fn parse_broker(b: &MetadataBroker) -> BrokerMetadata {
@@ -101,7 +373,7 @@ fn parse_broker(b: &MetadataBroker) -> BrokerMetadata {
     }
 }
 
-fn parse_topic(t: &MetadataTopic) -> TopicMetadata {
+fn parse_topic(t: &MetadataTopic, classifier: &Classifier) -> TopicMetadata {
     let mut partitions = t
         .partitions()
         .iter()
@@ -113,17 +385,20 @@ fn parse_topic(t: &MetadataTopic) -> TopicMetadata {
             error: p
                 .error()
                 .map(|e| KafkaError::MetadataFetch(e.into()).to_string()),
+            low_watermark: None,
+            high_watermark: None,
         })
         .collect::<Vec<_>>();
-    partitions.sort_by(|a, b| a.id.cmp(&b.id));
+    partitions.sort_by_key(|p| p.id);
 
     TopicMetadata {
         name: t.name().to_string(),
         partitions,
+        category: classifier.topic(t.name()),
     }
 }
 
-fn parse_group(g: &GroupInfo) -> GroupMetadata {
+fn parse_group(g: &GroupInfo, classifier: &Classifier) -> GroupMetadata {
     let members = g
         .members()
         .iter()
@@ -138,5 +413,116 @@ fn parse_group(g: &GroupInfo) -> GroupMetadata {
         name: g.name().to_owned(),
         state: g.state().to_owned(),
         members,
+        managed_by_seekr: classifier.managed_by_seekr(g.name()),
     }
 }
+
+#[test]
+fn it_fills_the_watermarks_it_can_fetch_in_time() {
+    use rdkafka::types::RDKafkaErrorCode;
+
+    let partition = |id, error: Option<&str>| PartitionMetadata {
+        id,
+        leader: 0,
+        replicas: vec![0],
+        isr: vec![0],
+        error: error.map(str::to_string),
+        low_watermark: None,
+        high_watermark: None,
+    };
+    let topic = |name: &str, partitions| TopicMetadata {
+        name: name.to_string(),
+        partitions,
+        category: Classifier::default().topic(name),
+    };
+    let mut topics = vec![
+        topic(
+            "orders",
+            vec![
+                partition(0, None),
+                partition(1, None),
+                partition(2, Some("leader not available")),
+            ],
+        ),
+        topic("refunds", vec![partition(0, None)]),
+    ];
+
+    // Partition 1 times out, partition 2 is in error and never asked for.
+    let mut fetched = vec![];
+    let deadline = Instant::now() + Duration::from_secs(5);
+    fill_watermarks(&mut topics, deadline, |topic, partition, _| {
+        fetched.push((topic.to_string(), partition));
+        match partition {
+            1 => Err(KafkaError::MetadataFetch(
+                RDKafkaErrorCode::OperationTimedOut,
+            )),
+            _ => Ok((10, 42)),
+        }
+    });
+    assert_eq!(fetched.len(), 3);
+    let watermarks = |t: &TopicMetadata| {
+        t.partitions
+            .iter()
+            .map(|p| (p.low_watermark, p.high_watermark))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        watermarks(&topics[0]),
+        vec![(Some(10), Some(42)), (None, None), (None, None)]
+    );
+    assert_eq!(watermarks(&topics[1]), vec![(Some(10), Some(42))]);
+
+    // Past the deadline nothing more is fetched.
+    let mut late = vec![topic("audit", vec![partition(0, None)])];
+    fill_watermarks(&mut late, Instant::now(), |_, _, _| unreachable!());
+    assert_eq!(watermarks(&late[0]), vec![(None, None)]);
+}
+
+#[test]
+fn it_computes_group_lag_from_committed_offsets() {
+    let committed = vec![
+        ("orders".to_string(), 0, 40),
+        ("orders".to_string(), 1, 42),
+        // Truncated below the committed offset, e.g. after the topic was recreated.
+        ("refunds".to_string(), 0, 9),
+    ];
+    let highs = HashMap::from([
+        (("orders", 0), 42),
+        (("orders", 1), 42),
+        (("refunds", 0), 3),
+    ]);
+
+    let lag = group_lag(committed, |topic, partition| Ok(highs[&(topic, partition)])).unwrap();
+    let lags = lag
+        .iter()
+        .map(|l| (l.topic.as_str(), l.partition, l.high_watermark, l.lag))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lags,
+        vec![
+            ("orders", 0, 42, 2),
+            ("orders", 1, 42, 0),
+            ("refunds", 0, 3, 0)
+        ]
+    );
+}
+
+#[test]
+fn it_tells_topic_overrides_from_defaults() {
+    let entry = |name: &str, source: ConfigSource, is_default: bool| ConfigEntry {
+        name: name.to_string(),
+        value: Some("604800000".to_string()),
+        source,
+        is_read_only: false,
+        is_default,
+        is_sensitive: false,
+    };
+
+    let retention = topic_config_entry(&entry("retention.ms", ConfigSource::DynamicTopic, false));
+    assert_eq!(retention.source, TopicConfigSource::Topic);
+    assert!(!retention.is_default);
+
+    let cleanup = topic_config_entry(&entry("cleanup.policy", ConfigSource::Default, true));
+    assert_eq!(cleanup.source, TopicConfigSource::Default);
+    assert!(cleanup.is_default);
+}
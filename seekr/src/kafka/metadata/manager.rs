@@ -1,113 +1,805 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 use std::{collections::HashMap, result::Result, sync::Arc};
 
-use serde::Serialize;
-use tokio::sync::RwLock;
-use tokio::time::interval;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch, Notify, RwLock};
+use tokio::task::AbortHandle;
+use tokio::time::Instant;
 
 use crate::clusters::{cluster::Cluster, store::ClusterStore};
+use crate::counters::Counters;
 use crate::errors::AnyError;
+use crate::events::{EventLog, MetadataEvent};
+use crate::governance::owner::Owner;
+use crate::history::diff::diff;
+use crate::history::recorder::HistoryRecorder;
+use crate::ids::ClusterId;
 use crate::kafka::config;
+use crate::kafka::sensitive::redact;
+use crate::logs::dedup;
+use crate::metrics::{self, Registry};
+use crate::page::Page;
+use crate::request_id::RequestId;
 use crate::shutdown::Shutdown;
+use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+use crate::warmup::{
+    self, ClusterWarmup, PrioritizeError, Prioritized, WarmupProgress, WarmupState,
+};
 
+use super::classify::TopicCategory;
 use super::consumer::{KafkaMetadataConsumer, MetadataConsumer};
-use super::ClusterMetadata;
+use super::schedule::{self, PollOutcome, PollQueue, PollStats, DEFAULT_POLL_BUDGET};
+use super::throughput::{SkewConfig, ThroughputTracker, TopicThroughput};
+use super::{ClusterMetadata, GroupLag, TopicConfigEntry, TopicMetadata, TopicOffsets};
 
-#[derive(Debug, Clone, Serialize)]
+/// How long after a cluster missing from the cache was registered again
+/// before reads of it may register it once more, see `MetadataManager::heal`.
+pub const HEAL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CachedMetadataEntry {
     Unknown,
     Processing,
     Meta(ClusterMetadata),
+
+    /// Polls failed before any succeeded, and the brokers' error.
     Failed(String),
 }
 
+/// When a cluster's cached metadata was fetched, and how its polls fared since.
+///
+/// Failed polls keep the metadata of the last one that succeeded, so readers
+/// tell how old it is from here.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheInfo {
+    pub fetched_at: DateTime<Utc>,
+
+    /// Older than twice the interval the cluster is polled at.
+    pub stale: bool,
+    pub attempts_since_success: u32,
+
+    /// The broker error of the last poll, while polls keep failing.
+    pub last_error: Option<String>,
+}
+
+/// What a read of a consumer group's lag found.
+#[derive(Debug, PartialEq)]
+pub enum GroupLagRead {
+    Lag(Vec<GroupLag>),
+
+    /// The cluster's metadata isn't cached yet, or its last poll failed.
+    Processing,
+
+    MissingCluster,
+    MissingGroup,
+}
+
+/// A cluster's config a metadata consumer can't be built from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidConsumerConfig(pub String);
+
+impl fmt::Display for InvalidConsumerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid metadata consumer config: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidConsumerConfig {}
+
+/// Builds the consumer a `MetadataManager` polls a cluster with.
+pub type MetadataConsumerFactory = Arc<
+    dyn Fn(&Cluster) -> Result<Arc<dyn MetadataConsumer + Send + Sync>, AnyError> + Send + Sync,
+>;
+
 #[derive(Clone)]
 pub struct ConsumerContext {
     consumer: Arc<dyn MetadataConsumer + Send + Sync>,
     sd: Arc<Shutdown>,
+
+    /// Wakes the poll loop to pick up a next poll brought forward.
+    poke: Arc<Notify>,
+
+    /// When the last completed poll started, which answers the refreshes
+    /// requested before then.
+    polled: watch::Sender<Option<Instant>>,
+
+    /// Aborts the poll task of a consumer that won't stop by the deadline.
+    task: Arc<Mutex<Option<AbortHandle>>>,
 }
 
 pub struct MetadataManager {
     store: Arc<dyn ClusterStore + Send + Sync>,
+    factory: MetadataConsumerFactory,
+    history: Option<Arc<HistoryRecorder>>,
+    events: Arc<EventLog>,
+    counters: Option<Arc<Counters>>,
+    metrics: Arc<Registry>,
+    queue: PollQueue,
+
+    /// When clusters missing from the cache were last registered again by `heal`.
+    heals: Mutex<HashMap<ClusterId, Instant>>,
+
+    /// Identifies this manager's cache, whose versions only compare to its own.
+    instance: String,
+    started: Instant,
+
+    /// Set once the clusters are registered, or a primary's cache synced.
+    ready: AtomicBool,
     state: Arc<RwLock<State>>,
 }
 
 struct State {
-    context: HashMap<i64, ConsumerContext>,
-    cache: HashMap<i64, CachedMetadataEntry>,
+    context: HashMap<ClusterId, ConsumerContext>,
+    /// Entries are shared, so readers snapshot them without copying.
+    cache: HashMap<ClusterId, Arc<CachedMetadataEntry>>,
+
+    /// Topics whose watermarks are fetched on each poll, flagged `true` when
+    /// the newest record timestamp should be sampled as well.
+    watches: HashMap<ClusterId, HashMap<String, bool>>,
+    offsets: HashMap<ClusterId, Vec<TopicOffsets>>,
+    throughput: HashMap<ClusterId, ThroughputTracker>,
+
+    /// When each cluster is polled next.
+    next_poll: HashMap<ClusterId, Instant>,
+    /// Clusters with refreshes waiting for their next poll to start.
+    refreshes: HashSet<ClusterId>,
+    polls: HashMap<ClusterId, PollStats>,
+    warmup: HashMap<ClusterId, Warmup>,
+
+    /// When each cluster's metadata was last polled successfully.
+    fetches: HashMap<ClusterId, Fetch>,
+
+    /// Bumped whenever a cluster's cached metadata or watermarks change.
+    version: u64,
+    versions: HashMap<ClusterId, u64>,
+}
+
+impl State {
+    /// Note a change to the cached metadata or watermarks of a cluster.
+    fn touch(&mut self, id: ClusterId) {
+        self.version += 1;
+        self.versions.insert(id, self.version);
+    }
+
+    fn cache_info(&self, id: ClusterId) -> Option<CacheInfo> {
+        let fetch = self.fetches.get(&id)?;
+        let interval = self.polls.get(&id).map(|s| s.next_interval());
+        Some(CacheInfo {
+            fetched_at: fetch.fetched_at,
+            stale: interval.is_some_and(|i| fetch.at.elapsed() > i * 2),
+            attempts_since_success: fetch.attempts_since_success,
+            last_error: fetch.last_error.clone(),
+        })
+    }
+
+    /// Note a poll of the cluster completing, the first one ending its warm-up.
+    fn polled(&mut self, id: ClusterId) {
+        let Some(w) = self.warmup.get_mut(&id) else {
+            return;
+        };
+        if w.first_poll.is_some() {
+            return;
+        }
+
+        let latency = w.registered.elapsed();
+        w.first_poll = Some(latency);
+        info!(
+            cluster_id = id.as_i64(), first_poll_ms = warmup::millis(latency);
+            "First metadata poll of cluster {} completed in {}ms", id, latency.as_millis()
+        );
+    }
+}
+
+/// The last successful poll of a cluster, and the ones failing after it.
+struct Fetch {
+    at: Instant,
+    fetched_at: DateTime<Utc>,
+    attempts_since_success: u32,
+    last_error: Option<String>,
+}
+
+/// When a cluster was registered, and how long its first poll took.
+struct Warmup {
+    registered: Instant,
+    first_poll: Option<Duration>,
 }
 
 impl MetadataManager {
     pub fn new(store: Arc<dyn ClusterStore + Send + Sync>) -> Self {
+        let factory: MetadataConsumerFactory = Arc::new(|c| {
+            let consumer = KafkaMetadataConsumer::create(c)?;
+            Ok(Arc::new(consumer))
+        });
+
+        Self::with_factory(store, factory)
+    }
+
+    pub fn with_factory(
+        store: Arc<dyn ClusterStore + Send + Sync>,
+        factory: MetadataConsumerFactory,
+    ) -> Self {
         let state = State {
             context: HashMap::new(),
             cache: HashMap::new(),
+            watches: HashMap::new(),
+            offsets: HashMap::new(),
+            throughput: HashMap::new(),
+            next_poll: HashMap::new(),
+            refreshes: HashSet::new(),
+            polls: HashMap::new(),
+            warmup: HashMap::new(),
+            fetches: HashMap::new(),
+            version: 0,
+            versions: HashMap::new(),
         };
         MetadataManager {
             store,
+            factory,
+            history: None,
+            events: Arc::new(EventLog::default()),
+            counters: None,
+            metrics: metrics::registry(),
+            queue: PollQueue::new(DEFAULT_POLL_BUDGET),
+            heals: Mutex::new(HashMap::new()),
+            instance: uuid::Uuid::new_v4().simple().to_string(),
+            started: Instant::now(),
+            ready: AtomicBool::new(false),
             state: Arc::new(RwLock::new(state)),
         }
     }
 
+    /// Record the metadata history of polled clusters.
+    pub fn with_history(mut self, history: Arc<HistoryRecorder>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Receive the changes polls find in the metadata of every cluster.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<MetadataEvent> {
+        self.events.subscribe()
+    }
+
+    /// The metadata changes of every cluster, along with the last ones kept.
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    /// Tally the topics and partitions of polled clusters.
+    pub fn with_counters(mut self, counters: Arc<Counters>) -> Self {
+        self.counters = Some(counters);
+        self
+    }
+
+    /// Record poll metrics in the given registry rather than the shared one.
+    pub fn with_metrics(mut self, metrics: Arc<Registry>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Run at most `budget` metadata polls at once.
+    pub fn with_poll_budget(mut self, budget: usize) -> Self {
+        self.queue = PollQueue::new(budget);
+        self
+    }
+
     pub async fn start(self: Arc<Self>) -> Result<(), AnyError> {
         debug!("Starting Metadata service...");
 
-        // Fetch all registered clusters from db
-        let clusters = self.store.list(None).await?;
+        // Fetch all registered clusters from db, warming up the most important ones first
+        let mut clusters = self.store.list(None, Page::ALL).await?;
+        clusters.sort_by_key(schedule::priority);
 
         for c in clusters {
-            self.clone().init(c).await?;
+            self.clone().init(c, true).await?;
         }
 
+        self.ready.store(true, Ordering::SeqCst);
         Ok(())
     }
 
+    /// Whether the manager serves metadata, having registered the clusters
+    /// or synced a primary's cache. It stays ready once stopped, since the
+    /// cache is still served.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
     pub async fn stop(self: Arc<Self>) {
         debug!("Stopping Metadata manager...");
         debug!("Metadata manager shutdown has been initiated...");
 
-        let state = self.state.read().await;
-        let contexts = state.context.values();
+        // Polls still fetching need the state to finish, so it mustn't stay locked.
+        let contexts = self.begin_stop().await;
+        join_all(contexts.values().map(|c| c.sd.wait_complete())).await;
+        self.end_stop(&contexts).await;
 
-        for c in contexts {
-            c.sd.begin();
-            c.sd.wait_complete().await;
+        debug!("Metadata manager shutdown has been completed...");
+    }
+
+    /// Stop like `stop`, giving the polls until the deadline to wind down.
+    /// Those still running then, like one stuck in a blocking fetch, are
+    /// aborted and their clusters returned.
+    pub async fn stop_within(self: Arc<Self>, deadline: Duration) -> Vec<ClusterId> {
+        debug!("Stopping Metadata manager within {:?}...", deadline);
+
+        let until = Instant::now() + deadline;
+        let contexts = self.begin_stop().await;
+        let stuck = join_all(contexts.iter().map(|(id, c)| async move {
+            let stopped = tokio::time::timeout_at(until, c.sd.wait_complete()).await;
+            stopped.is_err().then_some(*id)
+        }))
+        .await
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        for id in &stuck {
+            warn!(
+                "Metadata poll of cluster {} didn't stop within {:?}, aborting it",
+                id, deadline
+            );
+            if let Some(task) = contexts[id].task.lock().unwrap().as_ref() {
+                task.abort();
+            }
         }
+        self.end_stop(&contexts).await;
 
         debug!("Metadata manager shutdown has been completed...");
+        stuck
+    }
+
+    async fn begin_stop(&self) -> HashMap<ClusterId, ConsumerContext> {
+        let contexts = self.state.read().await.context.clone();
+        for c in contexts.values() {
+            c.sd.begin();
+        }
+        contexts
     }
 
-    pub async fn register(self: Arc<Self>, c: Cluster) {
-        info!("Registering metadata consumer for cluster {}", c.id);
+    async fn end_stop(&self, contexts: &HashMap<ClusterId, ConsumerContext>) {
+        // The cache is kept, so the clusters are served until they're polled again.
+        let mut state = self.state.write().await;
+        state.context.retain(|id, _| !contexts.contains_key(id));
+    }
 
-        let result = self.init(c).await;
-        if result.is_err() {
-            error!(
-                "Error: registering cluster: {}",
-                result.unwrap_err().source().unwrap()
-            );
+    /// Start polling the cluster, logging under the id of the request that
+    /// asked for it, if any.
+    pub async fn register(self: Arc<Self>, c: Cluster, request_id: Option<RequestId>) {
+        RequestId::within(request_id, async move {
+            info!("Registering metadata consumer for cluster {}", c.id);
+            debug!("cluster {} config: {:?}", c.id, redact(&c.config));
+
+            if let Err(e) = self.init(c, false).await {
+                error!("Error: registering cluster: {}", e);
+            }
+        })
+        .await
+    }
+
+    /// Poll the cluster with a consumer built from its updated config, keeping
+    /// its cached metadata so it's served throughout.
+    ///
+    /// The new consumer is built before the old one is stopped: a config it
+    /// can't be built from fails with `InvalidConsumerConfig` and leaves the
+    /// cluster polled as it was. A cluster that isn't polled yet is registered.
+    pub async fn reregister(self: Arc<Self>, c: Cluster) -> Result<(), AnyError> {
+        info!("Re-registering metadata consumer for cluster {}", c.id);
+        debug!("cluster {} config: {:?}", c.id, redact(&c.config));
+
+        let consumer = (self.factory)(&c).map_err(|e| InvalidConsumerConfig(e.to_string()))?;
+
+        let current = self.state.read().await.context.get(&c.id).cloned();
+        if let Some(current) = current {
+            current.sd.begin();
+            current.sd.wait_complete().await;
+        }
+
+        // The stopped context is replaced rather than removed first, so a
+        // registration racing this one finds the cluster registered.
+        self.state.write().await.throughput.remove(&c.id);
+        self.launch(c, consumer, false).await;
+        Ok(())
+    }
+
+    /// Register a cluster that exists but isn't cached, e.g. after its
+    /// registration failed, at most once per `HEAL_INTERVAL` so clients reading
+    /// it over and over don't set off a registration each time.
+    ///
+    /// Returns whether a registration was started.
+    pub async fn heal(self: Arc<Self>, c: Cluster) -> bool {
+        let now = Instant::now();
+        {
+            let mut heals = self.heals.lock().unwrap();
+            if matches!(heals.get(&c.id), Some(at) if now.duration_since(*at) < HEAL_INTERVAL) {
+                return false;
+            }
+            heals.retain(|_, at| now.duration_since(*at) < HEAL_INTERVAL);
+            heals.insert(c.id, now);
         }
+
+        info!(
+            cluster_id = c.id.as_i64();
+            "Cluster {} exists but isn't cached, registering it again", c.id
+        );
+        self.register(c, RequestId::current()).await;
+        true
+    }
+
+    /// Set who notifications about the cluster are routed to.
+    pub async fn set_owner(&self, id: ClusterId, owner: Option<Owner>) {
+        if let Some(history) = &self.history {
+            history.set_owner(id, owner).await;
+        }
+    }
+
+    /// Stop polling the cluster and forget its metadata, logging under the id
+    /// of the request that asked for it, if any.
+    pub async fn remove(self: Arc<Self>, id: ClusterId, request_id: Option<RequestId>) {
+        RequestId::within(request_id, self.forget(id)).await
     }
 
-    pub async fn remove(self: Arc<Self>, id: i64) {
+    async fn forget(self: Arc<Self>, id: ClusterId) {
         info!("Removing metadata consumer for cluster {}", id);
 
         let mut state = self.state.write().await;
-        let context = state.context.remove(&id);
-        if context.is_some() {
-            context.unwrap().sd.begin();
+        if let Some(context) = state.context.remove(&id) {
+            context.sd.begin();
+        }
+        state.offsets.remove(&id);
+        state.throughput.remove(&id);
+        state.next_poll.remove(&id);
+        state.refreshes.remove(&id);
+        state.polls.remove(&id);
+        state.warmup.remove(&id);
+        state.fetches.remove(&id);
+        state.touch(id);
+        drop(state);
+        self.queue.forget(id);
+        self.metrics.forget("cluster_id", &id.to_string());
+
+        if let Some(counters) = &self.counters {
+            counters.clear_metadata(id);
+        }
+        self.events.forget(id);
+        if let Some(history) = &self.history {
+            history.forget(id).await;
+        }
+    }
+
+    /// The cached metadata of the cluster, logging under the id of the request
+    /// that reads it, if any.
+    pub async fn get(
+        self: Arc<Self>,
+        id: ClusterId,
+        request_id: Option<RequestId>,
+    ) -> Result<Option<Arc<CachedMetadataEntry>>, AnyError> {
+        RequestId::within(request_id, async move {
+            info!("Fetching cached metadata for cluster {}", id);
+
+            // The entry is shared rather than copied, large clusters' metadata
+            // runs to megabytes.
+            Ok(self.snapshot(id).await)
+        })
+        .await
+    }
+
+    /// The cached entry as it is now, sharing it rather than copying it, so
+    /// large entries can be read without holding on to the cache.
+    pub async fn snapshot(&self, id: ClusterId) -> Option<Arc<CachedMetadataEntry>> {
+        self.state.read().await.cache.get(&id).cloned()
+    }
+
+    /// When the cluster's cached metadata was fetched and whether it's stale,
+    /// once a poll of this instance succeeded.
+    pub async fn cache_info(&self, id: ClusterId) -> Option<CacheInfo> {
+        let state = self.state.read().await;
+        state.cache_info(id)
+    }
+
+    /// The cache info of every cluster that has any, like `cache_info`'s.
+    pub async fn cache_infos(&self) -> HashMap<ClusterId, CacheInfo> {
+        let state = self.state.read().await;
+        let ids = state.fetches.keys();
+        ids.filter_map(|id| Some((*id, state.cache_info(*id)?)))
+            .collect()
+    }
+
+    /// The cached entry of every cluster, shared like `snapshot`'s.
+    pub async fn snapshots(&self) -> Vec<(ClusterId, Arc<CachedMetadataEntry>)> {
+        let state = self.state.read().await;
+        state.cache.iter().map(|(id, e)| (*id, e.clone())).collect()
+    }
+
+    /// Replace the topics whose watermarks are fetched alongside the cluster metadata.
+    ///
+    /// Watermarks are fetched on the cluster's metadata poll, by the consumer
+    /// that already polls it, so watching topics never adds broker connections.
+    pub async fn watch(&self, id: ClusterId, topics: HashMap<String, bool>) {
+        let mut state = self.state.write().await;
+        if topics.is_empty() {
+            state.watches.remove(&id);
+            state.offsets.remove(&id);
+        } else {
+            state.watches.insert(id, topics);
+        }
+    }
+
+    /// The watermarks fetched on the last poll.
+    pub async fn offsets(&self, id: ClusterId) -> Option<Vec<TopicOffsets>> {
+        self.state.read().await.offsets.get(&id).cloned()
+    }
+
+    /// How far the group is behind in the partitions it committed offsets
+    /// for, read from the brokers by the consumer polling the cluster.
+    pub async fn group_lag(&self, id: ClusterId, group: &str) -> Result<GroupLagRead, AnyError> {
+        let state = self.state.read().await;
+        let Some(entry) = state.cache.get(&id).cloned() else {
+            return Ok(GroupLagRead::MissingCluster);
+        };
+        let CachedMetadataEntry::Meta(metadata) = entry.as_ref() else {
+            return Ok(GroupLagRead::Processing);
+        };
+        if !metadata.groups.iter().any(|g| g.name == group) {
+            return Ok(GroupLagRead::MissingGroup);
+        }
+        let Some(consumer) = state.context.get(&id).map(|c| c.consumer.clone()) else {
+            return Err(format!("cluster {} isn't polled by this instance", id).into());
+        };
+        drop(state);
+
+        let lag = consumer.fetch_group_offsets(group, metadata).await?;
+        Ok(GroupLagRead::Lag(lag))
+    }
+
+    /// The settings of the topic, described by the brokers to the consumer
+    /// polling the cluster, which connects for it on the first one.
+    pub async fn topic_config(
+        &self,
+        id: ClusterId,
+        topic: &str,
+    ) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        let consumer = self
+            .state
+            .read()
+            .await
+            .context
+            .get(&id)
+            .map(|c| c.consumer.clone());
+        let Some(consumer) = consumer else {
+            return Err(format!("cluster {} isn't polled by this instance", id).into());
+        };
+        consumer.fetch_topic_config(topic).await
+    }
+
+    /// The per-partition throughput of the cluster's topics, when `throughput.enabled` is set.
+    pub async fn throughput(&self, id: ClusterId) -> Option<HashMap<String, TopicThroughput>> {
+        let state = self.state.read().await;
+        state.throughput.get(&id).map(|t| t.topics().clone())
+    }
+
+    /// How long until the cluster is polled next, and its cached metadata may change.
+    pub async fn retry_after(&self, id: ClusterId) -> Option<Duration> {
+        let state = self.state.read().await;
+        let next = state.next_poll.get(&id)?;
+        Some(next.saturating_duration_since(Instant::now()))
+    }
+
+    /// The cluster's priority and its configured and effective poll intervals.
+    pub async fn polling(&self, id: ClusterId) -> Option<PollStats> {
+        self.state.read().await.polls.get(&id).cloned()
+    }
+
+    /// Poll the cluster now, however long its interval was stretched or its
+    /// circuit holds off polls, and return the entry the poll cached: its
+    /// metadata or the error it failed with. `None` when the cluster isn't
+    /// polled by this instance.
+    ///
+    /// The poll starts after the refresh, so it sees what changed before it.
+    /// Refreshes arriving meanwhile, or while a poll is under way, wait for
+    /// the same next poll rather than fetching one each.
+    pub async fn refresh(&self, id: ClusterId) -> Option<Arc<CachedMetadataEntry>> {
+        let requested = Instant::now();
+        let mut state = self.state.write().await;
+        let context = state.context.get(&id)?;
+        let (poke, mut polled) = (context.poke.clone(), context.polled.subscribe());
+        if let Some(adaptive) = state.polls.get_mut(&id).and_then(|s| s.adaptive.as_mut()) {
+            adaptive.reset();
+        }
+        state.next_poll.insert(id, requested);
+        state.refreshes.insert(id);
+        drop(state);
+
+        self.queue.prioritize(id);
+        poke.notify_one();
+
+        // The poll loop ends once the cluster is removed, dropping the sender.
+        loop {
+            if polled
+                .borrow_and_update()
+                .is_some_and(|started| started >= requested)
+            {
+                break;
+            }
+            if polled.changed().await.is_err() {
+                break;
+            }
+        }
+        self.snapshot(id).await
+    }
+
+    /// Note the cluster being read, which snaps a stretched interval back so
+    /// the clusters people look at are kept fresh.
+    pub async fn activity(&self, id: ClusterId) {
+        let stretched = |state: &State| {
+            let adaptive = state.polls.get(&id).and_then(|s| s.adaptive.as_ref());
+            adaptive.is_some_and(|a| a.is_stretched())
+        };
+        if !stretched(&*self.state.read().await) {
+            return;
+        }
+
+        let mut state = self.state.write().await;
+        if !stretched(&state) {
+            return;
+        }
+        let Some(stats) = state.polls.get_mut(&id) else {
+            return;
+        };
+        let last_poll = stats.last_poll;
+        if let Some(adaptive) = stats.adaptive.as_mut() {
+            adaptive.reset();
+        }
+        let base = stats.interval;
+        if let (Some(last), Some(next)) = (last_poll, state.next_poll.get_mut(&id)) {
+            *next = std::cmp::min(*next, last + base);
+        }
+        let poke = state.context.get(&id).map(|c| c.poke.clone());
+        drop(state);
+
+        debug!("Interval of cluster {} snapped back on activity", id);
+        if let Some(poke) = poke {
+            poke.notify_one();
+        }
+    }
+
+    /// How far the cached clusters warmed up since startup, by the state of
+    /// their cache entries.
+    pub async fn warmup(&self) -> WarmupProgress {
+        let state = self.state.read().await;
+        let clusters = state
+            .cache
+            .iter()
+            .map(|(id, entry)| {
+                let warmup = state.warmup.get(id);
+                ClusterWarmup {
+                    cluster_id: *id,
+                    state: WarmupState::of(entry),
+                    registered_ms: warmup
+                        .map(|w| warmup::millis(w.registered.duration_since(self.started))),
+                    first_poll_ms: warmup.and_then(|w| w.first_poll).map(warmup::millis),
+                    queue_position: self.queue.position(*id),
+                }
+            })
+            .collect();
+
+        WarmupProgress::new(self.started.elapsed(), clusters)
+    }
+
+    /// Move the next polls of the clusters to the front of the line, unless
+    /// they warmed up already. Among themselves, they keep their priority.
+    pub async fn prioritize(&self, ids: &[ClusterId]) -> Prioritized {
+        let state = self.state.read().await;
+        let mut prioritized = Prioritized::default();
+        for id in ids {
+            let error = |message: &str| PrioritizeError {
+                cluster_id: *id,
+                message: message.to_string(),
+            };
+            match state.cache.get(id).map(|e| e.as_ref()) {
+                None => prioritized.errors.push(error("Unknown cluster")),
+                Some(CachedMetadataEntry::Meta(_)) => prioritized.already_ready.push(*id),
+                Some(_) if !state.context.contains_key(id) => prioritized
+                    .errors
+                    .push(error("The cluster isn't polled by this instance")),
+                Some(_) => {
+                    self.queue.prioritize(*id);
+                    prioritized.prioritized.push(*id);
+                }
+            }
         }
+
+        prioritized
     }
 
-    pub async fn get(self: Arc<Self>, id: i64) -> Result<Option<CachedMetadataEntry>, AnyError> {
-        info!("Fetching cached metadata for cluster {}", id);
+    /// The cached metadata changed since the cursor, or all of it when the
+    /// cursor is of another instance, e.g. of a primary that restarted.
+    pub async fn changes(&self, since: Option<&SyncCursor>) -> CacheSync {
+        let state = self.state.read().await;
+        let since = since
+            .filter(|c| c.instance == self.instance)
+            .map(|c| c.version);
+
+        let entries = state
+            .cache
+            .iter()
+            .filter(|(id, _)| match since {
+                Some(version) => state.versions.get(id).is_some_and(|v| *v > version),
+                None => true,
+            })
+            .map(|(id, entry)| SyncedEntry {
+                cluster_id: *id,
+                entry: entry.as_ref().clone(),
+                offsets: state.offsets.get(id).cloned(),
+            })
+            .collect();
+
+        CacheSync {
+            cursor: SyncCursor {
+                instance: self.instance.clone(),
+                version: state.version,
+            },
+            full: since.is_none(),
+            entries,
+            clusters: state.cache.keys().copied().collect(),
+        }
+    }
 
+    /// Bring the gauges read at scrape time up to date: the number of clusters
+    /// polled, and the age of each one's cached metadata.
+    pub async fn export_metrics(&self) {
         let state = self.state.read().await;
-        let meta = state.cache.get(&id);
-        Ok(meta.map(|m| m.to_owned()))
+        self.metrics.set(
+            &metrics::CLUSTERS_REGISTERED,
+            &[],
+            state.context.len() as f64,
+        );
+        for (id, fetch) in &state.fetches {
+            self.metrics.set(
+                &metrics::METADATA_CACHE_AGE,
+                &[("cluster_id", &id.to_string())],
+                fetch.at.elapsed().as_secs_f64(),
+            );
+        }
+    }
+
+    /// Apply the changes synced from a primary's cache, dropping the clusters it no longer has.
+    pub async fn apply(&self, sync: CacheSync) {
+        self.ready.store(true, Ordering::SeqCst);
+        let mut state = self.state.write().await;
+        let clusters = sync
+            .clusters
+            .iter()
+            .collect::<std::collections::HashSet<_>>();
+        state.cache.retain(|id, _| clusters.contains(id));
+        state.offsets.retain(|id, _| clusters.contains(id));
+
+        for e in sync.entries {
+            if let Some(counters) = &self.counters {
+                match &e.entry {
+                    CachedMetadataEntry::Meta(metadata) => {
+                        counters.set_metadata(e.cluster_id, metadata)
+                    }
+                    _ => counters.clear_metadata(e.cluster_id),
+                }
+            }
+            state.cache.insert(e.cluster_id, Arc::new(e.entry));
+            match e.offsets {
+                Some(offsets) => state.offsets.insert(e.cluster_id, offsets),
+                None => state.offsets.remove(&e.cluster_id),
+            };
+        }
     }
 
-    async fn init(self: Arc<Self>, c: Cluster) -> Result<(), AnyError> {
+    /// Start polling the cluster, its polls after the first staggered when
+    /// it's started along with the other clusters.
+    async fn init(self: Arc<Self>, c: Cluster, stagger: bool) -> Result<(), AnyError> {
         info!("Initializing metadata consumer for cluster {}...", c.id);
 
         // Acquire read lock and check if consumer exist
@@ -122,51 +814,97 @@ impl MetadataManager {
         }
 
         // Create consumer for cluster
-        let consumer = Arc::new(KafkaMetadataConsumer::create(&c)?);
+        let consumer = (self.factory)(&c)?;
+        manager.state.write().await.warmup.insert(
+            c.id,
+            Warmup {
+                registered: Instant::now(),
+                first_poll: None,
+            },
+        );
+        self.launch(c, consumer, stagger).await;
+        Ok(())
+    }
+
+    /// Track the cluster's consumer and start polling with it.
+    async fn launch(
+        self: Arc<Self>,
+        c: Cluster,
+        consumer: Arc<dyn MetadataConsumer + Send + Sync>,
+        stagger: bool,
+    ) {
         let sd = Arc::new(Shutdown::new());
-        let context = ConsumerContext { consumer, sd };
+        let context = ConsumerContext {
+            consumer,
+            sd,
+            poke: Arc::new(Notify::new()),
+            polled: watch::channel(None).0,
+            task: Arc::default(),
+        };
 
         // Acquire write lock and track consumers
-        let mut state = manager.state.write().await;
+        let mut state = self.state.write().await;
         state.context.insert(c.id, context.clone());
-        state.cache.insert(c.id, CachedMetadataEntry::Processing);
+        state.next_poll.insert(c.id, Instant::now());
+
+        // Metadata synced from a primary is served until the first poll replaces it.
+        if let Entry::Vacant(e) = state.cache.entry(c.id) {
+            e.insert(Arc::new(CachedMetadataEntry::Processing));
+            state.touch(c.id);
+        }
         drop(state);
 
+        self.set_owner(c.id, c.owner.clone()).await;
+
         // Spawn thread to poll metadata in the background
         let manager = self.clone();
-        tokio::spawn(async move { manager.poll(c, context).await });
-        Ok(())
+        let task = context.task.clone();
+        let handle = tokio::spawn(async move { manager.poll(c, context, stagger).await });
+        *task.lock().unwrap() = Some(handle.abort_handle());
     }
 
-    async fn poll(self: Arc<Self>, cluster: Cluster, context: ConsumerContext) {
+    async fn poll(self: Arc<Self>, cluster: Cluster, context: ConsumerContext, stagger: bool) {
         let refresh: u64 = cluster
             .config
             .get(config::METADATA_POLL_INTERVAL)
             .unwrap_or(&String::from("30000"))
             .parse()
             .unwrap_or(30_000);
-        let mut interval = interval(Duration::from_millis(refresh));
+        let refresh = Duration::from_millis(refresh);
+        let priority = schedule::priority(&cluster);
+        let throughput = cluster
+            .config
+            .get(config::THROUGHPUT_ENABLED)
+            .is_some_and(|v| v == "true");
 
-        loop {
-            tokio::select! {
-                _ = interval.tick() => {
-                    trace!("Polling metadata for cluster {}...", cluster.id);
-
-                    let result = context.consumer.fetch_meta().await;
-                    if result.is_err() {
-                        let msg =  format!("Error: Failed to fetch metadata for cluster {} - {:?}", cluster.id, result.err());
-                        error!("{}", msg);
-
-                        let mut state = self.state.write().await;
-                        state.cache.insert(cluster.id, CachedMetadataEntry::Failed(msg));
-                        continue;
-                    }
+        let adaptive = schedule::adaptive(&cluster, refresh);
+
+        let mut state = self.state.write().await;
+        state.polls.insert(
+            cluster.id,
+            PollStats::new(priority, refresh).with_adaptive(adaptive),
+        );
+        if throughput {
+            let tracker = ThroughputTracker::new(SkewConfig::from(&cluster));
+            state.throughput.insert(cluster.id, tracker);
+        }
+        drop(state);
 
-                    let metadata = result.unwrap();
-                    trace!("Metadata: {:?}", metadata);
+        // Clusters started together poll in lockstep after their first poll,
+        // unless each one's second poll comes a different share of the interval later.
+        let mut stagger = stagger.then(|| schedule::stagger(cluster.id, refresh));
 
-                    let mut state = self.state.write().await;
-                    state.cache.insert(cluster.id, CachedMetadataEntry::Meta(metadata));
+        let mut breaker = schedule::breaker(&cluster);
+        let mut due = Instant::now();
+        loop {
+            let permit = tokio::select! {
+                permit = self.queue.acquire(cluster.id, priority, due) => permit,
+                _ = context.poke.notified() => {
+                    // A refresh or a read brought the next poll forward.
+                    if let Some(next) = self.state.read().await.next_poll.get(&cluster.id) {
+                        due = std::cmp::min(due, *next);
+                    }
+                    continue;
                 }
                 _ = context.sd.wait_begin() => {
                     debug!("Metadata manager poll shutdown started...");
@@ -175,7 +913,1347 @@ impl MetadataManager {
                     context.sd.complete();
                     break;
                 }
+            };
+
+            // Polls granted late push back the next one, stretching the effective interval.
+            let now = Instant::now();
+            self.waited(&cluster, now.saturating_duration_since(due), refresh);
+            let mut state = self.state.write().await;
+            due = now
+                + state
+                    .polls
+                    .get(&cluster.id)
+                    .map_or(refresh, |s| s.next_interval());
+            state.next_poll.insert(cluster.id, due);
+            state.refreshes.remove(&cluster.id);
+            if let Some(stats) = state.polls.get_mut(&cluster.id) {
+                stats.polled(now);
+            }
+            drop(state);
+
+            // Shutdown doesn't wait for a slow fetch, so a demoted primary stops promptly.
+            let started = Instant::now();
+            let outcome = tokio::select! {
+                outcome = self.fetch(&cluster, &context, refresh, throughput) => outcome,
+                _ = context.sd.wait_begin() => PollOutcome::Failed,
+            };
+            drop(permit);
+            context.polled.send_replace(Some(now));
+
+            let id = cluster.id.to_string();
+            let result = match outcome {
+                PollOutcome::Failed => "failure",
+                _ => "success",
+            };
+            self.metrics.inc(
+                &metrics::METADATA_POLLS,
+                &[("cluster_id", &id), ("outcome", result)],
+            );
+            self.metrics.set(
+                &metrics::METADATA_POLL_DURATION,
+                &[("cluster_id", &id)],
+                started.elapsed().as_secs_f64(),
+            );
+
+            let was_open = breaker.is_open();
+            breaker.record(outcome != PollOutcome::Failed);
+            match (was_open, breaker.is_open()) {
+                (false, true) => warn!(
+                    "Circuit opened for cluster {} after {} failed metadata polls",
+                    cluster.id,
+                    schedule::CIRCUIT_THRESHOLD
+                ),
+                (true, false) => info!("Circuit closed for cluster {}", cluster.id),
+                _ => {}
             }
+
+            // Adaptive clusters wait longer while their metadata doesn't change,
+            // an open circuit holds off the next poll for its cooldown instead.
+            let backoff = breaker
+                .is_open()
+                .then(|| breaker.backoff(cluster.id, refresh));
+            let offset = stagger.take().unwrap_or_default();
+            let mut state = self.state.write().await;
+            let interval = match state.polls.get_mut(&cluster.id) {
+                Some(stats) => {
+                    stats.backoff = backoff;
+                    stats.record(outcome)
+                }
+                None => refresh,
+            };
+            // Refreshes requested during the poll wait for another one, right away,
+            // whatever the circuit.
+            due = if state.refreshes.contains(&cluster.id) {
+                Instant::now()
+            } else if let Some(backoff) = backoff {
+                now + backoff
+            } else {
+                now + interval + offset
+            };
+            state.next_poll.insert(cluster.id, due);
+        }
+    }
+
+    /// Note how long a due poll waited for the poll budget, for tuning it.
+    fn waited(&self, cluster: &Cluster, waited: Duration, interval: Duration) {
+        self.metrics.set(
+            &metrics::METADATA_POLL_QUEUE_WAIT,
+            &[("cluster_id", &cluster.id.to_string())],
+            waited.as_secs_f64(),
+        );
+        if waited > interval {
+            info!(
+                "Metadata poll of cluster {} waited {:?} for the poll budget, longer than its interval",
+                cluster.id, waited
+            );
+        } else if !waited.is_zero() {
+            debug!(
+                "Metadata poll of cluster {} waited {:?} for the poll budget",
+                cluster.id, waited
+            );
         }
     }
+
+    /// Poll the cluster, returning how its metadata compares to the cached one.
+    async fn fetch(
+        &self,
+        cluster: &Cluster,
+        context: &ConsumerContext,
+        refresh: Duration,
+        throughput: bool,
+    ) -> PollOutcome {
+        trace!("Polling metadata for cluster {}...", cluster.id);
+
+        let result = async {
+            fail_point!(crate::failpoints::METADATA_FETCH, cluster.id)?;
+            context.consumer.fetch_meta().await
+        }
+        .await;
+        let metadata = match result {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                let msg = format!(
+                    "Error: Failed to fetch metadata for cluster {} - {:?}",
+                    cluster.id, e
+                );
+                log_dedup!(
+                    dedup::key(dedup::METADATA_POLL, cluster.id),
+                    log::Level::Error,
+                    cluster_id = cluster.id.as_i64(); "{}", msg
+                );
+
+                // The last good metadata is kept, noting the failure alongside it.
+                let mut state = self.state.write().await;
+                let kept = match state.fetches.get_mut(&cluster.id) {
+                    Some(fetch) => {
+                        fetch.attempts_since_success += 1;
+                        fetch.last_error = Some(msg);
+                        true
+                    }
+                    None => {
+                        state
+                            .cache
+                            .insert(cluster.id, Arc::new(CachedMetadataEntry::Failed(msg)));
+                        false
+                    }
+                };
+                state.touch(cluster.id);
+                state.polled(cluster.id);
+                drop(state);
+
+                if let (false, Some(counters)) = (kept, &self.counters) {
+                    counters.clear_metadata(cluster.id);
+                }
+                return PollOutcome::Failed;
+            }
+        };
+        trace!("Metadata: {:?}", metadata);
+        clear_dedup!(dedup::key(dedup::METADATA_POLL, cluster.id));
+
+        let mut state = self.state.write().await;
+        let fetched_at = Utc::now();
+        let (outcome, changes) = match state.cache.get(&cluster.id).map(|e| e.as_ref()) {
+            Some(CachedMetadataEntry::Meta(cached)) if is_unchanged(cached, &metadata) => {
+                (PollOutcome::Unchanged, vec![])
+            }
+            // The first metadata of a cluster isn't a change of it.
+            Some(CachedMetadataEntry::Meta(cached)) => {
+                (PollOutcome::Changed, diff(cached, &metadata))
+            }
+            _ => (PollOutcome::Changed, vec![]),
+        };
+        state.cache.insert(
+            cluster.id,
+            Arc::new(CachedMetadataEntry::Meta(metadata.clone())),
+        );
+        state.fetches.insert(
+            cluster.id,
+            Fetch {
+                at: Instant::now(),
+                fetched_at,
+                attempts_since_success: 0,
+                last_error: None,
+            },
+        );
+        state.touch(cluster.id);
+        state.polled(cluster.id);
+        let mut watched = state.watches.get(&cluster.id).cloned().unwrap_or_default();
+        drop(state);
+        self.events.publish(cluster.id, fetched_at, changes);
+
+        if let Some(counters) = &self.counters {
+            counters.set_metadata(cluster.id, &metadata);
+        }
+
+        if let Some(history) = &self.history {
+            if let Err(e) = history
+                .record(cluster.id, &metadata, Utc::now(), refresh)
+                .await
+            {
+                warn!(
+                    "Failed to record metadata history for cluster {} - {}",
+                    cluster.id, e
+                );
+            }
+        }
+
+        // Throughput covers every topic, without sampling the newest records.
+        if throughput {
+            let user = metadata
+                .topics
+                .iter()
+                .filter(|t| t.category == TopicCategory::User);
+            for t in user {
+                watched.entry(t.name.clone()).or_insert(false);
+            }
+        }
+
+        if watched.is_empty() {
+            return outcome;
+        }
+
+        match context.consumer.fetch_offsets(&metadata, &watched).await {
+            Ok(offsets) => {
+                let mut state = self.state.write().await;
+                if let Some(tracker) = state.throughput.get_mut(&cluster.id) {
+                    for t in tracker.observe(&offsets, Utc::now().timestamp_millis()) {
+                        match t.hot_partition {
+                            true => warn!(
+                                "Hot partition {:?} detected on topic {} of cluster {} ({:.0}% of traffic)",
+                                t.hottest_partition,
+                                t.name,
+                                cluster.id,
+                                t.hottest_share * 100.0
+                            ),
+                            false => info!(
+                                "Hot partition cleared on topic {} of cluster {}",
+                                t.name, cluster.id
+                            ),
+                        }
+                    }
+                }
+                state.offsets.insert(cluster.id, offsets);
+                state.touch(cluster.id);
+            }
+            Err(e) => warn!(
+                cluster_id = cluster.id.as_i64();
+                "Failed to fetch offsets for cluster {} - {}", cluster.id, e
+            ),
+        }
+        outcome
+    }
+}
+
+/// Whether the metadata is the same as the cached one, apart from the
+/// watermarks which move with every message produced and would otherwise keep
+/// adaptive polling from ever stretching.
+fn is_unchanged(cached: &ClusterMetadata, metadata: &ClusterMetadata) -> bool {
+    let same_topic = |a: &TopicMetadata, b: &TopicMetadata| {
+        a.name == b.name
+            && a.category == b.category
+            && a.partitions.len() == b.partitions.len()
+            && a.partitions.iter().zip(&b.partitions).all(|(a, b)| {
+                a.id == b.id
+                    && a.leader == b.leader
+                    && a.replicas == b.replicas
+                    && a.isr == b.isr
+                    && a.error == b.error
+            })
+    };
+    cached.brokers == metadata.brokers
+        && cached.groups == metadata.groups
+        && cached.topics.len() == metadata.topics.len()
+        && cached
+            .topics
+            .iter()
+            .zip(&metadata.topics)
+            .all(|(a, b)| same_topic(a, b))
+}
+
+/// A consumer serving fixed metadata, without any topics to fetch offsets for.
+#[cfg(all(test, feature = "chaos"))]
+struct StaticConsumer;
+
+#[cfg(all(test, feature = "chaos"))]
+#[async_trait::async_trait]
+impl MetadataConsumer for StaticConsumer {
+    async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        Ok(crate::history::diff::metadata(&[1], &[]))
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_topic_config(&self, _topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        Ok(vec![])
+    }
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(start_paused = true)]
+async fn it_recovers_from_failed_metadata_fetches() {
+    use crate::clusters::cluster::Kind;
+    use crate::failpoints::{self, Failpoint, Mode};
+
+    let id = ClusterId(952);
+    let config = HashMap::from([(
+        config::METADATA_POLL_INTERVAL.to_string(),
+        "1000".to_string(),
+    )]);
+    let mut cluster = Cluster::new(None, Kind::Kafka, "chaos".to_string(), config);
+    cluster.id = id;
+
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::METADATA_FETCH, id),
+        mode: Mode::Error,
+        count: Some(2),
+    });
+
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Ok(Arc::new(StaticConsumer)));
+    let manager = Arc::new(MetadataManager::with_factory(store, factory));
+    manager.clone().register(cluster, None).await;
+
+    // Each poll consumes one injected failure, then the cluster recovers.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    for expected_failed in [true, true, false] {
+        let entry = manager.clone().get(id, None).await.unwrap();
+        assert_eq!(
+            matches!(entry.as_deref(), Some(CachedMetadataEntry::Failed(_))),
+            expected_failed,
+            "{:?}",
+            entry
+        );
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+    }
+    assert!(matches!(
+        manager.clone().get(id, None).await.unwrap().as_deref(),
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+
+    manager.stop().await;
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(start_paused = true)]
+async fn it_opens_the_circuit_of_clusters_failing_to_poll() {
+    use crate::clusters::cluster::Kind;
+    use crate::failpoints::{self, Failpoint, Mode};
+
+    let id = ClusterId(9521);
+    let name = format!("{}:{}", failpoints::METADATA_FETCH, id);
+    let config = HashMap::from([(
+        config::METADATA_POLL_INTERVAL.to_string(),
+        "1000".to_string(),
+    )]);
+    let mut cluster = Cluster::new(None, Kind::Kafka, "chaos".to_string(), config);
+    cluster.id = id;
+
+    failpoints::arm(Failpoint {
+        name: name.clone(),
+        mode: Mode::Error,
+        count: Some(5),
+    });
+
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Ok(Arc::new(StaticConsumer)));
+    let manager = Arc::new(MetadataManager::with_factory(store, factory));
+    manager.clone().register(cluster, None).await;
+
+    let remaining = || {
+        failpoints::list()
+            .into_iter()
+            .find(|f| f.name == name)
+            .and_then(|f| f.count)
+            .unwrap_or(0)
+    };
+
+    // Polls at 0s, 1s and 2s fail and open the circuit, which holds off the
+    // next poll for 30s, then 60s, and 120s before the poll that succeeds.
+    let expected = [
+        (2_500, 2, 29),
+        (31_900, 2, 0),
+        (32_500, 1, 59),
+        (92_500, 0, 119),
+        (211_900, 0, 0),
+    ];
+    let mut elapsed = 0;
+    for (at, left, retry_after) in expected {
+        tokio::time::sleep(Duration::from_millis(at - elapsed)).await;
+        elapsed = at;
+        assert_eq!(remaining(), left, "at {}ms", at);
+        assert_eq!(
+            manager.retry_after(id).await.map(|d| d.as_secs()),
+            Some(retry_after),
+            "at {}ms",
+            at
+        );
+        assert!(matches!(
+            manager.clone().get(id, None).await.unwrap().as_deref(),
+            Some(CachedMetadataEntry::Failed(_))
+        ));
+    }
+
+    // The first poll that succeeds closes the circuit.
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert!(matches!(
+        manager.clone().get(id, None).await.unwrap().as_deref(),
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+    assert_eq!(manager.retry_after(id).await.map(|d| d.as_secs()), Some(0));
+
+    manager.stop().await;
+}
+
+/// When each fetch of the slow consumers started.
+#[cfg(test)]
+type Polls = Arc<std::sync::Mutex<Vec<(ClusterId, Instant)>>>;
+
+/// A consumer taking a while to fetch metadata, noting when each fetch started.
+#[cfg(test)]
+struct SlowConsumer {
+    id: ClusterId,
+    fetch: Duration,
+    polls: Polls,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl MetadataConsumer for SlowConsumer {
+    async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        self.polls.lock().unwrap().push((self.id, Instant::now()));
+        tokio::time::sleep(self.fetch).await;
+        Ok(crate::history::diff::metadata(&[1], &[]))
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_topic_config(&self, _topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        Ok(vec![])
+    }
+}
+
+/// Clusters of the given priorities and their consumers, fetching for `fetch` of
+/// the priority, polled every second by a manager with the given budget.
+#[cfg(test)]
+fn slow_clusters(
+    priorities: &[schedule::Priority],
+    fetch: fn(schedule::Priority) -> Duration,
+    budget: usize,
+) -> (Vec<Cluster>, MetadataManager, Polls) {
+    use crate::clusters::cluster::Kind;
+
+    let clusters = priorities
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let priority = serde_json::to_value(p).unwrap();
+            let config = HashMap::from([
+                (
+                    config::METADATA_POLL_INTERVAL.to_string(),
+                    "1000".to_string(),
+                ),
+                (
+                    config::METADATA_PRIORITY.to_string(),
+                    priority.as_str().unwrap().to_string(),
+                ),
+            ]);
+            let mut cluster = Cluster::new(None, Kind::Kafka, format!("c{}", i), config);
+            cluster.id = ClusterId(i as i64 + 1);
+            cluster
+        })
+        .collect::<Vec<_>>();
+
+    let polls: Polls = Arc::default();
+    let recorded = polls.clone();
+    let factory: MetadataConsumerFactory = Arc::new(move |c| {
+        Ok(Arc::new(SlowConsumer {
+            id: c.id,
+            fetch: fetch(schedule::priority(c)),
+            polls: recorded.clone(),
+        }))
+    });
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let manager = MetadataManager::with_factory(store, factory).with_poll_budget(budget);
+
+    (clusters, manager, polls)
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_honors_high_priority_intervals_under_saturation() {
+    // Six slow low priority clusters saturate the three unreserved slots.
+    let mut priorities = vec![schedule::Priority::High];
+    priorities.extend([schedule::Priority::Low; 6]);
+    let fetch = |p| match p {
+        schedule::Priority::High => Duration::from_millis(100),
+        _ => Duration::from_millis(3_000),
+    };
+    let (clusters, manager, polls) = slow_clusters(&priorities, fetch, 4);
+    let manager = Arc::new(manager);
+    for c in clusters {
+        manager.clone().register(c, None).await;
+    }
+
+    tokio::time::sleep(Duration::from_secs(30)).await;
+
+    let starts = |id| {
+        let polls = polls.lock().unwrap();
+        polls
+            .iter()
+            .filter(|(c, _)| *c == id)
+            .map(|(_, at)| *at)
+            .collect::<Vec<_>>()
+    };
+    let gaps = |starts: &[Instant]| starts.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+
+    let high = starts(ClusterId(1));
+    assert!(high.len() >= 29, "{}", high.len());
+    for gap in gaps(&high) {
+        assert!(
+            gap.as_millis() >= 1_000 && gap.as_millis() < 1_010,
+            "{:?}",
+            gap
+        );
+    }
+    let stats = manager.polling(ClusterId(1)).await.unwrap();
+    assert!(stats.stretch().unwrap() < 1.01);
+
+    // Low priority clusters take turns, two rounds of three slow fetches apart.
+    for id in 2..=7 {
+        let gaps = gaps(&starts(ClusterId(id)));
+        let stats = manager.polling(ClusterId(id)).await.unwrap();
+        assert_eq!(stats.priority, schedule::Priority::Low);
+        assert_eq!(stats.effective, gaps.last().copied());
+
+        let stretch = stats.stretch().unwrap();
+        assert!((6.0..6.1).contains(&stretch), "{} {}", id, stretch);
+    }
+
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_warms_up_clusters_by_priority() {
+    let priorities = [
+        schedule::Priority::Low,
+        schedule::Priority::Normal,
+        schedule::Priority::High,
+        schedule::Priority::Low,
+        schedule::Priority::High,
+    ];
+    let (clusters, manager, polls) = slow_clusters(&priorities, |_| Duration::from_millis(100), 1);
+    for c in clusters {
+        manager.store.put(c).await.unwrap();
+    }
+    let manager = Arc::new(manager);
+    manager.clone().start().await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(450)).await;
+
+    let polled = polls
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, _)| priorities[id.0 as usize - 1])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        polled,
+        [
+            schedule::Priority::High,
+            schedule::Priority::High,
+            schedule::Priority::Normal,
+            schedule::Priority::Low,
+            schedule::Priority::Low
+        ]
+    );
+
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_reports_warmup_progress_and_prioritizes_pending_clusters() {
+    use crate::clusters::cluster::Kind;
+    use crate::clusters::store::MemoryClusterStore;
+
+    // The larger the id, the slower the cluster's first poll.
+    let polls: Polls = Arc::default();
+    let recorded = polls.clone();
+    let factory: MetadataConsumerFactory = Arc::new(move |c| {
+        Ok(Arc::new(SlowConsumer {
+            id: c.id,
+            fetch: Duration::from_millis(c.id.as_i64() as u64 * 100),
+            polls: recorded.clone(),
+        }))
+    });
+    let store = Arc::new(MemoryClusterStore::default());
+    for id in 1..=5 {
+        let cluster = Cluster::new(
+            Some(ClusterId(id)),
+            Kind::Kafka,
+            id.to_string(),
+            HashMap::new(),
+        );
+        store.put(cluster).await.unwrap();
+    }
+    let manager = Arc::new(MetadataManager::with_factory(store, factory).with_poll_budget(1));
+    manager.clone().start().await.unwrap();
+
+    // The totals always add up to the cache's states.
+    let check = |progress: &WarmupProgress, sync: &CacheSync| {
+        let count = |state| {
+            sync.entries
+                .iter()
+                .filter(|e| WarmupState::of(&e.entry) == state)
+                .count()
+        };
+        assert_eq!(progress.total, sync.entries.len());
+        assert_eq!(progress.ready, count(WarmupState::Ready));
+        assert_eq!(progress.failed, count(WarmupState::Failed));
+        assert_eq!(progress.pending, count(WarmupState::Pending));
+    };
+
+    // Cluster 1 is ready, 2 is being polled, and 3, 4 and 5 wait in line.
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let progress = manager.warmup().await;
+    check(&progress, &manager.changes(None).await);
+    assert_eq!((progress.ready, progress.pending), (1, 4));
+    assert_eq!(progress.clusters[4].queue_position, Some(2));
+
+    let prioritized = manager
+        .prioritize(&[ClusterId(5), ClusterId(1), ClusterId(99)])
+        .await;
+    assert_eq!(prioritized.prioritized, vec![ClusterId(5)]);
+    assert_eq!(prioritized.already_ready, vec![ClusterId(1)]);
+    assert_eq!(prioritized.errors[0].cluster_id, ClusterId(99));
+    assert_eq!(manager.warmup().await.clusters[4].queue_position, Some(0));
+
+    let mut ready = progress.ready;
+    for _ in 0..60 {
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        let progress = manager.warmup().await;
+        check(&progress, &manager.changes(None).await);
+        assert!(progress.ready >= ready);
+        ready = progress.ready;
+    }
+
+    let progress = manager.warmup().await;
+    assert!(progress.is_complete());
+    assert_eq!(progress.ready, 5);
+    let polled = polls
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, _)| id.as_i64())
+        .collect::<Vec<_>>();
+    assert_eq!(polled, vec![1, 2, 5, 3, 4]);
+
+    let first_poll = |id: usize| progress.clusters[id - 1].first_poll_ms.unwrap();
+    assert_eq!(first_poll(1), 100);
+    assert_eq!(first_poll(5), 800);
+    assert_eq!(first_poll(4), 1_500);
+
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_fails_polls_of_clusters_without_credentials() {
+    use crate::clusters::cluster::Kind;
+    use crate::kafka::auth::{AuthContext, MockTokenSource, TokenCache};
+
+    /// Checks the token like the Kafka consumer does, before asking the brokers.
+    struct Authenticated(AuthContext);
+
+    #[async_trait::async_trait]
+    impl MetadataConsumer for Authenticated {
+        async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+            self.0.check().await?;
+            Ok(crate::history::diff::metadata(&[1], &[]))
+        }
+
+        async fn fetch_offsets(
+            &self,
+            _metadata: &ClusterMetadata,
+            _topics: &HashMap<String, bool>,
+        ) -> Result<Vec<TopicOffsets>, AnyError> {
+            Ok(vec![])
+        }
+
+        async fn fetch_group_offsets(
+            &self,
+            _group: &str,
+            _metadata: &ClusterMetadata,
+        ) -> Result<Vec<GroupLag>, AnyError> {
+            Ok(vec![])
+        }
+
+        async fn fetch_topic_config(
+            &self,
+            _topic: &str,
+        ) -> Result<Vec<TopicConfigEntry>, AnyError> {
+            Ok(vec![])
+        }
+    }
+
+    let config = HashMap::from([(
+        config::METADATA_POLL_INTERVAL.to_string(),
+        "1000".to_string(),
+    )]);
+    let cluster = Cluster::new(Some(ClusterId(1)), Kind::Kafka, "msk".to_string(), config);
+
+    let source = Arc::new(MockTokenSource::new(Duration::from_secs(15 * 60), 1));
+    let tokens = TokenCache::start(source);
+    let factory: MetadataConsumerFactory = Arc::new(move |_| {
+        let context = AuthContext::with_tokens(tokens.clone());
+        Ok(Arc::new(Authenticated(context)))
+    });
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let manager = Arc::new(MetadataManager::with_factory(store, factory));
+    manager.clone().register(cluster, None).await;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    match manager
+        .clone()
+        .get(ClusterId(1), None)
+        .await
+        .unwrap()
+        .as_deref()
+    {
+        Some(CachedMetadataEntry::Failed(msg)) => assert!(
+            msg.contains("could not obtain AWS credentials: no credentials in the chain"),
+            "{}",
+            msg
+        ),
+        entry => panic!("{:?}", entry),
+    }
+
+    // The token is refreshed in the background and the next poll succeeds.
+    tokio::time::sleep(Duration::from_millis(2_000)).await;
+    assert!(matches!(
+        manager
+            .clone()
+            .get(ClusterId(1), None)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+
+    manager.stop().await;
+}
+
+/// A consumer serving metadata that changes, or fails to fetch, when a test
+/// says so, noting when each fetch started.
+#[cfg(test)]
+#[derive(Default)]
+struct ScriptedConsumer {
+    generation: std::sync::atomic::AtomicUsize,
+    failing: std::sync::atomic::AtomicBool,
+    polls: std::sync::Mutex<Vec<Instant>>,
+}
+
+#[cfg(test)]
+impl ScriptedConsumer {
+    fn change(&self) {
+        self.generation
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    fn fail(&self, failing: bool) {
+        self.failing
+            .store(failing, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// When each fetch started, in milliseconds after `start`.
+    fn polls(&self, start: Instant) -> Vec<u128> {
+        let polls = self.polls.lock().unwrap();
+        polls.iter().map(|at| (*at - start).as_millis()).collect()
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl MetadataConsumer for ScriptedConsumer {
+    async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        self.polls.lock().unwrap().push(Instant::now());
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("brokers unreachable".into());
+        }
+        let partitions = self.generation.load(Ordering::SeqCst) + 1;
+        Ok(crate::history::diff::metadata(
+            &[1],
+            &[("orders", partitions)],
+        ))
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_topic_config(&self, _topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        Ok(vec![])
+    }
+}
+
+/// Clusters polled every second with the given extra settings, and the
+/// consumers polling them.
+#[cfg(test)]
+fn scripted_clusters(
+    settings: &[&[(&str, &str)]],
+) -> (Vec<Cluster>, MetadataManager, Vec<Arc<ScriptedConsumer>>) {
+    use crate::clusters::cluster::Kind;
+
+    let mut clusters = vec![];
+    let mut consumers = HashMap::new();
+    for (i, settings) in settings.iter().enumerate() {
+        let mut config = HashMap::from([(
+            config::METADATA_POLL_INTERVAL.to_string(),
+            "1000".to_string(),
+        )]);
+        config.extend(settings.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        let id = ClusterId(i as i64 + 1);
+        clusters.push(Cluster::new(Some(id), Kind::Kafka, id.to_string(), config));
+        consumers.insert(id, Arc::new(ScriptedConsumer::default()));
+    }
+
+    let scripted = clusters
+        .iter()
+        .map(|c| consumers[&c.id].clone())
+        .collect::<Vec<_>>();
+    let factory: MetadataConsumerFactory = Arc::new(move |c| Ok(consumers[&c.id].clone()));
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let manager = MetadataManager::with_factory(store, factory);
+
+    (clusters, manager, scripted)
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_stretches_the_polls_of_unchanged_adaptive_clusters() {
+    use schedule::AdaptiveState;
+
+    let adaptive: &[(&str, &str)] = &[
+        (config::METADATA_POLL_ADAPTIVE, "true"),
+        (config::METADATA_POLL_MAX_INTERVAL, "4000"),
+    ];
+    let (clusters, manager, consumers) = scripted_clusters(&[adaptive, &[]]);
+    let manager = Arc::new(manager);
+    let start = Instant::now();
+    for c in clusters {
+        manager.clone().register(c, None).await;
+    }
+    let (id, scripted) = (ClusterId(1), &consumers[0]);
+    let adaptation = || async {
+        let stats = manager.polling(id).await.unwrap();
+        let adaptive = stats.adaptive.unwrap();
+        (
+            adaptive.state(),
+            adaptive.interval.as_millis(),
+            adaptive.unchanged,
+        )
+    };
+    let at = |ms: u64| tokio::time::sleep_until(start + Duration::from_millis(ms));
+
+    // After three unchanged polls, each one stretches the interval by half up to 4s.
+    at(10_200).await;
+    assert_eq!(
+        scripted.polls(start),
+        [0, 1_000, 2_000, 3_000, 4_500, 6_750, 10_125]
+    );
+    assert_eq!(adaptation().await, (AdaptiveState::Stretched, 4_000, 6));
+
+    // A change snaps back to the base interval, which is kept while changes arrive.
+    scripted.change();
+    at(14_200).await;
+    scripted.change();
+    at(15_200).await;
+    scripted.change();
+    at(16_200).await;
+    assert_eq!(adaptation().await, (AdaptiveState::Base, 1_000, 0));
+
+    // A refresh polls right away, however long the interval was stretched.
+    at(21_000).await;
+    assert_eq!(adaptation().await, (AdaptiveState::Stretching, 1_500, 4));
+    let entry = manager.refresh(id).await.unwrap();
+    assert!(matches!(*entry, CachedMetadataEntry::Meta(_)));
+
+    // Reading the cluster brings the next poll forward to the base interval.
+    at(25_000).await;
+    manager.activity(id).await;
+    at(25_600).await;
+    assert_eq!(
+        scripted.polls(start)[7..],
+        [
+            14_125, 15_125, 16_125, 17_125, 18_125, 19_125, 20_625, 21_000, 22_000, 23_000, 24_500,
+            25_500
+        ]
+    );
+    assert_eq!(manager.refresh(ClusterId(3)).await, None);
+
+    // Clusters that didn't opt in keep their interval.
+    let fixed = consumers[1].polls(start);
+    assert_eq!(fixed.len(), 26);
+    assert!(
+        fixed.windows(2).all(|w| w[1] - w[0] == 1_000),
+        "{:?}",
+        fixed
+    );
+    assert_eq!(manager.polling(ClusterId(2)).await.unwrap().adaptive, None);
+
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_coalesces_refreshes_into_the_next_poll() {
+    let fetch = |_| Duration::from_millis(500);
+    let (clusters, manager, polls) = slow_clusters(&[schedule::Priority::Normal], fetch, 4);
+    let manager = Arc::new(manager);
+    let start = Instant::now();
+    for c in clusters {
+        manager.clone().register(c, None).await;
+    }
+    let id = ClusterId(1);
+
+    // Refreshes during a poll don't take its answer, they share the next one.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let (a, b, c) = tokio::join!(
+        manager.refresh(id),
+        manager.refresh(id),
+        manager.refresh(id)
+    );
+    assert_eq!(start.elapsed(), Duration::from_millis(1_000));
+    for entry in [&a, &b, &c] {
+        assert!(matches!(
+            **entry.as_ref().unwrap(),
+            CachedMetadataEntry::Meta(_)
+        ));
+    }
+    assert_eq!(a, manager.snapshot(id).await);
+
+    let starts = polls
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, at)| (*at - start).as_millis())
+        .collect::<Vec<_>>();
+    assert_eq!(starts, [0, 500]);
+
+    manager.clone().remove(id, None).await;
+    assert_eq!(manager.refresh(id).await, None);
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_backs_off_failing_adaptive_clusters_by_the_circuit() {
+    let adaptive: &[(&str, &str)] = &[
+        (config::METADATA_POLL_ADAPTIVE, "true"),
+        (config::METADATA_POLL_MAX_INTERVAL, "4000"),
+    ];
+    let (clusters, manager, consumers) = scripted_clusters(&[adaptive]);
+    let manager = Arc::new(manager);
+    let start = Instant::now();
+    for c in clusters {
+        manager.clone().register(c, None).await;
+    }
+    let (id, scripted) = (ClusterId(1), &consumers[0]);
+    let at = |ms: u64| tokio::time::sleep_until(start + Duration::from_millis(ms));
+
+    // The cooldowns of the circuit, lengthened by the cluster's jitter.
+    let mut breaker = schedule::Breaker::default();
+    let cooldowns = (0..5)
+        .map(|_| {
+            breaker.record(false);
+            breaker.backoff(id, Duration::from_secs(1)).as_millis()
+        })
+        .collect::<Vec<_>>();
+    let (first, second) = (cooldowns[2], cooldowns[3]);
+
+    // Polls at 6.75s, 7.75s and 8.75s fail, the first one snapping the
+    // interval back, the last one opening the circuit for 30s and its jitter.
+    at(5_000).await;
+    scripted.fail(true);
+    at(9_000).await;
+    let retry_after = || async { manager.retry_after(id).await.map(|d| d.as_secs()) };
+    assert_eq!(retry_after().await, Some(((first - 250) / 1_000) as u64));
+    assert_eq!(
+        manager.polling(id).await.unwrap().backoff,
+        Some(Duration::from_millis(first as u64))
+    );
+
+    // The metadata of the last good poll is kept, marked stale with the error.
+    assert!(matches!(
+        manager.clone().get(id, None).await.unwrap().as_deref(),
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+    let info = manager.cache_info(id).await.unwrap();
+    assert_eq!(info.attempts_since_success, 3);
+    assert!(info.last_error.unwrap().contains("brokers unreachable"));
+    assert!(info.stale);
+
+    // Reads don't bring polls forward while the circuit is open.
+    manager.activity(id).await;
+    assert_eq!(retry_after().await, Some(((first - 250) / 1_000) as u64));
+    let adaptive = manager.polling(id).await.unwrap().adaptive.unwrap();
+    assert!(!adaptive.is_stretched());
+
+    // The cooldown doubles, then the first poll that succeeds closes the
+    // circuit and the cluster is polled at its base interval again.
+    at(45_000).await;
+    scripted.fail(false);
+    at((8_750 + first + second + 250) as u64).await;
+    assert_eq!(
+        scripted.polls(start),
+        [
+            0,
+            1_000,
+            2_000,
+            3_000,
+            4_500,
+            6_750,
+            7_750,
+            8_750,
+            8_750 + first,
+            8_750 + first + second
+        ]
+    );
+    assert_eq!(manager.polling(id).await.unwrap().backoff, None);
+    assert!(matches!(
+        manager.clone().get(id, None).await.unwrap().as_deref(),
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+    assert_eq!(retry_after().await, Some(0));
+    let info = manager.cache_info(id).await.unwrap();
+    assert_eq!((info.attempts_since_success, info.last_error), (0, None));
+    assert!(!info.stale);
+
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_exports_poll_metrics() {
+    let (clusters, manager, consumers) = scripted_clusters(&[&[]]);
+    let registry = Arc::new(Registry::new(metrics::FAMILIES));
+    let manager = Arc::new(manager.with_metrics(registry.clone()));
+    let start = Instant::now();
+    for c in clusters {
+        manager.clone().register(c, None).await;
+    }
+    let at = |ms: u64| tokio::time::sleep_until(start + Duration::from_millis(ms));
+
+    // Polls at 0s, 1s and 2s succeed, those at 3s and 4s fail.
+    at(2_500).await;
+    consumers[0].fail(true);
+    at(4_500).await;
+    manager.export_metrics().await;
+    let rendered = registry.render();
+    for line in [
+        "seekr_clusters_registered 1",
+        "seekr_metadata_polls_total{cluster_id=\"1\",outcome=\"failure\"} 2",
+        "seekr_metadata_polls_total{cluster_id=\"1\",outcome=\"success\"} 3",
+        "seekr_metadata_poll_duration_seconds{cluster_id=\"1\"} 0",
+        "seekr_metadata_cache_age_seconds{cluster_id=\"1\"} 2.5",
+    ] {
+        assert!(rendered.contains(line), "{} in {}", line, rendered);
+    }
+
+    // A removed cluster's series go with it.
+    manager.clone().remove(ClusterId(1), None).await;
+    manager.export_metrics().await;
+    let rendered = registry.render();
+    assert!(!rendered.contains("cluster_id"), "{}", rendered);
+    assert!(rendered.contains("seekr_clusters_registered 0"));
+
+    manager.stop().await;
+}
+
+#[test]
+fn it_ignores_watermarks_when_comparing_metadata() {
+    let cached = crate::history::diff::metadata(&[1], &[("orders", 2)]);
+
+    let mut moved = cached.clone();
+    for p in moved.topics[0].partitions.iter_mut() {
+        p.low_watermark = Some(3);
+        p.high_watermark = Some(17);
+    }
+    assert!(is_unchanged(&cached, &moved));
+
+    let mut led = moved.clone();
+    led.topics[0].partitions[1].leader = 2;
+    assert!(!is_unchanged(&cached, &led));
+    assert!(!is_unchanged(
+        &cached,
+        &crate::history::diff::metadata(&[1], &[("orders", 3)])
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_reregisters_clusters_with_their_new_config() {
+    use crate::clusters::cluster::Kind;
+
+    // Every consumer built, failing to build for an invalid config.
+    let built = Arc::new(std::sync::Mutex::new(Vec::<Arc<ScriptedConsumer>>::new()));
+    let consumers = built.clone();
+    let factory: MetadataConsumerFactory = Arc::new(move |c| {
+        if c.config.get(config::BOOTSTRAP_SERVERS).unwrap() == "invalid" {
+            return Err("invalid bootstrap servers".into());
+        }
+        let consumer = Arc::new(ScriptedConsumer::default());
+        consumers.lock().unwrap().push(consumer.clone());
+        Ok(consumer)
+    });
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let manager = Arc::new(MetadataManager::with_factory(store, factory));
+
+    let cluster = |servers: &str, interval: &str| {
+        let config = HashMap::from([
+            (config::BOOTSTRAP_SERVERS.to_string(), servers.to_string()),
+            (
+                config::METADATA_POLL_INTERVAL.to_string(),
+                interval.to_string(),
+            ),
+        ]);
+        Cluster::new(Some(ClusterId(1)), Kind::Kafka, "local".to_string(), config)
+    };
+    manager
+        .clone()
+        .register(cluster("a:9092", "1000"), None)
+        .await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let start = Instant::now();
+
+    // The new consumer takes over, polling at the new interval, while the
+    // cached metadata is served throughout.
+    manager
+        .clone()
+        .reregister(cluster("b:9092", "5000"))
+        .await
+        .unwrap();
+    assert!(matches!(
+        manager
+            .clone()
+            .get(ClusterId(1), None)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+    tokio::time::sleep(Duration::from_millis(6_000)).await;
+    let polls = |i: usize| built.lock().unwrap()[i].polls(start);
+    assert!(polls(0).is_empty());
+    assert_eq!(polls(1), [0, 5_000]);
+
+    // An invalid config fails, the consumer polling the cluster keeps at it.
+    let e = manager
+        .clone()
+        .reregister(cluster("invalid", "1000"))
+        .await
+        .unwrap_err();
+    assert!(e.downcast_ref::<InvalidConsumerConfig>().is_some());
+    tokio::time::sleep(Duration::from_millis(5_000)).await;
+    assert_eq!(built.lock().unwrap().len(), 2);
+    assert_eq!(polls(1), [0, 5_000, 10_000]);
+
+    manager.stop().await;
+}
+
+/// A consumer whose fetch blocks its thread, like a client call that never
+/// returns, so its poll can't notice the shutdown.
+#[cfg(test)]
+struct BlockingConsumer {
+    fetch: Duration,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl MetadataConsumer for BlockingConsumer {
+    async fn fetch_meta(&self) -> Result<ClusterMetadata, AnyError> {
+        std::thread::sleep(self.fetch);
+        Ok(crate::history::diff::metadata(&[1], &[]))
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &ClusterMetadata,
+        _topics: &HashMap<String, bool>,
+    ) -> Result<Vec<TopicOffsets>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &ClusterMetadata,
+    ) -> Result<Vec<GroupLag>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_topic_config(&self, _topic: &str) -> Result<Vec<TopicConfigEntry>, AnyError> {
+        Ok(vec![])
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 3)]
+async fn it_aborts_polls_ignoring_the_shutdown_past_the_deadline() {
+    use crate::clusters::cluster::Kind;
+
+    // The first cluster's fetch blocks, the second's is awaited as usual.
+    let factory: MetadataConsumerFactory = Arc::new(|c| {
+        Ok(match c.id {
+            ClusterId(1) => Arc::new(BlockingConsumer {
+                fetch: Duration::from_millis(1_000),
+            }),
+            _ => Arc::new(SlowConsumer {
+                id: c.id,
+                fetch: Duration::from_millis(1_000),
+                polls: Arc::default(),
+            }),
+        })
+    });
+    let store = Arc::new(crate::clusters::store::MemoryClusterStore::default());
+    let manager = Arc::new(MetadataManager::with_factory(store, factory));
+    for i in 1..=2 {
+        let mut cluster = Cluster::new(None, Kind::Kafka, format!("c{}", i), HashMap::new());
+        cluster.id = ClusterId(i);
+        manager.clone().register(cluster, None).await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let started = Instant::now();
+    let stuck = manager
+        .clone()
+        .stop_within(Duration::from_millis(200))
+        .await;
+    assert!(started.elapsed() < Duration::from_millis(500));
+    assert_eq!(stuck, [ClusterId(1)]);
+    assert!(manager.state.read().await.context.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_staggers_the_polls_of_clusters_started_together() {
+    let (clusters, manager, consumers) = scripted_clusters(&[&[], &[], &[]]);
+    let registry = Arc::new(Registry::new(metrics::FAMILIES));
+    let manager = Arc::new(manager.with_metrics(registry.clone()));
+    let start = Instant::now();
+    let registered = clusters.last().cloned().unwrap();
+    for c in &clusters[..2] {
+        manager.store.put(c.clone()).await.unwrap();
+    }
+    manager.clone().start().await.unwrap();
+    manager.clone().register(registered, None).await;
+
+    // Every cluster is polled right away, the ones started together then
+    // polled each a different share of its interval later.
+    tokio::time::sleep(Duration::from_millis(2_500)).await;
+    let interval = Duration::from_secs(1);
+    for (i, c) in clusters[..2].iter().enumerate() {
+        let second = 1_000 + schedule::stagger(c.id, interval).as_millis();
+        assert_eq!(consumers[i].polls(start)[..2], [0, second]);
+    }
+    assert_eq!(consumers[2].polls(start)[..3], [0, 1_000, 2_000]);
+    assert_ne!(
+        schedule::stagger(ClusterId(1), interval),
+        schedule::stagger(ClusterId(2), interval)
+    );
+
+    // How long the last polls waited for the poll budget is exported.
+    manager.export_metrics().await;
+    let rendered = registry.render();
+    assert!(
+        rendered.contains("seekr_metadata_poll_queue_wait_seconds{cluster_id=\"3\"}"),
+        "{}",
+        rendered
+    );
+
+    manager.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_publishes_the_changes_polls_find() {
+    use crate::history::diff::Change;
+
+    let (clusters, manager, consumers) = scripted_clusters(&[&[]]);
+    let manager = Arc::new(manager);
+    let mut events = manager.subscribe_events();
+    let (cluster, scripted) = (clusters[0].clone(), &consumers[0]);
+    let id = cluster.id;
+    manager.clone().register(cluster, None).await;
+
+    // The first poll and unchanged ones publish nothing.
+    tokio::time::sleep(Duration::from_millis(1_500)).await;
+    assert!(events.try_recv().is_err());
+
+    scripted.change();
+    tokio::time::sleep(Duration::from_millis(1_000)).await;
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.cluster_id, id);
+    assert_eq!(
+        event.change,
+        Change::PartitionsChanged {
+            topic: "orders".to_string(),
+            from: 1,
+            to: 2
+        }
+    );
+    assert_eq!(manager.events().since(id, None), [event.clone()]);
+    assert!(manager.events().since(id, Some(event.at)).is_empty());
+
+    manager.clone().remove(id, None).await;
+    assert!(manager.events().since(id, None).is_empty());
 }
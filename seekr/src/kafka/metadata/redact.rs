@@ -0,0 +1,504 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use regex::Regex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use super::manager::CachedMetadataEntry;
+use super::ClusterMetadata;
+
+/// What a redaction hides of a cluster's metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    /// Broker hosts, replaced with `broker-1`, `broker-2`, ...
+    Hosts,
+
+    /// The hosts group members connect from, replaced with `client-1`, ...
+    ClientHosts,
+
+    /// The member and client ids of group members, replaced with hashes.
+    GroupMembers,
+
+    /// Group names, replaced with `group-1`, ...
+    Groups,
+
+    /// Topic name segments matching the sensitive topic patterns, replaced
+    /// with `masked-1`, ...
+    Topics,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Hosts,
+        Category::ClientHosts,
+        Category::GroupMembers,
+        Category::Groups,
+        Category::Topics,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Hosts => "hosts",
+            Category::ClientHosts => "client_hosts",
+            Category::GroupMembers => "group_members",
+            Category::Groups => "groups",
+            Category::Topics => "topics",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// The `redact` parameter of metadata reads.
+#[derive(Debug, Default, Deserialize)]
+pub struct RedactQuery {
+    pub redact: Option<String>,
+}
+
+/// The categories a response is redacted by.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Redaction {
+    categories: BTreeSet<Category>,
+}
+
+impl Redaction {
+    pub fn of(categories: impl IntoIterator<Item = Category>) -> Self {
+        Self {
+            categories: categories.into_iter().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+
+    pub fn contains(&self, category: Category) -> bool {
+        self.categories.contains(&category)
+    }
+}
+
+/// The redaction profiles of `--redaction-profile`, and the topic name
+/// segments `topics` masks.
+#[derive(Clone, Debug, Default)]
+pub struct RedactionPolicy {
+    profiles: BTreeMap<String, Redaction>,
+    sensitive_topics: Vec<Regex>,
+}
+
+impl RedactionPolicy {
+    /// Profiles are given as `name=category,category`, patterns must match
+    /// a whole segment of a topic name.
+    pub fn new(profiles: &[String], sensitive_topics: &[String]) -> Result<Self, String> {
+        let mut policy = Self::default();
+        for profile in profiles {
+            let (name, categories) = profile
+                .split_once('=')
+                .ok_or_else(|| format!("redaction profile '{}' isn't name=categories", profile))?;
+            let name = name.trim();
+            if Category::parse(name).is_some() {
+                return Err(format!(
+                    "redaction profile '{}' is named like a category",
+                    name
+                ));
+            }
+            let categories = categories
+                .split(',')
+                .map(|c| category(c.trim()))
+                .collect::<Result<_, _>>()?;
+            policy
+                .profiles
+                .insert(name.to_string(), Redaction { categories });
+        }
+        for pattern in sensitive_topics {
+            let regex = Regex::new(&format!("^(?:{})$", pattern))
+                .map_err(|e| format!("invalid sensitive topic pattern '{}': {}", pattern, e))?;
+            policy.sensitive_topics.push(regex);
+        }
+        Ok(policy)
+    }
+
+    /// The redaction of a `redact` parameter, a list of categories and profile names.
+    pub fn resolve(&self, redact: &str) -> Result<Redaction, String> {
+        let mut categories = BTreeSet::new();
+        for name in redact.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match self.profiles.get(name) {
+                Some(profile) => categories.extend(profile.categories.iter().copied()),
+                None => {
+                    categories.insert(category(name).map_err(|e| {
+                        let profiles = self.profiles.keys().cloned().collect::<Vec<_>>();
+                        match profiles.is_empty() {
+                            true => e,
+                            false => format!("{} or profile: {}", e, profiles.join(", ")),
+                        }
+                    })?);
+                }
+            }
+        }
+        Ok(Redaction { categories })
+    }
+
+    /// A redacted copy of the metadata, leaving what it's read from untouched.
+    ///
+    /// Pseudonyms are numbered in the order things appear, so within one
+    /// response the same host or name always gets the same one, and e.g. a
+    /// broker named in a partition error can still be told apart.
+    pub fn apply(&self, redaction: &Redaction, metadata: &ClusterMetadata) -> ClusterMetadata {
+        let mut redacted = metadata.clone();
+        if redaction.is_empty() {
+            return redacted;
+        }
+
+        // Hosts are replaced wherever they appear, e.g. in partition errors.
+        let mut hosts = Pseudonyms::new("broker");
+        if redaction.contains(Category::Hosts) {
+            let mut brokers = redacted.brokers.iter_mut().collect::<Vec<_>>();
+            brokers.sort_by_key(|b| b.id);
+            for broker in brokers {
+                broker.host = hosts.get(&broker.host);
+            }
+        }
+        let mut clients = Pseudonyms::new("client");
+        let mut groups = Pseudonyms::new("group");
+        for group in redacted.groups.iter_mut() {
+            if redaction.contains(Category::Groups) {
+                group.name = groups.get(&group.name);
+            }
+            for member in group.members.iter_mut() {
+                if redaction.contains(Category::ClientHosts) {
+                    member.client_host = clients.get(&member.client_host);
+                }
+                if redaction.contains(Category::GroupMembers) {
+                    member.id = hash("member", &member.id);
+                    member.client_id = hash("client", &member.client_id);
+                }
+            }
+        }
+        if redaction.contains(Category::Topics) {
+            let mut segments = Pseudonyms::new("masked");
+            for topic in redacted.topics.iter_mut() {
+                topic.name = self.mask(&topic.name, &mut segments);
+            }
+        }
+
+        let replaced = hosts
+            .replacements()
+            .chain(clients.replacements())
+            .collect::<Vec<_>>();
+        scrub(&mut redacted, &replaced);
+        redacted
+    }
+
+    /// The entry with its metadata redacted, the other states have nothing to redact.
+    pub fn apply_entry(
+        &self,
+        redaction: &Redaction,
+        entry: &CachedMetadataEntry,
+    ) -> CachedMetadataEntry {
+        match entry {
+            CachedMetadataEntry::Meta(metadata) => {
+                CachedMetadataEntry::Meta(self.apply(redaction, metadata))
+            }
+            _ => entry.clone(),
+        }
+    }
+
+    fn mask(&self, topic: &str, segments: &mut Pseudonyms) -> String {
+        let mut masked = String::with_capacity(topic.len());
+        for (i, segment) in topic.split('.').enumerate() {
+            if i > 0 {
+                masked.push('.');
+            }
+            match self.sensitive_topics.iter().any(|p| p.is_match(segment)) {
+                true => masked.push_str(&segments.get(segment)),
+                false => masked.push_str(segment),
+            }
+        }
+        masked
+    }
+}
+
+/// The policy registered with the server, or one without profiles.
+impl FromRequest for RedactionPolicy {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let policy = req.app_data::<Data<RedactionPolicy>>();
+        ready(Ok(policy.map(|p| p.get_ref().clone()).unwrap_or_default()))
+    }
+}
+
+fn category(name: &str) -> Result<Category, String> {
+    Category::parse(name).ok_or_else(|| {
+        let names = Category::ALL.map(|c| c.name());
+        format!(
+            "unknown redaction '{}', expected one of: {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+/// Numbered stand-ins for the values of one response.
+struct Pseudonyms {
+    prefix: &'static str,
+    assigned: HashMap<String, String>,
+}
+
+impl Pseudonyms {
+    fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            assigned: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, value: &str) -> String {
+        let next = self.assigned.len() + 1;
+        self.assigned
+            .entry(value.to_string())
+            .or_insert_with(|| format!("{}-{}", self.prefix, next))
+            .clone()
+    }
+
+    fn replacements(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.assigned
+            .iter()
+            .filter(|(value, _)| !value.is_empty())
+            .map(|(v, p)| (v.clone(), p.clone()))
+    }
+}
+
+fn hash(prefix: &str, value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let hex = digest
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!("{}-{}", prefix, &hex[..12])
+}
+
+/// Replace what was redacted wherever else it appears, longest first so a
+/// host isn't partly replaced by one it contains.
+fn scrub(metadata: &mut ClusterMetadata, replacements: &[(String, String)]) {
+    if replacements.is_empty() {
+        return;
+    }
+
+    let mut replacements = replacements.iter().collect::<Vec<_>>();
+    replacements.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+    let scrub = |text: &mut String| {
+        for (value, pseudonym) in &replacements {
+            if text.contains(value.as_str()) {
+                *text = text.replace(value.as_str(), pseudonym);
+            }
+        }
+    };
+
+    for group in metadata.groups.iter_mut() {
+        scrub(&mut group.name);
+        scrub(&mut group.state);
+        for member in group.members.iter_mut() {
+            scrub(&mut member.id);
+            scrub(&mut member.client_id);
+        }
+    }
+    for topic in metadata.topics.iter_mut() {
+        scrub(&mut topic.name);
+        for partition in topic.partitions.iter_mut() {
+            if let Some(error) = partition.error.as_mut() {
+                scrub(error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn fixture() -> ClusterMetadata {
+    use super::{BrokerMetadata, GroupMember, GroupMetadata, PartitionMetadata, TopicMetadata};
+
+    let broker = |id, host: &str| BrokerMetadata {
+        id,
+        host: host.to_string(),
+        port: 9092,
+    };
+    let member = |id: &str, client_id: &str, client_host: &str| GroupMember {
+        id: id.to_string(),
+        client_id: client_id.to_string(),
+        client_host: client_host.to_string(),
+    };
+    let partition = |id, leader, error: Option<&str>| PartitionMetadata {
+        id,
+        leader,
+        replicas: vec![1, 2],
+        isr: vec![leader],
+        error: error.map(str::to_string),
+        low_watermark: None,
+        high_watermark: None,
+    };
+
+    ClusterMetadata {
+        brokers: vec![
+            broker(2, "kafka-b.prod.internal"),
+            broker(1, "kafka-a.prod.internal"),
+            broker(3, "kafka-a.prod.internal"),
+        ],
+        groups: vec![
+            GroupMetadata {
+                name: "billing-reconciler".to_string(),
+                state: "Stable".to_string(),
+                members: vec![
+                    member(
+                        "billing-1-6b1f",
+                        "billing@kafka-a.prod.internal",
+                        "/10.0.4.17",
+                    ),
+                    member("billing-2-0c2e", "billing", "/10.0.4.18"),
+                ],
+                managed_by_seekr: false,
+            },
+            GroupMetadata {
+                name: "audit".to_string(),
+                state: "Empty".to_string(),
+                members: vec![member("audit-1-77aa", "audit", "/10.0.4.17")],
+                managed_by_seekr: false,
+            },
+        ],
+        topics: vec![TopicMetadata {
+            name: "acme.payments.events".to_string(),
+            partitions: vec![
+                partition(0, 1, None),
+                partition(
+                    1,
+                    2,
+                    Some("leader kafka-b.prod.internal:9092 not available"),
+                ),
+            ],
+            category: Default::default(),
+        }],
+    }
+}
+
+#[test]
+fn it_gives_stable_pseudonyms_within_a_response() {
+    let policy = RedactionPolicy::default();
+    let metadata = fixture();
+    let redacted = policy.apply(&Redaction::of([Category::Hosts]), &metadata);
+
+    // Numbered by broker id, brokers sharing a host share its pseudonym.
+    let hosts = redacted
+        .brokers
+        .iter()
+        .map(|b| (b.id, b.host.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(hosts, [(2, "broker-2"), (1, "broker-1"), (3, "broker-1")]);
+    assert_eq!(
+        redacted.topics[0].partitions[1].error.as_deref(),
+        Some("leader broker-2:9092 not available")
+    );
+    assert_eq!(
+        policy.apply(&Redaction::of([Category::Hosts]), &metadata),
+        redacted
+    );
+
+    // The metadata read from is left alone.
+    assert_eq!(metadata, fixture());
+    assert_eq!(policy.apply(&Redaction::default(), &metadata), metadata);
+}
+
+#[test]
+fn it_redacts_each_category() {
+    let policy = RedactionPolicy::default();
+    let metadata = fixture();
+    let redact = |category| policy.apply(&Redaction::of([category]), &metadata);
+
+    let clients = redact(Category::ClientHosts);
+    let members = &clients.groups[0].members;
+    assert_eq!(members[0].client_host, "client-1");
+    assert_eq!(members[1].client_host, "client-2");
+    assert_eq!(clients.groups[1].members[0].client_host, "client-1");
+    assert_eq!(clients.brokers, metadata.brokers);
+
+    let members = redact(Category::GroupMembers);
+    let member = &members.groups[0].members[0];
+    assert!(member.id.starts_with("member-"));
+    assert!(member.client_id.starts_with("client-"));
+    assert_ne!(member.id, members.groups[0].members[1].id);
+    assert_eq!(member.client_host, "/10.0.4.17");
+
+    let groups = redact(Category::Groups);
+    assert_eq!(groups.groups[0].name, "group-1");
+    assert_eq!(groups.groups[1].name, "group-2");
+    assert_eq!(groups.groups[0].members, metadata.groups[0].members);
+}
+
+#[test]
+fn it_masks_sensitive_topic_segments() {
+    let policy =
+        RedactionPolicy::new(&[], &["acme|globex".to_string(), "pay.*".to_string()]).unwrap();
+    let mut metadata = fixture();
+    metadata.topics.push(super::TopicMetadata {
+        name: "globex.acme.orders".to_string(),
+        partitions: vec![],
+        category: Default::default(),
+    });
+
+    let redacted = policy.apply(&Redaction::of([Category::Topics]), &metadata);
+    assert_eq!(redacted.topics[0].name, "masked-1.masked-2.events");
+    assert_eq!(redacted.topics[1].name, "masked-3.masked-1.orders");
+
+    // Patterns match whole segments only.
+    let policy = RedactionPolicy::new(&[], &["acm".to_string()]).unwrap();
+    let redacted = policy.apply(&Redaction::of([Category::Topics]), &metadata);
+    assert_eq!(redacted.topics[0].name, "acme.payments.events");
+}
+
+#[test]
+fn it_leaves_no_host_in_redacted_output() {
+    let policy = RedactionPolicy::new(
+        &["external=hosts,client_hosts,group_members".to_string()],
+        &[],
+    )
+    .unwrap();
+    let metadata = fixture();
+    let redaction = policy.resolve("external").unwrap();
+    let output = serde_json::to_string(&policy.apply(&redaction, &metadata)).unwrap();
+
+    let mut hosts = metadata
+        .brokers
+        .iter()
+        .map(|b| b.host.clone())
+        .collect::<Vec<_>>();
+    for group in &metadata.groups {
+        hosts.extend(group.members.iter().map(|m| m.client_host.clone()));
+    }
+    for host in hosts {
+        assert!(!output.contains(&host), "{} survived in {}", host, output);
+    }
+    assert!(!output.contains("prod.internal"), "{}", output);
+}
+
+#[test]
+fn it_resolves_profiles_and_categories() {
+    let policy = RedactionPolicy::new(&["external=hosts, client_hosts".to_string()], &[]).unwrap();
+    assert_eq!(
+        policy.resolve("external,groups").unwrap(),
+        Redaction::of([Category::Hosts, Category::ClientHosts, Category::Groups])
+    );
+    assert_eq!(
+        policy.resolve("host").unwrap_err(),
+        "unknown redaction 'host', expected one of: hosts, client_hosts, group_members, \
+         groups, topics or profile: external"
+    );
+
+    assert!(RedactionPolicy::new(&["external".to_string()], &[]).is_err());
+    assert!(RedactionPolicy::new(&["hosts=groups".to_string()], &[]).is_err());
+    assert!(RedactionPolicy::new(&["x=hots".to_string()], &[]).is_err());
+    assert!(RedactionPolicy::new(&[], &["(".to_string()]).is_err());
+}
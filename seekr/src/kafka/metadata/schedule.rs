@@ -0,0 +1,699 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::clusters::cluster::Cluster;
+use crate::ids::ClusterId;
+use crate::kafka::config;
+
+pub use seekr_api_types::metadata::{AdaptiveState, Priority};
+
+/// How many metadata polls run at once when no budget is configured.
+pub const DEFAULT_POLL_BUDGET: usize = 8;
+
+/// The priority configured by `metadata.priority`, `normal` by default.
+pub fn priority(cluster: &Cluster) -> Priority {
+    let Some(value) = cluster.config.get(config::METADATA_PRIORITY) else {
+        return Priority::default();
+    };
+
+    match value.parse() {
+        Ok(priority) => priority,
+        Err(e) => {
+            warn!(
+                "Ignoring {} of cluster {}: {}",
+                config::METADATA_PRIORITY,
+                cluster.id,
+                e
+            );
+            Priority::default()
+        }
+    }
+}
+
+/// The longest interval of adaptive clusters when `metadata.poll.max.interval.ms` isn't set.
+pub const DEFAULT_MAX_ADAPTIVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Unchanged polls in a row after which an adaptive cluster's interval starts stretching.
+pub const ADAPTIVE_SETTLE_POLLS: u32 = 3;
+
+/// The adaptation of the cluster's interval, when `metadata.poll.adaptive` is set.
+pub fn adaptive(cluster: &Cluster, interval: Duration) -> Option<Adaptive> {
+    let enabled = cluster
+        .config
+        .get(config::METADATA_POLL_ADAPTIVE)
+        .is_some_and(|v| v == "true");
+    if !enabled {
+        return None;
+    }
+
+    let max = match cluster.config.get(config::METADATA_POLL_MAX_INTERVAL) {
+        None => DEFAULT_MAX_ADAPTIVE_INTERVAL,
+        Some(value) => match value.parse() {
+            Ok(ms) => Duration::from_millis(ms),
+            Err(e) => {
+                warn!(
+                    "Ignoring {} of cluster {}: {}",
+                    config::METADATA_POLL_MAX_INTERVAL,
+                    cluster.id,
+                    e
+                );
+                DEFAULT_MAX_ADAPTIVE_INTERVAL
+            }
+        },
+    };
+    Some(Adaptive::new(interval, max))
+}
+
+/// How a poll went, compared to the entry it replaced in the cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PollOutcome {
+    Changed,
+    Unchanged,
+    Failed,
+}
+
+/// Stretches the interval of a cluster whose metadata keeps coming back the same.
+///
+/// After `ADAPTIVE_SETTLE_POLLS` unchanged polls in a row, every further one
+/// lengthens the interval by half, up to the maximum. A change, a failure or
+/// activity on the cluster snaps it back to the base interval, where it stays
+/// while changes keep arriving.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adaptive {
+    pub base: Duration,
+    pub max: Duration,
+    pub interval: Duration,
+    pub unchanged: u32,
+}
+
+impl Adaptive {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            interval: base,
+            unchanged: 0,
+        }
+    }
+
+    /// Note how a poll went, failures are left for the circuit to back off.
+    pub fn record(&mut self, outcome: PollOutcome) {
+        match outcome {
+            PollOutcome::Unchanged => {
+                self.unchanged = self.unchanged.saturating_add(1);
+                if self.unchanged >= ADAPTIVE_SETTLE_POLLS {
+                    self.interval = std::cmp::min(self.interval + self.interval / 2, self.max);
+                }
+            }
+            PollOutcome::Changed | PollOutcome::Failed => self.reset(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.interval = self.base;
+        self.unchanged = 0;
+    }
+
+    pub fn is_stretched(&self) -> bool {
+        self.interval > self.base
+    }
+
+    pub fn state(&self) -> AdaptiveState {
+        match self.interval {
+            i if i >= self.max && self.max > self.base => AdaptiveState::Stretched,
+            i if i > self.base => AdaptiveState::Stretching,
+            _ => AdaptiveState::Base,
+        }
+    }
+}
+
+/// How a cluster's metadata polls actually went, compared to its configured interval.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PollStats {
+    pub priority: Priority,
+    pub interval: Duration,
+
+    /// The time between the last two polls, once the cluster was polled twice.
+    pub effective: Option<Duration>,
+    pub last_poll: Option<Instant>,
+    pub adaptive: Option<Adaptive>,
+
+    /// How long the open circuit holds off the next poll, while it's open.
+    pub backoff: Option<Duration>,
+}
+
+impl PollStats {
+    pub fn new(priority: Priority, interval: Duration) -> Self {
+        Self {
+            priority,
+            interval,
+            effective: None,
+            last_poll: None,
+            adaptive: None,
+            backoff: None,
+        }
+    }
+
+    pub fn with_adaptive(mut self, adaptive: Option<Adaptive>) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    /// The interval the next poll is due after, stretched for adaptive clusters.
+    pub fn next_interval(&self) -> Duration {
+        self.adaptive.as_ref().map_or(self.interval, |a| a.interval)
+    }
+
+    /// Note how a poll went, returning the interval the next one is due after.
+    pub fn record(&mut self, outcome: PollOutcome) -> Duration {
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.record(outcome);
+        }
+        self.next_interval()
+    }
+
+    /// Note a poll starting now.
+    pub fn polled(&mut self, now: Instant) {
+        if let Some(last) = self.last_poll {
+            self.effective = Some(now.duration_since(last));
+        }
+        self.last_poll = Some(now);
+    }
+
+    /// How many times longer the effective interval is than the configured one.
+    pub fn stretch(&self) -> Option<f64> {
+        let effective = self.effective?;
+        match self.interval.is_zero() {
+            true => None,
+            false => Some(effective.as_secs_f64() / self.interval.as_secs_f64()),
+        }
+    }
+}
+
+/// Polls failing in a row after which a cluster's circuit opens.
+pub const CIRCUIT_THRESHOLD: u32 = 3;
+
+/// How long an open circuit holds off the next poll, doubling for every poll failing after.
+pub const CIRCUIT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// The longest an open circuit holds off the next poll when `metadata.poll.max.backoff.ms` isn't set.
+pub const MAX_CIRCUIT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// The most of a cooldown added to it, in percent, so clusters failing
+/// together don't retry together.
+pub const CIRCUIT_JITTER_PERCENT: u64 = 10;
+
+/// The breaker of the cluster, its cooldown capped by `metadata.poll.max.backoff.ms`.
+pub fn breaker(cluster: &Cluster) -> Breaker {
+    let max = match cluster.config.get(config::METADATA_POLL_MAX_BACKOFF) {
+        None => MAX_CIRCUIT_COOLDOWN,
+        Some(value) => match value.parse() {
+            Ok(ms) => Duration::from_millis(ms),
+            Err(e) => {
+                warn!(
+                    "Ignoring {} of cluster {}: {}",
+                    config::METADATA_POLL_MAX_BACKOFF,
+                    cluster.id,
+                    e
+                );
+                MAX_CIRCUIT_COOLDOWN
+            }
+        },
+    };
+    Breaker::default().with_max(max)
+}
+
+/// How much later than its interval the second poll of a cluster started
+/// along with the others comes, a share of the interval that differs between
+/// clusters, so they don't go on polling in lockstep.
+pub fn stagger(id: ClusterId, interval: Duration) -> Duration {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    let ms = interval.as_millis() as u64;
+    Duration::from_millis(hasher.finish() % std::cmp::max(ms, 1))
+}
+
+/// Backs off polling a cluster whose metadata keeps failing to fetch.
+///
+/// Once `CIRCUIT_THRESHOLD` polls in a row failed, the circuit opens and the
+/// cluster is polled after a cooldown instead of its interval, so unreachable
+/// clusters don't hold on to the poll budget. The first poll that succeeds
+/// closes it again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Breaker {
+    failures: u32,
+    max: Duration,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            failures: 0,
+            max: MAX_CIRCUIT_COOLDOWN,
+        }
+    }
+}
+
+impl Breaker {
+    pub fn with_max(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Note how a poll went.
+    pub fn record(&mut self, fetched: bool) {
+        self.failures = match fetched {
+            true => 0,
+            false => self.failures.saturating_add(1),
+        };
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.failures >= CIRCUIT_THRESHOLD
+    }
+
+    /// How long after the last poll the next one is due.
+    pub fn delay(&self, interval: Duration) -> Duration {
+        match self.failures.checked_sub(CIRCUIT_THRESHOLD) {
+            None => interval,
+            Some(n) => {
+                let factor = 1u32.checked_shl(n).unwrap_or(u32::MAX);
+                let cooldown = CIRCUIT_COOLDOWN.saturating_mul(factor);
+                std::cmp::max(std::cmp::min(cooldown, self.max), interval)
+            }
+        }
+    }
+
+    /// The delay of an open circuit lengthened by up to `CIRCUIT_JITTER_PERCENT`
+    /// of it, by a share that differs between clusters and between failures.
+    ///
+    /// The jitter never takes the delay past the max backoff: a delay it would
+    /// take past it is shortened by the jitter from the max instead.
+    pub fn backoff(&self, id: ClusterId, interval: Duration) -> Duration {
+        let delay = self.delay(interval);
+        if !self.is_open() {
+            return delay;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        (id, self.failures).hash(&mut hasher);
+        let permille = hasher.finish() % 1_000;
+        let jitter = delay.as_millis() as u64 * CIRCUIT_JITTER_PERCENT * permille / 100_000;
+        let jitter = Duration::from_millis(jitter);
+
+        // An interval longer than the max backoff is kept as is.
+        let max = std::cmp::max(self.max, delay);
+        match delay + jitter {
+            lengthened if lengthened <= max => lengthened,
+            _ => max.saturating_sub(jitter),
+        }
+    }
+}
+
+/// Whether a waiting poll was moved to the front of the line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Rank {
+    Prioritized,
+    Queued,
+}
+
+/// A due poll waiting for the budget, ordered by rank, priority, then due time.
+type Ticket = (Rank, Priority, Instant, ClusterId);
+
+/// Grants due metadata polls a slot of a bounded budget, highest priority first.
+///
+/// Each poll blocks a thread on broker round trips, so only `budget` of them
+/// run at once. A slice of the budget is reserved for high priority clusters,
+/// which keeps their interval even when the rest is saturated by slow or
+/// numerous other clusters.
+pub struct PollQueue {
+    budget: usize,
+    reserved: usize,
+    state: Mutex<QueueState>,
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    running: usize,
+
+    /// How many of the running polls are of high priority clusters.
+    high: usize,
+    waiting: BTreeSet<Ticket>,
+
+    /// Clusters whose next poll goes to the front of the line.
+    prioritized: HashSet<ClusterId>,
+}
+
+impl QueueState {
+    fn ticket(&self, id: ClusterId) -> Option<Ticket> {
+        self.waiting.iter().find(|t| t.3 == id).copied()
+    }
+}
+
+impl PollQueue {
+    /// A queue running up to `budget` polls at once, a quarter of which is
+    /// reserved for high priority clusters.
+    pub fn new(budget: usize) -> Self {
+        let budget = budget.max(1);
+        Self {
+            budget,
+            reserved: (budget / 4).max(1).min(budget - 1),
+            state: Mutex::new(QueueState::default()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Wait until the cluster's poll is due and granted a slot of the budget,
+    /// which it holds until the returned permit is dropped.
+    pub async fn acquire(&self, id: ClusterId, priority: Priority, due: Instant) -> PollPermit<'_> {
+        if due > Instant::now() {
+            tokio::time::sleep_until(due).await;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let rank = match state.prioritized.contains(&id) {
+            true => Rank::Prioritized,
+            false => Rank::Queued,
+        };
+        state.waiting.insert((rank, priority, due, id));
+        drop(state);
+        let mut waiting = Waiting {
+            queue: self,
+            id: Some(id),
+        };
+
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if self.grant(id) {
+                waiting.id = None;
+                // The next ticket in line may fit into the budget as well.
+                self.changed.notify_waiters();
+                return PollPermit {
+                    queue: self,
+                    priority,
+                };
+            }
+
+            changed.await;
+        }
+    }
+
+    /// Grant the slot when the cluster is first in line and its priority may use one.
+    fn grant(&self, id: ClusterId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(ticket) = state.waiting.first().copied().filter(|t| t.3 == id) else {
+            return false;
+        };
+
+        // High priority polls fill the reserved slice first, the others never touch it.
+        let others = state.running - state.high;
+        let fits = match ticket.1 {
+            Priority::High => state.running < self.budget,
+            _ => state.running < self.budget && others < self.budget - self.reserved,
+        };
+        if !fits {
+            return false;
+        }
+
+        state.waiting.remove(&ticket);
+        state.prioritized.remove(&id);
+        state.running += 1;
+        if ticket.1 == Priority::High {
+            state.high += 1;
+        }
+        true
+    }
+
+    /// Move the next poll of the cluster to the front of the line, ahead of
+    /// every poll not prioritized, whether it is waiting already or not yet due.
+    pub fn prioritize(&self, id: ClusterId) {
+        let mut state = self.state.lock().unwrap();
+        state.prioritized.insert(id);
+        if let Some(ticket) = state.ticket(id) {
+            state.waiting.remove(&ticket);
+            state
+                .waiting
+                .insert((Rank::Prioritized, ticket.1, ticket.2, id));
+        }
+        drop(state);
+        self.changed.notify_waiters();
+    }
+
+    /// Forget a prioritization not granted yet, e.g. of a removed cluster.
+    pub fn forget(&self, id: ClusterId) {
+        self.state.lock().unwrap().prioritized.remove(&id);
+    }
+
+    /// How many polls are ahead of the cluster's, while it waits for a slot.
+    pub fn position(&self, id: ClusterId) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        state.waiting.iter().position(|t| t.3 == id)
+    }
+}
+
+/// A slot of the poll budget, released on drop.
+pub struct PollPermit<'a> {
+    queue: &'a PollQueue,
+    priority: Priority,
+}
+
+impl Drop for PollPermit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.running -= 1;
+        if self.priority == Priority::High {
+            state.high -= 1;
+        }
+        drop(state);
+        self.queue.changed.notify_waiters();
+    }
+}
+
+/// Takes a ticket out of line when its poll is cancelled, e.g. on shutdown.
+struct Waiting<'a> {
+    queue: &'a PollQueue,
+    id: Option<ClusterId>,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let mut state = self.queue.state.lock().unwrap();
+            if let Some(ticket) = state.ticket(id) {
+                state.waiting.remove(&ticket);
+            }
+            drop(state);
+            self.queue.changed.notify_waiters();
+        }
+    }
+}
+
+#[test]
+fn it_opens_the_circuit_after_consecutive_failures() {
+    let interval = Duration::from_secs(1);
+    let mut breaker = Breaker::default();
+
+    for _ in 0..CIRCUIT_THRESHOLD - 1 {
+        breaker.record(false);
+    }
+    assert!(!breaker.is_open());
+    assert_eq!(breaker.delay(interval), interval);
+
+    let delays = (0..6)
+        .map(|_| {
+            breaker.record(false);
+            breaker.delay(interval).as_secs()
+        })
+        .collect::<Vec<_>>();
+    assert!(breaker.is_open());
+    assert_eq!(delays, vec![30, 60, 120, 240, 300, 300]);
+
+    // A cluster polled less often than the cooldown keeps its interval.
+    assert_eq!(breaker.delay(Duration::from_secs(600)).as_secs(), 600);
+
+    breaker.record(true);
+    assert!(!breaker.is_open());
+    assert_eq!(breaker.delay(interval), interval);
+}
+
+#[test]
+fn it_jitters_the_backoff_up_to_its_cap() {
+    let interval = Duration::from_secs(1);
+    let mut breaker = Breaker::default().with_max(Duration::from_secs(90));
+
+    for _ in 0..CIRCUIT_THRESHOLD - 1 {
+        breaker.record(false);
+    }
+    assert_eq!(breaker.backoff(ClusterId(1), interval), interval);
+
+    let max = Duration::from_secs(90);
+    let tenth = |d: Duration| d * CIRCUIT_JITTER_PERCENT as u32 / 100;
+    for cooldown in [30, 60, 90, 90] {
+        breaker.record(false);
+        let cooldown = Duration::from_secs(cooldown);
+        assert_eq!(breaker.delay(interval), cooldown);
+        let backoff = breaker.backoff(ClusterId(1), interval);
+        let (least, most) = match cooldown + tenth(cooldown) <= max {
+            true => (cooldown, cooldown + tenth(cooldown)),
+            false => (max - tenth(max), max),
+        };
+        assert!(backoff >= least && backoff <= most, "{:?}", backoff);
+        assert_eq!(breaker.backoff(ClusterId(1), interval), backoff);
+    }
+
+    // Clusters failing alike retry at different times, none past the cap.
+    let others = (2..100)
+        .map(|id| breaker.backoff(ClusterId(id), interval))
+        .collect::<HashSet<_>>();
+    assert!(others.len() > 1);
+    assert!(others.iter().all(|backoff| *backoff <= max), "{:?}", others);
+
+    // A cooldown the jitter would take past the cap is shortened instead.
+    let mut breaker = Breaker::default().with_max(Duration::from_secs(65));
+    for _ in 0..=CIRCUIT_THRESHOLD {
+        breaker.record(false);
+    }
+    assert_eq!(breaker.delay(interval), Duration::from_secs(60));
+    for id in 1..100 {
+        let backoff = breaker.backoff(ClusterId(id), interval);
+        assert!(backoff <= Duration::from_secs(65), "{:?}", backoff);
+    }
+}
+
+#[test]
+fn it_stretches_adaptive_intervals_while_nothing_changes() {
+    let mut adaptive = Adaptive::new(Duration::from_secs(10), Duration::from_secs(60));
+
+    let intervals = (0..8)
+        .map(|_| {
+            adaptive.record(PollOutcome::Unchanged);
+            adaptive.interval.as_secs_f64()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        intervals,
+        vec![10.0, 10.0, 15.0, 22.5, 33.75, 50.625, 60.0, 60.0]
+    );
+    assert_eq!(adaptive.state(), AdaptiveState::Stretched);
+
+    // A change snaps back, and changes arriving keep it at the base interval.
+    adaptive.record(PollOutcome::Changed);
+    assert_eq!(adaptive.state(), AdaptiveState::Base);
+    for outcome in [
+        PollOutcome::Unchanged,
+        PollOutcome::Unchanged,
+        PollOutcome::Changed,
+    ] {
+        adaptive.record(outcome);
+        assert_eq!(adaptive.interval, Duration::from_secs(10));
+    }
+    for _ in 0..3 {
+        adaptive.record(PollOutcome::Unchanged);
+    }
+    assert_eq!(adaptive.state(), AdaptiveState::Stretching);
+    adaptive.record(PollOutcome::Failed);
+    assert_eq!(
+        (adaptive.state(), adaptive.unchanged),
+        (AdaptiveState::Base, 0)
+    );
+
+    // A maximum below the interval never stretches it.
+    let mut fixed = Adaptive::new(Duration::from_secs(10), Duration::from_secs(5));
+    for _ in 0..5 {
+        fixed.record(PollOutcome::Unchanged);
+    }
+    assert_eq!(
+        (fixed.interval, fixed.state()),
+        (Duration::from_secs(10), AdaptiveState::Base)
+    );
+}
+
+#[test]
+fn it_parses_priorities_and_reserves_a_slice_of_the_budget() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+
+    let cluster = |priority: &str| {
+        let config = HashMap::from([(config::METADATA_PRIORITY.to_string(), priority.to_string())]);
+        Cluster::new(None, Kind::Kafka, "local".to_string(), config)
+    };
+    assert_eq!(priority(&cluster("high")), Priority::High);
+    assert_eq!(priority(&cluster("urgent")), Priority::Normal);
+    assert!(Priority::High < Priority::Low);
+
+    for (budget, reserved) in [(1, 0), (2, 1), (8, 2), (10, 2)] {
+        assert_eq!(PollQueue::new(budget).reserved, reserved, "{}", budget);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_grants_slots_by_priority_and_keeps_the_reserved_slice() {
+    let queue = PollQueue::new(2);
+    let now = Instant::now();
+
+    let low = queue.acquire(ClusterId(1), Priority::Low, now).await;
+
+    // The only unreserved slot is taken, so only high priority polls get one.
+    let normal = queue.acquire(ClusterId(2), Priority::Normal, now);
+    tokio::pin!(normal);
+    assert!(futures::poll!(normal.as_mut()).is_pending());
+    let high = queue.acquire(ClusterId(3), Priority::High, now).await;
+
+    drop(low);
+    let _normal = normal.await;
+    drop(high);
+    assert_eq!(queue.state.lock().unwrap().running, 1);
+    assert!(queue.state.lock().unwrap().waiting.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_moves_prioritized_polls_to_the_front_of_the_line() {
+    let queue = PollQueue::new(1);
+    let now = Instant::now();
+    let running = queue.acquire(ClusterId(1), Priority::High, now).await;
+
+    let second = queue.acquire(ClusterId(2), Priority::Normal, now);
+    let third = queue.acquire(ClusterId(3), Priority::High, now);
+    let fourth = queue.acquire(ClusterId(4), Priority::Low, now);
+    tokio::pin!(second, third, fourth);
+    assert!(futures::poll!(second.as_mut()).is_pending());
+    assert!(futures::poll!(third.as_mut()).is_pending());
+    assert!(futures::poll!(fourth.as_mut()).is_pending());
+    assert_eq!(queue.position(ClusterId(4)), Some(2));
+
+    // Prioritized polls go ahead of higher priority ones too.
+    queue.prioritize(ClusterId(4));
+    assert_eq!(queue.position(ClusterId(4)), Some(0));
+    assert_eq!(queue.position(ClusterId(3)), Some(1));
+
+    drop(running);
+    assert!(futures::poll!(third.as_mut()).is_pending());
+    let permit = fourth.await;
+    assert_eq!(queue.position(ClusterId(4)), None);
+
+    // Clusters prioritized before their poll is due go first once it is.
+    queue.prioritize(ClusterId(5));
+    let fifth = queue.acquire(ClusterId(5), Priority::Low, now + Duration::from_secs(1));
+    tokio::pin!(fifth);
+    assert!(futures::poll!(fifth.as_mut()).is_pending());
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert!(futures::poll!(fifth.as_mut()).is_pending());
+    assert_eq!(queue.position(ClusterId(5)), Some(0));
+
+    drop(permit);
+    assert!(futures::poll!(third.as_mut()).is_pending());
+    drop(fifth.await);
+    drop(third.await);
+    assert!(queue.state.lock().unwrap().prioritized.is_empty());
+}
@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::clusters::cluster::Cluster;
+use crate::kafka::config;
+
+use super::TopicOffsets;
+
+/// Default multiple of the mean partition rate that marks a partition as hot.
+pub const DEFAULT_HOT_THRESHOLD: f64 = 3.0;
+
+/// Default number of consecutive samples that set or clear the hot flag.
+pub const DEFAULT_HOT_SAMPLES: usize = 3;
+
+/// Skew detection settings resolved from the cluster config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkewConfig {
+    pub threshold: f64,
+    pub samples: usize,
+}
+
+impl Default for SkewConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_HOT_THRESHOLD,
+            samples: DEFAULT_HOT_SAMPLES,
+        }
+    }
+}
+
+impl SkewConfig {
+    pub fn from(cluster: &Cluster) -> Self {
+        let get = |key: &str| cluster.config.get(key);
+
+        Self {
+            threshold: get(config::HOT_PARTITION_THRESHOLD)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_HOT_THRESHOLD),
+            samples: get(config::HOT_PARTITION_SAMPLES)
+                .and_then(|v| v.parse().ok())
+                .map(|v: usize| v.max(1))
+                .unwrap_or(DEFAULT_HOT_SAMPLES),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionRate {
+    pub id: i32,
+
+    /// Messages per second between the last two polls.
+    pub rate: f64,
+
+    /// Number of rates sampled for the partition, up to the detection window.
+    pub samples: usize,
+}
+
+/// The per-partition throughput of a topic and how evenly it is spread.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TopicThroughput {
+    pub name: String,
+
+    /// Messages per second across all partitions.
+    pub rate: f64,
+
+    pub partitions: Vec<PartitionRate>,
+
+    /// Standard deviation of the partition rates relative to their mean.
+    pub coefficient_of_variation: f64,
+
+    /// The partition with the highest rate, and its share of the topic rate.
+    pub hottest_partition: Option<i32>,
+    pub hottest_share: f64,
+
+    /// Set once one partition exceeded the threshold for the configured number
+    /// of consecutive samples, and cleared once it stayed below for as many.
+    pub hot_partition: bool,
+}
+
+/// Message rates of the partitions present in both samples.
+///
+/// Partitions whose high watermark went backwards, e.g. because the topic was
+/// recreated, report a rate of zero.
+pub fn rates(
+    previous: &TopicOffsets,
+    current: &TopicOffsets,
+    elapsed_ms: i64,
+) -> BTreeMap<i32, f64> {
+    if elapsed_ms <= 0 {
+        return BTreeMap::new();
+    }
+
+    let previous = previous
+        .partitions
+        .iter()
+        .map(|p| (p.id, p.high))
+        .collect::<HashMap<_, _>>();
+
+    current
+        .partitions
+        .iter()
+        .filter_map(|p| {
+            let before = previous.get(&p.id)?;
+            let delta = (p.high - before).max(0);
+            Some((p.id, delta as f64 * 1_000.0 / elapsed_ms as f64))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Skew {
+    mean: f64,
+    coefficient_of_variation: f64,
+    hottest: Option<(i32, f64)>,
+}
+
+fn skew(rates: &[(i32, f64)]) -> Skew {
+    if rates.is_empty() {
+        return Skew {
+            mean: 0.0,
+            coefficient_of_variation: 0.0,
+            hottest: None,
+        };
+    }
+
+    let n = rates.len() as f64;
+    let mean = rates.iter().map(|(_, r)| r).sum::<f64>() / n;
+    let variance = rates.iter().map(|(_, r)| (r - mean).powi(2)).sum::<f64>() / n;
+    let coefficient_of_variation = match mean > 0.0 {
+        true => variance.sqrt() / mean,
+        false => 0.0,
+    };
+    let hottest = rates
+        .iter()
+        .copied()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+
+    Skew {
+        mean,
+        coefficient_of_variation,
+        hottest,
+    }
+}
+
+/// Tracks the partition rates of a single topic and flags sustained hot partitions.
+#[derive(Debug, Clone)]
+pub struct HotPartitionDetector {
+    config: SkewConfig,
+    windows: BTreeMap<i32, VecDeque<f64>>,
+    hot_streak: usize,
+    cold_streak: usize,
+    hot: bool,
+}
+
+impl HotPartitionDetector {
+    pub fn new(config: SkewConfig) -> Self {
+        Self {
+            config,
+            windows: BTreeMap::new(),
+            hot_streak: 0,
+            cold_streak: 0,
+            hot: false,
+        }
+    }
+
+    /// Add a sample of partition rates and return the resulting throughput.
+    ///
+    /// Only partitions sampled for the whole window take part in the skew
+    /// analysis, so a partition created mid-window can't skew the mean.
+    pub fn push(&mut self, name: &str, rates: &BTreeMap<i32, f64>) -> TopicThroughput {
+        self.windows.retain(|id, _| rates.contains_key(id));
+        for (&id, &rate) in rates {
+            let window = self.windows.entry(id).or_default();
+            window.push_back(rate);
+            if window.len() > self.config.samples {
+                window.pop_front();
+            }
+        }
+
+        let window = self.windows.values().map(|w| w.len()).max().unwrap_or(0);
+        let eligible = self
+            .windows
+            .iter()
+            .filter(|(_, w)| w.len() == window)
+            .map(|(&id, w)| (id, *w.back().unwrap()))
+            .collect::<Vec<_>>();
+
+        let skew = skew(&eligible);
+        let sample_hot = eligible.len() > 1
+            && skew.mean > 0.0
+            && skew
+                .hottest
+                .is_some_and(|(_, rate)| rate > self.config.threshold * skew.mean);
+
+        // Require as many consecutive samples to clear the flag as to set it.
+        if sample_hot {
+            self.hot_streak += 1;
+            self.cold_streak = 0;
+        } else {
+            self.cold_streak += 1;
+            self.hot_streak = 0;
+        }
+        if self.hot_streak >= self.config.samples {
+            self.hot = true;
+        }
+        if self.cold_streak >= self.config.samples {
+            self.hot = false;
+        }
+
+        let eligible_total = eligible.iter().map(|(_, r)| r).sum::<f64>();
+        let hottest_share = match (skew.hottest, eligible_total > 0.0) {
+            (Some((_, rate)), true) => rate / eligible_total,
+            _ => 0.0,
+        };
+
+        TopicThroughput {
+            name: name.to_string(),
+            rate: rates.values().sum(),
+            partitions: self
+                .windows
+                .iter()
+                .map(|(&id, w)| PartitionRate {
+                    id,
+                    rate: *w.back().unwrap(),
+                    samples: w.len(),
+                })
+                .collect(),
+            coefficient_of_variation: skew.coefficient_of_variation,
+            hottest_partition: skew.hottest.map(|(id, _)| id),
+            hottest_share,
+            hot_partition: self.hot,
+        }
+    }
+}
+
+/// Derives per-partition throughput of a cluster's topics from successive watermark polls.
+#[derive(Debug, Clone, Default)]
+pub struct ThroughputTracker {
+    config: SkewConfig,
+    previous: Option<(i64, HashMap<String, TopicOffsets>)>,
+    detectors: HashMap<String, HotPartitionDetector>,
+    topics: HashMap<String, TopicThroughput>,
+}
+
+impl ThroughputTracker {
+    pub fn new(config: SkewConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Record a watermark poll, returning the topics whose hot flag changed.
+    pub fn observe(&mut self, offsets: &[TopicOffsets], at_ms: i64) -> Vec<TopicThroughput> {
+        let current = offsets
+            .iter()
+            .map(|t| (t.name.clone(), t.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut changed = Vec::new();
+        if let Some((previous_ms, previous)) = &self.previous {
+            self.detectors.retain(|name, _| current.contains_key(name));
+            self.topics.retain(|name, _| current.contains_key(name));
+
+            for (name, topic) in &current {
+                let Some(before) = previous.get(name) else {
+                    continue;
+                };
+
+                let rates = rates(before, topic, at_ms - previous_ms);
+                let detector = self
+                    .detectors
+                    .entry(name.clone())
+                    .or_insert_with(|| HotPartitionDetector::new(self.config.clone()));
+                let throughput = detector.push(name, &rates);
+
+                let was_hot = self.topics.get(name).is_some_and(|t| t.hot_partition);
+                if throughput.hot_partition != was_hot {
+                    changed.push(throughput.clone());
+                }
+                self.topics.insert(name.clone(), throughput);
+            }
+        }
+
+        self.previous = Some((at_ms, current));
+        changed
+    }
+
+    pub fn topics(&self) -> &HashMap<String, TopicThroughput> {
+        &self.topics
+    }
+}
+
+#[cfg(test)]
+fn run(detector: &mut HotPartitionDetector, series: &[&[(i32, f64)]]) -> Vec<bool> {
+    series
+        .iter()
+        .map(|sample| {
+            let rates = sample.iter().copied().collect();
+            detector.push("orders", &rates).hot_partition
+        })
+        .collect()
+}
+
+#[cfg(test)]
+const EVEN: &[(i32, f64)] = &[(0, 10.0), (1, 10.0), (2, 10.0), (3, 10.0)];
+
+#[cfg(test)]
+const SKEWED: &[(i32, f64)] = &[(0, 100.0), (1, 10.0), (2, 10.0), (3, 10.0)];
+
+#[test]
+fn it_flags_sustained_hot_partitions() {
+    let mut detector = HotPartitionDetector::new(SkewConfig::default());
+
+    let flags = run(&mut detector, &[SKEWED, SKEWED, SKEWED, SKEWED]);
+    assert_eq!(flags, vec![false, false, true, true]);
+
+    let throughput = detector.push("orders", &SKEWED.iter().copied().collect());
+    assert_eq!(throughput.hottest_partition, Some(0));
+    assert!((throughput.hottest_share - 100.0 / 130.0).abs() < 1e-9);
+    assert!(throughput.coefficient_of_variation > 1.0);
+    assert_eq!(throughput.rate, 130.0);
+}
+
+#[test]
+fn it_ignores_single_bursts_and_clears_with_hysteresis() {
+    let mut detector = HotPartitionDetector::new(SkewConfig::default());
+
+    // A burst shorter than the window never sets the flag.
+    let flags = run(&mut detector, &[EVEN, EVEN, EVEN, SKEWED, SKEWED, EVEN]);
+    assert!(flags.iter().all(|hot| !hot));
+
+    // Once set, a single even sample doesn't clear it.
+    let flags = run(
+        &mut detector,
+        &[SKEWED, SKEWED, SKEWED, EVEN, SKEWED, EVEN, EVEN, EVEN],
+    );
+    assert_eq!(
+        flags,
+        vec![false, false, true, true, true, true, true, false]
+    );
+}
+
+#[test]
+fn it_never_flags_single_partition_topics() {
+    let mut detector = HotPartitionDetector::new(SkewConfig::default());
+
+    let single: &[(i32, f64)] = &[(0, 500.0)];
+    let flags = run(&mut detector, &[single; 6]);
+    assert!(flags.iter().all(|hot| !hot));
+
+    let throughput = detector.push("orders", &BTreeMap::from([(0, 500.0)]));
+    assert_eq!(throughput.coefficient_of_variation, 0.0);
+    assert_eq!(throughput.hottest_share, 1.0);
+}
+
+#[test]
+fn it_excludes_partitions_created_mid_window() {
+    let mut detector = HotPartitionDetector::new(SkewConfig::default());
+    let even: &[(i32, f64)] = &[(0, 10.0), (1, 10.0), (2, 10.0)];
+    let added: &[(i32, f64)] = &[(0, 10.0), (1, 10.0), (2, 10.0), (3, 200.0)];
+
+    run(&mut detector, &[even, even, even]);
+
+    // The new partition only counts once it was sampled for the whole window.
+    let flags = run(&mut detector, &[added, added, added, added, added]);
+    assert_eq!(flags, vec![false, false, false, false, true]);
+
+    let throughput = detector.push("orders", &added.iter().copied().collect());
+    let new = throughput.partitions.iter().find(|p| p.id == 3).unwrap();
+    assert_eq!(new.samples, 3);
+    assert_eq!(throughput.hottest_partition, Some(3));
+}
+
+#[test]
+fn it_derives_rates_from_watermarks() {
+    use super::PartitionOffsets;
+
+    let offsets = |highs: &[(i32, i64)]| TopicOffsets {
+        name: "orders".to_string(),
+        partitions: highs
+            .iter()
+            .map(|&(id, high)| PartitionOffsets { id, low: 0, high })
+            .collect(),
+        head_timestamp: None,
+    };
+
+    let previous = offsets(&[(0, 100), (1, 500)]);
+    let current = offsets(&[(0, 300), (1, 400), (2, 50)]);
+
+    assert_eq!(
+        rates(&previous, &current, 2_000),
+        BTreeMap::from([(0, 100.0), (1, 0.0)])
+    );
+
+    let mut tracker = ThroughputTracker::new(SkewConfig::default());
+    assert!(tracker.observe(&[previous], 0).is_empty());
+    assert!(tracker.topics().is_empty());
+    tracker.observe(&[current], 2_000);
+    assert_eq!(tracker.topics()["orders"].rate, 100.0);
+}
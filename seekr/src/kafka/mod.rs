@@ -1,9 +1,67 @@
+pub mod admin;
+pub mod auth;
+pub mod client;
 pub mod metadata;
+pub mod producer;
+pub mod sensitive;
 pub mod streams;
 
 pub mod config {
     pub const BOOTSTRAP_SERVERS: &str = "bootstrap.servers";
     pub const SEEKR_GROUP_ID: &str = "seekr.group.id";
+    pub const AUTO_OFFSET_RESET: &str = "auto.offset.reset";
+    pub const START_AT_TIMESTAMP: &str = "start.at.timestamp";
+    pub const SECURITY_PROTOCOL: &str = "security.protocol";
+    pub const SASL_MECHANISM: &str = "sasl.mechanism";
+    pub const SASL_USERNAME: &str = "sasl.username";
+    pub const SASL_PASSWORD: &str = "sasl.password";
+    pub const SSL_CA_LOCATION: &str = "ssl.ca.location";
+    pub const SSL_CERTIFICATE_LOCATION: &str = "ssl.certificate.location";
+    pub const SSL_KEY_LOCATION: &str = "ssl.key.location";
+    pub const AUTH_PROVIDER: &str = "auth.provider";
+    pub const AWS_REGION: &str = "aws.region";
+    pub const AWS_ROLE_ARN: &str = "aws.role.arn";
     pub const METADATA_POLL_INTERVAL: &str = "metadata.poll.interval.ms";
+    pub const METADATA_POLL_ADAPTIVE: &str = "metadata.poll.adaptive";
+    pub const METADATA_POLL_MAX_INTERVAL: &str = "metadata.poll.max.interval.ms";
+    pub const METADATA_POLL_MAX_BACKOFF: &str = "metadata.poll.max.backoff.ms";
+    pub const METADATA_PRIORITY: &str = "metadata.priority";
+    pub const METADATA_SYSTEM_TOPICS: &str = "metadata.system.topics";
     pub const METRICS_POLL_INTERVAL: &str = "metrics.poll.interval.ms";
+    pub const STORAGE_POLL_INTERVAL: &str = "storage.poll.interval.ms";
+    pub const THROUGHPUT_ENABLED: &str = "throughput.enabled";
+    pub const HOT_PARTITION_THRESHOLD: &str = "hot.partition.threshold";
+    pub const HOT_PARTITION_SAMPLES: &str = "hot.partition.samples";
+    pub const LIVENESS_ENABLED: &str = "liveness.enabled";
+    pub const LIVENESS_STALL_THRESHOLD: &str = "liveness.stall.threshold.ms";
+    pub const LIVENESS_MAX_RECREATIONS: &str = "liveness.max.recreations";
+    pub const CHANGEFEED_ENABLED: &str = "changefeed.enabled";
+    pub const CHANGEFEED_INCLUDE_PAYLOAD: &str = "changefeed.include.payload";
+    pub const RETENTION: &str = "retention.ms";
+    pub const INDEX_SHARD_PERIOD: &str = "index.shard.period";
+    pub const DOCUMENT_ID: &str = "document.id";
+    pub const TOMBSTONE_ACTION: &str = "tombstone.action";
+    pub const PAYLOAD_FORMAT: &str = "payload.format";
+    pub const BUDGET_ENABLED: &str = "budget.enabled";
+    pub const BUDGET_WINDOW: &str = "budget.window.ms";
+    pub const BUDGET_SUSTAINED_WINDOWS: &str = "budget.sustained.windows";
+    pub const CATCHUP_THRESHOLD: &str = "catchup.threshold";
+    pub const CATCHUP_SUSTAINED: &str = "catchup.sustained.ms";
+    pub const FRESHNESS_MAX_LAG: &str = "freshness.max.lag.ms";
+    pub const FRESHNESS_IDLE_STALE: &str = "freshness.idle.stale";
+    pub const COMMANDS_TIMEOUT: &str = "commands.timeout.ms";
+    pub const PRODUCE_ENABLED: &str = "produce.enabled";
+    pub const PRODUCE_TOPICS_REGEX: &str = "produce.topics.regex";
+    pub const PRODUCE_MAX_PAYLOAD_BYTES: &str = "produce.max.payload.bytes";
+
+    /// Settings passed as they are to every client of a cluster.
+    pub const CONNECTION: [&str; 7] = [
+        SECURITY_PROTOCOL,
+        SASL_MECHANISM,
+        SASL_USERNAME,
+        SASL_PASSWORD,
+        SSL_CA_LOCATION,
+        SSL_CERTIFICATE_LOCATION,
+        SSL_KEY_LOCATION,
+    ];
 }
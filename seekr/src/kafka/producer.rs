@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use tokio::sync::RwLock;
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::client::broker_config;
+
+/// Timeout for a message to be acknowledged by the brokers.
+pub const SEND_TIMEOUT: Duration = Duration::from_millis(10_000);
+
+/// A message to write to a topic.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutgoingMessage {
+    pub key: Option<String>,
+    pub payload: String,
+    pub headers: HashMap<String, String>,
+}
+
+#[async_trait]
+pub trait MessageProducer {
+    /// Write a message to a topic, returning the partition and offset it was written to.
+    async fn send(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        message: OutgoingMessage,
+    ) -> Result<(i32, i64), AnyError>;
+}
+
+/// Produces to Kafka, keeping one producer per cluster.
+#[derive(Default)]
+pub struct KafkaMessageProducer {
+    producers: RwLock<HashMap<ClusterId, FutureProducer>>,
+}
+
+impl KafkaMessageProducer {
+    async fn producer(&self, cluster: &Cluster) -> Result<FutureProducer, AnyError> {
+        if let Some(p) = self.producers.read().await.get(&cluster.id) {
+            return Ok(p.clone());
+        }
+
+        let producer = broker_config(cluster).create::<FutureProducer>()?;
+
+        self.producers
+            .write()
+            .await
+            .insert(cluster.id, producer.clone());
+
+        Ok(producer)
+    }
+}
+
+#[async_trait]
+impl MessageProducer for KafkaMessageProducer {
+    async fn send(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        message: OutgoingMessage,
+    ) -> Result<(i32, i64), AnyError> {
+        let producer = self.producer(cluster).await?;
+
+        let mut headers = OwnedHeaders::new();
+        for (k, v) in &message.headers {
+            headers = headers.insert(Header {
+                key: k,
+                value: Some(v.as_str()),
+            });
+        }
+
+        let mut record = FutureRecord::to(topic)
+            .payload(&message.payload)
+            .headers(headers);
+        if let Some(key) = &message.key {
+            record = record.key(key);
+        }
+
+        producer
+            .send(record, SEND_TIMEOUT)
+            .await
+            .map_err(|(e, _)| e.into())
+    }
+}
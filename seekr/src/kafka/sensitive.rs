@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+/// What the values of sensitive keys are replaced with in redacted configs.
+pub const REDACTED: &str = "***";
+
+/// The keys whose values are secrets, whichever client they're for.
+const DEFAULT_PATTERNS: [&str; 4] = ["*.password", "*.secret", "sasl.*password*", "ssl.key.*"];
+
+/// The config keys whose values are secrets, never logged nor returned as
+/// they are. Patterns are matched against whole keys, `*` standing for any
+/// characters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SensitiveKeys {
+    patterns: Vec<String>,
+}
+
+impl Default for SensitiveKeys {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+impl SensitiveKeys {
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        self.patterns.iter().any(|p| glob(p, key))
+    }
+
+    /// The config with the values of sensitive keys replaced.
+    pub fn redact(&self, config: &HashMap<String, String>) -> HashMap<String, String> {
+        config
+            .iter()
+            .map(|(k, v)| match self.is_sensitive(k) {
+                true => (k.clone(), REDACTED.to_string()),
+                false => (k.clone(), v.clone()),
+            })
+            .collect()
+    }
+
+    /// The config written back with the redacted values it was read with
+    /// replaced by the current ones, so round trips keep the secrets.
+    pub fn restore(
+        &self,
+        mut config: HashMap<String, String>,
+        current: &HashMap<String, String>,
+    ) -> HashMap<String, String> {
+        for (key, value) in config.iter_mut() {
+            if value == REDACTED && self.is_sensitive(key) {
+                if let Some(secret) = current.get(key) {
+                    *value = secret.clone();
+                }
+            }
+        }
+        config
+    }
+}
+
+/// The config with the values of the default sensitive keys replaced, for
+/// logs and responses.
+pub fn redact(config: &HashMap<String, String>) -> HashMap<String, String> {
+    SensitiveKeys::default().redact(config)
+}
+
+/// The config with the default sensitive keys still redacted set back to
+/// their current values.
+pub fn restore(
+    config: HashMap<String, String>,
+    current: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    SensitiveKeys::default().restore(config, current)
+}
+
+/// Whether the whole of `text` matches `pattern`, `*` matching any characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`, the prefix must be all of it.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[test]
+fn it_redacts_sensitive_keys_and_restores_them_on_write() {
+    let keys = SensitiveKeys::default();
+    for key in [
+        "sasl.password",
+        "ssl.key.password",
+        "ssl.keystore.password",
+        "sasl.oauthbearer.client.secret",
+        "sasl.jaas.password.file",
+        "ssl.key.location",
+        "ssl.key.pem",
+    ] {
+        assert!(keys.is_sensitive(key), "{}", key);
+    }
+    for key in [
+        "sasl.username",
+        "sasl.mechanism",
+        "ssl.ca.location",
+        "password",
+        "secret.rotation",
+    ] {
+        assert!(!keys.is_sensitive(key), "{}", key);
+    }
+    assert!(keys
+        .clone()
+        .with_pattern("aws.*")
+        .is_sensitive("aws.role.arn"));
+
+    let config = HashMap::from([
+        ("sasl.username".to_string(), "seekr".to_string()),
+        ("sasl.password".to_string(), "hunter2".to_string()),
+        (
+            "ssl.key.location".to_string(),
+            "/etc/seekr/client.key".to_string(),
+        ),
+    ]);
+    let redacted = redact(&config);
+    assert_eq!(redacted["sasl.username"], "seekr");
+    assert_eq!(redacted["sasl.password"], REDACTED);
+    assert_eq!(redacted["ssl.key.location"], REDACTED);
+    assert!(!format!("{:?}", redacted).contains("hunter2"));
+
+    assert_eq!(restore(redacted.clone(), &config), config);
+    let mut changed = redacted;
+    changed.insert("sasl.password".to_string(), "correct-horse".to_string());
+    let written = restore(changed, &config);
+    assert_eq!(written["sasl.password"], "correct-horse");
+    assert_eq!(written["ssl.key.location"], "/etc/seekr/client.key");
+}
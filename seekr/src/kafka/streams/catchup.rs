@@ -0,0 +1,515 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kafka::config;
+use crate::subscriptions::subscription::Subscription;
+
+/// How often the end offsets are sampled to estimate the catch-up.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Samples kept per partition, the window the rates are measured over.
+pub const WINDOW: usize = 12;
+
+/// The lag, in messages, a subscription is considered caught up within by default.
+pub const DEFAULT_THRESHOLD: i64 = 100;
+
+/// How long the lag stays on one side of the threshold before `caught_up` flips, by default.
+pub const DEFAULT_SUSTAINED: Duration = Duration::from_secs(60);
+
+/// Weight of the most recent interval in the smoothed rates.
+const SMOOTHING: f64 = 0.3;
+
+/// Fewest intervals measured before the confidence can be more than low.
+const MIN_INTERVALS: usize = 3;
+
+/// Catch-up settings resolved from the subscription config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CatchUpConfig {
+    pub threshold: i64,
+    pub sustained: Duration,
+}
+
+impl Default for CatchUpConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            sustained: DEFAULT_SUSTAINED,
+        }
+    }
+}
+
+impl CatchUpConfig {
+    /// The settings of `catchup.threshold` and `catchup.sustained.ms`.
+    pub fn from(subscription: &Subscription) -> Self {
+        let get = |key: &str| subscription.config.get(key);
+
+        Self {
+            threshold: get(config::CATCHUP_THRESHOLD)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_THRESHOLD)
+                .max(0),
+            sustained: get(config::CATCHUP_SUSTAINED)
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_SUSTAINED),
+        }
+    }
+}
+
+/// When the lag closes at the current rates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Eta {
+    /// Not enough samples to measure the rates yet.
+    Unknown,
+
+    /// The lag closes in about this many milliseconds.
+    Ms(u64),
+
+    /// Production keeps up with consumption, the lag never closes at the current rates.
+    Never,
+}
+
+/// How much the consumption rate varied over the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+/// The position of a partition and its high watermark at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    /// Milliseconds since the samples started.
+    pub at_ms: u64,
+
+    /// The offset of the next message to consume.
+    pub position: i64,
+
+    /// The offset the next produced message gets.
+    pub end: i64,
+}
+
+impl Sample {
+    pub fn lag(&self) -> i64 {
+        (self.end - self.position).max(0)
+    }
+}
+
+/// The catch-up of one partition.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PartitionCatchUp {
+    pub partition: i32,
+    pub lag: i64,
+
+    /// Smoothed messages consumed per second.
+    pub consumption_rate: f64,
+
+    /// Smoothed messages produced per second.
+    pub production_rate: f64,
+
+    pub eta: Eta,
+    pub confidence: Confidence,
+}
+
+/// The catch-up of a subscription, over all its partitions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CatchUpReport {
+    pub lag: i64,
+
+    /// The latest of the partitions' ETAs.
+    pub eta: Eta,
+
+    /// The lowest of the partitions' confidences.
+    pub confidence: Confidence,
+
+    /// Whether the lag has stayed within `catchup.threshold` for `catchup.sustained.ms`.
+    pub caught_up: bool,
+
+    pub partitions: Vec<PartitionCatchUp>,
+}
+
+/// Estimate the catch-up of a partition from its samples, oldest first.
+///
+/// The rates are the exponentially smoothed rates of the intervals between
+/// samples, so a production spike moves the estimate without taking it over.
+pub fn estimate(partition: i32, samples: &[Sample]) -> PartitionCatchUp {
+    let lag = samples.last().map_or(0, Sample::lag);
+    let intervals = samples
+        .windows(2)
+        .filter_map(|w| {
+            let secs = w[1].at_ms.checked_sub(w[0].at_ms)? as f64 / 1000.0;
+            (secs > 0.0).then(|| {
+                (
+                    (w[1].position - w[0].position) as f64 / secs,
+                    (w[1].end - w[0].end) as f64 / secs,
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let consumption = smooth(intervals.iter().map(|(c, _)| *c));
+    let production = smooth(intervals.iter().map(|(_, p)| *p));
+    let eta = match (lag, intervals.is_empty()) {
+        (0, _) => Eta::Ms(0),
+        (_, true) => Eta::Unknown,
+        _ if consumption <= production => Eta::Never,
+        _ => Eta::Ms((lag as f64 / (consumption - production) * 1000.0).ceil() as u64),
+    };
+
+    PartitionCatchUp {
+        partition,
+        lag,
+        consumption_rate: consumption,
+        production_rate: production,
+        eta,
+        confidence: confidence(&intervals.iter().map(|(c, _)| *c).collect::<Vec<_>>()),
+    }
+}
+
+fn smooth(rates: impl Iterator<Item = f64>) -> f64 {
+    rates
+        .reduce(|smoothed, rate| SMOOTHING * rate + (1.0 - SMOOTHING) * smoothed)
+        .unwrap_or(0.0)
+}
+
+/// The confidence in rates that varied as much as these, by their coefficient of variation.
+fn confidence(rates: &[f64]) -> Confidence {
+    if rates.len() < MIN_INTERVALS {
+        return Confidence::Low;
+    }
+
+    let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+    if mean <= 0.0 {
+        return Confidence::Low;
+    }
+    let variance = rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / rates.len() as f64;
+    match variance.sqrt() / mean {
+        cv if cv < 0.2 => Confidence::High,
+        cv if cv < 0.5 => Confidence::Medium,
+        _ => Confidence::Low,
+    }
+}
+
+/// The ETA of the subscription, known once every partition's is.
+pub fn combine(partitions: &[PartitionCatchUp]) -> (Eta, Confidence) {
+    let etas = partitions.iter().map(|p| p.eta);
+    let eta = if etas.clone().any(|eta| eta == Eta::Never) {
+        Eta::Never
+    } else if etas.clone().any(|eta| eta == Eta::Unknown) {
+        Eta::Unknown
+    } else {
+        Eta::Ms(
+            etas.filter_map(|eta| match eta {
+                Eta::Ms(ms) => Some(ms),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0),
+        )
+    };
+
+    let confidence = partitions
+        .iter()
+        .map(|p| p.confidence)
+        .min()
+        .unwrap_or(Confidence::Low);
+    (eta, confidence)
+}
+
+/// Whether a subscription is caught up, flipping only once the lag stayed on
+/// the other side of the threshold for the sustained period.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CaughtUp {
+    caught_up: bool,
+
+    /// When the lag last crossed to the other side of the threshold.
+    crossed_at: Option<u64>,
+}
+
+impl CaughtUp {
+    pub fn is_caught_up(&self) -> bool {
+        self.caught_up
+    }
+
+    /// Record the lag at `at_ms`, returning whether the subscription is caught up.
+    pub fn update(&mut self, config: &CatchUpConfig, lag: Option<i64>, at_ms: u64) -> bool {
+        // An unknown lag is never within the threshold.
+        let within = lag.is_some_and(|lag| lag <= config.threshold);
+        if within == self.caught_up {
+            self.crossed_at = None;
+            return self.caught_up;
+        }
+
+        let crossed_at = *self.crossed_at.get_or_insert(at_ms);
+        if at_ms - crossed_at >= config.sustained.as_millis() as u64 {
+            self.caught_up = within;
+            self.crossed_at = None;
+        }
+        self.caught_up
+    }
+}
+
+/// Samples the positions and end offsets of a worker's partitions, and
+/// estimates when it catches up.
+#[derive(Debug)]
+pub struct CatchUp {
+    config: CatchUpConfig,
+    samples: BTreeMap<i32, Vec<Sample>>,
+
+    /// Whether a partition with messages hasn't delivered one yet, so its lag is unknown.
+    pending: bool,
+
+    caught_up: CaughtUp,
+}
+
+impl CatchUp {
+    pub fn new(config: CatchUpConfig) -> Self {
+        Self {
+            config,
+            samples: Default::default(),
+            pending: false,
+            caught_up: Default::default(),
+        }
+    }
+
+    /// Record the positions consumed so far and the end offsets fetched at `at_ms`.
+    pub fn record(&mut self, at_ms: u64, positions: &HashMap<i32, i64>, ends: &HashMap<i32, i64>) {
+        // Partitions no longer assigned are dropped with their samples.
+        self.samples.retain(|p, _| ends.contains_key(p));
+        self.pending = false;
+
+        for (&partition, &end) in ends {
+            let position = match positions.get(&partition) {
+                Some(&position) => position,
+                None if end <= 0 => 0,
+                None => {
+                    self.pending = true;
+                    continue;
+                }
+            };
+
+            let samples = self.samples.entry(partition).or_default();
+            if samples.len() == WINDOW {
+                samples.remove(0);
+            }
+            samples.push(Sample {
+                at_ms,
+                position,
+                end,
+            });
+        }
+
+        let lag = (!self.pending).then(|| self.lag());
+        self.caught_up.update(&self.config, lag, at_ms);
+    }
+
+    fn lag(&self) -> i64 {
+        self.samples
+            .values()
+            .filter_map(|s| s.last())
+            .map(Sample::lag)
+            .sum()
+    }
+
+    /// The catch-up estimated from the samples, `None` until the first was recorded.
+    pub fn report(&self) -> Option<CatchUpReport> {
+        if self.samples.is_empty() && !self.pending {
+            return None;
+        }
+
+        let partitions = self
+            .samples
+            .iter()
+            .map(|(&partition, samples)| estimate(partition, samples))
+            .collect::<Vec<_>>();
+        let (eta, confidence) = match self.pending {
+            true => (Eta::Unknown, Confidence::Low),
+            false => combine(&partitions),
+        };
+
+        Some(CatchUpReport {
+            lag: self.lag(),
+            eta,
+            confidence,
+            caught_up: self.caught_up.is_caught_up(),
+            partitions,
+        })
+    }
+}
+
+#[cfg(test)]
+fn samples(every_ms: u64, points: &[(i64, i64)]) -> Vec<Sample> {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &(position, end))| Sample {
+            at_ms: i as u64 * every_ms,
+            position,
+            end,
+        })
+        .collect()
+}
+
+#[test]
+fn it_estimates_a_steady_catch_up() {
+    // 100 messages consumed and 20 produced per second, 7,600 behind after 40 seconds.
+    let points = (0..5)
+        .map(|i| (i * 1_000, 10_800 + i * 200))
+        .collect::<Vec<_>>();
+    let estimate = estimate(0, &samples(10_000, &points));
+
+    assert_eq!(estimate.lag, 7_600);
+    assert!((estimate.consumption_rate - 100.0).abs() < 1e-9);
+    assert!((estimate.production_rate - 20.0).abs() < 1e-9);
+    assert_eq!(estimate.eta, Eta::Ms(95_000));
+    assert_eq!(estimate.confidence, Confidence::High);
+
+    // A single sample has no rates yet, a partition without lag is done.
+    let first = super::catchup::estimate(0, &samples(10_000, &points[..1]));
+    assert_eq!(
+        (first.eta, first.confidence),
+        (Eta::Unknown, Confidence::Low)
+    );
+    let done = super::catchup::estimate(0, &samples(10_000, &[(5, 5), (9, 9)]));
+    assert_eq!(done.eta, Eta::Ms(0));
+}
+
+#[test]
+fn it_smooths_a_production_spike() {
+    // Production jumps tenfold for one interval of an otherwise steady catch-up.
+    let produced = [0, 200, 400, 4_400, 4_600, 4_800];
+    let points = produced
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| (i as i64 * 1_000, 20_000 + p))
+        .collect::<Vec<_>>();
+    let spiked = estimate(0, &samples(10_000, &points));
+
+    // The spike slows the estimate down, but doesn't make it converge never.
+    let Eta::Ms(eta) = spiked.eta else {
+        panic!("expected an ETA, got {:?}", spiked.eta);
+    };
+    assert!(spiked.production_rate < 100.0, "{}", spiked.production_rate);
+    assert!(eta > 187_500, "{}", eta);
+    assert_eq!(spiked.confidence, Confidence::High);
+}
+
+#[test]
+fn it_never_converges_while_production_outpaces_consumption() {
+    let points = (0..4)
+        .map(|i| (i * 500, 1_000 + i * 800))
+        .collect::<Vec<_>>();
+    let estimate = estimate(3, &samples(10_000, &points));
+    assert_eq!(estimate.eta, Eta::Never);
+
+    // One partition that never converges holds back the whole subscription.
+    let converging = super::catchup::estimate(0, &samples(10_000, &[(0, 100), (100, 150)]));
+    assert_eq!(converging.eta, Eta::Ms(10_000));
+    assert_eq!(
+        combine(&[converging.clone(), estimate]),
+        (Eta::Never, Confidence::Low)
+    );
+    assert_eq!(combine(&[converging]), (Eta::Ms(10_000), Confidence::Low));
+}
+
+#[test]
+fn it_rates_the_confidence_by_how_much_consumption_varies() {
+    let steady = [
+        (0, 10_000),
+        (1_000, 10_000),
+        (2_000, 10_000),
+        (3_000, 10_000),
+    ];
+    let bursty = [
+        (0, 10_000),
+        (1_300, 10_000),
+        (1_900, 10_000),
+        (3_000, 10_000),
+    ];
+    let erratic = [
+        (0, 10_000),
+        (2_500, 10_000),
+        (2_600, 10_000),
+        (3_000, 10_000),
+    ];
+
+    assert_eq!(
+        estimate(0, &samples(1_000, &steady)).confidence,
+        Confidence::High
+    );
+    assert_eq!(
+        estimate(0, &samples(1_000, &bursty)).confidence,
+        Confidence::Medium
+    );
+    assert_eq!(
+        estimate(0, &samples(1_000, &erratic)).confidence,
+        Confidence::Low
+    );
+}
+
+#[test]
+fn it_flips_caught_up_only_after_the_sustained_period() {
+    let config = CatchUpConfig {
+        threshold: 100,
+        sustained: Duration::from_secs(60),
+    };
+    let mut caught_up = CaughtUp::default();
+
+    // Within the threshold, but not for long enough yet.
+    assert!(!caught_up.update(&config, Some(50), 0));
+    assert!(!caught_up.update(&config, Some(80), 30_000));
+    // A blip above the threshold restarts the period.
+    assert!(!caught_up.update(&config, Some(500), 40_000));
+    assert!(!caught_up.update(&config, Some(20), 50_000));
+    assert!(!caught_up.update(&config, Some(20), 100_000));
+    assert!(caught_up.update(&config, Some(20), 110_000));
+
+    // A short spike over the threshold doesn't flap the flag.
+    assert!(caught_up.update(&config, Some(900), 120_000));
+    assert!(caught_up.update(&config, Some(10), 130_000));
+    assert!(caught_up.update(&config, Some(900), 140_000));
+    assert!(caught_up.update(&config, None, 190_000));
+    assert!(!caught_up.update(&config, Some(900), 200_000));
+}
+
+#[test]
+fn it_tracks_the_catch_up_of_assigned_partitions() {
+    let mut catch_up = CatchUp::new(CatchUpConfig {
+        threshold: 10,
+        sustained: Duration::from_secs(20),
+    });
+    assert_eq!(catch_up.report(), None);
+
+    // Partition 1 has messages but none was consumed yet, so the lag isn't known.
+    let ends = HashMap::from([(0, 1_000), (1, 500), (2, 0)]);
+    catch_up.record(0, &HashMap::from([(0, 0)]), &ends);
+    let report = catch_up.report().unwrap();
+    assert_eq!((report.eta, report.lag), (Eta::Unknown, 1_000));
+
+    for (i, at_ms) in [10_000, 20_000, 30_000, 40_000].into_iter().enumerate() {
+        let consumed = (i as i64 + 1) * 250;
+        let positions = HashMap::from([(0, consumed.min(1_000)), (1, (consumed * 2).min(500))]);
+        catch_up.record(at_ms, &positions, &ends);
+    }
+    let report = catch_up.report().unwrap();
+    assert_eq!(report.lag, 0);
+    assert_eq!(report.eta, Eta::Ms(0));
+    assert_eq!(report.partitions.len(), 3);
+    assert!(!report.caught_up);
+
+    catch_up.record(60_000, &HashMap::from([(0, 1_000), (1, 500)]), &ends);
+    assert!(catch_up.report().unwrap().caught_up);
+
+    // Partitions revoked from the worker leave the report.
+    catch_up.record(
+        70_000,
+        &HashMap::from([(0, 1_000)]),
+        &HashMap::from([(0, 1_000)]),
+    );
+    assert_eq!(catch_up.report().unwrap().partitions.len(), 1);
+}
@@ -1,59 +1,186 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use rdkafka::consumer::stream_consumer::StreamConsumer;
 use rdkafka::consumer::{CommitMode, Consumer};
 use rdkafka::message::Headers;
-use rdkafka::{ClientConfig, Message};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
 
 use crate::clusters::cluster::Cluster;
 use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::kafka::auth::AuthContext;
+use crate::kafka::client::{build_client_config, cluster_group_id};
 use crate::kafka::config;
+use crate::kafka::sensitive::redact;
+use crate::logs::dedup;
 use crate::subscriptions::subscription::Subscription;
 
-use super::StreamsMessage;
+use super::{PayloadFormat, StreamsMessage};
 
 /// Timeout for fetching message.
 pub const POLL_TIMEOUT_MS: i32 = 5_000;
 
+/// Timeout for fetching partition watermarks.
+pub const FETCH_WATERMARKS_TIMEOUT_MS: Duration = Duration::from_millis(5_000);
+
 #[async_trait]
 pub trait StreamsConsumer {
     async fn consume(&self) -> Result<Option<StreamsMessage>, AnyError>;
+
+    /// Commit the offset after the message, once it was indexed.
+    async fn commit(&self, message: &StreamsMessage) -> Result<(), AnyError>;
+
+    /// Fetch the high watermark of every partition currently assigned to the consumer.
+    async fn fetch_end_offsets(&self) -> Result<HashMap<i32, i64>, AnyError>;
+
+    /// Fetch the number of partitions of the subscribed topic.
+    async fn topic_partitions(&self) -> Result<usize, AnyError>;
 }
 
 pub struct KafkaStreamsConsumer {
-    pub inner: Arc<StreamConsumer>,
+    pub inner: Arc<StreamConsumer<AuthContext>>,
+    topic: String,
+    subscription_id: SubscriptionId,
+    format: PayloadFormat,
 }
 
 impl KafkaStreamsConsumer {
     pub fn create(cluster: &Cluster, subscription: &Subscription) -> Result<Self, AnyError> {
-        debug!("cluster config: {:?}", cluster.config);
-
-        let bootstraps = cluster
-            .config
-            .get(config::BOOTSTRAP_SERVERS)
-            .unwrap_or(&String::from("localhost:9092"))
-            .to_owned();
-
-        let group_id = cluster
-            .config
-            .get(config::SEEKR_GROUP_ID)
-            .unwrap_or(&String::from("seekr.io"))
-            .to_owned();
-
-        let consumer = ClientConfig::new()
-            .set("bootstrap.servers", &bootstraps)
-            .set("group.id", &group_id)
-            .set("api.version.request", "true")
-            .create::<StreamConsumer>()?;
+        let client = build_client_config(cluster, Some(subscription))?;
+        let consumer = Self::connect(cluster, subscription, client)?;
+        if let Some(at) = start_at(&subscription.config)? {
+            consumer.start_at(at)?;
+        }
+        Ok(consumer)
+    }
+
+    /// A consumer of the messages produced from now on, in a group of its
+    /// own so it takes no partitions from the subscription's workers.
+    ///
+    /// The group is named after the cluster's, so ACLs on that prefix cover it.
+    pub fn create_live(cluster: &Cluster, subscription: &Subscription) -> Result<Self, AnyError> {
+        let group_id = format!(
+            "{}.live.{}",
+            cluster_group_id(cluster),
+            uuid::Uuid::new_v4().simple()
+        );
+        let mut client = build_client_config(cluster, None)?;
+        client.set("group.id", group_id);
+        Self::connect(cluster, subscription, client)
+    }
+
+    fn connect(
+        cluster: &Cluster,
+        subscription: &Subscription,
+        mut client: ClientConfig,
+    ) -> Result<Self, AnyError> {
+        debug!("cluster config: {:?}", redact(&cluster.config));
+
+        let auth = AuthContext::of(cluster)?;
+        auth.configure(&mut client);
+        let consumer = client.create_with_context::<_, StreamConsumer<_>>(auth)?;
 
         consumer.subscribe(&[&subscription.topic_name])?;
+        let format = PayloadFormat::of(&subscription.config).unwrap_or_else(|e| {
+            warn!(
+                "Indexing the payloads of subscription {} as JSON: {}",
+                subscription.id, e
+            );
+            PayloadFormat::default()
+        });
 
         Ok(Self {
             inner: Arc::new(consumer),
+            topic: subscription.topic_name.clone(),
+            subscription_id: subscription.id,
+            format,
         })
     }
+
+    /// Commit the offsets of the first messages produced at or after `at` for
+    /// the partitions the group has no offset of yet, so they start there
+    /// rather than per `auto.offset.reset`. Partitions without such messages
+    /// are left to `auto.offset.reset`.
+    fn start_at(&self, at: DateTime<Utc>) -> Result<(), AnyError> {
+        let metadata = self
+            .inner
+            .fetch_metadata(Some(&self.topic), FETCH_WATERMARKS_TIMEOUT_MS)?;
+        let mut partitions = TopicPartitionList::new();
+        for partition in metadata.topics().iter().flat_map(|t| t.partitions()) {
+            partitions.add_partition(&self.topic, partition.id());
+        }
+
+        let committed = self
+            .inner
+            .committed_offsets(partitions, FETCH_WATERMARKS_TIMEOUT_MS)?;
+        let mut times = TopicPartitionList::new();
+        for tp in committed.elements() {
+            if tp.offset() == Offset::Invalid {
+                let time = Offset::Offset(at.timestamp_millis());
+                times.add_partition_offset(&self.topic, tp.partition(), time)?;
+            }
+        }
+        if times.count() == 0 {
+            return Ok(());
+        }
+
+        let offsets = self
+            .inner
+            .offsets_for_times(times, FETCH_WATERMARKS_TIMEOUT_MS)?;
+        let mut start = TopicPartitionList::new();
+        for tp in offsets.elements() {
+            if let Offset::Offset(offset) = tp.offset() {
+                start.add_partition_offset(&self.topic, tp.partition(), Offset::Offset(offset))?;
+            }
+        }
+        if start.count() > 0 {
+            info!(
+                "Starting {} partitions of subscription {} at {}",
+                start.count(),
+                self.subscription_id,
+                at
+            );
+            self.inner.commit(&start, CommitMode::Sync)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `auto.offset.reset` of a subscription, `earliest` or `latest`.
+pub fn offset_reset(config: &HashMap<String, String>) -> Result<Option<&str>, String> {
+    match config.get(config::AUTO_OFFSET_RESET).map(String::as_str) {
+        None => Ok(None),
+        Some(reset @ ("earliest" | "latest")) => Ok(Some(reset)),
+        Some(reset) => Err(format!(
+            "unknown offset reset '{}', expected earliest or latest",
+            reset
+        )),
+    }
+}
+
+/// The `start.at.timestamp` of a subscription, an RFC 3339 time or
+/// milliseconds since the epoch.
+pub fn start_at(config: &HashMap<String, String>) -> Result<Option<DateTime<Utc>>, String> {
+    let Some(value) = config.get(config::START_AT_TIMESTAMP) else {
+        return Ok(None);
+    };
+    let at = match value.parse::<i64>() {
+        Ok(ms) => DateTime::from_timestamp_millis(ms),
+        Err(_) => DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|at| at.with_timezone(&Utc)),
+    };
+    match at {
+        Some(at) if at.timestamp_millis() >= 0 => Ok(Some(at)),
+        _ => Err(format!(
+            "'{}' is neither an RFC 3339 time nor milliseconds since the epoch",
+            value
+        )),
+    }
 }
 
 #[async_trait]
@@ -61,38 +188,253 @@ impl StreamsConsumer for KafkaStreamsConsumer {
     async fn consume(&self) -> Result<Option<StreamsMessage>, AnyError> {
         match self.inner.recv().await {
             Err(e) => {
-                warn!("Kafka error: {}", e);
+                log_dedup!(
+                    dedup::key(dedup::STREAMS_CONSUME, self.subscription_id),
+                    log::Level::Warn,
+                    "Kafka error: {}",
+                    e
+                );
                 Err(e.into())
             }
             Ok(m) => {
-                let headers: HashMap<_, _> = m
-                    .headers()
-                    .unwrap()
-                    .iter()
-                    .map(|h| {
-                        (
-                            h.key.to_string(),
-                            String::from_utf8(h.value.unwrap_or(b"").to_vec()).unwrap(),
-                        )
-                    })
-                    .collect();
-
-                let payload = match m.payload_view::<str>() {
-                    None => None,
-                    Some(Ok(s)) => Some(s.to_string()),
-                    Some(Err(e)) => {
-                        warn!("Error while deserializing message payload: {:?}", e);
-                        None
-                    }
-                };
-
-                debug!("key: '{:?}', payload: '{:?}', topic: {}, partition: {}, offset: {}, timestamp: {:?}",
-					  m.key(), payload, m.topic(), m.partition(), m.offset(), m.timestamp());
-
-                self.inner.commit_message(&m, CommitMode::Async).unwrap();
-
-                Ok(Some(StreamsMessage { payload, headers }))
+                clear_dedup!(dedup::key(dedup::STREAMS_CONSUME, self.subscription_id));
+                let message = streams_message(&m).with_format(self.format);
+                debug!(
+                    "key: '{:?}', payload: '{:?}', topic: {}, partition: {}, offset: {}, timestamp: {:?}",
+                    m.key(),
+                    message.payload,
+                    m.topic(),
+                    m.partition(),
+                    m.offset(),
+                    m.timestamp()
+                );
+
+                Ok(Some(message))
             }
         }
     }
+
+    async fn commit(&self, message: &StreamsMessage) -> Result<(), AnyError> {
+        fail_point!(crate::failpoints::OFFSET_COMMIT, self.subscription_id)?;
+
+        let mut offsets = TopicPartitionList::new();
+        offsets.add_partition_offset(
+            &self.topic,
+            message.partition,
+            Offset::Offset(message.offset + 1),
+        )?;
+        self.inner.commit(&offsets, CommitMode::Async)?;
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<HashMap<i32, i64>, AnyError> {
+        let inner = self.inner.clone();
+
+        // Watermark queries are blocking broker round trips.
+        tokio::task::spawn_blocking(move || {
+            let assignment = inner.assignment()?;
+            let mut offsets = HashMap::new();
+
+            for tp in assignment.elements() {
+                let (_, high) = inner.fetch_watermarks(
+                    tp.topic(),
+                    tp.partition(),
+                    FETCH_WATERMARKS_TIMEOUT_MS,
+                )?;
+                offsets.insert(tp.partition(), high);
+            }
+
+            Ok(offsets)
+        })
+        .await?
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        let (inner, topic) = (self.inner.clone(), self.topic.clone());
+
+        tokio::task::spawn_blocking(move || {
+            let metadata = inner.fetch_metadata(Some(&topic), FETCH_WATERMARKS_TIMEOUT_MS)?;
+            Ok(metadata
+                .topics()
+                .first()
+                .map_or(0, |t| t.partitions().len()))
+        })
+        .await?
+    }
+}
+
+/// Convert a consumed Kafka message, decoding its payload and headers as UTF-8,
+/// without parsing the payload.
+pub fn streams_message(m: &impl Message) -> StreamsMessage {
+    let headers: HashMap<_, _> = m
+        .headers()
+        .map(|headers| {
+            headers
+                .iter()
+                .map(|h| {
+                    (
+                        h.key.to_string(),
+                        String::from_utf8_lossy(h.value.unwrap_or(b"")).into_owned(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let payload = match m.payload_view::<str>() {
+        None => None,
+        Some(Ok(s)) => Some(s.to_string()),
+        Some(Err(e)) => {
+            warn!("Error while deserializing message payload: {:?}", e);
+            None
+        }
+    };
+
+    StreamsMessage {
+        payload,
+        payload_json: None,
+        topic: m.topic().to_string(),
+        key: m.key().map(|k| String::from_utf8_lossy(k).into_owned()),
+        headers,
+        partition: m.partition(),
+        offset: m.offset(),
+        timestamp: m.timestamp().to_millis(),
+    }
+}
+
+#[cfg(test)]
+fn owned(
+    payload: Option<&[u8]>,
+    headers: Option<rdkafka::message::OwnedHeaders>,
+) -> rdkafka::message::OwnedMessage {
+    rdkafka::message::OwnedMessage::new(
+        payload.map(|p| p.to_vec()),
+        None,
+        "orders".to_string(),
+        rdkafka::message::Timestamp::CreateTime(1_700_000_000_000),
+        2,
+        42,
+        headers,
+    )
+}
+
+#[test]
+fn it_converts_messages_without_headers() {
+    let message = streams_message(&owned(Some(&br#"{"id": 1}"#[..]), None));
+
+    assert!(message.headers.is_empty());
+    assert_eq!(message.payload.as_deref(), Some(r#"{"id": 1}"#));
+    assert_eq!((message.partition, message.offset), (2, 42));
+    assert_eq!(message.timestamp, Some(1_700_000_000_000));
+    assert_eq!(message.topic, "orders");
+}
+
+#[test]
+fn it_decodes_binary_header_values_lossily() {
+    use rdkafka::message::{Header, OwnedHeaders};
+
+    let headers = OwnedHeaders::new()
+        .insert(Header {
+            key: "trace",
+            value: Some("abc"),
+        })
+        .insert(Header {
+            key: "digest",
+            value: Some(&[0xff, 0xfe][..]),
+        })
+        .insert::<[u8]>(Header {
+            key: "empty",
+            value: None,
+        });
+    let message = streams_message(&owned(Some(&b"{}"[..]), Some(headers)));
+
+    assert_eq!(message.headers["trace"], "abc");
+    assert_eq!(message.headers["digest"], "\u{FFFD}\u{FFFD}");
+    assert_eq!(message.headers["empty"], "");
+}
+
+#[test]
+fn it_converts_empty_and_missing_payloads() {
+    assert_eq!(
+        streams_message(&owned(Some(&b""[..]), None))
+            .payload
+            .as_deref(),
+        Some("")
+    );
+    assert_eq!(streams_message(&owned(None, None)).payload, None);
+    assert_eq!(
+        streams_message(&owned(Some(&[0xff, 0xfe][..]), None)).payload,
+        None
+    );
+}
+
+#[test]
+fn it_configures_consumers_per_subscription() {
+    use crate::clusters::cluster::Kind;
+    use crate::ids::ClusterId;
+
+    let cluster = |group: Option<&str>| {
+        let mut config = HashMap::from([(
+            config::BOOTSTRAP_SERVERS.to_string(),
+            "kafka:9092".to_string(),
+        )]);
+        if let Some(group) = group {
+            config.insert(config::SEEKR_GROUP_ID.to_string(), group.to_string());
+        }
+        Cluster::new(Some(ClusterId(1)), Kind::Kafka, "c".to_string(), config)
+    };
+    let subscription = |config: &[(&str, &str)]| {
+        let config = config
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Subscription::new(
+            Some(SubscriptionId(7)),
+            ClusterId(1),
+            "orders".to_string(),
+            config,
+        )
+    };
+
+    let client = build_client_config(&cluster(None), Some(&subscription(&[]))).unwrap();
+    assert_eq!(client.get("bootstrap.servers"), Some("kafka:9092"));
+    assert_eq!(client.get("group.id"), Some("seekr.io.7"));
+    assert_eq!(client.get("enable.auto.commit"), Some("false"));
+    assert_eq!(client.get(config::AUTO_OFFSET_RESET), None);
+
+    let client =
+        build_client_config(&cluster(Some("seekr.prod")), Some(&subscription(&[]))).unwrap();
+    assert_eq!(client.get("group.id"), Some("seekr.prod.7"));
+
+    let overrides = subscription(&[
+        (config::SEEKR_GROUP_ID, "orders-indexer"),
+        (config::AUTO_OFFSET_RESET, "earliest"),
+        (config::START_AT_TIMESTAMP, "2024-01-01T00:00:00Z"),
+    ]);
+    let client = build_client_config(&cluster(Some("seekr.prod")), Some(&overrides)).unwrap();
+    assert_eq!(client.get("group.id"), Some("orders-indexer"));
+    assert_eq!(client.get(config::AUTO_OFFSET_RESET), Some("earliest"));
+    // Seeking isn't a client setting, it happens once connected.
+    assert_eq!(client.get(config::START_AT_TIMESTAMP), None);
+
+    let invalid = subscription(&[(config::AUTO_OFFSET_RESET, "smallest")]);
+    assert!(build_client_config(&cluster(None), Some(&invalid)).is_err());
+}
+
+#[test]
+fn it_parses_start_timestamps() {
+    let start_at = |value: &str| {
+        start_at(&HashMap::from([(
+            config::START_AT_TIMESTAMP.to_string(),
+            value.to_string(),
+        )]))
+    };
+
+    let at = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    assert_eq!(start_at("1700000000000"), Ok(Some(at)));
+    assert_eq!(start_at("2023-11-14T22:13:20Z"), Ok(Some(at)));
+    assert_eq!(start_at("2023-11-14T23:13:20+01:00"), Ok(Some(at)));
+    assert!(start_at("-1").is_err());
+    assert!(start_at("yesterday").is_err());
+    assert_eq!(self::start_at(&HashMap::new()), Ok(None));
 }
@@ -0,0 +1,460 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kafka::config;
+use crate::subscriptions::subscription::Subscription;
+
+/// The event-time lag a subscription's source is considered stale beyond by default.
+pub const DEFAULT_MAX_LAG: Duration = Duration::from_secs(15 * 60);
+
+/// Share of the threshold the event-time lag must fall back under for a stale source to clear.
+const RECOVERY: f64 = 0.5;
+
+/// Freshness settings resolved from the subscription config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FreshnessConfig {
+    pub max_lag: Duration,
+
+    /// Whether a partition nothing is produced to goes stale too.
+    pub idle_is_stale: bool,
+}
+
+impl Default for FreshnessConfig {
+    fn default() -> Self {
+        Self {
+            max_lag: DEFAULT_MAX_LAG,
+            idle_is_stale: false,
+        }
+    }
+}
+
+impl FreshnessConfig {
+    /// The settings of `freshness.max.lag.ms` and `freshness.idle.stale`.
+    pub fn from(subscription: &Subscription) -> Self {
+        let get = |key: &str| subscription.config.get(key);
+
+        Self {
+            max_lag: get(config::FRESHNESS_MAX_LAG)
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_MAX_LAG),
+            idle_is_stale: get(config::FRESHNESS_IDLE_STALE)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// What a partition's producer and consumer did between two samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceState {
+    /// The partition was only sampled once.
+    Unknown,
+
+    /// Newer events were consumed.
+    Flowing,
+
+    /// Messages keep being produced and consumed, but none with a newer event time.
+    Stalled,
+
+    /// Nothing was produced, and everything produced before was consumed.
+    Idle,
+
+    /// Messages wait to be consumed, the event-time lag is the consumer's.
+    Behind,
+}
+
+/// The offsets and event-time watermark of a partition at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Observation {
+    /// Epoch milliseconds.
+    pub at_ms: i64,
+
+    /// The offset of the next message to consume.
+    pub position: i64,
+
+    /// The offset the next produced message gets.
+    pub end: i64,
+
+    /// The latest event time consumed, `None` until a message with a timestamp was.
+    pub watermark: Option<i64>,
+}
+
+impl Observation {
+    /// Milliseconds since the latest event time consumed.
+    pub fn event_lag_ms(&self) -> Option<i64> {
+        self.watermark.map(|ts| (self.at_ms - ts).max(0))
+    }
+}
+
+/// What the source of a partition did between two observations.
+pub fn source(previous: &Observation, current: &Observation) -> SourceState {
+    let produced = current.end > previous.end;
+    let consumed = current.position > previous.position;
+    let advanced = current.watermark > previous.watermark;
+
+    if !produced && current.position >= current.end {
+        SourceState::Idle
+    } else if produced && consumed && !advanced {
+        SourceState::Stalled
+    } else if advanced {
+        SourceState::Flowing
+    } else {
+        SourceState::Behind
+    }
+}
+
+/// Whether the source of a partition is stale after an observation.
+///
+/// A source goes stale once its event-time lag is over `max_lag` while it
+/// stalls, or idles with `idle_is_stale`, and stays stale until the lag is
+/// back under half of `max_lag`, so a lag hovering at the threshold doesn't
+/// flap the flag.
+pub fn is_stale(
+    config: &FreshnessConfig,
+    stale: bool,
+    source: SourceState,
+    event_lag_ms: Option<i64>,
+) -> bool {
+    let Some(lag) = event_lag_ms else {
+        return stale;
+    };
+    let max_lag = config.max_lag.as_millis() as i64;
+
+    match stale {
+        true => lag > (max_lag as f64 * RECOVERY) as i64,
+        false => {
+            lag > max_lag
+                && (source == SourceState::Stalled
+                    || (config.idle_is_stale && source == SourceState::Idle))
+        }
+    }
+}
+
+/// The event-time freshness of one partition.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionFreshness {
+    pub partition: i32,
+
+    /// The latest event time consumed, in epoch milliseconds.
+    pub watermark: Option<i64>,
+
+    /// Milliseconds between the latest event time consumed and the last sample.
+    pub event_lag_ms: Option<i64>,
+
+    pub source: SourceState,
+    pub stale: bool,
+}
+
+/// The event-time freshness of a subscription, over all its partitions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FreshnessReport {
+    /// Milliseconds between the latest event time consumed from any partition and the last sample.
+    pub event_lag_ms: Option<i64>,
+
+    /// Whether a partition's producer has stopped sending newer events.
+    pub stale_source: bool,
+
+    pub partitions: Vec<PartitionFreshness>,
+}
+
+#[derive(Debug)]
+struct Tracked {
+    last: Observation,
+    source: SourceState,
+    stale: bool,
+}
+
+/// Tracks the latest event time consumed from a worker's partitions, and
+/// whether their producers stalled.
+#[derive(Debug)]
+pub struct Freshness {
+    config: FreshnessConfig,
+    watermarks: HashMap<i32, i64>,
+    partitions: BTreeMap<i32, Tracked>,
+}
+
+impl Freshness {
+    pub fn new(config: FreshnessConfig) -> Self {
+        Self {
+            config,
+            watermarks: Default::default(),
+            partitions: Default::default(),
+        }
+    }
+
+    /// Record the event time of a consumed message. Late events older than the
+    /// watermark leave it where it is.
+    pub fn observe(&mut self, partition: i32, event_ts: Option<i64>) {
+        let Some(ts) = event_ts else {
+            return;
+        };
+        let watermark = self.watermarks.entry(partition).or_insert(ts);
+        *watermark = (*watermark).max(ts);
+    }
+
+    /// Record the positions consumed so far and the end offsets fetched at
+    /// `at_ms`, returning whether the source is stale when that changed.
+    pub fn record(
+        &mut self,
+        at_ms: i64,
+        positions: &HashMap<i32, i64>,
+        ends: &HashMap<i32, i64>,
+    ) -> Option<bool> {
+        let was_stale = self.is_stale();
+        self.partitions.retain(|p, _| ends.contains_key(p));
+
+        for (&partition, &end) in ends {
+            // A partition with messages but none consumed yet has nothing to compare.
+            let position = match positions.get(&partition) {
+                Some(&position) => position,
+                None if end <= 0 => 0,
+                None => continue,
+            };
+
+            let current = Observation {
+                at_ms,
+                position,
+                end,
+                watermark: self.watermarks.get(&partition).copied(),
+            };
+            match self.partitions.get_mut(&partition) {
+                Some(tracked) => {
+                    tracked.source = source(&tracked.last, &current);
+                    tracked.stale = is_stale(
+                        &self.config,
+                        tracked.stale,
+                        tracked.source,
+                        current.event_lag_ms(),
+                    );
+                    tracked.last = current;
+                }
+                None => {
+                    let tracked = Tracked {
+                        last: current,
+                        source: SourceState::Unknown,
+                        stale: false,
+                    };
+                    self.partitions.insert(partition, tracked);
+                }
+            }
+        }
+
+        let stale = self.is_stale();
+        (stale != was_stale).then_some(stale)
+    }
+
+    fn is_stale(&self) -> bool {
+        self.partitions.values().any(|t| t.stale)
+    }
+
+    /// The freshness as of the last sample, `None` until the first was recorded.
+    pub fn report(&self) -> Option<FreshnessReport> {
+        let at_ms = self.partitions.values().map(|t| t.last.at_ms).max()?;
+        let latest = self
+            .partitions
+            .values()
+            .filter_map(|t| t.last.watermark)
+            .max();
+
+        Some(FreshnessReport {
+            event_lag_ms: latest.map(|ts| (at_ms - ts).max(0)),
+            stale_source: self.is_stale(),
+            partitions: self
+                .partitions
+                .iter()
+                .map(|(&partition, t)| PartitionFreshness {
+                    partition,
+                    watermark: t.last.watermark,
+                    event_lag_ms: t.last.event_lag_ms(),
+                    source: t.source,
+                    stale: t.stale,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+fn observations(every_ms: i64, points: &[(i64, i64, Option<i64>)]) -> Vec<Observation> {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &(position, end, watermark))| Observation {
+            at_ms: 1_000_000 + i as i64 * every_ms,
+            position,
+            end,
+            watermark,
+        })
+        .collect()
+}
+
+/// The stale flag of a partition over consecutive observations.
+#[cfg(test)]
+fn stale_flags(config: &FreshnessConfig, observations: &[Observation]) -> Vec<bool> {
+    let mut stale = false;
+    observations
+        .windows(2)
+        .map(|w| {
+            stale = is_stale(config, stale, source(&w[0], &w[1]), w[1].event_lag_ms());
+            stale
+        })
+        .collect()
+}
+
+#[cfg(test)]
+const MINUTE: i64 = 60_000;
+
+#[test]
+fn it_flags_a_producer_that_stopped_sending_newer_events() {
+    let config = FreshnessConfig {
+        max_lag: Duration::from_secs(120),
+        idle_is_stale: false,
+    };
+
+    // Messages keep arriving and being consumed, but all carry the event time
+    // the producer stalled at, one minute in.
+    let stalled = Some(1_000_000 + MINUTE);
+    let points = (0..6)
+        .map(|i| {
+            (
+                i * 10 + 10,
+                i * 10 + 10,
+                stalled.min(Some(1_000_000 + i * MINUTE)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let stalling = observations(MINUTE, &points);
+
+    assert_eq!(source(&stalling[0], &stalling[1]), SourceState::Flowing);
+    assert_eq!(source(&stalling[2], &stalling[3]), SourceState::Stalled);
+    assert_eq!(
+        stale_flags(&config, &stalling),
+        [false, false, false, true, true]
+    );
+
+    // A consumer that falls behind lags in event time too, but that's not the producer's.
+    let behind = observations(
+        MINUTE,
+        &[
+            (10, 10, Some(1_000_000)),
+            (10, 50, Some(1_000_000)),
+            (10, 90, Some(1_000_000)),
+        ],
+    );
+    assert_eq!(source(&behind[1], &behind[2]), SourceState::Behind);
+    assert_eq!(stale_flags(&config, &behind), [false, false]);
+}
+
+#[test]
+fn it_leaves_idle_topics_fresh_unless_idleness_is_staleness() {
+    let mut config = FreshnessConfig {
+        max_lag: Duration::from_secs(120),
+        idle_is_stale: false,
+    };
+
+    // Everything was consumed and nothing more is produced for five minutes.
+    let idle = observations(MINUTE, &[(42, 42, Some(1_000_000)); 6]);
+    assert_eq!(source(&idle[0], &idle[1]), SourceState::Idle);
+    assert_eq!(idle[5].event_lag_ms(), Some(5 * MINUTE));
+    assert_eq!(stale_flags(&config, &idle), [false; 5]);
+
+    config.idle_is_stale = true;
+    assert_eq!(
+        stale_flags(&config, &idle),
+        [false, false, true, true, true]
+    );
+}
+
+#[test]
+fn it_keeps_the_watermark_on_late_events() {
+    let mut freshness = Freshness::new(FreshnessConfig::default());
+    assert_eq!(freshness.report(), None);
+
+    freshness.observe(0, Some(5_000));
+    freshness.observe(0, Some(2_000));
+    freshness.observe(0, None);
+    freshness.observe(1, Some(1_000));
+
+    let positions = HashMap::from([(0, 3), (1, 1)]);
+    let ends = HashMap::from([(0, 3), (1, 1), (2, 7)]);
+    assert_eq!(freshness.record(9_000, &positions, &ends), None);
+
+    let report = freshness.report().unwrap();
+    assert_eq!(report.event_lag_ms, Some(4_000));
+    assert!(!report.stale_source);
+
+    // Partition 2 has messages but delivered none yet.
+    assert_eq!(report.partitions.len(), 2);
+    assert_eq!(report.partitions[0].watermark, Some(5_000));
+    assert_eq!(report.partitions[0].source, SourceState::Unknown);
+    assert_eq!(report.partitions[1].event_lag_ms, Some(8_000));
+
+    // A late event arriving with the next messages doesn't count as newer.
+    freshness.observe(0, Some(4_000));
+    let positions = HashMap::from([(0, 4), (1, 1)]);
+    let ends = HashMap::from([(0, 4), (1, 1)]);
+    assert_eq!(freshness.record(10_000, &positions, &ends), None);
+    let report = freshness.report().unwrap();
+    assert_eq!(report.partitions[0].watermark, Some(5_000));
+    assert_eq!(report.partitions[0].source, SourceState::Stalled);
+    assert_eq!(report.partitions[1].source, SourceState::Idle);
+}
+
+#[test]
+fn it_clears_a_stale_source_only_once_the_lag_recovered() {
+    let config = FreshnessConfig {
+        max_lag: Duration::from_secs(100),
+        idle_is_stale: false,
+    };
+
+    // Under the threshold while stalled, then over it.
+    assert!(!is_stale(
+        &config,
+        false,
+        SourceState::Stalled,
+        Some(90_000)
+    ));
+    assert!(is_stale(
+        &config,
+        false,
+        SourceState::Stalled,
+        Some(110_000)
+    ));
+
+    // Dipping just under the threshold doesn't clear it, half of it does.
+    assert!(is_stale(&config, true, SourceState::Flowing, Some(90_000)));
+    assert!(is_stale(&config, true, SourceState::Flowing, Some(60_000)));
+    assert!(!is_stale(&config, true, SourceState::Flowing, Some(40_000)));
+
+    // Flowing or idle sources don't go stale, whatever their lag.
+    assert!(!is_stale(
+        &config,
+        false,
+        SourceState::Flowing,
+        Some(500_000)
+    ));
+    assert!(!is_stale(&config, false, SourceState::Idle, Some(500_000)));
+    assert!(!is_stale(
+        &config,
+        false,
+        SourceState::Behind,
+        Some(500_000)
+    ));
+
+    // The tracker reports the change once, when the flag flips.
+    let mut freshness = Freshness::new(config);
+    let ends = |end: i64| HashMap::from([(0, end)]);
+    freshness.observe(0, Some(0));
+    assert_eq!(freshness.record(10_000, &ends(1), &ends(1)), None);
+    assert_eq!(freshness.record(120_000, &ends(2), &ends(2)), Some(true));
+    assert_eq!(freshness.record(130_000, &ends(3), &ends(3)), None);
+    freshness.observe(0, Some(80_000));
+    assert_eq!(freshness.record(140_000, &ends(4), &ends(4)), None);
+    freshness.observe(0, Some(130_000));
+    assert_eq!(freshness.record(150_000, &ends(5), &ends(5)), Some(false));
+    assert!(!freshness.report().unwrap().stale_source);
+}
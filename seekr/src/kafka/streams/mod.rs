@@ -1,12 +1,132 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
+use crate::kafka::config;
+
+pub mod catchup;
 pub mod consumer;
+pub mod freshness;
 pub mod service;
+pub mod stages;
+pub mod watchdog;
 
 #[derive(PartialEq, Serialize, Deserialize, Debug, Clone)]
 pub struct StreamsMessage {
     pub payload: Option<String>,
+    /// The payload parsed as JSON, when the subscription's `payload.format`
+    /// is `json` and it parses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_json: Option<Value>,
+    #[serde(default)]
+    pub topic: String,
+    /// The message key, decoded as UTF-8.
+    #[serde(default)]
+    pub key: Option<String>,
     pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub partition: i32,
+    #[serde(default)]
+    pub offset: i64,
+    /// Point in time in UTC Epoch milliseconds, when the message was produced.
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+}
+
+impl StreamsMessage {
+    /// Parse the payload as JSON under `PayloadFormat::Json`, keeping it as
+    /// text with a warning when it isn't.
+    pub fn with_format(mut self, format: PayloadFormat) -> Self {
+        self.payload_json = match (format, &self.payload) {
+            (PayloadFormat::Json, Some(payload)) => match serde_json::from_str(payload) {
+                Ok(json) => Some(json),
+                Err(e) => {
+                    warn!(
+                        "Indexing message {}-{} of topic {} as text, its payload isn't JSON: {}",
+                        self.partition, self.offset, self.topic, e
+                    );
+                    None
+                }
+            },
+            _ => None,
+        };
+        self
+    }
+}
+
+/// How the payloads of a subscription are indexed, set by `payload.format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PayloadFormat {
+    /// Payloads are parsed as JSON, those that aren't are indexed as text.
+    #[default]
+    Json,
+
+    /// Payloads are indexed as text.
+    Text,
+}
+
+impl PayloadFormat {
+    /// The format configured by `payload.format`, JSON when unset.
+    pub fn of(config: &HashMap<String, String>) -> Result<Self, String> {
+        config
+            .get(config::PAYLOAD_FORMAT)
+            .map_or(Ok(PayloadFormat::default()), |v| v.parse())
+    }
+}
+
+impl FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(PayloadFormat::Json),
+            "text" => Ok(PayloadFormat::Text),
+            _ => Err(format!(
+                "unknown payload format '{}', expected json or text",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn message(payload: Option<&str>) -> StreamsMessage {
+    StreamsMessage {
+        payload: payload.map(|p| p.to_string()),
+        payload_json: None,
+        topic: "orders".to_string(),
+        key: None,
+        headers: HashMap::new(),
+        partition: 2,
+        offset: 41,
+        timestamp: Some(1_700_000_000_000),
+    }
+}
+
+#[test]
+fn it_parses_json_payloads_and_keeps_the_rest_as_text() {
+    let json = message(Some(r#"{"order":7}"#)).with_format(PayloadFormat::Json);
+    assert_eq!(json.payload_json, Some(serde_json::json!({"order": 7})));
+
+    let text = message(Some("not json")).with_format(PayloadFormat::Json);
+    assert_eq!(text.payload_json, None);
+    assert_eq!(text.payload.as_deref(), Some("not json"));
+
+    let text = message(Some(r#"{"order":7}"#)).with_format(PayloadFormat::Text);
+    assert_eq!(text.payload_json, None);
+
+    assert_eq!(PayloadFormat::of(&HashMap::new()), Ok(PayloadFormat::Json));
+    assert!("xml".parse::<PayloadFormat>().is_err());
+}
+
+#[test]
+fn it_reads_messages_serialized_before_the_json_payload() {
+    let json = r#"{"payload":"{}","headers":{},"partition":1,"offset":2}"#;
+    let message: StreamsMessage = serde_json::from_str(json).unwrap();
+    assert_eq!((message.topic.as_str(), message.payload_json), ("", None));
+
+    let serialized = serde_json::to_value(&message).unwrap();
+    assert!(serialized.get("payload_json").is_none());
 }
@@ -1,55 +1,1637 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use std::{collections::HashMap, sync::Arc};
 
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
-use tokio::time::sleep;
+use tokio::time::{interval, Instant, MissedTickBehavior};
 
+use crate::changefeed::record::ChangeRecord;
+use crate::changefeed::store::ChangefeedStore;
 use crate::clusters::cluster::Cluster;
+use crate::commands;
+use crate::commands::command::{Command, CommandKind};
+use crate::commands::processor::{CommandHandler, CommandProcessor};
+use crate::commands::store::CommandStore;
+use crate::debug::store::DebugStore;
+use crate::debug::trace::{Outcome, Stage, TraceEvent, Tracer};
+use crate::debug::{self, DebugControl, DebugSession};
 use crate::errors::AnyError;
+use crate::kafka::config;
+use crate::logs::dedup;
+use crate::metrics::{self, Registry};
+use crate::shards::router::{ShardRouter, Write};
+use crate::shards::store::DocumentStore;
+use crate::shards::tombstone::Tombstones;
 use crate::shutdown::Shutdown;
 use crate::subscriptions::subscription::Subscription;
 
-use super::consumer::StreamsConsumer;
+use super::catchup::{self, CatchUp, CatchUpConfig, CatchUpReport};
+use super::consumer::{KafkaStreamsConsumer, StreamsConsumer};
+use super::freshness::{Freshness, FreshnessConfig, FreshnessReport};
+use super::stages::{BottleneckChange, Budgets, StageReport, StageTimings};
+use super::watchdog::{Liveness, Watchdog};
+use super::StreamsMessage;
 
-#[derive(Clone)]
-pub struct StreamsContext {
-    consumer: Arc<dyn StreamsConsumer + Send + Sync>,
-    sd: Arc<Shutdown>,
+/// How often expired changefeed records are truncated.
+const CHANGEFEED_RETENTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often debug sessions are read and the trace is published.
+const DEBUG_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the document counts of the shard manifest are refreshed.
+const MANIFEST_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often queued commands are applied.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times a write the index rejected is retried before its message is skipped.
+const SINK_RETRIES: u32 = 3;
+
+/// How long the first retry of a rejected write waits, doubling for each retry after.
+const SINK_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Builds the consumer a `StreamsService` reads from.
+pub type ConsumerFactory = Arc<
+    dyn Fn(&Cluster, &Subscription) -> Result<Arc<dyn StreamsConsumer + Send + Sync>, AnyError>
+        + Send
+        + Sync,
+>;
+
+/// Builds the Kafka consumers workers read from outside of tests.
+pub fn kafka_consumers() -> ConsumerFactory {
+    Arc::new(|c, s| {
+        let consumer = KafkaStreamsConsumer::create(c, s)?;
+        Ok(Arc::new(consumer))
+    })
+}
+
+/// Builds the Kafka consumers live streams read from, each in a group of its own.
+pub fn live_consumers() -> ConsumerFactory {
+    Arc::new(|c, s| {
+        let consumer = KafkaStreamsConsumer::create_live(c, s)?;
+        Ok(Arc::new(consumer))
+    })
 }
 
-struct State {
-    context: HashMap<i64, StreamsContext>,
+/// The state of a worker, exchanged as its variant name.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub enum WorkerState {
+    Starting,
+    Running,
+    Paused,
+    Errored,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamsStatus {
+    /// Current state of the worker.
+    pub state: WorkerState,
+
+    /// Total number of stalls detected by the liveness watchdog.
+    pub stalls: u64,
+
+    /// Total number of times the consumer was torn down and recreated.
+    pub recreations: u64,
+
+    /// Point in time of the most recent stall.
+    pub last_stall_at: Option<DateTime<Utc>>,
+
+    /// The error that moved the worker into the `Errored` state.
+    pub last_error: Option<String>,
+
+    /// The active debug session, if the worker is being traced.
+    pub debug: Option<DebugSession>,
+
+    /// Where the pipeline spends its time, and the stage over its budget.
+    pub stages: StageReport,
+
+    /// Total number of tombstones deleted, marked or ignored.
+    pub tombstones_processed: u64,
+
+    /// Total number of messages skipped after the index rejected every write of them.
+    pub sink_failures: u64,
+
+    /// When the worker catches up, `None` until the end offsets were first sampled.
+    pub catch_up: Option<CatchUpReport>,
+
+    /// How fresh the events consumed are, `None` until the end offsets were first sampled.
+    pub freshness: Option<FreshnessReport>,
+}
+
+/// Liveness settings resolved from the subscription config.
+#[derive(Debug, Clone)]
+struct LivenessConfig {
+    enabled: bool,
+    threshold: Duration,
+    max_recreations: u32,
+}
+
+impl LivenessConfig {
+    fn from(subscription: &Subscription) -> Self {
+        let get = |key: &str| subscription.config.get(key);
+
+        Self {
+            enabled: get(config::LIVENESS_ENABLED)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+            threshold: Duration::from_millis(
+                get(config::LIVENESS_STALL_THRESHOLD)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(300_000),
+            ),
+            max_recreations: get(config::LIVENESS_MAX_RECREATIONS)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+        }
+    }
+
+    fn check_interval(&self) -> Duration {
+        std::cmp::max(self.threshold / 4, Duration::from_millis(1))
+    }
+}
+
+/// Changefeed settings resolved from the subscription config.
+#[derive(Debug, Clone)]
+struct ChangefeedConfig {
+    enabled: bool,
+    include_payload: bool,
+    retention: Option<Duration>,
+}
+
+impl ChangefeedConfig {
+    fn from(subscription: &Subscription) -> Self {
+        let flag = |key: &str| subscription.config.get(key).is_some_and(|v| v == "true");
+
+        Self {
+            enabled: flag(config::CHANGEFEED_ENABLED),
+            include_payload: flag(config::CHANGEFEED_INCLUDE_PAYLOAD),
+            retention: subscription
+                .config
+                .get(config::RETENTION)
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis),
+        }
+    }
 }
 
 pub struct StreamsService {
-    clusters: Cluster,
-    subscriptions: Subscription,
-    state: Arc<RwLock<State>>,
+    cluster: Cluster,
+    subscription: Subscription,
+    factory: ConsumerFactory,
+    changefeed: Arc<dyn ChangefeedStore + Send + Sync>,
+    debug: Arc<dyn DebugStore + Send + Sync>,
+    commands: Arc<dyn CommandStore + Send + Sync>,
+    documents: Arc<dyn DocumentStore + Send + Sync>,
+    tracer: Arc<Tracer>,
+    timings: Arc<StageTimings>,
+    metrics: Arc<Registry>,
+    log_target: String,
+    paused: AtomicBool,
+    status: Arc<RwLock<StreamsStatus>>,
+    sd: Arc<Shutdown>,
 }
 
 impl StreamsService {
-    pub fn new(clusters: Cluster, subscriptions: Subscription) -> Self {
-        let state = State {
-            context: HashMap::new(),
+    pub fn new(
+        cluster: Cluster,
+        subscription: Subscription,
+        changefeed: Arc<dyn ChangefeedStore + Send + Sync>,
+        debug: Arc<dyn DebugStore + Send + Sync>,
+        commands: Arc<dyn CommandStore + Send + Sync>,
+        documents: Arc<dyn DocumentStore + Send + Sync>,
+    ) -> Self {
+        Self::with_factory(
+            cluster,
+            subscription,
+            changefeed,
+            debug,
+            commands,
+            documents,
+            kafka_consumers(),
+        )
+    }
+
+    pub fn with_factory(
+        cluster: Cluster,
+        subscription: Subscription,
+        changefeed: Arc<dyn ChangefeedStore + Send + Sync>,
+        debug: Arc<dyn DebugStore + Send + Sync>,
+        commands: Arc<dyn CommandStore + Send + Sync>,
+        documents: Arc<dyn DocumentStore + Send + Sync>,
+        factory: ConsumerFactory,
+    ) -> Self {
+        let status = StreamsStatus {
+            state: WorkerState::Starting,
+            stalls: 0,
+            recreations: 0,
+            last_stall_at: None,
+            last_error: None,
+            debug: None,
+            stages: StageReport::default(),
+            tombstones_processed: 0,
+            sink_failures: 0,
+            catch_up: None,
+            freshness: None,
         };
 
         Self {
-            clusters,
-            subscriptions,
-            state: Arc::new(RwLock::new(state)),
+            log_target: debug::log_target(subscription.id),
+            timings: Arc::new(StageTimings::new(Budgets::from(&subscription))),
+            cluster,
+            subscription,
+            factory,
+            changefeed,
+            debug,
+            commands,
+            documents,
+            tracer: Arc::new(Tracer::default()),
+            metrics: metrics::registry(),
+            paused: AtomicBool::new(false),
+            status: Arc::new(RwLock::new(status)),
+            sd: Arc::new(Shutdown::new()),
+        }
+    }
+
+    /// Stop with the given shutdown, shared with whoever supervises the service.
+    pub(crate) fn with_shutdown(mut self, sd: Arc<Shutdown>) -> Self {
+        self.sd = sd;
+        self
+    }
+
+    /// Count the writes the index rejects in the given registry.
+    pub fn with_metrics(mut self, metrics: Arc<Registry>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Whether the worker is starting, running, paused or errored.
+    pub async fn state(&self) -> WorkerState {
+        self.status.read().await.state
+    }
+
+    pub async fn status(&self) -> StreamsStatus {
+        let mut status = self.status.read().await.clone();
+        status.stages = self.report(&status);
+        status
+    }
+
+    /// The stage timings, together with the liveness and tombstone counters.
+    fn report(&self, status: &StreamsStatus) -> StageReport {
+        StageReport {
+            stalls: status.stalls,
+            recreations: status.recreations,
+            tombstones_processed: status.tombstones_processed,
+            sink_failures: status.sink_failures,
+            catch_up: status.catch_up.clone(),
+            freshness: status.freshness.clone(),
+            ..self.timings.report()
         }
     }
 
     pub async fn start(self: Arc<Self>) {
-        info!("starting stream service");
+        info!(
+            target: &self.log_target,
+            "Starting stream service for subscription {}",
+            self.subscription.id
+        );
+
+        let liveness = LivenessConfig::from(&self.subscription);
+        let feed = ChangefeedConfig::from(&self.subscription);
+        let mut consumer = match self.create_consumer().await {
+            Ok(c) => c,
+            Err(e) => return self.fail(e).await,
+        };
+
+        let mut watchdog = Watchdog::new(liveness.threshold, Instant::now());
+        let mut check = interval(liveness.check_interval());
+        let mut retention = interval(CHANGEFEED_RETENTION_INTERVAL);
+        let mut sync = interval(DEBUG_SYNC_INTERVAL);
+        let mut refresh = interval(MANIFEST_REFRESH_INTERVAL);
+        let mut window = interval(self.timings.budgets().window());
+        window.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut router = ShardRouter::new(self.documents.clone(), &self.subscription);
+        let tombstones = Tombstones::from(&self.subscription);
+        let mut control = DebugControl::new(self.subscription.id, self.tracer.clone());
+        let mut consecutive_stalls = 0;
+
+        // The offset of the next message to consume, for each partition delivered from.
+        let mut positions = HashMap::new();
+        let mut catch_up = CatchUp::new(CatchUpConfig::from(&self.subscription));
+        let mut freshness = Freshness::new(FreshnessConfig::from(&self.subscription));
+        let mut sample = interval(catchup::SAMPLE_INTERVAL);
+        let sampling = Instant::now();
+
+        let mut poll = interval(COMMAND_POLL_INTERVAL);
+        let mut processor = CommandProcessor::new(
+            self.commands.clone(),
+            self.subscription.id,
+            commands::timeout(&self.subscription),
+        );
+
+        // Restore the pause applied before the indexer restarted.
+        match commands::is_paused(self.commands.clone(), self.subscription.id).await {
+            Ok(paused) => self.set_paused(paused).await,
+            Err(e) => return self.fail(e).await,
+        }
 
         loop {
-            sleep(Duration::from_millis(1100)).await;
-            info!("1100 ms have elapsed");
+            let current = consumer.clone();
+            let started = Instant::now();
+
+            // Branches run to completion once picked, so the message in flight
+            // is indexed and committed before a shutdown is noticed.
+            tokio::select! {
+                _ = self.sd.wait_begin() => {
+                    info!(target: &self.log_target, "Stream service for subscription {} stopped", self.subscription.id);
+                    self.sd.complete();
+                    return;
+                }
+                result = current.consume(), if !self.is_paused() => {
+                    let elapsed = started.elapsed();
+                    self.trace_consume(&result, elapsed);
+
+                    if let Ok(Some(m)) = result {
+                        watchdog.record_receive(Instant::now());
+                        consecutive_stalls = 0;
+                        self.timings.record(Stage::Consume, elapsed);
+
+                        let decoding = Instant::now();
+                        let write = tombstones.write(&m);
+                        self.timings.record(Stage::Decode, decoding.elapsed());
+
+                        // Ignored tombstones have nothing to write, but still change the feed.
+                        let sinking = Instant::now();
+                        let indexed = match write {
+                            Some(write) => self.flush(&mut router, &m, vec![write]).await,
+                            None => true,
+                        };
+
+                        // Skipped rather than retried forever, so it doesn't hold the
+                        // partition back, and left out of the feed as nothing changed.
+                        if indexed {
+                            if m.payload.is_none() {
+                                self.status.write().await.tombstones_processed += 1;
+                            }
+                            if feed.enabled {
+                                self.append_change(&m, &tombstones, feed.include_payload).await;
+                            }
+                        } else {
+                            self.skip(&m).await;
+                        }
+                        self.timings.record(Stage::Sink, sinking.elapsed());
+
+                        // Offsets are only committed once the message was indexed or skipped.
+                        let committing = Instant::now();
+                        if let Err(e) = current.commit(&m).await {
+                            warn!(target: &self.log_target, "Unable to commit offset {}-{} for subscription {}: {}", m.partition, m.offset, self.subscription.id, e);
+                        }
+                        self.timings.record(Stage::Commit, committing.elapsed());
+                        positions.insert(m.partition, m.offset + 1);
+                        freshness.observe(m.partition, m.timestamp);
+                    }
+                }
+                _ = sample.tick(), if !self.is_paused() => {
+                    match consumer.fetch_end_offsets().await {
+                        Ok(ends) => {
+                            let at_ms = sampling.elapsed().as_millis() as u64;
+                            catch_up.record(at_ms, &positions, &ends);
+                            let change = freshness.record(Utc::now().timestamp_millis(), &positions, &ends);
+                            self.report_freshness(change);
+
+                            let mut status = self.status.write().await;
+                            status.catch_up = catch_up.report();
+                            status.freshness = freshness.report();
+                        }
+                        Err(e) => debug!(target: &self.log_target, "Unable to sample end offsets for subscription {}: {}", self.subscription.id, e),
+                    }
+                }
+                _ = window.tick() => {
+                    self.evaluate_stages().await;
+                }
+                _ = sync.tick() => {
+                    self.sync_debug(&mut control).await;
+                }
+                _ = poll.tick() => {
+                    let paused = self.is_paused();
+                    if let Err(e) = processor.poll(self.as_ref()).await {
+                        warn!(target: &self.log_target, "Unable to apply commands for subscription {}: {}", self.subscription.id, e);
+                    }
+
+                    // Don't count the time spent paused as a stall.
+                    if paused && !self.is_paused() {
+                        watchdog.reset(Instant::now());
+                    }
+                }
+                _ = refresh.tick() => {
+                    if let Err(e) = router.refresh().await {
+                        debug!(target: &self.log_target, "Unable to refresh shard manifest: {}", e);
+                    }
+                }
+                _ = retention.tick(), if feed.retention.is_some() => {
+                    if feed.enabled {
+                        self.truncate_changes(feed.retention.unwrap()).await;
+                    }
+                    if router.is_sharded() {
+                        self.expire_shards(&mut router, feed.retention.unwrap()).await;
+                    }
+                }
+                _ = check.tick(), if liveness.enabled && !self.is_paused() => {
+                    let offsets = match consumer.fetch_end_offsets().await {
+                        Ok(offsets) => offsets,
+                        Err(e) => {
+                            warn!(target: &self.log_target, "Unable to fetch end offsets for subscription {}: {}", self.subscription.id, e);
+                            continue;
+                        }
+                    };
+
+                    // Only a consumer without an assignment needs the topic's partitions.
+                    let partitions = match offsets.is_empty() {
+                        true => consumer.topic_partitions().await.unwrap_or_else(|e| {
+                            warn!(target: &self.log_target, "Unable to fetch partitions for subscription {}: {}", self.subscription.id, e);
+                            0
+                        }),
+                        false => offsets.len(),
+                    };
+
+                    if watchdog.check(Instant::now(), &offsets, partitions) != Liveness::Stalled {
+                        continue;
+                    }
+
+                    consecutive_stalls += 1;
+                    self.record_stall().await;
+
+                    if consecutive_stalls > liveness.max_recreations {
+                        let msg = format!("consumer stalled {} times in a row", consecutive_stalls);
+                        return self.fail(msg.into()).await;
+                    }
+
+                    warn!(target: &self.log_target, "Consumer for subscription {} stalled, recreating...", self.subscription.id);
+
+                    // Drop the wedged consumer before creating its replacement so
+                    // the new one resumes from the committed offsets.
+                    drop(current);
+                    consumer = match self.create_consumer().await {
+                        Ok(c) => c,
+                        Err(e) => return self.fail(e).await,
+                    };
+
+                    self.status.write().await.recreations += 1;
+                    watchdog.reset(Instant::now());
+                }
+            }
         }
     }
 
+    /// Stop consuming, once the message in flight, if any, is committed.
     pub async fn stop(self: Arc<Self>) {
-        info!("stopping stream service");
+        info!(
+            target: &self.log_target,
+            "Stopping stream service for subscription {}",
+            self.subscription.id
+        );
+
+        self.sd.begin();
+        self.sd.wait_complete().await;
+    }
+
+    async fn create_consumer(&self) -> Result<Arc<dyn StreamsConsumer + Send + Sync>, AnyError> {
+        fail_point!(crate::failpoints::CONSUMER_CREATE, self.subscription.id)?;
+        (self.factory)(&self.cluster, &self.subscription)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    async fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+        self.status.write().await.state = if paused {
+            WorkerState::Paused
+        } else {
+            WorkerState::Running
+        };
+        self.publish_stages().await;
+    }
+
+    async fn append_change(
+        &self,
+        message: &StreamsMessage,
+        tombstones: &Tombstones,
+        include_payload: bool,
+    ) {
+        let record = ChangeRecord::from_message(self.subscription.id, message, include_payload)
+            .with_document_id(tombstones.ids.id(message));
+        let started = Instant::now();
+        let result = self.changefeed.append(vec![record]).await;
+
+        self.tracer.record(|| {
+            let outcome = match result {
+                Ok(_) => Outcome::Ok,
+                Err(_) => Outcome::Failed,
+            };
+            let detail = format!("changefeed {}-{}", message.partition, message.offset);
+            TraceEvent::new(Stage::Sink, started.elapsed(), outcome, Some(detail))
+        });
+
+        if let Err(e) = result {
+            warn!(
+                target: &self.log_target,
+                "Unable to append change for subscription {}: {}",
+                self.subscription.id, e
+            );
+        }
+    }
+
+    /// Apply the message's writes, retrying with backoff while the index
+    /// rejects them, and returning whether they were applied.
+    async fn flush(
+        &self,
+        router: &mut ShardRouter,
+        message: &StreamsMessage,
+        writes: Vec<Write>,
+    ) -> bool {
+        let mut backoff = SINK_RETRY_BACKOFF;
+
+        for attempt in 0..=SINK_RETRIES {
+            if attempt > 0 {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+
+            let started = Instant::now();
+            let result = router.apply(writes.clone()).await;
+
+            self.tracer.record(|| {
+                let outcome = match result {
+                    Ok(_) => Outcome::Ok,
+                    Err(_) => Outcome::Failed,
+                };
+                let detail = format!("index {}-{}", message.partition, message.offset);
+                TraceEvent::new(Stage::Sink, started.elapsed(), outcome, Some(detail))
+            });
+
+            let key = || dedup::key(dedup::SINK_RETRY, self.subscription.id);
+            match result {
+                Ok(_) => {
+                    clear_dedup!(key(), target: &self.log_target);
+                    return true;
+                }
+                Err(e) => {
+                    log_dedup!(
+                        key(),
+                        target: &self.log_target,
+                        log::Level::Warn,
+                        "Unable to write documents of message {}-{} for subscription {} (attempt {} of {}): {}",
+                        message.partition,
+                        message.offset,
+                        self.subscription.id,
+                        attempt + 1,
+                        SINK_RETRIES + 1,
+                        e
+                    );
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Give up on a message the index rejected every write of, counting it
+    /// before its offset is committed like any other.
+    async fn skip(&self, message: &StreamsMessage) {
+        let id = self.subscription.id.to_string();
+        self.metrics
+            .inc(&metrics::SINK_WRITE_FAILURES, &[("subscription_id", &id)]);
+        self.status.write().await.sink_failures += 1;
+
+        error!(
+            target: &self.log_target,
+            "Skipping message {}-{} (key {:?}) of subscription {}, the index rejected it {} times",
+            message.partition,
+            message.offset,
+            message.key,
+            self.subscription.id,
+            SINK_RETRIES + 1
+        );
+    }
+
+    /// Drop the shards holding only documents past retention.
+    async fn expire_shards(&self, router: &mut ShardRouter, retention: Duration) {
+        let cutoff = Utc::now().timestamp_millis() - retention.as_millis() as i64;
+
+        match router.expire(cutoff).await {
+            Ok(0) => {}
+            Ok(n) => info!(
+                target: &self.log_target,
+                "Dropped {} expired shards of subscription {}",
+                n, self.subscription.id
+            ),
+            Err(e) => warn!(
+                target: &self.log_target,
+                "Unable to drop expired shards of subscription {}: {}",
+                self.subscription.id, e
+            ),
+        }
+    }
+
+    async fn truncate_changes(&self, retention: Duration) {
+        let before = Utc::now().timestamp_millis() - retention.as_millis() as i64;
+
+        match self.changefeed.truncate(self.subscription.id, before).await {
+            Ok(0) => {}
+            Ok(n) => debug!(
+                target: &self.log_target,
+                "Truncated {} changefeed records for subscription {}",
+                n, self.subscription.id
+            ),
+            Err(e) => warn!(
+                target: &self.log_target,
+                "Unable to truncate changefeed for subscription {}: {}",
+                self.subscription.id, e
+            ),
+        }
+    }
+
+    fn trace_consume(&self, result: &Result<Option<StreamsMessage>, AnyError>, elapsed: Duration) {
+        match result {
+            Ok(Some(m)) => trace!(
+                target: &self.log_target,
+                "Consumed message at {}-{} in {:?}",
+                m.partition,
+                m.offset,
+                elapsed
+            ),
+            Ok(None) => trace!(target: &self.log_target, "Consumed nothing in {:?}", elapsed),
+            Err(e) => debug!(target: &self.log_target, "Consume failed: {}", e),
+        }
+
+        self.tracer.record(|| {
+            let (outcome, detail) = match result {
+                Ok(Some(m)) => (Outcome::Ok, Some(format!("{}-{}", m.partition, m.offset))),
+                Ok(None) => (Outcome::Empty, None),
+                Err(e) => (Outcome::Failed, Some(e.to_string())),
+            };
+            TraceEvent::new(Stage::Consume, elapsed, outcome, detail)
+        });
+    }
+
+    /// Apply the latest debug session and publish the trace while it is active.
+    async fn sync_debug(&self, control: &mut DebugControl) {
+        let session = match self.debug.session(self.subscription.id).await {
+            Ok(session) => session,
+            Err(e) => {
+                debug!(target: &self.log_target, "Unable to read debug session: {}", e);
+                return;
+            }
+        };
+
+        let changed = control.apply(session, Utc::now());
+        if changed {
+            let active = control.active().cloned();
+            match &active {
+                Some(s) => info!(
+                    target: &self.log_target,
+                    "Tracing subscription {} at {:?} until {}",
+                    self.subscription.id,
+                    s.level,
+                    s.expires_at
+                ),
+                None => info!(
+                    target: &self.log_target,
+                    "Debug session for subscription {} ended", self.subscription.id
+                ),
+            }
+            self.status.write().await.debug = active;
+        }
+
+        // Publish the trace while active, and clear it once the session ends.
+        if control.active().is_none() && !changed {
+            return;
+        }
+
+        let events = self.tracer.snapshot();
+        if let Err(e) = self.debug.put_trace(self.subscription.id, events).await {
+            debug!(target: &self.log_target, "Unable to publish trace: {}", e);
+        }
+    }
+
+    /// Close the window of stage timings, warning once when a stage becomes the
+    /// bottleneck, and publish the timings.
+    async fn evaluate_stages(&self) {
+        match self.timings.evaluate() {
+            Some(BottleneckChange::Detected { stage, p99, budget }) => warn!(
+                target: &self.log_target,
+                "Subscription {} is bottlenecked on {}: p99 of {:?} is over its budget of {:?}",
+                self.subscription.id,
+                stage.name(),
+                p99,
+                budget
+            ),
+            Some(BottleneckChange::Cleared { stage }) => info!(
+                target: &self.log_target,
+                "Subscription {} is no longer bottlenecked on {}",
+                self.subscription.id,
+                stage.name()
+            ),
+            None => {}
+        }
+
+        self.publish_stages().await;
+    }
+
+    /// Publish the stage timings, along with the state of the worker for the
+    /// server to show.
+    async fn publish_stages(&self) {
+        let status = self.status.read().await.clone();
+        let report = StageReport {
+            state: Some(status.state),
+            ..self.report(&status)
+        };
+        if let Err(e) = self.debug.put_stages(self.subscription.id, report).await {
+            debug!(target: &self.log_target, "Unable to publish stage timings: {}", e);
+        }
+    }
+
+    /// Log the source of the subscription going stale or fresh again.
+    fn report_freshness(&self, change: Option<bool>) {
+        match change {
+            Some(true) => warn!(
+                target: &self.log_target,
+                "Source of subscription {} is stale: messages keep arriving without newer event times",
+                self.subscription.id
+            ),
+            Some(false) => info!(
+                target: &self.log_target,
+                "Source of subscription {} is fresh again",
+                self.subscription.id
+            ),
+            None => {}
+        }
+    }
+
+    async fn record_stall(&self) {
+        let mut status = self.status.write().await;
+        status.stalls += 1;
+        status.last_stall_at = Some(Utc::now());
+    }
+
+    async fn fail(&self, e: AnyError) {
+        error!(
+            target: &self.log_target,
+            "Stream service for subscription {} errored: {}",
+            self.subscription.id, e
+        );
+
+        {
+            let mut status = self.status.write().await;
+            status.state = WorkerState::Errored;
+            status.last_error = Some(e.to_string());
+        }
+        self.publish_stages().await;
+    }
+}
+
+#[async_trait]
+impl CommandHandler for StreamsService {
+    async fn handle(&self, command: &Command) -> Result<Option<String>, AnyError> {
+        // Failed rather than applied, a command queued by a newer server keeps its kind.
+        let paused = match &command.kind {
+            CommandKind::Pause => true,
+            CommandKind::Resume => false,
+            kind => return Err(format!("unsupported command '{}'", kind).into()),
+        };
+        let result = match (self.is_paused(), paused) {
+            (true, true) => "already paused",
+            (false, false) => "already running",
+            (_, true) => "paused",
+            (_, false) => "resumed",
+        };
+
+        info!(
+            target: &self.log_target,
+            "Subscription {} {}", self.subscription.id, result
+        );
+
+        self.set_paused(paused).await;
+        Ok(Some(result.to_string()))
+    }
+}
+
+/// A scripted consumer that never delivers and reports the given end offsets.
+#[cfg(test)]
+struct SilentConsumer {
+    end_offsets: std::sync::Mutex<Box<dyn FnMut() -> i64 + Send>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl StreamsConsumer for SilentConsumer {
+    async fn consume(&self) -> Result<Option<StreamsMessage>, AnyError> {
+        std::future::pending().await
+    }
+
+    async fn commit(&self, _message: &StreamsMessage) -> Result<(), AnyError> {
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<std::collections::HashMap<i32, i64>, AnyError> {
+        let offset = (self.end_offsets.lock().unwrap())();
+        Ok(std::collections::HashMap::from([(0, offset)]))
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        Ok(1)
     }
 }
+
+#[cfg(test)]
+fn silent_service(
+    advancing: bool,
+    max_recreations: u32,
+    commands: Arc<dyn CommandStore + Send + Sync>,
+) -> Arc<StreamsService> {
+    let factory: ConsumerFactory = Arc::new(move |_, _| {
+        let mut offset = 0;
+        let consumer = SilentConsumer {
+            end_offsets: std::sync::Mutex::new(Box::new(move || {
+                if advancing {
+                    offset += 10;
+                }
+                offset
+            })),
+        };
+        Ok(Arc::new(consumer))
+    });
+
+    scripted_service(factory, max_recreations, commands)
+}
+
+#[cfg(test)]
+fn scripted_service(
+    factory: ConsumerFactory,
+    max_recreations: u32,
+    commands: Arc<dyn CommandStore + Send + Sync>,
+) -> Arc<StreamsService> {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+
+    let config = HashMap::from([
+        (
+            config::LIVENESS_STALL_THRESHOLD.to_string(),
+            "1000".to_string(),
+        ),
+        (
+            config::LIVENESS_MAX_RECREATIONS.to_string(),
+            max_recreations.to_string(),
+        ),
+    ]);
+
+    let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), HashMap::new());
+    let subscription = Subscription::new(None, cluster.id, "orders".to_string(), config);
+
+    let changefeed = Arc::new(crate::changefeed::store::MemoryChangefeedStore::default());
+    let debug = Arc::new(crate::debug::store::MemoryDebugStore::default());
+    let documents = Arc::new(crate::shards::store::MemoryDocumentStore::default());
+    Arc::new(StreamsService::with_factory(
+        cluster,
+        subscription,
+        changefeed,
+        debug,
+        commands,
+        documents,
+        factory,
+    ))
+}
+
+#[cfg(test)]
+fn memory_commands() -> Arc<dyn CommandStore + Send + Sync> {
+    Arc::new(crate::commands::store::MemoryCommandStore::default())
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_recreates_stalled_consumers_until_errored() {
+    let service = silent_service(true, 2, memory_commands());
+
+    tokio::time::timeout(Duration::from_secs(60), service.clone().start())
+        .await
+        .expect("service should give up on a consumer that keeps stalling");
+
+    let status = service.status().await;
+    assert_eq!(status.state, WorkerState::Errored);
+    assert_eq!(status.recreations, 2);
+    assert_eq!(status.stalls, 3);
+    assert!(status.last_stall_at.is_some());
+}
+
+/// A consumer that lost its assignment, on a topic with partitions.
+#[cfg(test)]
+struct UnassignedConsumer;
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl StreamsConsumer for UnassignedConsumer {
+    async fn consume(&self) -> Result<Option<StreamsMessage>, AnyError> {
+        std::future::pending().await
+    }
+
+    async fn commit(&self, _message: &StreamsMessage) -> Result<(), AnyError> {
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<std::collections::HashMap<i32, i64>, AnyError> {
+        Ok(Default::default())
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        Ok(3)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_recreates_consumers_left_without_an_assignment() {
+    let factory: ConsumerFactory = Arc::new(|_, _| Ok(Arc::new(UnassignedConsumer)));
+    let service = scripted_service(factory, 1, memory_commands());
+
+    tokio::time::timeout(Duration::from_secs(60), service.clone().start())
+        .await
+        .expect("service should give up on a consumer that is never assigned");
+
+    let status = service.status().await;
+    assert_eq!(status.state, WorkerState::Errored);
+    assert_eq!((status.stalls, status.recreations), (2, 1));
+    assert_eq!((status.stages.stalls, status.stages.recreations), (2, 1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_ignores_idle_topics() {
+    let service = silent_service(false, 2, memory_commands());
+
+    let result = tokio::time::timeout(Duration::from_secs(60), service.clone().start()).await;
+    assert!(
+        result.is_err(),
+        "service should keep running on an idle topic"
+    );
+
+    let status = service.status().await;
+    assert_eq!(status.state, WorkerState::Running);
+    assert_eq!(status.stalls, 0);
+    assert_eq!(status.recreations, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_pauses_and_resumes_through_commands() {
+    use crate::commands::command::CommandStatus;
+    use crate::commands::enqueue;
+
+    let commands = memory_commands();
+    let service = silent_service(true, 0, commands.clone());
+    let id = service.subscription.id;
+
+    enqueue(
+        commands.as_ref(),
+        id,
+        CommandKind::Pause,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    // Paused workers are not checked for stalls, so an advancing topic is fine.
+    let result = tokio::time::timeout(Duration::from_secs(60), service.clone().start()).await;
+    assert!(result.is_err(), "paused service should keep running");
+    assert_eq!(service.status().await.state, WorkerState::Paused);
+    assert_eq!(service.status().await.stalls, 0);
+
+    // A restarted worker restores the pause, and applies the resume.
+    let restarted = silent_service(false, 0, commands.clone());
+    enqueue(
+        commands.as_ref(),
+        id,
+        CommandKind::Resume,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    let _ = tokio::time::timeout(Duration::from_secs(5), restarted.clone().start()).await;
+    assert_eq!(restarted.status().await.state, WorkerState::Running);
+
+    let history = commands.list(id, 10).await.unwrap();
+    let results = history
+        .iter()
+        .map(|c| (c.kind.clone(), c.status.clone(), c.result.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        vec![
+            (
+                CommandKind::Resume,
+                CommandStatus::Acknowledged,
+                Some("resumed")
+            ),
+            (
+                CommandKind::Pause,
+                CommandStatus::Acknowledged,
+                Some("paused")
+            ),
+        ]
+    );
+}
+
+/// A scripted consumer that delivers a message on every call.
+#[cfg(test)]
+#[derive(Default)]
+struct FlowingConsumer {
+    offset: std::sync::atomic::AtomicI64,
+    payload: Option<String>,
+
+    /// Time between deliveries.
+    interval: Duration,
+
+    /// The offsets committed, in order.
+    commits: Arc<std::sync::Mutex<Vec<i64>>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl StreamsConsumer for FlowingConsumer {
+    async fn consume(&self) -> Result<Option<StreamsMessage>, AnyError> {
+        if !self.interval.is_zero() {
+            tokio::time::sleep(self.interval).await;
+        }
+        Ok(Some(
+            StreamsMessage {
+                payload: self.payload.clone(),
+                payload_json: None,
+                topic: "orders".to_string(),
+                key: None,
+                headers: Default::default(),
+                partition: 0,
+                offset: self.offset.fetch_add(1, Ordering::SeqCst),
+                timestamp: None,
+            }
+            .with_format(super::PayloadFormat::Json),
+        ))
+    }
+
+    async fn commit(&self, message: &StreamsMessage) -> Result<(), AnyError> {
+        self.commits.lock().unwrap().push(message.offset);
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<std::collections::HashMap<i32, i64>, AnyError> {
+        let offset = self.offset.load(Ordering::SeqCst);
+        Ok(std::collections::HashMap::from([(0, offset)]))
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        Ok(1)
+    }
+}
+
+/// A changefeed sink that takes `delay` to append each batch of records.
+#[cfg(test)]
+struct SlowChangefeed {
+    delay: Duration,
+    inner: crate::changefeed::store::MemoryChangefeedStore,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl ChangefeedStore for SlowChangefeed {
+    async fn append(&self, records: Vec<ChangeRecord>) -> Result<(), AnyError> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.append(records).await
+    }
+
+    async fn read(
+        &self,
+        id: crate::ids::SubscriptionId,
+        cursor: &crate::changefeed::cursor::Cursor,
+        limit: usize,
+    ) -> Result<Vec<ChangeRecord>, AnyError> {
+        self.inner.read(id, cursor, limit).await
+    }
+
+    async fn get(
+        &self,
+        id: crate::ids::SubscriptionId,
+        partition: i32,
+        offset: i64,
+    ) -> Result<Option<ChangeRecord>, AnyError> {
+        self.inner.get(id, partition, offset).await
+    }
+
+    async fn truncate(
+        &self,
+        id: crate::ids::SubscriptionId,
+        before_ms: i64,
+    ) -> Result<usize, AnyError> {
+        self.inner.truncate(id, before_ms).await
+    }
+
+    async fn truncated(
+        &self,
+        id: crate::ids::SubscriptionId,
+    ) -> Result<std::collections::HashMap<i32, i64>, AnyError> {
+        self.inner.truncated(id).await
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_only_feeds_changes_that_were_indexed() {
+    use std::collections::HashMap;
+
+    use crate::changefeed::cursor::Cursor;
+    use crate::changefeed::store::MemoryChangefeedStore;
+    use crate::clusters::cluster::Kind;
+    use crate::shards::store::MemoryDocumentStore;
+
+    let config = HashMap::from(
+        [
+            (config::LIVENESS_ENABLED, "false"),
+            (config::CHANGEFEED_ENABLED, "true"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string())),
+    );
+    let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), HashMap::new());
+    let subscription = Subscription::new(None, cluster.id, "orders".to_string(), config);
+    let id = subscription.id;
+
+    let factory: ConsumerFactory = Arc::new(|_, _| {
+        Ok(Arc::new(FlowingConsumer {
+            payload: Some(r#"{"total":10}"#.to_string()),
+            interval: Duration::from_millis(10),
+            ..Default::default()
+        }))
+    });
+    let changefeed = Arc::new(MemoryChangefeedStore::default());
+    let documents = Arc::new(MemoryDocumentStore::default());
+    documents.failing.store(true, Ordering::SeqCst);
+    let service = Arc::new(StreamsService::with_factory(
+        cluster,
+        subscription,
+        changefeed.clone(),
+        Arc::new(crate::debug::store::MemoryDebugStore::default()),
+        memory_commands(),
+        documents,
+        factory,
+    ));
+
+    let _ = tokio::time::timeout(Duration::from_millis(100), service.start()).await;
+
+    let records = changefeed.read(id, &Cursor::default(), 10).await.unwrap();
+    assert!(records.is_empty(), "{:?}", records);
+}
+
+#[tokio::test]
+async fn it_fails_commands_it_does_not_recognize_and_keeps_them() {
+    use crate::commands::command::CommandStatus;
+
+    let queue = memory_commands();
+    let service = silent_service(false, 3, queue.clone());
+    let id = service.subscription.id;
+    let drain = CommandKind::Unrecognized("drain".to_string());
+    commands::enqueue(queue.as_ref(), id, drain.clone(), serde_json::Value::Null)
+        .await
+        .unwrap();
+
+    let mut processor = CommandProcessor::new(queue.clone(), id, Duration::from_secs(30));
+    assert_eq!(processor.poll(service.as_ref()).await.unwrap(), 1);
+    assert!(!service.is_paused());
+
+    let command = queue.list(id, 1).await.unwrap().remove(0);
+    assert_eq!(command.kind, drain);
+    assert_eq!(command.status, CommandStatus::Failed);
+    assert_eq!(
+        command.result.as_deref(),
+        Some("unsupported command 'drain'")
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_attributes_a_slow_sink_as_the_bottleneck() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+    use crate::debug::store::MemoryDebugStore;
+
+    let config = HashMap::from(
+        [
+            (config::LIVENESS_ENABLED, "false"),
+            (config::CHANGEFEED_ENABLED, "true"),
+            (config::BUDGET_WINDOW, "1000"),
+            (config::BUDGET_SUSTAINED_WINDOWS, "2"),
+            ("budget.decode.ms", "10"),
+            ("budget.sink.ms", "100"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string())),
+    );
+    let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), HashMap::new());
+    let subscription = Subscription::new(None, cluster.id, "orders".to_string(), config);
+
+    let factory: ConsumerFactory = Arc::new(|_, _| Ok(Arc::new(FlowingConsumer::default())));
+    let changefeed = Arc::new(SlowChangefeed {
+        delay: Duration::from_millis(250),
+        inner: Default::default(),
+    });
+    let debug = Arc::new(MemoryDebugStore::default());
+    let service = Arc::new(StreamsService::with_factory(
+        cluster,
+        subscription,
+        changefeed,
+        debug.clone(),
+        memory_commands(),
+        Arc::new(crate::shards::store::MemoryDocumentStore::default()),
+        factory,
+    ));
+
+    let _ = tokio::time::timeout(Duration::from_secs(10), service.clone().start()).await;
+
+    let stages = service.status().await.stages;
+    assert_eq!(stages.bottleneck, Some(Stage::Sink));
+    let sink = &stages.stages[Stage::Sink as usize];
+    assert!(sink.count > 30, "{}", sink.count);
+    assert_eq!(sink.p99_us, Some(500_000));
+    assert!(sink.share > 0.9, "{}", sink.share);
+    assert_eq!(stages.stages[Stage::Transform as usize].count, 0);
+
+    let published = debug.stages(service.subscription.id).await.unwrap();
+    assert_eq!(published.unwrap().bottleneck, Some(Stage::Sink));
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_reports_a_caught_up_worker_once_its_lag_stayed_low() {
+    use crate::clusters::cluster::Kind;
+    use crate::debug::store::MemoryDebugStore;
+    use crate::kafka::streams::catchup::Eta;
+
+    let config = HashMap::from(
+        [
+            (config::LIVENESS_ENABLED, "false"),
+            (config::BUDGET_WINDOW, "5000"),
+            (config::CATCHUP_THRESHOLD, "5"),
+            (config::CATCHUP_SUSTAINED, "20000"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string())),
+    );
+    let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), HashMap::new());
+    let subscription = Subscription::new(None, cluster.id, "orders".to_string(), config);
+
+    let factory: ConsumerFactory = Arc::new(|_, _| {
+        Ok(Arc::new(FlowingConsumer {
+            interval: Duration::from_millis(10),
+            ..Default::default()
+        }))
+    });
+    let debug = Arc::new(MemoryDebugStore::default());
+    let service = Arc::new(StreamsService::with_factory(
+        cluster,
+        subscription,
+        Arc::new(crate::changefeed::store::MemoryChangefeedStore::default()),
+        debug.clone(),
+        memory_commands(),
+        Arc::new(crate::shards::store::MemoryDocumentStore::default()),
+        factory,
+    ));
+
+    let _ = tokio::time::timeout(Duration::from_secs(15), service.clone().start()).await;
+    let catch_up = service.status().await.catch_up.unwrap();
+    assert!(!catch_up.caught_up);
+    assert!(catch_up.lag <= 1, "{}", catch_up.lag);
+
+    let _ = tokio::time::timeout(Duration::from_secs(30), service.clone().start()).await;
+    let catch_up = service.status().await.catch_up.unwrap();
+    assert!(catch_up.caught_up);
+    assert!(
+        matches!(catch_up.eta, Eta::Ms(ms) if ms < 1_000),
+        "{:?}",
+        catch_up.eta
+    );
+    assert!(catch_up.partitions[0].consumption_rate > 50.0);
+
+    let published = debug.stages(service.subscription.id).await.unwrap();
+    let published = published.unwrap();
+    assert!(published.catch_up.is_some());
+
+    // Messages without a timestamp leave the event time unknown, never stale.
+    let freshness = published.freshness.unwrap();
+    assert_eq!(freshness.event_lag_ms, None);
+    assert!(!freshness.stale_source);
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(start_paused = true)]
+async fn it_errors_when_a_stalled_consumer_cannot_be_recreated() {
+    use crate::failpoints::{self, Failpoint, Mode};
+    use crate::ids::SubscriptionId;
+
+    let Ok(mut service) = Arc::try_unwrap(silent_service(true, 3, memory_commands())) else {
+        unreachable!()
+    };
+    service.subscription.id = SubscriptionId(952);
+    let service = Arc::new(service);
+
+    let worker = tokio::spawn(service.clone().start());
+    while service.status().await.state != WorkerState::Running {
+        tokio::task::yield_now().await;
+    }
+
+    // The first consumer stalls, and its replacement fails to connect.
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::CONSUMER_CREATE, 952),
+        mode: Mode::Error,
+        count: Some(1),
+    });
+    tokio::time::timeout(Duration::from_secs(60), worker)
+        .await
+        .expect("service should give up once its consumer cannot be recreated")
+        .unwrap();
+
+    let status = service.status().await;
+    assert_eq!(status.state, WorkerState::Errored);
+    assert_eq!(status.stalls, 1);
+    assert_eq!(status.recreations, 0);
+    assert_eq!(
+        status.last_error.as_deref(),
+        Some("failpoint consumer.create triggered")
+    );
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(start_paused = true)]
+async fn it_skips_the_messages_the_sink_keeps_rejecting() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+    use crate::failpoints::{self, Failpoint, Mode};
+    use crate::ids::SubscriptionId;
+    use crate::shards::store::MemoryDocumentStore;
+
+    let config = HashMap::from([(config::LIVENESS_ENABLED.to_string(), "false".to_string())]);
+    let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), HashMap::new());
+    let id = SubscriptionId(9523);
+    let subscription = Subscription::new(Some(id), cluster.id, "orders".to_string(), config);
+
+    let commits = Arc::new(std::sync::Mutex::new(vec![]));
+    let committed = commits.clone();
+    let factory: ConsumerFactory = Arc::new(move |_, _| {
+        Ok(Arc::new(FlowingConsumer {
+            payload: Some(r#"{"total":10}"#.to_string()),
+            interval: Duration::from_millis(10),
+            commits: committed.clone(),
+            ..Default::default()
+        }))
+    });
+    let documents = Arc::new(MemoryDocumentStore::default());
+    let registry = Arc::new(Registry::new(&[metrics::SINK_WRITE_FAILURES]));
+    let service = Arc::new(
+        StreamsService::with_factory(
+            cluster,
+            subscription,
+            Arc::new(crate::changefeed::store::MemoryChangefeedStore::default()),
+            Arc::new(crate::debug::store::MemoryDebugStore::default()),
+            memory_commands(),
+            documents.clone(),
+            factory,
+        )
+        .with_metrics(registry.clone()),
+    );
+    let indexed = || async {
+        let mut count = 0;
+        for shard in documents.shards(id).await.unwrap() {
+            count += documents.count(&shard).await.unwrap();
+        }
+        count
+    };
+
+    // Rejections within the retries only hold the message back.
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::MEILISEARCH_SUBMIT, id),
+        mode: Mode::Error,
+        count: Some(SINK_RETRIES),
+    });
+    let worker = tokio::spawn(service.clone().start());
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let offsets = commits.lock().unwrap().clone();
+    assert!(offsets.len() > 10, "{:?}", offsets);
+    assert!(offsets.iter().copied().eq(0..offsets.len() as i64));
+    assert_eq!(indexed().await, offsets.len());
+    assert_eq!(service.status().await.sink_failures, 0);
+
+    // A message the index keeps rejecting is counted and skipped, the ones after it indexed.
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::MEILISEARCH_SUBMIT, id),
+        mode: Mode::Error,
+        count: Some(SINK_RETRIES + 1),
+    });
+    let skipped = commits.lock().unwrap().len() as i64;
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    service.clone().stop().await;
+    worker.await.unwrap();
+
+    let offsets = commits.lock().unwrap().clone();
+    assert!(offsets.len() as i64 > skipped + 10, "{:?}", offsets);
+    assert!(offsets.iter().copied().eq(0..offsets.len() as i64));
+    assert_eq!(indexed().await, offsets.len() - 1);
+    let status = service.status().await;
+    assert_ne!(status.state, WorkerState::Errored);
+    assert_eq!(status.sink_failures, 1);
+    assert_eq!(status.stages.sink_failures, 1);
+    let series = format!(
+        "seekr_sink_write_failures_total{{subscription_id=\"{}\"}} 1",
+        id
+    );
+    assert!(registry.render().contains(&series), "{}", registry.render());
+}
+
+/// A scripted consumer that delivers its messages once, then nothing.
+#[cfg(test)]
+#[derive(Default)]
+struct ReplayConsumer {
+    messages: std::sync::Mutex<std::collections::VecDeque<StreamsMessage>>,
+
+    /// The offsets committed, in order.
+    commits: Arc<std::sync::Mutex<Vec<i64>>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl StreamsConsumer for ReplayConsumer {
+    async fn consume(&self) -> Result<Option<StreamsMessage>, AnyError> {
+        let next = self.messages.lock().unwrap().pop_front();
+        match next {
+            Some(m) => Ok(Some(m)),
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn commit(&self, message: &StreamsMessage) -> Result<(), AnyError> {
+        self.commits.lock().unwrap().push(message.offset);
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(&self) -> Result<std::collections::HashMap<i32, i64>, AnyError> {
+        Ok(Default::default())
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, AnyError> {
+        Ok(1)
+    }
+}
+
+#[cfg(test)]
+fn keyed(key: &str, payload: Option<&str>, offset: i64) -> StreamsMessage {
+    StreamsMessage {
+        payload: payload.map(|p| p.to_string()),
+        payload_json: None,
+        topic: "orders".to_string(),
+        key: Some(key.to_string()),
+        headers: Default::default(),
+        partition: 0,
+        offset,
+        timestamp: Some(1_700_000_000_000),
+    }
+    .with_format(super::PayloadFormat::Json)
+}
+
+/// A service of subscription 1 replaying the messages into the store, and the offsets it commits.
+#[cfg(test)]
+fn replay_service(
+    action: &str,
+    messages: Vec<StreamsMessage>,
+    documents: Arc<crate::shards::store::MemoryDocumentStore>,
+    changefeed: Arc<crate::changefeed::store::MemoryChangefeedStore>,
+) -> (Arc<StreamsService>, Arc<std::sync::Mutex<Vec<i64>>>) {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+    use crate::ids::SubscriptionId;
+
+    let config = HashMap::from(
+        [
+            (config::LIVENESS_ENABLED, "false"),
+            (config::CHANGEFEED_ENABLED, "true"),
+            (config::DOCUMENT_ID, "key"),
+            (config::TOMBSTONE_ACTION, action),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string())),
+    );
+    let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), HashMap::new());
+    let subscription = Subscription::new(
+        Some(SubscriptionId(1)),
+        cluster.id,
+        "orders".to_string(),
+        config,
+    );
+
+    let commits = Arc::new(std::sync::Mutex::new(vec![]));
+    let consumer = Arc::new(ReplayConsumer {
+        messages: std::sync::Mutex::new(messages.into()),
+        commits: commits.clone(),
+    });
+    let factory: ConsumerFactory = Arc::new(move |_, _| Ok(consumer.clone()));
+    let service = Arc::new(StreamsService::with_factory(
+        cluster,
+        subscription,
+        changefeed,
+        Arc::new(crate::debug::store::MemoryDebugStore::default()),
+        memory_commands(),
+        documents,
+        factory,
+    ));
+    (service, commits)
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_handles_tombstones_of_compacted_topics_by_action() {
+    use crate::changefeed::cursor::Cursor;
+    use crate::changefeed::record::Operation;
+    use crate::shards::tombstone::DELETED;
+    use crate::shards::{DocumentIds, PRIMARY_KEY};
+
+    let messages = vec![
+        keyed("a", Some(r#"{"total":1}"#), 0),
+        keyed("b", Some(r#"{"total":2}"#), 1),
+        keyed("a", None, 2),
+        keyed("b", None, 3),
+        // Re-created after its delete, the document ends up present.
+        keyed("b", Some(r#"{"total":3}"#), 4),
+    ];
+    let a = DocumentIds::Key.id(&messages[0]);
+    let b = DocumentIds::Key.id(&messages[1]);
+
+    for action in ["ignore", "delete", "index_marker"] {
+        let documents = Arc::new(crate::shards::store::MemoryDocumentStore::default());
+        let changefeed = Arc::new(crate::changefeed::store::MemoryChangefeedStore::default());
+        let (service, commits) = replay_service(
+            action,
+            messages.clone(),
+            documents.clone(),
+            changefeed.clone(),
+        );
+        let _ = tokio::time::timeout(Duration::from_secs(1), service.clone().start()).await;
+
+        assert_eq!(*commits.lock().unwrap(), [0, 1, 2, 3, 4], "{}", action);
+        assert_eq!(service.status().await.tombstones_processed, 2, "{}", action);
+
+        let indexes = documents.indexes.read().await;
+        let docs = &indexes["sub_1"].1;
+        let doc = |id: &str| docs.iter().find(|d| d[PRIMARY_KEY] == id).cloned();
+        assert_eq!(doc(&b).unwrap()["total"], 3, "{}", action);
+        match action {
+            "ignore" => assert_eq!(doc(&a).unwrap()["total"], 1),
+            "delete" => assert_eq!(doc(&a), None),
+            _ => assert_eq!(doc(&a).unwrap()[DELETED], true),
+        }
+
+        let records = changefeed
+            .read(service.subscription.id, &Cursor::default(), 10)
+            .await
+            .unwrap();
+        let deletes = records
+            .iter()
+            .filter(|r| r.operation == Operation::Delete)
+            .map(|r| r.document_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(deletes, [a.as_str(), b.as_str()], "{}", action);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_skips_deletes_it_cannot_flush() {
+    use crate::shards::router::TestRouter;
+    use crate::shards::DocumentIds;
+
+    let tombstone = keyed("a", None, 7);
+    let id = DocumentIds::Key.id(&tombstone);
+
+    let documents = Arc::new(crate::shards::store::MemoryDocumentStore::default());
+    TestRouter::new(documents.clone(), None)
+        .route(vec![(id.as_str(), 0)])
+        .await;
+    documents.failing.store(true, Ordering::SeqCst);
+
+    let (service, commits) = replay_service(
+        "delete",
+        vec![tombstone],
+        documents.clone(),
+        Default::default(),
+    );
+    let _ = tokio::time::timeout(Duration::from_secs(10), service.clone().start()).await;
+
+    // The delete is committed past, while the document it couldn't delete stays.
+    assert_eq!(*commits.lock().unwrap(), [7]);
+    let status = service.status().await;
+    assert_ne!(status.state, WorkerState::Errored);
+    assert_eq!(status.tombstones_processed, 0);
+    assert_eq!(status.sink_failures, 1);
+    assert_eq!(documents.indexes.read().await["sub_1"].1.len(), 1);
+}
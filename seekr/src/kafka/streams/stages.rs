@@ -0,0 +1,467 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::debug::trace::Stage;
+use crate::kafka::config;
+use crate::subscriptions::subscription::Subscription;
+
+use super::catchup::CatchUpReport;
+use super::freshness::FreshnessReport;
+use super::service::WorkerState;
+
+/// Upper bounds of the histogram buckets in microseconds, in 1-2-5 steps from 10µs to 50s.
+const BOUNDS_US: [u64; 22] = [
+    10,
+    20,
+    50,
+    100,
+    200,
+    500,
+    1_000,
+    2_000,
+    5_000,
+    10_000,
+    20_000,
+    50_000,
+    100_000,
+    200_000,
+    500_000,
+    1_000_000,
+    2_000_000,
+    5_000_000,
+    10_000_000,
+    20_000_000,
+    50_000_000,
+    u64::MAX,
+];
+
+/// Default length of the window a stage's p99 is compared to its budget over.
+const DEFAULT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Default number of consecutive windows a stage must be over budget to be the bottleneck.
+const DEFAULT_SUSTAINED_WINDOWS: u32 = 3;
+
+/// A latency histogram with fixed buckets, recorded into without locking.
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; BOUNDS_US.len()],
+    sum_us: AtomicU64,
+}
+
+impl Histogram {
+    fn record(&self, elapsed_us: u64) {
+        let bucket = BOUNDS_US.partition_point(|&bound| bound < elapsed_us);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(elapsed_us, Ordering::Relaxed);
+    }
+
+    fn counts(&self) -> Counts {
+        Counts {
+            buckets: self.buckets.each_ref().map(|b| b.load(Ordering::Relaxed)),
+            sum_us: self.sum_us.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The counts recorded since the last call, resetting the histogram.
+    fn take(&self) -> Counts {
+        Counts {
+            buckets: self
+                .buckets
+                .each_ref()
+                .map(|b| b.swap(0, Ordering::Relaxed)),
+            sum_us: self.sum_us.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// A point in time copy of a histogram.
+#[derive(Clone, Copy, Debug, Default)]
+struct Counts {
+    buckets: [u64; BOUNDS_US.len()],
+    sum_us: u64,
+}
+
+impl Counts {
+    fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// The upper bound of the bucket holding the `q` quantile, `None` without samples.
+    fn quantile(&self, q: f64) -> Option<u64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((count as f64 * q).ceil() as u64).clamp(1, count);
+        let mut seen = 0;
+        for (bucket, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(BOUNDS_US[bucket]);
+            }
+        }
+        None
+    }
+}
+
+/// Budget settings resolved from the subscription config.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Budgets {
+    enabled: bool,
+    window: Duration,
+    sustained: u32,
+
+    /// The p99 budget of each stage, indexed like `Stage::ALL`.
+    limits: [Option<Duration>; Stage::ALL.len()],
+}
+
+impl Budgets {
+    /// The budgets configured by `budget.<stage>.ms`, checked over `budget.window.ms`
+    /// long windows unless `budget.enabled` is `false`.
+    pub fn from(subscription: &Subscription) -> Self {
+        let get = |key: &str| subscription.config.get(key);
+        let millis = |key: &str| {
+            get(key)
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+        };
+
+        Self {
+            enabled: get(config::BUDGET_ENABLED)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+            window: millis(config::BUDGET_WINDOW).unwrap_or(DEFAULT_WINDOW),
+            sustained: get(config::BUDGET_SUSTAINED_WINDOWS)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_SUSTAINED_WINDOWS)
+                .max(1),
+            limits: Stage::ALL.map(|stage| millis(&budget_key(stage))),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        std::cmp::max(self.window, Duration::from_millis(1))
+    }
+
+    fn limit(&self, stage: Stage) -> Option<Duration> {
+        match self.enabled {
+            true => self.limits[stage as usize],
+            false => None,
+        }
+    }
+}
+
+/// The config key of a stage's budget, e.g. `budget.sink.ms`.
+pub fn budget_key(stage: Stage) -> String {
+    format!("budget.{}.ms", stage.name())
+}
+
+/// How the bottleneck changed when a window was evaluated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BottleneckChange {
+    /// The stage has been over its budget for the sustained window.
+    Detected {
+        stage: Stage,
+        p99: Duration,
+        budget: Duration,
+    },
+
+    /// No stage is over its budget anymore.
+    Cleared { stage: Stage },
+}
+
+/// Timings of a single pipeline stage.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StageMetrics {
+    pub stage: Stage,
+
+    /// Number of timings recorded since the worker started.
+    pub count: u64,
+
+    /// Median and 99th percentile since the worker started, in microseconds.
+    pub p50_us: Option<u64>,
+    pub p99_us: Option<u64>,
+
+    /// 99th percentile of the last window, in microseconds.
+    pub window_p99_us: Option<u64>,
+
+    /// Share of the pipeline's time spent in the stage during the last window.
+    pub share: f64,
+
+    /// The budget of the stage, if one is configured.
+    pub budget_ms: Option<u64>,
+}
+
+/// Where a subscription's pipeline spends its time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageReport {
+    pub stages: Vec<StageMetrics>,
+
+    /// The stage whose p99 has been over its budget for the sustained window.
+    pub bottleneck: Option<Stage>,
+
+    /// Stalls detected by the liveness watchdog since the worker started.
+    #[serde(default)]
+    pub stalls: u64,
+
+    /// Times the consumer was torn down and recreated since the worker started.
+    #[serde(default)]
+    pub recreations: u64,
+
+    /// Tombstones deleted, marked or ignored since the worker started.
+    #[serde(default)]
+    pub tombstones_processed: u64,
+
+    /// Messages skipped since the worker started, the index having rejected them.
+    #[serde(default)]
+    pub sink_failures: u64,
+
+    /// When the worker catches up with its partitions' high watermarks.
+    #[serde(default)]
+    pub catch_up: Option<CatchUpReport>,
+
+    /// How far behind the event time consumed is, and whether the producer stalled.
+    #[serde(default)]
+    pub freshness: Option<FreshnessReport>,
+
+    /// The state of the worker when the report was published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<WorkerState>,
+}
+
+/// Per-stage timings of a streams worker, with the budget each stage is held to.
+///
+/// Recording is a few relaxed atomic adds into pre-allocated histograms, so
+/// the timings stay wired into the hot path and can be shared by concurrent
+/// lanes of a worker. Budgets are only checked once per window.
+#[derive(Debug)]
+pub struct StageTimings {
+    budgets: Budgets,
+    total: [Histogram; Stage::ALL.len()],
+    window: [Histogram; Stage::ALL.len()],
+    evaluation: Mutex<Evaluation>,
+}
+
+/// The outcome of the windows evaluated so far.
+#[derive(Debug, Default)]
+struct Evaluation {
+    /// Number of consecutive windows each stage was over budget.
+    over: [u32; Stage::ALL.len()],
+    window_p99_us: [Option<u64>; Stage::ALL.len()],
+    shares: [f64; Stage::ALL.len()],
+    bottleneck: Option<Stage>,
+}
+
+impl StageTimings {
+    pub fn new(budgets: Budgets) -> Self {
+        Self {
+            budgets,
+            total: Default::default(),
+            window: Default::default(),
+            evaluation: Mutex::new(Evaluation::default()),
+        }
+    }
+
+    pub fn budgets(&self) -> &Budgets {
+        &self.budgets
+    }
+
+    pub fn record(&self, stage: Stage, elapsed: Duration) {
+        let elapsed_us = elapsed.as_micros() as u64;
+        self.total[stage as usize].record(elapsed_us);
+        self.window[stage as usize].record(elapsed_us);
+    }
+
+    /// Close the current window, comparing each stage's p99 to its budget.
+    ///
+    /// The bottleneck is the stage over budget for the sustained number of
+    /// windows that took the largest share of the last one. A change is only
+    /// returned when the bottleneck appears, moves or clears, so callers warn
+    /// once rather than on every window.
+    pub fn evaluate(&self) -> Option<BottleneckChange> {
+        let counts = self.window.each_ref().map(Histogram::take);
+        let mut evaluation = self.evaluation.lock().unwrap();
+
+        // An idle window tells nothing about the budgets, so it neither
+        // extends nor breaks a streak.
+        if counts.iter().all(|c| c.count() == 0) {
+            return None;
+        }
+
+        let total_us = counts.iter().map(|c| c.sum_us).sum::<u64>();
+        if total_us > 0 {
+            evaluation.shares = counts.map(|c| c.sum_us as f64 / total_us as f64);
+        }
+        evaluation.window_p99_us = counts.map(|c| c.quantile(0.99));
+
+        for stage in Stage::ALL {
+            let i = stage as usize;
+            let over = match (self.budgets.limit(stage), evaluation.window_p99_us[i]) {
+                (Some(budget), Some(p99)) => p99 > budget.as_micros() as u64,
+                _ => false,
+            };
+            evaluation.over[i] = if over { evaluation.over[i] + 1 } else { 0 };
+        }
+
+        let bottleneck = Stage::ALL
+            .into_iter()
+            .filter(|&s| evaluation.over[s as usize] >= self.budgets.sustained)
+            .max_by(|a, b| {
+                let share = |s: &Stage| evaluation.shares[*s as usize];
+                share(a).total_cmp(&share(b))
+            });
+
+        let previous = std::mem::replace(&mut evaluation.bottleneck, bottleneck);
+        match (previous, bottleneck) {
+            (previous, Some(stage)) if previous != Some(stage) => {
+                Some(BottleneckChange::Detected {
+                    stage,
+                    p99: Duration::from_micros(evaluation.window_p99_us[stage as usize]?),
+                    budget: self.budgets.limit(stage)?,
+                })
+            }
+            (Some(stage), None) => Some(BottleneckChange::Cleared { stage }),
+            _ => None,
+        }
+    }
+
+    pub fn report(&self) -> StageReport {
+        let evaluation = self.evaluation.lock().unwrap();
+        let stages = Stage::ALL
+            .into_iter()
+            .map(|stage| {
+                let i = stage as usize;
+                let counts = self.total[i].counts();
+                StageMetrics {
+                    stage,
+                    count: counts.count(),
+                    p50_us: counts.quantile(0.5),
+                    p99_us: counts.quantile(0.99),
+                    window_p99_us: evaluation.window_p99_us[i],
+                    share: evaluation.shares[i],
+                    budget_ms: self.budgets.limit(stage).map(|b| b.as_millis() as u64),
+                }
+            })
+            .collect();
+
+        StageReport {
+            stages,
+            bottleneck: evaluation.bottleneck,
+            ..StageReport::default()
+        }
+    }
+}
+
+#[cfg(test)]
+fn timings(config: &[(&str, &str)]) -> StageTimings {
+    use std::collections::HashMap;
+
+    use crate::ids::ClusterId;
+
+    let config = config
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<HashMap<_, _>>();
+    let subscription = Subscription::new(None, ClusterId(1), "orders".to_string(), config);
+    StageTimings::new(Budgets::from(&subscription))
+}
+
+/// Record a window of messages whose transform takes `transform_ms`.
+#[cfg(test)]
+fn slow_transform(timings: &StageTimings, transform_ms: u64) {
+    for _ in 0..100 {
+        timings.record(Stage::Decode, Duration::from_micros(80));
+        timings.record(Stage::Transform, Duration::from_millis(transform_ms));
+        timings.record(Stage::Sink, Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn it_buckets_timings_into_quantiles() {
+    let histogram = Histogram::default();
+    for us in [5, 15, 15, 150, 90_000_000] {
+        histogram.record(us);
+    }
+
+    let counts = histogram.counts();
+    assert_eq!(counts.count(), 5);
+    assert_eq!(counts.quantile(0.2), Some(10));
+    assert_eq!(counts.quantile(0.5), Some(20));
+    assert_eq!(counts.quantile(0.8), Some(200));
+    assert_eq!(counts.quantile(0.99), Some(u64::MAX));
+    assert_eq!(Counts::default().quantile(0.99), None);
+
+    histogram.take();
+    assert_eq!(histogram.counts().count(), 0);
+}
+
+#[test]
+fn it_attributes_the_bottleneck_to_the_slow_stage_once_sustained() {
+    let timings = timings(&[
+        (config::BUDGET_SUSTAINED_WINDOWS, "3"),
+        ("budget.decode.ms", "1"),
+        ("budget.transform.ms", "50"),
+        ("budget.sink.ms", "50"),
+    ]);
+
+    // A single slow window isn't a bottleneck yet, and an idle one doesn't
+    // break the streak.
+    let mut changes = vec![];
+    for i in 0..5 {
+        slow_transform(&timings, 80);
+        changes.extend(timings.evaluate());
+        if i == 0 {
+            changes.extend(timings.evaluate());
+        }
+    }
+
+    assert_eq!(
+        changes,
+        vec![BottleneckChange::Detected {
+            stage: Stage::Transform,
+            p99: Duration::from_millis(100),
+            budget: Duration::from_millis(50),
+        }]
+    );
+
+    let report = timings.report();
+    assert_eq!(report.bottleneck, Some(Stage::Transform));
+    let transform = &report.stages[Stage::Transform as usize];
+    assert_eq!(transform.count, 500);
+    assert_eq!(transform.budget_ms, Some(50));
+    assert!(transform.share > 0.9, "{}", transform.share);
+
+    // A window under budget clears the bottleneck.
+    slow_transform(&timings, 10);
+    assert_eq!(
+        timings.evaluate(),
+        Some(BottleneckChange::Cleared {
+            stage: Stage::Transform
+        })
+    );
+    assert_eq!(timings.report().bottleneck, None);
+}
+
+#[test]
+fn it_keeps_collecting_without_budgets() {
+    let timings = timings(&[
+        (config::BUDGET_ENABLED, "false"),
+        ("budget.transform.ms", "50"),
+    ]);
+
+    for _ in 0..5 {
+        slow_transform(&timings, 80);
+        assert_eq!(timings.evaluate(), None);
+    }
+
+    let report = timings.report();
+    assert_eq!(report.bottleneck, None);
+    let transform = &report.stages[Stage::Transform as usize];
+    assert_eq!(transform.count, 500);
+    assert_eq!(transform.p99_us, Some(100_000));
+    assert_eq!(transform.budget_ms, None);
+}
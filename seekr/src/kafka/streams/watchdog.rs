@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Result of a single liveness evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liveness {
+    /// The consumer is receiving data, or has not been silent long enough to judge.
+    Alive,
+    /// Nothing was received, but the assigned partitions have no new data either.
+    Idle,
+    /// The partitions have new data but nothing has been received within the threshold.
+    Stalled,
+}
+
+/// Detects consumers that silently stop receiving data.
+///
+/// A consumer is only considered stalled when the end offsets of its assigned
+/// partitions have advanced since it last received a message *and* the
+/// threshold has elapsed. The end-offset check is what keeps idle topics from
+/// ever triggering a false positive.
+///
+/// A consumer assigned no partition of a topic that has some is stalled too,
+/// once that outlasts a whole check interval, as it would never see new data.
+#[derive(Debug)]
+pub struct Watchdog {
+    threshold: Duration,
+    last_received: Instant,
+    received_since_check: bool,
+    baseline: Option<HashMap<i32, i64>>,
+    unassigned: bool,
+}
+
+impl Watchdog {
+    pub fn new(threshold: Duration, now: Instant) -> Self {
+        Self {
+            threshold,
+            last_received: now,
+            received_since_check: false,
+            baseline: None,
+            unassigned: false,
+        }
+    }
+
+    /// Record that a message has been received.
+    pub fn record_receive(&mut self, now: Instant) {
+        self.last_received = now;
+        self.received_since_check = true;
+    }
+
+    /// Forget all observations, e.g. after the consumer was recreated.
+    pub fn reset(&mut self, now: Instant) {
+        self.last_received = now;
+        self.received_since_check = false;
+        self.baseline = None;
+        self.unassigned = false;
+    }
+
+    /// Evaluate liveness against the latest end offsets of the assigned partitions,
+    /// and the number of partitions of the topic.
+    pub fn check(
+        &mut self,
+        now: Instant,
+        end_offsets: &HashMap<i32, i64>,
+        topic_partitions: usize,
+    ) -> Liveness {
+        // The first check without an assignment may fall within a rebalance.
+        if end_offsets.is_empty() && topic_partitions > 0 {
+            let stalled = std::mem::replace(&mut self.unassigned, true);
+            return if stalled {
+                Liveness::Stalled
+            } else {
+                Liveness::Alive
+            };
+        }
+        self.unassigned = false;
+
+        // Any receive since the previous check moves the baseline forward, so
+        // only data produced after the last delivery counts as "new data".
+        let Some(baseline) = self
+            .baseline
+            .as_ref()
+            .filter(|_| !self.received_since_check)
+        else {
+            self.baseline = Some(end_offsets.clone());
+            self.received_since_check = false;
+            return Liveness::Alive;
+        };
+
+        let advanced = end_offsets
+            .iter()
+            .any(|(p, o)| baseline.get(p).is_some_and(|b| o > b));
+
+        if !advanced {
+            return Liveness::Idle;
+        }
+
+        if now.duration_since(self.last_received) >= self.threshold {
+            Liveness::Stalled
+        } else {
+            Liveness::Alive
+        }
+    }
+}
+
+#[cfg(test)]
+fn offsets(values: &[(i32, i64)]) -> HashMap<i32, i64> {
+    values.iter().copied().collect()
+}
+
+#[test]
+fn it_flags_a_stall_when_offsets_advance_without_delivery() {
+    let start = Instant::now();
+    let threshold = Duration::from_secs(60);
+    let mut watchdog = Watchdog::new(threshold, start);
+
+    assert_eq!(
+        watchdog.check(start, &offsets(&[(0, 10)]), 2),
+        Liveness::Alive
+    );
+
+    let later = start + Duration::from_secs(30);
+    assert_eq!(
+        watchdog.check(later, &offsets(&[(0, 20)]), 2),
+        Liveness::Alive
+    );
+
+    let later = start + threshold;
+    assert_eq!(
+        watchdog.check(later, &offsets(&[(0, 30)]), 2),
+        Liveness::Stalled
+    );
+}
+
+#[test]
+fn it_never_flags_an_idle_topic() {
+    let start = Instant::now();
+    let mut watchdog = Watchdog::new(Duration::from_secs(60), start);
+
+    assert_eq!(
+        watchdog.check(start, &offsets(&[(0, 10), (1, 4)]), 2),
+        Liveness::Alive
+    );
+
+    let much_later = start + Duration::from_secs(3600);
+    assert_eq!(
+        watchdog.check(much_later, &offsets(&[(0, 10), (1, 4)]), 2),
+        Liveness::Idle
+    );
+}
+
+#[test]
+fn it_moves_the_baseline_forward_on_receive() {
+    let start = Instant::now();
+    let threshold = Duration::from_secs(60);
+    let mut watchdog = Watchdog::new(threshold, start);
+
+    watchdog.check(start, &offsets(&[(0, 10)]), 2);
+
+    // Data arrives and is delivered, then the topic goes quiet.
+    let received = start + Duration::from_secs(10);
+    watchdog.record_receive(received);
+    watchdog.check(received, &offsets(&[(0, 20)]), 2);
+
+    let later = received + threshold * 2;
+    assert_eq!(
+        watchdog.check(later, &offsets(&[(0, 20)]), 2),
+        Liveness::Idle
+    );
+}
+
+#[test]
+fn it_flags_a_consumer_left_without_an_assignment() {
+    let start = Instant::now();
+
+    // An empty topic has nothing to assign.
+    let mut watchdog = Watchdog::new(Duration::from_secs(60), start);
+    assert_eq!(watchdog.check(start, &offsets(&[]), 0), Liveness::Alive);
+    assert_eq!(watchdog.check(start, &offsets(&[]), 0), Liveness::Idle);
+
+    // A single check without an assignment may be a rebalance.
+    let mut watchdog = Watchdog::new(Duration::from_secs(60), start);
+    assert_eq!(watchdog.check(start, &offsets(&[]), 2), Liveness::Alive);
+    assert_eq!(
+        watchdog.check(start, &offsets(&[(0, 10)]), 2),
+        Liveness::Alive
+    );
+    assert_eq!(watchdog.check(start, &offsets(&[]), 2), Liveness::Alive);
+    assert_eq!(watchdog.check(start, &offsets(&[]), 2), Liveness::Stalled);
+
+    watchdog.reset(start);
+    assert_eq!(watchdog.check(start, &offsets(&[]), 2), Liveness::Alive);
+}
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
-use async_once::AsyncOnce;
-use meilisearch_sdk::Client;
+use tokio::sync::OnceCell;
 
 use crate::session::CdrsSession;
 
@@ -17,17 +16,65 @@ extern crate log;
 #[macro_use]
 mod macros;
 
+pub mod api;
+pub mod apply;
+pub mod assignment;
+pub mod auth;
+pub mod backend;
+pub mod bundle;
+pub mod capabilities;
+pub mod changefeed;
 pub mod clusters;
+pub mod collisions;
+pub mod commands;
+pub mod cors;
+pub mod counters;
+pub mod deadline;
+pub mod debug;
+pub mod doctor;
+pub mod drain;
 pub mod errors;
+pub mod events;
+#[cfg(feature = "chaos")]
+pub mod failpoints;
+pub mod governance;
+pub mod health;
+pub mod history;
 pub mod id;
+pub use seekr_api_types::ids;
 pub mod indexer;
 pub mod kafka;
+pub mod lint;
+pub mod live;
 pub mod logger;
+pub mod logs;
+pub mod lookup;
+pub mod meilisearch;
+pub mod metrics;
+pub mod mirrors;
+pub mod page;
+pub mod produce;
+pub mod request_id;
+pub mod restart;
+pub mod sampling;
+pub mod schemas;
+pub mod search;
+pub mod search_cache;
 pub mod server;
 pub mod session;
+pub mod settings;
+pub mod shards;
 pub mod shutdown;
+pub mod standby;
+pub mod storage;
 pub mod subscriptions;
+pub mod sweeper;
+pub mod tail;
+pub mod validate;
 pub mod version;
+pub mod warmup;
+#[cfg(test)]
+mod wire;
 
 pub const BANNER: &str = "
    d888888o.   8 8888888888   8 8888888888   8 8888     ,88' 8 888888888o.
@@ -51,8 +98,6 @@ pub const GIT_BRANCH: &str = env!("GIT_BRANCH");
 pub const GIT_SHA: &str = env!("GIT_SHA");
 
 lazy_static! {
-    static ref SESSION: AsyncOnce<Arc<CdrsSession>> =
-        AsyncOnce::new(async { Arc::new(session::create_session().await) });
-    static ref ID_GENERATOR: Arc<id::Generator> = Arc::new(id::Generator::new(0, 0));
-    static ref MS_CLIENT: Arc<Client> = Arc::new(Client::new("http://localhost:7700", "masterKey"));
+    /// Created on first use, so it's only connected when a store is kept in Cassandra.
+    static ref SESSION: OnceCell<Arc<CdrsSession>> = OnceCell::new();
 }
@@ -0,0 +1,130 @@
+use actix_web::web::ServiceConfig;
+use actix_web::{get, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::lint::rules::RULES;
+use crate::lint::{LintPolicy, Severity, Target};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_lint_rules);
+}
+
+#[get("")]
+async fn get_lint_rules(policy: LintPolicy) -> impl Responder {
+    let rules = RULES
+        .iter()
+        .map(|r| LintRuleSummary {
+            name: r.name,
+            targets: r.targets,
+            severity: r.severity,
+            description: r.description,
+            denied: policy.is_denied(r.name),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(ListLintRulesResponse { rules })
+}
+
+#[derive(Serialize)]
+struct LintRuleSummary {
+    name: &'static str,
+    targets: &'static [Target],
+    severity: Severity,
+    description: &'static str,
+
+    /// Whether the server fails creates and updates the rule warns about.
+    denied: bool,
+}
+
+#[derive(Serialize)]
+struct ListLintRulesResponse {
+    rules: Vec<LintRuleSummary>,
+}
+
+#[actix_web::test]
+async fn it_warns_about_and_denies_linted_configurations() {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::web::Data;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::kafka::metadata::manager::{MetadataConsumerFactory, MetadataManager};
+    use crate::subscriptions::store::{MemorySubscriptionStore, SubscriptionStore};
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Data::new(MetadataManager::with_factory(cs.clone(), factory));
+    let policy = LintPolicy::deny(&["sasl-over-plaintext".to_string()]).unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs.clone()))
+            .app_data(Data::new(ss.clone()))
+            .app_data(manager)
+            .app_data(Data::new(policy))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let create = |mut config: Value| {
+        config["bootstrap.servers"] = json!("localhost:9092");
+        test::TestRequest::post()
+            .uri("/api/v1/clusters")
+            .set_json(json!({ "kind": "Kafka", "name": "c", "config": config }))
+            .to_request()
+    };
+
+    // Warnings are returned alongside the created cluster, and again on demand.
+    let body: Value =
+        test::call_and_read_body_json(&app, create(json!({ "metadata.poll.interval.ms": "1000" })))
+            .await;
+    assert_eq!(
+        body["warnings"][0]["rule"],
+        "poll-interval-below-fetch-timeout"
+    );
+    let id = body["id"].clone();
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/clusters/{}/lint", id))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["warnings"][0]["fix"].is_string());
+
+    // Denied rules fail the request instead.
+    let config = json!({
+        "security.protocol": "SASL_PLAINTEXT",
+        "sasl.mechanism": "PLAIN",
+        "sasl.username": "seekr",
+        "sasl.password": "hunter2",
+    });
+    let res = test::call_service(&app, create(config)).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/subscriptions?force=true")
+        .set_json(json!({
+            "cluster_id": id,
+            "topic_name": "orders",
+            "config": { "bootstrap.servers": "localhost:9092" },
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body["warnings"][0]["rule"],
+        "cluster-setting-on-subscription"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/lint-rules")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let denied = body["rules"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| r["denied"] == true)
+        .map(|r| r["name"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(denied, vec![json!("sasl-over-plaintext")]);
+}
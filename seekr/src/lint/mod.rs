@@ -0,0 +1,188 @@
+use std::collections::BTreeSet;
+use std::future::{ready, Ready};
+
+use actix_web::dev::Payload;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpRequest};
+use serde::Serialize;
+
+use crate::clusters::cluster::Cluster;
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::subscriptions::subscription::Subscription;
+
+pub mod endpoints;
+pub mod rules;
+
+/// How likely a configuration a rule flags is a mistake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// A setting without effect, harmless but misleading.
+    Info,
+
+    /// Works, though hardly ever as intended.
+    Warning,
+
+    /// Works against what the configuration is meant to do, e.g. exposes credentials.
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Cluster,
+    Subscription,
+}
+
+/// What rules are checked against: a cluster, or a subscription along with
+/// its cluster when it's known.
+#[derive(Clone, Copy)]
+pub enum Subject<'a> {
+    Cluster(&'a Cluster),
+    Subscription(&'a Subscription, Option<&'a Cluster>),
+}
+
+impl Subject<'_> {
+    fn target(&self) -> Target {
+        match self {
+            Subject::Cluster(_) => Target::Cluster,
+            Subject::Subscription(..) => Target::Subscription,
+        }
+    }
+}
+
+/// What a rule found, and how to fix it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub message: String,
+    pub fix: String,
+}
+
+/// A configuration that's legal, but almost certainly wrong.
+pub struct Rule {
+    pub name: &'static str,
+    pub targets: &'static [Target],
+    pub severity: Severity,
+
+    /// What the rule checks and why, as listed to users.
+    pub description: &'static str,
+
+    /// Checks the configuration, it never does I/O so linting is deterministic.
+    pub check: fn(&Subject) -> Option<Finding>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LintWarning {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub fix: String,
+}
+
+pub fn rule(name: &str) -> Option<&'static Rule> {
+    rules::RULES.iter().find(|r| r.name == name)
+}
+
+/// The warnings of every rule of the subject's kind, in the order rules are listed.
+pub fn lint(subject: Subject) -> Vec<LintWarning> {
+    rules::RULES
+        .iter()
+        .filter(|r| r.targets.contains(&subject.target()))
+        .filter_map(|r| {
+            let finding = (r.check)(&subject)?;
+            Some(LintWarning {
+                rule: r.name,
+                severity: r.severity,
+                message: finding.message,
+                fix: finding.fix,
+            })
+        })
+        .collect()
+}
+
+/// The warnings of the subscription, checked against its cluster when it exists.
+pub async fn lint_subscription(
+    cs: &(dyn ClusterStore + Send + Sync),
+    subscription: &Subscription,
+) -> Result<Vec<LintWarning>, AnyError> {
+    let cluster = cs.get(subscription.cluster_id).await?;
+    Ok(lint(Subject::Subscription(subscription, cluster.as_ref())))
+}
+
+/// The rules upgraded to validation failures with `--deny-lints`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LintPolicy {
+    deny: BTreeSet<&'static str>,
+}
+
+impl LintPolicy {
+    pub fn deny(names: &[String]) -> Result<Self, String> {
+        let deny = names
+            .iter()
+            .map(|n| {
+                rule(n.trim())
+                    .map(|r| r.name)
+                    .ok_or_else(|| format!("unknown lint rule '{}'", n))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { deny })
+    }
+
+    pub fn is_denied(&self, rule: &str) -> bool {
+        self.deny.contains(rule)
+    }
+
+    /// Fails with the messages of the warnings raised by denied rules, if any.
+    pub fn check(&self, warnings: &[LintWarning]) -> Result<(), String> {
+        let denied = warnings
+            .iter()
+            .filter(|w| self.is_denied(w.rule))
+            .map(|w| format!("{}: {}", w.rule, w.message))
+            .collect::<Vec<_>>();
+        match denied.is_empty() {
+            true => Ok(()),
+            false => Err(denied.join("; ")),
+        }
+    }
+}
+
+/// The policy registered with the server, or one denying nothing.
+impl FromRequest for LintPolicy {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let policy = req.app_data::<Data<LintPolicy>>();
+        ready(Ok(policy.map(|p| p.get_ref().clone()).unwrap_or_default()))
+    }
+}
+
+#[test]
+fn it_denies_only_known_rules() {
+    let policy = LintPolicy::deny(&["sasl-over-plaintext".to_string()]).unwrap();
+    assert!(policy.is_denied("sasl-over-plaintext"));
+    assert!(!policy.is_denied("retention-without-effect"));
+
+    let err = LintPolicy::deny(&["sasl-over-plain".to_string()]).unwrap_err();
+    assert_eq!(err, "unknown lint rule 'sasl-over-plain'");
+
+    let warning = |rule| LintWarning {
+        rule,
+        severity: Severity::Error,
+        message: "m".to_string(),
+        fix: "f".to_string(),
+    };
+    assert!(policy.check(&[warning("retention-without-effect")]).is_ok());
+    assert_eq!(
+        policy.check(&[warning("sasl-over-plaintext")]),
+        Err("sasl-over-plaintext: m".to_string())
+    );
+}
+
+#[test]
+fn it_lists_rules_with_unique_names() {
+    let names = rules::RULES.iter().map(|r| r.name).collect::<BTreeSet<_>>();
+    assert_eq!(names.len(), rules::RULES.len());
+    assert!(rules::RULES.iter().all(|r| !r.targets.is_empty()));
+}
@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+
+use crate::clusters::cluster::Cluster;
+use crate::debug::trace::Stage;
+use crate::kafka::config;
+use crate::kafka::metadata::classify::{Classifier, TopicCategory};
+use crate::kafka::metadata::consumer::FETCH_METADATA_TIMEOUT_MS;
+use crate::kafka::streams::stages::budget_key;
+use crate::subscriptions::subscription::Subscription;
+
+use super::{Finding, Rule, Severity, Subject, Target};
+
+const CLUSTER: &[Target] = &[Target::Cluster];
+const SUBSCRIPTION: &[Target] = &[Target::Subscription];
+const BOTH: &[Target] = &[Target::Cluster, Target::Subscription];
+
+/// Every rule, in the order warnings are reported.
+pub static RULES: &[Rule] = &[
+    Rule {
+        name: "unparsable-number",
+        targets: BOTH,
+        severity: Severity::Warning,
+        description: "A numeric setting that doesn't parse as a number is ignored for its default, without an error.",
+        check: unparsable_number,
+    },
+    Rule {
+        name: "poll-interval-below-fetch-timeout",
+        targets: CLUSTER,
+        severity: Severity::Warning,
+        description: "A metadata.poll.interval.ms shorter than the 15s metadata fetch timeout lets a slow poll run into the next one, so polls back up behind unresponsive brokers.",
+        check: poll_interval_below_fetch_timeout,
+    },
+    Rule {
+        name: "sasl-over-plaintext",
+        targets: CLUSTER,
+        severity: Severity::Error,
+        description: "SASL over a PLAINTEXT security.protocol sends credentials and records unencrypted.",
+        check: sasl_over_plaintext,
+    },
+    Rule {
+        name: "produce-topics-while-disabled",
+        targets: CLUSTER,
+        severity: Severity::Info,
+        description: "produce.topics.regex has no effect unless produce.enabled is true.",
+        check: produce_topics_while_disabled,
+    },
+    Rule {
+        name: "hot-partitions-without-throughput",
+        targets: CLUSTER,
+        severity: Severity::Info,
+        description: "hot.partition.* settings have no effect unless throughput.enabled is true.",
+        check: hot_partitions_without_throughput,
+    },
+    Rule {
+        name: "cluster-setting-on-subscription",
+        targets: SUBSCRIPTION,
+        severity: Severity::Warning,
+        description: "Cluster settings, e.g. bootstrap.servers or auth.provider, are ignored on subscriptions; workers connect with their cluster's.",
+        check: cluster_setting_on_subscription,
+    },
+    Rule {
+        name: "subscribed-non-user-topic",
+        targets: SUBSCRIPTION,
+        severity: Severity::Warning,
+        description: "Internal topics (named __*) and the cluster's metadata.system.topics hold Kafka's or tools' own records, hardly ever worth indexing.",
+        check: subscribed_non_user_topic,
+    },
+    Rule {
+        name: "changefeed-payload-without-changefeed",
+        targets: SUBSCRIPTION,
+        severity: Severity::Warning,
+        description: "changefeed.include.payload has no effect unless changefeed.enabled is true.",
+        check: changefeed_payload_without_changefeed,
+    },
+    Rule {
+        name: "retention-without-effect",
+        targets: SUBSCRIPTION,
+        severity: Severity::Warning,
+        description: "retention.ms only trims the changefeed and drops index shards, without changefeed.enabled or index.shard.period nothing is ever removed.",
+        check: retention_without_effect,
+    },
+    Rule {
+        name: "liveness-settings-while-disabled",
+        targets: SUBSCRIPTION,
+        severity: Severity::Info,
+        description: "liveness.* settings have no effect while liveness.enabled is false.",
+        check: liveness_settings_while_disabled,
+    },
+    Rule {
+        name: "budget-settings-while-disabled",
+        targets: SUBSCRIPTION,
+        severity: Severity::Info,
+        description: "budget.* settings have no effect while budget.enabled is false.",
+        check: budget_settings_while_disabled,
+    },
+];
+
+/// Settings read from the cluster only.
+const CLUSTER_SETTINGS: &[&str] = &[
+    config::BOOTSTRAP_SERVERS,
+    config::AUTH_PROVIDER,
+    config::AWS_REGION,
+    config::AWS_ROLE_ARN,
+    config::METADATA_POLL_INTERVAL,
+    config::METADATA_POLL_ADAPTIVE,
+    config::METADATA_POLL_MAX_INTERVAL,
+    config::METADATA_POLL_MAX_BACKOFF,
+    config::METADATA_PRIORITY,
+    config::METADATA_SYSTEM_TOPICS,
+    config::METRICS_POLL_INTERVAL,
+    config::STORAGE_POLL_INTERVAL,
+    config::THROUGHPUT_ENABLED,
+    config::HOT_PARTITION_THRESHOLD,
+    config::HOT_PARTITION_SAMPLES,
+    config::PRODUCE_ENABLED,
+    config::PRODUCE_TOPICS_REGEX,
+    config::PRODUCE_MAX_PAYLOAD_BYTES,
+];
+
+const CLUSTER_NUMBERS: &[&str] = &[
+    config::METADATA_POLL_INTERVAL,
+    config::METADATA_POLL_MAX_INTERVAL,
+    config::METADATA_POLL_MAX_BACKOFF,
+    config::METRICS_POLL_INTERVAL,
+    config::STORAGE_POLL_INTERVAL,
+    config::HOT_PARTITION_SAMPLES,
+    config::PRODUCE_MAX_PAYLOAD_BYTES,
+];
+
+const SUBSCRIPTION_NUMBERS: &[&str] = &[
+    config::LIVENESS_STALL_THRESHOLD,
+    config::LIVENESS_MAX_RECREATIONS,
+    config::RETENTION,
+    config::BUDGET_WINDOW,
+    config::BUDGET_SUSTAINED_WINDOWS,
+    config::COMMANDS_TIMEOUT,
+];
+
+fn is_true(config: &HashMap<String, String>, key: &str) -> bool {
+    config.get(key).is_some_and(|v| v == "true")
+}
+
+fn is_false(config: &HashMap<String, String>, key: &str) -> bool {
+    config.get(key).is_some_and(|v| v == "false")
+}
+
+/// The keys of the config starting with `prefix`, sorted so findings read the same every time.
+fn keys_with_prefix(config: &HashMap<String, String>, prefix: &str, except: &str) -> Vec<String> {
+    let mut keys = config
+        .keys()
+        .filter(|k| k.starts_with(prefix) && k.as_str() != except)
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+fn unparsable_number(subject: &Subject) -> Option<Finding> {
+    let (config, mut numbers) = match subject {
+        Subject::Cluster(c) => (&c.config, CLUSTER_NUMBERS.to_vec()),
+        Subject::Subscription(s, _) => (&s.config, SUBSCRIPTION_NUMBERS.to_vec()),
+    };
+    let budgets = Stage::ALL.map(budget_key);
+    if let Subject::Subscription(..) = subject {
+        numbers.extend(budgets.iter().map(String::as_str));
+    }
+
+    let mut bad = numbers
+        .into_iter()
+        .filter(|k| config.get(*k).is_some_and(|v| v.parse::<u64>().is_err()))
+        .map(|k| format!("{} = '{}'", k, config[k]))
+        .collect::<Vec<_>>();
+    if let Subject::Cluster(_) = subject {
+        let threshold = config::HOT_PARTITION_THRESHOLD;
+        if let Some(v) = config.get(threshold).filter(|v| v.parse::<f64>().is_err()) {
+            bad.push(format!("{} = '{}'", threshold, v));
+        }
+    }
+    bad.sort();
+
+    (!bad.is_empty()).then(|| Finding {
+        message: format!(
+            "Not a number, the default is used instead: {}",
+            bad.join(", ")
+        ),
+        fix: "Set a plain number, without units, or remove the setting".to_string(),
+    })
+}
+
+fn poll_interval_below_fetch_timeout(subject: &Subject) -> Option<Finding> {
+    let Subject::Cluster(cluster) = subject else {
+        return None;
+    };
+    let timeout = FETCH_METADATA_TIMEOUT_MS.as_millis() as u64;
+    let interval = cluster
+        .config
+        .get(config::METADATA_POLL_INTERVAL)?
+        .parse::<u64>()
+        .ok()
+        .filter(|i| *i < timeout)?;
+
+    Some(Finding {
+        message: format!(
+            "{} of {}ms is shorter than the {}ms metadata fetch timeout",
+            config::METADATA_POLL_INTERVAL,
+            interval,
+            timeout
+        ),
+        fix: format!(
+            "Set {} to at least {}",
+            config::METADATA_POLL_INTERVAL,
+            timeout
+        ),
+    })
+}
+
+fn sasl_over_plaintext(subject: &Subject) -> Option<Finding> {
+    let Subject::Cluster(cluster) = subject else {
+        return None;
+    };
+    let protocol = cluster.config.get("security.protocol")?.to_uppercase();
+    let sasl = !keys_with_prefix(&cluster.config, "sasl.", "").is_empty();
+    let plaintext = protocol == "SASL_PLAINTEXT" || (protocol == "PLAINTEXT" && sasl);
+
+    plaintext.then(|| Finding {
+        message: format!(
+            "security.protocol {} authenticates with SASL over an unencrypted connection",
+            protocol
+        ),
+        fix: "Set security.protocol to SASL_SSL".to_string(),
+    })
+}
+
+fn produce_topics_while_disabled(subject: &Subject) -> Option<Finding> {
+    let Subject::Cluster(cluster) = subject else {
+        return None;
+    };
+    let config = &cluster.config;
+    (config.contains_key(config::PRODUCE_TOPICS_REGEX) && !is_true(config, config::PRODUCE_ENABLED))
+        .then(|| Finding {
+            message: format!(
+                "{} is set, but producing isn't enabled",
+                config::PRODUCE_TOPICS_REGEX
+            ),
+            fix: format!(
+                "Set {} to true, or remove {}",
+                config::PRODUCE_ENABLED,
+                config::PRODUCE_TOPICS_REGEX
+            ),
+        })
+}
+
+fn hot_partitions_without_throughput(subject: &Subject) -> Option<Finding> {
+    let Subject::Cluster(cluster) = subject else {
+        return None;
+    };
+    let config = &cluster.config;
+    let keys = keys_with_prefix(config, "hot.partition.", "");
+    (!keys.is_empty() && !is_true(config, config::THROUGHPUT_ENABLED)).then(|| Finding {
+        message: format!("{} set, but throughput isn't tracked", keys.join(", ")),
+        fix: format!(
+            "Set {} to true, or remove {}",
+            config::THROUGHPUT_ENABLED,
+            keys.join(", ")
+        ),
+    })
+}
+
+fn cluster_setting_on_subscription(subject: &Subject) -> Option<Finding> {
+    let Subject::Subscription(subscription, _) = subject else {
+        return None;
+    };
+    let mut keys = subscription
+        .config
+        .keys()
+        .filter(|k| {
+            CLUSTER_SETTINGS.contains(&k.as_str())
+                || k.starts_with("security.")
+                || k.starts_with("sasl.")
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    (!keys.is_empty()).then(|| Finding {
+        message: format!("{} only apply to clusters", keys.join(", ")),
+        fix: format!(
+            "Move {} to the cluster {}",
+            keys.join(", "),
+            subscription.cluster_id
+        ),
+    })
+}
+
+fn subscribed_non_user_topic(subject: &Subject) -> Option<Finding> {
+    let Subject::Subscription(subscription, cluster) = subject else {
+        return None;
+    };
+    let topic = &subscription.topic_name;
+    let classifier = cluster.map(Classifier::from).unwrap_or_default();
+    let kind = match classifier.topic(topic) {
+        TopicCategory::Internal => "an internal Kafka topic",
+        TopicCategory::System => "listed in the cluster's metadata.system.topics",
+        _ => return None,
+    };
+
+    Some(Finding {
+        message: format!("Topic '{}' is {}", topic, kind),
+        fix: "Subscribe to the topic your applications produce to".to_string(),
+    })
+}
+
+fn changefeed_payload_without_changefeed(subject: &Subject) -> Option<Finding> {
+    let Subject::Subscription(subscription, _) = subject else {
+        return None;
+    };
+    let config = &subscription.config;
+    (is_true(config, config::CHANGEFEED_INCLUDE_PAYLOAD)
+        && !is_true(config, config::CHANGEFEED_ENABLED))
+    .then(|| Finding {
+        message: format!(
+            "{} is true, but the changefeed isn't enabled",
+            config::CHANGEFEED_INCLUDE_PAYLOAD
+        ),
+        fix: format!("Set {} to true", config::CHANGEFEED_ENABLED),
+    })
+}
+
+fn retention_without_effect(subject: &Subject) -> Option<Finding> {
+    let Subject::Subscription(subscription, _) = subject else {
+        return None;
+    };
+    let config = &subscription.config;
+    (config.contains_key(config::RETENTION)
+        && !is_true(config, config::CHANGEFEED_ENABLED)
+        && !config.contains_key(config::INDEX_SHARD_PERIOD))
+    .then(|| Finding {
+        message: format!(
+            "{} is set, but there's neither a changefeed nor index shards to trim",
+            config::RETENTION
+        ),
+        fix: format!(
+            "Set {} to monthly or weekly to drop documents past retention, or {} to true",
+            config::INDEX_SHARD_PERIOD,
+            config::CHANGEFEED_ENABLED
+        ),
+    })
+}
+
+fn liveness_settings_while_disabled(subject: &Subject) -> Option<Finding> {
+    let Subject::Subscription(subscription, _) = subject else {
+        return None;
+    };
+    settings_while_disabled(&subscription.config, "liveness.", config::LIVENESS_ENABLED)
+}
+
+fn budget_settings_while_disabled(subject: &Subject) -> Option<Finding> {
+    let Subject::Subscription(subscription, _) = subject else {
+        return None;
+    };
+    settings_while_disabled(&subscription.config, "budget.", config::BUDGET_ENABLED)
+}
+
+/// Settings under `prefix`, while the feature is turned off by its `enabled` key.
+fn settings_while_disabled(
+    config: &HashMap<String, String>,
+    prefix: &str,
+    enabled: &str,
+) -> Option<Finding> {
+    let keys = keys_with_prefix(config, prefix, enabled);
+    (!keys.is_empty() && is_false(config, enabled)).then(|| Finding {
+        message: format!("{} set, but {} is false", keys.join(", "), enabled),
+        fix: format!("Remove {}, or {}", keys.join(", "), enabled),
+    })
+}
+
+#[cfg(test)]
+fn cluster(config: &[(&str, &str)]) -> Cluster {
+    use crate::clusters::cluster::Kind;
+
+    let config = config
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Cluster::new(
+        Some(crate::ids::ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        config,
+    )
+}
+
+#[cfg(test)]
+fn subscription(topic: &str, config: &[(&str, &str)]) -> Subscription {
+    let config = config
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Subscription::new(
+        Some(crate::ids::SubscriptionId(2)),
+        crate::ids::ClusterId(1),
+        topic.to_string(),
+        config,
+    )
+}
+
+#[test]
+fn it_flags_unparsable_numbers() {
+    let c = cluster(&[
+        (config::METADATA_POLL_INTERVAL, "30s"),
+        (config::HOT_PARTITION_THRESHOLD, "2.5"),
+        (config::STORAGE_POLL_INTERVAL, "60000"),
+    ]);
+    let finding = unparsable_number(&Subject::Cluster(&c)).unwrap();
+    assert_eq!(
+        finding.message,
+        "Not a number, the default is used instead: metadata.poll.interval.ms = '30s'"
+    );
+
+    let s = subscription(
+        "orders",
+        &[("budget.sink.ms", "fast"), (config::RETENTION, "1d")],
+    );
+    let finding = unparsable_number(&Subject::Subscription(&s, None)).unwrap();
+    assert_eq!(
+        finding.message,
+        "Not a number, the default is used instead: budget.sink.ms = 'fast', retention.ms = '1d'"
+    );
+
+    let s = subscription("orders", &[(config::RETENTION, "86400000")]);
+    assert_eq!(unparsable_number(&Subject::Subscription(&s, None)), None);
+}
+
+#[test]
+fn it_flags_poll_intervals_below_the_fetch_timeout() {
+    let c = cluster(&[(config::METADATA_POLL_INTERVAL, "5000")]);
+    let finding = poll_interval_below_fetch_timeout(&Subject::Cluster(&c)).unwrap();
+    assert_eq!(
+        finding.fix,
+        "Set metadata.poll.interval.ms to at least 15000"
+    );
+
+    for interval in ["15000", "30s"] {
+        let c = cluster(&[(config::METADATA_POLL_INTERVAL, interval)]);
+        assert_eq!(
+            poll_interval_below_fetch_timeout(&Subject::Cluster(&c)),
+            None
+        );
+    }
+    assert_eq!(
+        poll_interval_below_fetch_timeout(&Subject::Cluster(&cluster(&[]))),
+        None
+    );
+}
+
+#[test]
+fn it_flags_sasl_over_plaintext() {
+    let flagged = [
+        vec![("security.protocol", "SASL_PLAINTEXT")],
+        vec![
+            ("security.protocol", "plaintext"),
+            ("sasl.mechanism", "PLAIN"),
+        ],
+    ];
+    for config in flagged {
+        let c = cluster(&config);
+        assert!(
+            sasl_over_plaintext(&Subject::Cluster(&c)).is_some(),
+            "{:?}",
+            config
+        );
+    }
+
+    let fine = [
+        vec![
+            ("security.protocol", "SASL_SSL"),
+            ("sasl.mechanism", "PLAIN"),
+        ],
+        vec![("security.protocol", "PLAINTEXT")],
+        vec![("sasl.mechanism", "PLAIN")],
+    ];
+    for config in fine {
+        let c = cluster(&config);
+        assert_eq!(
+            sasl_over_plaintext(&Subject::Cluster(&c)),
+            None,
+            "{:?}",
+            config
+        );
+    }
+}
+
+#[test]
+fn it_flags_produce_topics_while_disabled() {
+    let c = cluster(&[(config::PRODUCE_TOPICS_REGEX, "orders")]);
+    assert!(produce_topics_while_disabled(&Subject::Cluster(&c)).is_some());
+
+    let c = cluster(&[
+        (config::PRODUCE_TOPICS_REGEX, "orders"),
+        (config::PRODUCE_ENABLED, "true"),
+    ]);
+    assert_eq!(produce_topics_while_disabled(&Subject::Cluster(&c)), None);
+}
+
+#[test]
+fn it_flags_hot_partitions_without_throughput() {
+    let c = cluster(&[
+        (config::HOT_PARTITION_SAMPLES, "5"),
+        (config::HOT_PARTITION_THRESHOLD, "3"),
+    ]);
+    let finding = hot_partitions_without_throughput(&Subject::Cluster(&c)).unwrap();
+    assert_eq!(
+        finding.message,
+        "hot.partition.samples, hot.partition.threshold set, but throughput isn't tracked"
+    );
+
+    let c = cluster(&[
+        (config::HOT_PARTITION_SAMPLES, "5"),
+        (config::THROUGHPUT_ENABLED, "true"),
+    ]);
+    assert_eq!(
+        hot_partitions_without_throughput(&Subject::Cluster(&c)),
+        None
+    );
+}
+
+#[test]
+fn it_flags_cluster_settings_on_subscriptions() {
+    let s = subscription(
+        "orders",
+        &[
+            (config::BOOTSTRAP_SERVERS, "kafka:9092"),
+            ("sasl.username", "u"),
+            (config::CHANGEFEED_ENABLED, "true"),
+        ],
+    );
+    let finding = cluster_setting_on_subscription(&Subject::Subscription(&s, None)).unwrap();
+    assert_eq!(
+        finding.message,
+        "bootstrap.servers, sasl.username only apply to clusters"
+    );
+    assert_eq!(
+        finding.fix,
+        "Move bootstrap.servers, sasl.username to the cluster 1"
+    );
+
+    let s = subscription("orders", &[(config::CHANGEFEED_ENABLED, "true")]);
+    assert_eq!(
+        cluster_setting_on_subscription(&Subject::Subscription(&s, None)),
+        None
+    );
+}
+
+#[test]
+fn it_flags_subscriptions_to_non_user_topics() {
+    let c = cluster(&[(config::METADATA_SYSTEM_TOPICS, "audit-sink")]);
+
+    let s = subscription("__consumer_offsets", &[]);
+    let finding = subscribed_non_user_topic(&Subject::Subscription(&s, None)).unwrap();
+    assert_eq!(
+        finding.message,
+        "Topic '__consumer_offsets' is an internal Kafka topic"
+    );
+
+    // System topics are the cluster's to define.
+    let s = subscription("audit-sink", &[]);
+    assert!(subscribed_non_user_topic(&Subject::Subscription(&s, Some(&c))).is_some());
+    assert_eq!(
+        subscribed_non_user_topic(&Subject::Subscription(&s, None)),
+        None
+    );
+
+    let s = subscription("orders", &[]);
+    assert_eq!(
+        subscribed_non_user_topic(&Subject::Subscription(&s, Some(&c))),
+        None
+    );
+}
+
+#[test]
+fn it_flags_changefeed_payloads_without_changefeed() {
+    let s = subscription("orders", &[(config::CHANGEFEED_INCLUDE_PAYLOAD, "true")]);
+    assert!(changefeed_payload_without_changefeed(&Subject::Subscription(&s, None)).is_some());
+
+    let s = subscription(
+        "orders",
+        &[
+            (config::CHANGEFEED_INCLUDE_PAYLOAD, "true"),
+            (config::CHANGEFEED_ENABLED, "true"),
+        ],
+    );
+    assert_eq!(
+        changefeed_payload_without_changefeed(&Subject::Subscription(&s, None)),
+        None
+    );
+}
+
+#[test]
+fn it_flags_retention_without_effect() {
+    let s = subscription("orders", &[(config::RETENTION, "86400000")]);
+    assert!(retention_without_effect(&Subject::Subscription(&s, None)).is_some());
+
+    for enabling in [
+        (config::CHANGEFEED_ENABLED, "true"),
+        (config::INDEX_SHARD_PERIOD, "monthly"),
+    ] {
+        let s = subscription("orders", &[(config::RETENTION, "86400000"), enabling]);
+        assert_eq!(
+            retention_without_effect(&Subject::Subscription(&s, None)),
+            None
+        );
+    }
+}
+
+#[test]
+fn it_flags_liveness_settings_while_disabled() {
+    let s = subscription(
+        "orders",
+        &[
+            (config::LIVENESS_ENABLED, "false"),
+            (config::LIVENESS_STALL_THRESHOLD, "60000"),
+        ],
+    );
+    let finding = liveness_settings_while_disabled(&Subject::Subscription(&s, None)).unwrap();
+    assert_eq!(
+        finding.message,
+        "liveness.stall.threshold.ms set, but liveness.enabled is false"
+    );
+
+    // Liveness is on by default.
+    let s = subscription("orders", &[(config::LIVENESS_STALL_THRESHOLD, "60000")]);
+    assert_eq!(
+        liveness_settings_while_disabled(&Subject::Subscription(&s, None)),
+        None
+    );
+}
+
+#[test]
+fn it_flags_budget_settings_while_disabled() {
+    let s = subscription(
+        "orders",
+        &[(config::BUDGET_ENABLED, "false"), ("budget.sink.ms", "200")],
+    );
+    assert!(budget_settings_while_disabled(&Subject::Subscription(&s, None)).is_some());
+
+    let s = subscription("orders", &[(config::BUDGET_ENABLED, "false")]);
+    assert_eq!(
+        budget_settings_while_disabled(&Subject::Subscription(&s, None)),
+        None
+    );
+}
@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpResponse};
+use serde::Deserialize;
+
+use crate::clusters::store::{ClusterNotFound, ClusterStore};
+use crate::drain::{ConnectionKind, Drain};
+use crate::errors::{ApiError, ErrorBody};
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::live::{self, LiveStreams};
+use crate::subscriptions::store::{SubscriptionNotFound, SubscriptionStore};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(stream_messages);
+}
+
+#[get("/{cluster_id}/{id}/stream")]
+async fn stream_messages(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<LiveQuery>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    streams: Data<LiveStreams>,
+    drain: Data<Drain>,
+) -> Result<HttpResponse, ApiError> {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Streaming messages of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    let subscription = ss
+        .get(cluster_id, id)
+        .await?
+        .ok_or(SubscriptionNotFound(cluster_id, id))?;
+    let cluster = cs
+        .get(cluster_id)
+        .await?
+        .ok_or(ClusterNotFound(cluster_id))?;
+
+    let Some(permit) = streams.permit(cluster_id) else {
+        let message = format!(
+            "Cluster with id '{}' already serves {} live streams, retry once one ends",
+            cluster_id,
+            streams.per_cluster()
+        );
+        return Ok(
+            HttpResponse::TooManyRequests().json(ErrorBody::new("too_many_streams", message))
+        );
+    };
+    let consumer = streams.consumer(&cluster, &subscription)?;
+
+    let filter = query.into_inner().filter.filter(|f| !f.is_empty());
+    let connection = drain.connect(ConnectionKind::Sse);
+    let events = live::events(consumer, filter, connection, permit);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events))
+}
+
+#[derive(Deserialize)]
+struct LiveQuery {
+    /// Only stream messages whose payload contains this.
+    filter: Option<String>,
+}
+
+/// Reads the scripted messages, then waits for ever like a quiet topic.
+#[cfg(test)]
+#[derive(Default)]
+struct ScriptedConsumer {
+    messages: std::sync::Mutex<std::collections::VecDeque<crate::kafka::streams::StreamsMessage>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl crate::kafka::streams::consumer::StreamsConsumer for ScriptedConsumer {
+    async fn consume(
+        &self,
+    ) -> Result<Option<crate::kafka::streams::StreamsMessage>, crate::errors::AnyError> {
+        let next = self.messages.lock().unwrap().pop_front();
+        match next {
+            Some(m) => Ok(Some(m)),
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn commit(
+        &self,
+        _: &crate::kafka::streams::StreamsMessage,
+    ) -> Result<(), crate::errors::AnyError> {
+        Ok(())
+    }
+
+    async fn fetch_end_offsets(
+        &self,
+    ) -> Result<std::collections::HashMap<i32, i64>, crate::errors::AnyError> {
+        Ok(Default::default())
+    }
+
+    async fn topic_partitions(&self) -> Result<usize, crate::errors::AnyError> {
+        Ok(1)
+    }
+}
+
+#[actix_web::test]
+async fn it_streams_filtered_messages_until_the_client_leaves() {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::changefeed::endpoints::v1::next_chunk;
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::streams::service::ConsumerFactory;
+    use crate::kafka::streams::StreamsMessage;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::Subscription;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let subscription = Subscription::new(
+        Some(SubscriptionId(1)),
+        ClusterId(1),
+        "orders".to_string(),
+        HashMap::new(),
+    );
+    ss.put(subscription).await.unwrap();
+
+    let messages = ["tenant-7", "tenant-42", "tenant-42"]
+        .iter()
+        .enumerate()
+        .map(|(offset, tenant)| StreamsMessage {
+            payload: Some(format!("{{\"tenant\":\"{}\"}}", tenant)),
+            payload_json: None,
+            topic: "orders".to_string(),
+            key: None,
+            headers: HashMap::new(),
+            partition: 0,
+            offset: offset as i64,
+            timestamp: Some(1_700_000_000_000),
+        })
+        .collect();
+    let consumer = Arc::new(ScriptedConsumer {
+        messages: std::sync::Mutex::new(messages),
+    });
+    let shared = consumer.clone();
+    let consumers: ConsumerFactory = Arc::new(move |_, _| Ok(shared.clone()));
+    let streams = Data::new(LiveStreams::new(1).with_consumers(consumers));
+    let drain = Data::new(Drain::default());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(ss))
+            .app_data(streams)
+            .app_data(drain.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+    let stream = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    let res = test::call_service(&app, stream("/api/v1/subscriptions/1/1/stream?filter=42")).await;
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = res.map_into_boxed_body().into_body();
+    for offset in [1, 2] {
+        let event = next_chunk(&mut body).await.unwrap();
+        let data = event.strip_prefix("data: ").unwrap();
+        let message: serde_json::Value = serde_json::from_str(data.trim()).unwrap();
+        assert_eq!(message["offset"], offset);
+        assert_eq!(message["payload"], "{\"tenant\":\"tenant-42\"}");
+    }
+    assert_eq!(drain.connections()[&ConnectionKind::Sse], 1);
+
+    // The cluster serves a single stream, until the client leaves.
+    let res = test::call_service(&app, stream("/api/v1/subscriptions/1/1/stream")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let error: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(error["code"], "too_many_streams");
+
+    drop(body);
+    assert_eq!(Arc::strong_count(&consumer), 2);
+    assert_eq!(drain.connections()[&ConnectionKind::Sse], 0);
+
+    // Streams end once the server drains, dropping their consumer.
+    let res = test::call_service(&app, stream("/api/v1/subscriptions/1/1/stream")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut body = res.map_into_boxed_body().into_body();
+    let shutdown = tokio::spawn(async move { drain.shutdown().await });
+    let event = next_chunk(&mut body).await.unwrap();
+    assert!(event.starts_with("event: server_shutting_down\n"));
+    assert_eq!(next_chunk(&mut body).await, None);
+    assert!(!shutdown.await.unwrap());
+    assert_eq!(Arc::strong_count(&consumer), 2);
+
+    let res = test::call_service(&app, stream("/api/v1/subscriptions/1/9/stream")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
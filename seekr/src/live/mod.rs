@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::api::sse::event;
+use crate::changefeed::stream::SHUTTING_DOWN;
+use crate::clusters::cluster::Cluster;
+use crate::drain::Connection;
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::streams::consumer::StreamsConsumer;
+use crate::kafka::streams::service::{live_consumers, ConsumerFactory};
+use crate::kafka::streams::StreamsMessage;
+use crate::subscriptions::subscription::Subscription;
+
+pub mod endpoints;
+
+/// Live streams each cluster serves at once, unless configured otherwise.
+pub const DEFAULT_STREAMS_PER_CLUSTER: usize = 10;
+
+/// Opens live streams of subscriptions' topics, each with a consumer of its
+/// own, and bounds how many each cluster serves at once.
+pub struct LiveStreams {
+    consumers: ConsumerFactory,
+    per_cluster: usize,
+    permits: Mutex<HashMap<ClusterId, Arc<Semaphore>>>,
+}
+
+impl Default for LiveStreams {
+    fn default() -> Self {
+        Self::new(DEFAULT_STREAMS_PER_CLUSTER)
+    }
+}
+
+impl LiveStreams {
+    pub fn new(per_cluster: usize) -> Self {
+        Self {
+            consumers: live_consumers(),
+            per_cluster,
+            permits: Default::default(),
+        }
+    }
+
+    pub fn with_consumers(mut self, consumers: ConsumerFactory) -> Self {
+        self.consumers = consumers;
+        self
+    }
+
+    /// A permit to stream from the cluster, held until the stream ends, or
+    /// `None` when the cluster already serves as many as allowed.
+    pub fn permit(&self, cluster_id: ClusterId) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self
+            .permits
+            .lock()
+            .unwrap()
+            .entry(cluster_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_cluster)))
+            .clone();
+        semaphore.try_acquire_owned().ok()
+    }
+
+    pub fn per_cluster(&self) -> usize {
+        self.per_cluster
+    }
+
+    pub fn consumer(
+        &self,
+        cluster: &Cluster,
+        subscription: &Subscription,
+    ) -> Result<Arc<dyn StreamsConsumer + Send + Sync>, AnyError> {
+        (self.consumers)(cluster, subscription)
+    }
+}
+
+#[derive(Serialize)]
+struct Failure {
+    code: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ShuttingDown {}
+
+enum State {
+    Open {
+        consumer: Arc<dyn StreamsConsumer + Send + Sync>,
+        connection: Connection,
+        permit: OwnedSemaphorePermit,
+    },
+    Done,
+}
+
+/// Whether the message's payload contains the filter, every message without one.
+fn matches(message: &StreamsMessage, filter: Option<&str>) -> bool {
+    match filter {
+        None => true,
+        Some(filter) => message
+            .payload
+            .as_deref()
+            .is_some_and(|p| p.contains(filter)),
+    }
+}
+
+/// Stream the messages read by the consumer as server-sent events, one
+/// `data` event each, those whose payload doesn't contain `filter` skipped.
+///
+/// The consumer and the permit are dropped with the stream, when the client
+/// disconnects, a read fails with an `error` event, or the server starts
+/// draining with a final `server_shutting_down` event.
+pub fn events(
+    consumer: Arc<dyn StreamsConsumer + Send + Sync>,
+    filter: Option<String>,
+    connection: Connection,
+    permit: OwnedSemaphorePermit,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let state = State::Open {
+        consumer,
+        connection,
+        permit,
+    };
+
+    futures::stream::unfold(state, move |state| {
+        let filter = filter.clone();
+        async move {
+            let State::Open {
+                consumer,
+                mut connection,
+                permit,
+            } = state
+            else {
+                return None;
+            };
+
+            loop {
+                tokio::select! {
+                    read = consumer.consume() => match read {
+                        Ok(Some(message)) if matches(&message, filter.as_deref()) => {
+                            let state = State::Open { consumer, connection, permit };
+                            return Some((event(None, &message), state));
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            let failure = Failure {
+                                code: "kafka",
+                                message: e.to_string(),
+                            };
+                            return Some((event(Some("error"), &failure), State::Done));
+                        }
+                    },
+                    _ = connection.draining() => {
+                        return Some((event(Some(SHUTTING_DOWN), &ShuttingDown {}), State::Done));
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[test]
+fn it_bounds_the_streams_of_each_cluster() {
+    let live = LiveStreams::new(2);
+
+    let first = live.permit(ClusterId(1)).unwrap();
+    let _second = live.permit(ClusterId(1)).unwrap();
+    assert!(live.permit(ClusterId(1)).is_none());
+    assert!(live.permit(ClusterId(2)).is_some());
+
+    drop(first);
+    assert!(live.permit(ClusterId(1)).is_some());
+}
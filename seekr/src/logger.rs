@@ -1,9 +1,24 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 
 use fern::colors::Color;
 use fern::colors::ColoredLevelConfig;
+use log::kv::{self, Key, Value, VisitSource};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, clap::ValueEnum, Clone)]
+use crate::logs::LogBuffer;
+use crate::request_id::RequestId;
+
+lazy_static! {
+    static ref TARGETS: TargetLevels = TargetLevels::default();
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Level {
     Warn,
     Info,
@@ -25,7 +40,174 @@ impl FromStr for Level {
     }
 }
 
-pub fn init(verbosity: &Level) {
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Warn => write!(f, "warn"),
+            Level::Info => write!(f, "info"),
+            Level::Debug => write!(f, "debug"),
+            Level::Trace => write!(f, "trace"),
+        }
+    }
+}
+
+/// How records are written to stderr.
+#[derive(Debug, clap::ValueEnum, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Colored lines for people to read.
+    #[default]
+    Text,
+
+    /// One JSON object per line, for log aggregation.
+    Json,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl From<&Level> for LevelFilter {
+    fn from(level: &Level) -> Self {
+        match level {
+            Level::Warn => LevelFilter::Warn,
+            Level::Info => LevelFilter::Info,
+            Level::Debug => LevelFilter::Debug,
+            Level::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Log levels of `seekr` targets, with optional per-target overrides.
+///
+/// Overrides raise the verbosity of a single target (and its children) without
+/// affecting the rest of the process, e.g. the logger of one streams worker.
+#[derive(Debug)]
+pub struct TargetLevels {
+    base: RwLock<LevelFilter>,
+    overrides: RwLock<HashMap<String, LevelFilter>>,
+
+    /// Set while any override exists, so records above the base level are
+    /// rejected without taking the overrides lock.
+    elevated: AtomicBool,
+}
+
+impl Default for TargetLevels {
+    fn default() -> Self {
+        Self {
+            base: RwLock::new(LevelFilter::Info),
+            overrides: RwLock::new(HashMap::new()),
+            elevated: AtomicBool::new(false),
+        }
+    }
+}
+
+impl TargetLevels {
+    pub fn set_base(&self, level: LevelFilter) {
+        *self.base.write().unwrap() = level;
+    }
+
+    pub fn elevate(&self, target: &str, level: LevelFilter) {
+        let mut overrides = self.overrides.write().unwrap();
+        overrides.insert(target.to_string(), level);
+        self.elevated.store(true, Ordering::Release);
+    }
+
+    pub fn restore(&self, target: &str) {
+        let mut overrides = self.overrides.write().unwrap();
+        overrides.remove(target);
+        self.elevated
+            .store(!overrides.is_empty(), Ordering::Release);
+    }
+
+    /// The most verbose level any target is logged at.
+    pub fn max_level(&self) -> LevelFilter {
+        let base = *self.base.read().unwrap();
+        let overrides = self.overrides.read().unwrap();
+        overrides.values().copied().fold(base, Ord::max)
+    }
+
+    pub fn override_for(&self, target: &str) -> Option<LevelFilter> {
+        self.overrides.read().unwrap().get(target).copied()
+    }
+
+    pub fn enabled(&self, target: &str, level: log::Level) -> bool {
+        if level <= *self.base.read().unwrap() {
+            return true;
+        }
+        if !self.elevated.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let overrides = self.overrides.read().unwrap();
+        overrides.iter().any(|(t, &filter)| {
+            let matches = target == t
+                || target
+                    .strip_prefix(t.as_str())
+                    .is_some_and(|rest| rest.starts_with("::"));
+            matches && level <= filter
+        })
+    }
+}
+
+/// Raise the log level of a single target until it is restored.
+///
+/// The process-wide max level is only raised while an override exists, so
+/// disabled records stay cheap for the `log` macros the rest of the time.
+pub fn elevate(target: &str, level: &Level) {
+    TARGETS.elevate(target, level.into());
+    log::set_max_level(TARGETS.max_level());
+}
+
+/// Drop the override of a target, returning it to the process-wide level.
+pub fn restore(target: &str) {
+    TARGETS.restore(target);
+    log::set_max_level(TARGETS.max_level());
+}
+
+/// The level a target was elevated to, if any.
+pub fn override_for(target: &str) -> Option<LevelFilter> {
+    TARGETS.override_for(target)
+}
+
+/// A record as a single line of JSON, with its key/value pairs under `fields`
+/// along with the id of the request it was logged for.
+/// Newlines in the message are escaped, so multi-line messages stay on one line.
+fn json_line(message: &fmt::Arguments, record: &log::Record) -> String {
+    let mut fields = JsonFields(serde_json::Map::new());
+    let _ = record.key_values().visit(&mut fields);
+    if let Some(id) = RequestId::current() {
+        fields.0.insert("request_id".to_string(), id.0.into());
+    }
+
+    serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "level": record.level().as_str().to_lowercase(),
+        "target": record.target(),
+        "message": message.to_string(),
+        "fields": fields.0,
+    })
+    .to_string()
+}
+
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0
+            .insert(key.as_str().to_string(), value.to_string().into());
+        Ok(())
+    }
+}
+
+/// Log to stderr in the given format and, when given, to a searchable buffer
+/// of the latest records.
+pub fn init(verbosity: &Level, format: Format, buffer: Option<Arc<LogBuffer>>) {
     // std::env::set_var("RUST_LOG", "debug");
 
     let levels = ColoredLevelConfig::new()
@@ -37,27 +219,36 @@ pub fn init(verbosity: &Level) {
 
     let mut logger = fern::Dispatch::new();
 
-    logger = logger.format(move |out, message, record| {
-        out.finish(format_args!(
-            "{b}{time}{r} {l}{kind:<5}{r} {c}{name}{r} {l}{message}{r}",
-            l = format_args!("\x1B[{}m", levels.get_color(&record.level()).to_fg_str()),
-            b = format_args!("\x1B[{}m", Color::BrightBlack.to_fg_str()),
-            c = format_args!("\x1B[{}m", Color::Cyan.to_fg_str()),
-            r = "\x1B[0m",
-            time = chrono::Local::now().format("[%Y-%m-%d %H:%M:%S.%3f]"),
-            kind = record.level(),
-            name = record.target(),
-            message = message,
-        ))
-    });
-
-    logger = match verbosity {
-        Level::Warn => logger.level_for("seekr", log::LevelFilter::Warn),
-        Level::Info => logger.level_for("seekr", log::LevelFilter::Info),
-        Level::Debug => logger.level_for("seekr", log::LevelFilter::Debug),
-        _ => logger.level_for("seekr", log::LevelFilter::Trace),
+    // Only stderr is formatted, the buffer keeps the record's structured fields.
+    let console = match format {
+        Format::Text => fern::Dispatch::new().format(move |out, message, record| {
+            out.finish(format_args!(
+                "{b}{time}{r} {l}{kind:<5}{r} {c}{name}{r}{b}{request}{r} {l}{message}{r}",
+                l = format_args!("\x1B[{}m", levels.get_color(&record.level()).to_fg_str()),
+                b = format_args!("\x1B[{}m", Color::BrightBlack.to_fg_str()),
+                c = format_args!("\x1B[{}m", Color::Cyan.to_fg_str()),
+                r = "\x1B[0m",
+                time = chrono::Local::now().format("[%Y-%m-%d %H:%M:%S.%3f]"),
+                kind = record.level(),
+                name = record.target(),
+                request = RequestId::current()
+                    .map(|id| format!(" [{}]", id))
+                    .unwrap_or_default(),
+                message = message,
+            ))
+        }),
+        Format::Json => fern::Dispatch::new().format(|out, message, record| {
+            out.finish(format_args!("{}", json_line(message, record)))
+        }),
     };
 
+    // Levels of seekr targets are resolved by the filter, so targets can be
+    // elevated individually at runtime.
+    TARGETS.set_base(verbosity.into());
+    logger = logger
+        .level_for("seekr", log::LevelFilter::Trace)
+        .filter(|m| !m.target().starts_with("seekr") || TARGETS.enabled(m.target(), m.level()));
+
     logger = match verbosity {
         Level::Warn => logger.level_for("actix_web", log::LevelFilter::Warn),
         Level::Info => logger.level_for("actix_web", log::LevelFilter::Info),
@@ -77,7 +268,106 @@ pub fn init(verbosity: &Level) {
         Level::Trace => logger.level(log::LevelFilter::Trace),
     };
 
-    logger = logger.chain(std::io::stderr());
+    logger = logger.chain(console.chain(std::io::stderr()));
+    if let Some(buffer) = buffer {
+        logger = logger.chain(fern::Output::call(move |record| buffer.capture(record)));
+    }
 
     logger.apply().unwrap();
+
+    // fern sets the max level to the most verbose of its filters, which
+    // includes the `seekr` passthrough above; only overrides may raise it.
+    log::set_max_level(TARGETS.max_level());
+}
+
+#[test]
+fn it_elevates_a_single_target() {
+    let levels = TargetLevels::default();
+    levels.elevate("seekr::worker::1", LevelFilter::Trace);
+
+    let volume = |target: &str| {
+        [
+            log::Level::Error,
+            log::Level::Warn,
+            log::Level::Info,
+            log::Level::Debug,
+            log::Level::Trace,
+        ]
+        .into_iter()
+        .filter(|&l| levels.enabled(target, l))
+        .count()
+    };
+
+    assert_eq!(volume("seekr::worker::1"), 5);
+    assert_eq!(volume("seekr::worker::1::consume"), 5);
+    assert_eq!(volume("seekr::worker::2"), 3);
+    assert_eq!(volume("seekr::worker::10"), 3);
+
+    levels.restore("seekr::worker::1");
+    assert_eq!(volume("seekr::worker::1"), 3);
+}
+
+#[test]
+fn it_only_raises_the_max_level_while_elevated() {
+    let levels = TargetLevels::default();
+    assert_eq!(levels.max_level(), LevelFilter::Info);
+    assert!(!levels.enabled("seekr::worker::1", log::Level::Debug));
+
+    levels.elevate("seekr::worker::1", LevelFilter::Debug);
+    levels.elevate("seekr::worker::2", LevelFilter::Trace);
+    assert_eq!(levels.max_level(), LevelFilter::Trace);
+
+    levels.restore("seekr::worker::2");
+    assert_eq!(levels.max_level(), LevelFilter::Debug);
+    assert!(levels.enabled("seekr::worker::1", log::Level::Debug));
+
+    levels.restore("seekr::worker::1");
+    assert_eq!(levels.max_level(), LevelFilter::Info);
+    assert!(!levels.enabled("seekr::worker::1", log::Level::Debug));
+}
+
+#[test]
+fn it_formats_records_as_json_lines() {
+    let record = log::Record::builder()
+        .level(log::Level::Warn)
+        .target("seekr::kafka")
+        .key_values(&[("cluster_id", 7)])
+        .build();
+    let line = json_line(&format_args!("first\n  \"second\""), &record);
+    assert!(!line.contains('\n'), "{}", line);
+
+    let mut parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+    let timestamp = parsed["timestamp"].take();
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(timestamp.as_str().unwrap()).is_ok(),
+        "{}",
+        timestamp
+    );
+    assert_eq!(
+        parsed,
+        serde_json::json!({
+            "timestamp": null,
+            "level": "warn",
+            "target": "seekr::kafka",
+            "message": "first\n  \"second\"",
+            "fields": { "cluster_id": "7" },
+        })
+    );
+}
+
+#[test]
+fn it_tags_records_with_their_request() {
+    let record = log::Record::builder()
+        .level(log::Level::Info)
+        .target("seekr::clusters")
+        .build();
+    let line = |record: &log::Record| {
+        let line = json_line(&format_args!("Creating a new cluster"), record);
+        serde_json::from_str::<serde_json::Value>(&line).unwrap()
+    };
+
+    assert_eq!(line(&record)["fields"], serde_json::json!({}));
+    let id = RequestId("checkout-42".to_string());
+    let tagged = crate::request_id::sync_scope(id, || line(&record));
+    assert_eq!(tagged["fields"]["request_id"], "checkout-42");
 }
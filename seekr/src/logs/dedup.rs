@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Keys of the repetitive paths, the part before `:` of a key picks its interval.
+pub const METADATA_POLL: &str = "metadata-poll";
+pub const STREAMS_CONSUME: &str = "streams-consume";
+pub const SINK_RETRY: &str = "sink-retry";
+
+/// Keys whose state is kept, beyond which the least recently logged is evicted.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// How long repeats are suppressed for keys without an interval of their own.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(600);
+
+lazy_static! {
+    /// Deduplicates the poll loops of the process, see `log_dedup!`.
+    pub static ref DEDUP: DedupLogger = DedupLogger::new(DEFAULT_CAPACITY, DEFAULT_INTERVAL)
+        .with_interval(METADATA_POLL, Duration::from_secs(600))
+        .with_interval(STREAMS_CONSUME, Duration::from_secs(600))
+        .with_interval(SINK_RETRY, Duration::from_secs(60));
+}
+
+/// The key of a path, e.g. the metadata polls of one cluster.
+pub fn key(kind: &str, id: impl fmt::Display) -> String {
+    format!("{}:{}", kind, id)
+}
+
+/// Messages suppressed since a key was last logged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repeats {
+    pub count: u64,
+    pub over: Duration,
+}
+
+impl fmt::Display for Repeats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.over.as_secs();
+        match secs >= 60 {
+            true => write!(
+                f,
+                "previous message repeated {} times in the last {}m",
+                self.count,
+                secs / 60
+            ),
+            false => write!(
+                f,
+                "previous message repeated {} times in the last {}s",
+                self.count, secs
+            ),
+        }
+    }
+}
+
+/// Whether a message is logged, and the repeats suppressed before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admit {
+    Log(Option<Repeats>),
+    Suppress,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// When the key was last logged, repeats are suppressed for an interval after.
+    since: Instant,
+    suppressed: u64,
+
+    /// The tick the key was last seen at, the lowest is evicted first.
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    tick: u64,
+}
+
+/// Rate limits log messages by key: the first is logged right away, repeats
+/// within the key's interval are counted instead, and the count is reported
+/// once the interval rolls over or the key is cleared.
+///
+/// The state is bounded, a key evicted to make room for another loses its
+/// count and logs as a first occurrence again.
+#[derive(Debug)]
+pub struct DedupLogger {
+    capacity: usize,
+    interval: Duration,
+    intervals: HashMap<String, Duration>,
+    state: Mutex<State>,
+
+    /// Mirrors the number of entries, so clearing is free while nothing repeats.
+    len: AtomicUsize,
+}
+
+impl DedupLogger {
+    pub fn new(capacity: usize, interval: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            interval,
+            intervals: HashMap::new(),
+            state: Mutex::new(State::default()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Suppress repeats of the keys of a kind for their own interval.
+    pub fn with_interval(mut self, kind: &str, interval: Duration) -> Self {
+        self.intervals.insert(kind.to_string(), interval);
+        self
+    }
+
+    pub fn interval(&self, key: &str) -> Duration {
+        let kind = key.split_once(':').map_or(key, |(kind, _)| kind);
+        self.intervals.get(kind).copied().unwrap_or(self.interval)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Decide whether a message of the key is logged now.
+    pub fn admit(&self, key: &str) -> Admit {
+        let now = Instant::now();
+        let interval = self.interval(key);
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+
+        if let Some(entry) = state.entries.get_mut(key) {
+            entry.used = tick;
+            if now.duration_since(entry.since) < interval {
+                entry.suppressed += 1;
+                return Admit::Suppress;
+            }
+
+            let repeats = (entry.suppressed > 0).then(|| Repeats {
+                count: entry.suppressed,
+                over: now.duration_since(entry.since),
+            });
+            entry.since = now;
+            entry.suppressed = 0;
+            return Admit::Log(repeats);
+        }
+
+        if state.entries.len() >= self.capacity {
+            let lru = state
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.used)
+                .map(|(k, _)| k.clone());
+            if let Some(lru) = lru {
+                state.entries.remove(&lru);
+            }
+        }
+        state.entries.insert(
+            key.to_string(),
+            Entry {
+                since: now,
+                suppressed: 0,
+                used: tick,
+            },
+        );
+        self.len.store(state.entries.len(), Ordering::Relaxed);
+
+        Admit::Log(None)
+    }
+
+    /// Forget the key once its error cleared, returning the repeats suppressed
+    /// since it was last logged.
+    pub fn clear(&self, key: &str) -> Option<Repeats> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entries.remove(key)?;
+        self.len.store(state.entries.len(), Ordering::Relaxed);
+
+        (entry.suppressed > 0).then(|| Repeats {
+            count: entry.suppressed,
+            over: Instant::now().duration_since(entry.since),
+        })
+    }
+}
+
+#[cfg(test)]
+fn capture(dedup: &DedupLogger, key: &str, message: &str, lines: &mut Vec<String>) {
+    if let Admit::Log(repeats) = dedup.admit(key) {
+        lines.extend(repeats.map(|r| r.to_string()));
+        lines.push(message.to_string());
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_logs_the_first_occurrence_and_summarizes_repeats() {
+    let dedup = DedupLogger::new(8, Duration::from_secs(600));
+    let mut lines = vec![];
+
+    capture(&dedup, "metadata-poll:1", "poll failed", &mut lines);
+    assert_eq!(lines, vec!["poll failed"]);
+
+    for _ in 0..412 {
+        tokio::time::advance(Duration::from_secs(1)).await;
+        capture(&dedup, "metadata-poll:1", "poll failed", &mut lines);
+    }
+    // Other keys aren't held back by the suppressed one.
+    capture(&dedup, "metadata-poll:2", "poll failed 2", &mut lines);
+    assert_eq!(lines, vec!["poll failed", "poll failed 2"]);
+
+    tokio::time::advance(Duration::from_secs(188)).await;
+    capture(&dedup, "metadata-poll:1", "poll failed", &mut lines);
+    assert_eq!(
+        lines[2..],
+        [
+            "previous message repeated 412 times in the last 10m",
+            "poll failed"
+        ]
+    );
+
+    // The next window starts over, without repeats there is nothing to report.
+    tokio::time::advance(Duration::from_secs(600)).await;
+    capture(&dedup, "metadata-poll:1", "poll failed", &mut lines);
+    assert_eq!(lines[4..], ["poll failed"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_summarizes_repeats_once_the_error_clears() {
+    let dedup = DedupLogger::new(8, Duration::from_secs(600))
+        .with_interval(SINK_RETRY, Duration::from_secs(60));
+    assert_eq!(dedup.interval("sink-retry:1"), Duration::from_secs(60));
+    assert_eq!(dedup.interval("metadata-poll:1"), Duration::from_secs(600));
+
+    assert_eq!(dedup.admit("sink-retry:1"), Admit::Log(None));
+    assert_eq!(dedup.clear("sink-retry:1"), None);
+
+    dedup.admit("sink-retry:1");
+    for _ in 0..3 {
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(dedup.admit("sink-retry:1"), Admit::Suppress);
+    }
+    assert_eq!(
+        dedup.clear("sink-retry:1").map(|r| r.to_string()),
+        Some("previous message repeated 3 times in the last 15s".to_string())
+    );
+    assert!(dedup.is_empty());
+    assert_eq!(dedup.admit("sink-retry:1"), Admit::Log(None));
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_evicts_the_least_recently_logged_keys() {
+    let dedup = DedupLogger::new(4, Duration::from_secs(600));
+    dedup.admit("a");
+    dedup.admit("a");
+    for i in 0..100 {
+        dedup.admit(&key("b", i));
+        dedup.admit("a");
+    }
+    assert_eq!(dedup.len(), 4);
+
+    // The key logged all along was kept, with its count.
+    assert_eq!(dedup.admit("a"), Admit::Suppress);
+    assert_eq!(dedup.admit(&key("b", 0)), Admit::Log(None));
+}
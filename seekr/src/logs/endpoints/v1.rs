@@ -0,0 +1,49 @@
+use actix_web::web::{Data, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::auth::Principal;
+use crate::logs::{LogBuffer, LogFilter, DEFAULT_LIMIT};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_logs);
+}
+
+#[get("/logs")]
+async fn get_logs(
+    query: Query<LogsQuery>,
+    principal: Principal,
+    buffer: Data<LogBuffer>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    debug!("Searching the log buffer");
+
+    let query = query.into_inner();
+    let level = match query.level.as_deref().map(LogFilter::parse_level) {
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e.to_string()),
+        Some(Ok(level)) => Some(level),
+        None => None,
+    };
+
+    HttpResponse::Ok().json(buffer.query(&LogFilter {
+        level,
+        target: query.target,
+        q: query.q,
+        before: query.before,
+        after: query.after,
+        limit: query.limit.unwrap_or(DEFAULT_LIMIT),
+    }))
+}
+
+#[derive(Deserialize)]
+struct LogsQuery {
+    level: Option<String>,
+    target: Option<String>,
+    q: Option<String>,
+    before: Option<u64>,
+    after: Option<u64>,
+    limit: Option<usize>,
+}
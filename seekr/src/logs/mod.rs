@@ -0,0 +1,439 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use log::kv::{self, Key, Value, VisitSource};
+use serde::{Serialize, Serializer};
+
+use crate::errors::AnyError;
+use crate::request_id::RequestId;
+
+pub mod dedup;
+pub mod endpoints;
+
+/// The number of records kept by default.
+pub const DEFAULT_CAPACITY: usize = 5000;
+
+/// Bytes of a message kept in the buffer, the rest is cut off.
+pub const MAX_MESSAGE_LEN: usize = 2048;
+
+/// Structured fields kept per record, and bytes kept of each of them.
+const MAX_FIELDS: usize = 8;
+const MAX_FIELD_LEN: usize = 128;
+
+/// Records waiting for the writer, beyond which new records are dropped
+/// rather than blocking the thread that logged them.
+const QUEUE_SIZE: usize = 1024;
+
+/// The most records returned by a single query.
+pub const MAX_LIMIT: usize = 1000;
+pub const DEFAULT_LIMIT: usize = 200;
+
+/// Records of the buffer's own endpoint are never kept, so reading the logs
+/// doesn't fill them up.
+const OWN_TARGET: &str = "seekr::logs";
+const OWN_PATH: &str = "/debug/logs";
+
+/// Targets of streams workers, whose records are tagged with the subscription.
+const WORKER_TARGET: &str = "seekr::worker::";
+
+/// A log record kept in the buffer.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LogRecord {
+    /// Increases by one with every record kept, so pages stay stable while
+    /// new records arrive.
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_level")]
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+    /// Whether the message was cut off at `MAX_MESSAGE_LEN`.
+    pub truncated: bool,
+    /// The structured fields of the record, e.g. `cluster_id`.
+    pub fields: BTreeMap<String, String>,
+}
+
+fn serialize_level<S: Serializer>(level: &log::Level, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&level.as_str().to_lowercase())
+}
+
+impl LogRecord {
+    /// The record to keep of a logged one, `None` when it isn't kept.
+    pub fn capture(record: &log::Record) -> Option<Self> {
+        let target = record.target();
+        if target.starts_with(OWN_TARGET) {
+            return None;
+        }
+
+        let message = record.args().to_string();
+        // Access log lines of the buffer's own endpoint
+        if target.starts_with("actix_web") && message.contains(OWN_PATH) {
+            return None;
+        }
+
+        let mut fields = BTreeMap::new();
+        if let Some(id) = target.strip_prefix(WORKER_TARGET) {
+            let id = id.split("::").next().unwrap_or_default();
+            fields.insert("subscription_id".to_string(), id.to_string());
+        }
+        let _ = record.key_values().visit(&mut Fields(&mut fields));
+        if let Some(id) = RequestId::current() {
+            fields.insert("request_id".to_string(), id.0);
+        }
+
+        let (message, truncated) = truncate(message, MAX_MESSAGE_LEN);
+        Some(Self {
+            seq: 0,
+            at: Utc::now(),
+            level: record.level(),
+            target: target.to_string(),
+            message,
+            truncated,
+            fields,
+        })
+    }
+}
+
+struct Fields<'a>(&'a mut BTreeMap<String, String>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        if self.0.len() < MAX_FIELDS {
+            let (key, _) = truncate(key.as_str().to_string(), MAX_FIELD_LEN);
+            let (value, _) = truncate(value.to_string(), MAX_FIELD_LEN);
+            self.0.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+/// Cut `s` down to at most `max` bytes, on a char boundary.
+fn truncate(mut s: String, max: usize) -> (String, bool) {
+    if s.len() <= max {
+        return (s, false);
+    }
+
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s.truncate(end);
+    (s, true)
+}
+
+/// Which records a query returns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogFilter {
+    /// The least severe level returned, `warn` returns warnings and errors.
+    pub level: Option<log::Level>,
+    /// A target prefix, e.g. `seekr::kafka`.
+    pub target: Option<String>,
+    /// Either `key:value`, matching records with that field, or a substring
+    /// of the message.
+    pub q: Option<String>,
+    /// Only records older than this sequence, the cursor of the next page.
+    pub before: Option<u64>,
+    /// Only records newer than this sequence, to poll for new records.
+    pub after: Option<u64>,
+    pub limit: usize,
+}
+
+impl LogFilter {
+    pub fn parse_level(level: &str) -> Result<log::Level, AnyError> {
+        log::Level::from_str(level).map_err(|_| format!("unknown level {}", level).into())
+    }
+
+    fn matches(&self, record: &LogRecord) -> bool {
+        if self.before.is_some_and(|b| record.seq >= b) {
+            return false;
+        }
+        if self.after.is_some_and(|a| record.seq <= a) {
+            return false;
+        }
+        if self.level.is_some_and(|l| record.level > l) {
+            return false;
+        }
+        if let Some(target) = &self.target {
+            let matches = record.target == *target
+                || record
+                    .target
+                    .strip_prefix(target.as_str())
+                    .is_some_and(|rest| rest.starts_with("::"));
+            if !matches {
+                return false;
+            }
+        }
+
+        match self.q.as_deref() {
+            None => true,
+            Some(q) => match field(q) {
+                Some((key, value)) => record.fields.get(key).is_some_and(|v| v == value),
+                None => record.message.contains(q),
+            },
+        }
+    }
+}
+
+/// The key and value of a `key:value` query, keys being field names.
+fn field(q: &str) -> Option<(&str, &str)> {
+    let (key, value) = q.split_once(':')?;
+    let is_name = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_name.then_some((key, value))
+}
+
+/// A page of records, newest first.
+#[derive(Debug, Serialize)]
+pub struct LogPage {
+    pub records: Vec<LogRecord>,
+    /// The `before` cursor of the next page, unset on the last one.
+    pub next: Option<u64>,
+    /// The sequence of the newest record kept, the `after` to poll with.
+    pub latest: Option<u64>,
+    /// Records dropped because the writer fell behind.
+    pub dropped: u64,
+}
+
+/// The last `capacity` records, oldest first.
+struct Ring {
+    records: VecDeque<LogRecord>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+impl Ring {
+    fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            next_seq: 1,
+        }
+    }
+
+    fn push(&mut self, mut record: LogRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        record.seq = self.next_seq;
+        self.next_seq += 1;
+        self.records.push_back(record);
+    }
+
+    fn query(&self, filter: &LogFilter) -> (Vec<LogRecord>, Option<u64>) {
+        let limit = filter.limit.clamp(1, MAX_LIMIT);
+        let mut matching = self.records.iter().rev().filter(|r| filter.matches(r));
+
+        let records = matching.by_ref().take(limit).cloned().collect::<Vec<_>>();
+        let next = match matching.next() {
+            Some(_) => records.last().map(|r| r.seq),
+            None => None,
+        };
+        (records, next)
+    }
+
+    fn latest(&self) -> Option<u64> {
+        self.records.back().map(|r| r.seq)
+    }
+}
+
+/// A bounded, searchable buffer of the latest log records of the process.
+///
+/// Loggers hand records to a single writer thread through a bounded queue,
+/// so logging never waits on the buffer's lock, only queries do.
+pub struct LogBuffer {
+    ring: Arc<RwLock<Ring>>,
+    queue: SyncSender<LogRecord>,
+    dropped: AtomicU64,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let ring = Arc::new(RwLock::new(Ring::new(capacity)));
+        let (queue, rx) = mpsc::sync_channel::<LogRecord>(QUEUE_SIZE);
+
+        let writer = ring.clone();
+        std::thread::Builder::new()
+            .name("seekr-logs".to_string())
+            .spawn(move || {
+                // Ends once the buffer, and with it the queue, is dropped
+                for record in rx {
+                    writer.write().unwrap().push(record);
+                }
+            })
+            .expect("unable to start the log buffer");
+
+        Self {
+            ring,
+            queue,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Keep a logged record, unless it's excluded or the writer is behind.
+    pub fn capture(&self, record: &log::Record) {
+        let Some(record) = LogRecord::capture(record) else {
+            return;
+        };
+
+        if let Err(TrySendError::Full(_)) = self.queue.try_send(record) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn query(&self, filter: &LogFilter) -> LogPage {
+        let ring = self.ring.read().unwrap();
+        let (records, next) = ring.query(filter);
+        LogPage {
+            records,
+            next,
+            latest: ring.latest(),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+fn record(level: log::Level, target: &str, message: &str, cluster_id: i64) -> Option<LogRecord> {
+    LogRecord::capture(
+        &log::Record::builder()
+            .args(format_args!("{}", message))
+            .level(level)
+            .target(target)
+            .key_values(&("cluster_id", cluster_id))
+            .build(),
+    )
+}
+
+#[test]
+fn it_filters_by_level_target_and_query() {
+    let mut ring = Ring::new(100);
+    for (level, target, message, cluster_id) in [
+        (log::Level::Info, "seekr::kafka::metadata", "Polled", 42),
+        (log::Level::Warn, "seekr::kafka::metadata", "Slow poll", 42),
+        (log::Level::Error, "seekr::kafka", "Poll failed", 7),
+        (log::Level::Error, "seekr::kafkaesque", "Poll failed", 42),
+        (
+            log::Level::Warn,
+            "seekr::worker::12::consume",
+            "Lagging",
+            42,
+        ),
+    ] {
+        ring.push(record(level, target, message, cluster_id).unwrap());
+    }
+
+    let seqs = |filter: LogFilter| {
+        let (records, _) = ring.query(&LogFilter {
+            limit: 10,
+            ..filter
+        });
+        records.into_iter().map(|r| r.seq).collect::<Vec<_>>()
+    };
+
+    assert_eq!(seqs(LogFilter::default()), vec![5, 4, 3, 2, 1]);
+    let warn = Some(log::Level::Warn);
+    assert_eq!(
+        seqs(LogFilter {
+            level: warn,
+            ..LogFilter::default()
+        }),
+        vec![5, 4, 3, 2]
+    );
+    let kafka = Some("seekr::kafka".to_string());
+    assert_eq!(
+        seqs(LogFilter {
+            level: warn,
+            target: kafka.clone(),
+            q: Some("cluster_id:42".to_string()),
+            ..LogFilter::default()
+        }),
+        vec![2]
+    );
+    assert_eq!(
+        seqs(LogFilter {
+            target: kafka,
+            q: Some("failed".to_string()),
+            ..LogFilter::default()
+        }),
+        vec![3]
+    );
+
+    // Workers are tagged with the subscription they run.
+    assert_eq!(
+        seqs(LogFilter {
+            q: Some("subscription_id:12".to_string()),
+            ..LogFilter::default()
+        }),
+        vec![5]
+    );
+}
+
+#[test]
+fn it_bounds_memory_under_a_flood() {
+    let mut ring = Ring::new(100);
+    let message = "é".repeat(MAX_MESSAGE_LEN);
+    for _ in 0..10_000 {
+        ring.push(record(log::Level::Info, "seekr::kafka", &message, 1).unwrap());
+    }
+
+    assert_eq!(ring.records.len(), 100);
+    assert_eq!(ring.records.front().unwrap().seq, 9_901);
+    assert!(ring
+        .records
+        .iter()
+        .all(|r| r.truncated && r.message.len() <= MAX_MESSAGE_LEN));
+}
+
+#[test]
+fn it_pages_stably_while_records_arrive() {
+    let mut ring = Ring::new(1000);
+    let push = |ring: &mut Ring, n: usize| {
+        for i in 0..n {
+            let message = format!("record {}", i);
+            ring.push(record(log::Level::Info, "seekr::kafka", &message, 1).unwrap());
+        }
+    };
+    push(&mut ring, 25);
+
+    let mut filter = LogFilter {
+        limit: 10,
+        ..LogFilter::default()
+    };
+    let mut seen = vec![];
+    loop {
+        let (records, next) = ring.query(&filter);
+        seen.extend(records.iter().map(|r| r.seq));
+        // New records don't shift the pages already being walked.
+        push(&mut ring, 7);
+
+        match next {
+            Some(before) => filter.before = Some(before),
+            None => break,
+        }
+    }
+
+    assert_eq!(seen, (1..=25).rev().collect::<Vec<_>>());
+}
+
+#[test]
+fn it_excludes_its_own_requests() {
+    assert!(record(
+        log::Level::Info,
+        "seekr::logs::endpoints::v1",
+        "Fetching logs",
+        1
+    )
+    .is_none());
+    let access = "127.0.0.1 \"GET /api/v1/debug/logs?level=warn HTTP/1.1\" 200";
+    assert!(record(log::Level::Info, "actix_web::middleware::logger", access, 1).is_none());
+
+    let other = "127.0.0.1 \"GET /api/v1/clusters HTTP/1.1\" 200";
+    assert!(record(log::Level::Info, "actix_web::middleware::logger", other, 1).is_some());
+}
@@ -0,0 +1,412 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::api::error;
+use crate::changefeed::store::ChangefeedStore;
+use crate::clusters::store::ClusterStore;
+use crate::deadline::{Deadline, DeadlineExceeded};
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::streams::StreamsMessage;
+use crate::lookup::source::{RecordSource, LOOKUP_TIMEOUT};
+use crate::lookup::{self, Outcome};
+use crate::shards::store::DocumentStore;
+use crate::subscriptions::store::SubscriptionStore;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(lookup_message);
+}
+
+#[get("/{cluster_id}/{id}/lookup")]
+async fn lookup_message(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<LookupQuery>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    documents: Data<Arc<dyn DocumentStore + Send + Sync>>,
+    changefeed: Data<Arc<dyn ChangefeedStore + Send + Sync>>,
+    source: Data<Arc<dyn RecordSource + Send + Sync>>,
+    deadline: Deadline,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    let LookupQuery {
+        partition,
+        offset,
+        fetch_from_kafka,
+    } = query.into_inner();
+    info!(
+        "Looking up partition {} offset {} of subscription from cluster id {} with id {}",
+        partition, offset, cluster_id, id
+    );
+
+    if partition < 0 || offset < 0 {
+        return HttpResponse::BadRequest().body("partition and offset must not be negative");
+    }
+
+    let subscription = match deadline.run("subscriptions", ss.get(cluster_id, id)).await {
+        Ok(Ok(Some(s))) => s,
+        Ok(Ok(None)) => return HttpResponse::NotFound().finish(),
+        Ok(Err(e)) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return error::deadline_exceeded(&e),
+    };
+    let cluster = match deadline.run("clusters", cs.get(cluster_id)).await {
+        Ok(Ok(Some(c))) => c,
+        Ok(Ok(None)) => return HttpResponse::NotFound().finish(),
+        Ok(Err(e)) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return error::deadline_exceeded(&e),
+    };
+
+    let evidence = lookup::evidence(
+        documents.get_ref().as_ref(),
+        changefeed.get_ref().as_ref(),
+        source.get_ref().as_ref(),
+        &cluster,
+        &subscription,
+        partition,
+        offset,
+        &deadline,
+    );
+    let outcome = match evidence.await {
+        Ok(evidence) => lookup::classify(offset, &evidence),
+        Err(e) => match e.downcast_ref::<DeadlineExceeded>() {
+            Some(e) => return error::deadline_exceeded(e),
+            None => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+    };
+
+    // Only reach for the record itself when nothing is indexed to compare with.
+    let mut response = LookupResponse {
+        partition,
+        offset,
+        determined: outcome.is_determined(),
+        outcome,
+        kafka_record: None,
+        kafka_error: None,
+    };
+    let indexed = matches!(response.outcome, Outcome::Indexed { .. });
+    if fetch_from_kafka.unwrap_or_default() && !indexed {
+        let topic = &subscription.topic_name;
+        let timeout = deadline.clamp(LOOKUP_TIMEOUT);
+        let fetch = source.fetch(&cluster, topic, partition, offset, timeout);
+        match deadline.run("kafka_fetch", fetch).await {
+            Ok(Ok(record)) => response.kafka_record = record,
+            // A broker timing out on the clamped timeout ran out of the deadline.
+            Ok(Err(e)) => match deadline.check("kafka_fetch") {
+                Ok(()) => response.kafka_error = Some(e.to_string()),
+                Err(e) => return error::deadline_exceeded(&e),
+            },
+            Err(e) => return error::deadline_exceeded(&e),
+        }
+    }
+
+    HttpResponse::Ok().json(response)
+}
+
+#[derive(Deserialize)]
+struct LookupQuery {
+    partition: i32,
+    offset: i64,
+    fetch_from_kafka: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct LookupResponse {
+    partition: i32,
+    offset: i64,
+
+    /// `false` when the outcome is unknown, rather than known not to be indexed.
+    determined: bool,
+
+    #[serde(flatten)]
+    outcome: Outcome,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_record: Option<StreamsMessage>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_error: Option<String>,
+}
+
+#[cfg(test)]
+struct Fixture {
+    cs: Arc<dyn ClusterStore + Send + Sync>,
+    ss: Arc<dyn SubscriptionStore + Send + Sync>,
+    documents: Arc<crate::shards::store::MemoryDocumentStore>,
+    changefeed: Arc<dyn ChangefeedStore + Send + Sync>,
+    source: Arc<crate::lookup::source::MemoryRecordSource>,
+}
+
+#[cfg(test)]
+impl Fixture {
+    async fn new(source: crate::lookup::source::MemoryRecordSource) -> Self {
+        use std::collections::HashMap;
+
+        use crate::changefeed::record::ChangeRecord;
+        use crate::clusters::cluster::{Cluster, Kind};
+        use crate::clusters::store::MemoryClusterStore;
+        use crate::kafka::config;
+        use crate::shards::router::ShardRouter;
+        use crate::subscriptions::store::MemorySubscriptionStore;
+        use crate::subscriptions::subscription::Subscription;
+
+        let cs = Arc::new(MemoryClusterStore::default());
+        let cluster = Cluster::new(
+            Some(ClusterId(1)),
+            Kind::Kafka,
+            "local".to_string(),
+            HashMap::new(),
+        );
+        cs.put(cluster).await.unwrap();
+
+        let ss = Arc::new(MemorySubscriptionStore::default());
+        let config = HashMap::from([(config::CHANGEFEED_ENABLED.to_string(), "true".to_string())]);
+        let subscription = Subscription::new(
+            Some(SubscriptionId(1)),
+            ClusterId(1),
+            "orders".to_string(),
+            config,
+        );
+        ss.put(subscription.clone()).await.unwrap();
+
+        // Offset 10 was indexed, offset 11 is a tombstone.
+        let message = |offset: i64, payload: Option<&str>| StreamsMessage {
+            payload: payload.map(String::from),
+            payload_json: None,
+            topic: "orders".to_string(),
+            key: None,
+            headers: Default::default(),
+            partition: 3,
+            offset,
+            timestamp: Some(1_700_000_000_000),
+        };
+        let documents = Arc::new(crate::shards::store::MemoryDocumentStore::default());
+        let mut router = ShardRouter::new(documents.clone(), &subscription);
+        let indexed = message(10, Some("{\"order\":7}"));
+        router
+            .index(vec![
+                crate::shards::document(&indexed, Default::default()).unwrap()
+            ])
+            .await
+            .unwrap();
+
+        let changefeed = Arc::new(crate::changefeed::store::MemoryChangefeedStore::default());
+        let records = [indexed, message(11, None)]
+            .iter()
+            .map(|m| ChangeRecord::from_message(subscription.id, m, false))
+            .collect();
+        changefeed.append(records).await.unwrap();
+
+        Self {
+            cs,
+            ss,
+            documents,
+            changefeed,
+            source: Arc::new(source),
+        }
+    }
+
+    async fn lookup(&self, query: &str) -> serde_json::Value {
+        let (_, body) = self.lookup_within(query, None).await;
+        body
+    }
+
+    async fn lookup_within(
+        &self,
+        query: &str,
+        deadline_ms: Option<u64>,
+    ) -> (actix_web::http::StatusCode, serde_json::Value) {
+        use actix_web::{test, App};
+
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(self.cs.clone()))
+                .app_data(Data::new(self.ss.clone()))
+                .app_data(Data::new(
+                    self.documents.clone() as Arc<dyn DocumentStore + Send + Sync>
+                ))
+                .app_data(Data::new(self.changefeed.clone()))
+                .app_data(Data::new(
+                    self.source.clone() as Arc<dyn RecordSource + Send + Sync>
+                ))
+                .configure(crate::server::routes),
+        )
+        .await;
+
+        let uri = format!("/api/v1/subscriptions/1/1/lookup?{}", query);
+        let mut req = test::TestRequest::get().uri(&uri);
+        if let Some(ms) = deadline_ms {
+            req = req.insert_header((crate::deadline::HEADER, ms.to_string()));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        (res.status(), test::read_body_json(res).await)
+    }
+}
+
+#[actix_web::test]
+async fn it_looks_up_messages_by_coordinates() {
+    use crate::lookup::source::{MemoryRecordSource, PartitionPosition};
+
+    let source = MemoryRecordSource {
+        position: Some(PartitionPosition {
+            low_watermark: 0,
+            high_watermark: 20,
+            committed_offset: Some(12),
+        }),
+        records: vec![StreamsMessage {
+            payload: Some("{\"order\":8}".to_string()),
+            payload_json: None,
+            topic: "orders".to_string(),
+            key: None,
+            headers: Default::default(),
+            partition: 3,
+            offset: 15,
+            timestamp: None,
+        }],
+        ..Default::default()
+    };
+    let f = Fixture::new(source).await;
+
+    let body = f.lookup("partition=3&offset=10").await;
+    assert_eq!(body["outcome"], "indexed");
+    assert_eq!(body["determined"], true);
+    assert_eq!(body["document"]["order"], 7);
+    assert_eq!(body["document"]["_seekr_offset"], 10);
+
+    let body = f.lookup("partition=3&offset=11").await;
+    assert_eq!(body["outcome"], "filtered");
+
+    // Consumed, yet neither indexed nor in the changefeed.
+    let body = f.lookup("partition=3&offset=9").await;
+    assert_eq!(body["outcome"], "not_indexed");
+    assert_eq!(body["determined"], true);
+
+    let body = f
+        .lookup("partition=3&offset=15&fetch_from_kafka=true")
+        .await;
+    assert_eq!(body["outcome"], "not_yet_consumed");
+    assert_eq!(body["committed_offset"], 12);
+    assert_eq!(body["kafka_record"]["payload"], "{\"order\":8}");
+
+    let body = f.lookup("partition=3&offset=20").await;
+    assert_eq!(body["outcome"], "not_produced");
+}
+
+#[actix_web::test]
+async fn it_reports_undeterminable_outcomes() {
+    use crate::lookup::source::MemoryRecordSource;
+
+    let f = Fixture::new(MemoryRecordSource::default()).await;
+
+    // Local evidence doesn't need Kafka.
+    let body = f.lookup("partition=3&offset=10").await;
+    assert_eq!(body["outcome"], "indexed");
+
+    let body = f.lookup("partition=3&offset=9").await;
+    assert_eq!(body["outcome"], "unknown");
+    assert_eq!(body["determined"], false);
+    assert!(body["reason"]
+        .as_str()
+        .unwrap()
+        .contains("broker unreachable"));
+}
+
+#[actix_web::test]
+async fn it_clamps_kafka_timeouts_to_the_deadline() {
+    use std::time::Duration;
+
+    use crate::lookup::source::{MemoryRecordSource, PartitionPosition};
+
+    let source = MemoryRecordSource {
+        position: Some(PartitionPosition {
+            low_watermark: 0,
+            high_watermark: 20,
+            committed_offset: Some(12),
+        }),
+        ..Default::default()
+    };
+    let f = Fixture::new(source).await;
+
+    let (status, _) = f
+        .lookup_within("partition=3&offset=15&fetch_from_kafka=true", Some(1_000))
+        .await;
+    assert_eq!(status, 200);
+    let timeouts = f.source.timeouts.lock().unwrap().clone();
+    assert_eq!(timeouts.len(), 2);
+    assert!(timeouts
+        .iter()
+        .all(|t| *t > Duration::ZERO && *t <= Duration::from_millis(1_000)));
+
+    // Without a header, the route's budget leaves the default timeout alone.
+    f.lookup("partition=3&offset=15").await;
+    assert_eq!(f.source.timeouts.lock().unwrap()[2], LOOKUP_TIMEOUT);
+}
+
+#[actix_web::test]
+async fn it_aborts_lookups_past_their_deadline() {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+
+    use crate::changefeed::cursor::Cursor;
+    use crate::changefeed::record::ChangeRecord;
+    use crate::errors::AnyError;
+    use crate::lookup::source::MemoryRecordSource;
+
+    /// A changefeed whose reads hang.
+    struct Slow(Arc<dyn ChangefeedStore + Send + Sync>);
+
+    #[async_trait]
+    impl ChangefeedStore for Slow {
+        async fn append(&self, records: Vec<ChangeRecord>) -> Result<(), AnyError> {
+            self.0.append(records).await
+        }
+
+        async fn read(
+            &self,
+            id: SubscriptionId,
+            cursor: &Cursor,
+            limit: usize,
+        ) -> Result<Vec<ChangeRecord>, AnyError> {
+            self.0.read(id, cursor, limit).await
+        }
+
+        async fn get(
+            &self,
+            id: SubscriptionId,
+            partition: i32,
+            offset: i64,
+        ) -> Result<Option<ChangeRecord>, AnyError> {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            self.0.get(id, partition, offset).await
+        }
+
+        async fn truncate(&self, id: SubscriptionId, before_ms: i64) -> Result<usize, AnyError> {
+            self.0.truncate(id, before_ms).await
+        }
+
+        async fn truncated(&self, id: SubscriptionId) -> Result<HashMap<i32, i64>, AnyError> {
+            self.0.truncated(id).await
+        }
+    }
+
+    let mut f = Fixture::new(MemoryRecordSource::default()).await;
+    f.changefeed = Arc::new(Slow(f.changefeed.clone()));
+
+    let started = Instant::now();
+    let (status, body) = f.lookup_within("partition=3&offset=9", Some(50)).await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(status, 504);
+    assert_eq!(body["deadline_exceeded"], true);
+    assert_eq!(body["stage"], "changefeed");
+    assert_eq!(body["error"]["code"], "timeout");
+
+    // Kafka is never reached once the budget is gone.
+    assert!(f.source.timeouts.lock().unwrap().is_empty());
+
+    let (status, body) = f.lookup_within("partition=3&offset=9", Some(0)).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["code"], "invalid_request");
+}
@@ -0,0 +1,351 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::changefeed::record::{ChangeRecord, Operation};
+use crate::changefeed::store::ChangefeedStore;
+use crate::clusters::cluster::Cluster;
+use crate::deadline::Deadline;
+use crate::errors::AnyError;
+use crate::kafka::client;
+use crate::kafka::config;
+use crate::shards::search;
+use crate::shards::store::DocumentStore;
+use crate::subscriptions::subscription::Subscription;
+
+use self::source::{PartitionPosition, RecordSource, LOOKUP_TIMEOUT};
+
+pub mod endpoints;
+pub mod source;
+
+/// What the pipeline did with the message at a partition and offset.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    /// The message is indexed as this document.
+    Indexed { document: Value },
+
+    /// The message was consumed, and deliberately not indexed.
+    Filtered { reason: String },
+
+    /// The message was consumed, but no document is indexed for it.
+    NotIndexed { reason: String },
+
+    /// The subscription's consumer group hasn't reached the offset yet.
+    NotYetConsumed { committed_offset: Option<i64> },
+
+    /// No message was produced at the offset yet.
+    NotProduced { high_watermark: i64 },
+
+    /// Nothing retained tells what happened to the message.
+    Unknown { reason: String },
+}
+
+impl Outcome {
+    /// Whether the outcome is known, as opposed to `Unknown`.
+    pub fn is_determined(&self) -> bool {
+        !matches!(self, Outcome::Unknown { .. })
+    }
+}
+
+/// Whether the changefeed still holds a record of every message consumed at an offset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coverage {
+    Disabled,
+
+    /// Records up to and including the offset were truncated.
+    Truncated {
+        through: i64,
+    },
+
+    Retained,
+}
+
+impl Coverage {
+    pub fn of(subscription: &Subscription, truncated: Option<i64>, offset: i64) -> Self {
+        let enabled = subscription
+            .config
+            .get(config::CHANGEFEED_ENABLED)
+            .is_some_and(|v| v == "true");
+
+        match truncated {
+            _ if !enabled => Coverage::Disabled,
+            Some(through) if offset <= through => Coverage::Truncated { through },
+            _ => Coverage::Retained,
+        }
+    }
+}
+
+/// What the data sources know about a single message.
+#[derive(Clone, Debug)]
+pub struct Evidence {
+    /// The document indexed for the message.
+    pub document: Option<Value>,
+
+    /// The changefeed record of the message.
+    pub change: Option<ChangeRecord>,
+
+    pub changefeed: Coverage,
+
+    /// The partition's position in Kafka, or why it couldn't be read.
+    pub position: Result<PartitionPosition, String>,
+}
+
+/// Classify what happened to the message at `offset`.
+///
+/// Only local evidence of a message proves it was consumed; a missing one
+/// proves nothing unless the changefeed would have recorded it and Kafka
+/// confirms the consumer group moved past the offset.
+pub fn classify(offset: i64, evidence: &Evidence) -> Outcome {
+    if let Some(document) = &evidence.document {
+        return Outcome::Indexed {
+            document: document.clone(),
+        };
+    }
+
+    match &evidence.change {
+        Some(c) if c.operation == Operation::Delete => {
+            return Outcome::Filtered {
+                reason: "the message is a tombstone, which removes documents instead of indexing one"
+                    .to_string(),
+            }
+        }
+        Some(_) => {
+            return Outcome::NotIndexed {
+                reason: "the message was consumed and recorded in the changefeed, but its document \
+                         is missing; indexing may have failed, or its shard was dropped by retention"
+                    .to_string(),
+            }
+        }
+        None => {}
+    }
+
+    let position = match &evidence.position {
+        Ok(position) => position,
+        Err(e) => {
+            return Outcome::Unknown {
+                reason: format!("the partition's offsets couldn't be read from Kafka: {}", e),
+            }
+        }
+    };
+
+    if offset >= position.high_watermark {
+        return Outcome::NotProduced {
+            high_watermark: position.high_watermark,
+        };
+    }
+
+    match position.committed_offset {
+        Some(committed) if offset < committed => {}
+        committed_offset => return Outcome::NotYetConsumed { committed_offset },
+    }
+
+    if offset < position.low_watermark {
+        return Outcome::Unknown {
+            reason: format!(
+                "the offset predates the topic's retention (low watermark {}), and nothing was retained for it",
+                position.low_watermark
+            ),
+        };
+    }
+
+    match evidence.changefeed {
+        Coverage::Retained => Outcome::NotIndexed {
+            reason: "the message was consumed, but neither indexed nor recorded in the changefeed"
+                .to_string(),
+        },
+        Coverage::Truncated { through } => Outcome::Unknown {
+            reason: format!(
+                "the message was consumed, but the changefeed was truncated through offset {}",
+                through
+            ),
+        },
+        Coverage::Disabled => Outcome::Unknown {
+            reason: "the message was consumed, but the changefeed that records consumed messages is disabled"
+                .to_string(),
+        },
+    }
+}
+
+/// Gather the evidence of the subscription's message at the partition and offset.
+///
+/// Failing to reach Kafka is part of the evidence, failing to read the local
+/// stores is an error, and so is running out of the deadline at any stage.
+#[allow(clippy::too_many_arguments)]
+pub async fn evidence(
+    documents: &(dyn DocumentStore + Send + Sync),
+    changefeed: &(dyn ChangefeedStore + Send + Sync),
+    source: &(dyn RecordSource + Send + Sync),
+    cluster: &Cluster,
+    subscription: &Subscription,
+    partition: i32,
+    offset: i64,
+    deadline: &Deadline,
+) -> Result<Evidence, AnyError> {
+    let id = subscription.id;
+    let document = deadline
+        .run("documents", search::find(documents, id, partition, offset))
+        .await??;
+    let change = deadline
+        .run("changefeed", changefeed.get(id, partition, offset))
+        .await??;
+    let truncated = deadline
+        .run("changefeed", changefeed.truncated(id))
+        .await??
+        .get(&partition)
+        .copied();
+
+    deadline.check("kafka_position")?;
+    let timeout = deadline.clamp(LOOKUP_TIMEOUT);
+    let position = deadline
+        .run(
+            "kafka_position",
+            source.position(
+                cluster,
+                &subscription.topic_name,
+                &client::group_id(cluster, subscription),
+                partition,
+                timeout,
+            ),
+        )
+        .await?;
+    // A broker timing out on the clamped timeout ran out of the deadline.
+    if position.is_err() {
+        deadline.check("kafka_position")?;
+    }
+    let position = position.map_err(|e| e.to_string());
+
+    Ok(Evidence {
+        document,
+        change,
+        changefeed: Coverage::of(subscription, truncated, offset),
+        position,
+    })
+}
+
+#[cfg(test)]
+fn evidence_at(committed: Option<i64>) -> Evidence {
+    Evidence {
+        document: None,
+        change: None,
+        changefeed: Coverage::Retained,
+        position: Ok(PartitionPosition {
+            low_watermark: 100,
+            high_watermark: 1_000,
+            committed_offset: committed,
+        }),
+    }
+}
+
+#[test]
+fn it_classifies_local_evidence_first() {
+    use crate::ids::SubscriptionId;
+    use crate::kafka::streams::StreamsMessage;
+
+    let message = |payload: Option<&str>| StreamsMessage {
+        payload: payload.map(String::from),
+        payload_json: None,
+        topic: "orders".to_string(),
+        key: None,
+        headers: Default::default(),
+        partition: 3,
+        offset: 500,
+        timestamp: None,
+    };
+    let change = |payload| {
+        Some(ChangeRecord::from_message(
+            SubscriptionId(1),
+            &message(payload),
+            false,
+        ))
+    };
+
+    // A document wins regardless of what Kafka says.
+    let indexed = Evidence {
+        document: Some(serde_json::json!({ "order": 7 })),
+        position: Err("broker unreachable".to_string()),
+        ..evidence_at(None)
+    };
+    assert!(matches!(
+        classify(500, &indexed),
+        Outcome::Indexed { document } if document["order"] == 7
+    ));
+
+    let tombstone = Evidence {
+        change: change(None),
+        ..evidence_at(Some(600))
+    };
+    assert!(matches!(
+        classify(500, &tombstone),
+        Outcome::Filtered { .. }
+    ));
+
+    let lost = Evidence {
+        change: change(Some("{}")),
+        ..evidence_at(Some(600))
+    };
+    assert!(matches!(classify(500, &lost), Outcome::NotIndexed { .. }));
+}
+
+#[test]
+fn it_tells_not_indexed_from_undeterminable_offsets() {
+    let cases = [
+        // Consumed, and the changefeed would have recorded it.
+        (500, evidence_at(Some(600)), "not_indexed"),
+        (600, evidence_at(Some(600)), "not_yet_consumed"),
+        (500, evidence_at(None), "not_yet_consumed"),
+        (1_000, evidence_at(Some(600)), "not_produced"),
+        (50, evidence_at(Some(600)), "unknown"),
+        (
+            500,
+            Evidence {
+                changefeed: Coverage::Disabled,
+                ..evidence_at(Some(600))
+            },
+            "unknown",
+        ),
+        (
+            500,
+            Evidence {
+                changefeed: Coverage::Truncated { through: 550 },
+                ..evidence_at(Some(600))
+            },
+            "unknown",
+        ),
+        (
+            500,
+            Evidence {
+                position: Err("broker unreachable".to_string()),
+                ..evidence_at(Some(600))
+            },
+            "unknown",
+        ),
+    ];
+
+    for (offset, evidence, expected) in cases {
+        let outcome = classify(offset, &evidence);
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(json["outcome"], expected, "{} {:?}", offset, evidence);
+        assert_eq!(outcome.is_determined(), expected != "unknown");
+    }
+}
+
+#[test]
+fn it_derives_changefeed_coverage() {
+    use std::collections::HashMap;
+
+    use crate::ids::ClusterId;
+
+    let mut subscription =
+        Subscription::new(None, ClusterId(1), "orders".to_string(), HashMap::new());
+    assert_eq!(Coverage::of(&subscription, None, 5), Coverage::Disabled);
+
+    subscription
+        .config
+        .insert(config::CHANGEFEED_ENABLED.to_string(), "true".to_string());
+    assert_eq!(Coverage::of(&subscription, None, 5), Coverage::Retained);
+    assert_eq!(
+        Coverage::of(&subscription, Some(5), 5),
+        Coverage::Truncated { through: 5 }
+    );
+    assert_eq!(Coverage::of(&subscription, Some(4), 5), Coverage::Retained);
+}
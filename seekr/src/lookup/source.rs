@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{Message, Offset, TopicPartitionList};
+use serde::Serialize;
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::kafka::client::{build_client_config, cluster_group_id};
+use crate::kafka::streams::consumer::streams_message;
+use crate::kafka::streams::StreamsMessage;
+
+/// Timeout for each broker round trip of a lookup, unless the request's
+/// deadline leaves less.
+pub const LOOKUP_TIMEOUT: Duration = Duration::from_millis(5_000);
+
+/// How far the subscription's consumer group got in a partition.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PartitionPosition {
+    /// The oldest offset still retained by the topic.
+    pub low_watermark: i64,
+
+    /// The offset the next produced message gets.
+    pub high_watermark: i64,
+
+    /// The next offset the consumer group reads, if it ever committed one.
+    pub committed_offset: Option<i64>,
+}
+
+/// Reads single partitions of a subscription's topic on demand, bounding
+/// each broker round trip by `timeout`.
+#[async_trait]
+pub trait RecordSource {
+    /// The watermarks of the partition, and the offset committed by `group_id`.
+    async fn position(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        group_id: &str,
+        partition: i32,
+        timeout: Duration,
+    ) -> Result<PartitionPosition, AnyError>;
+
+    /// The message at the offset, or `None` when there is none, e.g. it was compacted.
+    async fn fetch(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        partition: i32,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<Option<StreamsMessage>, AnyError>;
+}
+
+/// Connects an ephemeral consumer per call, which never commits, so the
+/// offsets of the subscription's consumer group are left untouched.
+pub struct KafkaRecordSource;
+
+impl KafkaRecordSource {
+    fn connect(cluster: &Cluster, group_id: &str) -> Result<BaseConsumer, AnyError> {
+        let consumer = build_client_config(cluster, None)?
+            .set("group.id", group_id)
+            .create::<BaseConsumer>()?;
+
+        Ok(consumer)
+    }
+}
+
+#[async_trait]
+impl RecordSource for KafkaRecordSource {
+    async fn position(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        group_id: &str,
+        partition: i32,
+        timeout: Duration,
+    ) -> Result<PartitionPosition, AnyError> {
+        let consumer = Self::connect(cluster, group_id)?;
+        let topic = topic.to_string();
+
+        // Watermark and offset queries are blocking broker round trips.
+        tokio::task::spawn_blocking(move || {
+            let (low, high) = consumer.fetch_watermarks(&topic, partition, timeout)?;
+
+            let mut tpl = TopicPartitionList::new();
+            tpl.add_partition(&topic, partition);
+            let committed = consumer
+                .committed_offsets(tpl, timeout)?
+                .find_partition(&topic, partition)
+                .and_then(|e| match e.offset() {
+                    Offset::Offset(o) => Some(o),
+                    _ => None,
+                });
+
+            Ok(PartitionPosition {
+                low_watermark: low,
+                high_watermark: high,
+                committed_offset: committed,
+            })
+        })
+        .await?
+    }
+
+    async fn fetch(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        partition: i32,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<Option<StreamsMessage>, AnyError> {
+        // Assigned rather than subscribed, any group the cluster's ACLs allow will do.
+        let consumer = Self::connect(cluster, &cluster_group_id(cluster))?;
+        let topic = topic.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut tpl = TopicPartitionList::new();
+            tpl.add_partition_offset(&topic, partition, Offset::Offset(offset))?;
+            consumer.assign(&tpl)?;
+
+            match consumer.poll(timeout) {
+                Some(Ok(m)) if m.offset() == offset => Ok(Some(streams_message(&m))),
+                // Compacted topics skip to the next retained offset.
+                Some(Ok(_)) | None => Ok(None),
+                Some(Err(e)) => Err(e.into()),
+            }
+        })
+        .await?
+    }
+}
+
+/// A scripted partition used to exercise lookups in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryRecordSource {
+    /// The partition's position, or `None` when the broker is unreachable.
+    pub position: Option<PartitionPosition>,
+    pub records: Vec<StreamsMessage>,
+    /// The timeouts passed to each call.
+    pub timeouts: std::sync::Mutex<Vec<Duration>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl RecordSource for MemoryRecordSource {
+    async fn position(
+        &self,
+        _: &Cluster,
+        _: &str,
+        _: &str,
+        _: i32,
+        timeout: Duration,
+    ) -> Result<PartitionPosition, AnyError> {
+        self.timeouts.lock().unwrap().push(timeout);
+        self.position.ok_or_else(|| "broker unreachable".into())
+    }
+
+    async fn fetch(
+        &self,
+        _: &Cluster,
+        _: &str,
+        partition: i32,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<Option<StreamsMessage>, AnyError> {
+        self.timeouts.lock().unwrap().push(timeout);
+        Ok(self
+            .records
+            .iter()
+            .find(|m| m.partition == partition && m.offset == offset)
+            .cloned())
+    }
+}
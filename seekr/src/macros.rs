@@ -3,3 +3,115 @@ macro_rules! get_cfg {
 		let $i = || { $( if cfg!($i=$s) { return $s; } );+ "unknown"};
 	)
 }
+
+/// Evaluates the named failpoint, narrowed to an optional scope, to a
+/// `Result<(), AnyError>`. Without the `chaos` feature it is always `Ok(())`.
+macro_rules! fail_point {
+    ($name:expr) => {
+        fail_point!($name, "")
+    };
+    ($name:expr, $scope:expr) => {{
+        #[cfg(feature = "chaos")]
+        let result = $crate::failpoints::eval($name, &$scope.to_string()).await;
+        #[cfg(not(feature = "chaos"))]
+        let result: Result<(), $crate::errors::AnyError> = {
+            let _ = &$scope;
+            Ok(())
+        };
+        result
+    }};
+}
+
+/// Logs like `log!`, unless a message of the same key was logged within the
+/// key's interval, see `crate::logs::dedup`. The repeats suppressed in the
+/// meantime are reported right before the message.
+macro_rules! log_dedup {
+    ($key:expr, target: $target:expr, $lvl:expr, $($arg:tt)+) => {{
+        use $crate::logs::dedup::{Admit, DEDUP};
+        if let Admit::Log(repeats) = DEDUP.admit(&$key) {
+            if let Some(repeats) = repeats {
+                log!(target: $target, $lvl, "{}", repeats);
+            }
+            log!(target: $target, $lvl, $($arg)+);
+        }
+    }};
+    ($key:expr, $lvl:expr, $($arg:tt)+) => {
+        log_dedup!($key, target: module_path!(), $lvl, $($arg)+)
+    };
+}
+
+/// Forgets a key of `log_dedup!` once its error cleared, reporting the repeats
+/// suppressed since it was last logged. The key is only built while any key
+/// is tracked, so clearing on every success is cheap.
+macro_rules! clear_dedup {
+    ($key:expr, target: $target:expr) => {
+        if !$crate::logs::dedup::DEDUP.is_empty() {
+            if let Some(repeats) = $crate::logs::dedup::DEDUP.clear(&$key) {
+                info!(target: $target, "{}, cleared since", repeats);
+            }
+        }
+    };
+    ($key:expr) => {
+        clear_dedup!($key, target: module_path!())
+    };
+}
+
+/// Declares an enum stored or exchanged as a string, spelling out the string of
+/// every variant. A value written by another version reads back as
+/// `Unrecognized(raw)` rather than failing the whole document, and is written
+/// back as it was read, so a rolling deploy neither rejects nor loses it.
+macro_rules! wire_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident = $wire:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+        #[serde(from = "String", into = "String")]
+        #[non_exhaustive]
+        $vis enum $name {
+            $($(#[$vmeta])* $variant,)+
+            /// A value written by another version, kept as it was read.
+            Unrecognized(String),
+        }
+
+        impl $name {
+            /// The variants this version knows, in the order they were declared.
+            pub const KNOWN: &'static [$name] = &[$($name::$variant),+];
+
+            /// The string the variant is stored as.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $wire,)+
+                    $name::Unrecognized(raw) => raw,
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> Self {
+                match s.as_str() {
+                    $($wire => $name::$variant,)+
+                    _ => $name::Unrecognized(s),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(v: $name) -> Self {
+                match v {
+                    $name::Unrecognized(raw) => raw,
+                    v => v.as_str().to_string(),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use meilisearch_sdk::errors::{Error, ErrorCode};
+use meilisearch_sdk::Client;
+
+use crate::errors::AnyError;
+
+pub const DEFAULT_URL: &str = "http://localhost:7700";
+pub const DEFAULT_API_KEY: &str = "masterKey";
+
+/// Where the Meilisearch backed stores are kept, and the key they're read and written with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeilisearchConfig {
+    pub url: String,
+    pub api_key: String,
+}
+
+impl Default for MeilisearchConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_URL.to_string(),
+            api_key: DEFAULT_API_KEY.to_string(),
+        }
+    }
+}
+
+impl MeilisearchConfig {
+    pub fn client(&self) -> Arc<Client> {
+        Arc::new(Client::new(&self.url, &self.api_key))
+    }
+
+    /// Check that Meilisearch answers and accepts the key, so a misconfigured
+    /// instance fails at startup rather than on its first request.
+    pub async fn verify(&self) -> Result<(), AnyError> {
+        let client = self.client();
+        client
+            .health()
+            .await
+            .map_err(|e| format!("Meilisearch at {} is unreachable: {}", self.url, e))?;
+
+        // Health is answered without a key, the version isn't.
+        client.get_version().await.map_err(|e| {
+            format!(
+                "Meilisearch at {} rejected the API key, check --meilisearch-api-key: {}",
+                self.url, e
+            )
+        })?;
+        Ok(())
+    }
+}
+
+/// The document a lookup read, or `None` when Meilisearch has no document
+/// with its id. Only genuine failures, e.g. an unreachable instance, are errors.
+pub fn found<T>(result: Result<T, Error>) -> Result<Option<T>, AnyError> {
+    match result {
+        Ok(document) => Ok(Some(document)),
+        Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::DocumentNotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A Meilisearch instance holding no document nor index, answering every
+/// request like a lookup of a missing document, until the handle stops it.
+#[cfg(test)]
+pub fn missing() -> (MeilisearchConfig, actix_web::dev::ServerHandle) {
+    use actix_web::{web, App, HttpResponse, HttpServer};
+
+    let server = HttpServer::new(|| {
+        App::new().default_service(web::to(|| async {
+            HttpResponse::NotFound().json(serde_json::json!({
+                "message": "Document not found.",
+                "code": "document_not_found",
+                "type": "invalid_request",
+                "link": "https://docs.meilisearch.com/errors#document_not_found",
+            }))
+        }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let config = MeilisearchConfig {
+        url: format!("http://{}", server.addrs()[0]),
+        api_key: DEFAULT_API_KEY.to_string(),
+    };
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+    (config, handle)
+}
+
+#[test]
+fn it_tells_missing_documents_from_failed_lookups() {
+    let error = |code: &str| {
+        let body = serde_json::json!({
+            "message": "",
+            "code": code,
+            "type": "invalid_request",
+            "link": "",
+        });
+        Error::Meilisearch(serde_json::from_value(body).unwrap())
+    };
+
+    assert_eq!(found(Ok::<_, Error>(1)).unwrap(), Some(1));
+    assert_eq!(
+        found::<i32>(Err(error("document_not_found"))).unwrap(),
+        None
+    );
+    assert!(found::<i32>(Err(error("invalid_api_key"))).is_err());
+    assert!(found::<i32>(Err(Error::UnreachableServer)).is_err());
+}
@@ -0,0 +1,435 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, Error, HttpResponse, Responder};
+use tokio::time::Instant;
+
+use crate::kafka::metadata::manager::MetadataManager;
+
+// Operational metrics in the Prometheus text format, kept in memory so they're
+// served while Meilisearch and Kafka are down. Like the probes, `/metrics` is
+// mounted at the root and answered without authentication.
+
+/// The route label of requests that matched no route, so unknown paths don't
+/// each get a series of their own.
+pub const UNMATCHED: &str = "unmatched";
+
+/// The upper bounds of the request latency buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+/// A metric, whose series are told apart by their labels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Family {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: Kind,
+}
+
+pub const HTTP_REQUESTS: Family = Family {
+    name: "seekr_http_requests_total",
+    help: "HTTP requests answered, by route, method and status.",
+    kind: Kind::Counter,
+};
+
+pub const HTTP_REQUEST_DURATION: Family = Family {
+    name: "seekr_http_request_duration_seconds",
+    help: "Time taken to answer HTTP requests, by route and method.",
+    kind: Kind::Histogram,
+};
+
+pub const CLUSTERS_REGISTERED: Family = Family {
+    name: "seekr_clusters_registered",
+    help: "Clusters whose metadata this instance polls.",
+    kind: Kind::Gauge,
+};
+
+pub const METADATA_POLLS: Family = Family {
+    name: "seekr_metadata_polls_total",
+    help: "Metadata polls of each cluster, by outcome.",
+    kind: Kind::Counter,
+};
+
+pub const METADATA_POLL_DURATION: Family = Family {
+    name: "seekr_metadata_poll_duration_seconds",
+    help: "Time taken by the last metadata poll of each cluster.",
+    kind: Kind::Gauge,
+};
+
+pub const METADATA_POLL_QUEUE_WAIT: Family = Family {
+    name: "seekr_metadata_poll_queue_wait_seconds",
+    help: "Time the last metadata poll of each cluster waited for the poll budget once due.",
+    kind: Kind::Gauge,
+};
+
+pub const METADATA_CACHE_AGE: Family = Family {
+    name: "seekr_metadata_cache_age_seconds",
+    help: "Time since the cached metadata of each cluster was last polled successfully.",
+    kind: Kind::Gauge,
+};
+
+/// Counted by the indexer's workers, whose process serves no `/metrics`, so
+/// it isn't among the server's `FAMILIES`; the stage timings report the count.
+pub const SINK_WRITE_FAILURES: Family = Family {
+    name: "seekr_sink_write_failures_total",
+    help:
+        "Consumed messages skipped after the index rejected every write of them, by subscription.",
+    kind: Kind::Counter,
+};
+
+/// Every metric the server exports, in the order they're rendered.
+pub const FAMILIES: &[Family] = &[
+    HTTP_REQUESTS,
+    HTTP_REQUEST_DURATION,
+    CLUSTERS_REGISTERED,
+    METADATA_POLLS,
+    METADATA_POLL_DURATION,
+    METADATA_POLL_QUEUE_WAIT,
+    METADATA_CACHE_AGE,
+];
+
+lazy_static! {
+    static ref REGISTRY: Arc<Registry> = Arc::new(Registry::new(FAMILIES));
+}
+
+/// The registry shared by the server, which `/metrics` renders.
+pub fn registry() -> Arc<Registry> {
+    REGISTRY.clone()
+}
+
+type Labels = Vec<(&'static str, String)>;
+
+enum Sample {
+    Value(f64),
+    Histogram {
+        buckets: [u64; LATENCY_BUCKETS.len()],
+        sum: f64,
+        count: u64,
+    },
+}
+
+/// The current value of every series of a set of metrics.
+pub struct Registry {
+    families: Vec<Family>,
+    series: Mutex<HashMap<&'static str, BTreeMap<Labels, Sample>>>,
+}
+
+impl Registry {
+    pub fn new(families: &[Family]) -> Self {
+        Registry {
+            families: families.to_vec(),
+            series: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add one to a counter.
+    pub fn inc(&self, family: &Family, labels: &[(&'static str, &str)]) {
+        debug_assert_eq!(family.kind, Kind::Counter);
+        self.update(family, labels, |sample| match sample {
+            Some(Sample::Value(v)) => *v += 1.0,
+            _ => *sample = Some(Sample::Value(1.0)),
+        });
+    }
+
+    /// Set a gauge.
+    pub fn set(&self, family: &Family, labels: &[(&'static str, &str)], value: f64) {
+        debug_assert_eq!(family.kind, Kind::Gauge);
+        self.update(family, labels, |sample| {
+            *sample = Some(Sample::Value(value))
+        });
+    }
+
+    /// Count a value into the buckets of a histogram.
+    pub fn observe(&self, family: &Family, labels: &[(&'static str, &str)], value: f64) {
+        debug_assert_eq!(family.kind, Kind::Histogram);
+        self.update(family, labels, |sample| {
+            if !matches!(sample, Some(Sample::Histogram { .. })) {
+                *sample = Some(Sample::Histogram {
+                    buckets: [0; LATENCY_BUCKETS.len()],
+                    sum: 0.0,
+                    count: 0,
+                });
+            }
+            if let Some(Sample::Histogram {
+                buckets,
+                sum,
+                count,
+            }) = sample
+            {
+                for (bucket, bound) in buckets.iter_mut().zip(LATENCY_BUCKETS) {
+                    if value <= bound {
+                        *bucket += 1;
+                    }
+                }
+                *sum += value;
+                *count += 1;
+            }
+        });
+    }
+
+    /// Drop the series of every metric with the given label, e.g. those of a removed cluster.
+    pub fn forget(&self, label: &str, value: &str) {
+        let mut series = self.series.lock().unwrap();
+        for samples in series.values_mut() {
+            samples.retain(|labels, _| !labels.iter().any(|(k, v)| *k == label && v == value));
+        }
+    }
+
+    fn update(
+        &self,
+        family: &Family,
+        labels: &[(&'static str, &str)],
+        f: impl FnOnce(&mut Option<Sample>),
+    ) {
+        let labels = labels
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect::<Labels>();
+        let mut series = self.series.lock().unwrap();
+        let samples = series.entry(family.name).or_default();
+        let mut sample = samples.remove(&labels);
+        f(&mut sample);
+        if let Some(sample) = sample {
+            samples.insert(labels, sample);
+        }
+    }
+
+    /// Render every series in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap();
+        let mut out = String::new();
+        for family in &self.families {
+            let _ = writeln!(out, "# HELP {} {}", family.name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", family.name, family.kind.as_str());
+            let Some(samples) = series.get(family.name) else {
+                continue;
+            };
+            for (labels, sample) in samples {
+                match sample {
+                    Sample::Value(v) => {
+                        let _ = writeln!(out, "{}{} {}", family.name, render_labels(labels), v);
+                    }
+                    Sample::Histogram {
+                        buckets,
+                        sum,
+                        count,
+                    } => {
+                        for (bucket, bound) in buckets.iter().zip(LATENCY_BUCKETS) {
+                            let mut labels = labels.clone();
+                            labels.push(("le", bound.to_string()));
+                            let _ = writeln!(
+                                out,
+                                "{}_bucket{} {}",
+                                family.name,
+                                render_labels(&labels),
+                                bucket
+                            );
+                        }
+                        let mut inf = labels.clone();
+                        inf.push(("le", "+Inf".to_string()));
+                        let _ = writeln!(
+                            out,
+                            "{}_bucket{} {}",
+                            family.name,
+                            render_labels(&inf),
+                            count
+                        );
+                        let _ =
+                            writeln!(out, "{}_sum{} {}", family.name, render_labels(labels), sum);
+                        let _ = writeln!(
+                            out,
+                            "{}_count{} {}",
+                            family.name,
+                            render_labels(labels),
+                            count
+                        );
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn render_labels(labels: &[(&'static str, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels = labels
+        .iter()
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", k, v)
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Count requests and time them, by the pattern of the route they matched.
+pub async fn track(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| UNMATCHED.to_string());
+    let started = Instant::now();
+
+    let res = next.call(req).await;
+    let status = match &res {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+
+    let registry = registry();
+    registry.inc(
+        &HTTP_REQUESTS,
+        &[
+            ("method", &method),
+            ("route", &route),
+            ("status", status.as_str()),
+        ],
+    );
+    registry.observe(
+        &HTTP_REQUEST_DURATION,
+        &[("method", &method), ("route", &route)],
+        started.elapsed().as_secs_f64(),
+    );
+    res
+}
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(metrics);
+}
+
+#[get("/metrics")]
+async fn metrics(manager: Data<MetadataManager>) -> impl Responder {
+    manager.export_metrics().await;
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(registry().render())
+}
+
+#[test]
+fn it_renders_counters_gauges_and_histograms() {
+    let registry = Registry::new(&[METADATA_POLLS, HTTP_REQUEST_DURATION, CLUSTERS_REGISTERED]);
+    registry.inc(
+        &METADATA_POLLS,
+        &[("cluster_id", "1"), ("outcome", "success")],
+    );
+    registry.inc(
+        &METADATA_POLLS,
+        &[("cluster_id", "1"), ("outcome", "success")],
+    );
+    registry.inc(
+        &METADATA_POLLS,
+        &[("cluster_id", "2"), ("outcome", "failure")],
+    );
+    registry.observe(&HTTP_REQUEST_DURATION, &[("route", "/a\"b")], 0.2);
+    registry.observe(&HTTP_REQUEST_DURATION, &[("route", "/a\"b")], 3.0);
+
+    assert_eq!(
+        registry.render(),
+        [
+            "# HELP seekr_metadata_polls_total Metadata polls of each cluster, by outcome.",
+            "# TYPE seekr_metadata_polls_total counter",
+            "seekr_metadata_polls_total{cluster_id=\"1\",outcome=\"success\"} 2",
+            "seekr_metadata_polls_total{cluster_id=\"2\",outcome=\"failure\"} 1",
+            "# HELP seekr_http_request_duration_seconds Time taken to answer HTTP requests, by route and method.",
+            "# TYPE seekr_http_request_duration_seconds histogram",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.005\"} 0",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.01\"} 0",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.025\"} 0",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.05\"} 0",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.1\"} 0",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.25\"} 1",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"0.5\"} 1",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"1\"} 1",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"2.5\"} 1",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"5\"} 2",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"10\"} 2",
+            "seekr_http_request_duration_seconds_bucket{route=\"/a\\\"b\",le=\"+Inf\"} 2",
+            "seekr_http_request_duration_seconds_sum{route=\"/a\\\"b\"} 3.2",
+            "seekr_http_request_duration_seconds_count{route=\"/a\\\"b\"} 2",
+            "# HELP seekr_clusters_registered Clusters whose metadata this instance polls.",
+            "# TYPE seekr_clusters_registered gauge",
+            "",
+        ]
+        .join("\n")
+    );
+
+    registry.forget("cluster_id", "1");
+    assert!(!registry.render().contains("cluster_id=\"1\""));
+}
+
+#[actix_web::test]
+async fn it_serves_request_metrics_without_kafka_or_meilisearch() {
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App};
+
+    use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Data::new(MetadataManager::with_factory(cs, factory));
+    let app = test::init_service(
+        App::new()
+            .wrap(from_fn(track))
+            .app_data(manager)
+            .configure(configure)
+            .route(
+                "/metrics-test/{id}",
+                web::get().to(|| async { HttpResponse::Ok().finish() }),
+            ),
+    )
+    .await;
+
+    for id in [1, 2] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/metrics-test/{}", id))
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let res = test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    assert!(
+        body.contains(
+            "seekr_http_requests_total{method=\"GET\",route=\"/metrics-test/{id}\",status=\"200\"} 2"
+        ),
+        "{}",
+        body
+    );
+    assert!(body.contains(
+        "seekr_http_request_duration_seconds_count{method=\"GET\",route=\"/metrics-test/{id}\"} 2"
+    ));
+    assert!(body.contains("seekr_clusters_registered 0"));
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Json, Path, ServiceConfig};
+use actix_web::{delete, get, post, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Principal;
+use crate::clusters::store::ClusterStore;
+use crate::ids::{ClusterId, MirrorPairId};
+use crate::mirrors::lag::{self, MirrorStatus};
+use crate::mirrors::mirror_pair::{MirrorPair, TopicMapping};
+use crate::mirrors::monitor::MirrorMonitor;
+use crate::mirrors::store::MirrorPairStore;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(create_mirror_pair)
+        .service(get_mirror_pairs)
+        .service(get_mirror_pair)
+        .service(delete_mirror_pair)
+        .service(get_mirror_pair_status);
+}
+
+#[post("")]
+async fn create_mirror_pair(
+    r: Json<CreateMirrorPairRequest>,
+    principal: Principal,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    store: Data<Arc<dyn MirrorPairStore + Send + Sync>>,
+) -> impl Responder {
+    info!("Creating a new mirror pair");
+
+    if r.source_cluster_id == r.target_cluster_id {
+        return HttpResponse::BadRequest().body("Source and target clusters must differ");
+    }
+
+    if let Err(e) = lag::resolve(&r.topic_mapping, &[]) {
+        return HttpResponse::BadRequest().body(format!("Invalid topic mapping: {}", e));
+    }
+
+    for id in [r.source_cluster_id, r.target_cluster_id] {
+        match cs.get(id).await {
+            Ok(Some(_)) if principal.can_access(id) => {}
+            Ok(_) => {
+                return HttpResponse::NotFound().body(format!("Cluster with id '{}' not found", id))
+            }
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+
+    let pair = MirrorPair::new(
+        None,
+        r.source_cluster_id,
+        r.target_cluster_id,
+        r.topic_mapping.clone(),
+    );
+
+    match store.insert(pair).await {
+        Ok(id) => HttpResponse::Ok().json(CreateMirrorPairResponse { id }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("")]
+async fn get_mirror_pairs(
+    principal: Principal,
+    store: Data<Arc<dyn MirrorPairStore + Send + Sync>>,
+) -> impl Responder {
+    info!("Fetching all mirror pairs");
+
+    match store.list().await {
+        Ok(pairs) => {
+            let mirror_pairs = pairs
+                .into_iter()
+                .filter(|p| is_granted(&principal, p))
+                .collect();
+            HttpResponse::Ok().json(ListMirrorPairsResponse { mirror_pairs })
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{id}")]
+async fn get_mirror_pair(
+    id: Path<MirrorPairId>,
+    principal: Principal,
+    store: Data<Arc<dyn MirrorPairStore + Send + Sync>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    info!("Fetching mirror pair with id {}", id);
+
+    match store.get(id).await {
+        Ok(Some(mirror_pair)) if is_granted(&principal, &mirror_pair) => {
+            HttpResponse::Ok().json(ReadMirrorPairResponse { mirror_pair })
+        }
+        Ok(_) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[delete("/{id}")]
+async fn delete_mirror_pair(
+    id: Path<MirrorPairId>,
+    principal: Principal,
+    store: Data<Arc<dyn MirrorPairStore + Send + Sync>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    info!("Deleting mirror pair with id {}", id);
+
+    match store.get(id).await {
+        Ok(Some(pair)) if is_granted(&principal, &pair) => {}
+        Ok(_) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    match store.remove(id).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{id}/status")]
+async fn get_mirror_pair_status(
+    id: Path<MirrorPairId>,
+    principal: Principal,
+    store: Data<Arc<dyn MirrorPairStore + Send + Sync>>,
+    monitor: Data<MirrorMonitor>,
+) -> impl Responder {
+    let id = id.into_inner();
+    info!("Fetching status of mirror pair with id {}", id);
+
+    match store.get(id).await {
+        Ok(Some(pair)) if is_granted(&principal, &pair) => {}
+        Ok(_) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let status = monitor
+        .status(id)
+        .await
+        .unwrap_or_else(MirrorStatus::pending);
+
+    HttpResponse::Ok().json(status)
+}
+
+/// Pairs are only visible to keys granted both of their clusters.
+fn is_granted(principal: &Principal, pair: &MirrorPair) -> bool {
+    principal.can_access(pair.source_cluster_id) && principal.can_access(pair.target_cluster_id)
+}
+
+#[derive(Deserialize)]
+struct CreateMirrorPairRequest {
+    source_cluster_id: ClusterId,
+    target_cluster_id: ClusterId,
+    topic_mapping: TopicMapping,
+}
+
+#[derive(Serialize)]
+struct CreateMirrorPairResponse {
+    id: MirrorPairId,
+}
+
+#[derive(Serialize)]
+struct ListMirrorPairsResponse {
+    mirror_pairs: Vec<MirrorPair>,
+}
+
+#[derive(Serialize)]
+struct ReadMirrorPairResponse {
+    mirror_pair: MirrorPair,
+}
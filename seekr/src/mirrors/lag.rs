@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::kafka::metadata::classify::TopicCategory;
+use crate::kafka::metadata::{TopicMetadata, TopicOffsets};
+
+use super::mirror_pair::TopicMapping;
+
+/// The state of a mirror pair, exchanged as its variant name.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub enum MirrorState {
+    /// Waiting for the first watermarks of both clusters.
+    Pending,
+    Running,
+    Errored,
+}
+
+/// Replication lag of a single mirrored topic.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TopicLag {
+    pub source_topic: String,
+    pub target_topic: String,
+
+    /// Records on the source not yet on the target, summed over partitions.
+    pub lag: i64,
+
+    /// Age of the newest record on the target while it lags behind the
+    /// source, `0` when caught up and unknown when the target is empty.
+    pub staleness_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MirrorStatus {
+    pub state: MirrorState,
+    pub error: Option<String>,
+    pub topics: Vec<TopicLag>,
+    pub total_lag: i64,
+    pub max_staleness_ms: Option<i64>,
+
+    /// Mapped source topics that do not exist on the target.
+    pub missing_on_target: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MirrorStatus {
+    pub fn pending() -> Self {
+        Self::with_state(MirrorState::Pending, None)
+    }
+
+    pub fn errored(error: String) -> Self {
+        Self::with_state(MirrorState::Errored, Some(error))
+    }
+
+    fn with_state(state: MirrorState, error: Option<String>) -> Self {
+        Self {
+            state,
+            error,
+            topics: vec![],
+            total_lag: 0,
+            max_staleness_ms: None,
+            missing_on_target: vec![],
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Resolve the (source, target) topic names mirrored between the clusters.
+///
+/// Kafka's internal topics are never mirrored.
+pub fn resolve(
+    mapping: &TopicMapping,
+    source_topics: &[TopicMetadata],
+) -> Result<Vec<(String, String)>, regex::Error> {
+    let topics = source_topics
+        .iter()
+        .filter(|t| t.category != TopicCategory::Internal)
+        .map(|t| &t.name);
+
+    let mut resolved = match mapping {
+        TopicMapping::Regex {
+            pattern,
+            replacement,
+        } => {
+            let re = Regex::new(&format!("^(?:{})$", pattern))?;
+            topics
+                .filter(|t| re.is_match(t))
+                .map(|t| (t.clone(), re.replace(t, replacement.as_str()).into_owned()))
+                .collect::<Vec<_>>()
+        }
+        TopicMapping::Explicit(renames) => topics
+            .filter_map(|t| renames.get(t).map(|target| (t.clone(), target.clone())))
+            .collect::<Vec<_>>(),
+    };
+
+    resolved.sort();
+    Ok(resolved)
+}
+
+/// Compare the high watermarks of a source topic and its mirror.
+pub fn topic_lag(source: &TopicOffsets, target: &TopicOffsets, now_ms: i64) -> TopicLag {
+    let target_highs = target
+        .partitions
+        .iter()
+        .map(|p| (p.id, p.high))
+        .collect::<HashMap<_, _>>();
+
+    let lag = source
+        .partitions
+        .iter()
+        .map(|p| (p.high - target_highs.get(&p.id).copied().unwrap_or(0)).max(0))
+        .sum::<i64>();
+
+    let staleness_ms = match lag {
+        0 => Some(0),
+        _ => target.head_timestamp.map(|ts| (now_ms - ts).max(0)),
+    };
+
+    TopicLag {
+        source_topic: source.name.clone(),
+        target_topic: target.name.clone(),
+        lag,
+        staleness_ms,
+    }
+}
+
+/// Compute the replication status of every resolved topic.
+pub fn status(
+    resolved: &[(String, String)],
+    source: &[TopicOffsets],
+    target: &[TopicOffsets],
+    now: DateTime<Utc>,
+) -> MirrorStatus {
+    let source = source
+        .iter()
+        .map(|t| (t.name.as_str(), t))
+        .collect::<HashMap<_, _>>();
+    let target = target
+        .iter()
+        .map(|t| (t.name.as_str(), t))
+        .collect::<HashMap<_, _>>();
+
+    let mut topics = Vec::new();
+    let mut missing_on_target = Vec::new();
+
+    for (s, t) in resolved {
+        // Newly resolved topics have no watermarks until the next poll.
+        let Some(source) = source.get(s.as_str()) else {
+            continue;
+        };
+
+        match target.get(t.as_str()) {
+            Some(target) => topics.push(topic_lag(source, target, now.timestamp_millis())),
+            None => missing_on_target.push(s.clone()),
+        }
+    }
+
+    MirrorStatus {
+        state: MirrorState::Running,
+        error: None,
+        total_lag: topics.iter().map(|t| t.lag).sum(),
+        max_staleness_ms: topics.iter().filter_map(|t| t.staleness_ms).max(),
+        topics,
+        missing_on_target,
+        updated_at: now,
+    }
+}
+
+#[cfg(test)]
+fn offsets(name: &str, highs: &[i64], head_timestamp: Option<i64>) -> TopicOffsets {
+    use crate::kafka::metadata::PartitionOffsets;
+
+    TopicOffsets {
+        name: name.to_string(),
+        partitions: highs
+            .iter()
+            .enumerate()
+            .map(|(id, &high)| PartitionOffsets {
+                id: id as i32,
+                low: 0,
+                high,
+            })
+            .collect(),
+        head_timestamp,
+    }
+}
+
+#[cfg(test)]
+fn topics(names: &[&str]) -> Vec<TopicMetadata> {
+    let topics = names.iter().map(|&n| (n, 1)).collect::<Vec<_>>();
+    crate::history::diff::metadata(&[1], &topics).topics
+}
+
+#[test]
+fn it_resolves_renamed_topics() {
+    let source = topics(&["orders", "payments", "__consumer_offsets"]);
+
+    let mapping = TopicMapping::Regex {
+        pattern: "(.*)".to_string(),
+        replacement: "east.$1".to_string(),
+    };
+    assert_eq!(
+        resolve(&mapping, &source).unwrap(),
+        vec![
+            ("orders".to_string(), "east.orders".to_string()),
+            ("payments".to_string(), "east.payments".to_string()),
+        ]
+    );
+
+    let mapping = TopicMapping::Explicit(HashMap::from([
+        ("orders".to_string(), "orders-replica".to_string()),
+        ("absent".to_string(), "absent-replica".to_string()),
+    ]));
+    assert_eq!(
+        resolve(&mapping, &source).unwrap(),
+        vec![("orders".to_string(), "orders-replica".to_string())]
+    );
+}
+
+#[test]
+fn it_computes_lag_and_staleness_of_renamed_topics() {
+    let now = Utc::now();
+    let resolved = vec![("orders".to_string(), "east.orders".to_string())];
+    let source = [offsets("orders", &[100, 50], None)];
+    let target = [offsets(
+        "east.orders",
+        &[90, 50],
+        Some(now.timestamp_millis() - 3_000),
+    )];
+
+    let status = status(&resolved, &source, &target, now);
+
+    assert_eq!(status.topics.len(), 1);
+    assert_eq!(status.topics[0].target_topic, "east.orders");
+    assert_eq!(status.topics[0].lag, 10);
+    assert_eq!(status.topics[0].staleness_ms, Some(3_000));
+    assert_eq!(status.total_lag, 10);
+    assert!(status.missing_on_target.is_empty());
+}
+
+#[test]
+fn it_reports_topics_missing_on_the_target() {
+    let resolved = vec![
+        ("orders".to_string(), "east.orders".to_string()),
+        ("payments".to_string(), "east.payments".to_string()),
+    ];
+    let source = [
+        offsets("orders", &[5], None),
+        offsets("payments", &[7], None),
+    ];
+    let target = [offsets("east.orders", &[5], None)];
+
+    let status = status(&resolved, &source, &target, Utc::now());
+
+    assert_eq!(status.missing_on_target, vec!["payments".to_string()]);
+    assert_eq!(status.total_lag, 0);
+}
+
+#[test]
+fn it_handles_empty_topics() {
+    let resolved = vec![("orders".to_string(), "east.orders".to_string())];
+
+    // Both sides empty, nothing to replicate.
+    let source = [offsets("orders", &[0, 0], None)];
+    let target = [offsets("east.orders", &[0, 0], None)];
+    let empty = status(&resolved, &source, &target, Utc::now());
+    assert_eq!(empty.topics[0].lag, 0);
+    assert_eq!(empty.topics[0].staleness_ms, Some(0));
+
+    // An empty target with a missing partition lags by the whole source.
+    let source = [offsets("orders", &[3, 4], None)];
+    let target = [offsets("east.orders", &[0], None)];
+    let empty = status(&resolved, &source, &target, Utc::now());
+    assert_eq!(empty.topics[0].lag, 7);
+    assert_eq!(empty.topics[0].staleness_ms, None);
+}
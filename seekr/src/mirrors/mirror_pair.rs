@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ids::{ClusterId, MirrorPairId};
+
+/// How topics on the source cluster are named on the target cluster.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicMapping {
+    /// Mirror every source topic matching `pattern`, renamed with `replacement`.
+    ///
+    /// The replacement may reference capture groups, e.g. MirrorMaker2's default
+    /// naming is `{"pattern": "(.*)", "replacement": "source.$1"}`.
+    Regex {
+        pattern: String,
+        #[serde(default = "default_replacement")]
+        replacement: String,
+    },
+
+    /// Mirror the listed source topics to the given target topic names.
+    Explicit(HashMap<String, String>),
+}
+
+fn default_replacement() -> String {
+    "$0".to_string()
+}
+
+/// Two clusters kept in sync by an external replicator such as MirrorMaker2.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MirrorPair {
+    /// Specifies the unique identifier of the mirror pair.
+    pub id: MirrorPairId,
+
+    /// The cluster topics are replicated from.
+    pub source_cluster_id: ClusterId,
+
+    /// The cluster topics are replicated to.
+    pub target_cluster_id: ClusterId,
+
+    /// How source topics are named on the target.
+    pub topic_mapping: TopicMapping,
+
+    /// Represents the point in time in UTC Epoch time, when the mirror pair was created.
+    pub created_at: DateTime<Utc>,
+
+    /// Represents the point in time in UTC Epoch time, when the mirror pair was modified.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MirrorPair {
+    pub fn new(
+        id: Option<MirrorPairId>,
+        source_cluster_id: ClusterId,
+        target_cluster_id: ClusterId,
+        topic_mapping: TopicMapping,
+    ) -> Self {
+        MirrorPair {
+            id: id.unwrap_or_default(),
+            source_cluster_id,
+            target_cluster_id,
+            topic_mapping,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod endpoints;
+pub mod lag;
+pub mod mirror_pair;
+pub mod monitor;
+pub mod store;
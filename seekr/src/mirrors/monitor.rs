@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::RwLock;
+use tokio::time::interval;
+
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::ids::{ClusterId, MirrorPairId};
+use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataManager};
+use crate::shutdown::Shutdown;
+
+use super::lag::{self, MirrorStatus};
+use super::mirror_pair::MirrorPair;
+use super::store::MirrorPairStore;
+
+/// How often mirror statuses are recomputed from the cached watermarks.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Computes the replication lag of every mirror pair.
+///
+/// Watermarks are fetched by the metadata manager on each cluster's own poll
+/// interval, the monitor only tells it which topics to watch and reads the cache.
+pub struct MirrorMonitor {
+    pairs: Arc<dyn MirrorPairStore + Send + Sync>,
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    manager: Arc<MetadataManager>,
+    state: RwLock<State>,
+    sd: Shutdown,
+}
+
+#[derive(Default)]
+struct State {
+    statuses: HashMap<MirrorPairId, MirrorStatus>,
+    watched: HashSet<ClusterId>,
+}
+
+impl MirrorMonitor {
+    pub fn new(
+        pairs: Arc<dyn MirrorPairStore + Send + Sync>,
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        manager: Arc<MetadataManager>,
+    ) -> Self {
+        Self {
+            pairs,
+            clusters,
+            manager,
+            state: RwLock::new(State::default()),
+            sd: Shutdown::new(),
+        }
+    }
+
+    pub async fn start(self: Arc<Self>) {
+        debug!("Starting mirror monitor...");
+
+        tokio::spawn(async move { self.poll().await });
+    }
+
+    pub async fn stop(self: Arc<Self>) {
+        debug!("Stopping mirror monitor...");
+
+        self.sd.begin();
+        self.sd.wait_complete().await;
+    }
+
+    /// The last computed status of a mirror pair.
+    pub async fn status(&self, id: MirrorPairId) -> Option<MirrorStatus> {
+        self.state.read().await.statuses.get(&id).cloned()
+    }
+
+    async fn poll(self: Arc<Self>) {
+        let mut interval = interval(REFRESH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.refresh().await {
+                        warn!("Failed to refresh mirror statuses - {}", e);
+                    }
+                }
+                _ = self.sd.wait_begin() => {
+                    debug!("Mirror monitor poll shutdown started...");
+                    self.sd.complete();
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn refresh(&self) -> Result<(), AnyError> {
+        let pairs = self.pairs.list().await?;
+
+        let mut watches = HashMap::new();
+        let mut statuses = HashMap::new();
+        for pair in pairs {
+            let status = self.evaluate(&pair, &mut watches).await;
+            statuses.insert(pair.id, status);
+        }
+
+        let mut state = self.state.write().await;
+        for id in state.watched.iter().filter(|id| !watches.contains_key(id)) {
+            self.manager.watch(*id, HashMap::new()).await;
+        }
+
+        state.watched = watches.keys().copied().collect();
+        for (id, topics) in watches {
+            self.manager.watch(id, topics).await;
+        }
+
+        state.statuses = statuses;
+        Ok(())
+    }
+
+    async fn evaluate(
+        &self,
+        pair: &MirrorPair,
+        watches: &mut HashMap<ClusterId, HashMap<String, bool>>,
+    ) -> MirrorStatus {
+        for id in [pair.source_cluster_id, pair.target_cluster_id] {
+            match self.clusters.get(id).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return MirrorStatus::errored(format!("cluster {} was deleted", id)),
+                Err(e) => return MirrorStatus::errored(e.to_string()),
+            }
+        }
+
+        let entry = self.manager.clone().get(pair.source_cluster_id, None).await;
+        let Ok(Some(CachedMetadataEntry::Meta(metadata))) = entry.as_ref().map(Option::as_deref)
+        else {
+            return MirrorStatus::pending();
+        };
+
+        let resolved = match lag::resolve(&pair.topic_mapping, &metadata.topics) {
+            Ok(resolved) => resolved,
+            Err(e) => return MirrorStatus::errored(format!("invalid topic mapping: {}", e)),
+        };
+
+        // Only the target's head is sampled for staleness.
+        for (s, t) in &resolved {
+            let source = watches.entry(pair.source_cluster_id).or_default();
+            source.entry(s.clone()).or_insert(false);
+            let target = watches.entry(pair.target_cluster_id).or_default();
+            target.insert(t.clone(), true);
+        }
+
+        let source = self.manager.offsets(pair.source_cluster_id).await;
+        let target = self.manager.offsets(pair.target_cluster_id).await;
+        let (Some(source), Some(target)) = (source, target) else {
+            return MirrorStatus::pending();
+        };
+
+        lag::status(&resolved, &source, &target, Utc::now())
+    }
+}
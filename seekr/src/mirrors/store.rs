@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+
+use crate::errors::AnyError;
+use crate::id;
+use crate::ids::MirrorPairId;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+use super::mirror_pair::MirrorPair;
+
+#[async_trait]
+pub trait MirrorPairStore {
+    async fn list(&self) -> Result<Vec<MirrorPair>, AnyError>;
+    async fn get(&self, id: MirrorPairId) -> Result<Option<MirrorPair>, AnyError>;
+    async fn insert(&self, pair: MirrorPair) -> Result<MirrorPairId, AnyError>;
+    async fn remove(&self, id: MirrorPairId) -> Result<MirrorPairId, AnyError>;
+}
+
+pub const INDEX_NAME: &str = "mirror_pairs";
+
+pub struct MSMirrorPairStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+    /// A Distributed Unique ID generator.
+    generator: Arc<id::Generator>,
+}
+
+impl MSMirrorPairStore {
+    pub async fn new(client: Arc<Client>, generator: Arc<id::Generator>) -> Self {
+        if let Ok(task) = client.clone().create_index(INDEX_NAME, Some("id")).await {
+            task.wait_for_completion(&client, None, None).await.unwrap();
+        }
+
+        Self { client, generator }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+}
+
+#[async_trait]
+impl MirrorPairStore for MSMirrorPairStore {
+    async fn list(&self) -> Result<Vec<MirrorPair>, AnyError> {
+        let pairs = self.index().get_documents::<MirrorPair>().await?;
+        Ok(pairs.results)
+    }
+
+    async fn get(&self, id: MirrorPairId) -> Result<Option<MirrorPair>, AnyError> {
+        let pair = self
+            .index()
+            .get_document::<MirrorPair>(&id.to_string())
+            .await;
+
+        meilisearch::found(pair)
+    }
+
+    async fn insert(&self, pair: MirrorPair) -> Result<MirrorPairId, AnyError> {
+        let pair = MirrorPair {
+            id: MirrorPairId(self.generator.next_id()?),
+            ..pair
+        };
+
+        self.index()
+            .add_or_replace(&[&pair], Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(pair.id)
+    }
+
+    async fn remove(&self, id: MirrorPairId) -> Result<MirrorPairId, AnyError> {
+        self.index().delete_document(id.to_string()).await?;
+        Ok(id)
+    }
+}
+
+/// An in-memory store used to exercise mirror pair consumers in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryMirrorPairStore {
+    pairs: tokio::sync::RwLock<std::collections::BTreeMap<MirrorPairId, MirrorPair>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl MirrorPairStore for MemoryMirrorPairStore {
+    async fn list(&self) -> Result<Vec<MirrorPair>, AnyError> {
+        Ok(self.pairs.read().await.values().cloned().collect())
+    }
+
+    async fn get(&self, id: MirrorPairId) -> Result<Option<MirrorPair>, AnyError> {
+        Ok(self.pairs.read().await.get(&id).cloned())
+    }
+
+    async fn insert(&self, pair: MirrorPair) -> Result<MirrorPairId, AnyError> {
+        let mut pairs = self.pairs.write().await;
+        let id = MirrorPairId(pairs.keys().last().map_or(1, |id| id.as_i64() + 1));
+        pairs.insert(id, MirrorPair { id, ..pair });
+        Ok(id)
+    }
+
+    async fn remove(&self, id: MirrorPairId) -> Result<MirrorPairId, AnyError> {
+        self.pairs.write().await.remove(&id);
+        Ok(id)
+    }
+}
+
+pub async fn init_mirror_pair_store(
+    config: &MeilisearchConfig,
+) -> Arc<dyn MirrorPairStore + Send + Sync> {
+    Arc::new(MSMirrorPairStore::new(config.client(), id::generator()).await)
+}
@@ -0,0 +1,164 @@
+use std::future::Future;
+
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::indexes::Index;
+use serde::de::DeserializeOwned;
+
+use crate::errors::AnyError;
+
+/// How many documents are read from Meilisearch at once for a page without a limit.
+const BATCH: usize = 1000;
+
+/// The window of items a store lists, `Page::ALL` for every item.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Page {
+    /// How many items are skipped.
+    pub offset: usize,
+
+    /// The most items listed, all of the remaining ones when `None`.
+    pub limit: Option<usize>,
+}
+
+impl Page {
+    pub const ALL: Page = Page {
+        offset: 0,
+        limit: None,
+    };
+
+    pub fn new(offset: usize, limit: Option<usize>) -> Self {
+        Self { offset, limit }
+    }
+
+    /// The items of the page, out of all the items in order. An offset past
+    /// the end gives no items.
+    pub fn slice<T>(&self, items: impl IntoIterator<Item = T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// How many rows a store without offsets reads to skip to the end of the page.
+    pub fn end(&self) -> Option<usize> {
+        self.limit.map(|l| self.offset.saturating_add(l))
+    }
+
+    /// Read the page in batches of at most `BATCH` items, `fetch` being given
+    /// the offset and limit of each, until a batch comes back short.
+    async fn batched<T, F, Fut>(&self, mut fetch: F) -> Result<Vec<T>, AnyError>
+    where
+        F: FnMut(usize, usize) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, AnyError>>,
+    {
+        let mut items = vec![];
+        loop {
+            let limit = self
+                .limit
+                .map_or(BATCH, |l| l.saturating_sub(items.len()).min(BATCH));
+            if limit == 0 {
+                return Ok(items);
+            }
+
+            let batch = fetch(self.offset + items.len(), limit).await?;
+            let short = batch.len() < limit;
+            items.extend(batch);
+            if short {
+                return Ok(items);
+            }
+        }
+    }
+}
+
+/// The documents of the index in the page, in the index's order.
+pub async fn documents<T>(index: &Index, page: Page) -> Result<Vec<T>, AnyError>
+where
+    T: DeserializeOwned + 'static,
+{
+    page.batched(|offset, limit| async move {
+        let docs = index
+            .get_documents_with::<T>(
+                DocumentsQuery::new(index)
+                    .with_offset(offset)
+                    .with_limit(limit),
+            )
+            .await?;
+        Ok(docs.results)
+    })
+    .await
+}
+
+/// The documents of the index matching the filter in the page.
+///
+/// Searches don't reach past the index's `maxTotalHits`, 1000 unless changed.
+pub async fn hits<T>(index: &Index, filter: &str, page: Page) -> Result<Vec<T>, AnyError>
+where
+    T: DeserializeOwned + 'static,
+{
+    page.batched(|offset, limit| async move {
+        let results = index
+            .search()
+            .with_filter(filter)
+            .with_offset(offset)
+            .with_limit(limit)
+            .execute::<T>()
+            .await?;
+        Ok(results.hits.into_iter().map(|h| h.result).collect())
+    })
+    .await
+}
+
+/// How many documents of the index match the filter.
+pub async fn count(index: &Index, filter: Option<&str>) -> Result<usize, AnyError> {
+    let Some(filter) = filter else {
+        return Ok(index.get_stats().await?.number_of_documents);
+    };
+
+    let results = index
+        .search()
+        .with_filter(filter)
+        .with_limit(0)
+        .execute::<serde_json::Value>()
+        .await?;
+    Ok(results.estimated_total_hits.unwrap_or_default())
+}
+
+#[test]
+fn it_slices_pages() {
+    let items = 1..=5;
+    assert_eq!(Page::ALL.slice(items.clone()), vec![1, 2, 3, 4, 5]);
+    assert_eq!(Page::new(1, Some(2)).slice(items.clone()), vec![2, 3]);
+    assert_eq!(Page::new(4, Some(3)).slice(items.clone()), vec![5]);
+    assert_eq!(Page::new(9, None).slice(items), Vec::<i32>::new());
+    assert_eq!(Page::new(10, Some(5)).end(), Some(15));
+    assert_eq!(Page::ALL.end(), None);
+}
+
+#[tokio::test]
+async fn it_reads_pages_in_batches() {
+    use std::sync::Mutex;
+
+    let all = (0..2_500).collect::<Vec<_>>();
+    let calls = Mutex::new(vec![]);
+    let fetch = |offset: usize, limit: usize| {
+        calls.lock().unwrap().push((offset, limit));
+        let batch = all.iter().skip(offset).take(limit).copied().collect();
+        async move { Ok::<_, AnyError>(batch) }
+    };
+
+    let items = Page::ALL.batched(fetch).await.unwrap();
+    assert_eq!(items, all);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![(0, 1_000), (1_000, 1_000), (2_000, 1_000)]
+    );
+
+    calls.lock().unwrap().clear();
+    let items = Page::new(900, Some(1_200)).batched(fetch).await.unwrap();
+    assert_eq!(items.len(), 1_200);
+    assert_eq!(items[0], 900);
+    assert_eq!(*calls.lock().unwrap(), vec![(900, 1_000), (1_900, 200)]);
+
+    let past = Page::new(3_000, Some(10)).batched(fetch).await.unwrap();
+    assert!(past.is_empty());
+}
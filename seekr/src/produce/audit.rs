@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::governance::owner::Owner;
+use crate::ids::ClusterId;
+
+/// The log target audit records are written to.
+pub const AUDIT_TARGET: &str = "seekr::audit";
+
+/// A record of an accepted produce request.
+///
+/// Only a hash of the payload is kept, so audit logs never leak message contents.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditRecord {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub cluster_id: ClusterId,
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+
+    /// Hex encoded SHA-256 hash of the payload.
+    pub payload_hash: String,
+    pub payload_bytes: usize,
+
+    /// Who to contact about the cluster.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+}
+
+pub trait AuditLog {
+    fn record(&self, record: AuditRecord);
+}
+
+/// Writes audit records as JSON lines to the `seekr::audit` log target.
+pub struct LogAuditLog;
+
+impl AuditLog for LogAuditLog {
+    fn record(&self, record: AuditRecord) {
+        match serde_json::to_string(&record) {
+            Ok(line) => info!(target: AUDIT_TARGET, "{}", line),
+            Err(e) => error!(target: AUDIT_TARGET, "Unable to serialize audit record: {}", e),
+        }
+    }
+}
+
+/// Collects audit records in memory for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryAuditLog {
+    pub records: std::sync::Mutex<Vec<AuditRecord>>,
+}
+
+#[cfg(test)]
+impl AuditLog for MemoryAuditLog {
+    fn record(&self, record: AuditRecord) {
+        self.records.lock().unwrap().push(record);
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use actix_web::web::{Data, Json, Path, ServiceConfig};
+use actix_web::{post, put, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::Principal;
+use crate::clusters::store::ClusterStore;
+use crate::ids::ClusterId;
+use crate::kafka::producer::MessageProducer;
+use crate::produce::audit::AuditLog;
+use crate::produce::schema::{FieldError, Schema, SchemaKind};
+use crate::produce::store::{SchemaStore, TopicSchema};
+use crate::produce::{self, ProduceError, ProduceRequest};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(put_produce_schema).service(produce_message);
+}
+
+#[put("/{id}/topics/{topic}/produce-schema")]
+async fn put_produce_schema(
+    path: Path<(ClusterId, String)>,
+    r: Json<PutSchemaRequest>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    schemas: Data<Arc<dyn SchemaStore + Send + Sync>>,
+) -> impl Responder {
+    let (id, topic) = path.into_inner();
+    info!(
+        "Updating produce schema of topic {} in cluster with id {}",
+        topic, id
+    );
+
+    match cs.get(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    if let Err(e) = Schema::compile(r.kind, &r.schema) {
+        return HttpResponse::BadRequest().body(format!("Invalid schema: {}", e));
+    }
+
+    let schema = TopicSchema::new(id, topic, r.kind, r.schema.clone());
+
+    match schemas.put(schema).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[post("/{id}/topics/{topic}/messages")]
+async fn produce_message(
+    principal: Principal,
+    path: Path<(ClusterId, String)>,
+    r: Json<ProduceRequest>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    schemas: Data<Arc<dyn SchemaStore + Send + Sync>>,
+    producer: Data<Arc<dyn MessageProducer + Send + Sync>>,
+    audit: Data<Arc<dyn AuditLog + Send + Sync>>,
+) -> impl Responder {
+    let (id, topic) = path.into_inner();
+    info!("Producing to topic {} in cluster with id {}", topic, id);
+
+    let cluster = match cs.get(id).await {
+        Ok(Some(c)) => c,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    // Taken from the authenticated key, as callers could claim anyone in a header.
+    let actor = principal.actor();
+
+    let result = produce::produce(
+        &cluster,
+        &topic,
+        r.into_inner(),
+        &actor,
+        schemas.get_ref().as_ref(),
+        producer.get_ref().as_ref(),
+        audit.get_ref().as_ref(),
+    )
+    .await;
+
+    match result {
+        Ok(produced) => HttpResponse::Ok().json(produced),
+        Err(ProduceError::Disabled) => HttpResponse::Forbidden().body(format!(
+            "Producing is disabled for cluster '{}', set produce.enabled to enable it",
+            id
+        )),
+        Err(ProduceError::TopicNotAllowed(topic)) => HttpResponse::Forbidden().body(format!(
+            "Topic '{}' is not allowed by produce.topics.regex",
+            topic
+        )),
+        Err(ProduceError::PayloadTooLarge { size, max }) => {
+            HttpResponse::PayloadTooLarge().body(format!(
+                "Payload of {} bytes exceeds the limit of {} bytes",
+                size, max
+            ))
+        }
+        Err(ProduceError::InvalidPayload(errors)) => {
+            HttpResponse::UnprocessableEntity().json(SchemaMismatchResponse {
+                code: "schema_mismatch",
+                errors,
+            })
+        }
+        Err(ProduceError::Misconfigured(e)) => HttpResponse::InternalServerError().body(e),
+        Err(ProduceError::Failed(e)) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct PutSchemaRequest {
+    #[serde(default)]
+    kind: SchemaKind,
+    schema: Value,
+}
+
+#[derive(Serialize)]
+struct SchemaMismatchResponse {
+    code: &'static str,
+    errors: Vec<FieldError>,
+}
+
+#[actix_web::test]
+async fn it_audits_the_authenticated_key_as_the_actor() {
+    use std::collections::HashMap;
+
+    use actix_web::{test, App, HttpMessage};
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::ids::ApiKeyId;
+    use crate::produce::audit::MemoryAuditLog;
+    use crate::produce::store::MemorySchemaStore;
+    use crate::produce::RecordingProducer;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let config = HashMap::from([(
+        crate::kafka::config::PRODUCE_ENABLED.to_string(),
+        "true".to_string(),
+    )]);
+    let cluster = Cluster::new(Some(ClusterId(1)), Kind::Kafka, "test".to_string(), config);
+    cs.put(cluster).await.unwrap();
+
+    let audit = Arc::new(MemoryAuditLog::default());
+    let schemas: Arc<dyn SchemaStore + Send + Sync> = Arc::new(MemorySchemaStore::default());
+    let producer: Arc<dyn MessageProducer + Send + Sync> = Arc::new(RecordingProducer::default());
+    let log: Arc<dyn AuditLog + Send + Sync> = audit.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(schemas))
+            .app_data(Data::new(producer))
+            .app_data(Data::new(log))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/clusters/1/topics/orders/messages")
+        .insert_header(("x-seekr-actor", "mallory"))
+        .set_json(serde_json::json!({ "payload": { "total": 10 } }))
+        .to_request();
+    req.extensions_mut().insert(Principal {
+        key_id: Some(ApiKeyId(7)),
+        ..Principal::root()
+    });
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success(), "{}", resp.status());
+
+    let records = audit.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].actor, "key:7");
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::kafka::config;
+use crate::kafka::producer::{MessageProducer, OutgoingMessage};
+
+use self::audit::{AuditLog, AuditRecord};
+use self::schema::{FieldError, Schema};
+use self::store::SchemaStore;
+
+pub mod audit;
+pub mod endpoints;
+pub mod schema;
+pub mod store;
+
+/// Default maximum size of a produced payload, in bytes.
+pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+/// Produce guardrails resolved from the cluster config.
+#[derive(Debug, Clone)]
+pub struct ProduceConfig {
+    /// Producing is disabled unless `produce.enabled` is set to `true`.
+    pub enabled: bool,
+
+    /// Topics that may be written to, all topics when unset.
+    pub topics: Option<Regex>,
+
+    pub max_payload_bytes: usize,
+}
+
+impl ProduceConfig {
+    pub fn from(cluster: &Cluster) -> Result<Self, ProduceError> {
+        let get = |key: &str| cluster.config.get(key);
+
+        // Anchor the allowlist, so `orders` does not admit `orders-internal`.
+        let topics = match get(config::PRODUCE_TOPICS_REGEX) {
+            Some(r) => Some(Regex::new(&format!("^(?:{})$", r)).map_err(|e| {
+                ProduceError::Misconfigured(format!(
+                    "invalid {}: {}",
+                    config::PRODUCE_TOPICS_REGEX,
+                    e
+                ))
+            })?),
+            None => None,
+        };
+
+        Ok(Self {
+            enabled: get(config::PRODUCE_ENABLED).is_some_and(|v| v == "true"),
+            topics,
+            max_payload_bytes: get(config::PRODUCE_MAX_PAYLOAD_BYTES)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES),
+        })
+    }
+
+    pub fn allows(&self, topic: &str) -> bool {
+        self.topics.as_ref().is_none_or(|r| r.is_match(topic))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProduceRequest {
+    pub key: Option<String>,
+    pub payload: Value,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Produced {
+    pub partition: i32,
+    pub offset: i64,
+}
+
+#[derive(Debug)]
+pub enum ProduceError {
+    Disabled,
+    TopicNotAllowed(String),
+    PayloadTooLarge { size: usize, max: usize },
+    InvalidPayload(Vec<FieldError>),
+    Misconfigured(String),
+    Failed(AnyError),
+}
+
+impl From<AnyError> for ProduceError {
+    fn from(e: AnyError) -> Self {
+        ProduceError::Failed(e)
+    }
+}
+
+/// Validate a produce request against the cluster guardrails and write it to the topic.
+pub async fn produce(
+    cluster: &Cluster,
+    topic: &str,
+    request: ProduceRequest,
+    actor: &str,
+    schemas: &(dyn SchemaStore + Send + Sync),
+    producer: &(dyn MessageProducer + Send + Sync),
+    audit: &(dyn AuditLog + Send + Sync),
+) -> Result<Produced, ProduceError> {
+    let config = ProduceConfig::from(cluster)?;
+
+    if !config.enabled {
+        return Err(ProduceError::Disabled);
+    }
+
+    if !config.allows(topic) {
+        return Err(ProduceError::TopicNotAllowed(topic.to_string()));
+    }
+
+    let payload =
+        serde_json::to_string(&request.payload).map_err(|e| ProduceError::Failed(e.into()))?;
+    if payload.len() > config.max_payload_bytes {
+        return Err(ProduceError::PayloadTooLarge {
+            size: payload.len(),
+            max: config.max_payload_bytes,
+        });
+    }
+
+    if let Some(s) = schemas.get(cluster.id, topic).await? {
+        let schema = Schema::compile(s.kind, &s.definition).map_err(|e| {
+            ProduceError::Misconfigured(format!("invalid schema for topic '{}': {}", topic, e))
+        })?;
+        schema
+            .validate(&request.payload)
+            .map_err(ProduceError::InvalidPayload)?;
+    }
+
+    let payload_hash = format!("{:x}", Sha256::digest(payload.as_bytes()));
+    let payload_bytes = payload.len();
+    let message = OutgoingMessage {
+        key: request.key,
+        payload,
+        headers: request.headers,
+    };
+
+    let (partition, offset) = producer.send(cluster, topic, message).await?;
+
+    audit.record(AuditRecord {
+        at: Utc::now(),
+        actor: actor.to_string(),
+        cluster_id: cluster.id,
+        topic: topic.to_string(),
+        partition,
+        offset,
+        payload_hash,
+        payload_bytes,
+        owner: cluster.owner.clone(),
+    });
+
+    Ok(Produced { partition, offset })
+}
+
+/// A producer that records messages instead of writing them.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct RecordingProducer {
+    sent: std::sync::Mutex<Vec<(String, OutgoingMessage)>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl MessageProducer for RecordingProducer {
+    async fn send(
+        &self,
+        _cluster: &Cluster,
+        topic: &str,
+        message: OutgoingMessage,
+    ) -> Result<(i32, i64), AnyError> {
+        let mut sent = self.sent.lock().unwrap();
+        sent.push((topic.to_string(), message));
+        Ok((0, sent.len() as i64 - 1))
+    }
+}
+
+#[cfg(test)]
+struct Harness {
+    schemas: store::MemorySchemaStore,
+    producer: RecordingProducer,
+    audit: audit::MemoryAuditLog,
+}
+
+#[cfg(test)]
+impl Harness {
+    fn new() -> Self {
+        Self {
+            schemas: Default::default(),
+            producer: Default::default(),
+            audit: Default::default(),
+        }
+    }
+
+    async fn produce(
+        &self,
+        config: &[(&str, &str)],
+        topic: &str,
+        payload: Value,
+    ) -> Result<Produced, ProduceError> {
+        use crate::clusters::cluster::Kind;
+
+        let config = config
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let cluster = Cluster::new(None, Kind::Kafka, "test".to_string(), config);
+        let request = ProduceRequest {
+            key: None,
+            payload,
+            headers: HashMap::new(),
+        };
+
+        produce(
+            &cluster,
+            topic,
+            request,
+            "alice",
+            &self.schemas,
+            &self.producer,
+            &self.audit,
+        )
+        .await
+    }
+}
+
+#[tokio::test]
+async fn it_is_disabled_by_default() {
+    let h = Harness::new();
+
+    let result = h.produce(&[], "orders", serde_json::json!({})).await;
+    assert!(matches!(result, Err(ProduceError::Disabled)));
+    assert!(h.producer.sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn it_rejects_topics_outside_the_allowlist() {
+    let h = Harness::new();
+    let config = [
+        (config::PRODUCE_ENABLED, "true"),
+        (config::PRODUCE_TOPICS_REGEX, "orders|payments\\..*"),
+    ];
+
+    for topic in ["orders-internal", "users", "payments"] {
+        let result = h.produce(&config, topic, serde_json::json!({})).await;
+        assert!(matches!(result, Err(ProduceError::TopicNotAllowed(_))));
+    }
+
+    for topic in ["orders", "payments.eu"] {
+        assert!(h
+            .produce(&config, topic, serde_json::json!({}))
+            .await
+            .is_ok());
+    }
+}
+
+#[tokio::test]
+async fn it_enforces_the_max_payload_size() {
+    let h = Harness::new();
+    let config = [
+        (config::PRODUCE_ENABLED, "true"),
+        (config::PRODUCE_MAX_PAYLOAD_BYTES, "8"),
+    ];
+
+    let result = h
+        .produce(&config, "orders", serde_json::json!({ "id": 123456 }))
+        .await;
+    assert!(matches!(
+        result,
+        Err(ProduceError::PayloadTooLarge { size: 13, max: 8 })
+    ));
+}
+
+#[tokio::test]
+async fn it_validates_payloads_against_the_topic_schema() {
+    use crate::ids::ClusterId;
+
+    let h = Harness::new();
+    let definition = serde_json::json!({
+        "type": "object",
+        "properties": { "customer": { "properties": { "email": { "type": "string" } } } }
+    });
+    let schema = store::TopicSchema::new(
+        ClusterId::default(),
+        "orders".to_string(),
+        schema::SchemaKind::Json,
+        definition,
+    );
+    h.schemas.put(schema).await.unwrap();
+
+    let config = [(config::PRODUCE_ENABLED, "true")];
+    let invalid = serde_json::json!({ "customer": { "email": 42 } });
+    match h.produce(&config, "orders", invalid).await {
+        Err(ProduceError::InvalidPayload(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].path, "/customer/email");
+        }
+        other => panic!("expected a schema mismatch, got {:?}", other),
+    }
+
+    let valid = serde_json::json!({ "customer": { "email": "a@b.c" } });
+    assert!(h.produce(&config, "orders", valid).await.is_ok());
+}
+
+#[tokio::test]
+async fn it_audits_accepted_produces_without_the_payload() {
+    let h = Harness::new();
+    let config = [(config::PRODUCE_ENABLED, "true")];
+    let payload = serde_json::json!({ "secret": "hunter2" });
+
+    let produced = h.produce(&config, "orders", payload).await.unwrap();
+
+    let records = h.audit.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+
+    let record = &records[0];
+    assert_eq!(record.actor, "alice");
+    assert_eq!(record.topic, "orders");
+    assert_eq!(record.offset, produced.offset);
+    assert_eq!(
+        record.payload_hash,
+        format!("{:x}", Sha256::digest(b"{\"secret\":\"hunter2\"}"))
+    );
+    assert!(!serde_json::to_string(record).unwrap().contains("hunter2"));
+}
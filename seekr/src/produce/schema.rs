@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The language a produce schema is written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaKind {
+    #[default]
+    Json,
+}
+
+/// A mismatch between a payload and its schema.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    /// JSON pointer to the offending value, empty for the payload root.
+    pub path: String,
+    pub message: String,
+}
+
+enum Compiled {
+    Json(jsonschema::Validator),
+}
+
+/// A compiled schema that payloads are validated against.
+pub struct Schema {
+    compiled: Compiled,
+}
+
+impl Schema {
+    pub fn compile(kind: SchemaKind, definition: &Value) -> Result<Self, String> {
+        let compiled = match kind {
+            SchemaKind::Json => {
+                Compiled::Json(jsonschema::validator_for(definition).map_err(|e| e.to_string())?)
+            }
+        };
+
+        Ok(Self { compiled })
+    }
+
+    /// Validate a payload, reporting every mismatch.
+    pub fn validate(&self, payload: &Value) -> Result<(), Vec<FieldError>> {
+        let errors = match &self.compiled {
+            Compiled::Json(v) => v
+                .iter_errors(payload)
+                .map(|e| FieldError {
+                    path: e.instance_path().to_string(),
+                    message: e.to_string(),
+                })
+                .collect::<Vec<_>>(),
+        };
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+fn order_schema() -> Schema {
+    let definition = serde_json::json!({
+        "type": "object",
+        "required": ["id", "customer"],
+        "properties": {
+            "id": { "type": "integer" },
+            "customer": {
+                "type": "object",
+                "required": ["email"],
+                "properties": {
+                    "email": { "type": "string" },
+                    "tier": { "enum": ["free", "pro"] }
+                }
+            }
+        }
+    });
+
+    Schema::compile(SchemaKind::Json, &definition).unwrap()
+}
+
+#[test]
+fn it_accepts_matching_payloads() {
+    let payload = serde_json::json!({ "id": 1, "customer": { "email": "a@b.c", "tier": "pro" } });
+    assert_eq!(order_schema().validate(&payload), Ok(()));
+}
+
+#[test]
+fn it_reports_nested_property_errors() {
+    let payload = serde_json::json!({ "id": "1", "customer": { "tier": "gold" } });
+
+    let mut paths = order_schema()
+        .validate(&payload)
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.path)
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    assert_eq!(paths, vec!["/customer", "/customer/tier", "/id"]);
+}
+
+#[test]
+fn it_rejects_invalid_schemas() {
+    let definition = serde_json::json!({ "type": "not-a-type" });
+    assert!(Schema::compile(SchemaKind::Json, &definition).is_err());
+}
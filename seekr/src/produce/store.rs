@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+use super::schema::SchemaKind;
+
+/// The schema produced payloads of a topic are validated against.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopicSchema {
+    /// The unique id of the schema, derived from its cluster and topic.
+    pub id: String,
+
+    /// The cluster the topic belongs to.
+    pub cluster_id: ClusterId,
+
+    /// The topic the schema applies to.
+    pub topic: String,
+
+    /// The language the schema is written in.
+    pub kind: SchemaKind,
+
+    /// The schema document.
+    pub definition: Value,
+
+    /// Represents the point in time in UTC Epoch time, when the schema was modified.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TopicSchema {
+    pub fn new(cluster_id: ClusterId, topic: String, kind: SchemaKind, definition: Value) -> Self {
+        TopicSchema {
+            id: Self::id_for(cluster_id, &topic),
+            cluster_id,
+            topic,
+            kind,
+            definition,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Topic names may contain dots, which are not valid in document ids.
+    fn id_for(cluster_id: ClusterId, topic: &str) -> String {
+        let topic = base64::encode_config(topic, base64::URL_SAFE_NO_PAD);
+        format!("{}-{}", cluster_id, topic)
+    }
+}
+
+#[async_trait]
+pub trait SchemaStore {
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        topic: &str,
+    ) -> Result<Option<TopicSchema>, AnyError>;
+    async fn put(&self, schema: TopicSchema) -> Result<(), AnyError>;
+}
+
+pub const INDEX_NAME: &str = "produce_schemas";
+
+pub struct MSSchemaStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+}
+
+impl MSSchemaStore {
+    pub async fn new(client: Arc<Client>) -> Self {
+        if let Ok(task) = client.clone().create_index(INDEX_NAME, Some("id")).await {
+            task.wait_for_completion(&client, None, None).await.unwrap();
+        }
+
+        Self { client }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+}
+
+#[async_trait]
+impl SchemaStore for MSSchemaStore {
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        topic: &str,
+    ) -> Result<Option<TopicSchema>, AnyError> {
+        let id = TopicSchema::id_for(cluster_id, topic);
+
+        let schema = self.index().get_document::<TopicSchema>(&id).await;
+
+        meilisearch::found(schema)
+    }
+
+    async fn put(&self, schema: TopicSchema) -> Result<(), AnyError> {
+        self.index()
+            .add_or_replace(&[schema], Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// An in-memory store used to exercise produce guardrails in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemorySchemaStore {
+    schemas: tokio::sync::RwLock<std::collections::HashMap<String, TopicSchema>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl SchemaStore for MemorySchemaStore {
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        topic: &str,
+    ) -> Result<Option<TopicSchema>, AnyError> {
+        let id = TopicSchema::id_for(cluster_id, topic);
+        Ok(self.schemas.read().await.get(&id).cloned())
+    }
+
+    async fn put(&self, schema: TopicSchema) -> Result<(), AnyError> {
+        self.schemas.write().await.insert(schema.id.clone(), schema);
+        Ok(())
+    }
+}
+
+pub async fn init_schema_store(config: &MeilisearchConfig) -> Arc<dyn SchemaStore + Send + Sync> {
+    Arc::new(MSSchemaStore::new(config.client()).await)
+}
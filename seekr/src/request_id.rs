@@ -0,0 +1,145 @@
+use std::fmt;
+use std::future::{ready, Future, Ready};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+
+use crate::id;
+
+/// The header a request's id is read from and answered in.
+pub const HEADER: &str = "X-Request-Id";
+
+/// Ids callers send longer than this are replaced with one of our own.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+}
+
+/// The id a request is logged and answered under.
+///
+/// Callers can send their own, so a request is traced through their logs and
+/// ours; every log line written while the request is handled carries it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn generate() -> Self {
+        let id = match id::generator().next_id() {
+            Ok(id) => id.to_string(),
+            Err(_) => uuid::Uuid::new_v4().simple().to_string(),
+        };
+        RequestId(id)
+    }
+
+    /// The id a caller sent, unless it's empty, too long or not printable.
+    fn parse(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?.trim();
+        let valid = !value.is_empty()
+            && value.len() <= MAX_LEN
+            && value.chars().all(|c| c.is_ascii_graphic());
+        valid.then(|| RequestId(value.to_string()))
+    }
+
+    /// The id of the request being handled, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|id| id.clone()).ok()
+    }
+
+    /// Run `f` as part of the request, so its log lines carry the request's id.
+    pub async fn within<F: Future>(id: Option<Self>, f: F) -> F::Output {
+        match id {
+            Some(id) => CURRENT.scope(id, f).await,
+            None => f.await,
+        }
+    }
+}
+
+/// Run `f` as part of the request, for code that isn't async.
+#[cfg(test)]
+pub(crate) fn sync_scope<R>(id: RequestId, f: impl FnOnce() -> R) -> R {
+    CURRENT.sync_scope(id, f)
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The id attached by the middleware, or a new one outside of it.
+impl FromRequest for RequestId {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req.extensions().get::<RequestId>().cloned();
+        ready(Ok(id.unwrap_or_else(RequestId::generate)))
+    }
+}
+
+/// Attach the request's id, read from its header or generated, handle the
+/// request under it and echo it on the response.
+pub async fn propagate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = req
+        .headers()
+        .get(HEADER)
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+
+    req.extensions_mut().insert(id.clone());
+    let mut res = CURRENT.scope(id.clone(), next.call(req)).await?;
+    if let Ok(value) = HeaderValue::from_str(&id.0) {
+        res.headers_mut()
+            .insert(HeaderName::from_static("x-request-id"), value);
+    }
+    Ok(res)
+}
+
+#[actix_web::test]
+async fn it_echoes_given_ids_and_generates_missing_ones() {
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    let app = test::init_service(App::new().wrap(from_fn(propagate)).route(
+        "/",
+        web::get().to(|id: RequestId| async move {
+            let current = RequestId::current();
+            HttpResponse::Ok().body(format!("{} {}", id, current.unwrap()))
+        }),
+    ))
+    .await;
+    let call = |header: Option<&str>| {
+        let mut req = test::TestRequest::get().uri("/");
+        if let Some(header) = header {
+            req = req.insert_header((HEADER, header));
+        }
+        test::call_service(&app, req.to_request())
+    };
+
+    let res = call(Some("checkout-42")).await;
+    assert_eq!(res.headers().get(HEADER).unwrap(), "checkout-42");
+    assert_eq!(test::read_body(res).await, "checkout-42 checkout-42");
+
+    let long = "x".repeat(MAX_LEN + 1);
+    for header in [None, Some(""), Some("two words"), Some(long.as_str())] {
+        let res = call(header).await;
+        let id = res
+            .headers()
+            .get(HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(id.parse::<i64>().is_ok(), "{}", id);
+        let body = test::read_body(res).await;
+        assert_eq!(body, format!("{} {}", id, id));
+    }
+    assert_eq!(RequestId::current(), None);
+}
@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use actix_web::web::{Data, Json, ServiceConfig};
+use actix_web::{get, post, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::auth::Principal;
+use crate::drain::Drain;
+use crate::errors::AnyError;
+use crate::mirrors::monitor::MirrorMonitor;
+use crate::restart::{RestartPhase, SafeRestart, Step, DEFAULT_GRACE_PERIOD, MAX_GRACE_PERIOD};
+use crate::standby::coordinator::Coordinator;
+use crate::standby::Availability;
+use crate::sweeper::Sweeper;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(safe_restart).service(get_safe_restart_status);
+}
+
+#[derive(Default, Deserialize)]
+struct SafeRestartRequest {
+    grace_period_ms: Option<u64>,
+}
+
+#[post("/safe-restart")]
+#[allow(clippy::too_many_arguments)]
+async fn safe_restart(
+    principal: Principal,
+    r: Option<Json<SafeRestartRequest>>,
+    restart: Data<SafeRestart>,
+    availability: Option<Data<Availability>>,
+    drain: Option<Data<Drain>>,
+    coordinator: Option<Data<Coordinator>>,
+    mirrors: Option<Data<MirrorMonitor>>,
+    sweeper: Option<Data<Sweeper>>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let r = r.map(Json::into_inner).unwrap_or_default();
+    let grace = r
+        .grace_period_ms
+        .map_or(DEFAULT_GRACE_PERIOD, Duration::from_millis);
+    if grace > MAX_GRACE_PERIOD {
+        return HttpResponse::BadRequest().body(format!(
+            "grace_period_ms must be at most {}",
+            MAX_GRACE_PERIOD.as_millis()
+        ));
+    }
+
+    // Only the first call's steps run, a repeated call reports on them.
+    if let Some(status) = restart.status() {
+        return HttpResponse::Ok().json(status);
+    }
+
+    let coordinator_ = coordinator.clone();
+    let steps = vec![
+        Step::new(RestartPhase::ReadOnly, async move {
+            available(availability, "availability")?.leave();
+            Ok::<_, AnyError>(())
+        }),
+        Step::new(RestartPhase::Draining, async move {
+            available(drain, "drain")?.shutdown().await;
+            Ok::<_, AnyError>(())
+        }),
+        Step::new(RestartPhase::Stopping, async move {
+            if let Some(mirrors) = mirrors {
+                mirrors.into_inner().stop().await;
+            }
+            if let Some(sweeper) = sweeper {
+                sweeper.into_inner().stop().await;
+            }
+            // Stops the metadata consumers and releases the primary lease.
+            available(coordinator_, "coordinator")?
+                .into_inner()
+                .stop()
+                .await;
+            Ok::<_, AnyError>(())
+        }),
+        Step::new(RestartPhase::Announcing, async move {
+            available(coordinator, "coordinator")?.depart().await
+        }),
+    ];
+
+    match restart.begin(grace, steps) {
+        (status, true) => HttpResponse::Accepted().json(status),
+        (status, false) => HttpResponse::Ok().json(status),
+    }
+}
+
+#[get("/safe-restart/status")]
+async fn get_safe_restart_status(
+    principal: Principal,
+    restart: Data<SafeRestart>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    match restart.status() {
+        Some(status) => HttpResponse::Ok().json(status),
+        None => HttpResponse::NotFound().body("No safe restart was started"),
+    }
+}
+
+/// The data a step acts on, which processes without it don't register.
+fn available<T: ?Sized>(data: Option<Data<T>>, what: &str) -> Result<Data<T>, AnyError> {
+    data.ok_or_else(|| format!("no {} on this instance", what).into())
+}
+
+#[actix_web::test]
+async fn it_restarts_safely_and_tells_peers_it_left_on_purpose() {
+    use std::sync::Arc;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::drain::{ConnectionKind, ShutdownPhase};
+    use crate::kafka::metadata::manager::{MetadataConsumerFactory, MetadataManager};
+    use crate::standby::lease::{self, MemoryLeaseStore};
+    use crate::standby::sync::HttpCacheSource;
+    use crate::standby::ServerRole;
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers".into()));
+    let manager = Arc::new(MetadataManager::with_factory(
+        Arc::new(MemoryClusterStore::default()),
+        factory,
+    ));
+    let leases = Arc::new(MemoryLeaseStore::default());
+    let coordinator = Arc::new(Coordinator::new(
+        ServerRole::Primary,
+        "http://seekr-a:5000".to_string(),
+        manager,
+        leases.clone(),
+        Arc::new(HttpCacheSource::new(None)),
+    ));
+    coordinator.clone().start().await.unwrap();
+    let availability = Data::from(coordinator.availability());
+
+    let (exit, exited, code) = crate::restart::recorded_exit();
+    let drain = Data::new(Drain::new(Duration::from_secs(5)));
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(
+                SafeRestart::default().with_exit_code(75).with_exit(exit),
+            ))
+            .app_data(Data::from(coordinator.clone()))
+            .app_data(availability)
+            .app_data(drain.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/safe-restart/status")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // A stream still open holds the drain until it finishes.
+    let mut stream = drain.connect(ConnectionKind::Sse);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/safe-restart")
+        .set_json(serde_json::json!({ "grace_period_ms": 10_000 }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let started: Value = test::read_body_json(res).await;
+    assert_eq!(started["exit_code"], 75);
+    assert_eq!(started["grace_period_ms"], 10_000);
+    assert_eq!(
+        started["phases"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["phase"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["read_only", "draining", "stopping", "announcing"]
+    );
+
+    stream.draining().await;
+    assert_eq!(drain.phase(), ShutdownPhase::Draining);
+
+    // Writes are refused once read-only, a repeated restart reports the first.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/clusters")
+        .set_json(serde_json::json!({}))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/safe-restart")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let again: Value = test::read_body_json(res).await;
+    assert_eq!(again["started_at"], started["started_at"]);
+    assert_eq!(again["phases"][0]["status"], "done");
+    assert_eq!(again["phases"][1]["status"], "running");
+    assert!(lease::departures(leases.as_ref()).await.unwrap().is_empty());
+
+    drop(stream);
+    exited.notified().await;
+    assert_eq!(*code.lock().unwrap(), Some(75));
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/safe-restart/status")
+        .to_request();
+    let status: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(status["completed"], true);
+    assert_eq!(status["forced"], false);
+    assert!(status["phases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|p| p["status"] == "done"));
+
+    // A peer sharing the lease store tells the restart from a crash.
+    let departures = lease::departures(leases.as_ref()).await.unwrap();
+    assert_eq!(departures.len(), 1);
+    assert_eq!(departures[0].holder, coordinator.holder());
+    assert_eq!(departures[0].url, "http://seekr-a:5000");
+}
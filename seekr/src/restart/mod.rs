@@ -0,0 +1,360 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::errors::AnyError;
+
+pub mod endpoints;
+
+/// How long a safe restart runs before the instance exits regardless.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// The longest grace period a safe restart may ask for.
+pub const MAX_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// The path of the endpoint starting a safe restart, which read-only
+/// instances and standbys still serve, so it can be invoked again.
+pub const SAFE_RESTART_PATH: &str = "/admin/safe-restart";
+
+/// The steps of a safe restart, in the order they run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPhase {
+    /// Writes are refused, reads are still served.
+    ReadOnly,
+
+    /// Long-lived connections are asked to finish.
+    Draining,
+
+    /// Background work and the metadata consumers stop, and leases are released.
+    Stopping,
+
+    /// Peers are told the instance is leaving on purpose.
+    Announcing,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    /// The grace period expired before the phase ran or finished.
+    Skipped,
+}
+
+type Run = Pin<Box<dyn Future<Output = Result<(), AnyError>> + Send>>;
+
+/// One phase of a safe restart, run after the phases before it completed or failed.
+pub struct Step {
+    pub phase: RestartPhase,
+    run: Run,
+}
+
+impl Step {
+    pub fn new<F>(phase: RestartPhase, run: F) -> Self
+    where
+        F: Future<Output = Result<(), AnyError>> + Send + 'static,
+    {
+        Self {
+            phase,
+            run: Box::pin(run),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PhaseReport {
+    pub phase: RestartPhase,
+    pub status: PhaseStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The progress of the safe restart under way.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RestartStatus {
+    pub started_at: DateTime<Utc>,
+    pub grace_period_ms: u64,
+    pub exit_code: i32,
+    pub phases: Vec<PhaseReport>,
+
+    /// Whether the grace period expired before every phase completed.
+    pub forced: bool,
+
+    /// Whether the phases are over and the process is exiting.
+    pub completed: bool,
+}
+
+/// Ends the process once a safe restart completed.
+pub type Exit = Arc<dyn Fn(i32) + Send + Sync>;
+
+/// Runs at most one safe restart, which ends by exiting the process.
+pub struct SafeRestart {
+    exit_code: i32,
+    exit: Exit,
+    status: Arc<Mutex<Option<RestartStatus>>>,
+}
+
+impl Default for SafeRestart {
+    fn default() -> Self {
+        Self {
+            exit_code: 0,
+            exit: Arc::new(|code| std::process::exit(code)),
+            status: Default::default(),
+        }
+    }
+}
+
+impl SafeRestart {
+    /// Exit with `code` once the restart completed or its grace period expired.
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = code;
+        self
+    }
+
+    /// End the process with `exit` instead of `std::process::exit`.
+    pub fn with_exit(mut self, exit: Exit) -> Self {
+        self.exit = exit;
+        self
+    }
+
+    /// The status of the restart, `None` until one was started.
+    pub fn status(&self) -> Option<RestartStatus> {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Run the steps in order in the background, then exit, unless a restart
+    /// already started. Returns the status, and whether this call started it.
+    pub fn begin(&self, grace: Duration, steps: Vec<Step>) -> (RestartStatus, bool) {
+        let mut status = self.status.lock().unwrap();
+        if let Some(current) = status.as_ref() {
+            return (current.clone(), false);
+        }
+
+        let started = RestartStatus {
+            started_at: Utc::now(),
+            grace_period_ms: grace.as_millis() as u64,
+            exit_code: self.exit_code,
+            phases: steps
+                .iter()
+                .map(|s| PhaseReport {
+                    phase: s.phase,
+                    status: PhaseStatus::Pending,
+                    elapsed_ms: None,
+                    error: None,
+                })
+                .collect(),
+            forced: false,
+            completed: false,
+        };
+        *status = Some(started.clone());
+
+        warn!(
+            "Safe restart started, exiting with code {} within {:?}",
+            self.exit_code, grace
+        );
+        let (state, exit, code) = (self.status.clone(), self.exit.clone(), self.exit_code);
+        tokio::spawn(async move {
+            let forced = tokio::time::timeout(grace, run(state.clone(), steps))
+                .await
+                .is_err();
+
+            if let Some(status) = state.lock().unwrap().as_mut() {
+                for phase in &mut status.phases {
+                    if matches!(phase.status, PhaseStatus::Pending | PhaseStatus::Running) {
+                        phase.status = PhaseStatus::Skipped;
+                    }
+                }
+                status.forced = forced;
+                status.completed = true;
+            }
+
+            match forced {
+                true => warn!("Safe restart grace period of {:?} expired, exiting", grace),
+                false => info!("Safe restart completed, exiting"),
+            }
+            exit(code);
+        });
+
+        (started, true)
+    }
+}
+
+/// Run the steps one after the other, recording each as it goes.
+async fn run(state: Arc<Mutex<Option<RestartStatus>>>, steps: Vec<Step>) {
+    let update = |i: usize, f: &dyn Fn(&mut PhaseReport)| {
+        if let Some(status) = state.lock().unwrap().as_mut() {
+            f(&mut status.phases[i]);
+        }
+    };
+
+    for (i, step) in steps.into_iter().enumerate() {
+        info!("Safe restart phase {:?} started", step.phase);
+        update(i, &|p| p.status = PhaseStatus::Running);
+
+        let started = Instant::now();
+        let result = step.run.await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        // A failed phase doesn't hold the restart back, the instance leaves either way.
+        if let Err(e) = &result {
+            warn!("Safe restart phase {:?} failed: {}", step.phase, e);
+        }
+        update(i, &|p| {
+            p.elapsed_ms = Some(elapsed_ms);
+            match &result {
+                Ok(_) => p.status = PhaseStatus::Done,
+                Err(e) => {
+                    p.status = PhaseStatus::Failed;
+                    p.error = Some(e.to_string());
+                }
+            }
+        });
+    }
+}
+
+/// An exit recording its code instead of ending the test.
+#[cfg(test)]
+pub fn recorded_exit() -> (Exit, Arc<tokio::sync::Notify>, Arc<Mutex<Option<i32>>>) {
+    let exited = Arc::new(tokio::sync::Notify::new());
+    let code = Arc::new(Mutex::new(None));
+    let (exited_, code_) = (exited.clone(), code.clone());
+    let exit: Exit = Arc::new(move |c| {
+        *code_.lock().unwrap() = Some(c);
+        exited_.notify_one();
+    });
+    (exit, exited, code)
+}
+
+#[cfg(test)]
+fn statuses(status: &RestartStatus) -> Vec<(RestartPhase, PhaseStatus)> {
+    status.phases.iter().map(|p| (p.phase, p.status)).collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_runs_the_phases_in_order_then_exits() {
+    let (exit, exited, code) = recorded_exit();
+    let restart = SafeRestart::default().with_exit_code(3).with_exit(exit);
+
+    let ran = Arc::new(Mutex::new(vec![]));
+    let step = |phase, fails: bool| {
+        let ran = ran.clone();
+        Step::new(phase, async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            ran.lock().unwrap().push(phase);
+            match fails {
+                true => Err("lease store unavailable".into()),
+                false => Ok(()),
+            }
+        })
+    };
+    let steps = vec![
+        step(RestartPhase::ReadOnly, false),
+        step(RestartPhase::Draining, false),
+        step(RestartPhase::Stopping, true),
+        step(RestartPhase::Announcing, false),
+    ];
+    let (status, started) = restart.begin(Duration::from_secs(5), steps);
+    assert!(started);
+    assert_eq!(status.phases[0].status, PhaseStatus::Pending);
+
+    exited.notified().await;
+    assert_eq!(*code.lock().unwrap(), Some(3));
+    assert_eq!(
+        *ran.lock().unwrap(),
+        [
+            RestartPhase::ReadOnly,
+            RestartPhase::Draining,
+            RestartPhase::Stopping,
+            RestartPhase::Announcing
+        ]
+    );
+
+    let status = restart.status().unwrap();
+    assert!(status.completed);
+    assert!(!status.forced);
+    assert_eq!(
+        statuses(&status),
+        [
+            (RestartPhase::ReadOnly, PhaseStatus::Done),
+            (RestartPhase::Draining, PhaseStatus::Done),
+            (RestartPhase::Stopping, PhaseStatus::Failed),
+            (RestartPhase::Announcing, PhaseStatus::Done),
+        ]
+    );
+    assert_eq!(
+        status.phases[2].error.as_deref(),
+        Some("lease store unavailable")
+    );
+    assert_eq!(status.phases[0].elapsed_ms, Some(100));
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_exits_once_the_grace_period_expires() {
+    let (exit, exited, code) = recorded_exit();
+    let restart = SafeRestart::default().with_exit(exit);
+
+    let steps = vec![
+        Step::new(RestartPhase::ReadOnly, async { Ok(()) }),
+        Step::new(RestartPhase::Draining, async {
+            futures::future::pending::<()>().await;
+            Ok(())
+        }),
+        Step::new(RestartPhase::Announcing, async { Ok(()) }),
+    ];
+    let started = Instant::now();
+    restart.begin(Duration::from_secs(2), steps);
+
+    exited.notified().await;
+    assert_eq!(started.elapsed(), Duration::from_secs(2));
+    assert_eq!(*code.lock().unwrap(), Some(0));
+
+    let status = restart.status().unwrap();
+    assert!(status.forced);
+    assert!(status.completed);
+    assert_eq!(
+        statuses(&status),
+        [
+            (RestartPhase::ReadOnly, PhaseStatus::Done),
+            (RestartPhase::Draining, PhaseStatus::Skipped),
+            (RestartPhase::Announcing, PhaseStatus::Skipped),
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_reports_the_restart_under_way_when_begun_again() {
+    let (exit, exited, _) = recorded_exit();
+    let restart = SafeRestart::default().with_exit(exit);
+    let ran = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let steps = || {
+        let ran = ran.clone();
+        vec![Step::new(RestartPhase::Draining, async move {
+            ran.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })]
+    };
+
+    let (first, started) = restart.begin(Duration::from_secs(5), steps());
+    assert!(started);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let (again, started) = restart.begin(Duration::from_secs(60), steps());
+    assert!(!started);
+    assert_eq!(again.started_at, first.started_at);
+    assert_eq!(again.grace_period_ms, 5000);
+    assert_eq!(again.phases[0].status, PhaseStatus::Running);
+
+    exited.notified().await;
+    assert_eq!(ran.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
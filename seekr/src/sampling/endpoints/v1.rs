@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::{Data, Json, Path, ServiceConfig};
+use actix_web::{post, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::api::error;
+use crate::clusters::service::{self, TopicRead};
+use crate::clusters::store::ClusterStore;
+use crate::deadline::Deadline;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::sampling::sketch::{KeyDistribution, KeyStats};
+use crate::sampling::KeySource;
+
+/// Records sampled unless the request says otherwise, and at most.
+const DEFAULT_COUNT: usize = 1_000;
+const MAX_COUNT: usize = 10_000;
+
+/// Most frequent keys listed unless the request says otherwise, and at most.
+const DEFAULT_TOP: usize = 10;
+const MAX_TOP: usize = 100;
+
+/// How long a sample reads records, unless the request's deadline leaves less.
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(sample_keys);
+}
+
+#[post("/{id}/topics/{topic}/key-sample")]
+async fn sample_keys(
+    path: Path<(ClusterId, String)>,
+    r: Json<KeySampleRequest>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+    source: Data<Arc<dyn KeySource + Send + Sync>>,
+    deadline: Deadline,
+) -> impl Responder {
+    let (id, topic) = path.into_inner();
+    info!("Sampling keys of topic {} in cluster with id {}", topic, id);
+
+    let count = r.count.unwrap_or(DEFAULT_COUNT);
+    if count == 0 || count > MAX_COUNT {
+        return HttpResponse::BadRequest()
+            .body(format!("count must be between 1 and {}", MAX_COUNT));
+    }
+    let top = r.top.unwrap_or(DEFAULT_TOP).min(MAX_TOP);
+
+    let cluster = match deadline.run("clusters", cs.get(id)).await {
+        Ok(Ok(Some(c))) => c,
+        Ok(Ok(None)) => return HttpResponse::NotFound().finish(),
+        Ok(Err(e)) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return error::deadline_exceeded(&e),
+    };
+    let metadata = match service::topic(manager.into_inner(), id, &topic).await {
+        Ok(TopicRead::Topic(metadata, _)) => metadata,
+        Ok(TopicRead::Processing) => {
+            return HttpResponse::ServiceUnavailable().body(format!(
+                "Cluster metadata with id '{}' is still processing",
+                id
+            ))
+        }
+        Ok(TopicRead::MissingCluster | TopicRead::MissingTopic) => {
+            return HttpResponse::NotFound().body(format!("Topic '{}' not found", topic))
+        }
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let partitions = match r.partition {
+        Some(p) if metadata.partitions.iter().any(|m| m.id == p) => vec![p],
+        Some(p) => {
+            return HttpResponse::BadRequest()
+                .body(format!("Topic '{}' has no partition {}", topic, p))
+        }
+        None => metadata.partitions.iter().map(|p| p.id).collect(),
+    };
+
+    let timeout = deadline.clamp(SAMPLE_TIMEOUT);
+    let sample = source.sample(&cluster, &topic, &partitions, count, timeout);
+    let sample = match deadline.run("kafka_sample", sample).await {
+        Ok(Ok(sample)) => sample,
+        Ok(Err(e)) => return HttpResponse::InternalServerError().body(e.to_string()),
+        Err(e) => return error::deadline_exceeded(&e),
+    };
+
+    let mut stats = KeyStats::default();
+    for key in &sample.keys {
+        stats.add(key.as_deref());
+    }
+    let distribution = stats.distribution(top, metadata.partitions.len());
+
+    let caveat = sample.timed_out.then(|| {
+        format!(
+            "The time budget of {}ms ran out after {} of the {} records requested, the sample may not be representative",
+            timeout.as_millis(),
+            distribution.sampled,
+            count
+        )
+    });
+
+    HttpResponse::Ok().json(KeySampleResponse {
+        topic,
+        partitions,
+        requested: count,
+        distribution,
+        caveat,
+    })
+}
+
+#[derive(Deserialize)]
+struct KeySampleRequest {
+    count: Option<usize>,
+    /// Only sample this partition, e.g. a hot one.
+    partition: Option<i32>,
+    top: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct KeySampleResponse {
+    topic: String,
+    partitions: Vec<i32>,
+    requested: usize,
+
+    #[serde(flatten)]
+    distribution: KeyDistribution,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caveat: Option<String>,
+}
+
+#[actix_web::test]
+async fn it_samples_the_keys_of_hot_partitions() {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::json;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataConsumerFactory};
+    use crate::sampling::sketch::partition_for;
+    use crate::sampling::MemoryKeySource;
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = MetadataManager::with_factory(cs.clone(), factory);
+    manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            entries: vec![SyncedEntry {
+                cluster_id: ClusterId(1),
+                entry: CachedMetadataEntry::Meta(metadata(&[1], &[("orders", 6)])),
+                offsets: None,
+            }],
+            clusters: vec![ClusterId(1)],
+        })
+        .await;
+
+    let keys = (0..100)
+        .map(|i| match i % 4 {
+            0 => None,
+            1 | 2 => Some(b"tenant-42".to_vec()),
+            _ => Some(format!("tenant-{}", i).into_bytes()),
+        })
+        .collect();
+    let memory = Arc::new(MemoryKeySource {
+        keys,
+        times_out_after: Some(80),
+        ..Default::default()
+    });
+    let source: Arc<dyn KeySource + Send + Sync> = memory.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(manager))
+            .app_data(Data::new(source))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let sample = |body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/clusters/1/topics/orders/key-sample")
+            .set_json(body)
+            .to_request()
+    };
+
+    let req = sample(json!({ "count": 100, "partition": 3, "top": 1 }));
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["partitions"], json!([3]));
+    assert_eq!(body["sampled"], 80);
+    assert_eq!(body["null_keys"], 20);
+    assert_eq!(body["null_key_percent"], 25.0);
+    assert_eq!(body["distinct_keys_estimate"], 21);
+    assert_eq!(
+        body["top_keys"],
+        json!([{
+            "key": "tenant-42",
+            "encoding": "utf8",
+            "count": 40,
+            "percent": 50.0,
+            "partition": partition_for(b"tenant-42", 6),
+        }])
+    );
+    assert!(body["caveat"]
+        .as_str()
+        .unwrap()
+        .contains("after 80 of the 100 records requested"));
+
+    // Samples of the whole topic that complete carry no caveat.
+    let req = sample(json!({ "count": 50 }));
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["partitions"], json!([0, 1, 2, 3, 4, 5]));
+    assert!(body.get("caveat").is_none());
+    let calls = memory.calls.lock().unwrap().clone();
+    assert_eq!(calls.len(), 2);
+    assert!(calls.iter().all(|c| c.3 <= SAMPLE_TIMEOUT));
+
+    for (body, status) in [
+        (json!({ "count": MAX_COUNT + 1 }), StatusCode::BAD_REQUEST),
+        (json!({ "partition": 6 }), StatusCode::BAD_REQUEST),
+    ] {
+        let res = test::call_service(&app, sample(body)).await;
+        assert_eq!(res.status(), status);
+    }
+    let req = test::TestRequest::post()
+        .uri("/api/v1/clusters/1/topics/refunds/key-sample")
+        .set_json(json!({}))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{Message, Offset, TopicPartitionList};
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::kafka::auth::AuthContext;
+use crate::kafka::client::build_client_config;
+
+pub mod endpoints;
+pub mod sketch;
+
+/// The keys read from a topic, `None` for records without one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeySample {
+    pub keys: Vec<Option<Vec<u8>>>,
+
+    /// Set when the timeout passed before as many records as requested were read.
+    pub timed_out: bool,
+}
+
+/// Reads the keys of the newest records of a topic's partitions.
+#[async_trait]
+pub trait KeySource {
+    async fn sample(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        partitions: &[i32],
+        count: usize,
+        timeout: Duration,
+    ) -> Result<KeySample, AnyError>;
+}
+
+/// Samples with an ephemeral consumer assigned the partitions directly, it
+/// never joins a group nor commits, so no consumer group sees it.
+pub struct KafkaKeySource;
+
+#[async_trait]
+impl KeySource for KafkaKeySource {
+    async fn sample(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        partitions: &[i32],
+        count: usize,
+        timeout: Duration,
+    ) -> Result<KeySample, AnyError> {
+        let auth = AuthContext::of(cluster)?;
+        auth.check().await?;
+        let mut client = build_client_config(cluster, None)?;
+        client.set("enable.auto.offset.store", "false");
+        auth.configure(&mut client);
+        let consumer = client.create_with_context::<_, BaseConsumer<_>>(auth)?;
+
+        let topic = topic.to_string();
+        let partitions = partitions.to_vec();
+
+        // Watermark queries and polls are blocking broker round trips.
+        tokio::task::spawn_blocking(move || {
+            let deadline = Instant::now() + timeout;
+            let remaining = || deadline.saturating_duration_since(Instant::now());
+
+            // Read an even share of the newest records of every partition.
+            let share = count.div_ceil(partitions.len().max(1)) as i64;
+            let mut tpl = TopicPartitionList::new();
+            let mut available = 0;
+            for &p in &partitions {
+                let (low, high) = consumer.fetch_watermarks(&topic, p, remaining())?;
+                let from = low.max(high - share);
+                if from < high {
+                    tpl.add_partition_offset(&topic, p, Offset::Offset(from))?;
+                    available += (high - from) as usize;
+                }
+            }
+
+            let wanted = available.min(count);
+            let mut sample = KeySample::default();
+            if wanted == 0 {
+                return Ok(sample);
+            }
+
+            consumer.assign(&tpl)?;
+            while sample.keys.len() < wanted {
+                if remaining().is_zero() {
+                    sample.timed_out = true;
+                    break;
+                }
+                match consumer.poll(remaining()) {
+                    Some(Ok(m)) => sample.keys.push(m.key().map(|k| k.to_vec())),
+                    Some(Err(e)) => return Err(e.into()),
+                    None => {}
+                }
+            }
+
+            Ok(sample)
+        })
+        .await?
+    }
+}
+
+/// Serves scripted keys, counting the calls.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryKeySource {
+    pub keys: Vec<Option<Vec<u8>>>,
+
+    /// Serve only this many keys and report the timeout passing.
+    pub times_out_after: Option<usize>,
+
+    pub calls: std::sync::Mutex<Vec<(String, Vec<i32>, usize, Duration)>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl KeySource for MemoryKeySource {
+    async fn sample(
+        &self,
+        _cluster: &Cluster,
+        topic: &str,
+        partitions: &[i32],
+        count: usize,
+        timeout: Duration,
+    ) -> Result<KeySample, AnyError> {
+        let call = (topic.to_string(), partitions.to_vec(), count, timeout);
+        self.calls.lock().unwrap().push(call);
+
+        let served = self.times_out_after.unwrap_or(count).min(count);
+        Ok(KeySample {
+            keys: self.keys.iter().take(served).cloned().collect(),
+            timed_out: self.times_out_after.is_some_and(|n| n < count),
+        })
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Registers of the distinct key sketch, 2^12 of them for a standard error
+/// of about 1.6% in 4 KiB, however many keys are sampled.
+const PRECISION: u32 = 12;
+const REGISTERS: usize = 1 << PRECISION;
+
+/// Keys counted at once by the top-N tracker.
+const TRACKED_KEYS: usize = 1_024;
+
+/// The hash of Kafka's default partitioner, as in the Java client's
+/// `Utils.murmur2`, seeded with `0x9747b28c`.
+pub fn murmur2(data: &[u8]) -> i32 {
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+
+    let mut h = 0x9747b28c_u32 ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+
+    let rest = chunks.remainder();
+    if rest.len() >= 3 {
+        h ^= (rest[2] as u32) << 16;
+    }
+    if rest.len() >= 2 {
+        h ^= (rest[1] as u32) << 8;
+    }
+    if !rest.is_empty() {
+        h ^= rest[0] as u32;
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h as i32
+}
+
+/// The partition the default partitioner sends a keyed record to.
+pub fn partition_for(key: &[u8], partitions: usize) -> i32 {
+    ((murmur2(key) & 0x7fffffff) as usize % partitions) as i32
+}
+
+/// A 64 bit hash spreading keys evenly over the sketch's registers,
+/// FNV-1a finished with the SplitMix64 mixer.
+fn hash(key: &[u8]) -> u64 {
+    let mut h = 0xcbf29ce484222325_u64;
+    for b in key {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58476d1ce4e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// Estimates how many distinct keys were added, HyperLogLog style, in
+/// constant memory.
+pub struct DistinctSketch {
+    registers: Vec<u8>,
+}
+
+impl Default for DistinctSketch {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; REGISTERS],
+        }
+    }
+}
+
+impl DistinctSketch {
+    pub fn add(&mut self, key: &[u8]) {
+        let h = hash(key);
+        let register = (h >> (64 - PRECISION)) as usize;
+        let rank = ((h << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() + 1;
+        self.registers[register] = self.registers[register].max(rank as u8);
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum = self
+            .registers
+            .iter()
+            .map(|r| 2f64.powi(-(*r as i32)))
+            .sum::<f64>();
+        let raw = alpha * m * m / sum;
+
+        // Few keys leave registers empty, counting those is more accurate.
+        let empty = self.registers.iter().filter(|r| **r == 0).count();
+        let estimate = match raw <= 2.5 * m && empty > 0 {
+            true => m * (m / empty as f64).ln(),
+            false => raw,
+        };
+        estimate.round() as u64
+    }
+}
+
+/// Tracks the most frequent keys with the Space-Saving algorithm: once
+/// `TRACKED_KEYS` keys are counted, a new key replaces the least frequent
+/// one and inherits its count, so counts may overestimate by at most the
+/// count replaced.
+#[derive(Default)]
+pub struct TopKeys {
+    counts: HashMap<Vec<u8>, usize>,
+    evicted: bool,
+}
+
+impl TopKeys {
+    pub fn add(&mut self, key: &[u8]) {
+        if let Some(count) = self.counts.get_mut(key) {
+            *count += 1;
+            return;
+        }
+
+        let mut count = 0;
+        if self.counts.len() >= TRACKED_KEYS {
+            // Ties are broken by the key, so samples are summarized the same every time.
+            let (least, least_count) = self
+                .counts
+                .iter()
+                .min_by(|a, b| a.1.cmp(b.1).then_with(|| a.0.cmp(b.0)))
+                .map(|(k, c)| (k.clone(), *c))
+                .unwrap();
+            self.counts.remove(&least);
+            self.evicted = true;
+            count = least_count;
+        }
+        self.counts.insert(key.to_vec(), count + 1);
+    }
+
+    /// The `n` most frequent keys, by count then key.
+    pub fn top(&self, n: usize) -> Vec<(&[u8], usize)> {
+        let mut keys = self
+            .counts
+            .iter()
+            .map(|(k, c)| (k.as_slice(), *c))
+            .collect::<Vec<_>>();
+        keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        keys.truncate(n);
+        keys
+    }
+
+    /// Whether every key was counted, i.e. no more than `TRACKED_KEYS` were seen.
+    pub fn is_exact(&self) -> bool {
+        !self.evicted
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyEncoding {
+    Utf8,
+    /// Keys that aren't valid UTF-8.
+    Base64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyFrequency {
+    pub key: String,
+    pub encoding: KeyEncoding,
+    pub count: usize,
+    /// Of the keys sampled, null keys included.
+    pub percent: f64,
+    /// Where the default partitioner sends the key, unless the topic's
+    /// partitions are unknown.
+    pub partition: Option<i32>,
+}
+
+/// How the keys of a sample are distributed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyDistribution {
+    pub sampled: usize,
+    pub null_keys: usize,
+    pub null_key_percent: f64,
+    pub distinct_keys_estimate: u64,
+    pub top_keys: Vec<KeyFrequency>,
+    /// `false` when there were too many keys to count each, top counts may
+    /// then be overestimated.
+    pub exact_counts: bool,
+}
+
+/// Summarizes sampled keys in constant memory, however large the sample.
+#[derive(Default)]
+pub struct KeyStats {
+    sampled: usize,
+    null_keys: usize,
+    distinct: DistinctSketch,
+    top: TopKeys,
+}
+
+impl KeyStats {
+    pub fn add(&mut self, key: Option<&[u8]>) {
+        self.sampled += 1;
+        match key {
+            Some(key) => {
+                self.distinct.add(key);
+                self.top.add(key);
+            }
+            None => self.null_keys += 1,
+        }
+    }
+
+    /// The distribution with the `top` most frequent keys, hashed over the
+    /// topic's `partitions`.
+    pub fn distribution(&self, top: usize, partitions: usize) -> KeyDistribution {
+        let percent = |count: usize| match self.sampled {
+            0 => 0.0,
+            n => (count as f64 * 10_000.0 / n as f64).round() / 100.0,
+        };
+
+        let top_keys = self
+            .top
+            .top(top)
+            .into_iter()
+            .map(|(key, count)| {
+                let (key_str, encoding) = match std::str::from_utf8(key) {
+                    Ok(s) => (s.to_string(), KeyEncoding::Utf8),
+                    Err(_) => (base64::encode(key), KeyEncoding::Base64),
+                };
+                KeyFrequency {
+                    key: key_str,
+                    encoding,
+                    count,
+                    percent: percent(count),
+                    partition: (partitions > 0).then(|| partition_for(key, partitions)),
+                }
+            })
+            .collect();
+
+        KeyDistribution {
+            sampled: self.sampled,
+            null_keys: self.null_keys,
+            null_key_percent: percent(self.null_keys),
+            distinct_keys_estimate: self.distinct.estimate(),
+            top_keys,
+            exact_counts: self.top.is_exact(),
+        }
+    }
+}
+
+#[test]
+fn it_hashes_keys_like_the_default_partitioner() {
+    // The vectors of Kafka's own tests.
+    assert_eq!(murmur2(b"21"), -973932308);
+    assert_eq!(murmur2(b"foobar"), -790332482);
+    assert_eq!(murmur2(b"a-little-bit-long-string"), -985981536);
+    assert_eq!(murmur2(b"abc"), 479470107);
+
+    assert_eq!(partition_for(b"21", 12), (-973932308_i32 & 0x7fffffff) % 12);
+    assert!((0..6).contains(&partition_for(b"foobar", 6)));
+}
+
+#[test]
+fn it_summarizes_skewed_keys() {
+    let mut stats = KeyStats::default();
+    for i in 0..1_000 {
+        let key = match i % 10 {
+            // Half of the records share a single key.
+            0..=4 => "tenant-42".to_string(),
+            5 => "tenant-7".to_string(),
+            _ => format!("tenant-{}", 1_000 + i),
+        };
+        stats.add(Some(key.as_bytes()));
+    }
+
+    let d = stats.distribution(2, 6);
+    assert_eq!(d.sampled, 1_000);
+    assert_eq!(d.null_keys, 0);
+    assert!(d.exact_counts);
+    assert_eq!(
+        d.top_keys,
+        vec![
+            KeyFrequency {
+                key: "tenant-42".to_string(),
+                encoding: KeyEncoding::Utf8,
+                count: 500,
+                percent: 50.0,
+                partition: Some(partition_for(b"tenant-42", 6)),
+            },
+            KeyFrequency {
+                key: "tenant-7".to_string(),
+                encoding: KeyEncoding::Utf8,
+                count: 100,
+                percent: 10.0,
+                partition: Some(partition_for(b"tenant-7", 6)),
+            },
+        ]
+    );
+    let error = (d.distinct_keys_estimate as f64 - 402.0).abs() / 402.0;
+    assert!(error < 0.05, "{}", d.distinct_keys_estimate);
+}
+
+#[test]
+fn it_estimates_distinct_uniform_keys_in_constant_memory() {
+    let mut stats = KeyStats::default();
+    for i in 0..50_000_u32 {
+        stats.add(Some(&i.to_be_bytes()));
+    }
+
+    let d = stats.distribution(3, 0);
+    let error = (d.distinct_keys_estimate as f64 - 50_000.0).abs() / 50_000.0;
+    assert!(error < 0.05, "{}", d.distinct_keys_estimate);
+
+    // Too many keys to count each, the same ones are reported every time.
+    assert!(!d.exact_counts);
+    assert_eq!(d.top_keys.len(), 3);
+    assert_eq!(d.top_keys[0].encoding, KeyEncoding::Base64);
+    assert_eq!(d.top_keys[0].partition, None);
+    assert_eq!(stats.top.counts.len(), TRACKED_KEYS);
+
+    let mut again = KeyStats::default();
+    for i in 0..50_000_u32 {
+        again.add(Some(&i.to_be_bytes()));
+    }
+    assert_eq!(again.distribution(3, 0), d);
+}
+
+#[test]
+fn it_summarizes_null_keys() {
+    let mut stats = KeyStats::default();
+    for _ in 0..200 {
+        stats.add(None);
+    }
+
+    let d = stats.distribution(10, 6);
+    assert_eq!(d.sampled, 200);
+    assert_eq!(d.null_keys, 200);
+    assert_eq!(d.null_key_percent, 100.0);
+    assert_eq!(d.distinct_keys_estimate, 0);
+    assert!(d.top_keys.is_empty());
+
+    assert_eq!(
+        KeyStats::default().distribution(10, 6).null_key_percent,
+        0.0
+    );
+}
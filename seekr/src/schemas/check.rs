@@ -0,0 +1,167 @@
+use serde_json::Value;
+
+use crate::errors::AnyError;
+
+pub use seekr_api_types::admin::{SchemaIssue, SchemaReport};
+
+use super::store::SampleStore;
+use super::{Document, DOCUMENTS, VERSION_FIELD};
+
+/// How many of the most recent documents of each index are checked at startup.
+pub const SAMPLE_SIZE: usize = 100;
+
+/// Refuse incompatible documents in strict mode, otherwise only warn about them.
+pub fn enforce(report: &SchemaReport, strict: bool) -> Result<(), String> {
+    if report.is_compatible() {
+        info!(
+            "Checked {} stored documents against their schemas",
+            report.sampled
+        );
+        return Ok(());
+    }
+
+    match strict {
+        true => Err(report.to_string()),
+        false => {
+            warn!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Validate a stored document against the current schema of its kind.
+pub fn check(
+    document: &Document,
+    validator: &jsonschema::Validator,
+    value: &Value,
+) -> Vec<SchemaIssue> {
+    let id = value.get(document.key).map(|id| match id {
+        Value::String(s) => s.clone(),
+        id => id.to_string(),
+    });
+    let version = value.get(VERSION_FIELD).and_then(Value::as_u64);
+    let issue = |path: String, message: String| SchemaIssue {
+        document: document.name.to_string(),
+        id: id.clone(),
+        version,
+        path,
+        message,
+    };
+
+    let mut issues = validator
+        .iter_errors(value)
+        .map(|e| issue(e.instance_path().to_string(), e.to_string()))
+        .collect::<Vec<_>>();
+
+    if let Some(v) = version.filter(|&v| v > document.version) {
+        issues.push(issue(
+            format!("/{}", VERSION_FIELD),
+            format!(
+                "written with schema version {}, newer than the supported version {}",
+                v, document.version
+            ),
+        ));
+    }
+
+    issues
+}
+
+/// Check the most recent `limit` documents of every persisted kind.
+pub async fn verify(
+    store: &(dyn SampleStore + Send + Sync),
+    limit: usize,
+) -> Result<SchemaReport, AnyError> {
+    let mut report = SchemaReport::default();
+
+    for document in DOCUMENTS {
+        let validator = jsonschema::validator_for(&document.schema())?;
+        let sample = store.sample(document.index, limit).await?;
+
+        report.sampled += sample.len();
+        for value in &sample {
+            report.issues.extend(check(document, &validator, value));
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+fn fixture() -> super::store::MemorySampleStore {
+    use serde_json::json;
+
+    let cluster = json!({
+        "id": 1,
+        "kind": "Kafka",
+        "name": "local",
+        "config": {},
+        "created_at": "2022-10-01T12:00:00Z",
+        "updated_at": "2022-10-01T12:00:00Z",
+        "_schema_version": 1
+    });
+
+    // Written by a version that didn't know about a field now required.
+    let mut outdated = cluster.clone();
+    outdated["id"] = json!(2);
+    outdated.as_object_mut().unwrap().remove("name");
+    outdated.as_object_mut().unwrap().remove(VERSION_FIELD);
+
+    let mut store = super::store::MemorySampleStore::default();
+    store
+        .documents
+        .insert(super::CLUSTERS.index.to_string(), vec![cluster, outdated]);
+    store
+}
+
+#[tokio::test]
+async fn it_warns_about_documents_missing_required_fields() {
+    let report = verify(&fixture(), SAMPLE_SIZE).await.unwrap();
+
+    assert_eq!(report.sampled, 2);
+    assert!(!report.is_compatible());
+    assert_eq!(report.issues.len(), 1);
+
+    let issue = &report.issues[0];
+    assert_eq!(issue.document, "cluster");
+    assert_eq!(issue.id.as_deref(), Some("2"));
+    assert_eq!(issue.version, None);
+    assert!(issue.message.contains("\"name\""), "{}", issue.message);
+
+    // Outside of strict mode the server starts anyway.
+    assert_eq!(enforce(&report, false), Ok(()));
+}
+
+#[tokio::test]
+async fn it_refuses_incompatible_documents_in_strict_mode() {
+    let report = verify(&fixture(), SAMPLE_SIZE).await.unwrap();
+
+    let error = enforce(&report, true).unwrap_err();
+    assert!(
+        error.starts_with("1 of 2 sampled stored documents"),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("cluster 2 at '': \"name\" is a required property"),
+        "{}",
+        error
+    );
+
+    let compatible = SchemaReport {
+        sampled: 1,
+        issues: vec![],
+    };
+    assert_eq!(enforce(&compatible, true), Ok(()));
+}
+
+#[tokio::test]
+async fn it_flags_documents_from_newer_schema_versions() {
+    let mut store = fixture();
+    let clusters = store.documents.get_mut(super::CLUSTERS.index).unwrap();
+    clusters.truncate(1);
+    clusters[0][VERSION_FIELD] = serde_json::json!(super::CLUSTERS.version + 1);
+
+    let report = verify(&store, SAMPLE_SIZE).await.unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].path, "/_schema_version");
+}
@@ -0,0 +1,168 @@
+use actix_web::web::{Data, Path, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use seekr_api_types::admin::{ReadyResponse, ReadyStatus};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::capabilities::{self, Prober};
+use crate::schemas::check::SchemaReport;
+use crate::schemas::{self, DOCUMENTS};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(list_schemas)
+        .service(get_schema)
+        .service(get_ready);
+}
+
+#[get("/schemas")]
+async fn list_schemas() -> impl Responder {
+    let schemas = DOCUMENTS
+        .into_iter()
+        .map(SchemaResponse::from)
+        .collect::<Vec<_>>();
+
+    HttpResponse::Ok().json(schemas)
+}
+
+#[get("/schemas/{name}")]
+async fn get_schema(path: Path<String>) -> impl Responder {
+    match schemas::document(&path.into_inner()) {
+        Some(document) => HttpResponse::Ok().json(document.schema()),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// The server is ready once started; incompatible stored documents found by
+/// the startup check are reported, but only refuse the start in strict mode.
+/// Capabilities impaired by their dependencies are reported the same way.
+#[get("/ready")]
+async fn get_ready(
+    report: Option<Data<SchemaReport>>,
+    prober: Option<Data<Prober>>,
+) -> impl Responder {
+    let schema = report.map(|r| r.get_ref().clone()).unwrap_or_default();
+    let capabilities = match prober {
+        Some(prober) => capabilities::evaluate(&prober.probe().await),
+        None => Default::default(),
+    };
+    let status = match schema.is_compatible() && !capabilities::impaired(&capabilities) {
+        true => ReadyStatus::Ready,
+        false => ReadyStatus::Degraded,
+    };
+
+    HttpResponse::Ok().json(ReadyResponse {
+        status,
+        schema,
+        capabilities,
+    })
+}
+
+#[derive(Serialize)]
+struct SchemaResponse {
+    name: &'static str,
+    index: &'static str,
+    version: u64,
+    schema: Value,
+}
+
+impl From<&schemas::Document> for SchemaResponse {
+    fn from(d: &schemas::Document) -> Self {
+        Self {
+            name: d.name,
+            index: d.index,
+            version: d.version,
+            schema: d.schema(),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn it_reports_incompatible_documents_when_ready() {
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+
+    use crate::schemas::check::SchemaIssue;
+
+    let report = SchemaReport {
+        sampled: 3,
+        issues: vec![SchemaIssue {
+            document: "subscription".to_string(),
+            id: Some("7".to_string()),
+            version: None,
+            path: "".to_string(),
+            message: "\"topic_name\" is a required property".to_string(),
+        }],
+    };
+    let app = test::init_service(App::new().app_data(Data::new(report)).configure(configure)).await;
+
+    let req = TestRequest::get().uri("/ready").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["schema"]["issues"][0]["id"], "7");
+
+    let req = TestRequest::get().uri("/schemas").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let names = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "cluster",
+            "subscription",
+            "history_entry",
+            "metadata_snapshot"
+        ]
+    );
+
+    let req = TestRequest::get().uri("/schemas/cluster").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["title"], "Cluster");
+
+    let req = TestRequest::get().uri("/schemas/unknown").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn it_reports_capabilities_impaired_by_their_dependencies() {
+    use std::sync::Arc;
+
+    use actix_web::test::{self, TestRequest};
+    use actix_web::App;
+    use futures::FutureExt;
+
+    use crate::capabilities::HealthCheck;
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::{MetadataConsumerFactory, MetadataManager};
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers".into()));
+    let manager = Arc::new(MetadataManager::with_factory(
+        Arc::new(MemoryClusterStore::default()),
+        factory,
+    ));
+    let down: HealthCheck = Arc::new(|| async { Err("connection refused".into()) }.boxed());
+    let client = crate::meilisearch::MeilisearchConfig::default().client();
+    let prober = Prober::new(client, manager).with_meilisearch(down);
+    let app = test::init_service(App::new().app_data(Data::new(prober)).configure(configure)).await;
+
+    let req = TestRequest::get().uri("/ready").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["schema"]["issues"], serde_json::json!([]));
+    assert_eq!(
+        body["capabilities"]["search"],
+        serde_json::json!({"status": "unavailable", "limited_by": "meilisearch"})
+    );
+    assert_eq!(
+        body["capabilities"]["cluster_crud"]["status"],
+        "unavailable"
+    );
+    assert_eq!(
+        body["capabilities"]["metadata_read"],
+        serde_json::json!({"status": "ok"})
+    );
+}
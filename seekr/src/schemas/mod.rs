@@ -0,0 +1,153 @@
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::clusters::cluster::Cluster;
+use crate::history::event::{HistoryEntry, Snapshot};
+use crate::subscriptions::subscription::Subscription;
+use crate::{clusters, history, subscriptions};
+
+pub mod check;
+pub mod endpoints;
+pub mod store;
+
+/// The field persisted documents carry the version of their schema in.
+pub const VERSION_FIELD: &str = "_schema_version";
+
+/// A kind of document the server persists.
+pub struct Document {
+    pub name: &'static str,
+
+    /// The index the documents are stored in.
+    pub index: &'static str,
+
+    /// The field identifying a document within its index.
+    pub key: &'static str,
+
+    /// The schema version written documents are stamped with. Bump it
+    /// together with the golden schema whenever the document shape changes.
+    pub version: u64,
+
+    generate: fn() -> RootSchema,
+}
+
+impl Document {
+    /// The JSON Schema documents of this kind are validated against.
+    pub fn schema(&self) -> Value {
+        serde_json::to_value((self.generate)()).expect("schemas serialize to JSON")
+    }
+
+    /// Stamp a document with the current schema version before it is written.
+    pub fn versioned<'a, T: Serialize>(&self, document: &'a T) -> Versioned<'a, T> {
+        Versioned {
+            document,
+            version: self.version,
+        }
+    }
+}
+
+/// A document together with the schema version it is written with.
+#[derive(Serialize)]
+pub struct Versioned<'a, T> {
+    #[serde(flatten)]
+    document: &'a T,
+
+    #[serde(rename = "_schema_version")]
+    version: u64,
+}
+
+pub const CLUSTERS: Document = Document {
+    name: "cluster",
+    index: clusters::store::INDEX_NAME,
+    key: "id",
+    version: 1,
+    generate: || schema_for!(Cluster),
+};
+
+pub const SUBSCRIPTIONS: Document = Document {
+    name: "subscription",
+    index: subscriptions::store::INDEX_NAME,
+    key: "id",
+    version: 2,
+    generate: || schema_for!(Subscription),
+};
+
+pub const HISTORY: Document = Document {
+    name: "history_entry",
+    index: history::store::INDEX_NAME,
+    key: "id",
+    version: 1,
+    generate: || schema_for!(HistoryEntry),
+};
+
+pub const SNAPSHOTS: Document = Document {
+    name: "metadata_snapshot",
+    index: history::store::SNAPSHOTS_INDEX_NAME,
+    key: "cluster_id",
+    version: 1,
+    generate: || schema_for!(Snapshot),
+};
+
+/// Every persisted document kind.
+pub const DOCUMENTS: [&Document; 4] = [&CLUSTERS, &SUBSCRIPTIONS, &HISTORY, &SNAPSHOTS];
+
+pub fn document(name: &str) -> Option<&'static Document> {
+    DOCUMENTS.into_iter().find(|d| d.name == name)
+}
+
+/// Changing a persisted type changes its schema, which has to be reviewed
+/// together with the golden file under `src/schemas/goldens` and, when older
+/// documents no longer match, a version bump. Run with `UPDATE_GOLDENS=1` to
+/// rewrite the goldens.
+#[test]
+fn it_matches_golden_schemas() {
+    for document in DOCUMENTS {
+        let path = format!(
+            "{}/src/schemas/goldens/{}.json",
+            env!("CARGO_MANIFEST_DIR"),
+            document.name
+        );
+        let schema = serde_json::to_string_pretty(&document.schema()).unwrap() + "\n";
+
+        if std::env::var_os("UPDATE_GOLDENS").is_some() {
+            std::fs::write(&path, &schema).unwrap();
+            continue;
+        }
+
+        let golden = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(schema, golden, "document schema changed, see {}", path);
+    }
+}
+
+#[test]
+fn it_stamps_documents_with_their_schema_version() {
+    use std::collections::HashMap;
+
+    use crate::clusters::cluster::Kind;
+    use crate::ids::ClusterId;
+
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "local".to_string(),
+        HashMap::new(),
+    );
+
+    let json = serde_json::to_value(CLUSTERS.versioned(&cluster)).unwrap();
+    assert_eq!(json[VERSION_FIELD], 1);
+    assert_eq!(json["name"], "local");
+
+    // Readers ignore the stamp, also next to flattened fields.
+    assert_eq!(serde_json::from_value::<Cluster>(json).unwrap(), cluster);
+
+    let entry = HistoryEntry::new(
+        ClusterId(1),
+        cluster.created_at,
+        crate::history::event::HistoryEvent::Change {
+            change: crate::history::diff::Change::BrokerAdded { broker: 1 },
+        },
+    );
+    let json = serde_json::to_value(HISTORY.versioned(&entry)).unwrap();
+    assert_eq!(serde_json::from_value::<HistoryEntry>(json).unwrap(), entry);
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::Client;
+use serde_json::Value;
+
+use crate::errors::AnyError;
+use crate::meilisearch::MeilisearchConfig;
+
+#[async_trait]
+pub trait SampleStore {
+    /// Up to `limit` of the most recently written documents of an index, as stored.
+    async fn sample(&self, index: &str, limit: usize) -> Result<Vec<Value>, AnyError>;
+}
+
+pub struct MSSampleStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+}
+
+impl MSSampleStore {
+    pub fn new(client: Arc<Client>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SampleStore for MSSampleStore {
+    async fn sample(&self, index: &str, limit: usize) -> Result<Vec<Value>, AnyError> {
+        let index = self.client.index(index);
+
+        // Documents are listed in insertion order, so the newest are at the end.
+        let total = index.get_stats().await?.number_of_documents;
+        let docs = index
+            .get_documents_with::<Value>(
+                DocumentsQuery::new(&index)
+                    .with_offset(total.saturating_sub(limit))
+                    .with_limit(limit),
+            )
+            .await?;
+
+        Ok(docs.results)
+    }
+}
+
+/// An in-memory store used to exercise the compatibility check in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemorySampleStore {
+    pub documents: std::collections::HashMap<String, Vec<Value>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl SampleStore for MemorySampleStore {
+    async fn sample(&self, index: &str, limit: usize) -> Result<Vec<Value>, AnyError> {
+        let mut docs = self.documents.get(index).cloned().unwrap_or_default();
+        docs.drain(..docs.len().saturating_sub(limit));
+        Ok(docs)
+    }
+}
+
+pub async fn init_sample_store(config: &MeilisearchConfig) -> Arc<dyn SampleStore + Send + Sync> {
+    Arc::new(MSSampleStore::new(config.client()))
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Query, ServiceConfig};
+use actix_web::{get, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Principal;
+use crate::clusters::endpoints::v1::ClusterSummery;
+use crate::clusters::store::ClusterStore;
+use crate::errors::{AnyError, ApiError, ErrorBody};
+use crate::governance::policy::OwnershipPolicy;
+use crate::search::{Scored, SearchUnsupported, MIN_QUERY_LEN};
+use crate::subscriptions::endpoints::v1::SubscriptionSummery;
+use crate::subscriptions::store::SubscriptionStore;
+
+/// Hits of each type unless the request says otherwise, and at most.
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+const TYPES: [&str; 2] = ["clusters", "subscriptions"];
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(search);
+}
+
+#[get("")]
+async fn search(
+    query: Query<SearchQuery>,
+    principal: Principal,
+    policy: OwnershipPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let q = query.q.as_deref().unwrap_or_default().trim();
+    info!("Searching clusters and subscriptions for '{}'", q);
+
+    if q.chars().count() < MIN_QUERY_LEN {
+        return Err(ApiError::invalid(format!(
+            "q must be at least {} characters",
+            MIN_QUERY_LEN
+        )));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::invalid(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    let types = match &query.types {
+        Some(types) => types.split(',').map(str::trim).collect::<Vec<_>>(),
+        None => TYPES.to_vec(),
+    };
+    if let Some(t) = types.iter().find(|t| !TYPES.contains(t)) {
+        return Err(ApiError::invalid(format!(
+            "unknown type '{}', expected one of: {}",
+            t,
+            TYPES.join(", ")
+        )));
+    }
+
+    // Each type is searched only when asked for, both at once.
+    let clusters = async {
+        match types.contains(&"clusters") {
+            true => Some(cs.search(q, limit).await),
+            false => None,
+        }
+    };
+    let subscriptions = async {
+        match types.contains(&"subscriptions") {
+            true => Some(ss.search(q, limit).await),
+            false => None,
+        }
+    };
+    let (clusters, subscriptions) = tokio::join!(clusters, subscriptions);
+
+    let mut response = SearchResponse::default();
+    if let Some(hits) = type_hits("clusters", clusters, &mut response.errors)? {
+        let hits = hits.into_iter().filter(|h| principal.can_access(h.item.id));
+        response.clusters = Some(hits.map(|h| h.map(|c| c.to_summary(&policy))).collect());
+    }
+    if let Some(hits) = type_hits("subscriptions", subscriptions, &mut response.errors)? {
+        let hits = hits
+            .into_iter()
+            .filter(|h| principal.can_access(h.item.cluster_id))
+            .filter(|h| !h.item.is_pending_deletion());
+        response.subscriptions = Some(hits.map(|h| h.map(|s| s.to_summary(&policy))).collect());
+    }
+
+    // Only when no type asked for could be searched is the search itself unsupported.
+    if response.clusters.is_none() && response.subscriptions.is_none() {
+        if let Some(failure) = response.errors.values().next() {
+            return Ok(HttpResponse::NotImplemented().json(&failure.error));
+        }
+    }
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// The hits of a type, or `None` with the type's failure recorded when its
+/// store can't search. Other errors fail the whole search.
+fn type_hits<T>(
+    kind: &'static str,
+    result: Option<Result<Vec<Scored<T>>, AnyError>>,
+    errors: &mut BTreeMap<&'static str, TypeFailure>,
+) -> Result<Option<Vec<Scored<T>>>, ApiError> {
+    match result {
+        Some(Err(e)) if e.is::<SearchUnsupported>() => {
+            let failure = TypeFailure {
+                status: StatusCode::NOT_IMPLEMENTED.as_u16(),
+                error: ErrorBody::new("search_unsupported", e.to_string()),
+            };
+            errors.insert(kind, failure);
+            Ok(None)
+        }
+        Some(result) => Ok(Some(result?)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+    /// The types to search, comma separated, every type by default.
+    types: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Default, Serialize)]
+struct SearchResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clusters: Option<Vec<Scored<ClusterSummery>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscriptions: Option<Vec<Scored<SubscriptionSummery>>>,
+
+    /// The types asked for that couldn't be searched.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    errors: BTreeMap<&'static str, TypeFailure>,
+}
+
+/// Why a type couldn't be searched, with the status it would have been answered with.
+#[derive(Serialize)]
+struct TypeFailure {
+    status: u16,
+    #[serde(flatten)]
+    error: ErrorBody,
+}
+
+/// Subscriptions on a backend that can't search.
+#[cfg(test)]
+struct Unsearchable(crate::subscriptions::store::MemorySubscriptionStore);
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl SubscriptionStore for Unsearchable {
+    async fn list(
+        &self,
+        cluster_id: Option<crate::ids::ClusterId>,
+        page: crate::page::Page,
+    ) -> Result<Vec<crate::subscriptions::subscription::Subscription>, AnyError> {
+        self.0.list(cluster_id, page).await
+    }
+
+    async fn count(&self, cluster_id: Option<crate::ids::ClusterId>) -> Result<usize, AnyError> {
+        self.0.count(cluster_id).await
+    }
+
+    async fn search(
+        &self,
+        _q: &str,
+        _limit: usize,
+    ) -> Result<Vec<Scored<crate::subscriptions::subscription::Subscription>>, AnyError> {
+        Err(SearchUnsupported("Cassandra").into())
+    }
+
+    async fn get(
+        &self,
+        cluster_id: crate::ids::ClusterId,
+        id: crate::ids::SubscriptionId,
+    ) -> Result<Option<crate::subscriptions::subscription::Subscription>, AnyError> {
+        self.0.get(cluster_id, id).await
+    }
+
+    async fn insert(
+        &self,
+        s: crate::subscriptions::subscription::Subscription,
+    ) -> Result<crate::ids::SubscriptionId, AnyError> {
+        self.0.insert(s).await
+    }
+
+    async fn update(
+        &self,
+        s: crate::subscriptions::subscription::Subscription,
+    ) -> Result<crate::ids::SubscriptionId, AnyError> {
+        self.0.update(s).await
+    }
+
+    async fn put(
+        &self,
+        s: crate::subscriptions::subscription::Subscription,
+    ) -> Result<crate::ids::SubscriptionId, AnyError> {
+        self.0.put(s).await
+    }
+
+    async fn remove(
+        &self,
+        cluster_id: crate::ids::ClusterId,
+        id: crate::ids::SubscriptionId,
+    ) -> Result<crate::ids::SubscriptionId, AnyError> {
+        self.0.remove(cluster_id, id).await
+    }
+}
+
+#[actix_web::test]
+async fn it_searches_clusters_and_subscriptions_at_once() {
+    use std::collections::HashMap;
+
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::ids::{ClusterId, SubscriptionId};
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::Subscription;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    for (id, name) in [(1, "orders-eu"), (2, "payments"), (3, "orders")] {
+        let cluster = Cluster::new(
+            Some(ClusterId(id)),
+            Kind::Kafka,
+            name.to_string(),
+            HashMap::new(),
+        );
+        cs.put(cluster).await.unwrap();
+    }
+    let memory = MemorySubscriptionStore::default();
+    for (id, topic) in [(1, "orders.created"), (2, "refunds")] {
+        let subscription = Subscription::new(
+            Some(SubscriptionId(id)),
+            ClusterId(2),
+            topic.to_string(),
+            HashMap::new(),
+        );
+        memory.put(subscription).await.unwrap();
+    }
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(memory);
+    let unsearchable: Arc<dyn SubscriptionStore + Send + Sync> =
+        Arc::new(Unsearchable(MemorySubscriptionStore::default()));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs.clone()))
+            .app_data(Data::new(ss))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let search = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/search{}", query))
+            .to_request()
+    };
+    let names = |hits: &Value, field: &str| {
+        hits.as_array()
+            .unwrap()
+            .iter()
+            .map(|h| h[field].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, search("?q=order")).await;
+    assert_eq!(
+        names(&body["clusters"], "name"),
+        vec!["orders", "orders-eu"]
+    );
+    assert_eq!(body["clusters"][0]["score"], 1.0);
+    assert_eq!(
+        names(&body["subscriptions"], "topic_name"),
+        vec!["orders.created"]
+    );
+    assert!(body.get("errors").is_none());
+
+    let body: Value =
+        test::call_and_read_body_json(&app, search("?q=order&types=subscriptions")).await;
+    assert!(body.get("clusters").is_none());
+    assert_eq!(body["subscriptions"].as_array().unwrap().len(), 1);
+
+    for query in [
+        "?q=o",
+        "?q=%20o%20",
+        "",
+        "?q=order&types=topics",
+        "?q=order&limit=0",
+    ] {
+        let res = test::call_service(&app, search(query)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", query);
+    }
+
+    // A store that can't search fails its type only.
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(unsearchable))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let body: Value = test::call_and_read_body_json(&app, search("?q=pay")).await;
+    assert_eq!(names(&body["clusters"], "name"), vec!["payments"]);
+    assert!(body.get("subscriptions").is_none());
+    assert_eq!(body["errors"]["subscriptions"]["status"], 501);
+    assert_eq!(
+        body["errors"]["subscriptions"]["code"],
+        "search_unsupported"
+    );
+
+    let res = test::call_service(&app, search("?q=pay&types=subscriptions")).await;
+    assert_eq!(res.status(), StatusCode::NOT_IMPLEMENTED);
+}
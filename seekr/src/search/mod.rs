@@ -0,0 +1,115 @@
+use std::fmt;
+
+use meilisearch_sdk::indexes::Index;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::AnyError;
+
+pub mod endpoints;
+
+/// Queries shorter than this match too much of everything to be useful.
+pub const MIN_QUERY_LEN: usize = 2;
+
+/// An item matching a search, with how well it matches.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Scored<T> {
+    #[serde(flatten)]
+    pub item: T,
+
+    /// From 1 for the best match down towards 0, only comparable to the
+    /// scores of the same search of the same store.
+    pub score: f64,
+}
+
+impl<T> Scored<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Scored<U> {
+        Scored {
+            item: f(self.item),
+            score: self.score,
+        }
+    }
+}
+
+/// Score items ranked best first by their rank.
+pub fn rank<T>(items: Vec<T>) -> Vec<Scored<T>> {
+    let n = items.len() as f64;
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| Scored {
+            item,
+            score: 1.0 - i as f64 / n,
+        })
+        .collect()
+}
+
+/// A store whose backend can't search, e.g. Cassandra.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchUnsupported(pub &'static str);
+
+impl fmt::Display for SearchUnsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Search is unsupported for the {} backend", self.0)
+    }
+}
+
+impl std::error::Error for SearchUnsupported {}
+
+/// The best `limit` documents of the index matching `q`, in Meilisearch's
+/// relevance order.
+pub async fn meilisearch<T>(
+    index: &Index,
+    q: &str,
+    limit: usize,
+) -> Result<Vec<Scored<T>>, AnyError>
+where
+    T: DeserializeOwned + 'static,
+{
+    let results = index
+        .search()
+        .with_query(q)
+        .with_limit(limit)
+        .execute::<T>()
+        .await?;
+    Ok(rank(results.hits.into_iter().map(|h| h.result).collect()))
+}
+
+/// The items whose text contains `q` in any case, the shortest texts, the
+/// closest matches, first.
+pub fn in_memory<T>(
+    items: impl IntoIterator<Item = T>,
+    q: &str,
+    limit: usize,
+    text: impl Fn(&T) -> &str,
+) -> Vec<Scored<T>> {
+    let q = q.to_lowercase();
+    let mut matches = items
+        .into_iter()
+        .filter(|item| text(item).to_lowercase().contains(&q))
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|item| text(item).len());
+    matches.truncate(limit);
+    rank(matches)
+}
+
+#[test]
+fn it_ranks_in_memory_matches() {
+    let names = ["orders-v2", "refunds", "Orders", "audit"];
+    let hits = in_memory(names, "ORDER", 10, |n| *n);
+    assert_eq!(
+        hits,
+        vec![
+            Scored {
+                item: "Orders",
+                score: 1.0
+            },
+            Scored {
+                item: "orders-v2",
+                score: 0.5
+            },
+        ]
+    );
+    assert_eq!(in_memory(names, "r", 1, |n| *n).len(), 1);
+    assert!(in_memory(names, "kafka", 10, |n| *n).is_empty());
+}
@@ -0,0 +1,37 @@
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::auth::Principal;
+use crate::search_cache::{CacheStats, SearchCache};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_search_cache);
+}
+
+#[get("/search-cache")]
+async fn get_search_cache(
+    principal: Principal,
+    cache: Option<Data<SearchCache>>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    match cache {
+        Some(cache) => HttpResponse::Ok().json(SearchCacheResponse {
+            max_entries: cache.max_entries(),
+            max_bytes: cache.max_bytes(),
+            stats: cache.stats(),
+        }),
+        None => HttpResponse::NotFound().body("The search cache is disabled"),
+    }
+}
+
+#[derive(Serialize)]
+struct SearchCacheResponse {
+    max_entries: usize,
+    max_bytes: usize,
+    #[serde(flatten)]
+    stats: CacheStats,
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::shards::search::{self, SearchQuery};
+use crate::shards::store::DocumentStore;
+
+pub mod endpoints;
+
+/// The most searches kept by default.
+pub const DEFAULT_MAX_ENTRIES: usize = 1_000;
+
+/// The most bytes of results kept by default.
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// The searches of a subscription, identified by their normalized query.
+type Key = (SubscriptionId, String);
+
+/// Counters of the cache since the server started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+
+    /// Entries dropped because their subscription was written since they were cached.
+    pub invalidations: u64,
+
+    /// Entries dropped to stay within the entry count or byte size.
+    pub evictions: u64,
+
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+struct Entry {
+    body: String,
+    /// When the entry was last read or written, its position in `recency`.
+    used: u64,
+}
+
+impl Entry {
+    fn size(key: &Key, body: &str) -> usize {
+        key.1.len() + body.len()
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Key, Entry>,
+
+    /// The keys by when they were last used, least recently first.
+    recency: BTreeMap<u64, Key>,
+
+    /// The latest update marker seen for each subscription, carried by all its entries.
+    markers: HashMap<SubscriptionId, Option<u64>>,
+
+    clock: u64,
+    bytes: usize,
+    stats: CacheStats,
+}
+
+impl Inner {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Record the subscription's marker, dropping its entries once it moved.
+    ///
+    /// Returns whether the marker is the latest seen, a search that read an
+    /// older one raced a write and must neither read nor fill the cache.
+    fn observe(&mut self, id: SubscriptionId, marker: Option<u64>) -> bool {
+        match self.markers.get(&id) {
+            Some(known) if marker < *known => false,
+            Some(known) if marker == *known => true,
+            _ => {
+                let stale = self
+                    .entries
+                    .keys()
+                    .filter(|(i, _)| *i == id)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                self.stats.invalidations += stale.len() as u64;
+                stale.iter().for_each(|k| self.remove(k));
+                self.markers.insert(id, marker);
+                true
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.used);
+            self.bytes -= Entry::size(key, &entry.body);
+        }
+    }
+}
+
+/// Caches the results of searches until their subscription is written.
+///
+/// Entries carry the update marker of their subscription's shards when they
+/// were searched, and are served only while it's unchanged, so cached results
+/// are never older than the latest write. The least recently used entries are
+/// evicted beyond the entry count or byte size.
+pub struct SearchCache {
+    max_entries: usize,
+    max_bytes: usize,
+    inner: Mutex<Inner>,
+}
+
+impl Default for SearchCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES, DEFAULT_MAX_BYTES)
+    }
+}
+
+impl SearchCache {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+            inner: Default::default(),
+        }
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats {
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+            ..inner.stats
+        }
+    }
+
+    /// The serialized page of the search, from the cache while the
+    /// subscription wasn't written since it was cached.
+    pub async fn search(
+        &self,
+        store: &(dyn DocumentStore + Send + Sync),
+        id: SubscriptionId,
+        query: &SearchQuery,
+    ) -> Result<String, AnyError> {
+        // Read before searching, so a write landing meanwhile moves the marker past the entry.
+        let marker = store.last_update(id).await?;
+        let key = (id, key(query));
+        if let Some(body) = self.get(&key, marker) {
+            return Ok(body);
+        }
+
+        let page = search::search(store, id, query).await?;
+        let body = serde_json::to_string(&page)?;
+        self.insert(key, marker, body.clone());
+        Ok(body)
+    }
+
+    fn get(&self, key: &Key, marker: Option<u64>) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let used = inner.tick();
+        let body = match inner.observe(key.0, marker) {
+            true => inner.entries.get_mut(key).map(|entry| {
+                let last = std::mem::replace(&mut entry.used, used);
+                (last, entry.body.clone())
+            }),
+            false => None,
+        };
+
+        match body {
+            Some((last, body)) => {
+                inner.recency.remove(&last);
+                inner.recency.insert(used, key.clone());
+                inner.stats.hits += 1;
+                Some(body)
+            }
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: Key, marker: Option<u64>, body: String) {
+        let size = Entry::size(&key, &body);
+        if size > self.max_bytes || self.max_entries == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.observe(key.0, marker) {
+            return;
+        }
+
+        inner.remove(&key);
+        let used = inner.tick();
+        inner.recency.insert(used, key.clone());
+        inner.entries.insert(key, Entry { body, used });
+        inner.bytes += size;
+
+        while inner.entries.len() > self.max_entries || inner.bytes > self.max_bytes {
+            let Some((_, oldest)) = inner.recency.pop_first() else {
+                break;
+            };
+            inner.remove(&oldest);
+            inner.stats.evictions += 1;
+        }
+    }
+}
+
+/// The cache key of a search, the same for every spelling of the same query.
+///
+/// The fields are written in a fixed order, once their defaults were filled in.
+pub fn key(query: &SearchQuery) -> String {
+    #[derive(Serialize)]
+    struct Normalized<'a> {
+        from: Option<i64>,
+        limit: usize,
+        offset: usize,
+        q: Option<&'a str>,
+        to: Option<i64>,
+    }
+
+    let normalized = Normalized {
+        from: query.from,
+        limit: query.limit,
+        offset: query.offset,
+        q: query.q.as_deref().filter(|q| !q.is_empty()),
+        to: query.to,
+    };
+    serde_json::to_string(&normalized).unwrap()
+}
+
+#[cfg(test)]
+fn query(q: &str) -> SearchQuery {
+    SearchQuery {
+        q: Some(q.to_string()),
+        limit: 20,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn it_normalizes_queries_deterministically() {
+    let empty = SearchQuery {
+        q: Some(String::new()),
+        limit: 20,
+        ..Default::default()
+    };
+    let unset = SearchQuery {
+        limit: 20,
+        ..Default::default()
+    };
+    assert_eq!(key(&empty), key(&unset));
+    assert_eq!(
+        key(&unset),
+        r#"{"from":null,"limit":20,"offset":0,"q":null,"to":null}"#
+    );
+
+    assert_ne!(key(&query("orders")), key(&query("order")));
+    assert_ne!(
+        key(&query("orders")),
+        key(&SearchQuery {
+            offset: 20,
+            ..query("orders")
+        })
+    );
+
+    // Separators inside the query can't be mistaken for other fields.
+    let tricky = query(r#"a","limit":40,"q":"b"#);
+    assert_ne!(key(&tricky), key(&query("a")));
+    assert_eq!(key(&tricky), key(&tricky.clone()));
+}
+
+#[tokio::test]
+async fn it_invalidates_entries_once_the_subscription_is_written() {
+    use crate::shards::router::{router, ts};
+
+    // The router stands in for the indexer, each flush moves the store's marker.
+    let (store, mut router) = router(None);
+    router
+        .route(vec![("0-1", ts("2024-05-01T00:00:00Z"))])
+        .await;
+
+    let cache = SearchCache::default();
+    let id = SubscriptionId(1);
+    let search = |q| {
+        let (cache, store) = (&cache, store.clone());
+        async move {
+            let body = cache.search(store.as_ref(), id, &q).await.unwrap();
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()["estimated_total_hits"]
+                .as_u64()
+                .unwrap()
+        }
+    };
+
+    assert_eq!(search(query("2024")).await, 0);
+    assert_eq!(search(query("0-1")).await, 1);
+    assert_eq!(search(query("0-1")).await, 1);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+
+    router
+        .route(vec![("0-2", ts("2024-05-02T00:00:00Z"))])
+        .await;
+    assert_eq!(search(query("0-")).await, 2);
+    let stats = cache.stats();
+    assert_eq!((stats.invalidations, stats.entries), (2, 1));
+
+    // Entries cached after the flush are served until the next one.
+    assert_eq!(search(query("0-")).await, 2);
+    assert_eq!(search(query("0-1")).await, 1);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses), (2, 4));
+}
+
+#[test]
+fn it_bounds_the_cache_by_bytes_and_entries() {
+    let id = SubscriptionId(1);
+    let key = |k: &str| (id, k.to_string());
+    let cache = SearchCache::new(3, 1_000);
+
+    cache.insert(key("a"), Some(1), "x".repeat(300));
+    cache.insert(key("b"), Some(1), "x".repeat(300));
+    assert_eq!(cache.stats().bytes, 602);
+
+    // An oversized page is never cached, and doesn't evict anything to try.
+    cache.insert(key("huge"), Some(1), "x".repeat(5_000));
+    assert!(cache.get(&key("huge"), Some(1)).is_none());
+    assert_eq!((cache.stats().entries, cache.stats().evictions), (2, 0));
+
+    // Reading `a` makes `b` the least recently used, evicted to fit `c`.
+    assert!(cache.get(&key("a"), Some(1)).is_some());
+    cache.insert(key("c"), Some(1), "x".repeat(500));
+    assert!(cache.get(&key("b"), Some(1)).is_none());
+    assert!(cache.get(&key("a"), Some(1)).is_some());
+    assert!(cache.get(&key("c"), Some(1)).is_some());
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.bytes, stats.evictions), (2, 802, 1));
+    assert!(stats.bytes <= 1_000);
+
+    // Small pages are bounded by the entry count instead.
+    cache.insert(key("d"), Some(1), "x".to_string());
+    cache.insert(key("e"), Some(1), "x".to_string());
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.evictions), (3, 2));
+    assert!(cache.get(&key("a"), Some(1)).is_none());
+
+    // A search that read the marker before the latest write leaves the cache alone.
+    cache.insert(key("f"), Some(0), "x".to_string());
+    assert!(cache.get(&key("f"), Some(1)).is_none());
+    assert_eq!(cache.stats().invalidations, 0);
+}
@@ -1,56 +1,429 @@
-use actix_web::middleware;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::middleware::{self, from_fn};
 use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
 
-use crate::clusters::endpoints::v1::configure as configure_cluster;
-use crate::clusters::store::init_cluster_store;
+use crate::api;
+use crate::api::routes::RouteGroup;
+use crate::auth::reveal::RevealToken;
+use crate::auth::store::init_api_key_store;
+use crate::auth::{ApiToken, Authenticator};
+use crate::backend::StoreConfig;
+use crate::bundle::Bundler;
+use crate::capabilities::Prober;
+use crate::changefeed::store::init_changefeed_store;
+use crate::clusters::store::{init_cluster_store, ClusterStore};
+use crate::collisions::CollisionScanner;
+use crate::commands::store::init_command_store;
+use crate::cors::CorsConfig;
+use crate::counters::reconcile::Reconciler;
+use crate::counters::store::{CountedClusterStore, CountedSubscriptionStore};
+use crate::counters::Counters;
+use crate::debug::store::init_debug_store;
+use crate::drain::Drain;
+use crate::governance::policy::OwnershipPolicy;
+use crate::governance::report::OwnershipCheck;
+use crate::history::notify::{LogNotifier, Notifier};
+use crate::history::recorder::HistoryRecorder;
+use crate::history::store::init_history_store;
+use crate::kafka::admin::{KafkaAdminService, TopicAdmin};
 use crate::kafka::metadata::manager::MetadataManager;
+use crate::kafka::metadata::redact::RedactionPolicy;
+use crate::kafka::producer::{KafkaMessageProducer, MessageProducer};
+use crate::lint::LintPolicy;
+use crate::live::LiveStreams;
 use crate::logger;
-use crate::subscriptions::endpoints::v1::configure as configure_subscription;
-use crate::subscriptions::store::init_subscription_store;
+use crate::logs::LogBuffer;
+use crate::lookup::source::{KafkaRecordSource, RecordSource};
+use crate::mirrors::monitor::MirrorMonitor;
+use crate::mirrors::store::init_mirror_pair_store;
+use crate::produce::audit::{AuditLog, LogAuditLog};
+use crate::produce::store::init_schema_store;
+use crate::restart::SafeRestart;
+use crate::sampling::{KafkaKeySource, KeySource};
+use crate::schemas::check::{self as schema_check, SAMPLE_SIZE};
+use crate::schemas::store::init_sample_store;
+use crate::search_cache::SearchCache;
+use crate::settings::{RuntimeSettings, Snapshot};
+use crate::shards::store::init_document_store;
+use crate::standby::coordinator::Coordinator;
+use crate::standby::lease::{init_lease_store, LeaseStore};
+use crate::standby::middleware::InternalToken;
+use crate::standby::sync::HttpCacheSource;
+use crate::standby::ServerRole;
+use crate::storage::collector::StorageCollector;
+use crate::subscriptions::deletion::DeletionSweep;
+use crate::subscriptions::store::{init_purge_log, init_subscription_store, SubscriptionStore};
+use crate::sweeper::Sweeper;
+use crate::tail::{KafkaMessageTail, MessageTail};
 use crate::BANNER;
+use crate::{
+    apply, assignment, auth, bundle, changefeed, clusters, collisions, commands, counters, debug,
+    drain, events, governance, health, history, id, lint, live, logs, lookup, metrics, mirrors,
+    produce, restart, sampling, schemas, search, search_cache, settings, shards, standby, storage,
+    subscriptions, sweeper, tail, warmup,
+};
+
+/// actix's default access log line, ending with the id the request was
+/// answered under, so it's found along with the request's own log lines.
+const ACCESS_LOG_FORMAT: &str =
+    r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{X-Request-Id}o"#;
 
 pub struct ServerConfig {
     pub log: logger::Level,
+    pub log_format: logger::Format,
+
+    /// Which backends the stores are kept in, and how to reach them.
+    pub stores: StoreConfig,
     pub host: String,
     pub port: u16,
+
+    /// Require an API key on every request.
+    pub auth: bool,
+
+    /// A bootstrap secret with unrestricted access, used to create the first keys.
+    pub admin_key: Option<String>,
+
+    /// Named secrets, as `name:token:role` with role `read` or `admin`. Any of
+    /// them requires a key or token on every API request, like `auth`.
+    pub api_tokens: Vec<String>,
+
+    /// The admin token reads send to reveal config secrets, which stay
+    /// redacted for everyone when unset.
+    pub reveal_token: Option<String>,
+
+    /// Origins browsers may call the API from, `*` for any. CORS is off without them.
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Let browsers send cookies and credentials from the allowed origins.
+    pub cors_allow_credentials: bool,
+
+    /// Days after which an owner that wasn't re-confirmed is flagged as stale.
+    pub ownership_stale_days: u32,
+
+    /// Time long-lived connections get to finish on shutdown before they're closed.
+    pub drain_grace_period: Duration,
+
+    /// Time shutdown gets to complete before the remaining tasks are aborted.
+    pub shutdown_timeout: Duration,
+
+    /// How many live message streams each cluster serves at once.
+    pub live_streams_per_cluster: usize,
+
+    /// The code the process exits with once a safe restart completed.
+    pub safe_restart_exit_code: i32,
+
+    /// Refuse to start when stored documents don't match the current schemas.
+    pub strict_schema: bool,
+    /// How many cluster metadata polls run at once.
+    pub metadata_poll_budget: usize,
+
+    /// How many of the latest log records are kept searchable.
+    pub log_buffer_size: usize,
+
+    /// Serve repeated searches from a cache until their subscription is written.
+    pub search_cache: bool,
+
+    /// The most searches and bytes of results the search cache keeps.
+    pub search_cache_entries: usize,
+    pub search_cache_bytes: usize,
+
+    /// Whether the instance polls Kafka and accepts writes, follows a primary, or is elected.
+    pub role: ServerRole,
+
+    /// The primary a standby follows.
+    pub primary_url: Option<String>,
+
+    /// Where other instances reach this one once it's elected, `http://host:port` by default.
+    pub advertise_url: Option<String>,
+
+    /// The secret instances authenticate to each other's internal API with.
+    pub internal_token: Option<String>,
+
+    /// Lint rules whose warnings reject a cluster or subscription instead.
+    pub deny_lints: Vec<String>,
+
+    /// Named redactions of cluster metadata, as `name=category,category`.
+    pub redaction_profiles: Vec<String>,
+
+    /// Patterns of topic name segments the `topics` redaction masks.
+    pub sensitive_topic_patterns: Vec<String>,
+
+    /// The settings above, along with where each of them came from.
+    pub settings: Snapshot,
 }
 
 pub struct ServerState {}
 
 pub async fn run(config: ServerConfig) -> std::io::Result<()> {
-    // Set the default log level
-    logger::init(&config.log);
+    let unmounted = api::routes::unmounted(&registry());
+    if !unmounted.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("endpoints never mounted: {}", unmounted.join(", ")),
+        ));
+    }
+
+    // Set the default log level, keeping the latest records searchable
+    let log_buffer = Arc::new(LogBuffer::new(config.log_buffer_size));
+    logger::init(&config.log, config.log_format, Some(log_buffer.clone()));
+    let log_buffer = Data::from(log_buffer);
 
     // Output seekr banner
     info!("{}", BANNER);
     info!("Starting server...");
+    info!("{}", config.settings.summary());
 
-    // Initialize server shared state
-    let clusters = init_cluster_store().await;
-    let subscriptions = init_subscription_store().await;
-    let metadata_service = Data::new(MetadataManager::new(clusters.clone()));
+    // Generate ids as the configured worker, before any store or request does
+    id::install(config.stores.ids).map_err(std::io::Error::other)?;
+    let settings = Data::new(RuntimeSettings::new(config.settings.clone()));
 
-    // Start Metadata service
-    metadata_service
+    // Initialize server shared state, tallying clusters and subscriptions as they change
+    let ms = &config.stores.meilisearch;
+    let counters = Arc::new(Counters::default());
+    let clusters = init_cluster_store(&config.stores)
+        .await
+        .map_err(std::io::Error::other)?;
+    // Clusters stored before their name key can't be found by name without it
+    match clusters.backfill_name_keys().await {
+        Ok(0) => {}
+        Ok(n) => info!("Backfilled the name key of {} clusters", n),
+        Err(e) => warn!("Failed to backfill the name keys of clusters - {}", e),
+    }
+    let clusters: Arc<dyn ClusterStore + Send + Sync> =
+        Arc::new(CountedClusterStore::new(clusters, counters.clone()));
+    let subscriptions = init_subscription_store(&config.stores)
+        .await
+        .map_err(std::io::Error::other)?;
+    let subscriptions: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(
+        CountedSubscriptionStore::new(subscriptions, counters.clone()),
+    );
+    let changefeed = init_changefeed_store(ms).await;
+    let debug = init_debug_store(ms).await;
+    let commands = init_command_store(ms).await;
+    let schemas = init_schema_store(ms).await;
+    let mirror_pairs = init_mirror_pair_store(ms).await;
+    let history = init_history_store(ms).await;
+    let documents = init_document_store(ms).await;
+    let purges = init_purge_log(ms).await;
+
+    // Verify stored documents still match the types that read them
+    let schema_report = schema_check::verify(init_sample_store(ms).await.as_ref(), SAMPLE_SIZE)
+        .await
+        .map_err(std::io::Error::other)?;
+    schema_check::enforce(&schema_report, config.strict_schema)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let schema_report = Data::new(schema_report);
+
+    let notifier: Arc<dyn Notifier + Send + Sync> = Arc::new(LogNotifier);
+    let producer: Arc<dyn MessageProducer + Send + Sync> =
+        Arc::new(KafkaMessageProducer::default());
+    let topic_admin: Arc<dyn TopicAdmin + Send + Sync> = Arc::new(KafkaAdminService::default());
+    let audit: Arc<dyn AuditLog + Send + Sync> = Arc::new(LogAuditLog);
+    let records: Arc<dyn RecordSource + Send + Sync> = Arc::new(KafkaRecordSource);
+    let keys: Arc<dyn KeySource + Send + Sync> = Arc::new(KafkaKeySource);
+    let tails: Arc<dyn MessageTail + Send + Sync> = Arc::new(KafkaMessageTail);
+    let drain = Data::new(Drain::new(config.drain_grace_period));
+    let live_streams = Data::new(LiveStreams::new(config.live_streams_per_cluster));
+    let bundler = Data::new(Bundler::default());
+    let safe_restart =
+        Data::new(SafeRestart::default().with_exit_code(config.safe_restart_exit_code));
+    let search_cache = config.search_cache.then(|| {
+        Data::new(SearchCache::new(
+            config.search_cache_entries,
+            config.search_cache_bytes,
+        ))
+    });
+    let ownership = Data::new(OwnershipPolicy::days(config.ownership_stale_days.into()));
+    let lints = LintPolicy::deny(&config.deny_lints)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let lints = Data::new(lints);
+    let redactions =
+        RedactionPolicy::new(&config.redaction_profiles, &config.sensitive_topic_patterns)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let redactions = Data::new(redactions);
+    let metadata_service = Data::new(
+        MetadataManager::new(clusters.clone())
+            .with_poll_budget(config.metadata_poll_budget)
+            .with_history(Arc::new(HistoryRecorder::new(history.clone(), notifier)))
+            .with_counters(counters.clone()),
+    );
+    let reconciler = Arc::new(Reconciler::new(
+        counters.clone(),
+        clusters.clone(),
+        subscriptions.clone(),
+        documents.clone(),
+        metadata_service.clone().into_inner(),
+    ));
+    if let Err(e) = reconciler.load().await {
+        warn!("Failed to count clusters and subscriptions - {}", e);
+    }
+    let counters = Data::from(counters);
+    let tokens = ApiToken::parse_all(&config.api_tokens)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let cors = CorsConfig::new(&config.cors_allowed_origins, config.cors_allow_credentials)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let authenticator = match config.auth || !tokens.is_empty() {
+        true => {
+            let keys = init_api_key_store(ms).await;
+            Some(Data::new(
+                Authenticator::new(keys, config.admin_key.as_deref()).with_tokens(tokens),
+            ))
+        }
+        false => {
+            warn!("Authentication is disabled, every request has unrestricted access");
+            None
+        }
+    };
+
+    // Start Metadata service, on the primary only
+    let url = match config.role {
+        ServerRole::Standby => config.primary_url.clone().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a standby needs the url of its primary",
+            )
+        })?,
+        _ => config
+            .advertise_url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}:{}", config.host, config.port)),
+    };
+    let leases: Arc<dyn LeaseStore + Send + Sync> = init_lease_store(ms).await;
+    let coordinator = Arc::new(Coordinator::new(
+        config.role,
+        url,
+        metadata_service.clone().into_inner(),
+        leases.clone(),
+        Arc::new(HttpCacheSource::new(config.internal_token.clone())),
+    ));
+    let availability = Data::from(coordinator.availability());
+    let coordinator_ = Data::from(coordinator.clone());
+    let internal_token = config
+        .internal_token
+        .as_deref()
+        .map(|t| Data::new(InternalToken::new(t)));
+    let reveal_token = config
+        .reveal_token
+        .as_deref()
+        .map(|t| Data::new(RevealToken::new(t)));
+    coordinator
         .clone()
-        .into_inner()
         .start()
         .await
         .expect("unable to start metadata service");
 
+    // Start Mirror monitor
+    let prober = Data::new(Prober::new(
+        ms.client(),
+        metadata_service.clone().into_inner(),
+    ));
+    let mirror_monitor = Data::new(MirrorMonitor::new(
+        mirror_pairs.clone(),
+        clusters.clone(),
+        metadata_service.clone().into_inner(),
+    ));
+    mirror_monitor.clone().into_inner().start().await;
+
+    // Start Sweeper, with the periodic cleanup jobs
+    let sweeper = Data::new(Sweeper::default());
+    let ownership_check = Arc::new(OwnershipCheck::new(
+        clusters.clone(),
+        subscriptions.clone(),
+        ownership.get_ref().clone(),
+    ));
+    sweeper.register(ownership_check.job());
+    let deletion_sweep = Arc::new(DeletionSweep::new(
+        subscriptions.clone(),
+        documents.clone(),
+        purges.clone(),
+    ));
+    sweeper.register(deletion_sweep.job());
+    sweeper.register(reconciler.job());
+    let storage_collector = Data::new(
+        StorageCollector::new(clusters.clone(), metadata_service.clone().into_inner())
+            .with_availability(availability.clone().into_inner()),
+    );
+    sweeper.register(storage_collector.clone().into_inner().job());
+    sweeper.clone().into_inner().start().await;
+
+    // Look for ids that collided before, in the background
+    let collision_scanner = Data::new(CollisionScanner::new(
+        clusters.clone(),
+        subscriptions.clone(),
+        mirror_pairs.clone(),
+    ));
+    collision_scanner.clone().into_inner().start();
+
     // Start Http server
     let metadata_service_ = metadata_service.clone();
+    let mirror_monitor_ = mirror_monitor.clone();
+    let drain_ = drain.clone();
+    let sweeper_ = sweeper.clone();
     let server = HttpServer::new(move || {
-        App::new()
-            .wrap(middleware::Logger::default())
+        let mut app = App::new();
+        if let Some(authenticator) = &authenticator {
+            app = app.app_data(authenticator.clone());
+        }
+        if let Some(internal_token) = &internal_token {
+            app = app.app_data(internal_token.clone());
+        }
+        if let Some(reveal_token) = &reveal_token {
+            app = app.app_data(reveal_token.clone());
+        }
+        if let Some(search_cache) = &search_cache {
+            app = app.app_data(search_cache.clone());
+        }
+
+        app.wrap(from_fn(metrics::track))
+            .wrap(middleware::Logger::new(ACCESS_LOG_FORMAT))
             .wrap(middleware::Compress::default())
             .app_data(Data::new(clusters.clone()))
             .app_data(Data::new(subscriptions.clone()))
+            .app_data(Data::new(changefeed.clone()))
+            .app_data(Data::new(debug.clone()))
+            .app_data(Data::new(commands.clone()))
+            .app_data(Data::new(schemas.clone()))
+            .app_data(Data::new(producer.clone()))
+            .app_data(Data::new(topic_admin.clone()))
+            .app_data(Data::new(audit.clone()))
+            .app_data(Data::new(mirror_pairs.clone()))
+            .app_data(Data::new(history.clone()))
+            .app_data(Data::new(documents.clone()))
+            .app_data(Data::new(purges.clone()))
+            .app_data(Data::new(records.clone()))
+            .app_data(Data::new(keys.clone()))
+            .app_data(Data::new(tails.clone()))
+            .app_data(Data::new(leases.clone()))
+            .app_data(ownership.clone())
+            .app_data(lints.clone())
+            .app_data(redactions.clone())
+            .app_data(drain_.clone())
+            .app_data(live_streams.clone())
+            .app_data(bundler.clone())
+            .app_data(safe_restart.clone())
+            .app_data(coordinator_.clone())
+            .app_data(schema_report.clone())
+            .app_data(settings.clone())
+            .app_data(availability.clone())
             .app_data(metadata_service_.clone())
+            .app_data(prober.clone())
+            .app_data(mirror_monitor_.clone())
+            .app_data(sweeper_.clone())
+            .app_data(counters.clone())
+            .app_data(log_buffer.clone())
+            .app_data(collision_scanner.clone())
+            .app_data(storage_collector.clone())
             .configure(routes)
+            // Outermost, so preflights are answered before any authentication.
+            .wrap(middleware::Condition::new(
+                cors.is_enabled(),
+                cors.middleware(),
+            ))
     })
-    .bind((config.host.clone(), config.port.clone()))?
+    .bind((config.host.clone(), config.port))?
     .disable_signals()
     .run();
 
@@ -60,17 +433,42 @@ pub async fn run(config: ServerConfig) -> std::io::Result<()> {
         server.await
     });
 
+    let manager = metadata_service.into_inner();
+    let shutdown_timeout = config.shutdown_timeout;
     let shutdown_task = tokio::spawn(async move {
         // Listen for ctrl-c
         tokio::signal::ctrl_c().await.unwrap();
         info!("Global shutdown has been initiated...");
 
-        // Start shutdown of tasks
-        metadata_service.clone().into_inner().stop().await;
-        debug!("Metadata service shutdown completed...");
+        // New connections are refused first, so no request reaches the tasks
+        // being stopped. Long-lived connections are drained alongside the
+        // in-flight requests the graceful stop waits for, so they don't hold it open.
+        let stop = server_handle.clone();
+        let teardown = async {
+            tokio::join!(drain.shutdown(), stop.stop(true));
+            debug!("HTTP server shutdown completed...");
 
-        server_handle.stop(true).await;
-        debug!("HTTP server shutdown completed...");
+            mirror_monitor.into_inner().stop().await;
+            debug!("Mirror monitor shutdown completed...");
+
+            sweeper.into_inner().stop().await;
+            debug!("Sweeper shutdown completed...");
+
+            coordinator.stop().await;
+            debug!("Metadata service shutdown completed...");
+        };
+
+        if tokio::time::timeout(shutdown_timeout, teardown)
+            .await
+            .is_err()
+        {
+            warn!(
+                "Shutdown didn't complete within {:?}, aborting the remaining tasks",
+                shutdown_timeout
+            );
+            server_handle.stop(false).await;
+            manager.stop_within(Duration::ZERO).await;
+        }
     });
 
     let _ = tokio::try_join!(server_task, shutdown_task).expect("unable to join tasks");
@@ -78,7 +476,55 @@ pub async fn run(config: ServerConfig) -> std::io::Result<()> {
     Ok(())
 }
 
-fn routes(config: &mut web::ServiceConfig) {
-    config.service(web::scope("api/v1/clusters").configure(configure_cluster));
-    config.service(web::scope("api/v1/subscriptions").configure(configure_subscription));
+/// Every group of routes the server mounts, in the order they're mounted.
+///
+/// Each `endpoints` module describes its own group; one missing here fails
+/// the server at startup, see `api::routes::unmounted`.
+pub(crate) fn registry() -> Vec<RouteGroup> {
+    #[allow(unused_mut)]
+    let mut groups = vec![
+        standby::endpoints::ROUTES,
+        clusters::endpoints::ROUTES,
+        produce::endpoints::ROUTES,
+        history::endpoints::ROUTES,
+        events::endpoints::ROUTES,
+        storage::endpoints::ROUTES,
+        sampling::endpoints::ROUTES,
+        tail::endpoints::ROUTES,
+        subscriptions::endpoints::ROUTES,
+        changefeed::endpoints::ROUTES,
+        live::endpoints::ROUTES,
+        search::endpoints::ROUTES,
+        id::endpoints::ROUTES,
+        debug::endpoints::ROUTES,
+        commands::endpoints::ROUTES,
+        shards::endpoints::ROUTES,
+        lookup::endpoints::ROUTES,
+        apply::endpoints::ROUTES,
+        lint::endpoints::ROUTES,
+        assignment::endpoints::ROUTES,
+        mirrors::endpoints::ROUTES,
+        auth::endpoints::ROUTES,
+        governance::endpoints::ROUTES,
+        schemas::endpoints::ROUTES,
+        counters::endpoints::ROUTES,
+        collisions::endpoints::ROUTES,
+        warmup::endpoints::ROUTES,
+        restart::endpoints::ROUTES,
+        drain::endpoints::ROUTES,
+        settings::endpoints::ROUTES,
+        sweeper::endpoints::ROUTES,
+        search_cache::endpoints::ROUTES,
+        logs::endpoints::ROUTES,
+        bundle::endpoints::ROUTES,
+    ];
+    #[cfg(feature = "chaos")]
+    groups.push(crate::failpoints::endpoints::ROUTES);
+    groups
+}
+
+pub(crate) fn routes(config: &mut web::ServiceConfig) {
+    config.configure(health::configure);
+    config.configure(metrics::configure);
+    api::routes::mount(config, &registry());
 }
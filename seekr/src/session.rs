@@ -3,18 +3,23 @@ use cdrs_tokio::cluster::{NodeTcpConfigBuilder, TcpConnectionManager};
 use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
 use cdrs_tokio::transport::TransportTcp;
 
+use crate::errors::AnyError;
+
 pub type CdrsSession = Session<
     TransportTcp,
     TcpConnectionManager,
     RoundRobinLoadBalancingStrategy<TransportTcp, TcpConnectionManager>,
 >;
 
-pub async fn create_session() -> CdrsSession {
+/// A session with the Cassandra node at `contact_point`, e.g. `localhost:9042`,
+/// checked to answer a query.
+pub async fn create_session(contact_point: &str) -> Result<CdrsSession, AnyError> {
     let config = NodeTcpConfigBuilder::new()
-        .with_contact_point("localhost:9042".into())
+        .with_contact_point(contact_point.into())
         .build()
-        .await
-        .unwrap();
+        .await?;
 
-    TcpSessionBuilder::new(RoundRobinLoadBalancingStrategy::new(), config).build()
+    let session = TcpSessionBuilder::new(RoundRobinLoadBalancingStrategy::new(), config).build();
+    session.query("SELECT now() FROM system.local;").await?;
+    Ok(session)
 }
@@ -0,0 +1,60 @@
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, HttpResponse};
+
+use crate::auth::{RequestAuth, Role};
+use crate::errors::ApiError;
+use crate::settings::RuntimeSettings;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_config);
+}
+
+#[get("/config")]
+async fn get_config(
+    auth: RequestAuth,
+    settings: Data<RuntimeSettings>,
+) -> Result<HttpResponse, ApiError> {
+    auth.require(Role::Admin)?;
+
+    Ok(HttpResponse::Ok().json(settings.current()))
+}
+
+#[actix_web::test]
+async fn it_serves_the_current_settings() {
+    use actix_web::{test, App};
+
+    use crate::settings::{SettingsBuilder, Source};
+
+    let settings = Data::new(RuntimeSettings::new(
+        SettingsBuilder::new("server")
+            .setting("port", 5000, Source::Default)
+            .secret("admin-key", Some("seekr_root"), Source::Flag)
+            .build(),
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(settings.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/debug/config")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["command"], "server");
+    assert_eq!(body["settings"][0]["value"], "5000");
+    assert_eq!(body["settings"][1]["value"], "[redacted]");
+    assert_eq!(body["settings"][1]["source"], "flag");
+
+    settings.reload(
+        SettingsBuilder::new("server")
+            .setting("port", 6000, Source::Env)
+            .build(),
+    );
+    let req = test::TestRequest::get()
+        .uri("/api/v1/debug/config")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["settings"][0]["value"], "6000");
+}
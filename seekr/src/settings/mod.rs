@@ -0,0 +1,194 @@
+use std::fmt;
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+pub mod endpoints;
+
+/// What secret values are replaced with wherever they'd be shown.
+pub const REDACTED: &str = "[redacted]";
+
+/// Redact a secret value, keeping whether one was set.
+pub fn redact<T>(value: Option<T>) -> Option<String> {
+    value.map(|_| REDACTED.to_string())
+}
+
+/// Where the effective value of a setting came from, in increasing precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Default,
+    Env,
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Env => write!(f, "env"),
+            Source::Flag => write!(f, "flag"),
+        }
+    }
+}
+
+/// The effective value of a single setting.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Setting {
+    /// The name of the setting's flag, e.g. `admin-key`.
+    pub name: &'static str,
+
+    /// The value, or `None` when unset.
+    pub value: Option<String>,
+    pub source: Source,
+    pub secret: bool,
+}
+
+/// The settings a process runs with.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Snapshot {
+    /// The command the settings are of, e.g. `server`.
+    pub command: &'static str,
+
+    /// Point in time in UTC, when the settings were taken.
+    pub taken_at: DateTime<Utc>,
+    pub settings: Vec<Setting>,
+}
+
+impl Snapshot {
+    pub fn get(&self, name: &str) -> Option<&Setting> {
+        self.settings.iter().find(|s| s.name == name)
+    }
+
+    /// A one-line summary of the settings that aren't defaults.
+    pub fn summary(&self) -> String {
+        let changed = self
+            .settings
+            .iter()
+            .filter(|s| s.source != Source::Default)
+            .map(|s| {
+                let value = s.value.as_deref().unwrap_or("<unset>");
+                format!("{}={} ({})", s.name, value, s.source)
+            })
+            .collect::<Vec<_>>();
+
+        match changed.is_empty() {
+            true => format!("Running {} with default settings", self.command),
+            false => format!("Running {} with {}", self.command, changed.join(", ")),
+        }
+    }
+}
+
+/// Records each setting along with its source as a configuration is built.
+pub struct SettingsBuilder {
+    command: &'static str,
+    settings: Vec<Setting>,
+}
+
+impl SettingsBuilder {
+    pub fn new(command: &'static str) -> Self {
+        Self {
+            command,
+            settings: vec![],
+        }
+    }
+
+    pub fn setting(mut self, name: &'static str, value: impl ToString, source: Source) -> Self {
+        self.settings.push(Setting {
+            name,
+            value: Some(value.to_string()),
+            source,
+            secret: false,
+        });
+        self
+    }
+
+    /// Record a secret setting, whose value is redacted right away.
+    pub fn secret<T>(mut self, name: &'static str, value: Option<T>, source: Source) -> Self {
+        self.settings.push(Setting {
+            name,
+            value: redact(value),
+            source,
+            secret: true,
+        });
+        self
+    }
+
+    pub fn build(self) -> Snapshot {
+        Snapshot {
+            command: self.command,
+            taken_at: Utc::now(),
+            settings: self.settings,
+        }
+    }
+}
+
+/// The settings the running process uses, replaced whenever they're reloaded.
+pub struct RuntimeSettings {
+    current: RwLock<Snapshot>,
+}
+
+impl RuntimeSettings {
+    pub fn new(snapshot: Snapshot) -> Self {
+        Self {
+            current: RwLock::new(snapshot),
+        }
+    }
+
+    pub fn current(&self) -> Snapshot {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replace the settings after they were reloaded, logging the new summary.
+    pub fn reload(&self, snapshot: Snapshot) {
+        info!("{}", snapshot.summary());
+        *self.current.write().unwrap() = snapshot;
+    }
+}
+
+#[test]
+fn it_redacts_secrets_and_summarizes_changed_settings() {
+    let snapshot = SettingsBuilder::new("server")
+        .setting("host", "localhost", Source::Default)
+        .setting("port", 8080, Source::Flag)
+        .secret("admin-key", Some("seekr_root"), Source::Env)
+        .secret::<&str>("tls_key", None, Source::Default)
+        .build();
+
+    let admin_key = snapshot.get("admin-key").unwrap();
+    assert_eq!(admin_key.value.as_deref(), Some(REDACTED));
+    assert!(admin_key.secret);
+    assert_eq!(snapshot.get("tls_key").unwrap().value, None);
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert!(!json.contains("seekr_root"));
+
+    assert_eq!(
+        snapshot.summary(),
+        "Running server with port=8080 (flag), admin-key=[redacted] (env)"
+    );
+    let defaults = SettingsBuilder::new("indexer")
+        .setting("log", "info", Source::Default)
+        .build();
+    assert_eq!(defaults.summary(), "Running indexer with default settings");
+}
+
+#[test]
+fn it_replaces_the_snapshot_on_reload() {
+    let settings = RuntimeSettings::new(
+        SettingsBuilder::new("server")
+            .setting("log", "info", Source::Default)
+            .build(),
+    );
+
+    settings.reload(
+        SettingsBuilder::new("server")
+            .setting("log", "debug", Source::Env)
+            .build(),
+    );
+
+    let log = settings.current().get("log").cloned().unwrap();
+    assert_eq!(log.value.as_deref(), Some("debug"));
+    assert_eq!(log.source, Source::Env);
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use actix_web::http::header::ContentType;
+use actix_web::web::{Data, Json, Path, Query, ServiceConfig};
+use actix_web::{get, put, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::search_cache::SearchCache;
+use crate::shards::period::ShardPeriod;
+use crate::shards::search::{self, SearchQuery};
+use crate::shards::shard::{IndexSettings, Shard};
+use crate::shards::store::DocumentStore;
+use crate::shards::update_settings;
+use crate::subscriptions::store::SubscriptionStore;
+
+/// Default number of hits returned per page.
+const DEFAULT_LIMIT: usize = 20;
+
+/// Maximum number of hits returned per page.
+const MAX_LIMIT: usize = 1_000;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(search_documents)
+        .service(get_shards)
+        .service(put_settings);
+}
+
+#[get("/{cluster_id}/{id}/search")]
+async fn search_documents(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<SearchParams>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    store: Data<Arc<dyn DocumentStore + Send + Sync>>,
+    cache: Option<Data<SearchCache>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Searching documents of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    match ss.get(cluster_id, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let params = query.into_inner();
+    let query = SearchQuery {
+        q: params.q.filter(|q| !q.is_empty()),
+        from: params.from,
+        to: params.to,
+        offset: params.offset.unwrap_or_default(),
+        limit: params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+
+    let store = store.get_ref().as_ref();
+    let page = match cache.filter(|_| params.cache.unwrap_or(true)) {
+        Some(cache) => cache.search(store, id, &query).await,
+        None => search::search(store, id, &query)
+            .await
+            .and_then(|page| Ok(serde_json::to_string(&page)?)),
+    };
+
+    match page {
+        Ok(page) => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(page),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[get("/{cluster_id}/{id}/shards")]
+async fn get_shards(
+    path: Path<(ClusterId, SubscriptionId)>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    store: Data<Arc<dyn DocumentStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Fetching shards of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    let subscription = match ss.get(cluster_id, id).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    match store.shards(id).await {
+        Ok(shards) => HttpResponse::Ok().json(ShardsResponse {
+            period: ShardPeriod::from(&subscription),
+            shards,
+        }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[put("/{cluster_id}/{id}/settings")]
+async fn put_settings(
+    path: Path<(ClusterId, SubscriptionId)>,
+    settings: Json<IndexSettings>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    store: Data<Arc<dyn DocumentStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Updating index settings of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    match ss.get(cluster_id, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    let settings = settings.into_inner();
+    match update_settings(store.get_ref().as_ref(), id, &settings).await {
+        Ok(shards) => HttpResponse::Ok().json(SettingsResponse { settings, shards }),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    /// Whether the page may be served from, and kept in, the search cache.
+    cache: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ShardsResponse {
+    period: Option<ShardPeriod>,
+    shards: Vec<Shard>,
+}
+
+#[derive(Serialize)]
+struct SettingsResponse {
+    settings: IndexSettings,
+    /// Number of live shards the settings were applied to.
+    shards: usize,
+}
+
+#[actix_web::test]
+async fn it_serves_repeated_searches_from_the_cache() {
+    use actix_web::{test, App};
+
+    use crate::shards::router::{router, ts};
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::Subscription;
+
+    let ss = Arc::new(MemorySubscriptionStore::default());
+    let subscription = Subscription::new(
+        Some(SubscriptionId(1)),
+        ClusterId(1),
+        "orders".to_string(),
+        Default::default(),
+    );
+    ss.put(subscription).await.unwrap();
+    let (store, mut router) = router(None);
+    router
+        .route(vec![("0-1", ts("2024-05-01T00:00:00Z"))])
+        .await;
+
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = ss;
+    let store: Arc<dyn DocumentStore + Send + Sync> = store;
+    let cache = Data::new(SearchCache::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(ss))
+            .app_data(Data::new(store))
+            .app_data(cache.clone())
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    // The same query, spelled with its parameters reordered or its defaults filled in.
+    for uri in [
+        "/api/v1/subscriptions/1/1/search?q=0-1&limit=5",
+        "/api/v1/subscriptions/1/1/search?limit=5&q=0-1",
+        "/api/v1/subscriptions/1/1/search?offset=0&limit=5&q=0-1&cache=true",
+        "/api/v1/subscriptions/1/1/search?q=0-1&limit=5&cache=false",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page["estimated_total_hits"], 1, "{}", uri);
+        assert_eq!(page["limit"], 5);
+    }
+
+    // Searches opting out neither read nor fill the cache.
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 1, 1));
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::kafka::config;
+use crate::kafka::streams::StreamsMessage;
+
+use self::shard::IndexSettings;
+use self::store::DocumentStore;
+
+pub mod endpoints;
+pub mod period;
+pub mod router;
+pub mod search;
+pub mod shard;
+pub mod store;
+pub mod tombstone;
+
+/// The primary key of indexed documents, derived from their Kafka coordinates or key.
+pub const PRIMARY_KEY: &str = "_seekr_id";
+
+/// Point in time in UTC Epoch milliseconds, when the document's message was produced.
+pub const EVENT_TS: &str = "_seekr_event_ts";
+
+/// The partition of the document's message.
+pub const PARTITION: &str = "_seekr_partition";
+
+/// The offset of the document's message.
+pub const OFFSET: &str = "_seekr_offset";
+
+/// How the documents of a subscription are identified, set by `document.id`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DocumentIds {
+    /// Every message is its own document, `{partition}-{offset}`.
+    #[default]
+    Offset,
+
+    /// Messages with the same key replace each other's document, as in a
+    /// compacted topic. Messages without a key fall back to their offset.
+    Key,
+}
+
+impl FromStr for DocumentIds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "offset" => Ok(DocumentIds::Offset),
+            "key" => Ok(DocumentIds::Key),
+            _ => Err(format!(
+                "unknown document id '{}', expected offset or key",
+                s
+            )),
+        }
+    }
+}
+
+impl DocumentIds {
+    /// The document ids configured by `document.id`, offsets when unset.
+    pub fn of(config: &HashMap<String, String>) -> Result<Self, String> {
+        config
+            .get(config::DOCUMENT_ID)
+            .map_or(Ok(DocumentIds::Offset), |v| v.parse())
+    }
+
+    /// The id of the message's document.
+    ///
+    /// Keys are hashed, since Meilisearch only accepts alphanumeric ids.
+    pub fn id(&self, message: &StreamsMessage) -> String {
+        match (self, &message.key) {
+            (DocumentIds::Key, Some(key)) => format!("k-{:x}", Sha256::digest(key.as_bytes())),
+            _ => format!("{}-{}", message.partition, message.offset),
+        }
+    }
+}
+
+/// The document indexed for a message, or `None` for tombstones.
+///
+/// JSON objects are indexed as is, any other payload is wrapped in a `value`
+/// field, as text unless it was parsed as JSON.
+pub fn document(message: &StreamsMessage, ids: DocumentIds) -> Option<Value> {
+    let payload = message.payload.as_deref()?;
+
+    let mut document = match &message.payload_json {
+        Some(Value::Object(o)) => o.clone(),
+        Some(v) => Map::from_iter([("value".to_string(), v.clone())]),
+        None => Map::from_iter([("value".to_string(), Value::from(payload))]),
+    };
+
+    let id = ids.id(message);
+    let event_ts = message
+        .timestamp
+        .unwrap_or_else(|| Utc::now().timestamp_millis());
+    document.insert(PRIMARY_KEY.to_string(), Value::from(id));
+    document.insert(EVENT_TS.to_string(), Value::from(event_ts));
+    document.insert(PARTITION.to_string(), Value::from(message.partition));
+    document.insert(OFFSET.to_string(), Value::from(message.offset));
+
+    Some(Value::Object(document))
+}
+
+/// The event timestamp of an indexed document.
+pub fn event_ts(document: &Value) -> i64 {
+    document[EVENT_TS].as_i64().unwrap_or_default()
+}
+
+/// Update the settings template of a subscription and apply it to its live
+/// shards, returning the number of shards updated.
+pub async fn update_settings(
+    store: &(dyn DocumentStore + Send + Sync),
+    id: SubscriptionId,
+    settings: &IndexSettings,
+) -> Result<usize, AnyError> {
+    // Store the template first, so shards opened meanwhile pick it up.
+    store.put_settings(id, settings).await?;
+
+    let shards = store.shards(id).await?;
+    for shard in &shards {
+        store.apply_settings(shard, settings).await?;
+    }
+
+    Ok(shards.len())
+}
+
+#[test]
+fn it_builds_documents_from_messages() {
+    use crate::kafka::streams::{message, PayloadFormat};
+
+    let mut message = message(Some("{\"order\":7}")).with_format(PayloadFormat::Json);
+
+    let doc = document(&message, DocumentIds::Offset).unwrap();
+    assert_eq!(doc["order"], 7);
+    assert_eq!(doc[PRIMARY_KEY], "2-41");
+    assert_eq!(event_ts(&doc), 1_700_000_000_000);
+    assert_eq!(doc[PARTITION], 2);
+    assert_eq!(doc[OFFSET], 41);
+
+    message.payload = Some("not json".to_string());
+    message = message.with_format(PayloadFormat::Json);
+    assert_eq!(
+        document(&message, DocumentIds::Offset).unwrap()["value"],
+        "not json"
+    );
+
+    // Text payloads aren't parsed, even when they're JSON.
+    message.payload = Some("7".to_string());
+    let text = message.clone().with_format(PayloadFormat::Text);
+    assert_eq!(document(&text, DocumentIds::Offset).unwrap()["value"], "7");
+    let json = message.clone().with_format(PayloadFormat::Json);
+    assert_eq!(document(&json, DocumentIds::Offset).unwrap()["value"], 7);
+
+    // Keyed documents share an id, unkeyed ones keep their offset.
+    assert_eq!(
+        document(&message, DocumentIds::Key).unwrap()[PRIMARY_KEY],
+        "2-41"
+    );
+    message.key = Some("order-7".to_string());
+    let id = DocumentIds::Key.id(&message);
+    assert!(id.starts_with("k-"));
+    message.offset = 42;
+    assert_eq!(
+        document(&message, DocumentIds::Key).unwrap()[PRIMARY_KEY],
+        id
+    );
+
+    message.payload = None;
+    assert_eq!(document(&message, DocumentIds::Key), None);
+}
+
+#[tokio::test]
+async fn it_applies_settings_to_live_and_future_shards() {
+    use self::router::{router, ts};
+
+    let (store, mut router) = router(Some(period::ShardPeriod::Monthly));
+    router
+        .route(vec![("0-1", ts("2024-04-03T00:00:00Z"))])
+        .await;
+
+    let settings = IndexSettings {
+        filterable_attributes: Some(vec!["customer".to_string()]),
+        ..Default::default()
+    };
+    let updated = update_settings(store.as_ref(), SubscriptionId(1), &settings)
+        .await
+        .unwrap();
+    assert_eq!(updated, 1);
+
+    router
+        .route(vec![("0-2", ts("2024-05-03T00:00:00Z"))])
+        .await;
+
+    let indexes = store.indexes.read().await;
+    assert_eq!(indexes["sub_1_2024_04"].0, settings);
+    assert_eq!(indexes["sub_1_2024_05"].0, settings);
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::kafka::config;
+use crate::subscriptions::subscription::Subscription;
+
+/// The time span covered by each shard of a sharded subscription.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardPeriod {
+    Weekly,
+    Monthly,
+}
+
+impl FromStr for ShardPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "weekly" => Ok(ShardPeriod::Weekly),
+            "monthly" => Ok(ShardPeriod::Monthly),
+            _ => Err(format!("unknown shard period '{}'", s)),
+        }
+    }
+}
+
+impl ShardPeriod {
+    /// The shard period configured by `index.shard.period`, if the subscription is sharded.
+    pub fn from(subscription: &Subscription) -> Option<Self> {
+        let value = subscription.config.get(config::INDEX_SHARD_PERIOD)?;
+        match value.parse() {
+            Ok(period) => Some(period),
+            Err(e) => {
+                warn!(
+                    "Ignoring {} of subscription {}: {}",
+                    config::INDEX_SHARD_PERIOD,
+                    subscription.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// The key and `[start, end)` range, in UTC Epoch milliseconds, of the
+    /// period holding the given timestamp.
+    pub fn bucket(&self, ts: i64) -> (String, i64, i64) {
+        let date = DateTime::from_timestamp_millis(ts)
+            .unwrap_or_default()
+            .date_naive();
+
+        let (key, start, end) = match self {
+            ShardPeriod::Monthly => {
+                let start = NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap();
+                let end = match date.month() {
+                    12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1).unwrap(),
+                    m => NaiveDate::from_ymd_opt(date.year(), m + 1, 1).unwrap(),
+                };
+                let key = format!("{:04}_{:02}", date.year(), date.month());
+                (key, start, end)
+            }
+            ShardPeriod::Weekly => {
+                let week = date.iso_week();
+                let start =
+                    NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon).unwrap();
+                let key = format!("{:04}_w{:02}", week.year(), week.week());
+                (key, start, start + Duration::days(7))
+            }
+        };
+
+        let millis = |d: NaiveDate| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp_millis();
+        (key, millis(start), millis(end))
+    }
+}
+
+#[cfg(test)]
+fn ts(s: &str) -> i64 {
+    s.parse::<DateTime<chrono::Utc>>()
+        .unwrap()
+        .timestamp_millis()
+}
+
+#[test]
+fn it_buckets_timestamps_into_periods() {
+    let (key, start, end) = ShardPeriod::Monthly.bucket(ts("2024-05-17T08:30:00Z"));
+    assert_eq!(key, "2024_05");
+    assert_eq!(start, ts("2024-05-01T00:00:00Z"));
+    assert_eq!(end, ts("2024-06-01T00:00:00Z"));
+
+    let (key, _, end) = ShardPeriod::Monthly.bucket(ts("2023-12-31T23:59:59Z"));
+    assert_eq!(key, "2023_12");
+    assert_eq!(end, ts("2024-01-01T00:00:00Z"));
+
+    // ISO weeks start on Monday, and may belong to the previous year.
+    let (key, start, end) = ShardPeriod::Weekly.bucket(ts("2021-01-02T12:00:00Z"));
+    assert_eq!(key, "2020_w53");
+    assert_eq!(start, ts("2020-12-28T00:00:00Z"));
+    assert_eq!(end, ts("2021-01-04T00:00:00Z"));
+}
@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::subscriptions::subscription::Subscription;
+
+use super::event_ts;
+use super::period::ShardPeriod;
+use super::shard::Shard;
+use super::store::DocumentStore;
+
+/// A write to the documents of a subscription.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Write {
+    /// Insert or replace the document with the same primary key.
+    Upsert(Value),
+
+    /// Delete the document with the given primary key.
+    Delete(String),
+}
+
+/// Routes the documents of a subscription into the shards covering their event time.
+///
+/// Shards are created the first time a document falls into their range, so
+/// documents produced long ago lazily create old shards.
+pub struct ShardRouter {
+    store: Arc<dyn DocumentStore + Send + Sync>,
+    id: SubscriptionId,
+    period: Option<ShardPeriod>,
+    /// The shards opened by this router, by id.
+    shards: HashMap<String, Shard>,
+}
+
+impl ShardRouter {
+    pub fn new(store: Arc<dyn DocumentStore + Send + Sync>, subscription: &Subscription) -> Self {
+        Self {
+            store,
+            id: subscription.id,
+            period: ShardPeriod::from(subscription),
+            shards: HashMap::new(),
+        }
+    }
+
+    pub fn is_sharded(&self) -> bool {
+        self.period.is_some()
+    }
+
+    /// Index the documents into their shards, creating the missing ones.
+    pub async fn index(&mut self, documents: Vec<Value>) -> Result<(), AnyError> {
+        let mut batches: BTreeMap<String, (Shard, Vec<Value>)> = BTreeMap::new();
+        for d in documents {
+            let shard = Shard::route(self.id, self.period, event_ts(&d));
+            batches
+                .entry(shard.id.clone())
+                .or_insert((shard, vec![]))
+                .1
+                .push(d);
+        }
+
+        for (id, (shard, documents)) in batches {
+            if !self.shards.contains_key(&id) {
+                self.open(shard).await?;
+            }
+            fail_point!(crate::failpoints::MEILISEARCH_SUBMIT, self.id)?;
+            self.store
+                .add_documents(&self.shards[&id], &documents)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply the writes in order, batching consecutive writes of the same kind.
+    pub async fn apply(&mut self, writes: Vec<Write>) -> Result<(), AnyError> {
+        let mut writes = writes.into_iter().peekable();
+        while let Some(write) = writes.next() {
+            match write {
+                Write::Upsert(d) => {
+                    let mut documents = vec![d];
+                    while let Some(Write::Upsert(d)) =
+                        writes.next_if(|w| matches!(w, Write::Upsert(_)))
+                    {
+                        documents.push(d);
+                    }
+                    self.index(documents).await?;
+                }
+                Write::Delete(id) => {
+                    let mut ids = vec![id];
+                    while let Some(Write::Delete(id)) =
+                        writes.next_if(|w| matches!(w, Write::Delete(_)))
+                    {
+                        ids.push(id);
+                    }
+                    self.delete(&ids).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the documents from every shard of the subscription, since
+    /// a document's earlier versions may sit in the shards of their own event time.
+    pub async fn delete(&mut self, ids: &[String]) -> Result<(), AnyError> {
+        for shard in self.store.shards(self.id).await? {
+            fail_point!(crate::failpoints::MEILISEARCH_SUBMIT, self.id)?;
+            self.store.delete_documents(&shard, ids).await?;
+        }
+        Ok(())
+    }
+
+    /// Create the shard's index and register it in the manifest.
+    ///
+    /// Both steps are idempotent and the manifest entry only holds values
+    /// derived from the shard's range and index, so concurrent indexers
+    /// opening the same shard converge on a single entry.
+    async fn open(&mut self, mut shard: Shard) -> Result<(), AnyError> {
+        let settings = self.store.settings(self.id).await?;
+        self.store.create_index(&shard, &settings).await?;
+
+        shard.documents = self.store.count(&shard).await?;
+        self.store.put_shard(&shard).await?;
+
+        info!(
+            "Opened shard {} for subscription {}",
+            shard.id, shard.subscription_id
+        );
+        self.shards.insert(shard.id.clone(), shard);
+        Ok(())
+    }
+
+    /// Refresh the document counts of the shards opened by this router.
+    pub async fn refresh(&mut self) -> Result<(), AnyError> {
+        for shard in self.shards.values_mut() {
+            let documents = self.store.count(shard).await?;
+            if documents != shard.documents {
+                shard.documents = documents;
+                self.store.put_shard(shard).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop every shard whose range ended before the cutoff, returning the number dropped.
+    pub async fn expire(&mut self, cutoff: i64) -> Result<usize, AnyError> {
+        let expired = self
+            .store
+            .shards(self.id)
+            .await?
+            .into_iter()
+            .filter(|s| s.is_expired(cutoff))
+            .collect::<Vec<_>>();
+
+        for shard in &expired {
+            self.store.remove_shard(shard).await?;
+            self.shards.remove(&shard.id);
+        }
+
+        Ok(expired.len())
+    }
+}
+
+#[cfg(test)]
+pub fn ts(s: &str) -> i64 {
+    s.parse::<chrono::DateTime<chrono::Utc>>()
+        .unwrap()
+        .timestamp_millis()
+}
+
+/// A router of subscription 1 over an in-memory store.
+#[cfg(test)]
+pub fn router(period: Option<ShardPeriod>) -> (Arc<super::store::MemoryDocumentStore>, TestRouter) {
+    let store = Arc::new(super::store::MemoryDocumentStore::default());
+    (store.clone(), TestRouter::new(store, period))
+}
+
+#[cfg(test)]
+pub struct TestRouter(pub ShardRouter);
+
+#[cfg(test)]
+impl TestRouter {
+    pub fn new(store: Arc<super::store::MemoryDocumentStore>, period: Option<ShardPeriod>) -> Self {
+        let mut config = HashMap::new();
+        if let Some(p) = period {
+            let value = serde_json::to_value(p).unwrap();
+            config.insert(
+                crate::kafka::config::INDEX_SHARD_PERIOD.to_string(),
+                value.as_str().unwrap().to_string(),
+            );
+        }
+        let subscription = Subscription::new(
+            Some(SubscriptionId(1)),
+            crate::ids::ClusterId(1),
+            "orders".to_string(),
+            config,
+        );
+        TestRouter(ShardRouter::new(store, &subscription))
+    }
+
+    /// Index documents with the given ids and event timestamps.
+    pub async fn route(&mut self, documents: Vec<(&str, i64)>) {
+        let documents = documents
+            .into_iter()
+            .map(|(id, ts)| serde_json::json!({ super::PRIMARY_KEY: id, super::EVENT_TS: ts }))
+            .collect();
+        self.0.index(documents).await.unwrap();
+    }
+}
+
+#[cfg(test)]
+fn upsert(id: &str, ts: i64) -> Write {
+    Write::Upsert(serde_json::json!({ super::PRIMARY_KEY: id, super::EVENT_TS: ts }))
+}
+
+#[cfg(test)]
+fn manifest(shards: &[Shard]) -> Vec<(&str, usize)> {
+    shards
+        .iter()
+        .map(|s| (s.id.as_str(), s.documents))
+        .collect()
+}
+
+#[tokio::test]
+async fn it_lazily_creates_old_shards() {
+    let (store, mut router) = router(Some(ShardPeriod::Monthly));
+
+    router
+        .route(vec![("0-1", ts("2024-05-03T00:00:00Z"))])
+        .await;
+    // A document produced years ago lands in its own, new shard.
+    router
+        .route(vec![("0-2", ts("2019-02-11T00:00:00Z"))])
+        .await;
+    router
+        .route(vec![("0-3", ts("2024-05-09T00:00:00Z"))])
+        .await;
+    router.0.refresh().await.unwrap();
+
+    let shards = store.shards(SubscriptionId(1)).await.unwrap();
+    assert_eq!(
+        manifest(&shards),
+        vec![("sub_1_2019_02", 1), ("sub_1_2024_05", 2)]
+    );
+    assert_eq!(shards[0].start_ms, ts("2019-02-01T00:00:00Z"));
+    assert_eq!(shards[0].end_ms, ts("2019-03-01T00:00:00Z"));
+}
+
+#[tokio::test]
+async fn it_drops_whole_shards_past_retention() {
+    let (store, mut router) = router(Some(ShardPeriod::Monthly));
+    router
+        .route(vec![
+            ("0-1", ts("2024-03-31T23:00:00Z")),
+            ("0-2", ts("2024-04-15T00:00:00Z")),
+            ("0-3", ts("2024-05-02T00:00:00Z")),
+        ])
+        .await;
+
+    // April still holds documents newer than the cutoff, so only March goes.
+    let dropped = router.0.expire(ts("2024-04-20T00:00:00Z")).await.unwrap();
+    assert_eq!(dropped, 1);
+
+    router.0.refresh().await.unwrap();
+    let shards = store.shards(SubscriptionId(1)).await.unwrap();
+    assert_eq!(
+        manifest(&shards),
+        vec![("sub_1_2024_04", 1), ("sub_1_2024_05", 1)]
+    );
+    assert!(!store.indexes.read().await.contains_key("sub_1_2024_03"));
+
+    // Unsharded subscriptions never drop their single index.
+    let (store, mut router) = self::router(None);
+    router.route(vec![("0-1", 0)]).await;
+    assert_eq!(router.0.expire(i64::MAX - 1).await.unwrap(), 0);
+    assert_eq!(store.shards(SubscriptionId(1)).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn it_keeps_the_manifest_consistent_across_racing_indexers() {
+    let store = Arc::new(super::store::MemoryDocumentStore::default());
+
+    // An indexer restarting while its predecessor is still running, both
+    // opening the same shard at once.
+    let mut first = TestRouter::new(store.clone(), Some(ShardPeriod::Weekly));
+    let mut second = TestRouter::new(store.clone(), Some(ShardPeriod::Weekly));
+    tokio::join!(
+        first.route(vec![("0-1", ts("2024-05-06T00:00:00Z"))]),
+        second.route(vec![("1-1", ts("2024-05-07T00:00:00Z"))]),
+    );
+    tokio::join!(
+        first.route(vec![("0-2", ts("2024-05-08T00:00:00Z"))]),
+        second.route(vec![("1-2", ts("2024-05-12T23:59:59Z"))]),
+    );
+    first.0.refresh().await.unwrap();
+    second.0.refresh().await.unwrap();
+
+    let shards = store.shards(SubscriptionId(1)).await.unwrap();
+    assert_eq!(manifest(&shards), vec![("sub_1_2024_w19", 4)]);
+    assert_eq!(store.count(&shards[0]).await.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn it_applies_deletes_and_upserts_in_order() {
+    let (store, mut router) = router(Some(ShardPeriod::Monthly));
+    router
+        .0
+        .apply(vec![
+            upsert("k-1", ts("2024-04-03T00:00:00Z")),
+            upsert("k-2", ts("2024-04-04T00:00:00Z")),
+        ])
+        .await
+        .unwrap();
+
+    // A delete reaches the versions in older shards, and a document
+    // re-created after its delete in the same batch ends up present.
+    router
+        .0
+        .apply(vec![
+            Write::Delete("k-1".to_string()),
+            upsert("k-1", ts("2024-05-03T00:00:00Z")),
+            Write::Delete("k-2".to_string()),
+            Write::Delete("k-3".to_string()),
+        ])
+        .await
+        .unwrap();
+    router.0.refresh().await.unwrap();
+
+    let shards = store.shards(SubscriptionId(1)).await.unwrap();
+    assert_eq!(
+        manifest(&shards),
+        vec![("sub_1_2024_04", 0), ("sub_1_2024_05", 1)]
+    );
+    let indexes = store.indexes.read().await;
+    assert_eq!(indexes["sub_1_2024_05"].1[0][super::PRIMARY_KEY], "k-1");
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test]
+async fn it_retries_documents_rejected_by_meilisearch() {
+    use crate::failpoints::{self, Failpoint, Mode};
+
+    let store = Arc::new(super::store::MemoryDocumentStore::default());
+    let subscription = Subscription::new(
+        Some(SubscriptionId(952)),
+        crate::ids::ClusterId(1),
+        "orders".to_string(),
+        HashMap::from([(
+            crate::kafka::config::INDEX_SHARD_PERIOD.to_string(),
+            "monthly".to_string(),
+        )]),
+    );
+    let mut router = ShardRouter::new(store.clone(), &subscription);
+    let document = serde_json::json!({
+        super::PRIMARY_KEY: "0-1",
+        super::EVENT_TS: ts("2024-05-03T00:00:00Z"),
+    });
+
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::MEILISEARCH_SUBMIT, 952),
+        mode: Mode::Error,
+        count: Some(1),
+    });
+    assert!(router.index(vec![document.clone()]).await.is_err());
+
+    // The shard opened before the failure is reused by the retry.
+    router.index(vec![document]).await.unwrap();
+    router.refresh().await.unwrap();
+
+    let shards = store.shards(SubscriptionId(952)).await.unwrap();
+    assert_eq!(manifest(&shards), vec![("sub_952_2024_05", 1)]);
+}
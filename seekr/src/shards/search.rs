@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+
+use futures::future::try_join_all;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+
+use super::store::{DocumentStore, ShardQuery};
+use super::{event_ts, PRIMARY_KEY};
+
+/// A search across every shard of a subscription.
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    pub q: Option<String>,
+    /// Only match documents produced at or after this point in time, in UTC Epoch milliseconds.
+    pub from: Option<i64>,
+    /// Only match documents produced at or before this point in time, in UTC Epoch milliseconds.
+    pub to: Option<i64>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    pub hits: Vec<Value>,
+    pub offset: usize,
+    pub limit: usize,
+    pub estimated_total_hits: usize,
+    /// The shards the query was fanned out to.
+    pub shards: Vec<String>,
+}
+
+/// Orders documents by event time, newest first, then by document id.
+pub fn newest_first(a: &Value, b: &Value) -> Ordering {
+    event_ts(b)
+        .cmp(&event_ts(a))
+        .then_with(|| a[PRIMARY_KEY].as_str().cmp(&b[PRIMARY_KEY].as_str()))
+}
+
+/// Search the shards overlapping the query's time range and merge their hits.
+///
+/// Every shard returns its first `offset + limit` hits in the same order the
+/// merge uses, so the merged page is exactly the page a single index would return.
+pub async fn search(
+    store: &(dyn DocumentStore + Send + Sync),
+    id: SubscriptionId,
+    query: &SearchQuery,
+) -> Result<SearchPage, AnyError> {
+    let shards = store
+        .shards(id)
+        .await?
+        .into_iter()
+        .filter(|s| s.overlaps(query.from, query.to))
+        .collect::<Vec<_>>();
+
+    let per_shard = ShardQuery {
+        q: query.q.clone(),
+        from: query.from,
+        to: query.to,
+        at: None,
+        limit: query.offset + query.limit,
+    };
+    let results = try_join_all(shards.iter().map(|s| store.search(s, &per_shard))).await?;
+
+    let estimated_total_hits = results.iter().map(|r| r.total).sum();
+    let mut hits = results.into_iter().flat_map(|r| r.hits).collect::<Vec<_>>();
+    hits.sort_by(newest_first);
+
+    Ok(SearchPage {
+        hits: hits
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .collect(),
+        offset: query.offset,
+        limit: query.limit,
+        estimated_total_hits,
+        shards: shards.into_iter().map(|s| s.id).collect(),
+    })
+}
+
+/// The document indexed for the message at the given partition and offset,
+/// looked up in every shard since the message's event time is unknown.
+pub async fn find(
+    store: &(dyn DocumentStore + Send + Sync),
+    id: SubscriptionId,
+    partition: i32,
+    offset: i64,
+) -> Result<Option<Value>, AnyError> {
+    let query = ShardQuery {
+        at: Some((partition, offset)),
+        limit: 1,
+        ..Default::default()
+    };
+
+    for shard in store.shards(id).await? {
+        if let Some(hit) = store.search(&shard, &query).await?.hits.into_iter().next() {
+            return Ok(Some(hit));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+fn ids(page: &SearchPage) -> Vec<&str> {
+    page.hits
+        .iter()
+        .map(|h| h[PRIMARY_KEY].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn it_merges_shards_in_event_time_order() {
+    use super::router::{router, ts};
+
+    let (store, mut router) = router(Some(super::period::ShardPeriod::Monthly));
+    router
+        .route(vec![
+            ("0-1", ts("2024-04-03T00:00:00Z")),
+            ("0-2", ts("2024-05-20T00:00:00Z")),
+            ("1-1", ts("2024-04-28T00:00:00Z")),
+            ("1-2", ts("2024-05-02T00:00:00Z")),
+            ("2-1", ts("2024-05-20T00:00:00Z")),
+            ("2-2", ts("2024-06-01T00:00:00Z")),
+        ])
+        .await;
+
+    let query = SearchQuery {
+        limit: 10,
+        ..Default::default()
+    };
+    let page = search(store.as_ref(), SubscriptionId(1), &query)
+        .await
+        .unwrap();
+    assert_eq!(ids(&page), vec!["2-2", "0-2", "2-1", "1-2", "1-1", "0-1"]);
+    assert_eq!(page.estimated_total_hits, 6);
+    assert_eq!(page.shards.len(), 3);
+
+    // Pages never skip nor repeat documents across shard boundaries.
+    let mut paged = vec![];
+    for offset in (0..6).step_by(4) {
+        let query = SearchQuery {
+            offset,
+            limit: 4,
+            ..Default::default()
+        };
+        let page = search(store.as_ref(), SubscriptionId(1), &query)
+            .await
+            .unwrap();
+        paged.extend(ids(&page).into_iter().map(String::from));
+    }
+    assert_eq!(paged, ids(&page));
+
+    // Time filters only fan out to the overlapping shards.
+    let query = SearchQuery {
+        from: Some(ts("2024-05-01T00:00:00Z")),
+        to: Some(ts("2024-05-31T00:00:00Z")),
+        limit: 10,
+        ..Default::default()
+    };
+    let page = search(store.as_ref(), SubscriptionId(1), &query)
+        .await
+        .unwrap();
+    assert_eq!(page.shards, vec!["sub_1_2024_05"]);
+    assert_eq!(ids(&page), vec!["0-2", "2-1", "1-2"]);
+}
@@ -0,0 +1,127 @@
+use meilisearch_sdk::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+use crate::ids::SubscriptionId;
+
+use super::period::ShardPeriod;
+use super::{EVENT_TS, OFFSET, PARTITION, PRIMARY_KEY};
+
+/// An index holding the documents of a subscription produced within a time range.
+///
+/// Unsharded subscriptions have a single shard covering all time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shard {
+    /// The name of the shard's index, e.g. `sub_42_2024_05`, which also identifies the shard.
+    pub id: String,
+
+    /// The subscription the shard belongs to.
+    pub subscription_id: SubscriptionId,
+
+    /// Point in time in UTC Epoch milliseconds, where the shard's range starts (inclusive).
+    pub start_ms: i64,
+
+    /// Point in time in UTC Epoch milliseconds, where the shard's range ends (exclusive).
+    pub end_ms: i64,
+
+    /// Number of documents in the shard, as of the last manifest refresh.
+    pub documents: usize,
+}
+
+impl Shard {
+    /// The shard holding the documents of the subscription with the given event timestamp.
+    pub fn route(id: SubscriptionId, period: Option<ShardPeriod>, event_ts: i64) -> Self {
+        let Some(period) = period else {
+            return Shard {
+                id: format!("sub_{}", id),
+                subscription_id: id,
+                start_ms: i64::MIN,
+                end_ms: i64::MAX,
+                documents: 0,
+            };
+        };
+
+        let (key, start_ms, end_ms) = period.bucket(event_ts);
+        Shard {
+            id: format!("sub_{}_{}", id, key),
+            subscription_id: id,
+            start_ms,
+            end_ms,
+            documents: 0,
+        }
+    }
+
+    /// Returns `true` if the shard's range overlaps the inclusive `[from, to]` range.
+    pub fn overlaps(&self, from: Option<i64>, to: Option<i64>) -> bool {
+        from.is_none_or(|from| self.end_ms > from) && to.is_none_or(|to| self.start_ms <= to)
+    }
+
+    /// Returns `true` if every document the shard may hold is older than the cutoff.
+    pub fn is_expired(&self, cutoff: i64) -> bool {
+        self.end_ms <= cutoff
+    }
+}
+
+/// Index settings applied to every shard of a subscription, and to the ones created later.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexSettings {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub searchable_attributes: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub filterable_attributes: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sortable_attributes: Option<Vec<String>>,
+}
+
+impl IndexSettings {
+    /// The Meilisearch settings of a shard.
+    ///
+    /// The event timestamp and document id stay filterable and sortable, as
+    /// the search fan-out relies on them, and the Kafka coordinates stay
+    /// filterable for lookups.
+    pub fn resolve(&self) -> Settings {
+        let with = |attributes: &Option<Vec<String>>, required: &[&str]| {
+            let mut attributes = attributes.clone().unwrap_or_default();
+            for a in required {
+                if !attributes.iter().any(|x| x == a) {
+                    attributes.push(a.to_string());
+                }
+            }
+            attributes
+        };
+
+        let mut settings = Settings::new()
+            .with_filterable_attributes(with(
+                &self.filterable_attributes,
+                &[EVENT_TS, PARTITION, OFFSET],
+            ))
+            .with_sortable_attributes(with(&self.sortable_attributes, &[EVENT_TS, PRIMARY_KEY]));
+        if let Some(searchable) = &self.searchable_attributes {
+            settings = settings.with_searchable_attributes(searchable);
+        }
+        settings
+    }
+}
+
+#[test]
+fn it_routes_documents_by_event_time() {
+    let id = SubscriptionId(42);
+    let may = "2024-05-17T08:30:00Z"
+        .parse::<chrono::DateTime<chrono::Utc>>()
+        .unwrap()
+        .timestamp_millis();
+
+    let shard = Shard::route(id, Some(ShardPeriod::Monthly), may);
+    assert_eq!(shard.id, "sub_42_2024_05");
+    assert!(shard.overlaps(Some(may), None));
+    assert!(shard.overlaps(None, Some(shard.start_ms)));
+    assert!(!shard.overlaps(Some(shard.end_ms), None));
+    assert!(shard.is_expired(shard.end_ms));
+    assert!(!shard.is_expired(shard.end_ms - 1));
+
+    let unsharded = Shard::route(id, None, may);
+    assert_eq!(unsharded.id, "sub_42");
+    assert!(unsharded.overlaps(Some(0), Some(may)));
+    assert!(!unsharded.is_expired(may));
+}
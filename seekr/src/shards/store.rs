@@ -0,0 +1,470 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use meilisearch_sdk::errors::{Error, ErrorCode};
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::tasks::TasksSearchQuery;
+use meilisearch_sdk::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+use super::shard::{IndexSettings, Shard};
+use super::{EVENT_TS, OFFSET, PARTITION, PRIMARY_KEY};
+
+/// A query against a single shard.
+#[derive(Clone, Debug, Default)]
+pub struct ShardQuery {
+    pub q: Option<String>,
+    /// Only match documents produced at or after this point in time, in UTC Epoch milliseconds.
+    pub from: Option<i64>,
+    /// Only match documents produced at or before this point in time, in UTC Epoch milliseconds.
+    pub to: Option<i64>,
+    /// Only match the document of the message at this partition and offset.
+    pub at: Option<(i32, i64)>,
+    pub limit: usize,
+}
+
+/// The first hits of a shard, ordered by event time (newest first) then document id.
+#[derive(Clone, Debug, Default)]
+pub struct ShardHits {
+    pub hits: Vec<Value>,
+    pub total: usize,
+}
+
+#[async_trait]
+pub trait DocumentStore {
+    /// The shard manifest of a subscription, ordered by range.
+    async fn shards(&self, id: SubscriptionId) -> Result<Vec<Shard>, AnyError>;
+
+    /// Insert or replace a shard in its subscription's manifest.
+    async fn put_shard(&self, shard: &Shard) -> Result<(), AnyError>;
+
+    /// Remove a shard from the manifest, then drop its index.
+    async fn remove_shard(&self, shard: &Shard) -> Result<(), AnyError>;
+
+    /// Create the shard's index with the given settings, unless it already exists.
+    async fn create_index(&self, shard: &Shard, settings: &IndexSettings) -> Result<(), AnyError>;
+
+    async fn apply_settings(&self, shard: &Shard, settings: &IndexSettings)
+        -> Result<(), AnyError>;
+
+    async fn add_documents(&self, shard: &Shard, documents: &[Value]) -> Result<(), AnyError>;
+
+    /// Delete the documents with the given ids, ids missing from the shard are skipped.
+    async fn delete_documents(&self, shard: &Shard, ids: &[String]) -> Result<(), AnyError>;
+
+    /// The number of documents held by the shard's index.
+    async fn count(&self, shard: &Shard) -> Result<usize, AnyError>;
+
+    async fn search(&self, shard: &Shard, query: &ShardQuery) -> Result<ShardHits, AnyError>;
+
+    /// A marker of the latest write to the subscription's shards, which only
+    /// grows, or `None` before the first one.
+    async fn last_update(&self, id: SubscriptionId) -> Result<Option<u64>, AnyError>;
+
+    /// The settings template of a subscription's shards.
+    async fn settings(&self, id: SubscriptionId) -> Result<IndexSettings, AnyError>;
+
+    async fn put_settings(
+        &self,
+        id: SubscriptionId,
+        settings: &IndexSettings,
+    ) -> Result<(), AnyError>;
+}
+
+pub const INDEX_NAME: &str = "subscription_shards";
+pub const SETTINGS_INDEX_NAME: &str = "subscription_index_settings";
+
+/// The settings template of a subscription, as stored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SettingsTemplate {
+    subscription_id: SubscriptionId,
+    #[serde(flatten)]
+    settings: IndexSettings,
+}
+
+pub struct MSDocumentStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+}
+
+impl MSDocumentStore {
+    pub async fn new(client: Arc<Client>) -> Self {
+        for (name, key) in [(INDEX_NAME, "id"), (SETTINGS_INDEX_NAME, "subscription_id")] {
+            if let Ok(task) = client.clone().create_index(name, Some(key)).await {
+                task.wait_for_completion(&client, None, None).await.unwrap();
+            }
+        }
+
+        let index = client.index(INDEX_NAME);
+        if let Err(e) = index.set_filterable_attributes(["subscription_id"]).await {
+            warn!(
+                "Unable to set filterable attributes on {}: {}",
+                INDEX_NAME, e
+            );
+        }
+        if let Err(e) = index.set_sortable_attributes(["start_ms"]).await {
+            warn!("Unable to set sortable attributes on {}: {}", INDEX_NAME, e);
+        }
+
+        Self { client }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(INDEX_NAME)
+    }
+
+    fn templates(&self) -> Index {
+        self.client.index(SETTINGS_INDEX_NAME)
+    }
+}
+
+#[async_trait]
+impl DocumentStore for MSDocumentStore {
+    async fn shards(&self, id: SubscriptionId) -> Result<Vec<Shard>, AnyError> {
+        let results = self
+            .index()
+            .search()
+            .with_filter(&format!("subscription_id = {}", id))
+            .with_sort(&["start_ms:asc"])
+            .with_limit(10_000)
+            .execute::<Shard>()
+            .await?;
+
+        Ok(results.hits.into_iter().map(|h| h.result).collect())
+    }
+
+    async fn put_shard(&self, shard: &Shard) -> Result<(), AnyError> {
+        self.index()
+            .add_or_replace(&[shard], Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_shard(&self, shard: &Shard) -> Result<(), AnyError> {
+        // Leave the manifest first, so searches stop reading the shard before it is dropped.
+        self.index()
+            .delete_document(&shard.id)
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        self.client
+            .index(&shard.id)
+            .delete()
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_index(&self, shard: &Shard, settings: &IndexSettings) -> Result<(), AnyError> {
+        // Creating an existing index fails its task, which leaves the index untouched.
+        self.client
+            .create_index(&shard.id, Some(PRIMARY_KEY))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        self.apply_settings(shard, settings).await
+    }
+
+    async fn apply_settings(
+        &self,
+        shard: &Shard,
+        settings: &IndexSettings,
+    ) -> Result<(), AnyError> {
+        self.client
+            .index(&shard.id)
+            .set_settings(&settings.resolve())
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn add_documents(&self, shard: &Shard, documents: &[Value]) -> Result<(), AnyError> {
+        self.client
+            .index(&shard.id)
+            .add_or_replace(documents, Some(PRIMARY_KEY))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_documents(&self, shard: &Shard, ids: &[String]) -> Result<(), AnyError> {
+        self.client
+            .index(&shard.id)
+            .delete_documents(ids)
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn count(&self, shard: &Shard) -> Result<usize, AnyError> {
+        match self.client.index(&shard.id).get_stats().await {
+            Ok(stats) => Ok(stats.number_of_documents),
+            Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::IndexNotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn search(&self, shard: &Shard, query: &ShardQuery) -> Result<ShardHits, AnyError> {
+        let filter = [
+            query.from.map(|from| format!("{} >= {}", EVENT_TS, from)),
+            query.to.map(|to| format!("{} <= {}", EVENT_TS, to)),
+            query
+                .at
+                .map(|(p, o)| format!("{} = {} AND {} = {}", PARTITION, p, OFFSET, o)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" AND ");
+        let sort = [format!("{}:desc", EVENT_TS), format!("{}:asc", PRIMARY_KEY)];
+        let sort = sort.iter().map(String::as_str).collect::<Vec<_>>();
+
+        let index = self.client.index(&shard.id);
+        let mut search = index.search();
+        search.with_sort(&sort).with_limit(query.limit);
+        if let Some(q) = &query.q {
+            search.with_query(q);
+        }
+        if !filter.is_empty() {
+            search.with_filter(&filter);
+        }
+
+        match search.execute::<Value>().await {
+            Ok(results) => Ok(ShardHits {
+                total: results.estimated_total_hits.unwrap_or(results.hits.len()),
+                hits: results.hits.into_iter().map(|h| h.result).collect(),
+            }),
+            // A shard created by another indexer may not have its index yet.
+            Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::IndexNotFound => {
+                Ok(ShardHits::default())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn last_update(&self, id: SubscriptionId) -> Result<Option<u64>, AnyError> {
+        let shards = self.shards(id).await?;
+        if shards.is_empty() {
+            return Ok(None);
+        }
+
+        // The latest finished task of the shards, or of the manifest they're listed in.
+        let mut uids = shards.iter().map(|s| s.id.as_str()).collect::<Vec<_>>();
+        uids.push(INDEX_NAME);
+        let mut query = TasksSearchQuery::new(&self.client);
+        query
+            .with_index_uids(uids)
+            .with_statuses(["succeeded", "failed"])
+            .with_limit(1);
+
+        let tasks = self.client.get_tasks_with(&query).await?;
+        Ok(tasks.results.first().map(|t| t.get_uid() as u64))
+    }
+
+    async fn settings(&self, id: SubscriptionId) -> Result<IndexSettings, AnyError> {
+        let template = self
+            .templates()
+            .get_document::<SettingsTemplate>(&id.to_string())
+            .await;
+
+        let template = meilisearch::found(template)?;
+        Ok(template.map(|t| t.settings).unwrap_or_default())
+    }
+
+    async fn put_settings(
+        &self,
+        id: SubscriptionId,
+        settings: &IndexSettings,
+    ) -> Result<(), AnyError> {
+        let template = SettingsTemplate {
+            subscription_id: id,
+            settings: settings.clone(),
+        };
+
+        self.templates()
+            .add_or_replace(&[template], Some("subscription_id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// An in-memory document store used to exercise sharding in tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryDocumentStore {
+    pub manifest: tokio::sync::RwLock<std::collections::BTreeMap<String, Shard>>,
+    pub indexes:
+        tokio::sync::RwLock<std::collections::HashMap<String, (IndexSettings, Vec<Value>)>>,
+    templates: tokio::sync::RwLock<std::collections::HashMap<SubscriptionId, IndexSettings>>,
+
+    /// Number of writes to the shards of each subscription.
+    writes: std::sync::Mutex<std::collections::HashMap<SubscriptionId, u64>>,
+
+    /// Fails every write of documents while set.
+    pub failing: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+impl MemoryDocumentStore {
+    fn written(&self, shard: &Shard) {
+        *self
+            .writes
+            .lock()
+            .unwrap()
+            .entry(shard.subscription_id)
+            .or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl DocumentStore for MemoryDocumentStore {
+    async fn shards(&self, id: SubscriptionId) -> Result<Vec<Shard>, AnyError> {
+        let mut shards = self
+            .manifest
+            .read()
+            .await
+            .values()
+            .filter(|s| s.subscription_id == id)
+            .cloned()
+            .collect::<Vec<_>>();
+        shards.sort_by_key(|s| s.start_ms);
+        Ok(shards)
+    }
+
+    async fn put_shard(&self, shard: &Shard) -> Result<(), AnyError> {
+        let mut manifest = self.manifest.write().await;
+        manifest.insert(shard.id.clone(), shard.clone());
+        Ok(())
+    }
+
+    async fn remove_shard(&self, shard: &Shard) -> Result<(), AnyError> {
+        self.manifest.write().await.remove(&shard.id);
+        self.indexes.write().await.remove(&shard.id);
+        self.written(shard);
+        Ok(())
+    }
+
+    async fn create_index(&self, shard: &Shard, settings: &IndexSettings) -> Result<(), AnyError> {
+        let mut indexes = self.indexes.write().await;
+        let index = indexes.entry(shard.id.clone()).or_default();
+        index.0 = settings.clone();
+        Ok(())
+    }
+
+    async fn apply_settings(
+        &self,
+        shard: &Shard,
+        settings: &IndexSettings,
+    ) -> Result<(), AnyError> {
+        if let Some(index) = self.indexes.write().await.get_mut(&shard.id) {
+            index.0 = settings.clone();
+        }
+        Ok(())
+    }
+
+    async fn add_documents(&self, shard: &Shard, documents: &[Value]) -> Result<(), AnyError> {
+        if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(format!("unable to write to index {}", shard.id).into());
+        }
+
+        let mut indexes = self.indexes.write().await;
+        let Some((_, docs)) = indexes.get_mut(&shard.id) else {
+            return Err(format!("index {} not found", shard.id).into());
+        };
+        for d in documents {
+            docs.retain(|x| x[PRIMARY_KEY] != d[PRIMARY_KEY]);
+            docs.push(d.clone());
+        }
+        self.written(shard);
+        Ok(())
+    }
+
+    async fn delete_documents(&self, shard: &Shard, ids: &[String]) -> Result<(), AnyError> {
+        if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(format!("unable to write to index {}", shard.id).into());
+        }
+
+        if let Some((_, docs)) = self.indexes.write().await.get_mut(&shard.id) {
+            docs.retain(|x| !ids.iter().any(|id| x[PRIMARY_KEY] == id.as_str()));
+        }
+        self.written(shard);
+        Ok(())
+    }
+
+    async fn count(&self, shard: &Shard) -> Result<usize, AnyError> {
+        let indexes = self.indexes.read().await;
+        Ok(indexes.get(&shard.id).map_or(0, |(_, docs)| docs.len()))
+    }
+
+    async fn search(&self, shard: &Shard, query: &ShardQuery) -> Result<ShardHits, AnyError> {
+        let indexes = self.indexes.read().await;
+        let Some((_, docs)) = indexes.get(&shard.id) else {
+            return Ok(ShardHits::default());
+        };
+
+        let mut hits = docs
+            .iter()
+            .filter(|d| {
+                let ts = super::event_ts(d);
+                query.from.is_none_or(|from| ts >= from)
+                    && query.to.is_none_or(|to| ts <= to)
+                    && query.q.as_deref().is_none_or(|q| d.to_string().contains(q))
+                    && query
+                        .at
+                        .is_none_or(|(p, o)| d[PARTITION] == p && d[OFFSET] == o)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        hits.sort_by(super::search::newest_first);
+
+        let total = hits.len();
+        hits.truncate(query.limit);
+        Ok(ShardHits { hits, total })
+    }
+
+    async fn last_update(&self, id: SubscriptionId) -> Result<Option<u64>, AnyError> {
+        Ok(self.writes.lock().unwrap().get(&id).copied())
+    }
+
+    async fn settings(&self, id: SubscriptionId) -> Result<IndexSettings, AnyError> {
+        let templates = self.templates.read().await;
+        Ok(templates.get(&id).cloned().unwrap_or_default())
+    }
+
+    async fn put_settings(
+        &self,
+        id: SubscriptionId,
+        settings: &IndexSettings,
+    ) -> Result<(), AnyError> {
+        self.templates.write().await.insert(id, settings.clone());
+        Ok(())
+    }
+}
+
+pub async fn init_document_store(
+    config: &MeilisearchConfig,
+) -> Arc<dyn DocumentStore + Send + Sync> {
+    Arc::new(MSDocumentStore::new(config.client()).await)
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::Utc;
+
+use crate::kafka::config;
+use crate::kafka::streams::StreamsMessage;
+use crate::subscriptions::subscription::Subscription;
+
+use super::router::Write;
+use super::{DocumentIds, EVENT_TS, OFFSET, PARTITION, PRIMARY_KEY};
+
+/// Set on the marker documents indexed for tombstones.
+pub const DELETED: &str = "_deleted";
+
+/// Point in time in UTC Epoch milliseconds, when the marker was indexed.
+pub const SEEKR_TS: &str = "_seekr_ts";
+
+/// What a tombstone does to the documents of a subscription, set by `tombstone.action`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TombstoneAction {
+    /// Tombstones are skipped, the document of their key stays searchable.
+    #[default]
+    Ignore,
+
+    /// Tombstones delete the document of their key.
+    Delete,
+
+    /// Tombstones replace the document of their key with a marker.
+    IndexMarker,
+}
+
+impl FromStr for TombstoneAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(TombstoneAction::Ignore),
+            "delete" => Ok(TombstoneAction::Delete),
+            "index_marker" => Ok(TombstoneAction::IndexMarker),
+            _ => Err(format!(
+                "unknown tombstone action '{}', expected ignore, delete or index_marker",
+                s
+            )),
+        }
+    }
+}
+
+/// Turns the messages of a subscription into writes, tombstones included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tombstones {
+    pub ids: DocumentIds,
+    pub action: TombstoneAction,
+}
+
+impl Tombstones {
+    /// The handling configured by `document.id` and `tombstone.action`.
+    ///
+    /// Deleting needs key ids, a tombstone's offset is never the id of a document.
+    pub fn of(config: &HashMap<String, String>) -> Result<Self, String> {
+        let ids = DocumentIds::of(config)?;
+        let action = config
+            .get(config::TOMBSTONE_ACTION)
+            .map_or(Ok(TombstoneAction::default()), |v| v.parse())?;
+
+        if action == TombstoneAction::Delete && ids != DocumentIds::Key {
+            return Err(format!(
+                "{} delete needs {} key",
+                config::TOMBSTONE_ACTION,
+                config::DOCUMENT_ID
+            ));
+        }
+        Ok(Self { ids, action })
+    }
+
+    /// The handling of a subscription, ignoring tombstones if it's invalid.
+    pub fn from(subscription: &Subscription) -> Self {
+        Self::of(&subscription.config).unwrap_or_else(|e| {
+            warn!(
+                "Ignoring the tombstones of subscription {}: {}",
+                subscription.id, e
+            );
+            Self {
+                ids: DocumentIds::of(&subscription.config).unwrap_or_default(),
+                action: TombstoneAction::Ignore,
+            }
+        })
+    }
+
+    /// The write of a message, `None` for ignored tombstones.
+    pub fn write(&self, message: &StreamsMessage) -> Option<Write> {
+        if let Some(document) = super::document(message, self.ids) {
+            return Some(Write::Upsert(document));
+        }
+
+        match self.action {
+            TombstoneAction::Ignore => None,
+            TombstoneAction::Delete => message
+                .key
+                .as_ref()
+                .map(|_| Write::Delete(self.ids.id(message))),
+            TombstoneAction::IndexMarker => Some(Write::Upsert(serde_json::json!({
+                PRIMARY_KEY: self.ids.id(message),
+                DELETED: true,
+                SEEKR_TS: Utc::now().timestamp_millis(),
+                EVENT_TS: message.timestamp.unwrap_or_else(|| Utc::now().timestamp_millis()),
+                PARTITION: message.partition,
+                OFFSET: message.offset,
+            }))),
+        }
+    }
+}
+
+#[cfg(test)]
+fn config(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn it_rejects_deletes_of_offset_documents() {
+    assert_eq!(Tombstones::of(&config(&[])), Ok(Tombstones::default()));
+    assert_eq!(
+        Tombstones::of(&config(&[(config::TOMBSTONE_ACTION, "delete")])),
+        Err("tombstone.action delete needs document.id key".to_string())
+    );
+    assert!(Tombstones::of(&config(&[
+        (config::TOMBSTONE_ACTION, "delete"),
+        (config::DOCUMENT_ID, "offset"),
+    ]))
+    .is_err());
+    assert!(Tombstones::of(&config(&[(config::TOMBSTONE_ACTION, "purge")])).is_err());
+    assert!(Tombstones::of(&config(&[(config::DOCUMENT_ID, "uuid")])).is_err());
+
+    let tombstones = Tombstones::of(&config(&[
+        (config::TOMBSTONE_ACTION, "delete"),
+        (config::DOCUMENT_ID, "key"),
+    ]))
+    .unwrap();
+    assert_eq!(tombstones.action, TombstoneAction::Delete);
+    assert_eq!(tombstones.ids, DocumentIds::Key);
+}
+
+#[test]
+fn it_writes_tombstones_by_action() {
+    let message = StreamsMessage {
+        payload: None,
+        payload_json: None,
+        topic: "orders".to_string(),
+        key: Some("order-7".to_string()),
+        headers: Default::default(),
+        partition: 0,
+        offset: 9,
+        timestamp: Some(1_700_000_000_000),
+    };
+    let id = DocumentIds::Key.id(&message);
+    let tombstones = |action| Tombstones {
+        ids: DocumentIds::Key,
+        action,
+    };
+
+    assert_eq!(tombstones(TombstoneAction::Ignore).write(&message), None);
+    assert_eq!(
+        tombstones(TombstoneAction::Delete).write(&message),
+        Some(Write::Delete(id.clone()))
+    );
+    let Some(Write::Upsert(marker)) = tombstones(TombstoneAction::IndexMarker).write(&message)
+    else {
+        panic!("expected a marker document");
+    };
+    assert_eq!(marker[PRIMARY_KEY], id.as_str());
+    assert_eq!(marker[DELETED], true);
+    assert!(marker[SEEKR_TS].is_i64());
+
+    // Without a key, there's no document to delete.
+    let unkeyed = StreamsMessage {
+        key: None,
+        ..message
+    };
+    assert_eq!(tombstones(TombstoneAction::Delete).write(&unkeyed), None);
+}
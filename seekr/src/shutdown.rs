@@ -1,10 +1,9 @@
 use std::fmt;
-use std::sync::RwLock;
 
-use tokio::sync::Notify;
+use tokio::sync::watch;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-/// Types of Shutdown states
+/// Types of Shutdown states, in the order they're reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ShutdownState {
     NotStarted,
     Started,
@@ -21,80 +20,147 @@ impl fmt::Display for ShutdownState {
     }
 }
 
-#[derive(Debug)]
-struct Inner {
-    state: ShutdownState,
-}
-
 /// Listens for the shutdown signal.
 ///
-/// Shutdown is signalled using a `Notify`. Only a single value is
-/// ever sent. Once a value has been sent via the channel, the shutdown begins.
-///
-/// The `Shutdown` struct listens for the signal and tracks that the signal has
-/// been received. Callers may query for whether the shutdown signal has been
-/// received or not.
+/// The state is kept in a `watch` channel, so it only ever moves forward and
+/// waiting for a state already reached returns immediately, however late the
+/// waiter comes. Any number of tasks may wait on the same signal.
 #[derive(Debug)]
 pub(crate) struct Shutdown {
     /// State of the shutdown signal.
-    inner: RwLock<Inner>,
-
-    /// The channel used to listen for shutdown begin.
-    begin: Notify,
-
-    /// The channel used to signal for shutdown completion.
-    complete: Notify,
+    state: watch::Sender<ShutdownState>,
 }
 
 impl Shutdown {
-    /// Create a new `Shutdown` backed by the given `Notify`.
     pub(crate) fn new() -> Shutdown {
         Shutdown {
-            inner: RwLock::new(Inner {
-                state: ShutdownState::NotStarted,
-            }),
-            begin: Notify::new(),
-            complete: Notify::new(),
+            state: watch::channel(ShutdownState::NotStarted).0,
         }
     }
 
     /// Returns `true` if the shutdown signal has been received.
     pub(crate) fn is_shutdown(&self) -> bool {
-        let state = self.inner.read().unwrap().state;
-        state.eq(&ShutdownState::Started) || state.eq(&ShutdownState::Complete)
+        *self.state.borrow() != ShutdownState::NotStarted
     }
 
-    /// Wait for the begin shutdown notice.
+    /// Wait for the begin shutdown notice, returning immediately when it was
+    /// already given, e.g. while the caller was busy with a slow poll.
     pub(crate) async fn wait_begin(&self) {
-        self.begin.notified().await
+        self.wait_for(ShutdownState::Started).await
     }
 
-    /// Begin the shutdown.
+    /// Begin the shutdown. Beginning it again, or once it completed, does nothing.
     pub(crate) fn begin(&self) {
-        // If the shutdown signal has already been received, then return
-        // immediately.
-        if self.is_shutdown() {
-            return;
-        }
-
-        self.begin.notify_waiters();
-
-        // Remember that the signal has been received.
-        let mut inner = self.inner.write().unwrap();
-        inner.state = ShutdownState::Started;
+        self.advance(ShutdownState::Started);
     }
 
-    /// Wait for the shutdown to complete.
+    /// Wait for the shutdown to complete, returning immediately when it already has.
     pub(crate) async fn wait_complete(&self) {
-        self.complete.notified().await
+        self.wait_for(ShutdownState::Complete).await
     }
 
-    /// Complete the shutdown.
+    /// Complete the shutdown, which counts as having begun it too.
     pub(crate) fn complete(&self) {
-        self.complete.notify_waiters();
+        self.advance(ShutdownState::Complete);
+    }
+
+    /// Move to the state unless it's already past it, checked and changed
+    /// under the channel's lock so concurrent callers wake the waiters once.
+    fn advance(&self, to: ShutdownState) {
+        self.state.send_if_modified(|state| {
+            let advanced = *state < to;
+            if advanced {
+                *state = to;
+            }
+            advanced
+        });
+    }
 
-        // Remember that the signal has been received.
-        let mut inner = self.inner.write().unwrap();
-        inner.state = ShutdownState::Complete;
+    async fn wait_for(&self, reached: ShutdownState) {
+        // The sender lives as long as `self`, so the channel can't close.
+        let _ = self.state.subscribe().wait_for(|s| *s >= reached).await;
+    }
+}
+
+#[tokio::test]
+async fn it_notices_a_shutdown_begun_before_waiting() {
+    let sd = Shutdown::new();
+    sd.begin();
+
+    tokio::time::timeout(std::time::Duration::from_secs(1), sd.wait_begin())
+        .await
+        .expect("shutdown already begun");
+}
+
+#[tokio::test]
+async fn it_wakes_waiters_of_a_later_begin() {
+    let sd = std::sync::Arc::new(Shutdown::new());
+    let waiter = tokio::spawn({
+        let sd = sd.clone();
+        async move { sd.wait_begin().await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!sd.is_shutdown());
+
+    sd.begin();
+    tokio::time::timeout(std::time::Duration::from_secs(1), waiter)
+        .await
+        .expect("shutdown begun")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn it_notices_a_shutdown_completed_before_waiting() {
+    let sd = Shutdown::new();
+    sd.begin();
+    sd.complete();
+
+    let timeout = std::time::Duration::from_secs(1);
+    tokio::time::timeout(timeout, sd.wait_complete())
+        .await
+        .expect("shutdown already complete");
+    tokio::time::timeout(timeout, sd.wait_begin())
+        .await
+        .expect("shutdown already begun");
+
+    // A late begin doesn't take the shutdown back.
+    sd.begin();
+    tokio::time::timeout(timeout, sd.wait_complete())
+        .await
+        .expect("shutdown still complete");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn it_wakes_every_waiter_of_the_same_signal() {
+    let sd = std::sync::Arc::new(Shutdown::new());
+    let waiters = (0..2)
+        .map(|_| {
+            let sd = sd.clone();
+            tokio::spawn(async move {
+                sd.wait_begin().await;
+                sd.wait_complete().await;
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Concurrent beginners all leave the shutdown begun, not further.
+    let beginners = (0..4)
+        .map(|_| {
+            let sd = sd.clone();
+            tokio::spawn(async move { sd.begin() })
+        })
+        .collect::<Vec<_>>();
+    for b in beginners {
+        b.await.unwrap();
+    }
+    assert!(sd.is_shutdown());
+    assert_eq!(*sd.state.borrow(), ShutdownState::Started);
+
+    sd.complete();
+    for w in waiters {
+        tokio::time::timeout(std::time::Duration::from_secs(1), w)
+            .await
+            .expect("shutdown complete")
+            .unwrap();
     }
 }
@@ -0,0 +1,667 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::{interval, sleep_until, Instant, MissedTickBehavior};
+
+use crate::errors::AnyError;
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::shutdown::Shutdown;
+
+use super::election::Elector;
+use super::lease::{self, LeaseStore, PRIMARY_LEASE};
+use super::sync::{CacheSource, SyncCursor};
+use super::{Availability, ServerRole, Standing};
+
+/// How long the primary lease lasts unless it's renewed.
+pub const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(15);
+
+/// How often a standby pulls the changes to the primary's metadata cache.
+pub const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs the metadata manager according to the instance's role.
+///
+/// Only the primary runs metadata consumers, so brokers are polled once no
+/// matter how many instances serve the API. Standbys serve the metadata the
+/// primary polled, synced into their own cache, and in `auto` mode take over
+/// once the primary's lease lapses.
+pub struct Coordinator {
+    role: ServerRole,
+    holder: String,
+
+    /// Where this instance is reached in `auto` mode, and the primary in `standby` mode.
+    url: String,
+    manager: Arc<MetadataManager>,
+    leases: Arc<dyn LeaseStore + Send + Sync>,
+    source: Arc<dyn CacheSource + Send + Sync>,
+    availability: Arc<Availability>,
+    ttl: Duration,
+    sync_interval: Duration,
+    sd: Shutdown,
+}
+
+impl Coordinator {
+    pub fn new(
+        role: ServerRole,
+        url: String,
+        manager: Arc<MetadataManager>,
+        leases: Arc<dyn LeaseStore + Send + Sync>,
+        source: Arc<dyn CacheSource + Send + Sync>,
+    ) -> Self {
+        let standing = match role {
+            ServerRole::Primary => Standing::Primary,
+            ServerRole::Standby => Standing::Standby {
+                primary_url: Some(url.clone()),
+            },
+            ServerRole::Auto => Standing::Standby { primary_url: None },
+        };
+
+        Self {
+            role,
+            holder: uuid::Uuid::new_v4().simple().to_string(),
+            url,
+            manager,
+            leases,
+            source,
+            availability: Arc::new(Availability::new(standing)),
+            ttl: DEFAULT_LEASE_TTL,
+            sync_interval: DEFAULT_SYNC_INTERVAL,
+            sd: Shutdown::new(),
+        }
+    }
+
+    /// Hold the primary lease for `ttl` past each renewal.
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Pull the primary's cache changes every `interval` while on standby.
+    pub fn with_sync_interval(mut self, interval: Duration) -> Self {
+        self.sync_interval = interval;
+        self
+    }
+
+    pub fn availability(&self) -> Arc<Availability> {
+        self.availability.clone()
+    }
+
+    /// The id this instance holds leases under.
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Tell the other instances this one is leaving on purpose.
+    pub async fn depart(&self) -> Result<(), AnyError> {
+        // A standby's url is its primary's, which isn't the one leaving.
+        let url = match self.role {
+            ServerRole::Standby => "",
+            _ => &self.url,
+        };
+        let departure = lease::departure(&self.holder, url, self.leases.now());
+        self.leases.put(&departure).await
+    }
+
+    pub async fn start(self: Arc<Self>) -> Result<(), AnyError> {
+        info!("Starting as {} instance {}...", self.role, self.holder);
+
+        if self.role == ServerRole::Primary {
+            self.manager.clone().start().await?;
+        }
+
+        let elector = match self.role {
+            ServerRole::Auto => Some(Elector::new(
+                self.holder.clone(),
+                self.url.clone(),
+                self.ttl,
+            )),
+            _ => None,
+        };
+
+        tokio::spawn(async move { self.run(elector).await });
+        Ok(())
+    }
+
+    pub async fn stop(self: Arc<Self>) {
+        debug!("Stopping coordinator...");
+
+        self.sd.begin();
+        self.sd.wait_complete().await;
+    }
+
+    async fn run(&self, mut elector: Option<Elector>) {
+        let renew_interval = elector
+            .as_ref()
+            .map_or(self.sync_interval, Elector::renew_interval);
+        let mut renew = interval(renew_interval);
+        let mut sync = interval(self.sync_interval);
+        sync.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut cursor = None;
+
+        loop {
+            let deadline = elector.as_ref().and_then(Elector::deadline);
+
+            tokio::select! {
+                _ = renew.tick(), if elector.is_some() => {
+                    let elector = elector.as_mut().unwrap();
+                    if let Some(standing) = elector.step(self.leases.as_ref()).await {
+                        self.transition(standing).await;
+                    }
+                }
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let elector = elector.as_mut().unwrap();
+                    if let Some(standing) = elector.expire(Instant::now()) {
+                        self.transition(standing).await;
+                    }
+                }
+                _ = sync.tick(), if !self.availability.is_primary() => {
+                    self.sync(&mut cursor).await;
+                }
+                _ = self.sd.wait_begin() => {
+                    debug!("Coordinator shutdown started...");
+
+                    if self.availability.is_primary() {
+                        self.manager.clone().stop().await;
+                    }
+                    if elector.is_some() {
+                        if let Err(e) = self.leases.release(PRIMARY_LEASE, &self.holder).await {
+                            warn!("Unable to release the {} lease: {}", PRIMARY_LEASE, e);
+                        }
+                    }
+
+                    self.sd.complete();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Start the metadata consumers on promotion, and stop them on demotion.
+    async fn transition(&self, standing: Standing) {
+        let was_primary = self.availability.is_primary();
+
+        match &standing {
+            Standing::Primary if !was_primary => {
+                // Name the primary that left on purpose, so a planned takeover reads as one.
+                match lease::departures(self.leases.as_ref()).await {
+                    Ok(d) if !d.is_empty() => info!(
+                        "Instance {} promoted to primary, after {} restarted on purpose",
+                        self.holder, d[0].holder
+                    ),
+                    _ => info!("Instance {} promoted to primary", self.holder),
+                }
+                if let Err(e) = self.manager.clone().start().await {
+                    error!("Unable to start the metadata consumers: {}", e);
+                }
+                self.availability.set(standing);
+            }
+            Standing::Standby { primary_url } if was_primary => {
+                warn!(
+                    "Instance {} demoted to standby of {}, stopping the metadata consumers",
+                    self.holder,
+                    primary_url.as_deref().unwrap_or("no primary")
+                );
+                // Writes are turned away before the consumers wind down.
+                self.availability.set(standing);
+                self.manager.clone().stop().await;
+            }
+            _ => self.availability.set(standing),
+        }
+    }
+
+    async fn sync(&self, cursor: &mut Option<SyncCursor>) {
+        let Standing::Standby {
+            primary_url: Some(primary),
+        } = self.availability.standing()
+        else {
+            return;
+        };
+
+        match self.source.pull(&primary, cursor.as_ref()).await {
+            Ok(sync) => {
+                *cursor = Some(sync.cursor.clone());
+                self.manager.apply(sync).await;
+            }
+            Err(e) => warn!("Unable to sync the metadata cache from {}: {}", primary, e),
+        }
+    }
+}
+
+/// A consumer counting its polls, serving fixed metadata.
+#[cfg(test)]
+struct CountingConsumer {
+    fetches: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl crate::kafka::metadata::consumer::MetadataConsumer for CountingConsumer {
+    async fn fetch_meta(&self) -> Result<crate::kafka::metadata::ClusterMetadata, AnyError> {
+        self.fetches
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(crate::history::diff::metadata(&[1], &[("orders", 3)]))
+    }
+
+    async fn fetch_offsets(
+        &self,
+        _metadata: &crate::kafka::metadata::ClusterMetadata,
+        _topics: &std::collections::HashMap<String, bool>,
+    ) -> Result<Vec<crate::kafka::metadata::TopicOffsets>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_group_offsets(
+        &self,
+        _group: &str,
+        _metadata: &crate::kafka::metadata::ClusterMetadata,
+    ) -> Result<Vec<crate::kafka::metadata::GroupLag>, AnyError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_topic_config(
+        &self,
+        _topic: &str,
+    ) -> Result<Vec<crate::kafka::metadata::TopicConfigEntry>, crate::errors::AnyError> {
+        Ok(vec![])
+    }
+}
+
+/// The managers of the test's instances by url, pulled from in-process.
+#[cfg(test)]
+#[derive(Default)]
+struct Instances {
+    managers: std::sync::Mutex<std::collections::HashMap<String, Arc<MetadataManager>>>,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl CacheSource for Instances {
+    async fn pull(
+        &self,
+        primary: &str,
+        since: Option<&SyncCursor>,
+    ) -> Result<super::sync::CacheSync, AnyError> {
+        let manager = self.managers.lock().unwrap().get(primary).cloned();
+        match manager {
+            Some(manager) => Ok(manager.changes(since).await),
+            None => Err(format!("{} is unreachable", primary).into()),
+        }
+    }
+}
+
+/// A lease store an instance can be cut off from.
+#[cfg(test)]
+struct Partitioned {
+    inner: Arc<super::lease::MemoryLeaseStore>,
+    cut: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl LeaseStore for Partitioned {
+    async fn acquire(
+        &self,
+        name: &str,
+        holder: &str,
+        url: &str,
+        ttl: Duration,
+    ) -> Result<super::lease::Lease, AnyError> {
+        if self.cut.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err("store unreachable".into());
+        }
+        self.inner.acquire(name, holder, url, ttl).await
+    }
+
+    async fn release(&self, name: &str, holder: &str) -> Result<(), AnyError> {
+        if self.cut.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err("store unreachable".into());
+        }
+        self.inner.release(name, holder).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<super::lease::Lease>, AnyError> {
+        if self.cut.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err("store unreachable".into());
+        }
+        self.inner.list(prefix).await
+    }
+
+    async fn put(&self, lease: &super::lease::Lease) -> Result<(), AnyError> {
+        if self.cut.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err("store unreachable".into());
+        }
+        self.inner.put(lease).await
+    }
+
+    fn now(&self) -> i64 {
+        self.inner.now()
+    }
+}
+
+/// An instance of a test, polling cluster 1 every second while primary.
+#[cfg(test)]
+struct Instance {
+    url: String,
+    coordinator: Arc<Coordinator>,
+    manager: Arc<MetadataManager>,
+    leases: Arc<Partitioned>,
+    fetches: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[cfg(test)]
+impl Instance {
+    async fn start(
+        name: &str,
+        role: ServerRole,
+        leases: &Arc<super::lease::MemoryLeaseStore>,
+        instances: &Arc<Instances>,
+    ) -> Self {
+        use std::collections::HashMap;
+
+        use crate::clusters::cluster::{Cluster, Kind};
+        use crate::clusters::store::{ClusterStore, MemoryClusterStore};
+        use crate::kafka::config;
+        use crate::kafka::metadata::manager::MetadataConsumerFactory;
+
+        let clusters = Arc::new(MemoryClusterStore::default());
+        let config = HashMap::from([(
+            config::METADATA_POLL_INTERVAL.to_string(),
+            "1000".to_string(),
+        )]);
+        let cluster = Cluster::new(None, Kind::Kafka, "local".to_string(), config);
+        clusters.insert(cluster).await.unwrap();
+
+        let fetches = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let factory: MetadataConsumerFactory = Arc::new(move |_| {
+            let fetches = counter.clone();
+            Ok(Arc::new(CountingConsumer { fetches }))
+        });
+        let manager = Arc::new(MetadataManager::with_factory(clusters, factory));
+
+        let url = format!("http://{}", name);
+        instances
+            .managers
+            .lock()
+            .unwrap()
+            .insert(url.clone(), manager.clone());
+
+        let leases = Arc::new(Partitioned {
+            inner: leases.clone(),
+            cut: Default::default(),
+        });
+        let target = match role {
+            ServerRole::Standby => "http://a".to_string(),
+            _ => url.clone(),
+        };
+        let coordinator = Arc::new(
+            Coordinator::new(
+                role,
+                target,
+                manager.clone(),
+                leases.clone(),
+                instances.clone(),
+            )
+            .with_lease_ttl(Duration::from_secs(3))
+            .with_sync_interval(Duration::from_millis(500)),
+        );
+        coordinator.clone().start().await.unwrap();
+
+        Self {
+            url,
+            coordinator,
+            manager,
+            leases,
+            fetches,
+        }
+    }
+
+    fn standing(&self) -> Standing {
+        self.coordinator.availability.standing()
+    }
+
+    fn fetches(&self) -> usize {
+        self.fetches.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn cut(&self, cut: bool) {
+        self.leases
+            .cut
+            .store(cut, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    async fn cached(&self) -> Option<crate::kafka::metadata::manager::CachedMetadataEntry> {
+        self.manager
+            .clone()
+            .get(crate::ids::ClusterId(1))
+            .await
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+async fn sleep(ms: u64) {
+    tokio::time::sleep(Duration::from_millis(ms)).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_syncs_the_standby_cache_without_polling_kafka() {
+    use crate::kafka::metadata::manager::CachedMetadataEntry;
+
+    let leases = Arc::new(super::lease::MemoryLeaseStore::default());
+    let instances = Arc::new(Instances::default());
+    let a = Instance::start("a", ServerRole::Primary, &leases, &instances).await;
+    let b = Instance::start("b", ServerRole::Standby, &leases, &instances).await;
+
+    sleep(3_000).await;
+    assert!(matches!(
+        a.cached().await,
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+    assert_eq!(b.cached().await, a.cached().await);
+    assert!(a.fetches() >= 3);
+    assert_eq!(b.fetches(), 0);
+    assert_eq!(
+        b.standing(),
+        Standing::Standby {
+            primary_url: Some(a.url.clone())
+        }
+    );
+
+    // Syncs after the first only carry what changed, and a restarted primary's
+    // cursor starts over with the whole cache.
+    let full = a.manager.changes(None).await;
+    assert!(full.full);
+    let unchanged = a.manager.changes(Some(&full.cursor)).await;
+    assert!(!unchanged.full && unchanged.entries.is_empty());
+    let restarted = SyncCursor {
+        instance: "restarted".to_string(),
+        ..full.cursor.clone()
+    };
+    assert!(a.manager.changes(Some(&restarted)).await.full);
+
+    a.coordinator.clone().stop().await;
+    b.coordinator.clone().stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_elects_a_single_primary_and_hands_over_on_release() {
+    use crate::kafka::metadata::manager::CachedMetadataEntry;
+
+    let leases = Arc::new(super::lease::MemoryLeaseStore::default());
+    let instances = Arc::new(Instances::default());
+    let a = Instance::start("a", ServerRole::Auto, &leases, &instances).await;
+    sleep(100).await;
+    let b = Instance::start("b", ServerRole::Auto, &leases, &instances).await;
+
+    sleep(3_000).await;
+    assert_eq!(a.standing(), Standing::Primary);
+    assert_eq!(
+        b.standing(),
+        Standing::Standby {
+            primary_url: Some(a.url.clone())
+        }
+    );
+    assert!(a.fetches() > 0);
+    assert_eq!(b.fetches(), 0);
+    assert!(matches!(
+        b.cached().await,
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+
+    // A primary shutting down releases the lease, and the standby takes over
+    // serving the metadata it synced until its own first poll.
+    a.coordinator.clone().stop().await;
+    let stopped = a.fetches();
+    sleep(1_100).await;
+    assert_eq!(b.standing(), Standing::Primary);
+    assert!(b.fetches() > 0);
+    assert!(matches!(
+        b.cached().await,
+        Some(CachedMetadataEntry::Meta(_))
+    ));
+    assert_eq!(leases.get(PRIMARY_LEASE).unwrap().epoch, 2);
+
+    sleep(3_000).await;
+    assert_eq!(a.fetches(), stopped);
+    b.coordinator.clone().stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_demotes_a_partitioned_primary_before_the_standby_takes_over() {
+    let leases = Arc::new(super::lease::MemoryLeaseStore::default());
+    let instances = Arc::new(Instances::default());
+    let a = Instance::start("a", ServerRole::Auto, &leases, &instances).await;
+    sleep(100).await;
+    let b = Instance::start("b", ServerRole::Auto, &leases, &instances).await;
+    sleep(2_000).await;
+    assert_eq!(a.standing(), Standing::Primary);
+
+    // Cut off from the store, the primary keeps its lease until the deadline,
+    // and never shares the role with the standby while it lapses.
+    a.cut(true);
+    let mut demoted = None;
+    for _ in 0..100 {
+        sleep(50).await;
+        let primaries = [&a, &b]
+            .iter()
+            .filter(|i| i.standing() == Standing::Primary)
+            .count();
+        assert!(primaries <= 1, "both instances are primary");
+
+        if demoted.is_none() && a.standing() != Standing::Primary {
+            demoted = Some(a.fetches());
+        }
+    }
+
+    let demoted = demoted.expect("the partitioned primary should demote itself");
+    assert_eq!(a.fetches(), demoted);
+    assert_eq!(b.standing(), Standing::Primary);
+    assert!(b.fetches() > 0);
+
+    // Once reachable again, the former primary follows the new one.
+    a.cut(false);
+    sleep(1_100).await;
+    assert_eq!(
+        a.standing(),
+        Standing::Standby {
+            primary_url: Some(b.url.clone())
+        }
+    );
+    assert_eq!(a.fetches(), demoted);
+
+    a.coordinator.clone().stop().await;
+    b.coordinator.clone().stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_resolves_two_instances_briefly_holding_the_lease() {
+    let leases = Arc::new(super::lease::MemoryLeaseStore::default());
+    let instances = Arc::new(Instances::default());
+    let a = Instance::start("a", ServerRole::Auto, &leases, &instances).await;
+    sleep(100).await;
+    let b = Instance::start("b", ServerRole::Auto, &leases, &instances).await;
+    sleep(2_000).await;
+    assert_eq!(a.standing(), Standing::Primary);
+
+    // The store's clock runs ahead while the primary can't reach it, so the
+    // standby takes the lease before the primary's own deadline.
+    a.cut(true);
+    leases.lapse(PRIMARY_LEASE);
+    while b.standing() != Standing::Primary {
+        sleep(10).await;
+    }
+    assert_eq!(a.standing(), Standing::Primary);
+    assert_eq!(leases.get(PRIMARY_LEASE).unwrap().epoch, 2);
+
+    // The first renewal that gets through demotes the former primary.
+    a.cut(false);
+    sleep(1_100).await;
+    assert_eq!(
+        a.standing(),
+        Standing::Standby {
+            primary_url: Some(b.url.clone())
+        }
+    );
+    let demoted = a.fetches();
+    sleep(3_000).await;
+    assert_eq!(a.fetches(), demoted);
+    assert_eq!(b.standing(), Standing::Primary);
+
+    a.coordinator.clone().stop().await;
+    b.coordinator.clone().stop().await;
+}
+
+#[cfg(feature = "chaos")]
+#[tokio::test(start_paused = true)]
+async fn it_hands_over_the_lease_after_failed_renewals() {
+    use crate::failpoints::{self, Failpoint, Mode};
+
+    let leases = Arc::new(super::lease::MemoryLeaseStore::default());
+    let instances = Arc::new(Instances::default());
+    let a = Instance::start("a", ServerRole::Auto, &leases, &instances).await;
+    sleep(100).await;
+    let b = Instance::start("b", ServerRole::Auto, &leases, &instances).await;
+    sleep(2_000).await;
+    assert_eq!(a.standing(), Standing::Primary);
+
+    // Six renewals in a row fail, twice the lease's 3s ttl at one renewal a second.
+    failpoints::arm(Failpoint {
+        name: format!("{}:{}", failpoints::LEASE_RENEW, a.coordinator.holder),
+        mode: Mode::Error,
+        count: Some(6),
+    });
+
+    let mut standings = vec![];
+    for _ in 0..50 {
+        sleep(100).await;
+        let primaries = [&a, &b]
+            .iter()
+            .filter(|i| i.standing() == Standing::Primary)
+            .count();
+        assert!(primaries <= 1, "both instances are primary");
+        standings.push((
+            a.standing() == Standing::Primary,
+            b.standing() == Standing::Primary,
+        ));
+    }
+
+    // The primary demotes itself at its deadline, before the standby takes over.
+    let demoted = standings.iter().position(|(a, _)| !a).unwrap();
+    let promoted = standings.iter().position(|(_, b)| *b).unwrap();
+    assert!(demoted <= promoted, "{:?}", standings);
+    assert_eq!(b.standing(), Standing::Primary);
+    assert_eq!(leases.get(PRIMARY_LEASE).unwrap().epoch, 2);
+
+    // Renewing again, the former primary follows the new one.
+    sleep(2_000).await;
+    assert_eq!(
+        a.standing(),
+        Standing::Standby {
+            primary_url: Some(b.url.clone())
+        }
+    );
+    assert_eq!(b.standing(), Standing::Primary);
+
+    a.coordinator.clone().stop().await;
+    b.coordinator.clone().stop().await;
+}
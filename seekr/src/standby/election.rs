@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use tokio::time::{timeout_at, Instant};
+
+use super::lease::{LeaseStore, PRIMARY_LEASE};
+use super::Standing;
+
+/// Elects the primary among the instances sharing the lease store.
+///
+/// An instance holds the lease until `ttl` after it last *asked* to renew it,
+/// which is never later than the store lets the lease lapse. So a primary cut
+/// off from the store demotes itself before another instance can take over.
+pub struct Elector {
+    holder: String,
+    url: String,
+    ttl: Duration,
+
+    /// When the held lease lapses, as far as this instance knows.
+    deadline: Option<Instant>,
+    standing: Standing,
+}
+
+impl Elector {
+    pub fn new(holder: String, url: String, ttl: Duration) -> Self {
+        Self {
+            holder,
+            url,
+            ttl,
+            deadline: None,
+            standing: Standing::Standby { primary_url: None },
+        }
+    }
+
+    pub fn standing(&self) -> &Standing {
+        &self.standing
+    }
+
+    /// When the held lease lapses unless it's renewed before.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// How often the lease is renewed, leaving room for two failed renewals.
+    pub fn renew_interval(&self) -> Duration {
+        std::cmp::max(self.ttl / 3, Duration::from_millis(1))
+    }
+
+    /// Acquire or renew the lease, returning the new standing when it changed.
+    pub async fn step(&mut self, store: &(dyn LeaseStore + Send + Sync)) -> Option<Standing> {
+        let asked = Instant::now();
+        let acquire = async {
+            fail_point!(crate::failpoints::LEASE_RENEW, self.holder)?;
+            store
+                .acquire(PRIMARY_LEASE, &self.holder, &self.url, self.ttl)
+                .await
+        };
+
+        // A renewal hanging past the deadline mustn't keep a lapsed lease.
+        let result = match self.deadline {
+            Some(deadline) => timeout_at(deadline, acquire)
+                .await
+                .unwrap_or_else(|_| Err("timed out".into())),
+            None => acquire.await,
+        };
+
+        match result {
+            Ok(lease) if lease.holder == self.holder => {
+                self.deadline = Some(asked + self.ttl);
+                self.set(Standing::Primary)
+            }
+            Ok(lease) => {
+                self.deadline = None;
+                self.set(Standing::Standby {
+                    primary_url: Some(lease.url),
+                })
+            }
+            Err(e) => {
+                warn!("Unable to renew the {} lease: {}", PRIMARY_LEASE, e);
+                self.expire(Instant::now())
+            }
+        }
+    }
+
+    /// Give up the lease once its deadline passed, returning the new standing.
+    pub fn expire(&mut self, now: Instant) -> Option<Standing> {
+        match self.deadline {
+            Some(deadline) if deadline <= now => {
+                self.deadline = None;
+                self.set(Standing::Standby { primary_url: None })
+            }
+            _ => None,
+        }
+    }
+
+    fn set(&mut self, standing: Standing) -> Option<Standing> {
+        if standing == self.standing {
+            return None;
+        }
+
+        self.standing = standing.clone();
+        Some(standing)
+    }
+}
@@ -0,0 +1,103 @@
+use actix_web::web::{Data, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::standby::sync::SyncCursor;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(cache_sync);
+}
+
+#[get("/cache-sync")]
+async fn cache_sync(
+    query: Query<CacheSyncQuery>,
+    manager: Data<MetadataManager>,
+) -> impl Responder {
+    let query = query.into_inner();
+    let since = match (query.since, query.instance) {
+        (Some(version), Some(instance)) => Some(SyncCursor { instance, version }),
+        _ => None,
+    };
+
+    HttpResponse::Ok().json(manager.changes(since.as_ref()).await)
+}
+
+#[derive(Deserialize)]
+struct CacheSyncQuery {
+    since: Option<u64>,
+    instance: Option<String>,
+}
+
+#[actix_web::test]
+async fn it_serves_the_cache_to_token_holders_and_redirects_standby_writes() {
+    use std::sync::Arc;
+
+    use actix_web::http::header::{AUTHORIZATION, LOCATION};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::standby::middleware::InternalToken;
+    use crate::standby::sync::CacheSync;
+    use crate::standby::{Availability, Standing};
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers".into()));
+    let manager = Data::new(MetadataManager::with_factory(
+        Arc::new(MemoryClusterStore::default()),
+        factory,
+    ));
+    let availability = Data::new(Availability::new(Standing::Standby {
+        primary_url: Some("http://seekr-a:5000/".to_string()),
+    }));
+    let app = test::init_service(
+        App::new()
+            .app_data(manager)
+            .app_data(availability.clone())
+            .app_data(Data::new(InternalToken::new("internal-secret")))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/internal/v1/cache-sync")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/internal/v1/cache-sync?since=3&instance=elsewhere")
+        .insert_header((AUTHORIZATION, "Bearer internal-secret"))
+        .to_request();
+    let sync: CacheSync = test::call_and_read_body_json(&app, req).await;
+    assert!(sync.full);
+    assert_eq!(sync.cursor.version, 0);
+
+    // Standbys serve reads, and send writes to the primary.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/schemas")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v2/clusters?dry_run=true")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        res.headers().get(LOCATION).unwrap(),
+        "http://seekr-a:5000/api/v2/clusters?dry_run=true"
+    );
+
+    availability.set(Standing::Standby { primary_url: None });
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/clusters/1")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+}
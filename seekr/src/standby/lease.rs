@@ -0,0 +1,319 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AnyError;
+use crate::meilisearch::{self, MeilisearchConfig};
+
+/// The name of the lease held by the primary server.
+pub const PRIMARY_LEASE: &str = "primary";
+
+/// Names of the records instances leave when they restart on purpose.
+pub const DEPARTURE_PREFIX: &str = "departed:";
+
+/// How long a departure tells peers an instance left on purpose, rather than crashed.
+pub const DEPARTURE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// A time-limited claim of a role, shared by every instance through the store.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    pub name: String,
+
+    /// The instance holding the lease.
+    pub holder: String,
+
+    /// Where the holder is reached, e.g. `http://seekr-a:5000`.
+    pub url: String,
+
+    /// Incremented whenever the lease changes hands, fencing off former holders.
+    pub epoch: u64,
+
+    /// Point in time in UTC Epoch milliseconds, when the lease lapses unless renewed.
+    pub expires_at: i64,
+}
+
+impl Lease {
+    /// Grant or renew the lease, unless another holder's lease is still current.
+    pub fn acquire(
+        current: Option<Lease>,
+        name: &str,
+        holder: &str,
+        url: &str,
+        ttl: Duration,
+        now: i64,
+    ) -> Lease {
+        let expires_at = now + ttl.as_millis() as i64;
+        match current {
+            Some(l) if l.holder == holder => Lease {
+                url: url.to_string(),
+                expires_at,
+                ..l
+            },
+            Some(l) if l.expires_at > now => l,
+            current => Lease {
+                name: name.to_string(),
+                holder: holder.to_string(),
+                url: url.to_string(),
+                epoch: current.map_or(1, |l| l.epoch + 1),
+                expires_at,
+            },
+        }
+    }
+}
+
+/// The record of an instance leaving on purpose, which was reached at `url`.
+pub fn departure(holder: &str, url: &str, now: i64) -> Lease {
+    Lease {
+        name: format!("{}{}", DEPARTURE_PREFIX, holder),
+        holder: holder.to_string(),
+        url: url.to_string(),
+        epoch: 0,
+        expires_at: now + DEPARTURE_TTL.as_millis() as i64,
+    }
+}
+
+/// The instances that left on purpose within the last `DEPARTURE_TTL`.
+pub async fn departures(store: &(dyn LeaseStore + Send + Sync)) -> Result<Vec<Lease>, AnyError> {
+    let now = store.now();
+    let departures = store.list(DEPARTURE_PREFIX).await?;
+    Ok(departures
+        .into_iter()
+        .filter(|l| l.expires_at > now)
+        .collect())
+}
+
+#[async_trait]
+pub trait LeaseStore {
+    /// Acquire or renew the named lease for `holder`, returning the lease as it
+    /// stands afterwards, held by another instance when theirs is still current.
+    async fn acquire(
+        &self,
+        name: &str,
+        holder: &str,
+        url: &str,
+        ttl: Duration,
+    ) -> Result<Lease, AnyError>;
+
+    /// Let the lease lapse right away, if `holder` holds it.
+    async fn release(&self, name: &str, holder: &str) -> Result<(), AnyError>;
+
+    /// Every lease whose name starts with `prefix`, lapsed ones included.
+    async fn list(&self, prefix: &str) -> Result<Vec<Lease>, AnyError>;
+
+    /// Write the lease as is, for records nobody races for, e.g. preferences.
+    async fn put(&self, lease: &Lease) -> Result<(), AnyError>;
+
+    /// The time leases expire by, in UTC Epoch milliseconds.
+    fn now(&self) -> i64 {
+        now()
+    }
+}
+
+pub const INDEX_NAME: &str = "leases";
+
+/// The most leases listed at once.
+const MAX_LEASES: usize = 10_000;
+
+pub struct MSLeaseStore {
+    /// Meilisearch client that provides an interface for interacting with the DB.
+    client: Arc<Client>,
+}
+
+impl MSLeaseStore {
+    pub async fn new(client: Arc<Client>) -> Self {
+        if let Ok(task) = client.clone().create_index(INDEX_NAME, Some("name")).await {
+            task.wait_for_completion(&client, None, None).await.unwrap();
+        }
+
+        Self { client }
+    }
+
+    async fn get(&self, name: &str) -> Result<Option<Lease>, AnyError> {
+        let lease = self
+            .client
+            .index(INDEX_NAME)
+            .get_document::<Lease>(name)
+            .await;
+
+        meilisearch::found(lease)
+    }
+}
+
+#[async_trait]
+impl LeaseStore for MSLeaseStore {
+    async fn acquire(
+        &self,
+        name: &str,
+        holder: &str,
+        url: &str,
+        ttl: Duration,
+    ) -> Result<Lease, AnyError> {
+        let current = self.get(name).await?;
+        let lease = Lease::acquire(current.clone(), name, holder, url, ttl, now());
+        if Some(&lease) == current.as_ref() {
+            return Ok(lease);
+        }
+
+        // Writes aren't conditional, so instances racing for a lapsed lease
+        // both write it, and learn who won by reading it back.
+        self.put(&lease).await?;
+        Ok(self.get(name).await?.unwrap_or(lease))
+    }
+
+    async fn release(&self, name: &str, holder: &str) -> Result<(), AnyError> {
+        match self.get(name).await? {
+            Some(lease) if lease.holder == holder => {
+                let lease = Lease {
+                    expires_at: now(),
+                    ..lease
+                };
+                self.put(&lease).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<Lease>, AnyError> {
+        let index = self.client.index(INDEX_NAME);
+        let leases = index
+            .get_documents_with::<Lease>(DocumentsQuery::new(&index).with_limit(MAX_LEASES))
+            .await?;
+
+        Ok(leases
+            .results
+            .into_iter()
+            .filter(|l| l.name.starts_with(prefix))
+            .collect())
+    }
+
+    async fn put(&self, lease: &Lease) -> Result<(), AnyError> {
+        self.client
+            .index(INDEX_NAME)
+            .add_or_replace(&[lease], Some("name"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn now() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// An in-memory store shared by the instances of a test, on the test's clock.
+#[cfg(test)]
+pub struct MemoryLeaseStore {
+    origin: tokio::time::Instant,
+    leases: std::sync::Mutex<std::collections::HashMap<String, Lease>>,
+}
+
+#[cfg(test)]
+impl Default for MemoryLeaseStore {
+    fn default() -> Self {
+        Self {
+            origin: tokio::time::Instant::now(),
+            leases: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl MemoryLeaseStore {
+    /// Let a lease lapse as if the store's clock ran ahead of its holder's.
+    pub fn lapse(&self, name: &str) {
+        let now = self.now();
+        if let Some(lease) = self.leases.lock().unwrap().get_mut(name) {
+            lease.expires_at = now;
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Lease> {
+        self.leases.lock().unwrap().get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl LeaseStore for MemoryLeaseStore {
+    async fn acquire(
+        &self,
+        name: &str,
+        holder: &str,
+        url: &str,
+        ttl: Duration,
+    ) -> Result<Lease, AnyError> {
+        let now = self.now();
+        let mut leases = self.leases.lock().unwrap();
+        let lease = Lease::acquire(leases.get(name).cloned(), name, holder, url, ttl, now);
+        leases.insert(name.to_string(), lease.clone());
+        Ok(lease)
+    }
+
+    async fn release(&self, name: &str, holder: &str) -> Result<(), AnyError> {
+        let now = self.now();
+        if let Some(lease) = self.leases.lock().unwrap().get_mut(name) {
+            if lease.holder == holder {
+                lease.expires_at = now;
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<Lease>, AnyError> {
+        let leases = self.leases.lock().unwrap();
+        let mut leases = leases
+            .values()
+            .filter(|l| l.name.starts_with(prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        leases.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(leases)
+    }
+
+    async fn put(&self, lease: &Lease) -> Result<(), AnyError> {
+        let mut leases = self.leases.lock().unwrap();
+        leases.insert(lease.name.clone(), lease.clone());
+        Ok(())
+    }
+
+    fn now(&self) -> i64 {
+        self.origin.elapsed().as_millis() as i64
+    }
+}
+
+pub async fn init_lease_store(config: &MeilisearchConfig) -> Arc<dyn LeaseStore + Send + Sync> {
+    Arc::new(MSLeaseStore::new(config.client()).await)
+}
+
+#[test]
+fn it_hands_over_only_lapsed_leases() {
+    let ttl = Duration::from_millis(1_000);
+    let a = Lease::acquire(None, PRIMARY_LEASE, "a", "http://a", ttl, 0);
+    assert_eq!((a.holder.as_str(), a.epoch, a.expires_at), ("a", 1, 1_000));
+
+    // The holder renews, others wait for it to lapse.
+    let renewed = Lease::acquire(Some(a.clone()), PRIMARY_LEASE, "a", "http://a", ttl, 500);
+    assert_eq!((renewed.epoch, renewed.expires_at), (1, 1_500));
+    let held = Lease::acquire(
+        Some(renewed.clone()),
+        PRIMARY_LEASE,
+        "b",
+        "http://b",
+        ttl,
+        1_499,
+    );
+    assert_eq!(held, renewed);
+
+    let b = Lease::acquire(Some(renewed), PRIMARY_LEASE, "b", "http://b", ttl, 1_500);
+    assert_eq!(
+        (b.holder.as_str(), b.url.as_str(), b.epoch),
+        ("b", "http://b", 2)
+    );
+}
@@ -0,0 +1,94 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{AUTHORIZATION, LOCATION};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse};
+
+use crate::auth::hash_secret;
+use crate::restart::SAFE_RESTART_PATH;
+
+use super::{Availability, Standing};
+
+/// The secret instances authenticate to each other's internal API with.
+pub struct InternalToken {
+    hash: String,
+}
+
+impl InternalToken {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            hash: hash_secret(secret),
+        }
+    }
+}
+
+/// Send writes to the primary while this instance is a standby.
+///
+/// Writes are answered with a `307`, which clients follow with the same method
+/// and body, or a `503` while no primary is elected or the instance is
+/// restarting. Requests pass through untouched when no `Availability` is
+/// registered, and safe restarts are always handled by the instance itself.
+pub async fn redirect_writes(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || req.path().ends_with(SAFE_RESTART_PATH);
+    let availability = req.app_data::<Data<Availability>>();
+    if !safe && availability.is_some_and(|a| a.is_leaving()) {
+        let res =
+            HttpResponse::ServiceUnavailable().body("The instance is restarting, retry shortly");
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    let standing = availability
+        .map(|a| a.standing())
+        .unwrap_or(Standing::Primary);
+
+    let Standing::Standby { primary_url } = standing else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+    if safe {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let res = match primary_url {
+        Some(url) => {
+            let path = req
+                .uri()
+                .path_and_query()
+                .map_or(req.path(), |p| p.as_str());
+            let location = format!("{}{}", url.trim_end_matches('/'), path);
+            HttpResponse::TemporaryRedirect()
+                .insert_header((LOCATION, location))
+                .finish()
+        }
+        None => HttpResponse::ServiceUnavailable().body("No primary is elected, retry shortly"),
+    };
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Admit only callers presenting the internal token, rejecting every caller
+/// when none is configured.
+pub async fn authenticate_internal(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let secret = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let admitted = match (req.app_data::<Data<InternalToken>>(), secret) {
+        (Some(token), Some(secret)) => hash_secret(secret.trim()) == token.hash,
+        _ => false,
+    };
+
+    if !admitted {
+        let res = HttpResponse::Unauthorized().finish();
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+pub mod coordinator;
+pub mod election;
+pub mod endpoints;
+pub mod lease;
+pub mod middleware;
+pub mod sync;
+
+/// The role a server instance is started with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerRole {
+    /// Polls Kafka and accepts writes, without an election.
+    Primary,
+
+    /// Serves reads from the metadata cache synced from the primary, and redirects writes.
+    Standby,
+
+    /// Runs as a standby, and as the primary while it holds the primary lease.
+    Auto,
+}
+
+impl fmt::Display for ServerRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerRole::Primary => write!(f, "primary"),
+            ServerRole::Standby => write!(f, "standby"),
+            ServerRole::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// What an instance currently does.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "standing", rename_all = "snake_case")]
+pub enum Standing {
+    Primary,
+
+    /// Following the primary at `primary_url`, unknown while none is elected.
+    Standby {
+        primary_url: Option<String>,
+    },
+}
+
+/// The standing of the running instance, read by every request that writes.
+pub struct Availability {
+    standing: RwLock<Standing>,
+
+    /// Set once the instance is restarting, refusing writes whatever its standing.
+    leaving: AtomicBool,
+}
+
+impl Availability {
+    pub fn new(standing: Standing) -> Self {
+        Self {
+            standing: RwLock::new(standing),
+            leaving: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_leaving(&self) -> bool {
+        self.leaving.load(Ordering::Acquire)
+    }
+
+    /// Refuse writes from now on, the instance is going away.
+    pub fn leave(&self) {
+        self.leaving.store(true, Ordering::Release);
+    }
+
+    pub fn standing(&self) -> Standing {
+        self.standing.read().unwrap().clone()
+    }
+
+    pub fn is_primary(&self) -> bool {
+        *self.standing.read().unwrap() == Standing::Primary
+    }
+
+    pub(crate) fn set(&self, standing: Standing) {
+        *self.standing.write().unwrap() = standing;
+    }
+}
@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use isahc::AsyncReadResponseExt;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::CachedMetadataEntry;
+use crate::kafka::metadata::TopicOffsets;
+
+/// A position in a primary's metadata cache.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncCursor {
+    /// The primary instance the version is of.
+    pub instance: String,
+    pub version: u64,
+}
+
+/// The cached metadata and watermarks of a single cluster.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncedEntry {
+    pub cluster_id: ClusterId,
+    pub entry: CachedMetadataEntry,
+    pub offsets: Option<Vec<TopicOffsets>>,
+}
+
+/// The changes to a primary's metadata cache since a cursor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheSync {
+    /// Where the next sync continues from.
+    pub cursor: SyncCursor,
+
+    /// Whether the entries are the whole cache rather than the changes since the cursor.
+    pub full: bool,
+    pub entries: Vec<SyncedEntry>,
+
+    /// Every cluster in the cache, so the clusters removed since are dropped.
+    pub clusters: Vec<ClusterId>,
+}
+
+/// Where a standby pulls the primary's metadata cache from.
+#[async_trait]
+pub trait CacheSource {
+    /// The changes to the cache of the primary at `primary` since the cursor.
+    async fn pull(&self, primary: &str, since: Option<&SyncCursor>) -> Result<CacheSync, AnyError>;
+}
+
+/// Pulls the cache from the primary's internal API, authenticated with the internal token.
+pub struct HttpCacheSource {
+    token: Option<String>,
+}
+
+impl HttpCacheSource {
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+}
+
+#[async_trait]
+impl CacheSource for HttpCacheSource {
+    async fn pull(&self, primary: &str, since: Option<&SyncCursor>) -> Result<CacheSync, AnyError> {
+        let mut url = format!("{}/internal/v1/cache-sync", primary.trim_end_matches('/'));
+        if let Some(c) = since {
+            url.push_str(&format!("?since={}&instance={}", c.version, c.instance));
+        }
+
+        let mut request = isahc::Request::get(&url);
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        let mut response = isahc::send_async(request.body(())?).await?;
+        let body = response.text().await?;
+        if !response.status().is_success() {
+            return Err(format!("{} answered {}: {}", url, response.status(), body).into());
+        }
+
+        Ok(serde_json::from_str(&body)?)
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::{BrokerLogDirs, LogDirs};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrokerStatus {
+    Described,
+    Unsupported,
+    Failed,
+}
+
+/// The disk used by a broker, summed over its log dirs.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BrokerStorage {
+    pub broker_id: i32,
+    pub status: BrokerStatus,
+    pub log_dirs: usize,
+    pub size_bytes: u64,
+}
+
+/// The disk used by a partition.
+///
+/// Every replica is a full copy, so the replicated size counts the partition
+/// once per replica while the logical size counts it once, as large as its
+/// largest replica.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PartitionStorage {
+    pub partition: i32,
+    pub replicas: usize,
+    pub replicated_bytes: u64,
+    pub logical_bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TopicStorage {
+    pub name: String,
+    pub replicated_bytes: u64,
+    pub logical_bytes: u64,
+    pub partitions: Vec<PartitionStorage>,
+}
+
+/// The disk used by a cluster, per broker and per topic.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StorageReport {
+    pub collected_at: DateTime<Utc>,
+    pub replicated_bytes: u64,
+    pub logical_bytes: u64,
+    pub brokers: Vec<BrokerStorage>,
+    /// Ordered by name.
+    pub topics: Vec<TopicStorage>,
+    /// Set when some brokers weren't described, leaving their replicas out.
+    pub partial: bool,
+    pub warnings: Vec<String>,
+}
+
+impl StorageReport {
+    pub fn topic(&self, name: &str) -> Option<&TopicStorage> {
+        self.topics
+            .binary_search_by(|t| t.name.as_str().cmp(name))
+            .ok()
+            .map(|i| &self.topics[i])
+    }
+}
+
+/// Sum up what each broker answered, per broker, topic and partition.
+pub fn aggregate(responses: &[BrokerLogDirs], collected_at: DateTime<Utc>) -> StorageReport {
+    let mut brokers = vec![];
+    let mut warnings = vec![];
+    // The size of each replica, by topic and partition
+    let mut replicas = BTreeMap::<&str, BTreeMap<i32, Vec<u64>>>::new();
+
+    for response in responses {
+        let broker_id = response.broker_id;
+        let (status, log_dirs, size_bytes) = match &response.log_dirs {
+            LogDirs::Described(dirs) => {
+                let mut size = 0;
+                for r in dirs.iter().flat_map(|d| &d.replicas) {
+                    size += r.size_bytes;
+                    replicas
+                        .entry(r.topic.as_str())
+                        .or_default()
+                        .entry(r.partition)
+                        .or_default()
+                        .push(r.size_bytes);
+                }
+                (BrokerStatus::Described, dirs.len(), size)
+            }
+            LogDirs::Unsupported => {
+                warnings.push(format!(
+                    "Broker {} doesn't support DescribeLogDirs",
+                    broker_id
+                ));
+                (BrokerStatus::Unsupported, 0, 0)
+            }
+            LogDirs::Failed(e) => {
+                warnings.push(format!(
+                    "Failed to describe the log dirs of broker {} - {}",
+                    broker_id, e
+                ));
+                (BrokerStatus::Failed, 0, 0)
+            }
+        };
+        brokers.push(BrokerStorage {
+            broker_id,
+            status,
+            log_dirs,
+            size_bytes,
+        });
+    }
+    brokers.sort_by_key(|b| b.broker_id);
+
+    let topics = replicas
+        .into_iter()
+        .map(|(name, partitions)| {
+            let partitions = partitions
+                .into_iter()
+                .map(|(partition, sizes)| PartitionStorage {
+                    partition,
+                    replicas: sizes.len(),
+                    replicated_bytes: sizes.iter().sum(),
+                    logical_bytes: sizes.iter().copied().max().unwrap_or_default(),
+                })
+                .collect::<Vec<_>>();
+            TopicStorage {
+                name: name.to_string(),
+                replicated_bytes: partitions.iter().map(|p| p.replicated_bytes).sum(),
+                logical_bytes: partitions.iter().map(|p| p.logical_bytes).sum(),
+                partitions,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    StorageReport {
+        collected_at,
+        replicated_bytes: topics.iter().map(|t| t.replicated_bytes).sum(),
+        logical_bytes: topics.iter().map(|t| t.logical_bytes).sum(),
+        partial: brokers.iter().any(|b| b.status != BrokerStatus::Described),
+        brokers,
+        topics,
+        warnings,
+    }
+}
+
+/// The `n` topics using the most disk, replicas included, largest first.
+pub fn top(topics: &[TopicStorage], n: usize) -> Vec<&TopicStorage> {
+    let mut topics = topics.iter().collect::<Vec<_>>();
+    topics.sort_by(|a, b| {
+        b.replicated_bytes
+            .cmp(&a.replicated_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    topics.truncate(n);
+    topics
+}
+
+#[cfg(test)]
+fn described(broker_id: i32, dirs: &[(&str, &[(&str, i32, u64)])]) -> BrokerLogDirs {
+    use super::{LogDir, ReplicaSize};
+
+    let dirs = dirs
+        .iter()
+        .map(|(path, replicas)| LogDir {
+            path: path.to_string(),
+            replicas: replicas
+                .iter()
+                .map(|&(topic, partition, size_bytes)| ReplicaSize {
+                    topic: topic.to_string(),
+                    partition,
+                    size_bytes,
+                })
+                .collect(),
+        })
+        .collect();
+
+    BrokerLogDirs {
+        broker_id,
+        log_dirs: LogDirs::Described(dirs),
+    }
+}
+
+#[test]
+fn it_counts_replicas_once_logically() {
+    // orders-0 is replicated on brokers 1 and 2, and the follower lags a little.
+    let responses = [
+        described(2, &[("/data/a", &[("orders", 0, 90), ("refunds", 0, 10)])]),
+        described(
+            1,
+            &[
+                ("/data/a", &[("orders", 0, 100)]),
+                ("/data/b", &[("orders", 1, 50), ("refunds", 0, 10)]),
+            ],
+        ),
+    ];
+
+    let report = aggregate(&responses, Utc::now());
+    assert!(!report.partial);
+    assert_eq!(
+        report
+            .brokers
+            .iter()
+            .map(|b| (b.broker_id, b.log_dirs, b.size_bytes))
+            .collect::<Vec<_>>(),
+        vec![(1, 2, 160), (2, 1, 100)]
+    );
+
+    let orders = report.topic("orders").unwrap();
+    assert_eq!(orders.replicated_bytes, 100 + 90 + 50);
+    assert_eq!(orders.logical_bytes, 100 + 50);
+    assert_eq!(
+        orders.partitions[0],
+        PartitionStorage {
+            partition: 0,
+            replicas: 2,
+            replicated_bytes: 190,
+            logical_bytes: 100,
+        }
+    );
+    assert_eq!(report.topic("refunds").unwrap().logical_bytes, 10);
+    assert_eq!(report.replicated_bytes, 260);
+    assert_eq!(report.logical_bytes, 160);
+    assert_eq!(report.topic("payments"), None);
+}
+
+#[test]
+fn it_reports_partial_results_of_failing_brokers() {
+    let responses = [
+        described(1, &[("/data", &[("orders", 0, 100)])]),
+        BrokerLogDirs {
+            broker_id: 2,
+            log_dirs: LogDirs::Unsupported,
+        },
+        BrokerLogDirs {
+            broker_id: 3,
+            log_dirs: LogDirs::Failed("timed out".to_string()),
+        },
+    ];
+
+    let report = aggregate(&responses, Utc::now());
+    assert!(report.partial);
+    assert_eq!(report.brokers[1].status, BrokerStatus::Unsupported);
+    assert_eq!(report.brokers[2].status, BrokerStatus::Failed);
+    assert_eq!(
+        report.warnings,
+        vec![
+            "Broker 2 doesn't support DescribeLogDirs",
+            "Failed to describe the log dirs of broker 3 - timed out",
+        ]
+    );
+    assert_eq!(report.replicated_bytes, 100);
+}
+
+#[test]
+fn it_selects_the_largest_topics() {
+    let responses = [described(
+        1,
+        &[(
+            "/data",
+            &[
+                ("orders", 0, 100),
+                ("refunds", 0, 300),
+                ("payments", 0, 100),
+                ("invoices", 0, 50),
+            ],
+        )],
+    )];
+
+    let report = aggregate(&responses, Utc::now());
+    let names = |n| {
+        top(&report.topics, n)
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>()
+    };
+    // Ties are broken by name, so the selection is stable across collections.
+    assert_eq!(names(3), vec!["refunds", "orders", "payments"]);
+    assert_eq!(names(10).len(), 4);
+    assert_eq!(names(0), Vec::<&str>::new());
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::clusters::cluster::Cluster;
+use crate::clusters::store::ClusterStore;
+use crate::errors::AnyError;
+use crate::ids::ClusterId;
+use crate::kafka::config;
+use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataManager};
+use crate::page::Page;
+use crate::standby::Availability;
+use crate::sweeper::Job;
+
+use super::analysis::{self, StorageReport};
+use super::{KafkaLogDirSource, LogDirSource};
+
+/// How often clusters are collected, unless `storage.poll.interval.ms` says otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How often the job looks for clusters due a collection.
+const TICK: Duration = Duration::from_secs(60);
+
+/// Every cluster is described in a single run, which may take a while.
+const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Collects the log dir sizes of every cluster, on an interval of its own
+/// as describing every replica costs the brokers more than a metadata poll.
+pub struct StorageCollector {
+    clusters: Arc<dyn ClusterStore + Send + Sync>,
+    manager: Arc<MetadataManager>,
+    source: Arc<dyn LogDirSource + Send + Sync>,
+    availability: Option<Arc<Availability>>,
+    reports: RwLock<HashMap<ClusterId, StorageReport>>,
+}
+
+impl StorageCollector {
+    pub fn new(
+        clusters: Arc<dyn ClusterStore + Send + Sync>,
+        manager: Arc<MetadataManager>,
+    ) -> Self {
+        Self {
+            clusters,
+            manager,
+            source: Arc::new(KafkaLogDirSource),
+            availability: None,
+            reports: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_source(mut self, source: Arc<dyn LogDirSource + Send + Sync>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Only collect while the instance is the primary, standbys leave Kafka alone.
+    pub fn with_availability(mut self, availability: Arc<Availability>) -> Self {
+        self.availability = Some(availability);
+        self
+    }
+
+    /// The sweeper job collecting the clusters due, counting them.
+    pub fn job(self: Arc<Self>) -> Job {
+        Job::new("storage_collection", TICK, move || {
+            let collector = self.clone();
+            async move { collector.collect_due().await }
+        })
+        .with_timeout(TIMEOUT)
+    }
+
+    /// Collect every cluster whose last report is older than its interval.
+    pub async fn collect_due(&self) -> Result<usize, AnyError> {
+        if self.availability.as_ref().is_some_and(|a| !a.is_primary()) {
+            return Ok(0);
+        }
+
+        let clusters = self.clusters.list(None, Page::ALL).await?;
+        self.reports
+            .write()
+            .unwrap()
+            .retain(|id, _| clusters.iter().any(|c| c.id == *id));
+
+        let mut collected = 0;
+        for cluster in clusters {
+            let due = match self.report(cluster.id) {
+                Some(report) => {
+                    let age = (Utc::now() - report.collected_at)
+                        .to_std()
+                        .unwrap_or_default();
+                    age >= interval(&cluster)
+                }
+                None => true,
+            };
+            if !due {
+                continue;
+            }
+
+            match self.collect(&cluster).await {
+                Ok(true) => collected += 1,
+                Ok(false) => {}
+                Err(e) => warn!(
+                    "Failed to collect storage of cluster {} - {}",
+                    cluster.id, e
+                ),
+            }
+        }
+        Ok(collected)
+    }
+
+    /// Describe the log dirs of every broker of the cluster, `false` when
+    /// its brokers aren't known yet.
+    pub async fn collect(&self, cluster: &Cluster) -> Result<bool, AnyError> {
+        let entry = self.manager.clone().get(cluster.id, None).await?;
+        let Some(CachedMetadataEntry::Meta(metadata)) = entry.as_deref() else {
+            return Ok(false);
+        };
+
+        let brokers = metadata.brokers.iter().map(|b| b.id).collect::<Vec<_>>();
+        let responses = self.source.describe(cluster, &brokers).await;
+        let report = analysis::aggregate(&responses, Utc::now());
+        if report.partial {
+            warn!(
+                "Collected partial storage of cluster {}: {}",
+                cluster.id,
+                report.warnings.join(", ")
+            );
+        }
+
+        self.reports.write().unwrap().insert(cluster.id, report);
+        Ok(true)
+    }
+
+    pub fn report(&self, id: ClusterId) -> Option<StorageReport> {
+        self.reports.read().unwrap().get(&id).cloned()
+    }
+
+    /// The disk a topic uses across its replicas, as of the last collection.
+    pub fn size_bytes(&self, id: ClusterId, topic: &str) -> Option<u64> {
+        let reports = self.reports.read().unwrap();
+        let report = reports.get(&id)?;
+        report.topic(topic).map(|t| t.replicated_bytes)
+    }
+}
+
+fn interval(cluster: &Cluster) -> Duration {
+    cluster
+        .config
+        .get(config::STORAGE_POLL_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_INTERVAL)
+}
+
+#[tokio::test]
+async fn it_collects_clusters_on_their_own_interval() {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use crate::clusters::cluster::Kind;
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::MetadataConsumerFactory;
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    use super::{BrokerLogDirs, LogDir, LogDirs, ReplicaSize};
+
+    #[derive(Default)]
+    struct Brokers {
+        described: Mutex<Vec<(ClusterId, Vec<i32>)>>,
+    }
+
+    #[async_trait]
+    impl LogDirSource for Brokers {
+        async fn describe(&self, cluster: &Cluster, brokers: &[i32]) -> Vec<BrokerLogDirs> {
+            let described = (cluster.id, brokers.to_vec());
+            self.described.lock().unwrap().push(described);
+            brokers
+                .iter()
+                .map(|&broker_id| BrokerLogDirs {
+                    broker_id,
+                    log_dirs: LogDirs::Described(vec![LogDir {
+                        path: "/data".to_string(),
+                        replicas: vec![ReplicaSize {
+                            topic: "orders".to_string(),
+                            partition: 0,
+                            size_bytes: 100,
+                        }],
+                    }]),
+                })
+                .collect()
+        }
+    }
+
+    let cs = Arc::new(MemoryClusterStore::default());
+    for (id, interval) in [(1, "0"), (2, "3600000")] {
+        let config = HashMap::from([(
+            config::STORAGE_POLL_INTERVAL.to_string(),
+            interval.to_string(),
+        )]);
+        let cluster = Cluster::new(Some(ClusterId(id)), Kind::Kafka, id.to_string(), config);
+        cs.put(cluster).await.unwrap();
+    }
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = Arc::new(MetadataManager::with_factory(cs.clone(), factory));
+    let entries = [1, 2]
+        .into_iter()
+        .map(|id| SyncedEntry {
+            cluster_id: ClusterId(id),
+            entry: CachedMetadataEntry::Meta(metadata(&[1, 2], &[("orders", 1)])),
+            offsets: None,
+        })
+        .collect();
+    manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            entries,
+            clusters: vec![ClusterId(1), ClusterId(2)],
+        })
+        .await;
+
+    let source = Arc::new(Brokers::default());
+    let collector = StorageCollector::new(cs.clone(), manager).with_source(source.clone());
+    assert_eq!(collector.collect_due().await.unwrap(), 2);
+    assert_eq!(collector.size_bytes(ClusterId(1), "orders"), Some(200));
+    assert_eq!(collector.size_bytes(ClusterId(1), "refunds"), None);
+
+    // Only the cluster collected continuously is due again.
+    assert_eq!(collector.collect_due().await.unwrap(), 1);
+    assert_eq!(source.described.lock().unwrap().len(), 3);
+    assert_eq!(source.described.lock().unwrap()[0].1, vec![1, 2]);
+
+    // Reports of removed clusters are dropped.
+    cs.remove(ClusterId(2)).await.unwrap();
+    collector.collect_due().await.unwrap();
+    assert!(collector.report(ClusterId(2)).is_none());
+}
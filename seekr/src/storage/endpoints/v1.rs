@@ -0,0 +1,78 @@
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::ClusterId;
+use crate::storage::analysis::{self, BrokerStorage, TopicStorage};
+use crate::storage::collector::StorageCollector;
+
+/// Default number of the largest topics listed.
+const DEFAULT_TOP: usize = 10;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_storage);
+}
+
+#[get("/{id}/storage")]
+async fn get_storage(
+    path: Path<ClusterId>,
+    query: Query<StorageQuery>,
+    collector: Data<StorageCollector>,
+) -> impl Responder {
+    let id = path.into_inner();
+    info!("Fetching storage for cluster with id {}", id);
+
+    let Some(report) = collector.report(id) else {
+        return HttpResponse::NotFound()
+            .body(format!("Storage of cluster '{}' wasn't collected yet", id));
+    };
+
+    let top_topics = analysis::top(&report.topics, query.top.unwrap_or(DEFAULT_TOP))
+        .into_iter()
+        .map(|t| TopicSize {
+            name: t.name.clone(),
+            replicated_bytes: t.replicated_bytes,
+            logical_bytes: t.logical_bytes,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(StorageResponse {
+        collected_at: report.collected_at,
+        replicated_bytes: report.replicated_bytes,
+        logical_bytes: report.logical_bytes,
+        partial: report.partial,
+        warnings: report.warnings,
+        brokers: report.brokers,
+        top_topics,
+        topics: query.topics.then_some(report.topics),
+    })
+}
+
+#[derive(Deserialize)]
+struct StorageQuery {
+    top: Option<usize>,
+    /// Break every topic down per partition.
+    #[serde(default)]
+    topics: bool,
+}
+
+#[derive(Serialize)]
+struct TopicSize {
+    name: String,
+    replicated_bytes: u64,
+    logical_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct StorageResponse {
+    collected_at: DateTime<Utc>,
+    replicated_bytes: u64,
+    logical_bytes: u64,
+    partial: bool,
+    warnings: Vec<String>,
+    brokers: Vec<BrokerStorage>,
+    top_topics: Vec<TopicSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    topics: Option<Vec<TopicStorage>>,
+}
@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::clusters::cluster::Cluster;
+
+pub mod analysis;
+pub mod collector;
+pub mod endpoints;
+
+/// The size of a partition replica in one of a broker's log dirs.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReplicaSize {
+    pub topic: String,
+    pub partition: i32,
+    pub size_bytes: u64,
+}
+
+/// A log dir of a broker and the replicas stored in it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LogDir {
+    pub path: String,
+    pub replicas: Vec<ReplicaSize>,
+}
+
+/// What a broker answered to DescribeLogDirs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogDirs {
+    Described(Vec<LogDir>),
+    /// The broker runs a version without the API.
+    Unsupported,
+    Failed(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrokerLogDirs {
+    pub broker_id: i32,
+    pub log_dirs: LogDirs,
+}
+
+#[async_trait]
+pub trait LogDirSource {
+    /// Describe the log dirs of each of the brokers, which fail individually.
+    async fn describe(&self, cluster: &Cluster, brokers: &[i32]) -> Vec<BrokerLogDirs>;
+}
+
+/// Describes log dirs through the Kafka admin API.
+///
+/// The admin client of `rdkafka` doesn't expose DescribeLogDirs yet, so every
+/// broker is reported as unsupported until it does, rather than failing the
+/// collection.
+pub struct KafkaLogDirSource;
+
+#[async_trait]
+impl LogDirSource for KafkaLogDirSource {
+    async fn describe(&self, _cluster: &Cluster, brokers: &[i32]) -> Vec<BrokerLogDirs> {
+        brokers
+            .iter()
+            .map(|&broker_id| BrokerLogDirs {
+                broker_id,
+                log_dirs: LogDirs::Unsupported,
+            })
+            .collect()
+    }
+}
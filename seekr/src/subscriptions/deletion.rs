@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::errors::AnyError;
+use crate::ids::SubscriptionId;
+use crate::page::Page;
+use crate::shards::store::DocumentStore;
+use crate::sweeper::Job;
+
+use super::store::{PurgeLog, PurgedSubscription, SubscriptionStore};
+use super::subscription::{PendingDeletion, Subscription};
+
+/// How long a deleted subscription can be undeleted, unless the delete asks otherwise.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(15 * 60);
+
+/// The longest undo window a delete may ask for.
+pub const MAX_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often subscriptions past their undo window are looked for.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The pending deletion of a subscription deleted at `now`.
+///
+/// Deleting a subscription that is already pending deletion keeps its first
+/// request, and only ever brings the purge closer.
+pub fn schedule(
+    current: Option<&PendingDeletion>,
+    now: DateTime<Utc>,
+    grace: Duration,
+    purge_index: bool,
+    was_paused: bool,
+) -> Result<PendingDeletion, AnyError> {
+    let purge_at = now + chrono::Duration::from_std(grace)?;
+
+    Ok(match current {
+        Some(current) => PendingDeletion {
+            purge_at: purge_at.min(current.purge_at),
+            purge_index: purge_index || current.purge_index,
+            ..current.clone()
+        },
+        None => PendingDeletion {
+            requested_at: now,
+            purge_at,
+            purge_index,
+            was_paused,
+        },
+    })
+}
+
+/// Returns `true` if the subscription's undo window is over at `now`.
+pub fn is_due(subscription: &Subscription, now: DateTime<Utc>) -> bool {
+    subscription
+        .pending_deletion
+        .as_ref()
+        .is_some_and(|d| d.purge_at <= now)
+}
+
+/// Periodically purges the subscriptions whose undo window is over.
+pub struct DeletionSweep {
+    subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    documents: Arc<dyn DocumentStore + Send + Sync>,
+    purges: Arc<dyn PurgeLog + Send + Sync>,
+}
+
+impl DeletionSweep {
+    pub fn new(
+        subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+        documents: Arc<dyn DocumentStore + Send + Sync>,
+        purges: Arc<dyn PurgeLog + Send + Sync>,
+    ) -> Self {
+        Self {
+            subscriptions,
+            documents,
+            purges,
+        }
+    }
+
+    /// The sweeper job purging subscriptions, counting those purged.
+    pub fn job(self: Arc<Self>) -> Job {
+        Job::new("subscription_deletions", SWEEP_INTERVAL, move || {
+            let sweep = self.clone();
+            async move { Ok::<_, AnyError>(sweep.sweep(Utc::now()).await?.len()) }
+        })
+    }
+
+    /// Purge every subscription whose undo window is over at `now`, returning their ids.
+    pub async fn sweep(&self, now: DateTime<Utc>) -> Result<Vec<SubscriptionId>, AnyError> {
+        let mut purged = vec![];
+
+        for subscription in self.subscriptions.list(None, Page::ALL).await? {
+            if !is_due(&subscription, now) {
+                continue;
+            }
+
+            match self.purge(&subscription, now).await {
+                Ok(_) => purged.push(subscription.id),
+                Err(e) => warn!("Failed to purge subscription {} - {}", subscription.id, e),
+            }
+        }
+
+        Ok(purged)
+    }
+
+    async fn purge(&self, s: &Subscription, now: DateTime<Utc>) -> Result<(), AnyError> {
+        purge(
+            self.subscriptions.as_ref(),
+            self.documents.as_ref(),
+            self.purges.as_ref(),
+            s,
+            now,
+        )
+        .await
+    }
+}
+
+/// Remove the subscription for good, along with its documents when its delete
+/// asked for them, recording it as purged at `now`.
+pub async fn purge(
+    subscriptions: &(dyn SubscriptionStore + Send + Sync),
+    documents: &(dyn DocumentStore + Send + Sync),
+    purges: &(dyn PurgeLog + Send + Sync),
+    s: &Subscription,
+    now: DateTime<Utc>,
+) -> Result<(), AnyError> {
+    if s.pending_deletion.as_ref().is_some_and(|d| d.purge_index) {
+        for shard in documents.shards(s.id).await? {
+            documents.remove_shard(&shard).await?;
+        }
+    }
+
+    // Recorded first, so an undelete racing the removal answers gone rather than missing.
+    purges
+        .record(PurgedSubscription {
+            id: s.id,
+            cluster_id: s.cluster_id,
+            purged_at: now,
+        })
+        .await?;
+    subscriptions.remove(s.cluster_id, s.id).await?;
+
+    info!(
+        "Purged subscription {} from cluster id {}",
+        s.id, s.cluster_id
+    );
+    Ok(())
+}
+
+#[test]
+fn it_only_brings_the_purge_closer() {
+    let now = Utc::now();
+    let first = schedule(None, now, DEFAULT_GRACE_PERIOD, false, true).unwrap();
+    assert_eq!(first.requested_at, now);
+    assert_eq!(first.purge_at, now + chrono::Duration::minutes(15));
+
+    let later = now + chrono::Duration::minutes(1);
+    let extended = schedule(Some(&first), later, MAX_GRACE_PERIOD, false, false).unwrap();
+    assert_eq!(extended, first);
+
+    let sooner = schedule(Some(&first), later, Duration::from_secs(60), true, false).unwrap();
+    assert_eq!(sooner.requested_at, now);
+    assert_eq!(sooner.purge_at, later + chrono::Duration::minutes(1));
+    assert!(sooner.purge_index);
+    assert!(sooner.was_paused);
+}
+
+#[tokio::test]
+async fn it_purges_exactly_at_the_boundary() {
+    use std::collections::HashMap;
+
+    use crate::ids::ClusterId;
+    use crate::shards::shard::Shard;
+    use crate::shards::store::MemoryDocumentStore;
+    use crate::subscriptions::store::{MemoryPurgeLog, MemorySubscriptionStore};
+
+    let now = Utc::now();
+    let ss = Arc::new(MemorySubscriptionStore::default());
+    let ds = Arc::new(MemoryDocumentStore::default());
+    let purges = Arc::new(MemoryPurgeLog::default());
+
+    let subscription = |id: i64, purge_index: bool| Subscription {
+        pending_deletion: Some(
+            schedule(None, now, DEFAULT_GRACE_PERIOD, purge_index, false).unwrap(),
+        ),
+        ..Subscription::new(
+            Some(SubscriptionId(id)),
+            ClusterId(1),
+            "orders".to_string(),
+            HashMap::new(),
+        )
+    };
+    ss.put(subscription(1, true)).await.unwrap();
+    ss.put(subscription(2, false)).await.unwrap();
+    ss.put(Subscription::new(
+        Some(SubscriptionId(3)),
+        ClusterId(1),
+        "refunds".to_string(),
+        HashMap::new(),
+    ))
+    .await
+    .unwrap();
+    for id in [1, 2] {
+        let shard = Shard::route(SubscriptionId(id), None, 0);
+        ds.put_shard(&shard).await.unwrap();
+    }
+
+    let sweep = DeletionSweep::new(ss.clone(), ds.clone(), purges.clone());
+    let purge_at = now + chrono::Duration::minutes(15);
+
+    let early = purge_at - chrono::Duration::milliseconds(1);
+    assert!(sweep.sweep(early).await.unwrap().is_empty());
+    assert_eq!(ss.list(None, Page::ALL).await.unwrap().len(), 3);
+
+    let purged = sweep.sweep(purge_at).await.unwrap();
+    assert_eq!(purged, vec![SubscriptionId(1), SubscriptionId(2)]);
+    assert_eq!(ss.list(None, Page::ALL).await.unwrap().len(), 1);
+    assert!(purges.purged(SubscriptionId(1)).await.unwrap().is_some());
+    assert!(purges.purged(SubscriptionId(3)).await.unwrap().is_none());
+
+    // Only the index of the subscription deleted with `purge_index` is dropped.
+    assert!(ds.shards(SubscriptionId(1)).await.unwrap().is_empty());
+    assert_eq!(ds.shards(SubscriptionId(2)).await.unwrap().len(), 1);
+}
@@ -1,229 +1,929 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, ResponseError};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-
+use serde_json::json;
+
+use crate::api::list::ListQuery;
+use crate::api::ndjson;
+use crate::assignment::{self, Assignments};
+use crate::auth::reveal::Reveal;
+use crate::auth::Principal;
+use crate::clusters::endpoints::v1::reveal_forbidden;
 use crate::clusters::store::ClusterStore;
-use crate::errors::AnyError;
-use crate::subscriptions::store::SubscriptionStore;
-use crate::subscriptions::subscription::Subscription;
+use crate::commands::store::CommandStore;
+use crate::debug::store::DebugStore;
+use crate::errors::{ApiError, ErrorBody};
+use crate::governance::owner::{Confirmation, Owner};
+use crate::governance::policy::OwnershipPolicy;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::kafka::sensitive;
+use crate::kafka::streams::service::WorkerState;
+use crate::kafka::streams::PayloadFormat;
+use crate::lint::{self, LintPolicy, LintWarning, Subject};
+use crate::shards::tombstone::Tombstones;
+use crate::standby::lease::LeaseStore;
+use crate::subscriptions::deletion::{DEFAULT_GRACE_PERIOD, MAX_GRACE_PERIOD};
+use crate::subscriptions::service::{self, Deletion, SubscriptionError, TopicCheck, Undeletion};
+use crate::subscriptions::store::{PurgeLog, SubscriptionNotFound, SubscriptionStore};
+use crate::subscriptions::subscription::{PendingDeletion, Subscription, SubscriptionState};
+use crate::validate;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(create_subscription)
         .service(get_subscriptions)
         .service(get_subscription)
         .service(update_subscription)
-        .service(delete_subscription);
+        .service(delete_subscription)
+        .service(undelete_subscription)
+        .service(confirm_ownership)
+        .service(relocate_subscription)
+        .service(lint_subscription);
 }
 
 #[post("")]
 async fn create_subscription(
     r: web::Json<CreateSubscriptionRequest>,
+    query: web::Query<WriteSubscriptionQuery>,
+    principal: Principal,
+    lints: LintPolicy,
     cs: web::Data<Arc<dyn ClusterStore + Send + Sync>>,
     ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
-) -> impl Responder {
+    manager: web::Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
     info!("Creating a new subscription");
 
-    let result = cluster_exist(r.cluster_id, cs).await;
-    if result.is_err() {
-        return HttpResponse::InternalServerError().body(result.unwrap_err().to_string());
+    let r = r.into_inner();
+    if !principal.can_access(r.cluster_id) {
+        return Err(SubscriptionError::ClusterNotFound(r.cluster_id).into());
     }
-
-    if result.unwrap() == false {
-        return HttpResponse::NotFound()
-            .body(format!("Cluster with id '{}' not found", r.cluster_id));
+    validate_request(&r.topic_name, &r.config, r.owner.as_ref())?;
+    if !query.force {
+        if let Some(res) = topic_response(&manager, r.cluster_id, &r.topic_name).await {
+            return Ok(res);
+        }
     }
 
-    let subscription =
-        Subscription::new(None, r.cluster_id, r.topic_name.clone(), r.config.clone());
-
-    match ss.insert(subscription).await {
-        Ok(id) => HttpResponse::Ok().json(CreateSubscriptionResponse { id }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+    let candidate = Subscription::new(None, r.cluster_id, r.topic_name.clone(), r.config.clone());
+    let warnings = lint::lint_subscription(cs.as_ref().as_ref(), &candidate).await?;
+    lints.check(&warnings).map_err(ApiError::invalid)?;
+
+    let id = service::create(
+        cs.as_ref().as_ref(),
+        ss.as_ref().as_ref(),
+        r.cluster_id,
+        r.topic_name,
+        r.config,
+        r.owner,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CreateSubscriptionResponse { id, warnings }))
 }
 
 #[get("/{cluster_id}")]
 async fn get_subscriptions(
-    path: web::Path<i64>,
+    req: HttpRequest,
+    path: web::Path<ClusterId>,
+    query: web::Query<ListSubscriptionsQuery>,
+    list: ListQuery<Subscription>,
+    policy: OwnershipPolicy,
     cs: web::Data<Arc<dyn ClusterStore + Send + Sync>>,
     ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let cluster_id = path.into_inner();
     info!(
         "Listing all subscriptions in cluster with id {}",
         cluster_id
     );
 
-    let result = cluster_exist(cluster_id, cs).await;
-    if result.is_err() {
-        return HttpResponse::InternalServerError().body(result.unwrap_err().to_string());
+    // The store pages the subscriptions itself, unless some are left out or reordered first.
+    let (cs, ss) = (cs.as_ref().as_ref(), ss.as_ref().as_ref());
+    if list.is_given()
+        && list.is_paging_only()
+        && query.team.is_none()
+        && query.include_pending_deletion
+    {
+        let (subscriptions, total) = service::page(cs, ss, cluster_id, list.window()).await?;
+        let page = list.paged(subscriptions, total);
+        if ndjson::accepts(&req) {
+            let lines = page.items.into_iter().map(move |s| s.to_summary(&policy));
+            return Ok(ndjson::stream(lines));
+        }
+        return Ok(HttpResponse::Ok().json(page.map(|s| s.to_summary(&policy))));
     }
 
-    if result.unwrap() == false {
-        return HttpResponse::NotFound()
-            .body(format!("Cluster with id '{}' not found", cluster_id));
-    }
+    let result = service::list(
+        cs,
+        ss,
+        cluster_id,
+        query.team.as_deref(),
+        query.include_pending_deletion,
+    );
 
-    match ss.list(Some(cluster_id)).await {
-        Ok(subscriptions) => {
-            let subscriptions = subscriptions
-                .iter()
-                .map(|c| c.to_summary())
-                .collect::<Vec<SubscriptionSummery>>();
-            HttpResponse::Ok().json(ListSubscriptionsResponse { subscriptions })
-        }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    let listing = result.await?;
+    let page = list.apply(listing.subscriptions);
+
+    if ndjson::accepts(&req) {
+        let lines = page.items.into_iter().map(move |s| s.to_summary(&policy));
+        return Ok(ndjson::stream(lines));
     }
+    Ok(match list.is_given() {
+        true => HttpResponse::Ok().json(page.map(|s| s.to_summary(&policy))),
+        false => HttpResponse::Ok().json(ListSubscriptionsResponse {
+            subscriptions: page.items.iter().map(|s| s.to_summary(&policy)).collect(),
+            pending_deletion: listing.pending_deletion,
+        }),
+    })
 }
 
 #[get("/{cluster_id}/{id}")]
 async fn get_subscription(
-    path: web::Path<(i64, i64)>,
+    path: web::Path<(ClusterId, SubscriptionId)>,
+    policy: OwnershipPolicy,
+    reveal: Reveal,
     cs: web::Data<Arc<dyn ClusterStore + Send + Sync>>,
     ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
-) -> impl Responder {
+    ds: Option<web::Data<Arc<dyn DebugStore + Send + Sync>>>,
+) -> Result<HttpResponse, ApiError> {
     let (cluster_id, id) = path.into_inner();
     info!(
         "Fetching subscription from cluster id {} with id {}",
         cluster_id, id
     );
 
-    let result = cluster_exist(cluster_id, cs).await;
-    if result.is_err() {
-        return HttpResponse::InternalServerError().body(result.unwrap_err().to_string());
+    if reveal == Reveal::Denied {
+        return Ok(HttpResponse::Forbidden().json(reveal_forbidden()));
     }
-
-    if result.unwrap() == false {
-        return HttpResponse::NotFound()
-            .body(format!("Cluster with id '{}' not found", cluster_id));
+    let s = service::get(cs.as_ref().as_ref(), ss.as_ref().as_ref(), cluster_id, id)
+        .await?
+        .ok_or_else(|| not_found(cluster_id, id))?;
+
+    // The indexer stops the workers of paused subscriptions, the others
+    // publish their state along with their stage timings.
+    let worker_status = match (s.state, ds) {
+        (SubscriptionState::Paused, _) => Some(WorkerState::Paused),
+        (SubscriptionState::Active, Some(ds)) => ds.stages(id).await?.and_then(|r| r.state),
+        (SubscriptionState::Active, None) => None,
+    };
+    let mut subscription = s.to_summary(&policy);
+    if reveal == Reveal::Granted {
+        info!("Revealing the config secrets of subscription {}", id);
+        subscription.config = s.config;
     }
+    Ok(HttpResponse::Ok().json(ReadSubscriptionResponse {
+        subscription,
+        worker_status,
+    }))
+}
+
+#[get("/{cluster_id}/{id}/lint")]
+async fn lint_subscription(
+    path: web::Path<(ClusterId, SubscriptionId)>,
+    cs: web::Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Linting subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
 
-    match ss.get(cluster_id, id).await {
-        Ok(subscription) => {
-            let Some(s) = subscription else {
-				return HttpResponse::NotFound().finish();
-			};
+    let subscription = service::get(cs.as_ref().as_ref(), ss.as_ref().as_ref(), cluster_id, id)
+        .await?
+        .ok_or_else(|| not_found(cluster_id, id))?;
 
-            HttpResponse::Ok().json(ReadSubscriptionResponse {
-                subscription: s.to_summary(),
-            })
-        }
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
-    }
+    let warnings = lint::lint_subscription(cs.as_ref().as_ref(), &subscription).await?;
+    Ok(HttpResponse::Ok().json(LintSubscriptionResponse { id, warnings }))
 }
 
 #[put("/{cluster_id}/{id}")]
 async fn update_subscription(
-    path: web::Path<(i64, i64)>,
+    path: web::Path<(ClusterId, SubscriptionId)>,
     r: web::Json<UpdateSubscriptionRequest>,
+    query: web::Query<WriteSubscriptionQuery>,
+    lints: LintPolicy,
     cs: web::Data<Arc<dyn ClusterStore + Send + Sync>>,
     ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
-) -> impl Responder {
+    manager: web::Data<MetadataManager>,
+) -> Result<HttpResponse, ApiError> {
     let (cluster_id, id) = path.into_inner();
     info!(
         "Updating subscription from cluster id {} with id {}",
         cluster_id, id
     );
 
-    let result = cluster_exist(cluster_id, cs).await;
-    if result.is_err() {
-        return HttpResponse::InternalServerError().body(result.unwrap_err().to_string());
+    let r = r.into_inner();
+    validate_request(&r.topic_name, &r.config, r.owner.as_ref())?;
+    if !query.force {
+        if let Some(res) = topic_response(&manager, cluster_id, &r.topic_name).await {
+            return Ok(res);
+        }
     }
 
-    if result.unwrap() == false {
-        return HttpResponse::NotFound()
-            .body(format!("Cluster with id '{}' not found", cluster_id));
+    let candidate = Subscription::new(Some(id), cluster_id, r.topic_name.clone(), r.config.clone());
+    let warnings = lint::lint_subscription(cs.as_ref().as_ref(), &candidate).await?;
+    lints.check(&warnings).map_err(ApiError::invalid)?;
+
+    let id = service::update(
+        cs.as_ref().as_ref(),
+        ss.as_ref().as_ref(),
+        cluster_id,
+        id,
+        r.topic_name,
+        r.config,
+        r.owner,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(UpdateSubscriptionResponse { id, warnings }))
+}
+
+#[delete("/{cluster_id}/{id}")]
+async fn delete_subscription(
+    path: web::Path<(ClusterId, SubscriptionId)>,
+    query: web::Query<DeleteSubscriptionQuery>,
+    ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: web::Data<Arc<dyn CommandStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Deleting subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    let grace = query
+        .grace_period
+        .map_or(DEFAULT_GRACE_PERIOD, Duration::from_secs);
+    if grace > MAX_GRACE_PERIOD {
+        return Err(ApiError::invalid(format!(
+            "grace_period must be at most {} seconds",
+            MAX_GRACE_PERIOD.as_secs()
+        )));
     }
 
-    let subscription =
-        Subscription::new(Some(id), cluster_id, r.topic_name.clone(), r.config.clone());
+    let result = service::delete(
+        ss.as_ref().as_ref(),
+        qs.as_ref(),
+        cluster_id,
+        id,
+        grace,
+        query.purge_index,
+    );
 
-    match ss.update(subscription).await {
-        Ok(id) => HttpResponse::Ok().json(UpdateSubscriptionResponse { id }),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    match result.await? {
+        Deletion::Pending(pending) => Ok(HttpResponse::Ok().json(DeleteSubscriptionResponse {
+            id,
+            purge_at: pending.purge_at,
+        })),
+        Deletion::NotFound => Err(not_found(cluster_id, id)),
     }
 }
 
-#[delete("/{cluster_id}/{id}")]
-async fn delete_subscription(
-    path: web::Path<(i64, i64)>,
+#[post("/{cluster_id}/{id}/undelete")]
+async fn undelete_subscription(
+    path: web::Path<(ClusterId, SubscriptionId)>,
+    policy: OwnershipPolicy,
     ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
-) -> impl Responder {
+    qs: web::Data<Arc<dyn CommandStore + Send + Sync>>,
+    purges: web::Data<Arc<dyn PurgeLog + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
     let (cluster_id, id) = path.into_inner();
     info!(
-        "Deleting subscription from cluster id {} with id {}",
+        "Undeleting subscription from cluster id {} with id {}",
         cluster_id, id
     );
 
-    match ss.remove(cluster_id, id).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    let result = service::undelete(
+        ss.as_ref().as_ref(),
+        qs.as_ref(),
+        purges.as_ref().as_ref(),
+        cluster_id,
+        id,
+    );
+
+    match result.await? {
+        Undeletion::Restored(s) => Ok(HttpResponse::Ok().json(ReadSubscriptionResponse {
+            subscription: s.to_summary(&policy),
+        })),
+        Undeletion::NotPending => Err(ApiError::conflict(format!(
+            "Subscription with id '{}' is not pending deletion",
+            id
+        ))),
+        Undeletion::Purged => {
+            let message = format!("Subscription with id '{}' has been purged", id);
+            Ok(HttpResponse::Gone().json(ErrorBody::new("purged", message)))
+        }
+        Undeletion::NotFound => Err(not_found(cluster_id, id)),
     }
 }
 
-async fn cluster_exist(
-    cluster_id: i64,
+#[post("/{cluster_id}/{id}/confirm-ownership")]
+async fn confirm_ownership(
+    path: web::Path<(ClusterId, SubscriptionId)>,
     cs: web::Data<Arc<dyn ClusterStore + Send + Sync>>,
-) -> Result<bool, AnyError> {
-    let cluster = cs.get(cluster_id).await?;
-    Ok(cluster.is_some())
+    ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Confirming ownership of subscription from cluster id {} with id {}",
+        cluster_id, id
+    );
+
+    let result =
+        service::confirm_ownership(cs.as_ref().as_ref(), ss.as_ref().as_ref(), cluster_id, id);
+
+    match result.await? {
+        Confirmation::Confirmed(at) => Ok(HttpResponse::Ok().json(ConfirmOwnershipResponse {
+            id,
+            ownership_confirmed_at: at,
+        })),
+        Confirmation::Unowned => Err(ApiError::conflict(format!(
+            "Subscription with id '{}' has no owner to confirm",
+            id
+        ))),
+        Confirmation::NotFound => Err(not_found(cluster_id, id)),
+    }
+}
+
+#[post("/{cluster_id}/{id}/relocate")]
+async fn relocate_subscription(
+    path: web::Path<(ClusterId, SubscriptionId)>,
+    r: web::Json<RelocateSubscriptionRequest>,
+    principal: Principal,
+    ss: web::Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    leases: web::Data<Arc<dyn LeaseStore + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let (cluster_id, id) = path.into_inner();
+    info!(
+        "Relocating subscription from cluster id {} with id {} to {}",
+        cluster_id, id, r.target_instance
+    );
+
+    if !principal.is_admin() {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    if ss.get(cluster_id, id).await?.is_none() {
+        return Err(not_found(cluster_id, id));
+    }
+
+    let assignments = Assignments::load(leases.get_ref().as_ref()).await?;
+    if !assignments.instances.contains(&r.target_instance) {
+        return Err(ApiError::invalid(format!(
+            "No live indexer instance '{}'",
+            r.target_instance
+        )));
+    }
+
+    // The schedulers move the subscription on their next reconcile, the
+    // current owner stops it before the target starts it.
+    let preference = assignment::preference(id, &r.target_instance);
+    leases.put(&preference).await?;
+
+    Ok(HttpResponse::Accepted().json(RelocateSubscriptionResponse {
+        id,
+        owner: assignments.owner(id).map(|l| l.holder.clone()),
+        target_instance: preference.holder,
+    }))
+}
+
+/// Check the fields of a subscription to be created or updated.
+fn validate_request(
+    topic_name: &str,
+    config: &HashMap<String, String>,
+    owner: Option<&Owner>,
+) -> Result<(), ApiError> {
+    validate::subscription(topic_name, config)?;
+    if let Some(owner) = owner {
+        owner.validate().map_err(ApiError::invalid)?;
+    }
+    Tombstones::of(config).map_err(ApiError::invalid)?;
+    PayloadFormat::of(config).map_err(ApiError::invalid)?;
+    Ok(())
+}
+
+/// The response refusing a subscription to a topic that isn't on its cluster,
+/// or whose cluster's metadata doesn't tell yet.
+async fn topic_response(
+    manager: &MetadataManager,
+    cluster_id: ClusterId,
+    topic: &str,
+) -> Option<HttpResponse> {
+    match service::check_topic(manager, cluster_id, topic).await {
+        TopicCheck::Exists => None,
+        TopicCheck::Missing(close_matches) => {
+            let message = format!(
+                "Topic '{}' not found on cluster with id '{}', pass force=true if it's created later",
+                topic, cluster_id
+            );
+            let body = ErrorBody::new("topic_not_found", message)
+                .with_details(json!({ "close_matches": close_matches }));
+            Some(HttpResponse::UnprocessableEntity().json(body))
+        }
+        TopicCheck::Unavailable(message) => Some(ApiError::unavailable(message).error_response()),
+    }
+}
+
+fn not_found(cluster_id: ClusterId, id: SubscriptionId) -> ApiError {
+    SubscriptionNotFound(cluster_id, id).into()
+}
+
+#[derive(Deserialize)]
+struct RelocateSubscriptionRequest {
+    target_instance: String,
+}
+
+#[derive(Serialize)]
+struct RelocateSubscriptionResponse {
+    id: SubscriptionId,
+
+    /// The instance running the subscription until the target takes over.
+    owner: Option<String>,
+    target_instance: String,
 }
 
 #[derive(Deserialize)]
 struct CreateSubscriptionRequest {
-    cluster_id: i64,
+    cluster_id: ClusterId,
     topic_name: String,
     config: HashMap<String, String>,
+    #[serde(default)]
+    owner: Option<Owner>,
+}
+
+#[derive(Deserialize)]
+struct WriteSubscriptionQuery {
+    /// Skip checking that the topic exists, for topics created later.
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Serialize)]
 struct CreateSubscriptionResponse {
-    id: i64,
+    id: SubscriptionId,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<LintWarning>,
+}
+
+#[derive(Deserialize)]
+struct ListSubscriptionsQuery {
+    team: Option<String>,
+    #[serde(default)]
+    include_pending_deletion: bool,
 }
 
 #[derive(Serialize)]
 struct ListSubscriptionsResponse {
     subscriptions: Vec<SubscriptionSummery>,
+    pending_deletion: usize,
+}
+
+#[derive(Deserialize)]
+struct DeleteSubscriptionQuery {
+    /// Seconds the delete can be undone for.
+    grace_period: Option<u64>,
+    #[serde(default)]
+    purge_index: bool,
+}
+
+#[derive(Serialize)]
+struct DeleteSubscriptionResponse {
+    id: SubscriptionId,
+    purge_at: DateTime<Utc>,
 }
 
 #[derive(Serialize)]
 struct ReadSubscriptionResponse {
     subscription: SubscriptionSummery,
+
+    /// The state of the subscription's worker, unknown until it published one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    worker_status: Option<WorkerState>,
 }
 
 #[derive(Deserialize)]
 struct UpdateSubscriptionRequest {
     topic_name: String,
     config: HashMap<String, String>,
+    #[serde(default)]
+    owner: Option<Owner>,
 }
 
 #[derive(Serialize)]
 struct UpdateSubscriptionResponse {
-    id: i64,
+    id: SubscriptionId,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<LintWarning>,
 }
 
 #[derive(Serialize)]
-struct SubscriptionSummery {
-    id: i64,
-    cluster_id: i64,
+struct LintSubscriptionResponse {
+    id: SubscriptionId,
+    warnings: Vec<LintWarning>,
+}
+
+#[derive(Serialize)]
+struct ConfirmOwnershipResponse {
+    id: SubscriptionId,
+    ownership_confirmed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SubscriptionSummery {
+    id: SubscriptionId,
+    cluster_id: ClusterId,
     topic_name: String,
     config: HashMap<String, String>,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<Owner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ownership_confirmed_at: Option<DateTime<Utc>>,
+    ownership_stale: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pending_deletion: Option<PendingDeletion>,
+    #[serde(skip_serializing_if = "SubscriptionState::is_active")]
+    state: SubscriptionState,
+
+    /// Warnings of the rules that don't need the cluster, see the lint endpoint for all.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<LintWarning>,
 }
 
 impl Subscription {
-    fn to_summary(&self) -> SubscriptionSummery {
+    pub(crate) fn to_summary(&self, policy: &OwnershipPolicy) -> SubscriptionSummery {
+        let stale = policy.stale(self.owner.as_ref(), self.ownership_confirmed_at, Utc::now());
+
         SubscriptionSummery {
             id: self.id,
             cluster_id: self.cluster_id,
             topic_name: self.topic_name.clone(),
-            config: self.config.clone(),
+            config: sensitive::redact(&self.config),
             created_at: self.created_at,
             updated_at: self.updated_at,
+            owner: self.owner.clone(),
+            ownership_confirmed_at: self.ownership_confirmed_at,
+            ownership_stale: stale.is_some(),
+            pending_deletion: self.pending_deletion.clone(),
+            state: self.state,
+            warnings: lint::lint(Subject::Subscription(self, None)),
         }
     }
 }
+
+#[actix_web::test]
+async fn it_defers_deletes_behind_an_undo_window() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::commands::command::{CommandKind, CommandStatus};
+    use crate::commands::enqueue;
+    use crate::commands::store::MemoryCommandStore;
+    use crate::shards::store::MemoryDocumentStore;
+    use crate::subscriptions::deletion::DeletionSweep;
+    use crate::subscriptions::store::{MemoryPurgeLog, MemorySubscriptionStore};
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let qs: Arc<dyn CommandStore + Send + Sync> = Arc::new(MemoryCommandStore::default());
+    let purges: Arc<dyn PurgeLog + Send + Sync> = Arc::new(MemoryPurgeLog::default());
+
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "local".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+    for (id, topic) in [(1, "orders"), (2, "refunds")] {
+        let subscription = Subscription::new(
+            Some(SubscriptionId(id)),
+            ClusterId(1),
+            topic.to_string(),
+            HashMap::new(),
+        );
+        ss.put(subscription).await.unwrap();
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(cs))
+            .app_data(web::Data::new(ss.clone()))
+            .app_data(web::Data::new(qs.clone()))
+            .app_data(web::Data::new(purges.clone()))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let call = |req: test::TestRequest| test::call_service(&app, req.to_request());
+    let kinds = |id: i64| {
+        let qs = qs.clone();
+        async move {
+            let commands = qs.list(SubscriptionId(id), 10).await.unwrap();
+            commands.into_iter().map(|c| c.kind).collect::<Vec<_>>()
+        }
+    };
+    let list = |query: &str| {
+        let req = test::TestRequest::get().uri(&format!("/api/v1/subscriptions/1{}", query));
+        test::call_and_read_body_json::<_, _, Value>(&app, req.to_request())
+    };
+
+    let req = test::TestRequest::get().uri("/api/v1/subscriptions/1/1");
+    let original: Value = test::read_body_json(call(req).await).await;
+
+    let req = test::TestRequest::delete().uri("/api/v1/subscriptions/1/1?grace_period=86401");
+    assert_eq!(call(req).await.status(), StatusCode::BAD_REQUEST);
+
+    // Deleting pauses the worker and hides the subscription from default listings.
+    let req = test::TestRequest::delete().uri("/api/v1/subscriptions/1/1?grace_period=3600");
+    let deleted: Value = test::read_body_json(call(req).await).await;
+    assert_eq!(deleted["id"], 1);
+    assert_eq!(kinds(1).await, vec![CommandKind::Pause]);
+
+    let body = list("").await;
+    assert_eq!(body["subscriptions"].as_array().unwrap().len(), 1);
+    assert_eq!(body["subscriptions"][0]["id"], 2);
+    assert_eq!(body["pending_deletion"], 1);
+    let body = list("?include_pending_deletion=true").await;
+    assert_eq!(body["subscriptions"].as_array().unwrap().len(), 2);
+    assert_eq!(body["pending_deletion"], 1);
+
+    // Deleting again never postpones the purge.
+    let req = test::TestRequest::delete().uri("/api/v1/subscriptions/1/1?grace_period=86400");
+    let again: Value = test::read_body_json(call(req).await).await;
+    assert_eq!(again["purge_at"], deleted["purge_at"]);
+    assert_eq!(kinds(1).await, vec![CommandKind::Pause]);
+
+    // Undeleting restores the same subscription, and resumes its worker.
+    let req = test::TestRequest::post().uri("/api/v1/subscriptions/1/1/undelete");
+    let restored: Value = test::read_body_json(call(req).await).await;
+    assert_eq!(restored, original);
+    assert_eq!(
+        kinds(1).await,
+        vec![CommandKind::Resume, CommandKind::Pause]
+    );
+    let req = test::TestRequest::post().uri("/api/v1/subscriptions/1/1/undelete");
+    assert_eq!(call(req).await.status(), StatusCode::CONFLICT);
+
+    // A worker paused before the delete stays paused.
+    let pause = enqueue(
+        qs.as_ref(),
+        SubscriptionId(2),
+        CommandKind::Pause,
+        Value::Null,
+    )
+    .await
+    .unwrap();
+    qs.complete(pause.complete(CommandStatus::Acknowledged, None, Utc::now()))
+        .await
+        .unwrap();
+    let req = test::TestRequest::delete().uri("/api/v1/subscriptions/1/2");
+    assert_eq!(call(req).await.status(), StatusCode::OK);
+    assert_eq!(kinds(2).await, vec![CommandKind::Pause]);
+
+    // Once purged, the subscription is gone for good.
+    let sweep = DeletionSweep::new(ss.clone(), Arc::new(MemoryDocumentStore::default()), purges);
+    let purged = sweep
+        .sweep(Utc::now() + chrono::Duration::days(1))
+        .await
+        .unwrap();
+    assert_eq!(purged, vec![SubscriptionId(2)]);
+
+    let req = test::TestRequest::post().uri("/api/v1/subscriptions/1/2/undelete");
+    assert_eq!(call(req).await.status(), StatusCode::GONE);
+    let req = test::TestRequest::get().uri("/api/v1/subscriptions/1/2");
+    assert_eq!(call(req).await.status(), StatusCode::NOT_FOUND);
+    let req = test::TestRequest::post().uri("/api/v1/subscriptions/1/9/undelete");
+    assert_eq!(call(req).await.status(), StatusCode::NOT_FOUND);
+    assert_eq!(kinds(2).await, vec![CommandKind::Pause]);
+}
+
+#[actix_web::test]
+async fn it_rejects_deleting_tombstones_of_offset_documents() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "local".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(cs.clone()))
+            .app_data(web::Data::new(ss.clone()))
+            .app_data(manager(cs, Some(&["orders"])).await)
+            .configure(crate::server::routes),
+    )
+    .await;
+    let create = |config: serde_json::Value| {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/subscriptions")
+            .set_json(serde_json::json!({
+                "cluster_id": 1,
+                "topic_name": "orders",
+                "config": config,
+            }));
+        test::call_service(&app, req.to_request())
+    };
+
+    let res = create(serde_json::json!({ "tombstone.action": "delete" })).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "code": "invalid_request",
+            "message": "tombstone.action delete needs document.id key",
+        })
+    );
+
+    let res = create(serde_json::json!({
+        "tombstone.action": "delete",
+        "document.id": "key",
+    }))
+    .await;
+    assert!(res.status().is_success());
+}
+
+/// A manager caching the metadata of cluster 1 with `topics`, or still
+/// processing it without.
+#[cfg(test)]
+async fn manager(
+    cs: Arc<dyn ClusterStore + Send + Sync>,
+    topics: Option<&[&str]>,
+) -> web::Data<MetadataManager> {
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataConsumerFactory};
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = MetadataManager::with_factory(cs, factory);
+    let entry = match topics {
+        Some(topics) => {
+            let topics = topics.iter().map(|&t| (t, 1)).collect::<Vec<_>>();
+            CachedMetadataEntry::Meta(metadata(&[1], &topics))
+        }
+        None => CachedMetadataEntry::Processing,
+    };
+    manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            entries: vec![SyncedEntry {
+                cluster_id: ClusterId(1),
+                entry,
+                offsets: None,
+            }],
+            clusters: vec![ClusterId(1)],
+        })
+        .await;
+    web::Data::new(manager)
+}
+
+#[actix_web::test]
+async fn it_checks_that_topics_exist_on_the_cluster() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "local".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+
+    let topics = ["orders", "orders-v2", "payments"];
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(cs.clone()))
+            .app_data(web::Data::new(ss.clone()))
+            .app_data(manager(cs.clone(), Some(&topics)).await)
+            .configure(crate::server::routes),
+    )
+    .await;
+    let create = |uri: &str, topic: &str| {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_json(json!({ "cluster_id": 1, "topic_name": topic, "config": {} }));
+        test::call_service(&app, req.to_request())
+    };
+
+    let res = create("/api/v1/subscriptions", "order").await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "topic_not_found");
+    assert_eq!(
+        body["details"]["close_matches"],
+        json!(["orders", "orders-v2"])
+    );
+    assert!(ss
+        .list(None, crate::page::Page::ALL)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let res = create("/api/v1/subscriptions", "orders").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = create("/api/v1/subscriptions?force=true", "refunds").await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let update = |uri: &str| {
+        let req = test::TestRequest::put()
+            .uri(uri)
+            .set_json(json!({ "topic_name": "invoices", "config": {} }));
+        test::call_service(&app, req.to_request())
+    };
+    let res = update("/api/v1/subscriptions/1/1").await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["details"]["close_matches"], json!([]));
+
+    // Without metadata there's no telling, so nothing is created.
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(cs.clone()))
+            .app_data(web::Data::new(ss.clone()))
+            .app_data(manager(cs, None).await)
+            .configure(crate::server::routes),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/subscriptions")
+        .set_json(json!({ "cluster_id": 1, "topic_name": "orders", "config": {} }));
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "unavailable");
+    assert_eq!(
+        body["message"],
+        "Cluster metadata with id '1' is still processing, unable to check topic 'orders'"
+    );
+}
+
+#[actix_web::test]
+async fn it_answers_not_found_for_subscriptions_missing_from_meilisearch() {
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::subscriptions::store::MSSubscriptionStore;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+    let (config, meilisearch) = crate::meilisearch::missing();
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> =
+        Arc::new(MSSubscriptionStore::new(config.client(), crate::id::generator()).await);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(cs))
+            .app_data(web::Data::new(ss))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/subscriptions/1/42")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let error: Value = test::read_body_json(res).await;
+    assert_eq!(error["code"], "not_found");
+
+    meilisearch.stop(true).await;
+}
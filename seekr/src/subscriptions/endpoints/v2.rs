@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Json, Path, Query, ServiceConfig};
+use actix_web::{delete, get, post, put, HttpResponse, Responder};
+use chrono::Utc;
+use seekr_api_types::subscriptions::{
+    CreateSubscriptionRequest, IdResponse, ListSubscriptionsQuery, ListSubscriptionsResponse,
+    PatchSubscriptionRequest, PendingDeletionResource, ReadSubscriptionResponse,
+    SubscriptionResource, UpdateSubscriptionRequest,
+};
+use serde::Deserialize;
+
+use crate::api::error;
+use crate::api::list::{ListError, ListQuery};
+use crate::auth::Principal;
+use crate::clusters::store::ClusterStore;
+use crate::commands::store::CommandStore;
+use crate::governance::owner::Owner;
+use crate::governance::policy::OwnershipPolicy;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::sensitive;
+use crate::kafka::streams::PayloadFormat;
+use crate::lint::{self, LintPolicy};
+use crate::shards::tombstone::Tombstones;
+use crate::subscriptions::deletion::{DEFAULT_GRACE_PERIOD, MAX_GRACE_PERIOD};
+use crate::subscriptions::service::{self, Deletion, SubscriptionError, Undeletion};
+use crate::subscriptions::store::{PurgeLog, SubscriptionStore};
+use crate::subscriptions::subscription::Subscription;
+use crate::validate;
+
+// The handlers of `api/v2/subscriptions`, whose operations are shared with the
+// subscriptions nested under `api/v2/clusters/{cluster_id}`.
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(create_subscription)
+        .service(get_subscriptions)
+        .service(get_subscription)
+        .service(update_subscription)
+        .service(delete_subscription)
+        .service(undelete_subscription);
+}
+
+#[post("")]
+async fn create_subscription(
+    r: Json<CreateSubscriptionRequest>,
+    principal: Principal,
+    lints: LintPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let r = r.into_inner();
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+
+    match create(
+        &principal,
+        &lints,
+        cs,
+        ss,
+        r.cluster_id,
+        r.topic_name,
+        r.config,
+        r.owner,
+    )
+    .await
+    {
+        Ok(id) => HttpResponse::Created().json(IdResponse { id }),
+        Err(res) => res,
+    }
+}
+
+/// Create a subscription of the cluster once it's valid and passes the lints.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn create(
+    principal: &Principal,
+    lints: &LintPolicy,
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    topic_name: String,
+    config: HashMap<String, String>,
+    owner: Option<Owner>,
+) -> Result<SubscriptionId, HttpResponse> {
+    if !principal.can_access(cluster_id) {
+        return Err(error_response(SubscriptionError::ClusterNotFound(
+            cluster_id,
+        )));
+    }
+    let candidate = Subscription::new(None, cluster_id, topic_name, config);
+    check(lints, cs, &candidate, owner.as_ref()).await?;
+
+    service::create(
+        cs,
+        ss,
+        cluster_id,
+        candidate.topic_name,
+        candidate.config,
+        owner,
+    )
+    .await
+    .map_err(error_response)
+}
+
+#[get("/{cluster_id}")]
+async fn get_subscriptions(
+    path: Path<ClusterId>,
+    query: Query<ListSubscriptionsQuery>,
+    list: Result<ListQuery<Subscription>, ListError>,
+    policy: OwnershipPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+    get_all(path.into_inner(), &query, list, &policy, cs, ss).await
+}
+
+/// The subscriptions of the cluster, paged when the list query asks for it.
+pub(crate) async fn get_all(
+    cluster_id: ClusterId,
+    query: &ListSubscriptionsQuery,
+    list: Result<ListQuery<Subscription>, ListError>,
+    policy: &OwnershipPolicy,
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+) -> HttpResponse {
+    let list = match list {
+        Ok(list) => list,
+        Err(e) => return error::invalid(e.0),
+    };
+    // The store pages the subscriptions itself, unless some are left out or reordered first.
+    if list.is_given()
+        && list.is_paging_only()
+        && query.team.is_none()
+        && query.include_pending_deletion
+    {
+        return match service::page(cs, ss, cluster_id, list.window()).await {
+            Ok((subscriptions, total)) => HttpResponse::Ok().json(
+                list.paged(subscriptions, total)
+                    .map(|s| subscription_resource(&s, policy)),
+            ),
+            Err(e) => error_response(e),
+        };
+    }
+    let result = service::list(
+        cs,
+        ss,
+        cluster_id,
+        query.team.as_deref(),
+        query.include_pending_deletion,
+    );
+
+    let listing = match result.await {
+        Ok(listing) => listing,
+        Err(e) => return error_response(e),
+    };
+    let page = list.apply(listing.subscriptions);
+
+    match list.is_given() {
+        true => HttpResponse::Ok().json(page.map(|s| subscription_resource(&s, policy))),
+        false => HttpResponse::Ok().json(ListSubscriptionsResponse {
+            subscriptions: page
+                .items
+                .iter()
+                .map(|s| subscription_resource(s, policy))
+                .collect(),
+            pending_deletion: listing.pending_deletion,
+        }),
+    }
+}
+
+#[get("/{cluster_id}/{id}")]
+async fn get_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    policy: OwnershipPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    get_one(
+        cluster_id,
+        id,
+        &policy,
+        cs.as_ref().as_ref(),
+        ss.as_ref().as_ref(),
+    )
+    .await
+}
+
+pub(crate) async fn get_one(
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    policy: &OwnershipPolicy,
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+) -> HttpResponse {
+    match service::get(cs, ss, cluster_id, id).await {
+        Ok(Some(s)) => HttpResponse::Ok().json(ReadSubscriptionResponse {
+            subscription: subscription_resource(&s, policy),
+        }),
+        Ok(None) => not_found(id),
+        Err(e) => error_response(e),
+    }
+}
+
+#[put("/{cluster_id}/{id}")]
+async fn update_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    r: Json<UpdateSubscriptionRequest>,
+    lints: LintPolicy,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    let cs = cs.as_ref().as_ref();
+    let ss = ss.as_ref().as_ref();
+    update(cluster_id, id, r.into_inner(), &lints, cs, ss).await
+}
+
+/// Replace the subscription once it's valid and passes the lints.
+pub(crate) async fn update(
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    r: UpdateSubscriptionRequest,
+    lints: &LintPolicy,
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+) -> HttpResponse {
+    let candidate = Subscription::new(Some(id), cluster_id, r.topic_name, r.config);
+    if let Err(res) = check(lints, cs, &candidate, r.owner.as_ref()).await {
+        return res;
+    }
+
+    let result = service::update(
+        cs,
+        ss,
+        cluster_id,
+        id,
+        candidate.topic_name,
+        candidate.config,
+        r.owner,
+    )
+    .await;
+
+    match result {
+        Ok(id) => HttpResponse::Ok().json(IdResponse { id }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Change only the given fields of the subscription, checked as a whole
+/// like a replacement.
+pub(crate) async fn patch(
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    r: PatchSubscriptionRequest,
+    lints: &LintPolicy,
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+) -> HttpResponse {
+    let patched = match service::patched(cs, ss, cluster_id, id, r.topic_name, r.config).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return not_found(id),
+        Err(e) => return error_response(e),
+    };
+
+    let r = UpdateSubscriptionRequest {
+        topic_name: patched.topic_name,
+        config: patched.config,
+        owner: r.owner,
+    };
+    update(cluster_id, id, r, lints, cs, ss).await
+}
+
+/// Check a subscription about to be written, answering why it can't be.
+async fn check(
+    lints: &LintPolicy,
+    cs: &(dyn ClusterStore + Send + Sync),
+    candidate: &Subscription,
+    owner: Option<&Owner>,
+) -> Result<(), HttpResponse> {
+    validate::subscription(&candidate.topic_name, &candidate.config)
+        .map_err(error::invalid_fields)?;
+    if let Some(Err(e)) = owner.map(Owner::validate) {
+        return Err(error::invalid(e));
+    }
+    Tombstones::of(&candidate.config).map_err(error::invalid)?;
+    PayloadFormat::of(&candidate.config).map_err(error::invalid)?;
+
+    let warnings = lint::lint_subscription(cs, candidate)
+        .await
+        .map_err(|e| error_response(e.into()))?;
+    lints.check(&warnings).map_err(error::invalid)
+}
+
+#[delete("/{cluster_id}/{id}")]
+async fn delete_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    query: Query<DeleteSubscriptionQuery>,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    delete(cluster_id, id, &query, ss.as_ref().as_ref(), qs.as_ref()).await
+}
+
+/// Delete the subscription once its grace period is over.
+pub(crate) async fn delete(
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    query: &DeleteSubscriptionQuery,
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    qs: &Arc<dyn CommandStore + Send + Sync>,
+) -> HttpResponse {
+    let grace = query
+        .grace_period
+        .map_or(DEFAULT_GRACE_PERIOD, Duration::from_secs);
+    if grace > MAX_GRACE_PERIOD {
+        return error::invalid(format!(
+            "grace_period must be at most {} seconds",
+            MAX_GRACE_PERIOD.as_secs()
+        ));
+    }
+
+    let result = service::delete(ss, qs, cluster_id, id, grace, query.purge_index);
+
+    match result.await {
+        Ok(Deletion::Pending(_)) => HttpResponse::NoContent().finish(),
+        Ok(Deletion::NotFound) => not_found(id),
+        Err(e) => error_response(e),
+    }
+}
+
+#[post("/{cluster_id}/{id}/undelete")]
+async fn undelete_subscription(
+    path: Path<(ClusterId, SubscriptionId)>,
+    policy: OwnershipPolicy,
+    ss: Data<Arc<dyn SubscriptionStore + Send + Sync>>,
+    qs: Data<Arc<dyn CommandStore + Send + Sync>>,
+    purges: Data<Arc<dyn PurgeLog + Send + Sync>>,
+) -> impl Responder {
+    let (cluster_id, id) = path.into_inner();
+    let result = service::undelete(
+        ss.as_ref().as_ref(),
+        qs.as_ref(),
+        purges.as_ref().as_ref(),
+        cluster_id,
+        id,
+    );
+
+    match result.await {
+        Ok(Undeletion::Restored(s)) => HttpResponse::Ok().json(ReadSubscriptionResponse {
+            subscription: subscription_resource(&s, &policy),
+        }),
+        Ok(Undeletion::NotPending) => error::error(
+            StatusCode::CONFLICT,
+            "not_pending_deletion",
+            format!("Subscription with id '{}' is not pending deletion", id),
+        ),
+        Ok(Undeletion::Purged) => error::error(
+            StatusCode::GONE,
+            "purged",
+            format!("Subscription with id '{}' has been purged", id),
+        ),
+        Ok(Undeletion::NotFound) => not_found(id),
+        Err(e) => error_response(e),
+    }
+}
+
+fn not_found(id: SubscriptionId) -> HttpResponse {
+    error::not_found(format!("Subscription with id '{}' not found", id))
+}
+
+fn error_response(e: SubscriptionError) -> HttpResponse {
+    match e {
+        SubscriptionError::ClusterNotFound(cluster_id) => {
+            error::not_found(format!("Cluster with id '{}' not found", cluster_id))
+        }
+        SubscriptionError::Store(e) => error::internal(e),
+    }
+}
+
+fn subscription_resource(s: &Subscription, policy: &OwnershipPolicy) -> SubscriptionResource {
+    let stale = policy.stale(s.owner.as_ref(), s.ownership_confirmed_at, Utc::now());
+
+    SubscriptionResource {
+        id: s.id,
+        cluster_id: s.cluster_id,
+        topic_name: s.topic_name.clone(),
+        config: sensitive::redact(&s.config),
+        created_at: s.created_at,
+        updated_at: s.updated_at,
+        owner: s.owner.clone(),
+        ownership_confirmed_at: s.ownership_confirmed_at,
+        ownership_stale: stale.is_some(),
+        pending_deletion: s
+            .pending_deletion
+            .as_ref()
+            .map(|d| PendingDeletionResource {
+                requested_at: d.requested_at,
+                purge_at: d.purge_at,
+                purge_index: d.purge_index,
+            }),
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DeleteSubscriptionQuery {
+    grace_period: Option<u64>,
+    #[serde(default)]
+    purge_index: bool,
+}
+
+#[actix_web::test]
+async fn it_pages_subscriptions_without_changing_the_default_listing() {
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::subscriptions::store::MemorySubscriptionStore;
+    use crate::subscriptions::subscription::PendingDeletion;
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let ss: Arc<dyn SubscriptionStore + Send + Sync> = Arc::new(MemorySubscriptionStore::default());
+    let cluster = Cluster::new(None, Kind::Kafka, "c".to_string(), HashMap::new());
+    let cluster_id = cs.insert(cluster).await.unwrap();
+    for (topic, team, deleted) in [
+        ("orders", "payments", false),
+        ("refunds", "payments", true),
+        ("invoices", "billing", false),
+        ("orders.dlq", "payments", false),
+    ] {
+        let subscription = Subscription {
+            owner: Some(Owner::team(team)),
+            pending_deletion: deleted.then(|| PendingDeletion {
+                requested_at: Utc::now(),
+                purge_at: Utc::now(),
+                purge_index: false,
+                was_paused: false,
+            }),
+            ..Subscription::new(None, cluster_id, topic.to_string(), HashMap::new())
+        };
+        ss.insert(subscription).await.unwrap();
+    }
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(ss))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let list = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v2/subscriptions/{}{}", cluster_id, query))
+            .to_request()
+    };
+    let topics = |items: &Value| {
+        items
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["topic_name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, list("")).await;
+    assert_eq!(
+        topics(&body["subscriptions"]),
+        vec!["orders", "invoices", "orders.dlq"]
+    );
+    assert_eq!(body["pending_deletion"], 1);
+
+    // Parameters from before still apply, ahead of the new ones.
+    let query = "?team=payments&include_pending_deletion=true";
+    let before: Value = test::call_and_read_body_json(&app, list(query)).await;
+    assert_eq!(
+        topics(&before["subscriptions"]),
+        vec!["orders", "refunds", "orders.dlq"]
+    );
+    let query = "?include_pending_deletion=true&filter=team:eq:payments";
+    let after: Value = test::call_and_read_body_json(&app, list(query)).await;
+    assert_eq!(topics(&after["items"]), topics(&before["subscriptions"]));
+
+    let query = "?team=payments&filter=topic_name:prefix:orders&sort=topic_name:desc";
+    let body: Value = test::call_and_read_body_json(&app, list(query)).await;
+    assert_eq!(topics(&body["items"]), vec!["orders.dlq", "orders"]);
+    assert_eq!(body["total_estimate"], 2);
+
+    // Only paging every subscription is left to the store, pending or not.
+    let query = "?include_pending_deletion=true&limit=2&offset=1";
+    let body: Value = test::call_and_read_body_json(&app, list(query)).await;
+    assert_eq!(topics(&body["items"]), vec!["refunds", "invoices"]);
+    assert_eq!(body["total"], 4);
+    assert!(body["next_cursor"].is_string());
+    let body: Value = test::call_and_read_body_json(&app, list("?limit=2")).await;
+    assert_eq!(topics(&body["items"]), vec!["orders", "invoices"]);
+    assert_eq!(body["total"], 3);
+
+    let res = test::call_service(&app, list("?filter=topic_name:gt:a")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["error"]["code"], "invalid_request");
+    assert_eq!(
+        body["error"]["message"],
+        "cannot filter 'topic_name' with 'gt', expected one of: eq, ne, prefix, gte, lte"
+    );
+}
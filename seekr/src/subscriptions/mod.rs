@@ -1,3 +1,5 @@
+pub mod deletion;
 pub mod endpoints;
+pub mod service;
 pub mod store;
 pub mod subscription;
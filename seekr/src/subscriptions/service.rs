@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::clusters::store::ClusterStore;
+use crate::commands::command::{Command, CommandKind};
+use crate::commands::store::CommandStore;
+use crate::commands::{self, enqueue};
+use crate::errors::AnyError;
+use crate::governance::owner::{self, Confirmation, Owner};
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataManager};
+use crate::kafka::sensitive;
+use crate::page::Page;
+
+use super::deletion;
+use super::store::{PurgeLog, SubscriptionStore};
+use super::subscription::{PendingDeletion, Subscription, SubscriptionState};
+
+// Version-agnostic subscription operations shared by every API version.
+
+#[derive(Debug)]
+pub enum SubscriptionError {
+    ClusterNotFound(ClusterId),
+    Store(AnyError),
+}
+
+impl From<AnyError> for SubscriptionError {
+    fn from(e: AnyError) -> Self {
+        SubscriptionError::Store(e)
+    }
+}
+
+async fn ensure_cluster(
+    cs: &(dyn ClusterStore + Send + Sync),
+    cluster_id: ClusterId,
+) -> Result<(), SubscriptionError> {
+    match cs.get(cluster_id).await? {
+        Some(_) => Ok(()),
+        None => Err(SubscriptionError::ClusterNotFound(cluster_id)),
+    }
+}
+
+pub async fn create(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    topic_name: String,
+    config: HashMap<String, String>,
+    owner: Option<Owner>,
+) -> Result<SubscriptionId, SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+
+    let owner = owner.and_then(Owner::normalize);
+    let subscription = Subscription {
+        ownership_confirmed_at: owner.as_ref().map(|_| Utc::now()),
+        owner,
+        ..Subscription::new(None, cluster_id, topic_name, config)
+    };
+    Ok(ss.insert(subscription).await?)
+}
+
+/// Whether a subscription's topic exists, as far as its cluster's cached metadata knows.
+#[derive(Debug, PartialEq)]
+pub enum TopicCheck {
+    Exists,
+
+    /// The topic isn't on the cluster, along with the topics named alike.
+    Missing(Vec<String>),
+
+    /// The cluster's metadata isn't cached yet, or its last poll failed.
+    Unavailable(String),
+}
+
+/// Check that `topic` is on the cluster, in the metadata cached for it.
+pub async fn check_topic(
+    manager: &MetadataManager,
+    cluster_id: ClusterId,
+    topic: &str,
+) -> TopicCheck {
+    match manager.snapshot(cluster_id).await.as_deref() {
+        Some(CachedMetadataEntry::Meta(metadata)) => {
+            if metadata.topics.iter().any(|t| t.name == topic) {
+                return TopicCheck::Exists;
+            }
+            let mut alike = metadata
+                .topics
+                .iter()
+                .map(|t| t.name.clone())
+                .filter(|name| name.starts_with(topic) || topic.starts_with(name.as_str()))
+                .collect::<Vec<_>>();
+            alike.sort();
+            TopicCheck::Missing(alike)
+        }
+        Some(CachedMetadataEntry::Failed(e)) => TopicCheck::Unavailable(format!(
+            "Cluster metadata with id '{}' failed to load, unable to check topic '{}': {}",
+            cluster_id, topic, e
+        )),
+        _ => TopicCheck::Unavailable(format!(
+            "Cluster metadata with id '{}' is still processing, unable to check topic '{}'",
+            cluster_id, topic
+        )),
+    }
+}
+
+pub enum Deletion {
+    Pending(PendingDeletion),
+    NotFound,
+}
+
+pub enum Undeletion {
+    Restored(Box<Subscription>),
+    NotPending,
+    Purged,
+    NotFound,
+}
+
+pub enum StateChange {
+    /// The worker was told to follow, and the indexer stops or restarts it.
+    Changed(Command),
+
+    /// The subscription was already in the state.
+    Unchanged(SubscriptionState),
+    NotFound,
+}
+
+pub struct Listing {
+    pub subscriptions: Vec<Subscription>,
+
+    /// How many of the matching subscriptions are pending deletion, listed or not.
+    pub pending_deletion: usize,
+}
+
+/// Every subscription of the cluster, or only those owned by `team`.
+///
+/// Subscriptions pending deletion are left out, unless `include_pending` is set.
+pub async fn list(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    team: Option<&str>,
+    include_pending: bool,
+) -> Result<Listing, SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+
+    let mut subscriptions = ss.list(Some(cluster_id), Page::ALL).await?;
+    if let Some(team) = team {
+        subscriptions.retain(|s| s.owner.as_ref().is_some_and(|o| o.is_team(team)));
+    }
+
+    let pending_deletion = subscriptions
+        .iter()
+        .filter(|s| s.is_pending_deletion())
+        .count();
+    if !include_pending {
+        subscriptions.retain(|s| !s.is_pending_deletion());
+    }
+
+    Ok(Listing {
+        subscriptions,
+        pending_deletion,
+    })
+}
+
+/// A page of every subscription of the cluster, pending deletion or not,
+/// and how many it has across all pages.
+pub async fn page(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    page: Page,
+) -> Result<(Vec<Subscription>, usize), SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+
+    let subscriptions = ss.list(Some(cluster_id), page).await?;
+    Ok((subscriptions, ss.count(Some(cluster_id)).await?))
+}
+
+pub async fn get(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+) -> Result<Option<Subscription>, SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+    Ok(ss.get(cluster_id, id).await?)
+}
+
+/// Replace the subscription, which also re-confirms its owner.
+///
+/// An omitted owner keeps the current one, and an empty one clears it, and
+/// secrets still redacted keep their values.
+pub async fn update(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    topic_name: String,
+    config: HashMap<String, String>,
+    owner: Option<Owner>,
+) -> Result<SubscriptionId, SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+
+    let current = ss.get(cluster_id, id).await?;
+    let pending_deletion = current.as_ref().and_then(|s| s.pending_deletion.clone());
+    let state = current.as_ref().map(|s| s.state).unwrap_or_default();
+    let config = match &current {
+        Some(current) => sensitive::restore(config, &current.config),
+        None => config,
+    };
+    let owner = owner::resolve(current.and_then(|s| s.owner), owner);
+    let subscription = Subscription {
+        ownership_confirmed_at: owner.as_ref().map(|_| Utc::now()),
+        owner,
+        pending_deletion,
+        state,
+        ..Subscription::new(Some(id), cluster_id, topic_name, config)
+    };
+    Ok(ss.update(subscription).await?)
+}
+
+/// The subscription with only the given fields changed, `None` when it
+/// doesn't exist. Config entries are set one by one, and removed when `None`.
+pub async fn patched(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    topic_name: Option<String>,
+    config: HashMap<String, Option<String>>,
+) -> Result<Option<Subscription>, SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+
+    let Some(mut subscription) = ss.get(cluster_id, id).await? else {
+        return Ok(None);
+    };
+    if let Some(topic_name) = topic_name {
+        subscription.topic_name = topic_name;
+    }
+    for (key, value) in config {
+        match value {
+            Some(value) => subscription.config.insert(key, value),
+            None => subscription.config.remove(&key),
+        };
+    }
+    Ok(Some(subscription))
+}
+
+/// Confirm the subscription is still owned by its current owner.
+pub async fn confirm_ownership(
+    cs: &(dyn ClusterStore + Send + Sync),
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+) -> Result<Confirmation, SubscriptionError> {
+    ensure_cluster(cs, cluster_id).await?;
+
+    let Some(subscription) = ss.get(cluster_id, id).await? else {
+        return Ok(Confirmation::NotFound);
+    };
+    if subscription.owner.is_none() {
+        return Ok(Confirmation::Unowned);
+    }
+
+    let now = Utc::now();
+    ss.update(Subscription {
+        ownership_confirmed_at: Some(now),
+        ..subscription
+    })
+    .await?;
+    Ok(Confirmation::Confirmed(now))
+}
+
+/// Delete the subscription once the `grace` period is over, pausing its worker until then.
+///
+/// Deleting it again only ever brings the purge closer.
+pub async fn delete(
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    qs: &Arc<dyn CommandStore + Send + Sync>,
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    grace: Duration,
+    purge_index: bool,
+) -> Result<Deletion, SubscriptionError> {
+    let Some(subscription) = ss.get(cluster_id, id).await? else {
+        return Ok(Deletion::NotFound);
+    };
+
+    let now = Utc::now();
+    let pending = match &subscription.pending_deletion {
+        Some(current) => {
+            deletion::schedule(Some(current), now, grace, purge_index, current.was_paused)?
+        }
+        None => {
+            let was_paused = subscription.state == SubscriptionState::Paused
+                || commands::is_paused(qs.clone(), id).await?;
+            if !was_paused {
+                enqueue(qs.as_ref(), id, CommandKind::Pause, Value::Null).await?;
+            }
+            deletion::schedule(None, now, grace, purge_index, was_paused)?
+        }
+    };
+
+    ss.update(Subscription {
+        pending_deletion: Some(pending.clone()),
+        ..subscription
+    })
+    .await?;
+    Ok(Deletion::Pending(pending))
+}
+
+/// Pause or resume the subscription, telling its worker to follow right
+/// away, while the indexer stops the workers of paused subscriptions for good.
+pub async fn set_state(
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    qs: &Arc<dyn CommandStore + Send + Sync>,
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+    state: SubscriptionState,
+) -> Result<StateChange, SubscriptionError> {
+    let Some(subscription) = ss.get(cluster_id, id).await? else {
+        return Ok(StateChange::NotFound);
+    };
+    if subscription.state == state {
+        return Ok(StateChange::Unchanged(state));
+    }
+
+    ss.update(Subscription {
+        state,
+        ..subscription
+    })
+    .await?;
+
+    let kind = match state {
+        SubscriptionState::Active => CommandKind::Resume,
+        SubscriptionState::Paused => CommandKind::Pause,
+    };
+    let command = enqueue(qs.as_ref(), id, kind, Value::Null).await?;
+    Ok(StateChange::Changed(command))
+}
+
+/// Restore a subscription deleted within its undo window, resuming its worker
+/// unless it was paused before the delete.
+pub async fn undelete(
+    ss: &(dyn SubscriptionStore + Send + Sync),
+    qs: &Arc<dyn CommandStore + Send + Sync>,
+    purges: &(dyn PurgeLog + Send + Sync),
+    cluster_id: ClusterId,
+    id: SubscriptionId,
+) -> Result<Undeletion, SubscriptionError> {
+    let Some(subscription) = ss.get(cluster_id, id).await? else {
+        return Ok(match purges.purged(id).await? {
+            Some(p) if p.cluster_id == cluster_id => Undeletion::Purged,
+            _ => Undeletion::NotFound,
+        });
+    };
+
+    // Past its window, a subscription the sweep hasn't reached yet is as good as gone.
+    if deletion::is_due(&subscription, Utc::now()) {
+        return Ok(Undeletion::Purged);
+    }
+    let Some(pending) = subscription.pending_deletion.clone() else {
+        return Ok(Undeletion::NotPending);
+    };
+
+    let restored = Subscription {
+        pending_deletion: None,
+        ..subscription
+    };
+    ss.update(restored.clone()).await?;
+
+    if !pending.was_paused {
+        enqueue(qs.as_ref(), id, CommandKind::Resume, Value::Null).await?;
+    }
+    Ok(Undeletion::Restored(Box::new(restored)))
+}
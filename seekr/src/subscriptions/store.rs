@@ -1,11 +1,12 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::option::Option;
 use std::result;
 use std::sync::Arc;
 use std::vec::Vec;
 
 use async_trait::async_trait;
-use cdrs_tokio::error::Error;
+use cdrs_tokio::error::Error as CdrsError;
 use cdrs_tokio::frame::Frame;
 use cdrs_tokio::query_values;
 use cdrs_tokio::types::prelude::{Map, Row};
@@ -13,23 +14,69 @@ use cdrs_tokio::types::{AsRustType, ByName};
 use chrono::{DateTime, Utc};
 use meilisearch_sdk::indexes::Index;
 use meilisearch_sdk::Client;
+use serde::{Deserialize, Serialize};
 
+use crate::backend::{StoreBackend, StoreConfig};
 use crate::errors::AnyError;
+use crate::governance::owner;
+use crate::id;
+use crate::ids::{ClusterId, SubscriptionId};
+use crate::meilisearch::{self, MeilisearchConfig};
+use crate::page::{self, Page};
+use crate::schemas;
+use crate::search::{self, Scored, SearchUnsupported};
 use crate::session::CdrsSession;
-use crate::{id, ID_GENERATOR, MS_CLIENT};
 
-use super::subscription::Subscription;
+use super::subscription::{Subscription, SubscriptionState};
 
 #[async_trait]
 pub trait SubscriptionStore {
-    async fn list(&self, cluster_id: Option<i64>) -> Result<Vec<Subscription>, AnyError>;
-    async fn get(&self, cluster_id: i64, id: i64)
-        -> result::Result<Option<Subscription>, AnyError>;
-    async fn insert(&self, subscription: Subscription) -> result::Result<i64, AnyError>;
-    async fn update(&self, subscription: Subscription) -> result::Result<i64, AnyError>;
-    async fn remove(&self, cluster_id: i64, id: i64) -> result::Result<i64, AnyError>;
+    /// The subscriptions of the cluster, or of every cluster, in the page.
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: Page,
+    ) -> Result<Vec<Subscription>, AnyError>;
+    /// How many subscriptions `list` has across all pages.
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError>;
+    /// The best `limit` subscriptions of every cluster matching `q`, best
+    /// first, or `SearchUnsupported` for backends that can't search.
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Subscription>>, AnyError>;
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<Option<Subscription>, AnyError>;
+    async fn insert(&self, subscription: Subscription) -> result::Result<SubscriptionId, AnyError>;
+    async fn update(&self, subscription: Subscription) -> result::Result<SubscriptionId, AnyError>;
+    async fn remove(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<SubscriptionId, AnyError>;
+    /// Write the subscription under its own id whether or not it's stored,
+    /// as seeding does.
+    async fn put(&self, subscription: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        self.update(subscription).await
+    }
 }
 
+/// A subscription that was asked to be removed but isn't in the store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubscriptionNotFound(pub ClusterId, pub SubscriptionId);
+
+impl fmt::Display for SubscriptionNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Subscription with id '{}' not found in cluster '{}'",
+            self.1, self.0
+        )
+    }
+}
+
+impl std::error::Error for SubscriptionNotFound {}
+
 pub const INDEX_NAME: &str = "subscriptions";
 
 pub struct MSSubscriptionStore {
@@ -37,6 +84,8 @@ pub struct MSSubscriptionStore {
     client: Arc<Client>,
     /// A Distributed Unique ID generator.
     generator: Arc<id::Generator>,
+    /// Ids of inserts in flight, so racing inserts don't share one.
+    claims: id::Claims,
 }
 
 impl MSSubscriptionStore {
@@ -50,63 +99,82 @@ impl MSSubscriptionStore {
             }
         };
 
-        Self { client, generator }
+        Self {
+            client,
+            generator,
+            claims: id::Claims::default(),
+        }
     }
 
     fn index(&self) -> Index {
         self.client.index(INDEX_NAME)
     }
+
+    async fn exists(&self, id: i64) -> Result<bool, AnyError> {
+        let document = self
+            .index()
+            .get_document::<serde_json::Value>(&id.to_string())
+            .await;
+        Ok(meilisearch::found(document)?.is_some())
+    }
 }
 
 #[async_trait]
 impl SubscriptionStore for MSSubscriptionStore {
-    async fn list(&self, cluster_id: Option<i64>) -> Result<Vec<Subscription>, AnyError> {
-        if cluster_id.is_none() {
-            let subs = self.index().get_documents::<Subscription>().await?;
-            return Ok(subs.results);
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: Page,
+    ) -> Result<Vec<Subscription>, AnyError> {
+        match cluster_id {
+            Some(id) => page::hits(&self.index(), &format!("cluster_id = {}", id), page).await,
+            None => page::documents(&self.index(), page).await,
         }
+    }
 
-        let results = self
-            .index()
-            .search()
-            .with_filter(&format!("cluster_id = {}", cluster_id.unwrap()))
-            .execute::<Subscription>()
-            .await?;
-
-        let subs = results
-            .hits
-            .iter()
-            .map(|h| h.result.clone())
-            .collect::<Vec<_>>();
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError> {
+        let filter = cluster_id.map(|id| format!("cluster_id = {}", id));
+        page::count(&self.index(), filter.as_deref()).await
+    }
 
-        Ok(subs)
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Subscription>>, AnyError> {
+        search::meilisearch(&self.index(), q, limit).await
     }
 
     async fn get(
         &self,
-        _cluster_id: i64,
-        id: i64,
+        _cluster_id: ClusterId,
+        id: SubscriptionId,
     ) -> result::Result<Option<Subscription>, AnyError> {
-        let cluster = self
+        let subscription = self
             .index()
             .get_document::<Subscription>(&id.to_string())
-            .await?;
+            .await;
 
-        Ok(Some(cluster))
+        meilisearch::found(subscription)
     }
 
-    async fn insert(&self, s: Subscription) -> result::Result<i64, AnyError> {
+    async fn insert(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        // Regenerate ids already taken, e.g. by an instance sharing the worker id
+        let claim = self
+            .claims
+            .claim(|| Ok(self.generator.next_id()?), |id| self.exists(id))
+            .await?;
         let sub = Subscription {
-            id: self.generator.next_id().unwrap(),
+            id: SubscriptionId(claim.id),
             cluster_id: s.cluster_id,
             topic_name: s.topic_name,
             config: s.config,
             created_at: s.created_at,
             updated_at: s.updated_at,
+            owner: s.owner,
+            ownership_confirmed_at: s.ownership_confirmed_at,
+            pending_deletion: s.pending_deletion,
+            state: s.state,
         };
 
         self.index()
-            .add_or_replace(&vec![&sub], Some("id"))
+            .add_or_replace(&[schemas::SUBSCRIPTIONS.versioned(&sub)], Some("id"))
             .await?
             .wait_for_completion(&self.client, None, None)
             .await?;
@@ -114,9 +182,9 @@ impl SubscriptionStore for MSSubscriptionStore {
         Ok(sub.id)
     }
 
-    async fn update(&self, s: Subscription) -> result::Result<i64, AnyError> {
+    async fn update(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
         self.index()
-            .add_or_replace(&vec![&s], Some("id"))
+            .add_or_replace(&[schemas::SUBSCRIPTIONS.versioned(&s)], Some("id"))
             .await?
             .wait_for_completion(&self.client, None, None)
             .await?;
@@ -124,7 +192,11 @@ impl SubscriptionStore for MSSubscriptionStore {
         Ok(s.id)
     }
 
-    async fn remove(&self, _cluster_id: i64, id: i64) -> result::Result<i64, AnyError> {
+    async fn remove(
+        &self,
+        _cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<SubscriptionId, AnyError> {
         self.index().delete_document(id.to_string()).await?;
         Ok(id)
     }
@@ -144,74 +216,125 @@ impl CdrsSubscriptionStore {
         Self { session, generator }
     }
 
-    fn parse(&self, result: Result<Frame, Error>) -> Result<Vec<Row>, Error> {
+    fn parse(&self, result: Result<Frame, CdrsError>) -> Result<Vec<Row>, CdrsError> {
         let rows = result?
             .response_body()?
             .into_rows()
-            .ok_or(Error::General(format!("Failed to parse database response")))?;
+            .ok_or_else(|| CdrsError::General("Failed to parse database response".to_string()))?;
         Ok(rows)
     }
 
     fn map(&self, row: &Row) -> Subscription {
-        let id = row.r_by_name::<i64>(&"id").unwrap();
-        let cluster_id = row.r_by_name::<i64>(&"cluster_id").unwrap();
-        let topic_name = row.r_by_name::<String>(&"topic_name").unwrap();
+        let id = SubscriptionId(row.r_by_name::<i64>("id").unwrap());
+        let cluster_id = ClusterId(row.r_by_name::<i64>("cluster_id").unwrap());
+        let topic_name = row.r_by_name::<String>("topic_name").unwrap();
 
-        let config: HashMap<String, String> = match row.r_by_name::<Map>(&"config") {
+        let config: HashMap<String, String> = match row.r_by_name::<Map>("config") {
             Ok(m) => m.as_r_type().unwrap(),
             Err(_) => HashMap::new(),
         };
 
-        let created_at = row.r_by_name::<DateTime<Utc>>(&"created_at").unwrap();
-        let updated_at = row.r_by_name::<DateTime<Utc>>(&"updated_at").unwrap();
-
-        Subscription::init(id, cluster_id, topic_name, config, created_at, updated_at)
+        let created_at = row.r_by_name::<DateTime<Utc>>("created_at").unwrap();
+        let updated_at = row.r_by_name::<DateTime<Utc>>("updated_at").unwrap();
+
+        let pending_deletion = row
+            .by_name::<String>("pending_deletion")
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok());
+
+        // Rows written before the column read as active.
+        let state = row
+            .by_name::<String>("state")
+            .ok()
+            .flatten()
+            .map(|s| SubscriptionState::from(s.as_str()))
+            .unwrap_or_default();
+
+        Subscription {
+            owner: owner::read(row),
+            ownership_confirmed_at: owner::read_confirmed_at(row),
+            pending_deletion,
+            state,
+            ..Subscription::init(id, cluster_id, topic_name, config, created_at, updated_at)
+        }
     }
 }
 
 #[async_trait]
 impl SubscriptionStore for CdrsSubscriptionStore {
-    async fn list(&self, cluster_id: Option<i64>) -> Result<Vec<Subscription>, AnyError> {
-        let stmt = "SELECT * FROM adm.subscriptions WHERE cluster_id = ? LIMIT 100;";
-        let values = query_values!(cluster_id.unwrap());
-        let rows = self.session.query_with_values(stmt, values).await;
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: Page,
+    ) -> Result<Vec<Subscription>, AnyError> {
+        let stmt = list_statement(cluster_id, page);
+        let rows = match cluster_id {
+            Some(id) => {
+                let values = query_values!(id.as_i64());
+                self.session.query_with_values(stmt, values).await
+            }
+            None => self.session.query(stmt).await,
+        };
+        let rows = self.parse(rows)?;
+
+        Ok(page.slice(rows.iter().map(|r| self.map(r))))
+    }
+
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError> {
+        let stmt = count_statement(cluster_id);
+        let rows = match cluster_id {
+            Some(id) => {
+                let values = query_values!(id.as_i64());
+                self.session.query_with_values(stmt, values).await
+            }
+            None => self.session.query(stmt).await,
+        };
         let rows = self.parse(rows)?;
-        let subs = rows.iter().map(|r| self.map(r)).collect::<Vec<_>>();
+        let count = rows
+            .first()
+            .map_or(0, |r| r.r_by_name::<i64>("count").unwrap());
+
+        Ok(count as usize)
+    }
 
-        Ok(subs)
+    async fn search(&self, _q: &str, _limit: usize) -> Result<Vec<Scored<Subscription>>, AnyError> {
+        Err(SearchUnsupported("Cassandra").into())
     }
 
     async fn get(
         &self,
-        cluster_id: i64,
-        id: i64,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
     ) -> result::Result<Option<Subscription>, AnyError> {
         let stmt = "SELECT * FROM adm.subscriptions WHERE cluster_id = ? AND id = ?;";
-        let values = query_values!(cluster_id, id);
+        let values = query_values!(cluster_id.as_i64(), id.as_i64());
         let rows = self.session.query_with_values(stmt, values).await;
         let rows = self.parse(rows)?;
-        if rows.len() == 0 {
-            return Ok(None);
-        }
 
-        Ok(Some(self.map(rows.get(0).unwrap())))
+        Ok(rows.first().map(|r| self.map(r)))
     }
 
-    async fn insert(&self, s: Subscription) -> result::Result<i64, AnyError> {
+    async fn insert(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
         let stmt = "
-            INSERT INTO adm.subscriptions (id, cluster_id, topic_name, config, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?);";
+            INSERT INTO adm.subscriptions (id, cluster_id, topic_name, config, created_at, updated_at, owner, ownership_confirmed_at, pending_deletion, state)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);";
 
         let mut s = s.clone();
-        s.id = self.generator.next_id().unwrap();
+        s.id = SubscriptionId(self.generator.next_id()?);
 
+        let deletion = pending_deletion(&s);
         let values = query_values!(
-            s.id,
-            s.cluster_id,
+            s.id.as_i64(),
+            s.cluster_id.as_i64(),
             s.topic_name,
             s.config,
             s.created_at,
-            s.updated_at
+            s.updated_at,
+            owner::write(s.owner.as_ref()),
+            s.ownership_confirmed_at,
+            deletion,
+            s.state.as_str()
         );
 
         self.session.query_with_values(stmt, values).await?;
@@ -219,28 +342,361 @@ impl SubscriptionStore for CdrsSubscriptionStore {
         Ok(s.id)
     }
 
-    async fn update(&self, s: Subscription) -> result::Result<i64, AnyError> {
+    async fn update(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
         let stmt = "
 			UPDATE adm.subscriptions
-			SET topic_name = ?, config = ?, updated_at = ?
+			SET topic_name = ?, config = ?, updated_at = ?, owner = ?, ownership_confirmed_at = ?, pending_deletion = ?, state = ?
             WHERE cluster_id = ? AND id = ?;";
 
-        let values = query_values!(s.topic_name, s.config, s.updated_at, s.cluster_id, s.id);
+        let deletion = pending_deletion(&s);
+        let values = query_values!(
+            s.topic_name,
+            s.config,
+            s.updated_at,
+            owner::write(s.owner.as_ref()),
+            s.ownership_confirmed_at,
+            deletion,
+            s.state.as_str(),
+            s.cluster_id.as_i64(),
+            s.id.as_i64()
+        );
         self.session.query_with_values(stmt, values).await?;
 
         Ok(s.id)
     }
 
-    async fn remove(&self, cluster_id: i64, id: i64) -> result::Result<i64, AnyError> {
+    async fn remove(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<SubscriptionId, AnyError> {
         let stmt = "DELETE FROM adm.subscriptions WHERE cluster_id = ? AND id = ?;";
-        let values = query_values!(cluster_id, id);
+        let values = query_values!(cluster_id.as_i64(), id.as_i64());
         self.session.query_with_values(stmt, values).await?;
 
         Ok(id)
     }
 }
 
-pub async fn init_subscription_store() -> Arc<dyn SubscriptionStore + Send + Sync> {
-    // Arc::new(CdrsSubscriptionStore::new(session, generator))
-    Arc::new(MSSubscriptionStore::new(MS_CLIENT.clone(), ID_GENERATOR.clone()).await)
+/// The pending deletion as stored in a Cassandra text column, as JSON.
+/// The statement listing the subscriptions of a cluster, or of every cluster
+/// by scanning the whole table.
+///
+/// CQL has no offset, so the rows up to the end of the page are read and the
+/// offset skipped.
+fn list_statement(cluster_id: Option<ClusterId>, page: Page) -> String {
+    let filter = match cluster_id {
+        Some(_) => " WHERE cluster_id = ?",
+        None => "",
+    };
+    match page.end() {
+        Some(end) => format!(
+            "SELECT * FROM adm.subscriptions{} LIMIT {};",
+            filter,
+            end.max(1)
+        ),
+        None => format!("SELECT * FROM adm.subscriptions{};", filter),
+    }
+}
+
+fn count_statement(cluster_id: Option<ClusterId>) -> &'static str {
+    match cluster_id {
+        Some(_) => "SELECT COUNT(*) FROM adm.subscriptions WHERE cluster_id = ?;",
+        None => "SELECT COUNT(*) FROM adm.subscriptions;",
+    }
+}
+
+fn pending_deletion(s: &Subscription) -> Option<String> {
+    s.pending_deletion
+        .as_ref()
+        .and_then(|d| serde_json::to_string(d).ok())
+}
+
+/// An in-memory store, for tests and for trying seekr out without Cassandra.
+/// Nothing in it outlives the process.
+#[derive(Default)]
+pub struct MemorySubscriptionStore {
+    subscriptions: tokio::sync::RwLock<std::collections::BTreeMap<SubscriptionId, Subscription>>,
+    /// The highest id ever stored, so removing the newest subscription
+    /// doesn't hand its id to the next one.
+    last_id: std::sync::atomic::AtomicI64,
+}
+
+#[async_trait]
+impl SubscriptionStore for MemorySubscriptionStore {
+    async fn list(
+        &self,
+        cluster_id: Option<ClusterId>,
+        page: Page,
+    ) -> Result<Vec<Subscription>, AnyError> {
+        let subscriptions = self.subscriptions.read().await;
+        Ok(page.slice(
+            subscriptions
+                .values()
+                .filter(|s| cluster_id.is_none_or(|id| s.cluster_id == id))
+                .cloned(),
+        ))
+    }
+
+    async fn count(&self, cluster_id: Option<ClusterId>) -> Result<usize, AnyError> {
+        Ok(self.list(cluster_id, Page::ALL).await?.len())
+    }
+
+    async fn search(&self, q: &str, limit: usize) -> Result<Vec<Scored<Subscription>>, AnyError> {
+        let subscriptions = self.subscriptions.read().await;
+        Ok(search::in_memory(
+            subscriptions.values().cloned(),
+            q,
+            limit,
+            |s| &s.topic_name,
+        ))
+    }
+
+    async fn get(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<Option<Subscription>, AnyError> {
+        let subscriptions = self.subscriptions.read().await;
+        Ok(subscriptions
+            .get(&id)
+            .filter(|s| s.cluster_id == cluster_id)
+            .cloned())
+    }
+
+    async fn insert(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        let mut subscriptions = self.subscriptions.write().await;
+        let id = SubscriptionId(self.last_id.load(std::sync::atomic::Ordering::SeqCst) + 1);
+        self.last_id
+            .store(id.as_i64(), std::sync::atomic::Ordering::SeqCst);
+        subscriptions.insert(id, Subscription { id, ..s });
+        Ok(id)
+    }
+
+    async fn update(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        match self.subscriptions.write().await.get_mut(&s.id) {
+            Some(stored) if stored.cluster_id == s.cluster_id => {
+                *stored = s.clone();
+                Ok(s.id)
+            }
+            _ => Err(SubscriptionNotFound(s.cluster_id, s.id).into()),
+        }
+    }
+
+    async fn put(&self, s: Subscription) -> result::Result<SubscriptionId, AnyError> {
+        let mut subscriptions = self.subscriptions.write().await;
+        self.last_id
+            .fetch_max(s.id.as_i64(), std::sync::atomic::Ordering::SeqCst);
+        subscriptions.insert(s.id, s.clone());
+        Ok(s.id)
+    }
+
+    async fn remove(
+        &self,
+        cluster_id: ClusterId,
+        id: SubscriptionId,
+    ) -> result::Result<SubscriptionId, AnyError> {
+        let mut subscriptions = self.subscriptions.write().await;
+        match subscriptions.get(&id) {
+            Some(s) if s.cluster_id == cluster_id => {
+                subscriptions.remove(&id);
+                Ok(id)
+            }
+            _ => Err(SubscriptionNotFound(cluster_id, id).into()),
+        }
+    }
+}
+
+/// Fails when the selected backend can't be reached with the config.
+pub async fn init_subscription_store(
+    config: &StoreConfig,
+) -> Result<Arc<dyn SubscriptionStore + Send + Sync>, AnyError> {
+    config.verify("subscription", config.subscriptions).await?;
+    match config.subscriptions {
+        StoreBackend::Meilisearch => {
+            let client = config.meilisearch.client();
+            Ok(Arc::new(
+                MSSubscriptionStore::new(client, id::generator()).await,
+            ))
+        }
+        StoreBackend::Cassandra => {
+            let session = config.cassandra.session().await?;
+            Ok(Arc::new(CdrsSubscriptionStore::new(
+                session,
+                id::generator(),
+            )))
+        }
+        StoreBackend::Memory => Ok(Arc::new(MemorySubscriptionStore::default())),
+    }
+}
+
+pub const PURGES_INDEX_NAME: &str = "subscription_purges";
+
+/// A subscription the deletion sweep removed for good.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PurgedSubscription {
+    pub id: SubscriptionId,
+    pub cluster_id: ClusterId,
+    pub purged_at: DateTime<Utc>,
+}
+
+/// Remembers purged subscriptions, so undeleting them can tell gone from never existed.
+#[async_trait]
+pub trait PurgeLog {
+    async fn record(&self, purged: PurgedSubscription) -> Result<(), AnyError>;
+    async fn purged(&self, id: SubscriptionId) -> Result<Option<PurgedSubscription>, AnyError>;
+}
+
+pub struct MSPurgeLog {
+    client: Arc<Client>,
+}
+
+impl MSPurgeLog {
+    pub async fn new(client: Arc<Client>) -> Self {
+        if let Ok(task) = client
+            .clone()
+            .create_index(PURGES_INDEX_NAME, Some("id"))
+            .await
+        {
+            task.wait_for_completion(&client, None, None).await.unwrap();
+        }
+
+        Self { client }
+    }
+
+    fn index(&self) -> Index {
+        self.client.index(PURGES_INDEX_NAME)
+    }
+}
+
+#[async_trait]
+impl PurgeLog for MSPurgeLog {
+    async fn record(&self, purged: PurgedSubscription) -> Result<(), AnyError> {
+        self.index()
+            .add_or_replace(&[purged], Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn purged(&self, id: SubscriptionId) -> Result<Option<PurgedSubscription>, AnyError> {
+        let purged = self.index().get_document(&id.to_string()).await;
+
+        meilisearch::found(purged)
+    }
+}
+
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryPurgeLog {
+    purged: tokio::sync::RwLock<HashMap<SubscriptionId, PurgedSubscription>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl PurgeLog for MemoryPurgeLog {
+    async fn record(&self, purged: PurgedSubscription) -> Result<(), AnyError> {
+        self.purged.write().await.insert(purged.id, purged);
+        Ok(())
+    }
+
+    async fn purged(&self, id: SubscriptionId) -> Result<Option<PurgedSubscription>, AnyError> {
+        Ok(self.purged.read().await.get(&id).cloned())
+    }
+}
+
+pub async fn init_purge_log(config: &MeilisearchConfig) -> Arc<dyn PurgeLog + Send + Sync> {
+    Arc::new(MSPurgeLog::new(config.client()).await)
+}
+
+#[tokio::test]
+async fn it_keeps_subscriptions_to_their_cluster() {
+    let store = MemorySubscriptionStore::default();
+    let (a, b) = (ClusterId(1), ClusterId(2));
+    for (cluster_id, topic) in [(a, "orders"), (b, "orders"), (a, "refunds")] {
+        let s = Subscription::new(None, cluster_id, topic.to_string(), HashMap::new());
+        store.insert(s).await.unwrap();
+    }
+
+    let topics = |subscriptions: Vec<Subscription>| {
+        subscriptions
+            .into_iter()
+            .map(|s| (s.id, s.topic_name))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        topics(store.list(Some(a), Page::ALL).await.unwrap()),
+        [
+            (SubscriptionId(1), "orders".to_string()),
+            (SubscriptionId(3), "refunds".to_string())
+        ]
+    );
+    assert_eq!(store.count(None).await.unwrap(), 3);
+    assert!(store.get(a, SubscriptionId(2)).await.unwrap().is_none());
+
+    // Removing through another cluster, or twice, finds nothing to remove.
+    let e = store.remove(a, SubscriptionId(2)).await.unwrap_err();
+    assert_eq!(
+        e.downcast_ref(),
+        Some(&SubscriptionNotFound(a, SubscriptionId(2)))
+    );
+    store.remove(b, SubscriptionId(2)).await.unwrap();
+    assert!(store.remove(b, SubscriptionId(2)).await.is_err());
+    assert_eq!(store.count(Some(b)).await.unwrap(), 0);
+}
+
+#[test]
+fn it_scans_every_cluster_without_one() {
+    assert_eq!(
+        list_statement(None, Page::ALL),
+        "SELECT * FROM adm.subscriptions;"
+    );
+    assert_eq!(
+        list_statement(None, Page::new(20, Some(10))),
+        "SELECT * FROM adm.subscriptions LIMIT 30;"
+    );
+    assert_eq!(
+        count_statement(None),
+        "SELECT COUNT(*) FROM adm.subscriptions;"
+    );
+
+    let cluster = Some(ClusterId(1));
+    assert_eq!(
+        list_statement(cluster, Page::new(0, Some(10))),
+        "SELECT * FROM adm.subscriptions WHERE cluster_id = ? LIMIT 10;"
+    );
+    assert_eq!(
+        count_statement(cluster),
+        "SELECT COUNT(*) FROM adm.subscriptions WHERE cluster_id = ?;"
+    );
+}
+
+#[tokio::test]
+async fn it_never_reuses_ids_nor_updates_missing_subscriptions() {
+    let store = MemorySubscriptionStore::default();
+    let cluster_id = ClusterId(1);
+    let subscription =
+        |topic: &str| Subscription::new(None, cluster_id, topic.to_string(), HashMap::new());
+    store.insert(subscription("orders")).await.unwrap();
+    let refunds = store.insert(subscription("refunds")).await.unwrap();
+
+    // The newest subscription's id stays retired once it's removed.
+    store.remove(cluster_id, refunds).await.unwrap();
+    let payments = store.insert(subscription("payments")).await.unwrap();
+    assert_eq!(payments, SubscriptionId(3));
+
+    let e = store
+        .update(Subscription {
+            id: refunds,
+            ..subscription("refunds")
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(
+        e.downcast_ref(),
+        Some(&SubscriptionNotFound(cluster_id, refunds))
+    );
+    assert!(store.get(cluster_id, refunds).await.unwrap().is_none());
 }
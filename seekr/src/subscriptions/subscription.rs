@@ -1,16 +1,21 @@
 use std::collections::HashMap;
 
 use chrono::prelude::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::api::list::{Field, FieldType, ListSchema, Listable, Value};
+use crate::governance::owner::Owner;
+use crate::ids::{ClusterId, SubscriptionId};
+
 // The subscription for a topic with the given name.
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct Subscription {
     // Specifies the unique identifier of the subscription.
-    pub id: i64,
+    pub id: SubscriptionId,
 
     // Specifies the unique identifier of the subscription cluster.
-    pub cluster_id: i64,
+    pub cluster_id: ClusterId,
 
     // Specifies the topic name for the subscription.
     pub topic_name: String,
@@ -23,28 +28,101 @@ pub struct Subscription {
 
     /// Represents the point in time in UTC Epoch time, when the subscription was modified.
     pub updated_at: DateTime<Utc>,
+
+    /// Who to contact about the subscription.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<Owner>,
+
+    /// Represents the point in time in UTC Epoch time, when the owner was last confirmed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership_confirmed_at: Option<DateTime<Utc>>,
+
+    /// Set once the subscription is deleted, until it's purged or undeleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_deletion: Option<PendingDeletion>,
+
+    /// Whether the subscription is indexed, or paused until it's resumed.
+    #[serde(default)]
+    pub state: SubscriptionState,
+}
+
+/// A delete that can still be undone.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct PendingDeletion {
+    /// Represents the point in time in UTC Epoch time, when the subscription was first deleted.
+    pub requested_at: DateTime<Utc>,
+
+    /// Represents the point in time in UTC Epoch time, when the subscription is purged for good.
+    pub purge_at: DateTime<Utc>,
+
+    /// Whether the subscription's search index is dropped along with it.
+    #[serde(default)]
+    pub purge_index: bool,
+
+    /// Whether the worker was already paused, so undeleting leaves it paused.
+    #[serde(default)]
+    pub was_paused: bool,
+}
+
+/// Whether the indexer runs a worker for the subscription.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum SubscriptionState {
+    #[default]
+    Active,
+    Paused,
+}
+
+impl SubscriptionState {
+    pub fn is_active(&self) -> bool {
+        *self == SubscriptionState::Active
+    }
+
+    /// The state as stored in the `state` column of `adm.subscriptions`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionState::Active => "Active",
+            SubscriptionState::Paused => "Paused",
+        }
+    }
+}
+
+impl From<&str> for SubscriptionState {
+    fn from(s: &str) -> Self {
+        match s {
+            "Paused" => SubscriptionState::Paused,
+            _ => SubscriptionState::Active,
+        }
+    }
 }
 
 impl Subscription {
+    pub fn is_pending_deletion(&self) -> bool {
+        self.pending_deletion.is_some()
+    }
+
     pub fn new(
-        id: Option<i64>,
-        cluster_id: i64,
+        id: Option<SubscriptionId>,
+        cluster_id: ClusterId,
         topic_name: String,
         config: HashMap<String, String>,
     ) -> Self {
         Subscription {
-            id: id.unwrap_or(0),
+            id: id.unwrap_or_default(),
             cluster_id,
             topic_name,
             config,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            owner: None,
+            ownership_confirmed_at: None,
+            pending_deletion: None,
+            state: SubscriptionState::Active,
         }
     }
 
     pub fn init(
-        id: i64,
-        cluster_id: i64,
+        id: SubscriptionId,
+        cluster_id: ClusterId,
         topic_name: String,
         config: HashMap<String, String>,
         created_at: DateTime<Utc>,
@@ -57,6 +135,64 @@ impl Subscription {
             config,
             created_at,
             updated_at,
+            owner: None,
+            ownership_confirmed_at: None,
+            pending_deletion: None,
+            state: SubscriptionState::Active,
+        }
+    }
+}
+
+impl Listable for Subscription {
+    const SCHEMA: ListSchema = ListSchema {
+        fields: &[
+            Field {
+                name: "id",
+                kind: FieldType::Integer,
+                sortable: true,
+                filterable: true,
+                attribute: "id",
+            },
+            Field {
+                name: "topic_name",
+                kind: FieldType::String,
+                sortable: true,
+                filterable: true,
+                attribute: "topic_name",
+            },
+            Field {
+                name: "created_at",
+                kind: FieldType::Timestamp,
+                sortable: true,
+                filterable: true,
+                attribute: "created_at",
+            },
+            Field {
+                name: "updated_at",
+                kind: FieldType::Timestamp,
+                sortable: true,
+                filterable: true,
+                attribute: "updated_at",
+            },
+            Field {
+                name: "team",
+                kind: FieldType::String,
+                sortable: false,
+                filterable: true,
+                attribute: "owner.team",
+            },
+        ],
+        id: "id",
+    };
+
+    fn value(&self, field: &str) -> Option<Value> {
+        match field {
+            "id" => Some(Value::Integer(self.id.as_i64())),
+            "topic_name" => Some(Value::String(self.topic_name.clone())),
+            "created_at" => Some(Value::Timestamp(self.created_at)),
+            "updated_at" => Some(Value::Timestamp(self.updated_at)),
+            "team" => self.owner.as_ref()?.team.clone().map(Value::String),
+            _ => None,
         }
     }
 }
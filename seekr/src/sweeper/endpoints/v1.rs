@@ -0,0 +1,26 @@
+use actix_web::web::{Data, ServiceConfig};
+use actix_web::{get, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::auth::Principal;
+use crate::sweeper::{JobStatus, Sweeper};
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_sweeper);
+}
+
+#[get("/sweeper")]
+async fn get_sweeper(principal: Principal, sweeper: Data<Sweeper>) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    HttpResponse::Ok().json(SweeperResponse {
+        jobs: sweeper.jobs(),
+    })
+}
+
+#[derive(Serialize)]
+struct SweeperResponse {
+    jobs: Vec<JobStatus>,
+}
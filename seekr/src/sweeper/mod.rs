@@ -0,0 +1,420 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::time::{sleep_until, timeout, Instant};
+
+use crate::errors::AnyError;
+use crate::shutdown::Shutdown;
+
+pub mod endpoints;
+
+/// How long a job may run before it's cancelled, unless it asks otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The gap between the first runs of consecutively registered jobs.
+pub const DEFAULT_STAGGER: Duration = Duration::from_secs(5);
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<usize, AnyError>> + Send>>;
+
+/// A periodic cleanup task, returning how many items a run removed.
+pub struct Job {
+    name: String,
+    interval: Duration,
+    timeout: Duration,
+    run: Box<dyn Fn() -> JobFuture + Send + Sync>,
+}
+
+impl Job {
+    pub fn new<F, Fut>(name: &str, interval: Duration, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<usize, AnyError>> + Send + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            interval,
+            timeout: DEFAULT_TIMEOUT,
+            run: Box::new(move || Box::pin(run())),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// What the sweeper knows about a job's runs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub interval_ms: u64,
+    pub timeout_ms: u64,
+    pub running: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    pub last_removed: Option<usize>,
+    pub last_error: Option<String>,
+    pub runs: u64,
+    pub failures: u64,
+    pub removed_total: u64,
+}
+
+struct Entry {
+    job: Arc<Job>,
+    due: Instant,
+    status: JobStatus,
+}
+
+/// Runs every periodic cleanup job of the instance on a single schedule.
+///
+/// Jobs run one at a time, their first runs spaced out by the stagger, so
+/// their store load doesn't pile up. A job running past its timeout is
+/// cancelled and counted as failed, and shutdown cancels a run in progress.
+pub struct Sweeper {
+    entries: Mutex<Vec<Entry>>,
+    stagger: Duration,
+    registered: Notify,
+    sd: Shutdown,
+}
+
+impl Default for Sweeper {
+    fn default() -> Self {
+        Self::new(DEFAULT_STAGGER)
+    }
+}
+
+impl Sweeper {
+    pub fn new(stagger: Duration) -> Self {
+        Self {
+            entries: Mutex::new(vec![]),
+            stagger,
+            registered: Notify::new(),
+            sd: Shutdown::new(),
+        }
+    }
+
+    /// Add a job, first run one stagger after the previously registered one.
+    pub fn register(&self, job: Job) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let due = entries
+            .iter()
+            .map(|e| e.due + self.stagger)
+            .max()
+            .unwrap_or(now)
+            .max(now);
+
+        debug!(
+            "Registered sweeper job {} every {:?}",
+            job.name, job.interval
+        );
+        entries.push(Entry {
+            status: JobStatus {
+                name: job.name.clone(),
+                interval_ms: job.interval.as_millis() as u64,
+                timeout_ms: job.timeout.as_millis() as u64,
+                ..JobStatus::default()
+            },
+            job: Arc::new(job),
+            due,
+        });
+        self.registered.notify_one();
+    }
+
+    /// The status of every registered job, in registration order.
+    pub fn jobs(&self) -> Vec<JobStatus> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().map(|e| e.status.clone()).collect()
+    }
+
+    pub async fn start(self: Arc<Self>) {
+        debug!("Starting sweeper...");
+
+        tokio::spawn(async move { self.poll().await });
+    }
+
+    pub async fn stop(self: Arc<Self>) {
+        debug!("Stopping sweeper...");
+
+        self.sd.begin();
+        self.sd.wait_complete().await;
+    }
+
+    async fn poll(self: Arc<Self>) {
+        loop {
+            let next = self.next();
+
+            tokio::select! {
+                _ = Self::until(next.as_ref().map(|(_, due)| *due)) => {
+                    let (index, _) = next.unwrap();
+                    self.run(index).await;
+                }
+                _ = self.registered.notified() => {}
+                _ = self.sd.wait_begin() => {}
+            }
+
+            if self.sd.is_shutdown() {
+                debug!("Sweeper shutdown started...");
+                self.sd.complete();
+                break;
+            }
+        }
+    }
+
+    /// The job due first, and when.
+    fn next(&self) -> Option<(usize, Instant)> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, e)| e.due)
+            .map(|(i, e)| (i, e.due))
+    }
+
+    async fn until(due: Option<Instant>) {
+        match due {
+            Some(due) => sleep_until(due).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Run a job and record its outcome, unless shutdown cancels it.
+    async fn run(&self, index: usize) {
+        let job = {
+            let mut entries = self.entries.lock().unwrap();
+            entries[index].status.running = true;
+            entries[index].job.clone()
+        };
+
+        let started = Instant::now();
+        let at = Utc::now();
+        let result = tokio::select! {
+            result = timeout(job.timeout, (job.run)()) => result,
+            _ = self.sd.wait_begin() => {
+                debug!("Sweeper job {} cancelled by shutdown", job.name);
+                self.entries.lock().unwrap()[index].status.running = false;
+                return;
+            }
+        };
+
+        let result = match result {
+            Ok(result) => result,
+            Err(_) => Err(format!("timed out after {}ms", job.timeout.as_millis()).into()),
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        let entry = &mut entries[index];
+        let status = &mut entry.status;
+        status.running = false;
+        status.last_run_at = Some(at);
+        status.last_duration_ms = Some(started.elapsed().as_millis() as u64);
+        status.runs += 1;
+
+        match result {
+            Ok(removed) => {
+                if removed > 0 {
+                    debug!("Sweeper job {} removed {} items", job.name, removed);
+                }
+                status.last_removed = Some(removed);
+                status.last_error = None;
+                status.removed_total += removed as u64;
+            }
+            Err(e) => {
+                warn!("Sweeper job {} failed - {}", job.name, e);
+                status.last_removed = None;
+                status.last_error = Some(e.to_string());
+                status.failures += 1;
+            }
+        }
+
+        entry.due = Instant::now().max(entry.due + job.interval);
+    }
+}
+
+#[cfg(test)]
+fn sleeper(
+    name: &str,
+    interval: Duration,
+    sleep: Duration,
+    removed: usize,
+    log: Arc<Mutex<Vec<(String, Instant, Instant)>>>,
+) -> Job {
+    let name_ = name.to_string();
+    Job::new(name, interval, move || {
+        let (name, log) = (name_.clone(), log.clone());
+        async move {
+            let started = Instant::now();
+            tokio::time::sleep(sleep).await;
+            log.lock().unwrap().push((name, started, Instant::now()));
+            Ok::<_, AnyError>(removed)
+        }
+    })
+}
+
+#[tokio::test]
+async fn it_never_runs_two_jobs_at_once() {
+    tokio::time::pause();
+
+    let log = Arc::new(Mutex::new(vec![]));
+    let sweeper = Arc::new(Sweeper::new(Duration::from_secs(1)));
+    let (interval, sleep) = (Duration::from_secs(10), Duration::from_secs(3));
+    for name in ["a", "b", "c"] {
+        sweeper.register(sleeper(name, interval, sleep, 0, log.clone()));
+    }
+
+    let started = Instant::now();
+    sweeper.clone().start().await;
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    sweeper.stop().await;
+
+    let mut runs = log.lock().unwrap().clone();
+    runs.sort_by_key(|(_, start, _)| *start);
+    assert!(runs.len() >= 9);
+    for pair in runs.windows(2) {
+        assert!(
+            pair[0].2 <= pair[1].1,
+            "{} overlapped {}",
+            pair[0].0,
+            pair[1].0
+        );
+    }
+
+    // The first runs start a stagger apart, or once the previous one finished.
+    let firsts = ["a", "b", "c"].map(|name| {
+        let (_, start, _) = runs.iter().find(|(n, _, _)| n == name).unwrap();
+        start.duration_since(started).as_secs()
+    });
+    assert_eq!(firsts, [0, 3, 6]);
+}
+
+#[tokio::test]
+async fn it_cancels_a_job_past_its_timeout() {
+    tokio::time::pause();
+
+    let log = Arc::new(Mutex::new(vec![]));
+    let sweeper = Arc::new(Sweeper::new(Duration::from_secs(1)));
+    let slow = sleeper(
+        "slow",
+        Duration::from_secs(60),
+        Duration::from_secs(600),
+        5,
+        log.clone(),
+    );
+    sweeper.register(slow.with_timeout(Duration::from_secs(10)));
+    sweeper.register(sleeper(
+        "quick",
+        Duration::from_secs(60),
+        Duration::ZERO,
+        2,
+        log.clone(),
+    ));
+
+    sweeper.clone().start().await;
+    tokio::time::sleep(Duration::from_secs(30)).await;
+
+    let jobs = sweeper.jobs();
+    assert_eq!(jobs[0].runs, 1);
+    assert_eq!(jobs[0].failures, 1);
+    assert_eq!(
+        jobs[0].last_error.as_deref(),
+        Some("timed out after 10000ms")
+    );
+    assert_eq!(jobs[0].last_duration_ms, Some(10_000));
+    assert!(!jobs[0].running);
+    assert_eq!(jobs[1].runs, 1);
+    assert_eq!(jobs[1].failures, 0);
+    assert_eq!(jobs[1].last_removed, Some(2));
+
+    // The cancelled run never finished.
+    let names = log
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(n, _, _)| n.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["quick"]);
+
+    sweeper.stop().await;
+}
+
+#[tokio::test]
+async fn it_stops_promptly_in_the_middle_of_a_run() {
+    tokio::time::pause();
+
+    let log = Arc::new(Mutex::new(vec![]));
+    let sweeper = Arc::new(Sweeper::default());
+    let job = sleeper(
+        "long",
+        Duration::from_secs(60),
+        Duration::from_secs(3600),
+        0,
+        log,
+    );
+    sweeper.register(job.with_timeout(Duration::from_secs(7200)));
+
+    sweeper.clone().start().await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert!(sweeper.jobs()[0].running);
+
+    let started = Instant::now();
+    tokio::time::timeout(Duration::from_secs(1), sweeper.clone().stop())
+        .await
+        .expect("shutdown waited for the run");
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(sweeper.jobs()[0].runs, 0);
+}
+
+#[tokio::test]
+async fn it_reports_every_run() {
+    tokio::time::pause();
+
+    let failures = Arc::new(Mutex::new(0));
+    let sweeper = Arc::new(Sweeper::new(Duration::from_secs(1)));
+    let log = Arc::new(Mutex::new(vec![]));
+    sweeper.register(sleeper(
+        "removes",
+        Duration::from_secs(10),
+        Duration::from_millis(250),
+        3,
+        log,
+    ));
+    sweeper.register(Job::new("fails", Duration::from_secs(10), {
+        let failures = failures.clone();
+        move || {
+            let failures = failures.clone();
+            async move {
+                *failures.lock().unwrap() += 1;
+                Err::<usize, AnyError>("store unavailable".into())
+            }
+        }
+    }));
+
+    let before = Utc::now();
+    sweeper.clone().start().await;
+    // First runs at 0s and 1s, then every 10s: 4 runs each by 35s.
+    tokio::time::sleep(Duration::from_secs(35)).await;
+    sweeper.clone().stop().await;
+
+    let jobs = sweeper.jobs();
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[0].name, "removes");
+    assert_eq!(jobs[0].interval_ms, 10_000);
+    assert_eq!(jobs[0].runs, 4);
+    assert_eq!(jobs[0].failures, 0);
+    assert_eq!(jobs[0].removed_total, 12);
+    assert_eq!(jobs[0].last_removed, Some(3));
+    assert_eq!(jobs[0].last_duration_ms, Some(250));
+    assert!(jobs[0].last_run_at.unwrap() >= before);
+    assert_eq!(jobs[1].runs, 4);
+    assert_eq!(jobs[1].failures, 4);
+    assert_eq!(*failures.lock().unwrap(), 4);
+    assert_eq!(jobs[1].last_removed, None);
+    assert_eq!(jobs[1].last_error.as_deref(), Some("store unavailable"));
+    assert_eq!(jobs[1].removed_total, 0);
+}
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::{Data, Path, Query, ServiceConfig};
+use actix_web::{get, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::api::error;
+use crate::clusters::service::{self, TopicRead};
+use crate::clusters::store::{ClusterNotFound, ClusterStore};
+use crate::deadline::Deadline;
+use crate::errors::ApiError;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::MetadataManager;
+use crate::tail::{MessageTail, TailOffset, TailedMessage};
+
+/// Messages read unless the request says otherwise, and at most, so a tail
+/// never makes the brokers serve more than a glance at the partition.
+const DEFAULT_COUNT: usize = 20;
+const MAX_COUNT: usize = 500;
+
+/// How long a tail reads messages, unless the request's deadline leaves less.
+const TAIL_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(tail_messages);
+}
+
+#[get("/{id}/topics/{topic}/messages")]
+async fn tail_messages(
+    path: Path<(ClusterId, String)>,
+    query: Query<TailQuery>,
+    cs: Data<Arc<dyn ClusterStore + Send + Sync>>,
+    manager: Data<MetadataManager>,
+    source: Data<Arc<dyn MessageTail + Send + Sync>>,
+    deadline: Deadline,
+) -> Result<HttpResponse, ApiError> {
+    let (id, topic) = path.into_inner();
+    info!("Tailing topic {} in cluster with id {}", topic, id);
+
+    let count = query.count.unwrap_or(DEFAULT_COUNT);
+    if count == 0 || count > MAX_COUNT {
+        return Err(ApiError::invalid(format!(
+            "count must be between 1 and {}",
+            MAX_COUNT
+        )));
+    }
+    let offset = match &query.offset {
+        Some(offset) => offset.parse::<TailOffset>().map_err(ApiError::invalid)?,
+        None => TailOffset::Latest,
+    };
+    let partition = query.partition.unwrap_or(0);
+
+    let cluster = match deadline.run("clusters", cs.get(id)).await {
+        Ok(cluster) => cluster?.ok_or(ClusterNotFound(id))?,
+        Err(e) => return Ok(error::deadline_exceeded(&e)),
+    };
+    let metadata = match service::topic(manager.into_inner(), id, &topic).await? {
+        TopicRead::Topic(metadata, _) => metadata,
+        TopicRead::Processing => {
+            return Err(ApiError::unavailable(format!(
+                "Cluster metadata with id '{}' is still processing",
+                id
+            )))
+        }
+        TopicRead::MissingCluster | TopicRead::MissingTopic => {
+            return Err(ApiError::not_found(format!("Topic '{}' not found", topic)))
+        }
+    };
+    if !metadata.partitions.iter().any(|p| p.id == partition) {
+        return Err(ApiError::invalid(format!(
+            "Topic '{}' has no partition {}",
+            topic, partition
+        )));
+    }
+
+    // A cancelled request drops the tail, which stops the read and its consumer.
+    let timeout = deadline.clamp(TAIL_TIMEOUT);
+    let tail = source.tail(&cluster, &topic, partition, offset, count, timeout);
+    let tail = match deadline.run("kafka_tail", tail).await {
+        Ok(tail) => tail?,
+        Err(e) => return Ok(error::deadline_exceeded(&e)),
+    };
+
+    Ok(HttpResponse::Ok().json(TailResponse {
+        topic,
+        partition,
+        low_watermark: tail.low_watermark,
+        high_watermark: tail.high_watermark,
+        timed_out: tail.timed_out,
+        messages: tail.messages,
+    }))
+}
+
+#[derive(Deserialize)]
+struct TailQuery {
+    partition: Option<i32>,
+    /// `latest` for the newest messages, or the offset to read from.
+    offset: Option<String>,
+    count: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct TailResponse {
+    topic: String,
+    partition: i32,
+    low_watermark: i64,
+    high_watermark: i64,
+    /// Set when the time budget ran out before every message requested was read.
+    timed_out: bool,
+    messages: Vec<TailedMessage>,
+}
+
+#[actix_web::test]
+async fn it_tails_the_newest_messages_of_a_partition() {
+    use std::collections::HashMap;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::json;
+
+    use crate::clusters::cluster::{Cluster, Kind};
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::history::diff::metadata;
+    use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataConsumerFactory};
+    use crate::kafka::streams::StreamsMessage;
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+    use crate::tail::{MemoryMessageTail, PayloadEncoding};
+
+    let cs: Arc<dyn ClusterStore + Send + Sync> = Arc::new(MemoryClusterStore::default());
+    let cluster = Cluster::new(
+        Some(ClusterId(1)),
+        Kind::Kafka,
+        "c".to_string(),
+        HashMap::new(),
+    );
+    cs.put(cluster).await.unwrap();
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = MetadataManager::with_factory(cs.clone(), factory);
+    manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            entries: vec![SyncedEntry {
+                cluster_id: ClusterId(1),
+                entry: CachedMetadataEntry::Meta(metadata(&[1], &[("orders", 2)])),
+                offsets: None,
+            }],
+            clusters: vec![ClusterId(1)],
+        })
+        .await;
+
+    let messages = (10..50)
+        .map(|offset| TailedMessage {
+            message: StreamsMessage {
+                payload: Some(format!("{{\"id\":{}}}", offset)),
+                payload_json: None,
+                topic: "orders".to_string(),
+                key: Some(format!("tenant-{}", offset % 3)),
+                headers: HashMap::new(),
+                partition: 1,
+                offset,
+                timestamp: Some(1_700_000_000_000 + offset),
+            },
+            payload_encoding: PayloadEncoding::Utf8,
+        })
+        .collect();
+    let memory = Arc::new(MemoryMessageTail {
+        messages,
+        ..Default::default()
+    });
+    let source: Arc<dyn MessageTail + Send + Sync> = memory.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(cs))
+            .app_data(Data::new(manager))
+            .app_data(Data::new(source))
+            .configure(crate::server::routes),
+    )
+    .await;
+    let tail = |topic: &str, query: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/clusters/1/topics/{}/messages{}",
+                topic, query
+            ))
+            .to_request()
+    };
+
+    let body: serde_json::Value =
+        test::call_and_read_body_json(&app, tail("orders", "?partition=1&count=3")).await;
+    assert_eq!(body["low_watermark"], 10);
+    assert_eq!(body["high_watermark"], 50);
+    assert_eq!(body["timed_out"], false);
+    let offsets = body["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["offset"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [47, 48, 49]);
+    assert_eq!(
+        body["messages"][0],
+        json!({
+            "payload": "{\"id\":47}",
+            "payload_encoding": "utf8",
+            "topic": "orders",
+            "key": "tenant-2",
+            "headers": {},
+            "partition": 1,
+            "offset": 47,
+            "timestamp": 1_700_000_000_047i64,
+        })
+    );
+
+    let body: serde_json::Value =
+        test::call_and_read_body_json(&app, tail("orders", "?partition=1&offset=12")).await;
+    assert_eq!(body["messages"].as_array().unwrap().len(), DEFAULT_COUNT);
+    assert_eq!(body["messages"][0]["offset"], 12);
+
+    let calls = memory.calls.lock().unwrap().clone();
+    assert_eq!(calls[0].2, TailOffset::Latest);
+    assert_eq!(calls[1].2, TailOffset::At(12));
+    assert!(calls.iter().all(|c| c.1 == 1 && c.4 <= TAIL_TIMEOUT));
+
+    for (topic, query, status) in [
+        ("orders", "?count=501", StatusCode::BAD_REQUEST),
+        ("orders", "?count=0", StatusCode::BAD_REQUEST),
+        ("orders", "?offset=earliest", StatusCode::BAD_REQUEST),
+        ("orders", "?partition=2", StatusCode::BAD_REQUEST),
+        ("refunds", "", StatusCode::NOT_FOUND),
+    ] {
+        let res = test::call_service(&app, tail(topic, query)).await;
+        assert_eq!(res.status(), status, "{}{}", topic, query);
+    }
+    assert_eq!(memory.calls.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,285 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{Message, Offset, TopicPartitionList};
+use serde::Serialize;
+
+use crate::clusters::cluster::Cluster;
+use crate::errors::AnyError;
+use crate::kafka::auth::AuthContext;
+use crate::kafka::client::build_client_config;
+use crate::kafka::streams::consumer::streams_message;
+use crate::kafka::streams::StreamsMessage;
+
+pub mod endpoints;
+
+/// How long a single poll waits, so a cancelled tail stops reading soon after.
+const POLL_SLICE: Duration = Duration::from_millis(100);
+
+/// Where a tail starts reading a partition.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TailOffset {
+    /// The newest messages, as many as requested.
+    Latest,
+    At(i64),
+}
+
+impl FromStr for TailOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(TailOffset::Latest),
+            _ => match s.parse::<i64>() {
+                Ok(offset) if offset >= 0 => Ok(TailOffset::At(offset)),
+                _ => Err(format!(
+                    "offset must be 'latest' or a positive number, not '{}'",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// The offsets from which and up to which a tail reads, given the partition's
+/// watermarks, offsets no longer retained skipped.
+pub fn range(low: i64, high: i64, offset: TailOffset, count: usize) -> (i64, i64) {
+    let from = match offset {
+        TailOffset::Latest => high - count as i64,
+        TailOffset::At(offset) => offset,
+    };
+    let from = from.clamp(low, high.max(low));
+    (from, high.min(from + count as i64))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadEncoding {
+    Utf8,
+    /// Payloads that aren't valid UTF-8.
+    Base64,
+}
+
+/// A message read by a tail, with how its payload is encoded.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TailedMessage {
+    #[serde(flatten)]
+    pub message: StreamsMessage,
+    pub payload_encoding: PayloadEncoding,
+}
+
+impl TailedMessage {
+    fn of(m: &impl Message) -> Self {
+        let mut message = streams_message(m);
+        let mut payload_encoding = PayloadEncoding::Utf8;
+        if let Some(bytes) = m.payload() {
+            if std::str::from_utf8(bytes).is_err() {
+                message.payload = Some(base64::encode(bytes));
+                payload_encoding = PayloadEncoding::Base64;
+            }
+        }
+        Self {
+            message,
+            payload_encoding,
+        }
+    }
+}
+
+/// The messages read from a partition, oldest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tail {
+    pub messages: Vec<TailedMessage>,
+    pub low_watermark: i64,
+    pub high_watermark: i64,
+
+    /// Set when the timeout passed before every message in range was read.
+    pub timed_out: bool,
+}
+
+/// Reads the messages of a single partition on demand.
+#[async_trait]
+pub trait MessageTail {
+    async fn tail(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        partition: i32,
+        offset: TailOffset,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Tail, AnyError>;
+}
+
+/// Tails with an ephemeral consumer assigned the partition directly, it never
+/// joins a group nor commits, so no consumer group sees it.
+pub struct KafkaMessageTail;
+
+/// Tells the read the tail was dropped, e.g. with its request cancelled.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl MessageTail for KafkaMessageTail {
+    async fn tail(
+        &self,
+        cluster: &Cluster,
+        topic: &str,
+        partition: i32,
+        offset: TailOffset,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Tail, AnyError> {
+        let auth = AuthContext::of(cluster)?;
+        auth.check().await?;
+        let mut client = build_client_config(cluster, None)?;
+        client.set("enable.auto.offset.store", "false");
+        auth.configure(&mut client);
+        let consumer = client.create_with_context::<_, BaseConsumer<_>>(auth)?;
+
+        let topic = topic.to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel = CancelOnDrop(cancelled.clone());
+
+        // The read owns the consumer, which is dropped once the read ends,
+        // at the latest a poll after the tail is.
+        tokio::task::spawn_blocking(move || {
+            let deadline = Instant::now() + timeout;
+            let remaining = || deadline.saturating_duration_since(Instant::now());
+
+            let (low, high) = consumer.fetch_watermarks(&topic, partition, remaining())?;
+            let (from, to) = range(low, high, offset, count);
+            let mut tail = Tail {
+                low_watermark: low,
+                high_watermark: high,
+                ..Tail::default()
+            };
+            if from >= to {
+                return Ok(tail);
+            }
+
+            let mut tpl = TopicPartitionList::new();
+            tpl.add_partition_offset(&topic, partition, Offset::Offset(from))?;
+            consumer.assign(&tpl)?;
+            while !cancelled.load(Ordering::SeqCst) {
+                if remaining().is_zero() {
+                    tail.timed_out = true;
+                    break;
+                }
+                match consumer.poll(remaining().min(POLL_SLICE)) {
+                    // Compacted topics skip offsets, the range ends all the same.
+                    Some(Ok(m)) if m.offset() < to => {
+                        tail.messages.push(TailedMessage::of(&m));
+                        if m.offset() + 1 >= to {
+                            break;
+                        }
+                    }
+                    Some(Ok(_)) => break,
+                    Some(Err(e)) => return Err(e.into()),
+                    None => {}
+                }
+            }
+
+            Ok(tail)
+        })
+        .await?
+    }
+}
+
+/// Serves scripted messages of a partition, counting the calls.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryMessageTail {
+    pub messages: Vec<TailedMessage>,
+    pub calls: std::sync::Mutex<Vec<(String, i32, TailOffset, usize, Duration)>>,
+}
+
+#[cfg(test)]
+#[async_trait]
+impl MessageTail for MemoryMessageTail {
+    async fn tail(
+        &self,
+        _cluster: &Cluster,
+        topic: &str,
+        partition: i32,
+        offset: TailOffset,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Tail, AnyError> {
+        let call = (topic.to_string(), partition, offset, count, timeout);
+        self.calls.lock().unwrap().push(call);
+
+        let offsets = self.messages.iter().map(|m| m.message.offset);
+        let low = offsets.clone().min().unwrap_or(0);
+        let high = offsets.max().map_or(0, |o| o + 1);
+        let (from, to) = range(low, high, offset, count);
+        Ok(Tail {
+            messages: self
+                .messages
+                .iter()
+                .filter(|m| (from..to).contains(&m.message.offset))
+                .cloned()
+                .collect(),
+            low_watermark: low,
+            high_watermark: high,
+            timed_out: false,
+        })
+    }
+}
+
+#[test]
+fn it_reads_the_newest_messages_or_from_an_offset() {
+    assert_eq!(range(0, 100, TailOffset::Latest, 20), (80, 100));
+    assert_eq!(range(90, 100, TailOffset::Latest, 20), (90, 100));
+    assert_eq!(range(0, 0, TailOffset::Latest, 20), (0, 0));
+    assert_eq!(range(0, 100, TailOffset::At(10), 20), (10, 30));
+    assert_eq!(range(0, 100, TailOffset::At(95), 20), (95, 100));
+
+    // Offsets deleted by retention start at the oldest retained, ones not
+    // produced yet read nothing.
+    assert_eq!(range(50, 100, TailOffset::At(10), 20), (50, 70));
+    assert_eq!(range(50, 100, TailOffset::At(150), 20), (100, 100));
+
+    assert_eq!("latest".parse(), Ok(TailOffset::Latest));
+    assert_eq!("42".parse(), Ok(TailOffset::At(42)));
+    assert!("-1".parse::<TailOffset>().is_err());
+    assert!("earliest".parse::<TailOffset>().is_err());
+}
+
+#[test]
+fn it_encodes_binary_payloads_as_base64() {
+    use rdkafka::message::{OwnedMessage, Timestamp};
+
+    let message = |payload: &[u8]| {
+        let m = OwnedMessage::new(
+            Some(payload.to_vec()),
+            Some(b"tenant-42".to_vec()),
+            "orders".to_string(),
+            Timestamp::CreateTime(1_700_000_000_000),
+            0,
+            7,
+            None,
+        );
+        TailedMessage::of(&m)
+    };
+
+    let text = message(b"{\"id\":7}");
+    assert_eq!(text.payload_encoding, PayloadEncoding::Utf8);
+    assert_eq!(text.message.payload.as_deref(), Some("{\"id\":7}"));
+
+    let binary = message(&[0xff, 0x00, 0x2a]);
+    assert_eq!(binary.payload_encoding, PayloadEncoding::Base64);
+    assert_eq!(binary.message.payload.as_deref(), Some("/wAq"));
+    let json = serde_json::to_value(&binary).unwrap();
+    assert_eq!(json["payload_encoding"], "base64");
+    assert_eq!(json["key"], "tenant-42");
+    assert_eq!(json["offset"], 7);
+}
@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::clusters::cluster::Kind;
+use crate::kafka::config;
+use crate::kafka::streams::consumer;
+
+// Checks of the clusters and subscriptions written through the API, so a bad
+// value is reported with its field rather than failing later in the Kafka client.
+
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Kafka's own limit on the length of topic names.
+pub const MAX_TOPIC_NAME_LEN: usize = 249;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Numeric settings of clusters, with the least and most they can be set to.
+const CLUSTER_NUMBERS: &[(&str, u64, u64)] = &[
+    (config::METADATA_POLL_INTERVAL, 100, DAY_MS),
+    (config::METADATA_POLL_MAX_INTERVAL, 100, DAY_MS),
+    (config::METADATA_POLL_MAX_BACKOFF, 100, DAY_MS),
+    (config::METRICS_POLL_INTERVAL, 100, DAY_MS),
+    (config::STORAGE_POLL_INTERVAL, 1000, DAY_MS),
+];
+
+/// The `security.protocol`s and `sasl.mechanism`s librdkafka supports.
+const SECURITY_PROTOCOLS: [&str; 4] = ["PLAINTEXT", "SSL", "SASL_PLAINTEXT", "SASL_SSL"];
+const SASL_MECHANISMS: [&str; 5] = [
+    "PLAIN",
+    "SCRAM-SHA-256",
+    "SCRAM-SHA-512",
+    "GSSAPI",
+    "OAUTHBEARER",
+];
+
+/// Numeric settings of subscriptions, with the least and most they can be set to.
+const SUBSCRIPTION_NUMBERS: &[(&str, u64, u64)] = &[
+    (config::LIVENESS_STALL_THRESHOLD, 1000, DAY_MS),
+    (config::BUDGET_WINDOW, 1000, DAY_MS),
+    (config::CATCHUP_SUSTAINED, 1, DAY_MS),
+    (config::FRESHNESS_MAX_LAG, 1, DAY_MS),
+    (config::COMMANDS_TIMEOUT, 100, DAY_MS),
+];
+
+/// A value of a request that isn't valid, and the field it was given in.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    /// The field, with config settings as `config.<key>`.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Check a cluster to be created or updated, reporting every invalid field.
+pub fn cluster(
+    kind: &Kind,
+    name: &str,
+    config: &HashMap<String, String>,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    if let Err(e) = self::name(name) {
+        errors.push(FieldError::new("name", e));
+    }
+    match kind {
+        Kind::Unknown => errors.push(FieldError::new("kind", "kind must be set, e.g. Kafka")),
+        _ => {
+            if let Err(e) = kind.validate() {
+                errors.push(FieldError::new("kind", e));
+            }
+        }
+    }
+    if *kind == Kind::Kafka {
+        let field = format!("config.{}", config::BOOTSTRAP_SERVERS);
+        match config.get(config::BOOTSTRAP_SERVERS) {
+            Some(servers) => {
+                if let Err(e) = bootstrap_servers(servers) {
+                    errors.push(FieldError::new(field, e));
+                }
+            }
+            None => errors.push(FieldError::new(field, "required for Kafka clusters")),
+        }
+    }
+    errors.extend(numbers(config, CLUSTER_NUMBERS));
+    errors.extend(connection_settings(config));
+
+    finish(errors)
+}
+
+/// Check a subscription to be created or updated, reporting every invalid field.
+pub fn subscription(
+    topic_name: &str,
+    config: &HashMap<String, String>,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    if let Err(e) = self::topic_name(topic_name) {
+        errors.push(FieldError::new("topic_name", e));
+    }
+    errors.extend(numbers(config, SUBSCRIPTION_NUMBERS));
+    errors.extend(consumer_settings(config));
+
+    finish(errors)
+}
+
+/// Check the consumer settings a subscription overrides, which the indexer
+/// would otherwise only reject once it starts the subscription's worker.
+fn consumer_settings(config: &HashMap<String, String>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let field = |key: &str| format!("config.{}", key);
+
+    if config
+        .get(config::SEEKR_GROUP_ID)
+        .is_some_and(|g| g.trim().is_empty())
+    {
+        errors.push(FieldError::new(
+            field(config::SEEKR_GROUP_ID),
+            "must not be empty",
+        ));
+    }
+    if let Err(e) = consumer::offset_reset(config) {
+        errors.push(FieldError::new(field(config::AUTO_OFFSET_RESET), e));
+    }
+    match consumer::start_at(config) {
+        Ok(Some(at)) if at > Utc::now() => errors.push(FieldError::new(
+            field(config::START_AT_TIMESTAMP),
+            "must not be in the future",
+        )),
+        Ok(_) => {}
+        Err(e) => errors.push(FieldError::new(field(config::START_AT_TIMESTAMP), e)),
+    }
+
+    errors
+}
+
+/// Check how a cluster's clients connect, which librdkafka would otherwise
+/// only reject once the cluster is polled.
+fn connection_settings(config: &HashMap<String, String>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let field = |key: &str| format!("config.{}", key);
+
+    // SASL over a plaintext protocol connects, the sasl-over-plaintext lint
+    // rule warns about it.
+    let protocol = config.get(config::SECURITY_PROTOCOL);
+    if let Some(protocol) =
+        protocol.filter(|p| !SECURITY_PROTOCOLS.contains(&p.to_uppercase().as_str()))
+    {
+        errors.push(FieldError::new(
+            field(config::SECURITY_PROTOCOL),
+            format!(
+                "unknown protocol '{}', expected one of: {}",
+                protocol,
+                SECURITY_PROTOCOLS.join(", ")
+            ),
+        ));
+    }
+
+    match config.get(config::SASL_MECHANISM).map(String::as_str) {
+        Some(mechanism) if !SASL_MECHANISMS.contains(&mechanism) => errors.push(FieldError::new(
+            field(config::SASL_MECHANISM),
+            format!(
+                "unknown mechanism '{}', expected one of: {}",
+                mechanism,
+                SASL_MECHANISMS.join(", ")
+            ),
+        )),
+        Some("PLAIN" | "SCRAM-SHA-256" | "SCRAM-SHA-512") => {
+            for key in [config::SASL_USERNAME, config::SASL_PASSWORD] {
+                if config.get(key).map_or(true, |v| v.is_empty()) {
+                    errors.push(FieldError::new(
+                        field(key),
+                        "required for PLAIN and SCRAM mechanisms",
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    for key in [
+        config::SSL_CA_LOCATION,
+        config::SSL_CERTIFICATE_LOCATION,
+        config::SSL_KEY_LOCATION,
+    ] {
+        if config.get(key).is_some_and(|v| v.trim().is_empty()) {
+            errors.push(FieldError::new(field(key), "must not be empty"));
+        }
+    }
+
+    errors
+}
+
+/// Check a topic to be created, reporting every invalid field.
+pub fn topic(name: &str, partitions: i32, replication_factor: i32) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    if let Err(e) = topic_name(name) {
+        errors.push(FieldError::new("name", e));
+    }
+    if partitions < 1 {
+        errors.push(FieldError::new("partitions", "must be at least 1"));
+    }
+    if replication_factor < 1 {
+        errors.push(FieldError::new("replication_factor", "must be at least 1"));
+    }
+
+    finish(errors)
+}
+
+fn finish(errors: Vec<FieldError>) -> Result<(), Vec<FieldError>> {
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+pub fn name(name: &str) -> Result<(), String> {
+    let len = name.trim().chars().count();
+    if len == 0 {
+        return Err("must not be empty".to_string());
+    }
+    if len > MAX_NAME_LEN {
+        return Err(format!("must be at most {} characters", MAX_NAME_LEN));
+    }
+    Ok(())
+}
+
+/// A topic name Kafka accepts: ASCII letters, digits, `.`, `_` and `-`.
+pub fn topic_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("must not be empty".to_string());
+    }
+    if name.len() > MAX_TOPIC_NAME_LEN {
+        return Err(format!("must be at most {} characters", MAX_TOPIC_NAME_LEN));
+    }
+    if name == "." || name == ".." {
+        return Err(format!("'{}' is not a valid topic name", name));
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+    {
+        Some(c) => Err(format!(
+            "'{}' is not allowed, only ASCII letters, digits, '.', '_' and '-' are",
+            c
+        )),
+        None => Ok(()),
+    }
+}
+
+/// A comma-separated list of `host:port`, IPv6 hosts in brackets.
+pub fn bootstrap_servers(servers: &str) -> Result<(), String> {
+    for server in servers.split(',').map(str::trim) {
+        let invalid = || format!("'{}' is not a host:port", server);
+        let (host, port) = server.rsplit_once(':').ok_or_else(invalid)?;
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+        let valid_host = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':'));
+        if !valid_host || !port.parse::<u16>().is_ok_and(|p| p > 0) {
+            return Err(invalid());
+        }
+    }
+    Ok(())
+}
+
+fn numbers<'a>(
+    config: &'a HashMap<String, String>,
+    bounds: &'a [(&str, u64, u64)],
+) -> impl Iterator<Item = FieldError> + 'a {
+    bounds.iter().filter_map(|(key, min, max)| {
+        let value = config.get(*key)?;
+        let field = format!("config.{}", key);
+        match value.parse::<u64>() {
+            Ok(v) if (*min..=*max).contains(&v) => None,
+            Ok(_) => Some(FieldError::new(
+                field,
+                format!("must be between {} and {}", min, max),
+            )),
+            Err(_) => Some(FieldError::new(
+                field,
+                format!("'{}' is not a whole number", value),
+            )),
+        }
+    })
+}
+
+#[test]
+fn it_reports_every_invalid_cluster_field() {
+    let config = |entries: &[(&str, &str)]| {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>()
+    };
+    let fields = |r: Result<(), Vec<FieldError>>| {
+        r.unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect::<Vec<_>>()
+    };
+
+    let valid = config(&[(config::BOOTSTRAP_SERVERS, "a:9092, [::1]:9093")]);
+    assert_eq!(cluster(&Kind::Kafka, "orders", &valid), Ok(()));
+
+    let invalid = config(&[(config::METADATA_POLL_INTERVAL, "30s")]);
+    assert_eq!(
+        fields(cluster(&Kind::Kafka, "  ", &invalid)),
+        [
+            "name",
+            "config.bootstrap.servers",
+            "config.metadata.poll.interval.ms"
+        ]
+    );
+    assert_eq!(
+        fields(cluster(&Kind::Unknown, &"x".repeat(256), &HashMap::new())),
+        ["name", "kind"]
+    );
+    let slow = config(&[
+        (config::BOOTSTRAP_SERVERS, "a:9092"),
+        (config::STORAGE_POLL_INTERVAL, "0"),
+    ]);
+    assert_eq!(
+        cluster(&Kind::Kafka, "orders", &slow).unwrap_err()[0].message,
+        "must be between 1000 and 86400000"
+    );
+}
+
+#[test]
+fn it_checks_bootstrap_servers_and_topic_names() {
+    for servers in ["localhost:9092", "a:9092,b.example.com:9093", "[::1]:9092"] {
+        assert_eq!(bootstrap_servers(servers), Ok(()), "{}", servers);
+    }
+    for servers in ["localhost", "a:9092,", ":9092", "a:0", "a:port", "a b:9092"] {
+        assert!(bootstrap_servers(servers).is_err(), "{}", servers);
+    }
+
+    for topic in ["orders", "orders.v1_eu-west"] {
+        assert_eq!(topic_name(topic), Ok(()), "{}", topic);
+    }
+    for topic in ["", ".", "..", "orders v1", "orders/v1", &"t".repeat(250)] {
+        assert!(topic_name(topic).is_err(), "{}", topic);
+    }
+
+    assert_eq!(topic("orders", 6, 3), Ok(()));
+    let fields = topic("orders v1", 0, -1)
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.field)
+        .collect::<Vec<_>>();
+    assert_eq!(fields, ["name", "partitions", "replication_factor"]);
+
+    let lag = HashMap::from([(config::FRESHNESS_MAX_LAG.to_string(), "-1".to_string())]);
+    let errors = subscription("orders", &lag).unwrap_err();
+    assert_eq!(errors[0].field, "config.freshness.max.lag.ms");
+}
+
+#[test]
+fn it_checks_consumer_overrides_of_subscriptions() {
+    let config = |entries: &[(&str, &str)]| {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>()
+    };
+
+    let valid = config(&[
+        (config::SEEKR_GROUP_ID, "orders-indexer"),
+        (config::AUTO_OFFSET_RESET, "latest"),
+        (config::START_AT_TIMESTAMP, "1700000000000"),
+    ]);
+    assert_eq!(subscription("orders", &valid), Ok(()));
+
+    let invalid = config(&[
+        (config::SEEKR_GROUP_ID, " "),
+        (config::AUTO_OFFSET_RESET, "beginning"),
+        (config::START_AT_TIMESTAMP, "9999999999999"),
+    ]);
+    let fields = subscription("orders", &invalid)
+        .unwrap_err()
+        .into_iter()
+        .map(|e| e.field)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            "config.seekr.group.id",
+            "config.auto.offset.reset",
+            "config.start.at.timestamp"
+        ]
+    );
+}
+
+#[test]
+fn it_checks_how_clusters_connect() {
+    let config = |entries: &[(&str, &str)]| {
+        let mut config = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+        config.insert(config::BOOTSTRAP_SERVERS.to_string(), "a:9096".to_string());
+        config
+    };
+    let fields = |config: &HashMap<String, String>| match cluster(&Kind::Kafka, "c", config) {
+        Ok(()) => vec![],
+        Err(errors) => errors.into_iter().map(|e| e.field).collect::<Vec<_>>(),
+    };
+
+    let scram = config(&[
+        (config::SECURITY_PROTOCOL, "sasl_ssl"),
+        (config::SASL_MECHANISM, "SCRAM-SHA-512"),
+        (config::SASL_USERNAME, "seekr"),
+        (config::SASL_PASSWORD, "hunter2"),
+        (config::SSL_CA_LOCATION, "/etc/seekr/ca.pem"),
+    ]);
+    assert!(fields(&scram).is_empty());
+    let mtls = config(&[
+        (config::SECURITY_PROTOCOL, "SSL"),
+        (config::SSL_CERTIFICATE_LOCATION, "/etc/seekr/client.pem"),
+        (config::SSL_KEY_LOCATION, "/etc/seekr/client.key"),
+    ]);
+    assert!(fields(&mtls).is_empty());
+    let kerberos = config(&[
+        (config::SECURITY_PROTOCOL, "SASL_PLAINTEXT"),
+        (config::SASL_MECHANISM, "GSSAPI"),
+    ]);
+    assert!(fields(&kerberos).is_empty());
+
+    let invalid = config(&[
+        (config::SECURITY_PROTOCOL, "TLS"),
+        (config::SASL_MECHANISM, "SCRAM"),
+        (config::SSL_KEY_LOCATION, " "),
+    ]);
+    assert_eq!(
+        fields(&invalid),
+        [
+            "config.security.protocol",
+            "config.sasl.mechanism",
+            "config.ssl.key.location"
+        ]
+    );
+    let anonymous = config(&[
+        (config::SECURITY_PROTOCOL, "SASL_SSL"),
+        (config::SASL_MECHANISM, "PLAIN"),
+        (config::SASL_USERNAME, "seekr"),
+    ]);
+    assert_eq!(fields(&anonymous), ["config.sasl.password"]);
+}
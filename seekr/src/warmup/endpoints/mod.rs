@@ -0,0 +1,20 @@
+use actix_web::web::ServiceConfig;
+
+use crate::api::routes::{Audience, RouteGroup};
+use crate::api::ApiVersion;
+
+pub mod v1;
+
+pub const ROUTES: RouteGroup = RouteGroup {
+    module: module_path!(),
+    scope: "admin",
+    versions: &[ApiVersion::V1],
+    audience: Audience::Admins,
+    configure,
+};
+
+pub fn configure(cfg: &mut ServiceConfig, version: ApiVersion) {
+    if version == ApiVersion::V1 {
+        v1::configure(cfg);
+    }
+}
@@ -0,0 +1,110 @@
+use actix_web::web::{Data, Json, ServiceConfig};
+use actix_web::{get, post, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::auth::Principal;
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::MetadataManager;
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_warmup).service(prioritize_warmup);
+}
+
+#[get("/warmup")]
+async fn get_warmup(principal: Principal, manager: Data<MetadataManager>) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    HttpResponse::Ok().json(manager.warmup().await)
+}
+
+/// Clusters warmed up already are left alone, unknown ones are reported as
+/// errors without failing the others.
+#[post("/warmup/prioritize")]
+async fn prioritize_warmup(
+    principal: Principal,
+    manager: Data<MetadataManager>,
+    body: Json<PrioritizeRequest>,
+) -> impl Responder {
+    if !principal.is_admin() {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    info!(
+        "Prioritizing the warm-up of clusters {:?}",
+        body.cluster_ids
+    );
+    HttpResponse::Ok().json(manager.prioritize(&body.cluster_ids).await)
+}
+
+#[derive(Deserialize)]
+struct PrioritizeRequest {
+    cluster_ids: Vec<ClusterId>,
+}
+
+#[actix_web::test]
+async fn it_reports_warmup_and_prioritizes_clusters() {
+    use std::sync::Arc;
+
+    use actix_web::{test, App};
+
+    use crate::clusters::store::MemoryClusterStore;
+    use crate::kafka::metadata::manager::{CachedMetadataEntry, MetadataConsumerFactory};
+    use crate::standby::sync::{CacheSync, SyncCursor, SyncedEntry};
+
+    let factory: MetadataConsumerFactory = Arc::new(|_| Err("no brokers in tests".into()));
+    let manager = MetadataManager::with_factory(Arc::new(MemoryClusterStore::default()), factory);
+    let entries = [
+        (
+            1,
+            CachedMetadataEntry::Meta(crate::history::diff::metadata(&[1], &[])),
+        ),
+        (2, CachedMetadataEntry::Failed("unreachable".to_string())),
+    ];
+    manager
+        .apply(CacheSync {
+            cursor: SyncCursor {
+                instance: "primary".to_string(),
+                version: 1,
+            },
+            full: true,
+            clusters: entries.iter().map(|(id, _)| ClusterId(*id)).collect(),
+            entries: entries
+                .into_iter()
+                .map(|(id, entry)| SyncedEntry {
+                    cluster_id: ClusterId(id),
+                    entry,
+                    offsets: None,
+                })
+                .collect(),
+        })
+        .await;
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::new(manager))
+            .configure(crate::server::routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/warmup")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["ready"], 1);
+    assert_eq!(body["failed"], 1);
+    assert_eq!(body["clusters"][1]["state"], "failed");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/warmup/prioritize")
+        .set_json(serde_json::json!({ "cluster_ids": [1, 2, 3] }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["already_ready"], serde_json::json!([1]));
+    assert_eq!(body["prioritized"], serde_json::json!([]));
+    // Cluster 2 is synced from another instance, cluster 3 doesn't exist.
+    assert_eq!(body["errors"][0]["cluster_id"], 2);
+    assert_eq!(body["errors"][1]["cluster_id"], 3);
+    assert_eq!(body["errors"][1]["message"], "Unknown cluster");
+}
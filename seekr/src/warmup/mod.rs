@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::ids::ClusterId;
+use crate::kafka::metadata::manager::CachedMetadataEntry;
+
+pub mod endpoints;
+
+/// Where a cluster is in warming up its metadata cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmupState {
+    /// Not polled yet.
+    Pending,
+    Ready,
+    /// The last poll failed, it is retried on the cluster's interval.
+    Failed,
+}
+
+impl WarmupState {
+    pub fn of(entry: &CachedMetadataEntry) -> Self {
+        match entry {
+            CachedMetadataEntry::Meta(_) => WarmupState::Ready,
+            CachedMetadataEntry::Failed(_) => WarmupState::Failed,
+            CachedMetadataEntry::Unknown | CachedMetadataEntry::Processing => WarmupState::Pending,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ClusterWarmup {
+    pub cluster_id: ClusterId,
+    pub state: WarmupState,
+
+    /// Since startup, when the cluster was registered.
+    pub registered_ms: Option<u64>,
+
+    /// How long the first poll took to complete after registration, the
+    /// time the cluster held up the warm-up.
+    pub first_poll_ms: Option<u64>,
+
+    /// How many polls are ahead of the cluster's, while it waits for a slot.
+    pub queue_position: Option<usize>,
+}
+
+/// How far the metadata cache warmed up since startup.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WarmupProgress {
+    pub elapsed_ms: u64,
+    pub total: usize,
+    pub ready: usize,
+    pub failed: usize,
+    pub pending: usize,
+    /// Ordered by cluster id.
+    pub clusters: Vec<ClusterWarmup>,
+}
+
+impl WarmupProgress {
+    pub fn new(elapsed: Duration, mut clusters: Vec<ClusterWarmup>) -> Self {
+        clusters.sort_by_key(|c| c.cluster_id);
+        let count = |state| clusters.iter().filter(|c| c.state == state).count();
+
+        Self {
+            elapsed_ms: millis(elapsed),
+            total: clusters.len(),
+            ready: count(WarmupState::Ready),
+            failed: count(WarmupState::Failed),
+            pending: count(WarmupState::Pending),
+            clusters,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.pending == 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PrioritizeError {
+    pub cluster_id: ClusterId,
+    pub message: String,
+}
+
+/// What prioritizing each of the requested clusters did.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Prioritized {
+    /// Moved to the front of the line.
+    pub prioritized: Vec<ClusterId>,
+    /// Warmed up already, left as they are.
+    pub already_ready: Vec<ClusterId>,
+    pub errors: Vec<PrioritizeError>,
+}
+
+pub fn millis(d: Duration) -> u64 {
+    d.as_millis().try_into().unwrap_or(u64::MAX)
+}
@@ -0,0 +1,235 @@
+//! Wire compatibility suite pinning how enums are stored and exchanged.
+//!
+//! Every enum persisted or crossing the API is serialized variant by variant
+//! and compared with `src/wire/goldens/enums.json`, along with the codes of
+//! the integer columns. A changed representation would corrupt stored data or
+//! break clients silently, so rewriting the goldens with `UPDATE_GOLDENS=1`
+//! should only ever happen together with a migration.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::auth::Role;
+use crate::changefeed::record::{ChangeRecord, Operation};
+use crate::clusters::cluster::{Cluster, Kind};
+use crate::clusters::health::HealthStatus;
+use crate::commands::command::{Command, CommandKind, CommandStatus};
+use crate::debug::trace::{Outcome, Stage};
+use crate::kafka::streams::catchup::Confidence;
+use crate::kafka::streams::freshness::SourceState;
+use crate::kafka::streams::service::WorkerState;
+use crate::mirrors::lag::MirrorState;
+use crate::produce::schema::SchemaKind;
+use crate::shards::period::ShardPeriod;
+use crate::standby::ServerRole;
+use seekr_api_types::admin::{Capability, CapabilityStatus, Dependency, ReadyStatus};
+use seekr_api_types::clusters::ClusterKind;
+use seekr_api_types::metadata::{AdaptiveState, Priority, TopicCategory};
+
+/// The serialized form of each variant, by its name.
+fn forms<T: Serialize + Debug>(variants: &[T]) -> BTreeMap<String, Value> {
+    variants
+        .iter()
+        .map(|v| (format!("{:?}", v), serde_json::to_value(v).unwrap()))
+        .collect()
+}
+
+fn assert_golden(name: &str, actual: &Value) {
+    let path = format!("{}/src/wire/goldens/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let actual = serde_json::to_string_pretty(actual).unwrap() + "\n";
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(actual, golden, "representation changed, see {}", path);
+}
+
+#[test]
+fn enums_match_goldens() {
+    let json = json!({
+        "AdaptiveState": forms(&[
+            AdaptiveState::Base,
+            AdaptiveState::Stretching,
+            AdaptiveState::Stretched,
+        ]),
+        "Capability": forms(&[
+            Capability::ClusterCrud,
+            Capability::SubscriptionCrud,
+            Capability::MetadataRead,
+            Capability::Search,
+            Capability::Indexing,
+        ]),
+        "CapabilityStatus": forms(&[
+            CapabilityStatus::Ok,
+            CapabilityStatus::Degraded,
+            CapabilityStatus::Unavailable,
+        ]),
+        "ClusterKind": forms(&[ClusterKind::Unknown, ClusterKind::Kafka]),
+        "CommandKind": forms(CommandKind::KNOWN),
+        "CommandStatus": forms(CommandStatus::KNOWN),
+        "Confidence": forms(&[Confidence::Low, Confidence::Medium, Confidence::High]),
+        "Dependency": forms(&[Dependency::Meilisearch, Dependency::Kafka]),
+        "HealthStatus": forms(&[
+            HealthStatus::Unknown,
+            HealthStatus::Healthy,
+            HealthStatus::Degraded,
+            HealthStatus::Unhealthy,
+        ]),
+        "Kind": forms(Kind::KNOWN),
+        "MirrorState": forms(&[MirrorState::Pending, MirrorState::Running, MirrorState::Errored]),
+        "Operation": forms(Operation::KNOWN),
+        "Outcome": forms(&[Outcome::Ok, Outcome::Empty, Outcome::Failed]),
+        "Priority": forms(&[Priority::High, Priority::Normal, Priority::Low]),
+        "ReadyStatus": forms(&[ReadyStatus::Ready, ReadyStatus::Degraded]),
+        "Role": forms(&[Role::Admin, Role::Editor, Role::Readonly]),
+        "SchemaKind": forms(&[SchemaKind::Json]),
+        "ServerRole": forms(&[ServerRole::Primary, ServerRole::Standby, ServerRole::Auto]),
+        "ShardPeriod": forms(&[ShardPeriod::Weekly, ShardPeriod::Monthly]),
+        "SourceState": forms(&[
+            SourceState::Unknown,
+            SourceState::Flowing,
+            SourceState::Stalled,
+            SourceState::Idle,
+            SourceState::Behind,
+        ]),
+        "Stage": forms(&Stage::ALL),
+        "TopicCategory": forms(&[
+            TopicCategory::Internal,
+            TopicCategory::System,
+            TopicCategory::User,
+        ]),
+        "WorkerState": forms(&[
+            WorkerState::Starting,
+            WorkerState::Running,
+            WorkerState::Paused,
+            WorkerState::Errored,
+        ]),
+    });
+    assert_golden("enums.json", &json);
+
+    // The `kind` column of `adm.clusters`.
+    let codes = json!({
+        "Kind": Kind::KNOWN
+            .iter()
+            .map(|k| (format!("{:?}", k), k.code()))
+            .collect::<BTreeMap<_, _>>(),
+    });
+    assert_golden("codes.json", &codes);
+}
+
+#[test]
+fn it_reads_every_golden_form_back() {
+    // Reading a stored value gives back the variant it was written from.
+    for kind in CommandKind::KNOWN {
+        let stored = serde_json::to_string(kind).unwrap();
+        assert_eq!(&serde_json::from_str::<CommandKind>(&stored).unwrap(), kind);
+    }
+    for status in CommandStatus::KNOWN {
+        let stored = serde_json::to_string(status).unwrap();
+        assert_eq!(
+            &serde_json::from_str::<CommandStatus>(&stored).unwrap(),
+            status
+        );
+    }
+    for operation in Operation::KNOWN {
+        let stored = serde_json::to_string(operation).unwrap();
+        assert_eq!(
+            &serde_json::from_str::<Operation>(&stored).unwrap(),
+            operation
+        );
+    }
+    for kind in Kind::KNOWN {
+        assert_eq!(&Kind::from(kind.code()), kind);
+        let named = serde_json::to_string(kind).unwrap();
+        assert_eq!(&serde_json::from_str::<Kind>(&named).unwrap(), kind);
+    }
+}
+
+#[test]
+fn it_keeps_unrecognized_commands_through_a_read_modify_write() {
+    // A command queued by a newer server, in a state this version doesn't know either.
+    let stored = json!({
+        "id": 7,
+        "subscription_id": 1,
+        "kind": "drain",
+        "payload": {"grace_ms": 500},
+        "status": "scheduled",
+        "result": null,
+        "created_at": "2024-05-01T00:00:00Z",
+        "completed_at": null,
+    });
+    let command: Command = serde_json::from_value(stored.clone()).unwrap();
+    assert_eq!(command.kind, CommandKind::Unrecognized("drain".to_string()));
+    assert_eq!(
+        command.status,
+        CommandStatus::Unrecognized("scheduled".to_string())
+    );
+    assert_eq!(serde_json::to_value(&command).unwrap(), stored);
+
+    let failed = command.complete(
+        CommandStatus::Failed,
+        Some("unsupported command 'drain'".to_string()),
+        chrono::Utc::now(),
+    );
+    let written = serde_json::to_value(&failed).unwrap();
+    assert_eq!(written["kind"], "drain");
+    assert_eq!(written["payload"], stored["payload"]);
+    assert_eq!(written["status"], "failed");
+}
+
+#[test]
+fn it_keeps_unrecognized_changes_through_a_read_modify_write() {
+    let stored = json!({
+        "id": "1-0-42",
+        "subscription_id": 1,
+        "document_id": "0-42",
+        "operation": "truncate",
+        "_seekr_ts": 1_714_521_600_000i64,
+        "partition": 0,
+        "offset": 42,
+        "payload_hash": "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+    });
+    let mut record: ChangeRecord = serde_json::from_value(stored.clone()).unwrap();
+    assert_eq!(
+        record.operation,
+        Operation::Unrecognized("truncate".to_string())
+    );
+    assert_eq!(serde_json::to_value(&record).unwrap(), stored);
+
+    record = record.with_document_id("order-7".to_string());
+    let written = serde_json::to_value(&record).unwrap();
+    assert_eq!(written["operation"], "truncate");
+    assert_eq!(written["document_id"], "order-7");
+}
+
+#[test]
+fn it_keeps_unrecognized_cluster_kinds_through_a_read_modify_write() {
+    // A code written by a newer version to the `kind` column is written back as it was read.
+    let read = Kind::from(7);
+    assert_eq!(read, Kind::Unrecognized("7".to_string()));
+    assert_eq!(read.code(), 7);
+
+    // So is a name written by a newer version to the clusters index.
+    let stored = json!({
+        "id": 3,
+        "kind": "Pulsar",
+        "name": "events",
+        "config": {},
+        "created_at": "2024-05-01T00:00:00Z",
+        "updated_at": "2024-05-01T00:00:00Z",
+    });
+    let mut cluster: Cluster = serde_json::from_value(stored).unwrap();
+    assert_eq!(cluster.kind, Kind::Unrecognized("Pulsar".to_string()));
+    cluster.name = "renamed".to_string();
+    assert_eq!(serde_json::to_value(&cluster).unwrap()["kind"], "Pulsar");
+
+    // Clusters can't be created or updated with a kind this version doesn't know.
+    assert!(cluster.kind.validate().is_err());
+    assert!(Kind::Kafka.validate().is_ok());
+}